@@ -0,0 +1,741 @@
+//! Exchange-hosted algo orders: trailing stops (`move_order_stop`) and
+//! sliced execution (`iceberg`, `twap`).
+//!
+//! A trailing stop rides `callbackRatio` or `callbackSpread` behind the
+//! best price and converts into a market order when price retraces by the
+//! offset; OKX hosts the whole lifecycle, so it survives our own outages.
+//! The execution algos split a large order into `szLimit`-sized children
+//! the exchange works on our behalf, so a rebalance does not sit on the
+//! book as one block. [`TrailingStopRequest`] and [`ExecutionAlgoRequest`]
+//! build the `/api/v5/trade/order-algo` payload
+//! with the same conversions regular orders get — sizes in base units
+//! converted to contracts, prices quantized to the tick — and
+//! [`AlgoOrderFeed`] parses `orders-algo` channel pushes into
+//! [`DriverEvent::AlgoOrderUpdate`]s so the activation, and the regular
+//! order it places, are observable on the event stream. Listing and
+//! cancelling ride the pending-algo REST endpoints, and a sliced order's
+//! fills are polled into an [`AlgoProgress`] snapshot; see
+//! [`crate::rest::OkexClient::rest_fetch_pending_algo_orders`] and
+//! [`crate::rest::OkexClient::rest_fetch_algo_progress`].
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DriverError, DriverResult};
+use crate::events::{DriverEvent, DriverEventSender};
+use crate::instruments::Instrument;
+use crate::orders::{OkexPositionMode, PosSide, PositionIntent, Side, TradeMode};
+use crate::precision::{
+    checked_to_exchange_size, default_precision_epsilon, serialize_price, serialize_size,
+};
+
+/// How far the stop trails the best price. OKX accepts exactly one of
+/// `callbackRatio` and `callbackSpread`, which this type enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingOffset {
+    /// `callbackRatio`: fraction of the price, e.g. `0.05` trails 5%.
+    Ratio(Decimal),
+    /// `callbackSpread`: absolute price distance, in quote units.
+    Spread(Decimal),
+}
+
+/// One trailing-stop order as the caller states it; sizes in base units
+/// like everything else at this layer.
+#[derive(Debug, Clone)]
+pub struct TrailingStopRequest {
+    pub inst_id: String,
+    pub side: Side,
+    /// Size in base units; converted to contracts via `ctVal`.
+    pub size: Decimal,
+    pub offset: TrailingOffset,
+    /// Price at which the trailing starts; `None` activates immediately.
+    pub active_price: Option<Decimal>,
+    /// Required in long/short mode on contracts, as for regular orders.
+    pub position_intent: Option<PositionIntent>,
+    /// `algoClOrdId`, for idempotency and correlation.
+    pub client_algo_id: Option<String>,
+}
+
+/// Serialized `/api/v5/trade/order-algo` payload for a trailing stop.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OkexAlgoOrderParams {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tdMode")]
+    pub td_mode: TradeMode,
+    pub side: Side,
+    #[serde(rename = "posSide", skip_serializing_if = "Option::is_none")]
+    pub pos_side: Option<PosSide>,
+    #[serde(rename = "ordType")]
+    pub ord_type: String,
+    pub sz: String,
+    #[serde(rename = "callbackRatio", skip_serializing_if = "Option::is_none")]
+    pub callback_ratio: Option<String>,
+    #[serde(rename = "callbackSpread", skip_serializing_if = "Option::is_none")]
+    pub callback_spread: Option<String>,
+    #[serde(rename = "activePx", skip_serializing_if = "Option::is_none")]
+    pub active_px: Option<String>,
+    #[serde(rename = "pxVar", skip_serializing_if = "Option::is_none")]
+    pub px_var: Option<String>,
+    #[serde(rename = "pxSpread", skip_serializing_if = "Option::is_none")]
+    pub px_spread: Option<String>,
+    #[serde(rename = "szLimit", skip_serializing_if = "Option::is_none")]
+    pub sz_limit: Option<String>,
+    #[serde(rename = "pxLimit", skip_serializing_if = "Option::is_none")]
+    pub px_limit: Option<String>,
+    /// Seconds between TWAP chunks, serialized as a string like every
+    /// other numeric.
+    #[serde(rename = "timeInterval", skip_serializing_if = "Option::is_none")]
+    pub time_interval: Option<String>,
+    #[serde(rename = "algoClOrdId", skip_serializing_if = "Option::is_none")]
+    pub algo_cl_ord_id: Option<String>,
+}
+
+impl TrailingStopRequest {
+    /// Build the exchange payload against the instrument. Mirrors the
+    /// regular order builder: base size to contracts via `ctVal` with lot
+    /// flooring, prices quantized to the tick, `posSide` derived from
+    /// (side, intent) in long/short mode. OKX does not host trailing
+    /// stops for spot in cash mode, so that combination is rejected
+    /// locally instead of burning a round trip.
+    pub fn build(
+        &self,
+        instrument: &Instrument,
+        td_mode: TradeMode,
+        position_mode: OkexPositionMode,
+    ) -> DriverResult<OkexAlgoOrderParams> {
+        if instrument.contract_value.is_none() && td_mode == TradeMode::Cash {
+            return Err(DriverError::Config(format!(
+                "trailing stops are not supported for spot {} in cash mode",
+                self.inst_id
+            )));
+        }
+        let pos_side = derive_pos_side(
+            self.side,
+            self.position_intent,
+            position_mode,
+            instrument,
+            "trailing stop",
+            &self.inst_id,
+        )?;
+        let conversion = checked_to_exchange_size(
+            self.size,
+            instrument.contract_value,
+            default_precision_epsilon(),
+        );
+        if conversion.precision_loss {
+            log::warn!(
+                "trailing stop on {}: converting {} to contracts dropped {} base units",
+                self.inst_id,
+                self.size,
+                conversion.remainder
+            );
+        }
+        let sz = serialize_size(conversion.value, instrument.lot_size);
+        let requested: Decimal = sz.parse().unwrap_or_default();
+        if requested < instrument.min_size {
+            return Err(DriverError::BelowMinimumSize {
+                min: instrument.min_size,
+                requested,
+            });
+        }
+        let (callback_ratio, callback_spread) = match self.offset {
+            TrailingOffset::Ratio(ratio) => {
+                if ratio <= Decimal::ZERO || ratio >= Decimal::ONE {
+                    return Err(DriverError::Config(format!(
+                        "callback ratio {ratio} must be between 0 and 1 exclusive"
+                    )));
+                }
+                (Some(ratio.normalize().to_string()), None)
+            }
+            TrailingOffset::Spread(spread) => {
+                if spread <= Decimal::ZERO {
+                    return Err(DriverError::Config(format!(
+                        "callback spread {spread} must be positive"
+                    )));
+                }
+                (None, Some(serialize_price(spread, instrument.tick_size)))
+            }
+        };
+        Ok(OkexAlgoOrderParams {
+            inst_id: self.inst_id.clone(),
+            td_mode,
+            side: self.side,
+            pos_side,
+            ord_type: "move_order_stop".to_string(),
+            sz,
+            callback_ratio,
+            callback_spread,
+            active_px: self
+                .active_price
+                .map(|price| serialize_price(price, instrument.tick_size)),
+            px_var: None,
+            px_spread: None,
+            sz_limit: None,
+            px_limit: None,
+            time_interval: None,
+            algo_cl_ord_id: self.client_algo_id.clone(),
+        })
+    }
+}
+
+/// `posSide` from (side, intent) in long/short mode on contracts, exactly
+/// as the regular order builder derives it.
+fn derive_pos_side(
+    side: Side,
+    position_intent: Option<PositionIntent>,
+    position_mode: OkexPositionMode,
+    instrument: &Instrument,
+    ord_kind: &str,
+    inst_id: &str,
+) -> DriverResult<Option<PosSide>> {
+    match (position_mode, instrument.contract_value.is_some()) {
+        (OkexPositionMode::LongShort, true) => {
+            let Some(intent) = position_intent else {
+                return Err(DriverError::Config(format!(
+                    "long/short mode needs a position intent for {ord_kind} on {inst_id}"
+                )));
+            };
+            Ok(Some(match (side, intent) {
+                (Side::Buy, PositionIntent::Open) | (Side::Sell, PositionIntent::Close) => {
+                    PosSide::Long
+                }
+                (Side::Sell, PositionIntent::Open) | (Side::Buy, PositionIntent::Close) => {
+                    PosSide::Short
+                }
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// How far child-order prices may drift from the market. OKX accepts
+/// exactly one of `pxVar` and `pxSpread`, which this type enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceVariance {
+    /// `pxVar`: fraction of the price; OKX accepts 0.0001 through 0.01.
+    Ratio(Decimal),
+    /// `pxSpread`: absolute price distance, in quote units.
+    Spread(Decimal),
+}
+
+/// OKX bounds on the TWAP `timeInterval`, in seconds.
+const TWAP_INTERVAL_SECS: std::ops::RangeInclusive<u64> = 5..=120;
+
+/// Which exchange-side slicing strategy runs the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionStrategy {
+    /// `iceberg`: keeps one `szLimit`-sized child order resting, replacing
+    /// it as it fills.
+    Iceberg,
+    /// `twap`: places a `szLimit`-sized child order every `interval`.
+    Twap { interval: std::time::Duration },
+}
+
+/// One sliced execution order (iceberg or TWAP) as the caller states it;
+/// sizes in base units like everything else at this layer.
+#[derive(Debug, Clone)]
+pub struct ExecutionAlgoRequest {
+    pub inst_id: String,
+    pub side: Side,
+    pub strategy: ExecutionStrategy,
+    /// Total size in base units; converted to contracts via `ctVal`.
+    pub size: Decimal,
+    /// Per-child-order size in base units; converted and lot-floored into
+    /// `szLimit`.
+    pub size_limit: Decimal,
+    /// Worst acceptable child-order price (`pxLimit`).
+    pub price_limit: Decimal,
+    pub variance: PriceVariance,
+    /// Required in long/short mode on contracts, as for regular orders.
+    pub position_intent: Option<PositionIntent>,
+    /// `algoClOrdId`, for idempotency and correlation.
+    pub client_algo_id: Option<String>,
+}
+
+impl ExecutionAlgoRequest {
+    /// Build the exchange payload against the instrument, with the same
+    /// conversions the trailing-stop builder applies plus the slicing
+    /// bounds: `szLimit` must survive lot flooring at or above the
+    /// instrument minimum and cannot exceed the total, and a TWAP interval
+    /// must sit inside the OKX-accepted range.
+    pub fn build(
+        &self,
+        instrument: &Instrument,
+        td_mode: TradeMode,
+        position_mode: OkexPositionMode,
+    ) -> DriverResult<OkexAlgoOrderParams> {
+        let pos_side = derive_pos_side(
+            self.side,
+            self.position_intent,
+            position_mode,
+            instrument,
+            "execution algo",
+            &self.inst_id,
+        )?;
+        let to_contracts = |size: Decimal| {
+            let conversion = checked_to_exchange_size(
+                size,
+                instrument.contract_value,
+                default_precision_epsilon(),
+            );
+            if conversion.precision_loss {
+                log::warn!(
+                    "execution algo on {}: converting {size} to contracts dropped {} base units",
+                    self.inst_id,
+                    conversion.remainder
+                );
+            }
+            conversion.value
+        };
+        let sz = serialize_size(to_contracts(self.size), instrument.lot_size);
+        let total: Decimal = sz.parse().unwrap_or_default();
+        if total < instrument.min_size {
+            return Err(DriverError::BelowMinimumSize {
+                min: instrument.min_size,
+                requested: total,
+            });
+        }
+        let sz_limit = serialize_size(to_contracts(self.size_limit), instrument.lot_size);
+        let chunk: Decimal = sz_limit.parse().unwrap_or_default();
+        if chunk < instrument.min_size {
+            return Err(DriverError::BelowMinimumSize {
+                min: instrument.min_size,
+                requested: chunk,
+            });
+        }
+        if chunk > total {
+            return Err(DriverError::Config(format!(
+                "szLimit {chunk} exceeds the total size {total} on {}",
+                self.inst_id
+            )));
+        }
+        if self.price_limit <= Decimal::ZERO {
+            return Err(DriverError::Config(format!(
+                "price limit {} must be positive",
+                self.price_limit
+            )));
+        }
+        let (px_var, px_spread) = match self.variance {
+            PriceVariance::Ratio(ratio) => {
+                if ratio < Decimal::new(1, 4) || ratio > Decimal::new(1, 2) {
+                    return Err(DriverError::Config(format!(
+                        "price variance ratio {ratio} must be between 0.0001 and 0.01"
+                    )));
+                }
+                (Some(ratio.normalize().to_string()), None)
+            }
+            PriceVariance::Spread(spread) => {
+                if spread <= Decimal::ZERO {
+                    return Err(DriverError::Config(format!(
+                        "price spread {spread} must be positive"
+                    )));
+                }
+                (None, Some(serialize_price(spread, instrument.tick_size)))
+            }
+        };
+        let (ord_type, time_interval) = match self.strategy {
+            ExecutionStrategy::Iceberg => ("iceberg", None),
+            ExecutionStrategy::Twap { interval } => {
+                let secs = interval.as_secs();
+                if !TWAP_INTERVAL_SECS.contains(&secs) {
+                    return Err(DriverError::Config(format!(
+                        "TWAP interval {secs}s is outside the accepted {}-{}s range",
+                        TWAP_INTERVAL_SECS.start(),
+                        TWAP_INTERVAL_SECS.end()
+                    )));
+                }
+                ("twap", Some(secs.to_string()))
+            }
+        };
+        Ok(OkexAlgoOrderParams {
+            inst_id: self.inst_id.clone(),
+            td_mode,
+            side: self.side,
+            pos_side,
+            ord_type: ord_type.to_string(),
+            sz,
+            callback_ratio: None,
+            callback_spread: None,
+            active_px: None,
+            px_var,
+            px_spread,
+            sz_limit: Some(sz_limit),
+            px_limit: Some(serialize_price(self.price_limit, instrument.tick_size)),
+            time_interval,
+            algo_cl_ord_id: self.client_algo_id.clone(),
+        })
+    }
+}
+
+/// Execution progress of a sliced algo order, sizes back in base units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgoProgress {
+    pub filled: Decimal,
+    pub remaining: Decimal,
+    /// Regular orders the algo has placed so far, oldest first.
+    pub child_orders: Vec<String>,
+}
+
+impl AlgoProgress {
+    /// Snapshot from the `GET /api/v5/trade/order-algo` details, converting
+    /// contract sizes back to base units via `ctVal`.
+    pub fn from_details(
+        details: &crate::api_structs::OkexAlgoOrderDetails,
+        instrument: &Instrument,
+    ) -> Self {
+        let factor = instrument.contract_value.unwrap_or(Decimal::ONE);
+        let filled = details.filled_size.unwrap_or_default();
+        Self {
+            filled: filled * factor,
+            remaining: (details.size - filled).max(Decimal::ZERO) * factor,
+            child_orders: details.order_ids.clone(),
+        }
+    }
+}
+
+/// `orders-algo` push frame, borrowed from the frame buffer.
+#[derive(Deserialize)]
+struct AlgoFrame<'a> {
+    #[serde(borrow)]
+    arg: AlgoArg<'a>,
+    #[serde(borrow, default)]
+    data: Vec<WireAlgoUpdate<'a>>,
+    /// Set on subscription acks/rejections; pushes never have it.
+    #[serde(default)]
+    event: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct AlgoArg<'a> {
+    channel: &'a str,
+}
+
+#[derive(Deserialize)]
+struct WireAlgoUpdate<'a> {
+    #[serde(rename = "algoId")]
+    algo_id: &'a str,
+    #[serde(rename = "instId")]
+    inst_id: &'a str,
+    state: &'a str,
+    /// The regular order the activation placed; empty until then.
+    #[serde(rename = "ordId", default)]
+    ord_id: &'a str,
+}
+
+/// Parses `orders-algo` channel pushes into
+/// [`DriverEvent::AlgoOrderUpdate`]s; the connection owner feeds inbound
+/// frames through it alongside the other handlers.
+pub struct AlgoOrderFeed {
+    events: Option<DriverEventSender>,
+    dead_letters: Option<std::sync::Arc<dyn crate::dead_letter::DeadLetterSink>>,
+}
+
+impl Default for AlgoOrderFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlgoOrderFeed {
+    pub fn new() -> Self {
+        Self {
+            events: None,
+            dead_letters: None,
+        }
+    }
+
+    /// Emit [`DriverEvent::AlgoOrderUpdate`] on this stream.
+    pub fn set_event_sender(&mut self, events: DriverEventSender) {
+        self.events = Some(events);
+    }
+
+    /// Capture `orders-algo` pushes that fail typed deserialization; see
+    /// [`crate::dead_letter`].
+    pub fn set_dead_letter_sink(
+        &mut self,
+        sink: std::sync::Arc<dyn crate::dead_letter::DeadLetterSink>,
+    ) {
+        self.dead_letters = Some(sink);
+    }
+
+    /// Feed one inbound frame; `true` when it was an `orders-algo` push
+    /// this feed consumed. Acks and other channels are left for the next
+    /// handler.
+    pub fn handle_frame(&self, frame: &str) -> bool {
+        let parsed = match serde_json::from_str::<AlgoFrame<'_>>(frame) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                crate::dead_letter::report_typed_failure(
+                    self.dead_letters.as_deref(),
+                    "orders-algo",
+                    frame,
+                    &error,
+                );
+                return false;
+            }
+        };
+        if parsed.arg.channel != "orders-algo" || parsed.event.is_some() {
+            return false;
+        }
+        for update in &parsed.data {
+            if let Some(events) = &self.events {
+                let _ = events.send(DriverEvent::AlgoOrderUpdate {
+                    algo_id: update.algo_id.to_string(),
+                    inst_id: update.inst_id.to_string(),
+                    state: update.state.to_string(),
+                    order_id: (!update.ord_id.is_empty()).then(|| update.ord_id.to_string()),
+                });
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: dec("0.1"),
+            lot_size: dec("1"),
+            min_size: dec("1"),
+            contract_value: Some(dec("0.01")),
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn request(offset: TrailingOffset) -> TrailingStopRequest {
+        TrailingStopRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side: Side::Sell,
+            size: dec("0.5"),
+            offset,
+            active_price: None,
+            position_intent: None,
+            client_algo_id: Some("trail-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn the_ratio_variant_serializes_with_contract_sizing() {
+        let params = request(TrailingOffset::Ratio(dec("0.050")))
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap();
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["ordType"], "move_order_stop");
+        // 0.5 BTC at ctVal 0.01 is 50 contracts.
+        assert_eq!(json["sz"], "50");
+        assert_eq!(json["callbackRatio"], "0.05");
+        assert_eq!(json["tdMode"], "cross");
+        assert_eq!(json["algoClOrdId"], "trail-1");
+        assert!(json.get("callbackSpread").is_none());
+        assert!(json.get("activePx").is_none());
+        assert!(json.get("posSide").is_none());
+    }
+
+    #[test]
+    fn the_spread_variant_quantizes_spread_and_active_price_to_the_tick() {
+        let mut stop = request(TrailingOffset::Spread(dec("120.17")));
+        stop.active_price = Some(dec("43250.1700"));
+        stop.position_intent = Some(PositionIntent::Close);
+        let params = stop
+            .build(
+                &swap_instrument(),
+                TradeMode::Cross,
+                OkexPositionMode::LongShort,
+            )
+            .unwrap();
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["callbackSpread"], "120.1");
+        assert_eq!(json["activePx"], "43250.1");
+        assert!(json.get("callbackRatio").is_none());
+        // A sell that closes reduces a long.
+        assert_eq!(json["posSide"], "long");
+    }
+
+    #[test]
+    fn cash_mode_spot_and_bad_offsets_are_rejected_locally() {
+        let spot = Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            contract_value: None,
+            ..swap_instrument()
+        };
+        let err = request(TrailingOffset::Ratio(dec("0.05")))
+            .build(&spot, TradeMode::Cash, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "{err}");
+        assert!(err.to_string().contains("cash mode"), "{err}");
+
+        let err = request(TrailingOffset::Ratio(dec("1.5")))
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "{err}");
+
+        let mut dust = request(TrailingOffset::Ratio(dec("0.05")));
+        dust.size = dec("0.001");
+        let err = dust
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(matches!(err, DriverError::BelowMinimumSize { .. }), "{err}");
+    }
+
+    fn execution_request(strategy: ExecutionStrategy) -> ExecutionAlgoRequest {
+        ExecutionAlgoRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side: Side::Buy,
+            strategy,
+            size: dec("1.2"),
+            size_limit: dec("0.1"),
+            price_limit: dec("43500.17"),
+            variance: PriceVariance::Ratio(dec("0.0010")),
+            position_intent: None,
+            client_algo_id: Some("ice-7".to_string()),
+        }
+    }
+
+    #[test]
+    fn an_iceberg_serializes_with_contract_sized_chunks() {
+        let params = execution_request(ExecutionStrategy::Iceberg)
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap();
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["ordType"], "iceberg");
+        // 1.2 BTC total and 0.1 BTC chunks at ctVal 0.01.
+        assert_eq!(json["sz"], "120");
+        assert_eq!(json["szLimit"], "10");
+        assert_eq!(json["pxVar"], "0.001");
+        assert_eq!(json["pxLimit"], "43500.1");
+        assert_eq!(json["algoClOrdId"], "ice-7");
+        assert!(json.get("pxSpread").is_none());
+        assert!(json.get("timeInterval").is_none());
+        assert!(json.get("callbackRatio").is_none());
+    }
+
+    #[test]
+    fn a_twap_carries_the_interval_and_lot_floors_the_chunk() {
+        let mut twap = execution_request(ExecutionStrategy::Twap {
+            interval: std::time::Duration::from_secs(30),
+        });
+        // 0.057 BTC is 5.7 contracts; the chunk must floor to the lot.
+        twap.size_limit = dec("0.057");
+        twap.variance = PriceVariance::Spread(dec("12.34"));
+        let params = twap
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap();
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["ordType"], "twap");
+        assert_eq!(json["timeInterval"], "30");
+        assert_eq!(json["szLimit"], "5");
+        assert_eq!(json["pxSpread"], "12.3");
+        assert!(json.get("pxVar").is_none());
+    }
+
+    #[test]
+    fn execution_bounds_are_enforced_locally() {
+        let err = execution_request(ExecutionStrategy::Twap {
+            interval: std::time::Duration::from_secs(2),
+        })
+        .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+        .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "{err}");
+        assert!(err.to_string().contains("TWAP interval"), "{err}");
+
+        let mut dust = execution_request(ExecutionStrategy::Iceberg);
+        dust.size_limit = dec("0.001");
+        let err = dust
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(matches!(err, DriverError::BelowMinimumSize { .. }), "{err}");
+
+        let mut oversized = execution_request(ExecutionStrategy::Iceberg);
+        oversized.size_limit = dec("2");
+        let err = oversized
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeds the total"), "{err}");
+
+        let mut wide = execution_request(ExecutionStrategy::Iceberg);
+        wide.variance = PriceVariance::Ratio(dec("0.05"));
+        let err = wide
+            .build(&swap_instrument(), TradeMode::Cross, OkexPositionMode::Net)
+            .unwrap_err();
+        assert!(err.to_string().contains("variance ratio"), "{err}");
+    }
+
+    #[test]
+    fn algo_channel_pushes_surface_activation_and_the_placed_order() {
+        let mut feed = AlgoOrderFeed::new();
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        feed.set_event_sender(events_tx);
+
+        let ack = r#"{"event":"subscribe","arg":{"channel":"orders-algo"}}"#;
+        assert!(!feed.handle_frame(ack), "acks are for the subscription tracker");
+
+        let live = r#"{"arg":{"channel":"orders-algo","uid":"u1"},"data":[
+            {"algoId":"a1","instId":"BTC-USDT-SWAP","state":"live","ordId":""}
+        ]}"#;
+        assert!(feed.handle_frame(live));
+        let triggered = r#"{"arg":{"channel":"orders-algo","uid":"u1"},"data":[
+            {"algoId":"a1","instId":"BTC-USDT-SWAP","state":"effective","ordId":"ord900"}
+        ]}"#;
+        assert!(feed.handle_frame(triggered));
+
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            DriverEvent::AlgoOrderUpdate { algo_id, state, order_id: None, .. }
+                if algo_id == "a1" && state == "live"
+        ));
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            DriverEvent::AlgoOrderUpdate { state, order_id: Some(ord), .. }
+                if state == "effective" && ord == "ord900"
+        ));
+    }
+
+    #[test]
+    fn a_malformed_push_is_dead_lettered_without_stalling_the_feed() {
+        let mut feed = AlgoOrderFeed::new();
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        feed.set_event_sender(events_tx);
+        let sink = std::sync::Arc::new(crate::dead_letter::MemoryDeadLetterSink::new());
+        feed.set_dead_letter_sink(std::sync::Arc::clone(&sink) as _);
+
+        // A push whose algoId changed type under us: valid JSON, fails the
+        // typed parse.
+        let corrupted = r#"{"arg":{"channel":"orders-algo","uid":"u1"},"data":[
+            {"algoId":42,"instId":"BTC-USDT-SWAP","state":"live","ordId":""}
+        ]}"#;
+        assert!(!feed.handle_frame(corrupted));
+
+        let letters = sink.dead_letters();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].channel, "orders-algo");
+        assert!(letters[0].raw.contains("\"algoId\":42"), "{}", letters[0].raw);
+
+        // The next well-formed push still flows.
+        let live = r#"{"arg":{"channel":"orders-algo","uid":"u1"},"data":[
+            {"algoId":"a2","instId":"BTC-USDT-SWAP","state":"live","ordId":""}
+        ]}"#;
+        assert!(feed.handle_frame(live));
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            DriverEvent::AlgoOrderUpdate { algo_id, .. } if algo_id == "a2"
+        ));
+
+        // Frames for other handlers never count as dead letters.
+        assert!(!feed.handle_frame(r#"{"event":"subscribe","arg":{"channel":"orders-algo"}}"#));
+        assert_eq!(sink.dead_letters().len(), 1);
+    }
+}
@@ -0,0 +1,1040 @@
+//! Typed wire structs for the OKX v5 REST API.
+//!
+//! All numeric exchange fields are strings on the wire; `Decimal` fields rely
+//! on the `serde-with-str` feature so they round-trip as plain strings.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DriverError, DriverResult};
+
+/// Envelope common to every v5 REST response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexRestResponse<T> {
+    pub code: String,
+    pub msg: String,
+    #[serde(default = "Vec::new")]
+    pub data: Vec<T>,
+}
+
+/// Snapshot of `/api/v5/account/config`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexAccountConfig {
+    /// Position mode: `net_mode` or `long_short_mode`.
+    #[serde(rename = "posMode")]
+    pub pos_mode: String,
+    /// Account level: `1` simple, `2` single-currency margin,
+    /// `3` multi-currency margin, `4` portfolio margin.
+    #[serde(rename = "acctLv")]
+    pub acct_lv: String,
+    /// Whether borrowing happens automatically in multi-currency margin.
+    #[serde(rename = "autoLoan", default)]
+    pub auto_loan: bool,
+    /// Greeks display type (`PA`/`BS`); relevant for options accounts.
+    #[serde(rename = "greeksType", default)]
+    pub greeks_type: String,
+}
+
+impl OkexAccountConfig {
+    /// Whether the account mode allows margin operations (borrow/repay,
+    /// cross/isolated trade modes).
+    pub fn is_margin_capable(&self) -> bool {
+        matches!(self.acct_lv.as_str(), "2" | "3" | "4")
+    }
+
+    /// Whether balances are unified into the single trading account.
+    /// Classic simple accounts (acctLv `1`) keep a separate funding book
+    /// that `/api/v5/account/balance` never reports.
+    pub fn is_unified(&self) -> bool {
+        self.acct_lv != "1"
+    }
+
+    /// Human-readable name of the account level for error messages.
+    pub fn account_level_name(&self) -> &'static str {
+        match self.acct_lv.as_str() {
+            "1" => "simple",
+            "2" => "single-currency margin",
+            "3" => "multi-currency margin",
+            "4" => "portfolio margin",
+            _ => "unknown",
+        }
+    }
+
+    /// Check that the configured trade mode is usable under this account
+    /// level: `cross`/`isolated` need at least single-currency margin mode.
+    pub fn validate_trade_mode(&self, trade_mode: crate::orders::TradeMode) -> DriverResult<()> {
+        use crate::orders::TradeMode;
+        match trade_mode {
+            TradeMode::Cash => Ok(()),
+            TradeMode::Cross | TradeMode::Isolated if self.is_margin_capable() => Ok(()),
+            TradeMode::Cross | TradeMode::Isolated => Err(DriverError::Config(format!(
+                "trade mode {trade_mode:?} requires at least single-currency margin mode, \
+                 but the account is in {} mode (acctLv {})",
+                self.account_level_name(),
+                self.acct_lv
+            ))),
+        }
+    }
+}
+
+/// One entry of `/api/v5/account/balance` `details`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexBalanceDetail {
+    pub ccy: String,
+    #[serde(rename = "cashBal")]
+    pub cash_balance: Decimal,
+    #[serde(rename = "availBal")]
+    pub available_balance: Decimal,
+    /// Liability in multi-currency/portfolio margin mode; absent or empty on
+    /// other account levels.
+    #[serde(rename = "liab", default, with = "crate::api_structs::parse_opt_str")]
+    pub liability: Option<Decimal>,
+    /// Cross-margin share of the liability; absent or empty outside cross
+    /// positions.
+    #[serde(rename = "crossLiab", default, with = "crate::api_structs::parse_opt_str")]
+    pub cross_liability: Option<Decimal>,
+    /// Accrued, not-yet-deducted interest on the liability.
+    #[serde(default, with = "crate::api_structs::parse_opt_str")]
+    pub interest: Option<Decimal>,
+    /// USD equity before collateral-weight haircuts.
+    #[serde(rename = "eqUsd", default, with = "crate::api_structs::parse_opt_str")]
+    pub equity_usd: Option<Decimal>,
+    /// Discounted USD equity — what OKX actually counts as collateral after
+    /// tiered weights; absent on simple accounts.
+    #[serde(rename = "disEq", default, with = "crate::api_structs::parse_opt_str")]
+    pub discounted_equity: Option<Decimal>,
+}
+
+/// `/api/v5/account/balance` top-level entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexBalance {
+    pub details: Vec<OkexBalanceDetail>,
+}
+
+/// One entry of `/api/v5/asset/balances` — the funding account, which
+/// classic accounts keep separate from the trading account.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexAssetBalance {
+    pub ccy: String,
+    #[serde(rename = "bal")]
+    pub balance: Decimal,
+    #[serde(rename = "availBal")]
+    pub available_balance: Decimal,
+    #[serde(rename = "frozenBal")]
+    pub frozen_balance: Decimal,
+}
+
+/// Side for `/api/v5/account/borrow-repay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorrowRepaySide {
+    Borrow,
+    Repay,
+}
+
+/// Request body for `/api/v5/account/borrow-repay`.
+#[derive(Debug, Clone, Serialize)]
+pub struct OkexBorrowRepayRequest {
+    pub ccy: String,
+    pub side: BorrowRepaySide,
+    pub amt: Decimal,
+}
+
+/// Result entry of `/api/v5/account/borrow-repay`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexBorrowRepayResult {
+    pub ccy: String,
+    pub side: BorrowRepaySide,
+    pub amt: Decimal,
+}
+
+/// Entry of `/api/v5/account/borrow-repay-history`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexBorrowRepayHistory {
+    pub ccy: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(rename = "tradedLoan")]
+    pub traded_loan: Decimal,
+    #[serde(rename = "usedLoan")]
+    pub used_loan: Decimal,
+    #[serde(rename = "ts")]
+    pub timestamp: String,
+}
+
+/// One fill from `/api/v5/trade/fills`.
+///
+/// `px` is empty for some fill types (e.g. exercised options), so every
+/// numeric field OKX does not guarantee is an `Option` via `parse_opt_str`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransactionResult {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tradeId")]
+    pub trade_id: String,
+    #[serde(rename = "ordId")]
+    pub order_id: String,
+    #[serde(rename = "clOrdId", default)]
+    pub client_order_id: Option<String>,
+    #[serde(rename = "billId", default)]
+    pub bill_id: Option<String>,
+    #[serde(rename = "fillPx", default, with = "parse_opt_str")]
+    pub price: Option<Decimal>,
+    #[serde(rename = "fillSz")]
+    pub size: Decimal,
+    pub side: String,
+    #[serde(default, with = "parse_opt_str")]
+    pub fee: Option<Decimal>,
+    #[serde(rename = "feeCcy", default)]
+    pub fee_currency: Option<String>,
+    /// `"T"` taker or `"M"` maker; empty on some historical records.
+    #[serde(rename = "execType", default)]
+    pub exec_type: Option<String>,
+    /// Realized PnL of this execution, derivatives only; empty for spot.
+    #[serde(rename = "fillPnl", default, with = "parse_opt_str")]
+    pub fill_pnl: Option<Decimal>,
+    #[serde(rename = "ts")]
+    pub timestamp: String,
+}
+
+/// One entry of `/api/v5/account/positions`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexPosition {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "posSide")]
+    pub position_side: String,
+    #[serde(rename = "pos")]
+    pub position: Decimal,
+    /// Empty until the position has a fill.
+    #[serde(rename = "avgPx", default, with = "parse_opt_str")]
+    pub average_price: Option<Decimal>,
+    #[serde(rename = "upl", default, with = "parse_opt_str")]
+    pub unrealized_pnl: Option<Decimal>,
+    #[serde(rename = "lever", default, with = "parse_opt_str")]
+    pub leverage: Option<Decimal>,
+    #[serde(rename = "notionalUsd", default, with = "parse_opt_str")]
+    pub notional_usd: Option<Decimal>,
+}
+
+/// One entry of `/api/v5/account/bills`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexBillResponse {
+    #[serde(rename = "billId")]
+    pub bill_id: String,
+    pub ccy: String,
+    /// Set on trade and funding bills; empty for transfers.
+    #[serde(rename = "instId", default)]
+    pub inst_id: Option<String>,
+    /// Set on trade bills placed with a client order id; empty otherwise.
+    #[serde(rename = "clOrdId", default)]
+    pub client_order_id: Option<String>,
+    /// Execution id; trade bills only.
+    #[serde(rename = "tradeId", default)]
+    pub trade_id: Option<String>,
+    #[serde(rename = "ordId", default)]
+    pub order_id: Option<String>,
+    #[serde(rename = "balChg")]
+    pub balance_change: Decimal,
+    /// Fill size in contracts or base units; trade bills only.
+    #[serde(rename = "sz", default, with = "parse_opt_str")]
+    pub size: Option<Decimal>,
+    /// Empty for non-trade bills (transfers, funding).
+    #[serde(rename = "px", default, with = "parse_opt_str")]
+    pub price: Option<Decimal>,
+    /// Fee of the execution, negative when charged; trade bills only.
+    #[serde(default, with = "parse_opt_str")]
+    pub fee: Option<Decimal>,
+    /// `"T"` taker or `"M"` maker; trade bills only.
+    #[serde(rename = "execType", default)]
+    pub exec_type: Option<String>,
+    #[serde(rename = "type")]
+    pub bill_type: String,
+    #[serde(rename = "subType", default)]
+    pub sub_type: Option<String>,
+    #[serde(rename = "ts")]
+    pub timestamp: String,
+}
+
+/// One entry of `/api/v5/account/positions-history`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexPositionHistory {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "openAvgPx", default, with = "parse_opt_str")]
+    pub open_average_price: Option<Decimal>,
+    #[serde(rename = "closeAvgPx", default, with = "parse_opt_str")]
+    pub close_average_price: Option<Decimal>,
+    #[serde(rename = "realizedPnl", default, with = "parse_opt_str")]
+    pub realized_pnl: Option<Decimal>,
+    /// Trading fees over the position's lifetime; negative when charged.
+    #[serde(default, with = "parse_opt_str")]
+    pub fee: Option<Decimal>,
+    /// Accumulated funding; negative when paid.
+    #[serde(rename = "fundingFee", default, with = "parse_opt_str")]
+    pub funding_fee: Option<Decimal>,
+    /// Closed size in contracts.
+    #[serde(rename = "closeTotalPos")]
+    pub close_total_position: Decimal,
+    /// Open time, milliseconds.
+    #[serde(rename = "cTime")]
+    pub created_at: String,
+    /// Close (last update) time, milliseconds.
+    #[serde(rename = "uTime")]
+    pub updated_at: String,
+}
+
+/// One entry of `/api/v5/public/instruments`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexInstrumentInfo {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "instType")]
+    pub inst_type: String,
+    /// `live`, `suspend`, `preopen`, or `test`.
+    pub state: String,
+    #[serde(rename = "tickSz")]
+    pub tick_size: Decimal,
+    #[serde(rename = "lotSz")]
+    pub lot_size: Decimal,
+    #[serde(rename = "minSz")]
+    pub min_size: Decimal,
+    /// Contract value; empty for spot.
+    #[serde(rename = "ctVal", default, with = "parse_opt_str")]
+    pub contract_value: Option<Decimal>,
+    /// Settlement time in milliseconds; empty except for dated contracts.
+    #[serde(rename = "expTime", default, with = "parse_opt_str")]
+    pub expiry_time: Option<u64>,
+}
+
+/// One entry of `/api/v5/public/estimated-price` — the estimated delivery/
+/// settlement price, published within an hour of settlement.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexEstimatedPrice {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "instType")]
+    pub inst_type: String,
+    #[serde(rename = "settlePx")]
+    pub settlement_price: Decimal,
+    /// Estimate time, milliseconds.
+    pub ts: String,
+}
+
+/// One entry of `/api/v5/public/funding-rate` — the rate for the upcoming
+/// funding settlement of one perpetual.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexFundingRate {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: Decimal,
+    /// Scheduled settlement time of `funding_rate`, milliseconds.
+    #[serde(rename = "fundingTime")]
+    pub funding_time: String,
+    /// Predicted rate for the cycle after that; indicative only.
+    #[serde(rename = "nextFundingRate", default, with = "parse_opt_str")]
+    pub next_funding_rate: Option<Decimal>,
+}
+
+/// One entry of `/api/v5/system/status` — a scheduled or ongoing
+/// maintenance event.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexSystemStatus {
+    pub title: String,
+    /// `scheduled`, `ongoing`, `pre_open`, `completed` or `canceled`.
+    pub state: String,
+    /// Maintenance start, milliseconds.
+    #[serde(default)]
+    pub begin: Option<String>,
+    /// Maintenance end, milliseconds.
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+/// One entry of `/api/v5/market/books` — a depth snapshot with positional
+/// levels. Each level is `[price, size, liquidated, orders]`; the
+/// liquidated-orders slot is deprecated and always `"0"`, and books-lite
+/// responses omit the trailing slots entirely, so levels are kept as raw
+/// string arrays and typed during normalization.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexOrderBookSnapshot {
+    pub bids: Vec<Vec<String>>,
+    pub asks: Vec<Vec<String>>,
+    /// Snapshot time, milliseconds.
+    pub ts: String,
+}
+
+/// One entry of `/api/v5/market/tickers`. Only the last trade price is
+/// kept — the driver uses tickers for notional estimates, not for quoting.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexTicker {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    /// Last traded price; empty for instruments that have never traded.
+    #[serde(default, with = "parse_opt_str")]
+    pub last: Option<Decimal>,
+    /// Snapshot time, milliseconds.
+    #[serde(rename = "ts")]
+    pub timestamp: String,
+}
+
+/// One converted asset from `/api/v5/asset/convert-dust-assets`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexDustConversionDetail {
+    pub ccy: String,
+    /// Dust amount converted away, in `ccy` units.
+    #[serde(rename = "amt")]
+    pub amount: Decimal,
+    /// OKB credited for this asset, fee already deducted.
+    #[serde(rename = "cnvAmt")]
+    pub converted_amount: Decimal,
+    /// Conversion fee, in OKB.
+    pub fee: Decimal,
+}
+
+/// Result entry of `/api/v5/asset/convert-dust-assets`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexDustConversionResult {
+    /// Total OKB credited across all converted assets.
+    #[serde(rename = "totalCnvAmt")]
+    pub total_converted_amount: Decimal,
+    pub details: Vec<OkexDustConversionDetail>,
+}
+
+/// Quote from `/api/v5/asset/convert/estimate-quote`. Short-lived: the
+/// exchange honours it for `ttl_ms` (~10s) after `quote_time`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexConvertQuote {
+    #[serde(rename = "quoteId")]
+    pub quote_id: String,
+    #[serde(rename = "baseCcy")]
+    pub base_ccy: String,
+    #[serde(rename = "quoteCcy")]
+    pub quote_ccy: String,
+    /// `buy` (acquire base) or `sell` (dispose of base).
+    pub side: String,
+    /// Quoted conversion price, quote units per base unit.
+    #[serde(rename = "cnvtPx")]
+    pub convert_price: Decimal,
+    #[serde(rename = "baseSz")]
+    pub base_size: Decimal,
+    #[serde(rename = "quoteSz")]
+    pub quote_size: Decimal,
+    /// The amount the quote was requested for, echoed back.
+    #[serde(rename = "rfqSz")]
+    pub rfq_size: Decimal,
+    #[serde(rename = "rfqSzCcy")]
+    pub rfq_size_ccy: String,
+    /// When the quote was priced, milliseconds.
+    #[serde(rename = "quoteTime")]
+    pub quote_time: String,
+    /// Quote validity window, milliseconds.
+    #[serde(rename = "ttlMs", default, with = "parse_opt_str")]
+    pub ttl_ms: Option<u64>,
+}
+
+/// Result of `/api/v5/asset/convert/trade`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexConvertResult {
+    #[serde(rename = "tradeId")]
+    pub trade_id: String,
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "baseCcy")]
+    pub base_ccy: String,
+    #[serde(rename = "quoteCcy")]
+    pub quote_ccy: String,
+    pub side: String,
+    #[serde(rename = "fillPx")]
+    pub fill_price: Decimal,
+    #[serde(rename = "fillBaseSz")]
+    pub fill_base_size: Decimal,
+    #[serde(rename = "fillQuoteSz")]
+    pub fill_quote_size: Decimal,
+    /// `fullyFilled` on success.
+    pub state: String,
+    /// Execution time, milliseconds.
+    #[serde(rename = "ts")]
+    pub timestamp: String,
+}
+
+/// One row of `/api/v5/asset/currencies` — the endpoint returns one entry
+/// per (currency, chain) pair, with empty strings for numerics a chain
+/// does not define.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexCurrencyChain {
+    pub ccy: String,
+    /// Chain name, e.g. `USDT-ERC20`; empty for chainless assets.
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(rename = "canDep", default)]
+    pub can_deposit: bool,
+    #[serde(rename = "canWd", default)]
+    pub can_withdraw: bool,
+    #[serde(rename = "minWd", default, with = "parse_opt_str")]
+    pub min_withdrawal: Option<Decimal>,
+    /// Remaining withdrawal quota, USD, over the rolling 24h window.
+    #[serde(rename = "wdQuota", default, with = "parse_opt_str")]
+    pub withdrawal_quota: Option<Decimal>,
+    #[serde(rename = "minFee", default, with = "parse_opt_str")]
+    pub min_fee: Option<Decimal>,
+    #[serde(rename = "maxFee", default, with = "parse_opt_str")]
+    pub max_fee: Option<Decimal>,
+    /// Confirmations before a deposit is credited.
+    #[serde(rename = "minDepArrivalConfirm", default, with = "parse_opt_str")]
+    pub deposit_confirmations: Option<u32>,
+    /// Confirmations before a deposited amount may be withdrawn again.
+    #[serde(rename = "minWdUnlockConfirm", default, with = "parse_opt_str")]
+    pub withdrawal_unlock_confirmations: Option<u32>,
+}
+
+/// One entry of `/api/v5/account/leverage-info`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexLeverageInfo {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "mgnMode")]
+    pub margin_mode: String,
+    #[serde(rename = "lever", default, with = "parse_opt_str")]
+    pub leverage: Option<Decimal>,
+}
+
+/// One tier of `/api/v5/public/position-tiers` — the maintenance margin
+/// ladder: positions within `[min_size, max_size]` (contracts, or quote
+/// amount for margin pairs) carry these margin ratios and leverage cap.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexPositionTier {
+    #[serde(rename = "instId", default)]
+    pub inst_id: String,
+    #[serde(default, with = "parse_opt_str")]
+    pub tier: Option<u32>,
+    #[serde(rename = "minSz", default, with = "parse_opt_str")]
+    pub min_size: Option<Decimal>,
+    #[serde(rename = "maxSz", default, with = "parse_opt_str")]
+    pub max_size: Option<Decimal>,
+    /// Maintenance margin ratio.
+    #[serde(default, with = "parse_opt_str")]
+    pub mmr: Option<Decimal>,
+    /// Initial margin ratio.
+    #[serde(default, with = "parse_opt_str")]
+    pub imr: Option<Decimal>,
+    #[serde(rename = "maxLever", default, with = "parse_opt_str")]
+    pub max_leverage: Option<Decimal>,
+}
+
+/// One entry of `/api/v5/trade/orders-pending`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexPendingOrder {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "instType", default)]
+    pub inst_type: String,
+    #[serde(rename = "ordId")]
+    pub order_id: String,
+    #[serde(rename = "clOrdId", default)]
+    pub client_order_id: Option<String>,
+    #[serde(rename = "ordType", default)]
+    pub order_type: String,
+    /// Empty for market orders.
+    #[serde(rename = "px", default, with = "parse_opt_str")]
+    pub price: Option<Decimal>,
+    #[serde(rename = "sz")]
+    pub size: Decimal,
+    pub side: String,
+    pub state: String,
+    /// Creation time, milliseconds.
+    #[serde(rename = "cTime")]
+    pub created_at: String,
+}
+
+/// Order state push from the WS `orders` channel (and REST order fetches).
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexOrderUpdate {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordId")]
+    pub order_id: String,
+    #[serde(rename = "clOrdId", default)]
+    pub client_order_id: Option<String>,
+    /// `limit`, `market`, `optimal_limit_ioc`, ...; drives how a terminal
+    /// `canceled` state is read (see [`crate::orders::RawOrder`]).
+    #[serde(rename = "ordType", default)]
+    pub order_type: String,
+    pub state: String,
+    /// Empty for market orders.
+    #[serde(rename = "px", default, with = "parse_opt_str")]
+    pub price: Option<Decimal>,
+    #[serde(rename = "avgPx", default, with = "parse_opt_str")]
+    pub average_price: Option<Decimal>,
+    #[serde(rename = "sz")]
+    pub size: Decimal,
+    #[serde(rename = "accFillSz", default, with = "parse_opt_str")]
+    pub accumulated_fill_size: Option<Decimal>,
+    /// `base_ccy`/`quote_ccy` on spot market orders; empty otherwise.
+    #[serde(rename = "tgtCcy", default)]
+    pub target_currency: String,
+    pub side: String,
+    #[serde(rename = "uTime")]
+    pub updated_at: String,
+}
+
+/// One amendment for `/api/v5/trade/amend-order` / `amend-batch-orders`
+/// (REST and WS share the shape).
+///
+/// `new_px`/`new_sz` are pre-normalized strings; build them through the
+/// precision helpers, never by formatting Decimals directly.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OkexAmendOrderRequest {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordId")]
+    pub order_id: String,
+    #[serde(rename = "newPx", skip_serializing_if = "Option::is_none")]
+    pub new_px: Option<String>,
+    #[serde(rename = "newSz", skip_serializing_if = "Option::is_none")]
+    pub new_sz: Option<String>,
+}
+
+/// Result entry of `/api/v5/trade/cancel-all-after`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexCancelAllAfterResult {
+    /// When the switch will fire, milliseconds; `"0"` when disarmed.
+    #[serde(rename = "triggerTime")]
+    pub trigger_time: String,
+    pub ts: String,
+}
+
+/// Per-order result entry returned by the order/amend/cancel endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexOrderOpResult {
+    #[serde(rename = "ordId", default)]
+    pub order_id: String,
+    #[serde(rename = "clOrdId", default)]
+    pub client_order_id: Option<String>,
+    #[serde(rename = "sCode")]
+    pub s_code: String,
+    #[serde(rename = "sMsg", default)]
+    pub s_msg: String,
+}
+
+/// Per-order result entry of the algo-order endpoints (`order-algo`,
+/// `cancel-algos`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexAlgoOrderResult {
+    #[serde(rename = "algoId", default)]
+    pub algo_id: String,
+    #[serde(rename = "algoClOrdId", default)]
+    pub algo_client_id: Option<String>,
+    #[serde(rename = "sCode")]
+    pub s_code: String,
+    #[serde(rename = "sMsg", default)]
+    pub s_msg: String,
+}
+
+/// One resting algo order from `/api/v5/trade/orders-algo-pending`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexPendingAlgoOrder {
+    #[serde(rename = "algoId")]
+    pub algo_id: String,
+    #[serde(rename = "algoClOrdId", default)]
+    pub algo_client_id: Option<String>,
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordType", default)]
+    pub order_type: String,
+    pub state: String,
+    pub side: String,
+    /// Size in contracts (or base units for spot margin).
+    #[serde(rename = "sz")]
+    pub size: Decimal,
+    #[serde(rename = "callbackRatio", default, with = "parse_opt_str")]
+    pub callback_ratio: Option<Decimal>,
+    #[serde(rename = "callbackSpread", default, with = "parse_opt_str")]
+    pub callback_spread: Option<Decimal>,
+    #[serde(rename = "activePx", default, with = "parse_opt_str")]
+    pub active_price: Option<Decimal>,
+    /// The price the trail currently triggers at; moves with the market.
+    #[serde(rename = "moveTriggerPx", default, with = "parse_opt_str")]
+    pub move_trigger_price: Option<Decimal>,
+    /// Creation time, milliseconds.
+    #[serde(rename = "cTime")]
+    pub created_at: String,
+}
+
+/// One algo order's details from `GET /api/v5/trade/order-algo`; carries
+/// the fill progress the pending list omits.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkexAlgoOrderDetails {
+    #[serde(rename = "algoId")]
+    pub algo_id: String,
+    #[serde(rename = "algoClOrdId", default)]
+    pub algo_client_id: Option<String>,
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordType", default)]
+    pub order_type: String,
+    pub state: String,
+    /// Total size in contracts (or base units for spot margin).
+    #[serde(rename = "sz")]
+    pub size: Decimal,
+    /// Accumulated filled size, same denomination as `sz`.
+    #[serde(rename = "accFillSz", default, with = "parse_opt_str")]
+    pub filled_size: Option<Decimal>,
+    /// Regular orders the algo has placed so far, oldest first.
+    #[serde(rename = "ordIdList", default)]
+    pub order_ids: Vec<String>,
+}
+
+/// Deserializer for optional numeric fields OKX sends as `""` when not
+/// applicable.
+pub mod parse_opt_str {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        T::Err: Display,
+    {
+        let raw = Option::<String>::deserialize(deserializer)?;
+        match raw.as_deref() {
+            None | Some("") => Ok(None),
+            Some(s) => s.parse::<T>().map(Some).map_err(serde::de::Error::custom),
+        }
+    }
+
+    pub fn serialize<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Display,
+    {
+        match value {
+            Some(v) => serializer.serialize_str(&v.to_string()),
+            None => serializer.serialize_str(""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_detail_parses_empty_liability() {
+        let raw = r#"{"ccy":"USDT","cashBal":"100.5","availBal":"90","liab":""}"#;
+        let detail: OkexBalanceDetail = serde_json::from_str(raw).unwrap();
+        assert_eq!(detail.liability, None);
+    }
+
+    #[test]
+    fn balance_detail_parses_present_liability() {
+        let raw = r#"{"ccy":"USDT","cashBal":"-12.5","availBal":"0","liab":"12.5"}"#;
+        let detail: OkexBalanceDetail = serde_json::from_str(raw).unwrap();
+        assert_eq!(detail.liability, Some(Decimal::new(125, 1)));
+    }
+
+    #[test]
+    fn balance_detail_parses_cross_liability_and_interest() {
+        let raw = r#"{"ccy":"USDT","cashBal":"0","availBal":"0","liab":"500","crossLiab":"500","interest":"1.5"}"#;
+        let detail: OkexBalanceDetail = serde_json::from_str(raw).unwrap();
+        assert_eq!(detail.cross_liability, Some(Decimal::new(500, 0)));
+        assert_eq!(detail.interest, Some(Decimal::new(15, 1)));
+    }
+
+    /// Real payload shapes with `""` in every numeric field OKX may omit;
+    /// any of these failing to parse silently drops whole pages upstream.
+    const EMPTY_NUMERIC_FIXTURES: &str = include_str!("test_data/empty_numeric_fields.json");
+
+    fn fixture(key: &str) -> serde_json::Value {
+        serde_json::from_str::<serde_json::Value>(EMPTY_NUMERIC_FIXTURES).unwrap()[key].clone()
+    }
+
+    #[test]
+    fn transaction_result_tolerates_empty_numeric_fields() {
+        let result: TransactionResult =
+            serde_json::from_value(fixture("transaction_result")).unwrap();
+        assert_eq!(result.price, None);
+        assert_eq!(result.fee, None);
+        assert_eq!(result.size, Decimal::new(192834, 8));
+    }
+
+    #[test]
+    fn position_tolerates_empty_numeric_fields() {
+        let position: OkexPosition = serde_json::from_value(fixture("position")).unwrap();
+        assert_eq!(position.average_price, None);
+        assert_eq!(position.unrealized_pnl, None);
+        assert_eq!(position.notional_usd, None);
+        assert_eq!(position.leverage, Some(Decimal::new(10, 0)));
+    }
+
+    #[test]
+    fn bill_tolerates_empty_price() {
+        let bill: OkexBillResponse = serde_json::from_value(fixture("bill")).unwrap();
+        assert_eq!(bill.price, None);
+        assert_eq!(bill.balance_change, Decimal::new(300, 0));
+    }
+
+    #[test]
+    fn order_update_tolerates_empty_numeric_fields() {
+        let update: OkexOrderUpdate = serde_json::from_value(fixture("order_update")).unwrap();
+        assert_eq!(update.price, None);
+        assert_eq!(update.average_price, None);
+        assert_eq!(update.accumulated_fill_size, None);
+        assert_eq!(update.size, Decimal::new(5, 1));
+    }
+
+    #[test]
+    fn account_config_margin_capability() {
+        let simple: OkexAccountConfig =
+            serde_json::from_str(r#"{"posMode":"net_mode","acctLv":"1"}"#).unwrap();
+        assert!(!simple.is_margin_capable());
+        let multi: OkexAccountConfig =
+            serde_json::from_str(r#"{"posMode":"net_mode","acctLv":"3"}"#).unwrap();
+        assert!(multi.is_margin_capable());
+    }
+
+    #[test]
+    fn account_config_parses_every_account_level() {
+        // One fixture per acctLv; shapes as returned by /account/config.
+        let fixtures = [
+            (r#"{"posMode":"net_mode","acctLv":"1","autoLoan":false,"greeksType":"PA"}"#, "simple"),
+            (r#"{"posMode":"net_mode","acctLv":"2","autoLoan":false,"greeksType":"PA"}"#, "single-currency margin"),
+            (r#"{"posMode":"long_short_mode","acctLv":"3","autoLoan":true,"greeksType":"BS"}"#, "multi-currency margin"),
+            (r#"{"posMode":"net_mode","acctLv":"4","autoLoan":true,"greeksType":"BS"}"#, "portfolio margin"),
+        ];
+        for (raw, level_name) in fixtures {
+            let config: OkexAccountConfig = serde_json::from_str(raw).unwrap();
+            assert_eq!(config.account_level_name(), level_name, "fixture: {raw}");
+        }
+
+        let multi: OkexAccountConfig = serde_json::from_str(fixtures[2].0).unwrap();
+        assert!(multi.auto_loan);
+        assert_eq!(multi.greeks_type, "BS");
+    }
+
+    /// Strict mirrors of the core response structs with
+    /// `deny_unknown_fields`, parsed against fixtures where every expected
+    /// field is present. A typo'd `rename` on a production struct leaves the
+    /// real wire name unknown to its mirror, so the drift fails here instead
+    /// of silently defaulting the field in production. The production structs
+    /// themselves must keep tolerating unknown fields; only these mirrors
+    /// are strict. Keep field lists in sync with the structs above.
+    mod strict {
+        use super::*;
+
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictPendingOrder {
+            #[serde(rename = "instId")]
+            _inst_id: String,
+            #[serde(rename = "instType")]
+            _inst_type: String,
+            #[serde(rename = "ordId")]
+            _order_id: String,
+            #[serde(rename = "clOrdId")]
+            _client_order_id: Option<String>,
+            #[serde(rename = "ordType")]
+            _order_type: String,
+            #[serde(rename = "px", with = "parse_opt_str")]
+            _price: Option<Decimal>,
+            #[serde(rename = "sz")]
+            _size: Decimal,
+            #[serde(rename = "side")]
+            _side: String,
+            #[serde(rename = "state")]
+            _state: String,
+            #[serde(rename = "cTime")]
+            _created_at: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictOrderUpdate {
+            #[serde(rename = "instId")]
+            _inst_id: String,
+            #[serde(rename = "ordId")]
+            _order_id: String,
+            #[serde(rename = "clOrdId")]
+            _client_order_id: Option<String>,
+            #[serde(rename = "ordType")]
+            _order_type: String,
+            #[serde(rename = "state")]
+            _state: String,
+            #[serde(rename = "px", with = "parse_opt_str")]
+            _price: Option<Decimal>,
+            #[serde(rename = "avgPx", with = "parse_opt_str")]
+            _average_price: Option<Decimal>,
+            #[serde(rename = "sz")]
+            _size: Decimal,
+            #[serde(rename = "accFillSz", with = "parse_opt_str")]
+            _accumulated_fill_size: Option<Decimal>,
+            #[serde(rename = "side")]
+            _side: String,
+            #[serde(rename = "uTime")]
+            _updated_at: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictBill {
+            #[serde(rename = "billId")]
+            _bill_id: String,
+            #[serde(rename = "ccy")]
+            _ccy: String,
+            #[serde(rename = "instId")]
+            _inst_id: Option<String>,
+            #[serde(rename = "clOrdId")]
+            _client_order_id: Option<String>,
+            #[serde(rename = "tradeId")]
+            _trade_id: Option<String>,
+            #[serde(rename = "ordId")]
+            _order_id: Option<String>,
+            #[serde(rename = "balChg")]
+            _balance_change: Decimal,
+            #[serde(rename = "sz", with = "parse_opt_str")]
+            _size: Option<Decimal>,
+            #[serde(rename = "px", with = "parse_opt_str")]
+            _price: Option<Decimal>,
+            #[serde(rename = "fee", with = "parse_opt_str")]
+            _fee: Option<Decimal>,
+            #[serde(rename = "execType")]
+            _exec_type: Option<String>,
+            #[serde(rename = "type")]
+            _bill_type: String,
+            #[serde(rename = "subType")]
+            _sub_type: Option<String>,
+            #[serde(rename = "ts")]
+            _timestamp: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictTransactionResult {
+            #[serde(rename = "instId")]
+            _inst_id: String,
+            #[serde(rename = "tradeId")]
+            _trade_id: String,
+            #[serde(rename = "ordId")]
+            _order_id: String,
+            #[serde(rename = "clOrdId")]
+            _client_order_id: Option<String>,
+            #[serde(rename = "billId")]
+            _bill_id: Option<String>,
+            #[serde(rename = "fillPx", with = "parse_opt_str")]
+            _price: Option<Decimal>,
+            #[serde(rename = "fillSz")]
+            _size: Decimal,
+            #[serde(rename = "side")]
+            _side: String,
+            #[serde(rename = "fee", with = "parse_opt_str")]
+            _fee: Option<Decimal>,
+            #[serde(rename = "feeCcy")]
+            _fee_currency: Option<String>,
+            #[serde(rename = "execType")]
+            _exec_type: Option<String>,
+            #[serde(rename = "ts")]
+            _timestamp: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StrictPosition {
+            #[serde(rename = "instId")]
+            _inst_id: String,
+            #[serde(rename = "posSide")]
+            _position_side: String,
+            #[serde(rename = "pos")]
+            _position: Decimal,
+            #[serde(rename = "avgPx", with = "parse_opt_str")]
+            _average_price: Option<Decimal>,
+            #[serde(rename = "upl", with = "parse_opt_str")]
+            _unrealized_pnl: Option<Decimal>,
+            #[serde(rename = "lever", with = "parse_opt_str")]
+            _leverage: Option<Decimal>,
+            #[serde(rename = "notionalUsd", with = "parse_opt_str")]
+            _notional_usd: Option<Decimal>,
+        }
+
+        /// Fully populated payloads, one per struct; every field the
+        /// production structs map must appear here.
+        const COMPLETE_FIXTURES: &str = include_str!("test_data/complete_fields.json");
+
+        fn complete(key: &str) -> serde_json::Value {
+            serde_json::from_str::<serde_json::Value>(COMPLETE_FIXTURES).unwrap()[key].clone()
+        }
+
+        /// Parse the fixture with the strict mirror and the production
+        /// struct; the mirror catches drifted renames, the production parse
+        /// proves the fixture itself is representative.
+        fn assert_in_sync<Strict, Production>(key: &str)
+        where
+            Strict: serde::de::DeserializeOwned,
+            Production: serde::de::DeserializeOwned,
+        {
+            let value = complete(key);
+            if let Err(err) = serde_json::from_value::<Strict>(value.clone()) {
+                panic!("strict mirror for {key} rejected the fixture (renamed field drifted?): {err}");
+            }
+            if let Err(err) = serde_json::from_value::<Production>(value) {
+                panic!("production struct for {key} rejected the fixture: {err}");
+            }
+        }
+
+        #[test]
+        fn pending_order_fields_are_in_sync() {
+            assert_in_sync::<StrictPendingOrder, OkexPendingOrder>("pending_order");
+        }
+
+        #[test]
+        fn order_update_fields_are_in_sync() {
+            assert_in_sync::<StrictOrderUpdate, OkexOrderUpdate>("order_update");
+        }
+
+        #[test]
+        fn bill_fields_are_in_sync() {
+            assert_in_sync::<StrictBill, OkexBillResponse>("bill");
+        }
+
+        #[test]
+        fn transaction_result_fields_are_in_sync() {
+            assert_in_sync::<StrictTransactionResult, TransactionResult>("transaction_result");
+        }
+
+        #[test]
+        fn position_fields_are_in_sync() {
+            assert_in_sync::<StrictPosition, OkexPosition>("position");
+        }
+
+        #[test]
+        fn mirrors_reject_unmapped_fields() {
+            let mut value = complete("position");
+            value["margin"] = serde_json::json!("100");
+            assert!(
+                serde_json::from_value::<StrictPosition>(value.clone()).is_err(),
+                "strict mirror must reject unmapped fields"
+            );
+            // Production stays tolerant of the same payload.
+            serde_json::from_value::<OkexPosition>(value).unwrap();
+        }
+    }
+
+    #[test]
+    fn trade_mode_validation_against_account_level() {
+        use crate::orders::TradeMode;
+
+        let simple: OkexAccountConfig =
+            serde_json::from_str(r#"{"posMode":"net_mode","acctLv":"1"}"#).unwrap();
+        assert!(simple.validate_trade_mode(TradeMode::Cash).is_ok());
+        let err = simple.validate_trade_mode(TradeMode::Cross).unwrap_err();
+        assert!(err.to_string().contains("simple mode"), "got: {err}");
+
+        let margin: OkexAccountConfig =
+            serde_json::from_str(r#"{"posMode":"net_mode","acctLv":"2"}"#).unwrap();
+        assert!(margin.validate_trade_mode(TradeMode::Cross).is_ok());
+        assert!(margin.validate_trade_mode(TradeMode::Isolated).is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+//! Attribution of balance changes to their cause.
+//!
+//! The `balance_and_position` channel reports *that* a balance moved; the
+//! bills stream reports *why* money moved. [`BalanceReasonMatcher`] joins the
+//! two on (asset, amount, time window) and emits annotated
+//! [`BalanceUpdate`] events, holding a balance event back at most
+//! [`crate::config::OkexConfig::balance_attribution_delay`] before giving up
+//! and emitting it with [`BalanceChangeReason::Unknown`].
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::events::{BalanceUpdate, DriverEvent, DriverEventSender};
+
+/// How far apart a bill and a balance event may be and still match.
+const MATCH_WINDOW: Duration = Duration::from_secs(2);
+
+/// Best-effort cause of a balance change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceChangeReason {
+    Trade,
+    Fee,
+    Funding,
+    Transfer,
+    Unknown,
+}
+
+impl BalanceChangeReason {
+    /// Map the `balance_and_position` channel `eventType` where it is
+    /// unambiguous on its own.
+    pub fn from_event_type(event_type: &str) -> Option<Self> {
+        match event_type {
+            "filled" => Some(Self::Trade),
+            "funding_fee" => Some(Self::Funding),
+            "transferred" => Some(Self::Transfer),
+            "interest_deduction" => Some(Self::Fee),
+            _ => None,
+        }
+    }
+
+    /// Map an OKX bill `type` (transfer = 1, trade = 2, funding fee = 8,
+    /// interest deduction = 7).
+    pub fn from_bill_type(bill_type: &str) -> Self {
+        match bill_type {
+            "1" => Self::Transfer,
+            "2" => Self::Trade,
+            "7" => Self::Fee,
+            "8" => Self::Funding,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Balance event parked while we wait for an attributing bill.
+struct PendingBalance {
+    asset: String,
+    delta: Decimal,
+    exchange_time: DateTime<Utc>,
+    /// Emit with `Unknown` no later than this.
+    deadline: Instant,
+}
+
+/// Bill kept around briefly to attribute balance events that arrive after
+/// it.
+struct BillHint {
+    asset: String,
+    amount: Decimal,
+    reason: BalanceChangeReason,
+    seen_at: Instant,
+}
+
+/// Joins balance deltas with bills and pushes annotated events downstream.
+///
+/// All methods take an explicit `now` so the event loop drives time and
+/// tests stay deterministic.
+pub struct BalanceReasonMatcher {
+    max_delay: Duration,
+    pending: Vec<PendingBalance>,
+    bills: VecDeque<BillHint>,
+    out: DriverEventSender,
+}
+
+impl BalanceReasonMatcher {
+    pub fn new(max_delay: Duration, out: DriverEventSender) -> Self {
+        Self {
+            max_delay,
+            pending: Vec::new(),
+            bills: VecDeque::new(),
+            out,
+        }
+    }
+
+    /// Feed one balance delta from the account channel. When the channel
+    /// `eventType` already names the cause, the event is emitted
+    /// immediately; otherwise a recent matching bill is consulted, and
+    /// failing that the event is parked until [`Self::flush_expired`].
+    pub fn on_balance_delta(
+        &mut self,
+        asset: &str,
+        delta: Decimal,
+        exchange_time: DateTime<Utc>,
+        event_type: Option<&str>,
+        now: Instant,
+    ) {
+        if let Some(reason) = event_type.and_then(BalanceChangeReason::from_event_type) {
+            self.emit(asset, delta, reason, exchange_time);
+            return;
+        }
+
+        self.prune_bills(now);
+        if let Some(position) = self
+            .bills
+            .iter()
+            .position(|b| b.asset == asset && b.amount == delta)
+        {
+            let bill = self.bills.remove(position).expect("position is valid");
+            self.emit(asset, delta, bill.reason, exchange_time);
+            return;
+        }
+
+        self.pending.push(PendingBalance {
+            asset: asset.to_string(),
+            delta,
+            exchange_time,
+            deadline: now + self.max_delay,
+        });
+    }
+
+    /// Feed one bill record. Attributes a parked balance event when one
+    /// matches, otherwise keeps the bill around for the match window.
+    pub fn on_bill(
+        &mut self,
+        asset: &str,
+        amount: Decimal,
+        bill_type: &str,
+        now: Instant,
+    ) {
+        let reason = BalanceChangeReason::from_bill_type(bill_type);
+        if let Some(position) = self
+            .pending
+            .iter()
+            .position(|p| p.asset == asset && p.delta == amount)
+        {
+            let pending = self.pending.remove(position);
+            self.emit(&pending.asset, pending.delta, reason, pending.exchange_time);
+            return;
+        }
+
+        self.prune_bills(now);
+        self.bills.push_back(BillHint {
+            asset: asset.to_string(),
+            amount,
+            reason,
+            seen_at: now,
+        });
+    }
+
+    /// Emit every parked balance event whose deadline has passed with an
+    /// `Unknown` reason. The event loop calls this on a timer; the next
+    /// deadline is available via [`Self::next_deadline`].
+    pub fn flush_expired(&mut self, now: Instant) {
+        let mut index = 0;
+        while index < self.pending.len() {
+            if self.pending[index].deadline <= now {
+                let pending = self.pending.remove(index);
+                self.emit(
+                    &pending.asset,
+                    pending.delta,
+                    BalanceChangeReason::Unknown,
+                    pending.exchange_time,
+                );
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Earliest deadline among parked events, if any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.iter().map(|p| p.deadline).min()
+    }
+
+    fn prune_bills(&mut self, now: Instant) {
+        while let Some(front) = self.bills.front() {
+            if now.duration_since(front.seen_at) > MATCH_WINDOW {
+                self.bills.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn emit(
+        &self,
+        asset: &str,
+        delta: Decimal,
+        reason: BalanceChangeReason,
+        exchange_time: DateTime<Utc>,
+    ) {
+        // The receiver dropping just means the consumer went away.
+        let _ = self.out.send(DriverEvent::BalanceUpdate(BalanceUpdate {
+            asset: asset.to_string(),
+            delta,
+            reason,
+            exchange_time,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DriverEventReceiver;
+
+    fn setup() -> (BalanceReasonMatcher, DriverEventReceiver) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (BalanceReasonMatcher::new(Duration::from_millis(500), tx), rx)
+    }
+
+    fn delta(value: i64) -> Decimal {
+        Decimal::new(value, 1)
+    }
+
+    fn recv(rx: &mut DriverEventReceiver) -> BalanceUpdate {
+        match rx.try_recv().expect("expected an emitted event") {
+            DriverEvent::BalanceUpdate(update) => update,
+            other => panic!("expected a balance update, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn event_type_attribution_is_immediate() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_balance_delta("USDT", delta(-125), Utc::now(), Some("funding_fee"), now);
+
+        let update = recv(&mut rx);
+        assert_eq!(update.reason, BalanceChangeReason::Funding);
+        assert_eq!(update.delta, delta(-125));
+    }
+
+    #[test]
+    fn bill_before_balance_matches_within_window() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_bill("USDT", delta(-125), "2", now);
+        matcher.on_balance_delta("USDT", delta(-125), Utc::now(), None, now);
+
+        assert_eq!(recv(&mut rx).reason, BalanceChangeReason::Trade);
+    }
+
+    #[test]
+    fn bill_after_balance_attributes_parked_event() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_balance_delta("USDT", delta(3000), Utc::now(), None, now);
+        assert!(rx.try_recv().is_err(), "event must be parked, not emitted");
+
+        matcher.on_bill("USDT", delta(3000), "1", now + Duration::from_millis(100));
+        assert_eq!(recv(&mut rx).reason, BalanceChangeReason::Transfer);
+    }
+
+    #[test]
+    fn unmatched_event_flushes_as_unknown_at_deadline() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_balance_delta("USDT", delta(-1), Utc::now(), None, now);
+        assert_eq!(matcher.next_deadline(), Some(now + Duration::from_millis(500)));
+
+        matcher.flush_expired(now + Duration::from_millis(499));
+        assert!(rx.try_recv().is_err(), "deadline has not passed yet");
+
+        matcher.flush_expired(now + Duration::from_millis(500));
+        assert_eq!(recv(&mut rx).reason, BalanceChangeReason::Unknown);
+    }
+
+    #[test]
+    fn stale_bills_do_not_attribute() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_bill("USDT", delta(-125), "2", now);
+        matcher.on_balance_delta(
+            "USDT",
+            delta(-125),
+            Utc::now(),
+            None,
+            now + MATCH_WINDOW + Duration::from_millis(1),
+        );
+
+        assert!(rx.try_recv().is_err(), "stale bill must not match");
+    }
+
+    #[test]
+    fn different_asset_or_amount_does_not_match() {
+        let (mut matcher, mut rx) = setup();
+        let now = Instant::now();
+
+        matcher.on_bill("BTC", delta(-125), "2", now);
+        matcher.on_bill("USDT", delta(-124), "2", now);
+        matcher.on_balance_delta("USDT", delta(-125), Utc::now(), None, now);
+
+        assert!(rx.try_recv().is_err());
+    }
+}
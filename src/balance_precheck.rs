@@ -0,0 +1,371 @@
+//! Local insufficient-balance pre-check on order placement.
+//!
+//! A large share of rejected orders are plain insufficient-balance cases:
+//! each one wastes a WS round trip and pollutes the error metrics. The
+//! pre-check compares an order's requirement against the consumer-fed
+//! balance cache and fails fast with
+//! [`crate::errors::DriverError::InsufficientFunds`] when the account is
+//! clearly short. It only ever rejects on fresh data — an absent or stale
+//! cache disables the check rather than producing false rejections — and a
+//! runtime bypass turns it off without a config change.
+//!
+//! The requirement model is deliberately coarse: spot buys need the quote
+//! notional, spot sells the base amount, and contract orders an initial
+//! margin estimated as notional over the configured leverage (settled in
+//! the quote currency, or the base currency for `-USD-` coin-margined
+//! contracts). Orders without a price (market orders) are not checked.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use rust_decimal::Decimal;
+
+use crate::collateral::RawCollateral;
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::Instrument;
+use crate::orders::{OkexOrderParams, Side};
+
+/// Settings of the balance pre-check; see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalancePrecheckConfig {
+    /// Multiplier on the computed requirement: `1.05` demands 5% headroom
+    /// so fees and price movement between cache and placement do not slip
+    /// a short order past the check.
+    pub safety_margin: Decimal,
+    /// Assumed account leverage for the initial-margin estimate on
+    /// contract orders (notional / leverage).
+    pub leverage: Decimal,
+    /// Oldest balance cache still trusted; an older (or absent) cache
+    /// disables the check instead of rejecting on outdated numbers.
+    pub max_age: std::time::Duration,
+}
+
+impl Default for BalancePrecheckConfig {
+    fn default() -> Self {
+        Self {
+            safety_margin: Decimal::ONE,
+            leverage: Decimal::ONE,
+            max_age: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+struct CachedBalances {
+    /// Free balance per asset, as [`RawCollateral::free`] reports it.
+    free: HashMap<String, Decimal>,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    /// The fetch only covered a subset of currencies (the account-channel
+    /// `ccy` filter); an absent asset is then unknown, not zero.
+    partial: bool,
+}
+
+/// The check plus the cache it reads; one lives on the driver when the
+/// pre-check is configured.
+pub struct BalancePrecheck {
+    config: BalancePrecheckConfig,
+    bypass: AtomicBool,
+    cache: Mutex<Option<CachedBalances>>,
+}
+
+impl BalancePrecheck {
+    pub fn new(config: BalancePrecheckConfig) -> Self {
+        Self {
+            config,
+            bypass: AtomicBool::new(false),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Replace the cached balances with a fresh fetch. The consumer feeds
+    /// this from the same loop that polls `/api/v5/account/balance`.
+    pub fn update(&self, balances: &[RawCollateral], fetched_at: chrono::DateTime<chrono::Utc>) {
+        self.store(balances, fetched_at, false);
+    }
+
+    /// Like [`Self::update`] for a fetch that only covered the filtered
+    /// currency set (see
+    /// [`crate::config::OkexConfig::filter_account_currencies`]). Assets
+    /// missing from a partial cache pass the check as unknown instead of
+    /// being rejected as zero balances.
+    pub fn update_partial(
+        &self,
+        balances: &[RawCollateral],
+        fetched_at: chrono::DateTime<chrono::Utc>,
+    ) {
+        self.store(balances, fetched_at, true);
+    }
+
+    fn store(
+        &self,
+        balances: &[RawCollateral],
+        fetched_at: chrono::DateTime<chrono::Utc>,
+        partial: bool,
+    ) {
+        let free = balances
+            .iter()
+            .map(|balance| (balance.asset.clone(), balance.free))
+            .collect();
+        *self.cache.lock().unwrap() = Some(CachedBalances {
+            free,
+            fetched_at,
+            partial,
+        });
+    }
+
+    /// Skip the check entirely until re-enabled; for operators who need an
+    /// order through right now.
+    pub fn set_bypass(&self, bypass: bool) {
+        self.bypass.store(bypass, Ordering::Relaxed);
+    }
+
+    /// Reject the order when the fresh cache clearly cannot cover it; lets
+    /// everything else through, including whenever the cache is absent,
+    /// stale, or the requirement cannot be estimated.
+    pub fn check(
+        &self,
+        params: &OkexOrderParams,
+        instrument: &Instrument,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> DriverResult<()> {
+        if self.bypass.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let Some((asset, required)) = self.requirement(params, instrument) else {
+            return Ok(());
+        };
+        let cache = self.cache.lock().unwrap();
+        let Some(cache) = cache.as_ref() else {
+            return Ok(());
+        };
+        let max_age = chrono::Duration::from_std(self.config.max_age).unwrap_or_default();
+        if now - cache.fetched_at > max_age {
+            return Ok(());
+        }
+        // A currency an account-wide fetch did not report is a zero
+        // balance; a ccy-filtered fetch may simply not have covered it, and
+        // only ever rejecting on data is the module's contract.
+        let available = match cache.free.get(&asset).copied() {
+            Some(available) => available,
+            None if cache.partial => return Ok(()),
+            None => Decimal::ZERO,
+        };
+        let required = required * self.config.safety_margin;
+        if available < required {
+            return Err(DriverError::InsufficientFunds {
+                asset,
+                required,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// The asset and amount the order needs up front; `None` when no
+    /// estimate is possible (no price, or unparseable params).
+    fn requirement(
+        &self,
+        params: &OkexOrderParams,
+        instrument: &Instrument,
+    ) -> Option<(String, Decimal)> {
+        let price: Decimal = params.px.as_deref()?.parse().ok()?;
+        let size: Decimal = params.sz.parse().ok()?;
+        let base = params.inst_id.split('-').next()?.to_string();
+        let quote = instrument.quote_currency()?.to_string();
+        if let Some(contract_value) = instrument.contract_value {
+            let margin = price * size * contract_value / self.config.leverage;
+            // Coin-margined contracts (`BTC-USD-SWAP`) settle in the base
+            // currency; linear ones in the quote.
+            let settle = if quote == "USD" { base } else { quote };
+            return Some((settle, margin));
+        }
+        match params.side {
+            Side::Buy => Some((quote, price * size)),
+            Side::Sell => Some((base, size)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{OrderType, TradeMode};
+
+    fn spot_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            contract_value: Some("0.01".parse().unwrap()),
+            ..spot_instrument()
+        }
+    }
+
+    fn limit_order(inst_id: &str, side: Side, price: &str, size: &str) -> OkexOrderParams {
+        OkexOrderParams {
+            inst_id: inst_id.to_string(),
+            td_mode: TradeMode::Cash,
+            side,
+            ord_type: OrderType::Limit,
+            px: Some(price.to_string()),
+            sz: size.to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: None,
+        }
+    }
+
+    fn usdt(free: &str) -> RawCollateral {
+        RawCollateral {
+            asset: "USDT".to_string(),
+            total: free.parse().unwrap(),
+            free: free.parse().unwrap(),
+            liability: Decimal::ZERO,
+        }
+    }
+
+    fn now() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+    }
+
+    #[test]
+    fn spot_buys_need_the_quote_notional_and_sells_the_base_amount() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig::default());
+        precheck.update(&[usdt("50")], now());
+
+        // Buying 0.01 BTC at 43000 needs 430 USDT; only 50 are free.
+        let buy = limit_order("BTC-USDT", Side::Buy, "43000", "0.01");
+        let err = precheck.check(&buy, &spot_instrument(), now()).unwrap_err();
+        match err {
+            DriverError::InsufficientFunds {
+                asset,
+                required,
+                available,
+            } => {
+                assert_eq!(asset, "USDT");
+                assert_eq!(required, "430".parse().unwrap());
+                assert_eq!(available, "50".parse().unwrap());
+            }
+            other => panic!("expected InsufficientFunds, got {other}"),
+        }
+
+        // Selling needs base; no BTC in the cache means zero available.
+        let sell = limit_order("BTC-USDT", Side::Sell, "43000", "0.01");
+        let err = precheck.check(&sell, &spot_instrument(), now()).unwrap_err();
+        assert!(
+            matches!(err, DriverError::InsufficientFunds { asset, .. } if asset == "BTC"),
+            "sells are checked against the base asset"
+        );
+    }
+
+    #[test]
+    fn contract_margin_is_notional_over_leverage() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig {
+            leverage: "10".parse().unwrap(),
+            ..BalancePrecheckConfig::default()
+        });
+        precheck.update(&[usdt("50")], now());
+
+        // 5 contracts of 0.01 BTC at 43000 = 2150 notional, 215 at 10x.
+        let order = limit_order("BTC-USDT-SWAP", Side::Buy, "43000", "5");
+        let err = precheck.check(&order, &swap_instrument(), now()).unwrap_err();
+        assert!(matches!(
+            err,
+            DriverError::InsufficientFunds { required, .. } if required == "215".parse().unwrap()
+        ));
+
+        precheck.update(&[usdt("215")], now());
+        precheck.check(&order, &swap_instrument(), now()).unwrap();
+    }
+
+    #[test]
+    fn the_safety_margin_inflates_the_requirement() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig {
+            safety_margin: "1.05".parse().unwrap(),
+            ..BalancePrecheckConfig::default()
+        });
+        // Exactly the notional free: the 5% headroom tips it over.
+        precheck.update(&[usdt("430")], now());
+        let buy = limit_order("BTC-USDT", Side::Buy, "43000", "0.01");
+        let err = precheck.check(&buy, &spot_instrument(), now()).unwrap_err();
+        assert!(matches!(
+            err,
+            DriverError::InsufficientFunds { required, .. } if required == "451.5".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn a_stale_or_absent_cache_disables_the_check() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig::default());
+        let buy = limit_order("BTC-USDT", Side::Buy, "43000", "1");
+
+        // No cache yet: everything passes.
+        precheck.check(&buy, &spot_instrument(), now()).unwrap();
+
+        // A cache past max_age must not reject either.
+        precheck.update(&[usdt("0")], now());
+        let later = now() + chrono::Duration::seconds(31);
+        precheck.check(&buy, &spot_instrument(), later).unwrap();
+
+        // The same cache within max_age does.
+        precheck.check(&buy, &spot_instrument(), now()).unwrap_err();
+    }
+
+    #[test]
+    fn a_partial_cache_treats_missing_assets_as_unknown_not_zero() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig::default());
+        precheck.update_partial(&[usdt("50")], now());
+
+        // BTC is not in the filtered fetch: unknown, so the sell passes.
+        let sell = limit_order("BTC-USDT", Side::Sell, "43000", "0.01");
+        precheck.check(&sell, &spot_instrument(), now()).unwrap();
+
+        // Covered assets are still checked as usual.
+        let buy = limit_order("BTC-USDT", Side::Buy, "43000", "0.01");
+        let err = precheck.check(&buy, &spot_instrument(), now()).unwrap_err();
+        assert!(
+            matches!(err, DriverError::InsufficientFunds { asset, .. } if asset == "USDT"),
+            "a currency the partial fetch did cover rejects normally"
+        );
+
+        // An account-wide update restores zero-for-missing.
+        precheck.update(&[usdt("50")], now());
+        precheck.check(&sell, &spot_instrument(), now()).unwrap_err();
+    }
+
+    #[test]
+    fn the_bypass_lets_short_orders_through() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig::default());
+        precheck.update(&[usdt("0")], now());
+        let buy = limit_order("BTC-USDT", Side::Buy, "43000", "1");
+
+        precheck.set_bypass(true);
+        precheck.check(&buy, &spot_instrument(), now()).unwrap();
+        precheck.set_bypass(false);
+        precheck.check(&buy, &spot_instrument(), now()).unwrap_err();
+    }
+
+    #[test]
+    fn unpriced_orders_are_not_checked() {
+        let precheck = BalancePrecheck::new(BalancePrecheckConfig::default());
+        precheck.update(&[usdt("0")], now());
+        let market = OkexOrderParams {
+            px: None,
+            ord_type: OrderType::Market,
+            ..limit_order("BTC-USDT", Side::Buy, "0", "1")
+        };
+        precheck.check(&market, &spot_instrument(), now()).unwrap();
+    }
+}
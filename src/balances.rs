@@ -0,0 +1,117 @@
+//! Account-wide balance assembly across unified and classic accounts.
+//!
+//! On a unified trading account every asset class settles into the one
+//! book `/api/v5/account/balance` reads, so a single call is the whole
+//! picture. A classic simple account (acctLv 1) still keeps the split
+//! books of the pre-unified era — its funding account never shows up in
+//! the trading-account call, which is how a legacy account can report
+//! empty balances for money it demonstrably holds. The merge here keeps
+//! one entry per (asset, book) and tags each with its [`BalanceSource`],
+//! so downstream accounting can tell spendable trading balance from
+//! funding-account parking without a second fetch.
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::{OkexAssetBalance, OkexBalance};
+
+/// Which book a balance entry was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BalanceSource {
+    /// The (unified) trading account, `/api/v5/account/balance`.
+    Trading,
+    /// The funding account, `/api/v5/asset/balances`; only fetched for
+    /// classic accounts.
+    Funding,
+}
+
+/// One asset's balance in one book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBalance {
+    pub asset: String,
+    pub total: Decimal,
+    pub free: Decimal,
+    pub source: BalanceSource,
+}
+
+/// Merge the trading book with an optional funding book into one tagged
+/// list, sorted by asset with the trading entry first. An asset held in
+/// both books yields two entries — summing them would hide that the
+/// funding share is not tradeable.
+pub fn merge_balances(
+    trading: &OkexBalance,
+    funding: Option<&[OkexAssetBalance]>,
+) -> Vec<RawBalance> {
+    let mut balances: Vec<RawBalance> = trading
+        .details
+        .iter()
+        .map(|detail| RawBalance {
+            asset: detail.ccy.clone(),
+            total: detail.cash_balance,
+            free: detail.available_balance,
+            source: BalanceSource::Trading,
+        })
+        .collect();
+    if let Some(funding) = funding {
+        balances.extend(funding.iter().map(|balance| RawBalance {
+            asset: balance.ccy.clone(),
+            total: balance.balance,
+            free: balance.available_balance,
+            source: BalanceSource::Funding,
+        }));
+    }
+    balances.sort_by(|a, b| (&a.asset, a.source).cmp(&(&b.asset, b.source)));
+    balances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_asset_in_both_books_keeps_two_tagged_entries() {
+        let trading: OkexBalance = serde_json::from_str(
+            r#"{"details":[
+                {"ccy":"USDT","cashBal":"1000","availBal":"800"},
+                {"ccy":"BTC","cashBal":"0.5","availBal":"0.5"}
+            ]}"#,
+        )
+        .unwrap();
+        let funding: Vec<OkexAssetBalance> = serde_json::from_str(
+            r#"[
+                {"ccy":"USDT","bal":"250","availBal":"250","frozenBal":"0"},
+                {"ccy":"ETH","bal":"3","availBal":"2","frozenBal":"1"}
+            ]"#,
+        )
+        .unwrap();
+
+        let merged = merge_balances(&trading, Some(&funding));
+
+        let entries: Vec<(&str, BalanceSource)> = merged
+            .iter()
+            .map(|b| (b.asset.as_str(), b.source))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("BTC", BalanceSource::Trading),
+                ("ETH", BalanceSource::Funding),
+                ("USDT", BalanceSource::Trading),
+                ("USDT", BalanceSource::Funding),
+            ]
+        );
+        assert_eq!(merged[3].free, "250".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn a_unified_fetch_merges_nothing() {
+        let trading: OkexBalance = serde_json::from_str(
+            r#"{"details":[{"ccy":"USDT","cashBal":"1000","availBal":"800"}]}"#,
+        )
+        .unwrap();
+
+        let merged = merge_balances(&trading, None);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].source, BalanceSource::Trading);
+    }
+}
@@ -0,0 +1,57 @@
+//! Incremental cursor over the account bills stream.
+//!
+//! Reporting used to re-download overlapping bill history every polling
+//! cycle. [`BillsCursor`] remembers where the last poll stopped so
+//! [`crate::rest::OkexClient::poll_new_bills`] fetches only what is new,
+//! re-reading one page of overlap and deduping by `billId` to absorb clock
+//! skew between us and the exchange.
+
+use std::collections::VecDeque;
+
+use crate::errors::DriverResult;
+
+/// How many recently delivered bill ids the cursor remembers: one full
+/// page, matching the overlap re-fetched on every poll.
+pub(crate) const SEEN_CAPACITY: usize = 100;
+
+/// Position of the last delivered bill plus one page of recently delivered
+/// ids for overlap dedup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BillsCursor {
+    /// Newest delivered `billId`.
+    pub last_bill_id: Option<String>,
+    /// Newest delivered `ts`, milliseconds.
+    pub last_timestamp: Option<u64>,
+    /// Recently delivered ids, oldest first, bounded at [`SEEN_CAPACITY`].
+    pub seen: VecDeque<String>,
+}
+
+impl BillsCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn is_seen(&self, bill_id: &str) -> bool {
+        self.seen.iter().any(|id| id == bill_id)
+    }
+
+    pub(crate) fn mark_delivered(&mut self, bill_id: &str, timestamp: u64) {
+        self.last_bill_id = Some(bill_id.to_string());
+        self.last_timestamp = Some(
+            self.last_timestamp
+                .map_or(timestamp, |last| last.max(timestamp)),
+        );
+        self.seen.push_back(bill_id.to_string());
+        while self.seen.len() > SEEN_CAPACITY {
+            self.seen.pop_front();
+        }
+    }
+}
+
+/// Persistence hook so the cursor survives restarts without replaying
+/// history: load once at startup, save after every poll that delivered
+/// bills.
+pub trait BillsCursorStore {
+    fn load(&self) -> DriverResult<Option<BillsCursor>>;
+    fn save(&self, cursor: &BillsCursor) -> DriverResult<()>;
+}
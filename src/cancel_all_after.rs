@@ -0,0 +1,177 @@
+//! Dead-man's-switch management over `/api/v5/trade/cancel-all-after`.
+//!
+//! Once armed, the exchange cancels every open order on the account unless
+//! the timer is refreshed — so a driver crash flattens quotes within the
+//! configured timeout instead of leaving them resting. The switch is per
+//! account and covers all instruments: multiple driver instances on one key
+//! re-arm each other's timer (the most recent arm wins), and a single
+//! disarm drops the protection for all of them. Run one switch owner per
+//! key.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::rest::OkexClient;
+
+/// Operator-facing view of the switch for health reporting.
+#[derive(Debug, Clone)]
+pub struct CancelAllAfterStatus {
+    /// Whether the last (re-)arm call succeeded.
+    pub armed: bool,
+    /// The exchange-side timeout the switch is armed with.
+    pub timeout: Duration,
+    /// When the switch was last successfully refreshed.
+    pub last_refresh: Option<Instant>,
+}
+
+/// Handle to the background refresher. Dropping the guard (or calling
+/// [`Self::disarm`]) disarms the switch: a deliberate shutdown should not
+/// cancel quotes that a successor instance is about to adopt.
+pub struct CancelAllAfterGuard {
+    status: Arc<Mutex<CancelAllAfterStatus>>,
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl CancelAllAfterGuard {
+    /// Arm the switch with `timeout` and keep re-arming at half that
+    /// interval, so a single missed refresh never lets the timer fire.
+    pub fn spawn(client: Arc<OkexClient>, timeout: Duration) -> Self {
+        let status = Arc::new(Mutex::new(CancelAllAfterStatus {
+            armed: false,
+            timeout,
+            last_refresh: None,
+        }));
+        let shared = Arc::clone(&status);
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let timeout_secs = timeout.as_secs().max(1);
+            loop {
+                match client.rest_set_cancel_all_after(timeout_secs).await {
+                    Ok(_) => {
+                        let mut state = shared.lock().unwrap();
+                        state.armed = true;
+                        state.last_refresh = Some(Instant::now());
+                    }
+                    Err(err) => {
+                        shared.lock().unwrap().armed = false;
+                        log::warn!("cancel-all-after re-arm failed: {err}");
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(timeout / 2) => {}
+                    _ = &mut stop_rx => {
+                        if let Err(err) = client.rest_set_cancel_all_after(0).await {
+                            log::warn!("cancel-all-after disarm failed: {err}");
+                        }
+                        shared.lock().unwrap().armed = false;
+                        break;
+                    }
+                }
+            }
+        });
+        Self {
+            status,
+            stop: Some(stop_tx),
+            task,
+        }
+    }
+
+    /// Spawn the refresher when the config asks for one.
+    pub fn from_config(client: &Arc<OkexClient>) -> Option<Self> {
+        client
+            .config()
+            .cancel_all_after
+            .map(|timeout| Self::spawn(Arc::clone(client), timeout))
+    }
+
+    pub fn status(&self) -> CancelAllAfterStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Disarm the switch and wait for the refresher to finish.
+    pub async fn disarm(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.task).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    const ARM_RESPONSE: &str =
+        r#"{"code":"0","msg":"","data":[{"triggerTime":"1700000010000","ts":"1700000000000"}]}"#;
+
+    fn client(transport: &Arc<MockTransport>) -> Arc<OkexClient> {
+        Arc::new(OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        ))
+    }
+
+    #[tokio::test]
+    async fn arming_serializes_the_timeout_in_seconds() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ARM_RESPONSE);
+        let client = client(&transport);
+
+        let result = client.rest_set_cancel_all_after(30).await.unwrap();
+        assert_eq!(result.trigger_time, "1700000010000");
+
+        let request = &transport.requests()[0];
+        assert!(request.url.ends_with("/api/v5/trade/cancel-all-after"));
+        assert_eq!(request.body.as_deref(), Some(r#"{"timeOut":"30"}"#));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refresher_rearms_at_half_the_timeout() {
+        let transport = Arc::new(MockTransport::new());
+        for _ in 0..4 {
+            transport.push_json(ARM_RESPONSE);
+        }
+        let guard = CancelAllAfterGuard::spawn(client(&transport), Duration::from_secs(10));
+
+        tokio::time::sleep(Duration::from_secs(11)).await;
+
+        // Arms at t=0, t=5, and t=10.
+        assert_eq!(transport.requests().len(), 3);
+        assert!(transport
+            .requests()
+            .iter()
+            .all(|r| r.body.as_deref() == Some(r#"{"timeOut":"10"}"#)));
+        let status = guard.status();
+        assert!(status.armed);
+        assert!(status.last_refresh.is_some());
+
+        guard.disarm().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disarm_on_shutdown_sends_a_zero_timeout() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ARM_RESPONSE);
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"triggerTime":"0","ts":"1700000000000"}]}"#,
+        );
+        let guard = CancelAllAfterGuard::spawn(client(&transport), Duration::from_secs(10));
+        tokio::task::yield_now().await;
+
+        guard.disarm().await;
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests.last().unwrap().body.as_deref(),
+            Some(r#"{"timeOut":"0"}"#)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Driver capability discovery.
+//!
+//! Consumers keep asking "does this driver support X" before relying on a
+//! behavior, and hardcoded answers drift as features land. Instead, a
+//! [`DriverCapabilities`] snapshot is computed once at driver construction
+//! from the config, refined with the exchange-side account config when
+//! preflight fetches it, and consulted by
+//! [`OkexDriver::supports_feature`](crate::driver::OkexDriver::supports_feature).
+//! The full struct is exposed through
+//! [`OkexDriver::capabilities`](crate::driver::OkexDriver::capabilities)
+//! so startup logs can record the whole matrix in one line.
+
+use crate::api_structs::OkexAccountConfig;
+use crate::config::OkexConfig;
+
+/// A driver behavior a consumer may ask about before relying on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverFeature {
+    /// `ordType: post_only` placement.
+    PostOnly,
+    /// IOC-style placement (`optimal_limit_ioc`; contracts only, which
+    /// per-order validation enforces).
+    Ioc,
+    /// Multi-order placement in one call.
+    BatchOpen,
+    /// Multi-order cancellation in one call.
+    BatchCancel,
+    /// Exchange-hosted algo orders (trailing stops, iceberg, TWAP).
+    AlgoOrders,
+    /// `cross`/`isolated` trade modes.
+    MarginOrders,
+    /// The exchange-side cancel-all-after dead-man's switch.
+    CancelAllAfter,
+    /// Synthetic fills against fed book snapshots.
+    FillSimulation,
+    /// Funding-account conversions: dust sweeps and OKX Convert.
+    Conversions,
+    /// Whether orders reach the exchange at all.
+    LiveTrading,
+}
+
+/// One flag per [`DriverFeature`], answering what this driver instance can
+/// do. Constructed from the config; the margin flag starts from what the
+/// configured trade mode assumes and is replaced with the exchange's
+/// answer by [`Self::apply_account_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverCapabilities {
+    pub post_only: bool,
+    pub ioc: bool,
+    pub batch_open: bool,
+    pub batch_cancel: bool,
+    /// Dry-run has no simulation path for algo orders, so they are off
+    /// there; live accounts of every level can place them.
+    pub algo_orders: bool,
+    /// Until the account config is seen this trusts the configured trade
+    /// mode; preflight overwrites it with the actual account level.
+    pub margin_orders: bool,
+    pub cancel_all_after: bool,
+    pub fill_simulation: bool,
+    /// Demo trading serves no `/api/v5/asset/*` endpoints, so conversions
+    /// only exist on production accounts.
+    pub conversions: bool,
+    pub live_trading: bool,
+}
+
+impl DriverCapabilities {
+    /// The capability matrix the config alone implies; see
+    /// [`Self::apply_account_config`] for the account-state refinement.
+    pub fn from_config(config: &OkexConfig) -> Self {
+        Self {
+            post_only: true,
+            ioc: true,
+            batch_open: true,
+            batch_cancel: true,
+            algo_orders: !config.dry_run,
+            margin_orders: config.trade_mode != crate::orders::TradeMode::Cash,
+            cancel_all_after: config.cancel_all_after.is_some() && !config.dry_run,
+            fill_simulation: config.dry_run,
+            conversions: !config.use_testnet,
+            live_trading: !config.dry_run,
+        }
+    }
+
+    /// Replace the config-derived assumptions with exchange truth: margin
+    /// trade modes need at least a single-currency margin account.
+    pub fn apply_account_config(&mut self, account: &OkexAccountConfig) {
+        self.margin_orders = account.is_margin_capable();
+    }
+
+    pub fn supports(&self, feature: DriverFeature) -> bool {
+        match feature {
+            DriverFeature::PostOnly => self.post_only,
+            DriverFeature::Ioc => self.ioc,
+            DriverFeature::BatchOpen => self.batch_open,
+            DriverFeature::BatchCancel => self.batch_cancel,
+            DriverFeature::AlgoOrders => self.algo_orders,
+            DriverFeature::MarginOrders => self.margin_orders,
+            DriverFeature::CancelAllAfter => self.cancel_all_after,
+            DriverFeature::FillSimulation => self.fill_simulation,
+            DriverFeature::Conversions => self.conversions,
+            DriverFeature::LiveTrading => self.live_trading,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(acct_lv: &str) -> OkexAccountConfig {
+        serde_json::from_str(&format!(
+            r#"{{"posMode":"net_mode","acctLv":"{acct_lv}"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn a_default_spot_config_trades_live_without_margin() {
+        let caps = DriverCapabilities::from_config(&OkexConfig::default());
+        assert!(caps.supports(DriverFeature::PostOnly));
+        assert!(caps.supports(DriverFeature::Ioc));
+        assert!(caps.supports(DriverFeature::BatchOpen));
+        assert!(caps.supports(DriverFeature::BatchCancel));
+        assert!(caps.supports(DriverFeature::AlgoOrders));
+        assert!(caps.supports(DriverFeature::Conversions));
+        assert!(caps.supports(DriverFeature::LiveTrading));
+        assert!(!caps.supports(DriverFeature::MarginOrders));
+        assert!(!caps.supports(DriverFeature::CancelAllAfter));
+        assert!(!caps.supports(DriverFeature::FillSimulation));
+    }
+
+    #[test]
+    fn a_margin_config_assumes_margin_until_the_account_disagrees() {
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let mut caps = DriverCapabilities::from_config(&config);
+        assert!(caps.supports(DriverFeature::MarginOrders));
+
+        // A simple account cannot honour the configured cross mode.
+        caps.apply_account_config(&account("1"));
+        assert!(!caps.supports(DriverFeature::MarginOrders));
+        caps.apply_account_config(&account("3"));
+        assert!(caps.supports(DriverFeature::MarginOrders));
+    }
+
+    #[test]
+    fn dry_run_swaps_live_trading_for_simulation() {
+        let config = OkexConfig {
+            dry_run: true,
+            ..OkexConfig::default()
+        };
+        let caps = DriverCapabilities::from_config(&config);
+        assert!(!caps.supports(DriverFeature::LiveTrading));
+        assert!(caps.supports(DriverFeature::FillSimulation));
+        // No simulation path exists for exchange-hosted algos, and a
+        // dead-man's switch would cancel orders the dry run never placed.
+        assert!(!caps.supports(DriverFeature::AlgoOrders));
+        assert!(!caps.supports(DriverFeature::CancelAllAfter));
+        // Placement-shaped features stay on: dry-run accepts the calls.
+        assert!(caps.supports(DriverFeature::PostOnly));
+        assert!(caps.supports(DriverFeature::BatchOpen));
+    }
+
+    #[test]
+    fn demo_trading_loses_the_asset_endpoints() {
+        let config = OkexConfig {
+            use_testnet: true,
+            ..OkexConfig::default()
+        };
+        let caps = DriverCapabilities::from_config(&config);
+        assert!(!caps.supports(DriverFeature::Conversions));
+        assert!(caps.supports(DriverFeature::LiveTrading));
+    }
+
+    #[test]
+    fn the_armed_dead_mans_switch_is_reported() {
+        let config = OkexConfig {
+            cancel_all_after: Some(std::time::Duration::from_secs(30)),
+            ..OkexConfig::default()
+        };
+        assert!(DriverCapabilities::from_config(&config)
+            .supports(DriverFeature::CancelAllAfter));
+    }
+}
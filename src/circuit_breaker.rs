@@ -0,0 +1,341 @@
+//! Per-endpoint-category circuit breaking for exchange incidents.
+//!
+//! During an OKX incident every endpoint answers system-busy codes, and a
+//! retry layer that keeps hammering both worsens the outage and burns
+//! rate budget. Each endpoint category (the rate-limiter's `trade`,
+//! `account`, ... keys) gets a breaker: enough system-busy responses
+//! inside a short window open the circuit, calls then fail fast with
+//! [`crate::errors::DriverError::ExchangeUnavailable`] for a cooldown,
+//! and after the cooldown the circuit half-opens — traffic is admitted
+//! again and the first settled outcome closes or re-opens it. Every call
+//! during half-open is admitted rather than gating on a single probe,
+//! since a probe that dies at the transport layer would otherwise wedge
+//! the circuit open.
+//!
+//! Methods take `now` explicitly so tests drive the clock; the REST
+//! client passes `Instant::now()`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// OKX business codes meaning the exchange itself is unwell, as opposed
+/// to anything about the request: `50013` system busy, `50026` system
+/// error.
+pub const SYSTEM_BUSY_CODES: [&str; 2] = ["50013", "50026"];
+
+/// Breaker tunables, shared by every category.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// System-busy responses within `window` that open the circuit.
+    pub failure_threshold: u32,
+    /// Window the failures must fall into; responses older than this
+    /// stop counting.
+    pub window: Duration,
+    /// How long an open circuit fails fast before half-opening.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Observable state of one category's circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum CircuitState {
+    /// Normal traffic.
+    Closed,
+    /// Failing fast; no traffic reaches the exchange.
+    Open,
+    /// Cooldown elapsed; traffic flows again and the first settled
+    /// outcome decides between [`Closed`](Self::Closed) and
+    /// [`Open`](Self::Open).
+    HalfOpen,
+}
+
+/// Decision for one call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// The circuit is closed; proceed normally.
+    Allowed,
+    /// The circuit is half-open; this call doubles as the probe.
+    Probe,
+    /// The circuit is open; fail fast without touching the wire.
+    Rejected {
+        /// Until the cooldown elapses and the circuit half-opens.
+        retry_in: Duration,
+    },
+}
+
+/// One state change, for the event stream and logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub category: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+}
+
+enum Inner {
+    Closed {
+        failures: u32,
+        window_start: Option<Instant>,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen,
+}
+
+impl Inner {
+    fn state(&self) -> CircuitState {
+        match self {
+            Inner::Closed { .. } => CircuitState::Closed,
+            Inner::Open { .. } => CircuitState::Open,
+            Inner::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// The per-category breakers behind one REST client.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    categories: Mutex<HashMap<String, Inner>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            categories: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gate one call on `category`'s circuit. An open circuit whose
+    /// cooldown has elapsed half-opens here, which is the only transition
+    /// this method makes.
+    pub fn admit(&self, category: &str, now: Instant) -> (Admission, Option<Transition>) {
+        let mut categories = self.categories.lock().unwrap();
+        let Some(inner) = categories.get_mut(category) else {
+            return (Admission::Allowed, None);
+        };
+        match inner {
+            Inner::Closed { .. } => (Admission::Allowed, None),
+            Inner::HalfOpen => (Admission::Probe, None),
+            Inner::Open { until } => {
+                if now < *until {
+                    (
+                        Admission::Rejected {
+                            retry_in: *until - now,
+                        },
+                        None,
+                    )
+                } else {
+                    *inner = Inner::HalfOpen;
+                    (
+                        Admission::Probe,
+                        Some(Transition {
+                            category: category.to_string(),
+                            from: CircuitState::Open,
+                            to: CircuitState::HalfOpen,
+                        }),
+                    )
+                }
+            }
+        }
+    }
+
+    /// Record one system-busy response on `category`: counts toward the
+    /// threshold while closed, re-opens a half-open circuit immediately.
+    pub fn record_busy(&self, category: &str, now: Instant) -> Option<Transition> {
+        let mut categories = self.categories.lock().unwrap();
+        let inner = categories
+            .entry(category.to_string())
+            .or_insert(Inner::Closed {
+                failures: 0,
+                window_start: None,
+            });
+        let from = inner.state();
+        match inner {
+            Inner::Closed {
+                failures,
+                window_start,
+            } => {
+                let in_window = window_start
+                    .is_some_and(|start| now.duration_since(start) <= self.config.window);
+                if !in_window {
+                    *window_start = Some(now);
+                    *failures = 0;
+                }
+                *failures += 1;
+                if *failures < self.config.failure_threshold {
+                    return None;
+                }
+            }
+            // Responses from calls admitted before the circuit opened may
+            // still trickle in; the cooldown already covers them.
+            Inner::Open { .. } => return None,
+            Inner::HalfOpen => {}
+        }
+        *inner = Inner::Open {
+            until: now + self.config.cooldown,
+        };
+        Some(Transition {
+            category: category.to_string(),
+            from,
+            to: CircuitState::Open,
+        })
+    }
+
+    /// Record one healthy response on `category`: resets the failure
+    /// count, and closes a half-open circuit.
+    pub fn record_ok(&self, category: &str) -> Option<Transition> {
+        let mut categories = self.categories.lock().unwrap();
+        let inner = categories.get_mut(category)?;
+        match inner {
+            Inner::Closed { failures, .. } => {
+                *failures = 0;
+                None
+            }
+            // A late response from before the circuit opened proves
+            // nothing about the incident; only the half-open probe closes.
+            Inner::Open { .. } => None,
+            Inner::HalfOpen => {
+                *inner = Inner::Closed {
+                    failures: 0,
+                    window_start: None,
+                };
+                Some(Transition {
+                    category: category.to_string(),
+                    from: CircuitState::HalfOpen,
+                    to: CircuitState::Closed,
+                })
+            }
+        }
+    }
+
+    /// Current state per category that has seen any traffic worth
+    /// tracking, for the health snapshot.
+    pub fn states(&self) -> HashMap<String, CircuitState> {
+        self.categories
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(category, inner)| (category.clone(), inner.state()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        })
+    }
+
+    #[test]
+    fn the_threshold_within_the_window_opens_the_circuit() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        assert_eq!(breaker.record_busy("trade", start), None);
+        assert_eq!(breaker.record_busy("trade", start + Duration::from_secs(1)), None);
+        let transition = breaker
+            .record_busy("trade", start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(transition.from, CircuitState::Closed);
+        assert_eq!(transition.to, CircuitState::Open);
+
+        let (admission, _) = breaker.admit("trade", start + Duration::from_secs(3));
+        assert_eq!(
+            admission,
+            Admission::Rejected {
+                retry_in: Duration::from_secs(29)
+            }
+        );
+        // Other categories are unaffected.
+        assert_eq!(breaker.admit("account", start).0, Admission::Allowed);
+    }
+
+    #[test]
+    fn stale_failures_age_out_of_the_window() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        breaker.record_busy("trade", start);
+        breaker.record_busy("trade", start + Duration::from_secs(1));
+        // The window restarts here, so this is failure one of three.
+        assert_eq!(
+            breaker.record_busy("trade", start + Duration::from_secs(20)),
+            None
+        );
+        assert_eq!(
+            breaker.record_busy("trade", start + Duration::from_secs(21)),
+            None
+        );
+        assert_eq!(
+            *breaker.states().get("trade").unwrap(),
+            CircuitState::Closed
+        );
+    }
+
+    #[test]
+    fn the_cooldown_half_opens_and_the_probe_outcome_decides() {
+        let breaker = breaker();
+        let start = Instant::now();
+        for i in 0..3 {
+            breaker.record_busy("trade", start + Duration::from_secs(i));
+        }
+
+        // Cooldown elapsed: the next call is the probe.
+        let probe_at = start + Duration::from_secs(40);
+        let (admission, transition) = breaker.admit("trade", probe_at);
+        assert_eq!(admission, Admission::Probe);
+        assert_eq!(transition.unwrap().to, CircuitState::HalfOpen);
+
+        // A busy probe re-opens for a full cooldown...
+        let transition = breaker.record_busy("trade", probe_at).unwrap();
+        assert_eq!(transition.from, CircuitState::HalfOpen);
+        assert_eq!(transition.to, CircuitState::Open);
+        assert!(matches!(
+            breaker.admit("trade", probe_at + Duration::from_secs(1)).0,
+            Admission::Rejected { .. }
+        ));
+
+        // ...and a healthy one closes the circuit.
+        let retry_at = probe_at + Duration::from_secs(40);
+        assert_eq!(breaker.admit("trade", retry_at).0, Admission::Probe);
+        let transition = breaker.record_ok("trade").unwrap();
+        assert_eq!(transition.to, CircuitState::Closed);
+        assert_eq!(breaker.admit("trade", retry_at).0, Admission::Allowed);
+    }
+
+    #[test]
+    fn late_responses_from_before_the_open_change_nothing() {
+        let breaker = breaker();
+        let start = Instant::now();
+        for i in 0..3 {
+            breaker.record_busy("trade", start + Duration::from_secs(i));
+        }
+
+        assert_eq!(breaker.record_ok("trade"), None);
+        assert_eq!(breaker.record_busy("trade", start + Duration::from_secs(3)), None);
+        assert_eq!(*breaker.states().get("trade").unwrap(), CircuitState::Open);
+    }
+}
@@ -0,0 +1,243 @@
+//! Client order id generation with embedded strategy metadata.
+//!
+//! Generated `clOrdId`s encode the request's strategy/level attribution so
+//! post-trade analysis can attribute fills without a lookup table. The
+//! layout, inside OKX's 32-char alphanumeric constraint, is
+//!
+//! ```text
+//! fmt <LL> <strategy> <LL> <level> <sequence>
+//! ```
+//!
+//! where each `LL` is a two-digit length header and the sequence is a
+//! fixed-width base-36 counter. When the metadata does not fit, it is
+//! truncated — the sequence never is, so uniqueness survives any metadata.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::orders::OrderRequest;
+
+/// Hard cap OKX enforces on `clOrdId`.
+pub const CLIENT_ID_MAX_LEN: usize = 32;
+/// Marks ids generated by this driver; foreign ids fail [`parse_client_id`].
+const CLIENT_ID_PREFIX: &str = "fmt";
+/// Width of the two length headers.
+const LENGTH_WIDTH: usize = 2;
+/// Fixed width of the base-36 sequence suffix.
+const SEQUENCE_WIDTH: usize = 8;
+/// Values the sequence suffix can represent (36^8).
+const SEQUENCE_SPACE: u64 = 2_821_109_907_456;
+
+/// A `clOrdId` as this driver handles it, ours or foreign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOrderId(String);
+
+impl ClientOrderId {
+    /// Wrap an id echoed back by the exchange; `None` for the empty string
+    /// OKX uses when no client id was set.
+    pub fn from_exchange(id: &str) -> Option<Self> {
+        (!id.is_empty()).then(|| Self(id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ClientOrderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Strategy attribution the caller wants encoded into generated ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderMetadata {
+    /// Strategy tag, e.g. `grid`.
+    pub strategy: String,
+    /// Level id within the strategy, e.g. `L17`.
+    pub level_id: String,
+}
+
+/// Decoded contents of a generated client order id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdParts {
+    /// Strategy tag as encoded (sanitized, possibly truncated); empty when
+    /// the order carried no metadata.
+    pub strategy: String,
+    /// Level id as encoded; empty when the order carried no metadata.
+    pub level_id: String,
+    /// Sequence counter value.
+    pub sequence: u64,
+}
+
+/// Generates unique client order ids, one instance per driver.
+///
+/// The counter is seeded from the wall clock at construction, so ids stay
+/// unique across process restarts as long as restarts are more than a
+/// millisecond apart.
+pub struct ClientIdGenerator {
+    counter: AtomicU64,
+}
+
+impl ClientIdGenerator {
+    pub fn new() -> Self {
+        Self {
+            counter: AtomicU64::new(chrono::Utc::now().timestamp_millis() as u64),
+        }
+    }
+
+    /// Generate the `clOrdId` for one request, embedding its metadata.
+    ///
+    /// Metadata is reduced to OKX's alphanumeric charset and truncated to
+    /// fit — strategy first, level into whatever room remains — while the
+    /// sequence suffix always keeps its full width.
+    pub fn generate_client_id(&self, request: &OrderRequest) -> ClientOrderId {
+        let sequence = self.counter.fetch_add(1, Ordering::Relaxed) % SEQUENCE_SPACE;
+        let budget =
+            CLIENT_ID_MAX_LEN - CLIENT_ID_PREFIX.len() - 2 * LENGTH_WIDTH - SEQUENCE_WIDTH;
+        let (strategy, level_id) = match &request.metadata {
+            Some(metadata) => {
+                let mut strategy = sanitize(&metadata.strategy);
+                strategy.truncate(budget);
+                let mut level_id = sanitize(&metadata.level_id);
+                level_id.truncate(budget - strategy.len());
+                (strategy, level_id)
+            }
+            None => (String::new(), String::new()),
+        };
+        ClientOrderId(format!(
+            "{CLIENT_ID_PREFIX}{:02}{strategy}{:02}{level_id}{}",
+            strategy.len(),
+            level_id.len(),
+            base36(sequence)
+        ))
+    }
+}
+
+impl Default for ClientIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decode a generated id back into its parts; `None` for ids this driver
+/// did not generate (wrong prefix, drifted lengths, foreign charset).
+pub fn parse_client_id(id: &ClientOrderId) -> Option<ClientIdParts> {
+    let rest = id.as_str().strip_prefix(CLIENT_ID_PREFIX)?;
+    let (strategy, rest) = take_field(rest)?;
+    let (level_id, rest) = take_field(rest)?;
+    if rest.len() != SEQUENCE_WIDTH {
+        return None;
+    }
+    let sequence = u64::from_str_radix(rest, 36).ok()?;
+    Some(ClientIdParts {
+        strategy: strategy.to_string(),
+        level_id: level_id.to_string(),
+        sequence,
+    })
+}
+
+/// Read one two-digit length header and the field it describes.
+fn take_field(input: &str) -> Option<(&str, &str)> {
+    let length: usize = input.get(..LENGTH_WIDTH)?.parse().ok()?;
+    let rest = input.get(LENGTH_WIDTH..)?;
+    Some((rest.get(..length)?, rest.get(length..)?))
+}
+
+/// Reduce metadata to the charset OKX accepts in `clOrdId`.
+fn sanitize(raw: &str) -> String {
+    raw.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+/// Fixed-width lowercase base-36, zero-padded.
+fn base36(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut out = [b'0'; SEQUENCE_WIDTH];
+    for slot in out.iter_mut().rev() {
+        *slot = DIGITS[(value % 36) as usize];
+        value /= 36;
+    }
+    String::from_utf8(out.to_vec()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{OrderType, Side, SizeDenomination};
+
+    fn request(metadata: Option<OrderMetadata>) -> OrderRequest {
+        OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some("43250.1".parse().unwrap()),
+            amount: "0.5".parse().unwrap(),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata,
+        }
+    }
+
+    #[test]
+    fn round_trip_preserves_strategy_and_level() {
+        let generator = ClientIdGenerator::new();
+        let id = generator.generate_client_id(&request(Some(OrderMetadata {
+            strategy: "grid".to_string(),
+            level_id: "L17".to_string(),
+        })));
+
+        assert!(id.as_str().len() <= CLIENT_ID_MAX_LEN);
+        assert!(id.as_str().chars().all(|c| c.is_ascii_alphanumeric()));
+        let parts = parse_client_id(&id).unwrap();
+        assert_eq!(parts.strategy, "grid");
+        assert_eq!(parts.level_id, "L17");
+    }
+
+    #[test]
+    fn requests_without_metadata_still_get_parseable_ids() {
+        let generator = ClientIdGenerator::new();
+        let first = generator.generate_client_id(&request(None));
+        let second = generator.generate_client_id(&request(None));
+
+        assert_ne!(first, second);
+        let parts = parse_client_id(&first).unwrap();
+        assert!(parts.strategy.is_empty());
+        assert!(parts.level_id.is_empty());
+        assert_eq!(
+            parse_client_id(&second).unwrap().sequence,
+            parts.sequence + 1
+        );
+    }
+
+    #[test]
+    fn oversized_metadata_is_truncated_but_ids_stay_unique() {
+        let generator = ClientIdGenerator::new();
+        let metadata = OrderMetadata {
+            strategy: "a-very-long-strategy-name-indeed".to_string(),
+            level_id: "level-9000-of-the-deep-grid".to_string(),
+        };
+        let first = generator.generate_client_id(&request(Some(metadata.clone())));
+        let second = generator.generate_client_id(&request(Some(metadata)));
+
+        // The metadata portion gave way; the sequence portion did not.
+        assert_eq!(first.as_str().len(), CLIENT_ID_MAX_LEN);
+        assert_ne!(first, second);
+        let parts = parse_client_id(&first).unwrap();
+        assert!("averylongstrategynameindeed".starts_with(&parts.strategy));
+        assert_ne!(parts.sequence, parse_client_id(&second).unwrap().sequence);
+    }
+
+    #[test]
+    fn foreign_ids_do_not_parse() {
+        for raw in ["mm-42", "fmt9", "fmt99short0012345678", "x"] {
+            let id = ClientOrderId::from_exchange(raw).unwrap();
+            assert_eq!(parse_client_id(&id), None, "{raw}");
+        }
+        assert_eq!(ClientOrderId::from_exchange(""), None);
+    }
+}
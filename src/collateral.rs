@@ -0,0 +1,55 @@
+//! Collateral domain types.
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::OkexBalanceDetail;
+
+/// Per-asset collateral as risk sizing consumes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCollateral {
+    pub asset: String,
+    /// Collateral value; discounted equity on margin accounts, cash balance
+    /// on simple ones.
+    pub total: Decimal,
+    /// Available balance net of the liability and accrued interest; goes
+    /// negative when borrowings exceed what is available, so sizing that
+    /// treats a borrowed asset as "zero free" cannot over-trade against it.
+    pub free: Decimal,
+    /// Outstanding loan in this asset (margin trading); zero when nothing
+    /// is borrowed. Borrowed assets net out negative in `total`.
+    pub liability: Decimal,
+}
+
+/// Raw per-asset collateral values for dashboards: everything the exchange
+/// reports, before any mode-dependent interpretation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollateralDetail {
+    pub asset: String,
+    pub cash_balance: Decimal,
+    pub available_balance: Decimal,
+    /// USD equity before haircuts.
+    pub equity_usd: Option<Decimal>,
+    /// Discounted USD equity after tiered collateral weights.
+    pub discounted_equity: Option<Decimal>,
+    /// Outstanding loan in this asset.
+    pub liability: Option<Decimal>,
+    /// Cross-margin share of the liability.
+    pub cross_liability: Option<Decimal>,
+    /// Accrued, not-yet-deducted interest.
+    pub interest: Option<Decimal>,
+}
+
+impl CollateralDetail {
+    pub fn from_balance_detail(detail: &OkexBalanceDetail) -> Self {
+        Self {
+            asset: detail.ccy.clone(),
+            cash_balance: detail.cash_balance,
+            available_balance: detail.available_balance,
+            equity_usd: detail.equity_usd,
+            discounted_equity: detail.discounted_equity,
+            liability: detail.liability,
+            cross_liability: detail.cross_liability,
+            interest: detail.interest,
+        }
+    }
+}
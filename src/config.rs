@@ -0,0 +1,738 @@
+//! Driver configuration.
+
+/// Production REST endpoint.
+pub const DEFAULT_HTTP_BASE_URL: &str = "https://www.okx.com";
+/// Production private WS endpoint.
+pub const DEFAULT_WS_BASE_URL: &str = "wss://ws.okx.com:8443/ws/v5/private";
+
+/// Alternate REST endpoint served from AWS, used as the default fallback.
+pub const AWS_HTTP_BASE_URL: &str = "https://aws.okx.com";
+/// Alternate private WS endpoint served from AWS.
+pub const AWS_WS_BASE_URL: &str = "wss://wsaws.okx.com:8443/ws/v5/private";
+/// Demo-trading private WS endpoint. REST demo trading only needs the
+/// simulated-trading header, but the WS side is a separate host and needs a
+/// `brokerId` query parameter; the login payload is unchanged.
+pub const DEMO_WS_BASE_URL: &str = "wss://wspap.okx.com:8443/ws/v5/private";
+/// Query parameter OKX requires on demo-trading WS connections.
+const DEMO_WS_QUERY: &str = "brokerId=9999";
+
+/// Production public-data WS endpoint (tickers, books, funding). Hosted
+/// separately from the private endpoint, which is why the public
+/// connection gets its own lifecycle; see [`crate::ws::public`].
+pub const DEFAULT_PUBLIC_WS_BASE_URL: &str = "wss://ws.okx.com:8443/ws/v5/public";
+/// Alternate public-data WS endpoint served from AWS.
+pub const AWS_PUBLIC_WS_BASE_URL: &str = "wss://wsaws.okx.com:8443/ws/v5/public";
+/// Demo-trading public-data WS endpoint.
+pub const DEMO_PUBLIC_WS_BASE_URL: &str = "wss://wspap.okx.com:8443/ws/v5/public";
+
+/// What to do when a WS order op gets no ack within the request timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AckTimeoutAction {
+    /// Surface the timeout as-is; the order state is unknown.
+    #[default]
+    Fail,
+    /// Cancel by client order id over REST, then report a typed
+    /// ambiguous-outcome error stating whether the cancel found the order.
+    /// The safest mode for market making: nothing is left resting unknowingly.
+    CancelByClOrdId,
+    /// Re-submit the same order over REST; relies on `clOrdId` idempotency
+    /// to avoid a double fill.
+    FallbackRest,
+}
+
+/// What to do with records whose instrument the converter does not know
+/// (e.g. orders or fills left over after a pair was removed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownInstrumentPolicy {
+    /// Skip the record with a debug log; the historical behavior.
+    #[default]
+    Drop,
+    /// Skip the record, but log at warn level and count it through
+    /// [`crate::rest::MetricsHook::on_unknown_instrument_records`].
+    /// Recommended: silent drops hide removed-pair leftovers.
+    Warn,
+    /// Fail the whole call listing the offending instruments; for strict
+    /// reconciliation jobs where a dropped record is worse than an error.
+    Error,
+}
+
+/// One API credential set. The client keeps its active set swappable so
+/// keys rotate without a restart; see `OkexDriver::rotate_credentials`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OkexCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+    pub passphrase: String,
+}
+
+impl OkexCredentials {
+    /// HMAC-SHA256 signature over `payload`, base64-encoded — the scheme
+    /// shared by REST request signing and the WS `login` op.
+    pub(crate) fn sign(&self, payload: &str) -> String {
+        use base64::Engine;
+        use hmac::Mac;
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("hmac accepts any key length");
+        mac.update(payload.as_bytes());
+        base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Static configuration for one OKX account connection.
+#[derive(Debug, Clone)]
+pub struct OkexConfig {
+    pub api_key: String,
+    pub api_secret: String,
+    pub passphrase: String,
+    /// Staged replacement credential set, for deployments that provision
+    /// the next key ahead of time. Not used for signing until
+    /// `OkexDriver::rotate_credentials` promotes it.
+    pub next_credentials: Option<OkexCredentials>,
+    /// Ordered REST base URLs, without trailing slashes. The first entry is
+    /// the primary; the HTTP layer fails over to later entries after
+    /// consecutive connect/5xx failures.
+    pub http_base_urls: Vec<String>,
+    /// Ordered private WS URLs, primary first.
+    pub ws_base_urls: Vec<String>,
+    /// Ordered public-data WS URLs, primary first. A separate host from
+    /// the private endpoint; the public connection fails independently.
+    pub public_ws_base_urls: Vec<String>,
+    /// How long to stay on a fallback endpoint before probing the primary
+    /// again.
+    pub endpoint_cooldown: std::time::Duration,
+    /// Trade mode used for orders; validated against the account level at
+    /// startup.
+    pub trade_mode: crate::orders::TradeMode,
+    /// Expected account position mode (`net_mode` or `long_short_mode`).
+    /// When set, preflight fails if the exchange-side setting differs;
+    /// `None` skips the check.
+    pub position_mode: Option<String>,
+    /// Recovery behavior when a WS order op times out without an ack.
+    pub ack_timeout_action: AckTimeoutAction,
+    /// Coalesce batchable WS ops (orders, cancels, amends) queued within
+    /// this window into single frames, trading that bounded delay per op
+    /// for fewer frames during requote bursts. `None` — the default —
+    /// writes every op as its own frame.
+    pub ws_coalesce_window: Option<std::time::Duration>,
+    /// Emit [`crate::events::DriverEvent::WsLagDegraded`] when a WS
+    /// channel's p99 socket-read-to-handler-completion lag exceeds this
+    /// threshold; see [`crate::ws::lag`]. `None` — the default — disables
+    /// the event; per-sample lags still reach the metrics hook.
+    pub ws_lag_threshold: Option<std::time::Duration>,
+    /// Longest a balance event may be held back waiting for a bill to
+    /// attribute its cause before it is emitted with an `Unknown` reason.
+    pub balance_attribution_delay: std::time::Duration,
+    /// Ask for gzip-compressed responses and decompress them transparently.
+    /// Large trade-history pages shrink by ~5x; disable only when debugging
+    /// raw wire traffic.
+    pub enable_compression: bool,
+    /// Largest REST response body the transport will buffer; bigger
+    /// responses abort mid-read with
+    /// [`crate::errors::DriverError::ResponseTooLarge`]. Guards against
+    /// pathological payloads — a misbehaving proxy once served a
+    /// multi-hundred-MB HTML error page — not legitimate data: the
+    /// biggest real OKX pages are low single-digit MB.
+    pub max_response_bytes: usize,
+    /// Largest inbound WS text frame the dispatch loop will parse;
+    /// oversized frames are dropped and recorded in the error log
+    /// instead of being fed through serde.
+    pub max_ws_frame_bytes: usize,
+    /// When set, arm the exchange-side cancel-all-after dead-man's switch
+    /// with this timeout and keep it refreshed; see
+    /// [`crate::cancel_all_after`].
+    pub cancel_all_after: Option<std::time::Duration>,
+    /// Periodically cancel orders resting longer than a TTL, exemptions
+    /// aside; see [`crate::stale_orders`]. `None` — the default —
+    /// disables the reaper.
+    pub stale_order_reaper: Option<crate::stale_orders::StaleOrderReaperConfig>,
+    /// Client-side order placement throttle, distinct from the HTTP rate
+    /// limiter; see [`crate::order_throttle`]. `None` disables it.
+    pub order_throttle: Option<crate::order_throttle::OrderThrottleConfig>,
+    /// Reject new orders on dated contracts within this window of their
+    /// `expTime`, so quoting stops before settlement. `None` disables the
+    /// guard.
+    pub expiry_order_guard: Option<std::time::Duration>,
+    /// Attach an `expTime` deadline of now plus this window to REST order
+    /// placements and amendments, so the exchange rejects requests that
+    /// arrive late (a proxy retry, a connect stall) instead of acting on
+    /// a stale price. Rejections map to
+    /// [`crate::errors::DriverError::RequestExpired`]. `None` disables
+    /// the deadline; the WS path has no equivalent.
+    pub order_request_validity: Option<std::time::Duration>,
+    /// Reject orders locally when the cached balance clearly cannot cover
+    /// them, before any round trip; see [`crate::balance_precheck`].
+    /// `None` disables the check.
+    pub balance_precheck: Option<crate::balance_precheck::BalancePrecheckConfig>,
+    /// Validate and log orders without sending them: trade endpoints are
+    /// never touched and synthetic acks come back instead, while read-only
+    /// endpoints still hit the exchange. See [`crate::driver::OkexDriver`].
+    pub dry_run: bool,
+    /// When set, persist fetched instruments here and fall back to the file
+    /// on startup when the live fetch fails; see
+    /// [`crate::instruments::InstrumentCache`]. `None` disables persistence.
+    pub instrument_cache_path: Option<std::path::PathBuf>,
+    /// Start from the on-disk instrument cache without waiting for the live
+    /// fetch; the live data is picked up by the next refresh instead. Only
+    /// meaningful with `instrument_cache_path` set.
+    pub fast_start: bool,
+    /// Oldest instrument cache still trusted for order placement. Older
+    /// caches keep read paths working but block orders unless
+    /// `allow_stale_instrument_orders` overrides that.
+    pub instrument_cache_max_age: std::time::Duration,
+    /// Keep open orders on instruments outside the converter (manual UI
+    /// trades and other external activity) in fetch results, backed by a
+    /// synthetic instrument parsed from the `instId`, instead of silently
+    /// dropping them. Off by default; see
+    /// [`crate::instruments::Instrument::synthetic_from_inst_id`].
+    pub surface_external_orders: bool,
+    /// Permit order placement on instrument metadata older than
+    /// `instrument_cache_max_age`. Off by default: tick and lot sizes do
+    /// change, and quoting on stale ones gets orders rejected or mispriced.
+    pub allow_stale_instrument_orders: bool,
+    /// What mapping paths do with records on instruments the converter
+    /// does not know; see [`UnknownInstrumentPolicy`].
+    pub unknown_instrument_policy: UnknownInstrumentPolicy,
+    /// Subscribe the WS `account` channel with a `ccy` filter derived from
+    /// the configured instruments (see
+    /// [`crate::instruments::InstrumentConverter::account_channel_ccy`])
+    /// instead of account-wide, so dust currencies nobody trades stop
+    /// costing bandwidth and parse CPU. A filtered subscription delivers a
+    /// partial balance view; feed the pre-check through
+    /// `OkexDriver::update_cached_balances_partial` so uncovered assets
+    /// count as unknown, not zero. Disable to keep the unfiltered
+    /// subscription.
+    pub filter_account_currencies: bool,
+    /// Fail paginated fetches when any page element does not deserialize.
+    /// By default such elements are skipped and logged so one malformed
+    /// bill cannot block a whole export.
+    pub strict_parsing: bool,
+    /// When set, requests carry the `x-simulated-trading: 1` header.
+    pub use_testnet: bool,
+}
+
+impl OkexConfig {
+    /// The credential set configured at startup.
+    pub fn credentials(&self) -> OkexCredentials {
+        OkexCredentials {
+            api_key: self.api_key.clone(),
+            api_secret: self.api_secret.clone(),
+            passphrase: self.passphrase.clone(),
+        }
+    }
+
+    /// Primary REST base URL.
+    pub fn http_base_url(&self) -> &str {
+        self.http_base_urls
+            .first()
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_HTTP_BASE_URL)
+    }
+
+    /// Primary WS URL.
+    pub fn ws_base_url(&self) -> &str {
+        self.ws_base_urls
+            .first()
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_WS_BASE_URL)
+    }
+
+    /// WS URLs to actually connect to, demo-trading aware.
+    ///
+    /// On testnet the production defaults are swapped for the demo host and
+    /// every URL gets the `brokerId` query parameter appended. Explicitly
+    /// configured URLs are kept (only the query parameter is added), so the
+    /// endpoint remains overridable.
+    pub fn resolved_ws_urls(&self) -> Vec<String> {
+        if !self.use_testnet {
+            return self.ws_base_urls.clone();
+        }
+
+        let production_defaults =
+            vec![DEFAULT_WS_BASE_URL.to_string(), AWS_WS_BASE_URL.to_string()];
+        let bases = if self.ws_base_urls == production_defaults {
+            vec![DEMO_WS_BASE_URL.to_string()]
+        } else {
+            self.ws_base_urls.clone()
+        };
+
+        bases
+            .into_iter()
+            .map(|url| {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!("{url}{separator}{DEMO_WS_QUERY}")
+            })
+            .collect()
+    }
+
+    /// Primary resolved WS URL.
+    pub fn resolved_ws_url(&self) -> String {
+        self.resolved_ws_urls()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| DEFAULT_WS_BASE_URL.to_string())
+    }
+
+    /// Public-data WS URLs to connect to, demo-trading aware, with the
+    /// same override semantics as [`Self::resolved_ws_urls`].
+    pub fn resolved_public_ws_urls(&self) -> Vec<String> {
+        if !self.use_testnet {
+            return self.public_ws_base_urls.clone();
+        }
+
+        let production_defaults = vec![
+            DEFAULT_PUBLIC_WS_BASE_URL.to_string(),
+            AWS_PUBLIC_WS_BASE_URL.to_string(),
+        ];
+        let bases = if self.public_ws_base_urls == production_defaults {
+            vec![DEMO_PUBLIC_WS_BASE_URL.to_string()]
+        } else {
+            self.public_ws_base_urls.clone()
+        };
+
+        bases
+            .into_iter()
+            .map(|url| {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!("{url}{separator}{DEMO_WS_QUERY}")
+            })
+            .collect()
+    }
+
+    /// Primary resolved public-data WS URL.
+    pub fn resolved_public_ws_url(&self) -> String {
+        self.resolved_public_ws_urls()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| DEFAULT_PUBLIC_WS_BASE_URL.to_string())
+    }
+
+    /// Check the whole config and report *every* violation at once, so an
+    /// operator fixes a broken deployment in one pass instead of playing
+    /// whack-a-mole with one error per restart. Called by
+    /// [`crate::rest::OkexClient::new`], which refuses construction on a
+    /// non-empty report.
+    pub fn validate(&self) -> Result<(), ConfigValidationReport> {
+        let mut report = ConfigValidationReport::default();
+
+        // Dry-run never signs trade calls, so it may run credential-less;
+        // everything else needs a complete set.
+        if !self.dry_run {
+            for (field, value) in [
+                ("api_key", &self.api_key),
+                ("api_secret", &self.api_secret),
+                ("passphrase", &self.passphrase),
+            ] {
+                if value.is_empty() {
+                    report.push(field, "must not be empty");
+                } else if value.chars().any(char::is_whitespace) {
+                    report.push(field, "must not contain whitespace");
+                }
+            }
+        }
+        if let Some(next) = &self.next_credentials {
+            for (field, value) in [
+                ("next_credentials.api_key", &next.api_key),
+                ("next_credentials.api_secret", &next.api_secret),
+                ("next_credentials.passphrase", &next.passphrase),
+            ] {
+                if value.is_empty() {
+                    report.push(field, "must not be empty");
+                }
+            }
+        }
+
+        Self::validate_urls(&mut report, "http_base_urls", &self.http_base_urls, &["http://", "https://"]);
+        Self::validate_urls(&mut report, "ws_base_urls", &self.ws_base_urls, &["ws://", "wss://"]);
+        Self::validate_urls(
+            &mut report,
+            "public_ws_base_urls",
+            &self.public_ws_base_urls,
+            &["ws://", "wss://"],
+        );
+
+        if self.endpoint_cooldown.is_zero() {
+            report.push("endpoint_cooldown", "must be non-zero");
+        }
+        if let Some(mode) = &self.position_mode {
+            if mode != "net_mode" && mode != "long_short_mode" {
+                report.push(
+                    "position_mode",
+                    "must be `net_mode` or `long_short_mode`",
+                );
+            }
+        }
+        if self.ws_coalesce_window.is_some_and(|window| window.is_zero()) {
+            report.push("ws_coalesce_window", "must be non-zero when set");
+        }
+        if self.ws_lag_threshold.is_some_and(|threshold| threshold.is_zero()) {
+            report.push("ws_lag_threshold", "must be non-zero when set");
+        }
+        if let Some(throttle) = &self.order_throttle {
+            if throttle.max_per_second == 0 {
+                report.push("order_throttle.max_per_second", "must be at least 1");
+            }
+            if throttle.max_per_minute < throttle.max_per_second {
+                report.push(
+                    "order_throttle.max_per_minute",
+                    "must be at least max_per_second",
+                );
+            }
+        }
+        if let Some(precheck) = &self.balance_precheck {
+            if precheck.safety_margin < rust_decimal::Decimal::ONE {
+                report.push(
+                    "balance_precheck.safety_margin",
+                    "must be at least 1; below that the check under-reserves",
+                );
+            }
+            if precheck.leverage <= rust_decimal::Decimal::ZERO {
+                report.push("balance_precheck.leverage", "must be positive");
+            }
+            if precheck.max_age.is_zero() {
+                report.push("balance_precheck.max_age", "must be non-zero");
+            }
+        }
+        if self.order_request_validity.is_some_and(|window| window.is_zero()) {
+            report.push(
+                "order_request_validity",
+                "must be non-zero when set; a zero deadline rejects every order",
+            );
+        }
+        if self.expiry_order_guard.is_some_and(|window| window.is_zero()) {
+            report.push("expiry_order_guard", "must be non-zero when set");
+        }
+        if let Some(timeout) = self.cancel_all_after {
+            if timeout.is_zero() {
+                report.push("cancel_all_after", "must be non-zero when set");
+            }
+            if self.dry_run {
+                report.push(
+                    "cancel_all_after",
+                    "conflicts with dry_run: the dead-man's switch would cancel \
+                     live orders the dry run never placed",
+                );
+            }
+        }
+        if let Some(reaper) = &self.stale_order_reaper {
+            if reaper.ttl.is_zero() {
+                report.push("stale_order_reaper.ttl", "must be non-zero");
+            }
+            if reaper.scan_interval.is_zero() {
+                report.push("stale_order_reaper.scan_interval", "must be non-zero");
+            }
+        }
+        if self.fast_start && self.instrument_cache_path.is_none() {
+            report.push(
+                "fast_start",
+                "requires instrument_cache_path; there is no cache to start from",
+            );
+        }
+        if self.instrument_cache_max_age.is_zero() {
+            report.push("instrument_cache_max_age", "must be non-zero");
+        }
+
+        if report.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    fn validate_urls(
+        report: &mut ConfigValidationReport,
+        field: &str,
+        urls: &[String],
+        schemes: &[&str],
+    ) {
+        if urls.is_empty() {
+            report.push(field, "must list at least one URL");
+            return;
+        }
+        for (index, url) in urls.iter().enumerate() {
+            let field = format!("{field}[{index}]");
+            if !schemes.iter().any(|scheme| url.starts_with(scheme)) {
+                report.push(&field, &format!("must start with {}", schemes.join(" or ")));
+            } else if url.ends_with('/') {
+                // Paths are appended verbatim; a trailing slash doubles up.
+                report.push(&field, "must not end with a slash");
+            }
+        }
+    }
+}
+
+/// One rule violation found by [`OkexConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigViolation {
+    /// Path of the offending field, e.g. `order_throttle.max_per_second`.
+    pub field: String,
+    pub problem: String,
+}
+
+/// Every violation [`OkexConfig::validate`] found. `Display` renders one
+/// violation per line, prefixed with the field path, so the report reads
+/// directly as a fix list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigValidationReport {
+    pub violations: Vec<ConfigViolation>,
+}
+
+impl ConfigValidationReport {
+    fn push(&mut self, field: &str, problem: &str) {
+        self.violations.push(ConfigViolation {
+            field: field.to_string(),
+            problem: problem.to_string(),
+        });
+    }
+}
+
+impl std::fmt::Display for ConfigValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} violation(s)", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n  {}: {}", violation.field, violation.problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for OkexConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            api_secret: String::new(),
+            passphrase: String::new(),
+            next_credentials: None,
+            http_base_urls: vec![
+                DEFAULT_HTTP_BASE_URL.to_string(),
+                AWS_HTTP_BASE_URL.to_string(),
+            ],
+            ws_base_urls: vec![DEFAULT_WS_BASE_URL.to_string(), AWS_WS_BASE_URL.to_string()],
+            public_ws_base_urls: vec![
+                DEFAULT_PUBLIC_WS_BASE_URL.to_string(),
+                AWS_PUBLIC_WS_BASE_URL.to_string(),
+            ],
+            endpoint_cooldown: std::time::Duration::from_secs(60),
+            trade_mode: crate::orders::TradeMode::Cash,
+            position_mode: None,
+            ack_timeout_action: AckTimeoutAction::default(),
+            ws_coalesce_window: None,
+            ws_lag_threshold: None,
+            balance_attribution_delay: std::time::Duration::from_millis(500),
+            enable_compression: true,
+            max_response_bytes: crate::transport::DEFAULT_MAX_RESPONSE_BYTES,
+            max_ws_frame_bytes: crate::ws::DEFAULT_MAX_FRAME_BYTES,
+            cancel_all_after: None,
+            stale_order_reaper: None,
+            order_throttle: None,
+            expiry_order_guard: None,
+            order_request_validity: Some(std::time::Duration::from_millis(500)),
+            balance_precheck: None,
+            dry_run: false,
+            instrument_cache_path: None,
+            fast_start: false,
+            surface_external_orders: false,
+            instrument_cache_max_age: std::time::Duration::from_secs(24 * 60 * 60),
+            allow_stale_instrument_orders: false,
+            unknown_instrument_policy: UnknownInstrumentPolicy::default(),
+            filter_account_currencies: true,
+            strict_parsing: false,
+            use_testnet: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn production_ws_urls_resolve_unchanged() {
+        let config = OkexConfig::default();
+        assert_eq!(
+            config.resolved_ws_urls(),
+            vec![DEFAULT_WS_BASE_URL.to_string(), AWS_WS_BASE_URL.to_string()]
+        );
+        assert!(!config.resolved_ws_url().contains("brokerId"));
+    }
+
+    #[test]
+    fn testnet_swaps_to_demo_host_with_broker_query() {
+        let config = OkexConfig {
+            use_testnet: true,
+            ..OkexConfig::default()
+        };
+        assert_eq!(
+            config.resolved_ws_urls(),
+            vec!["wss://wspap.okx.com:8443/ws/v5/private?brokerId=9999".to_string()]
+        );
+    }
+
+    #[test]
+    fn testnet_resolves_public_ws_to_the_demo_host() {
+        let config = OkexConfig {
+            use_testnet: true,
+            ..OkexConfig::default()
+        };
+        assert_eq!(
+            config.resolved_public_ws_urls(),
+            vec!["wss://wspap.okx.com:8443/ws/v5/public?brokerId=9999".to_string()]
+        );
+        assert!(!OkexConfig::default()
+            .resolved_public_ws_url()
+            .contains("brokerId"));
+    }
+
+    #[test]
+    fn testnet_keeps_explicit_ws_override_but_appends_query() {
+        let config = OkexConfig {
+            use_testnet: true,
+            ws_base_urls: vec!["wss://example.test/ws/v5/private".to_string()],
+            ..OkexConfig::default()
+        };
+        assert_eq!(
+            config.resolved_ws_urls(),
+            vec!["wss://example.test/ws/v5/private?brokerId=9999".to_string()]
+        );
+    }
+
+    /// Default config plus a credential set, the minimal valid shape.
+    fn credentialed() -> OkexConfig {
+        OkexConfig {
+            api_key: "key-123".to_string(),
+            api_secret: "topsecret".to_string(),
+            passphrase: "hunter2".to_string(),
+            ..OkexConfig::default()
+        }
+    }
+
+    fn fields(report: &ConfigValidationReport) -> Vec<&str> {
+        report
+            .violations
+            .iter()
+            .map(|violation| violation.field.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn a_credentialed_default_config_validates_clean() {
+        assert_eq!(credentialed().validate(), Ok(()));
+    }
+
+    #[test]
+    fn missing_credentials_are_reported_unless_dry_run() {
+        let report = OkexConfig::default().validate().unwrap_err();
+        assert_eq!(fields(&report), ["api_key", "api_secret", "passphrase"]);
+
+        let dry = OkexConfig {
+            dry_run: true,
+            ..OkexConfig::default()
+        };
+        assert_eq!(dry.validate(), Ok(()));
+
+        let padded = OkexConfig {
+            api_key: "key 123".to_string(),
+            ..credentialed()
+        };
+        let report = padded.validate().unwrap_err();
+        assert_eq!(fields(&report), ["api_key"]);
+    }
+
+    #[test]
+    fn malformed_urls_are_reported_with_indexed_field_paths() {
+        let config = OkexConfig {
+            http_base_urls: vec![
+                "https://www.okx.com".to_string(),
+                "www.okx.com".to_string(),
+                "https://aws.okx.com/".to_string(),
+            ],
+            ws_base_urls: Vec::new(),
+            ..credentialed()
+        };
+        let report = config.validate().unwrap_err();
+        assert_eq!(
+            fields(&report),
+            ["http_base_urls[1]", "http_base_urls[2]", "ws_base_urls"]
+        );
+    }
+
+    #[test]
+    fn throttle_and_precheck_bounds_are_checked() {
+        let config = OkexConfig {
+            order_throttle: Some(crate::order_throttle::OrderThrottleConfig {
+                max_per_second: 10,
+                max_per_minute: 5,
+                mode: crate::order_throttle::ThrottleMode::FailFast,
+            }),
+            balance_precheck: Some(crate::balance_precheck::BalancePrecheckConfig {
+                safety_margin: "0.9".parse().unwrap(),
+                leverage: rust_decimal::Decimal::ZERO,
+                max_age: std::time::Duration::from_secs(30),
+            }),
+            ..credentialed()
+        };
+        let report = config.validate().unwrap_err();
+        assert_eq!(
+            fields(&report),
+            [
+                "order_throttle.max_per_minute",
+                "balance_precheck.safety_margin",
+                "balance_precheck.leverage",
+            ]
+        );
+    }
+
+    #[test]
+    fn dry_run_conflicts_with_the_cancel_all_after_switch() {
+        let config = OkexConfig {
+            dry_run: true,
+            cancel_all_after: Some(std::time::Duration::from_secs(30)),
+            ..OkexConfig::default()
+        };
+        let report = config.validate().unwrap_err();
+        assert_eq!(fields(&report), ["cancel_all_after"]);
+        assert!(report.to_string().contains("conflicts with dry_run"));
+    }
+
+    #[test]
+    fn a_broken_config_reports_every_violation_at_once() {
+        let config = OkexConfig {
+            position_mode: Some("hedged".to_string()),
+            ws_coalesce_window: Some(std::time::Duration::ZERO),
+            order_request_validity: Some(std::time::Duration::ZERO),
+            fast_start: true,
+            instrument_cache_max_age: std::time::Duration::ZERO,
+            ..OkexConfig::default()
+        };
+        let report = config.validate().unwrap_err();
+        assert_eq!(
+            fields(&report),
+            [
+                "api_key",
+                "api_secret",
+                "passphrase",
+                "position_mode",
+                "ws_coalesce_window",
+                "order_request_validity",
+                "fast_start",
+                "instrument_cache_max_age",
+            ]
+        );
+        // Display reads as a fix list: count up front, one line per field.
+        let rendered = report.to_string();
+        assert!(rendered.starts_with("8 violation(s)"), "{rendered}");
+        assert!(rendered.contains("\n  position_mode: "), "{rendered}");
+    }
+
+    #[test]
+    fn existing_query_string_is_extended_not_duplicated() {
+        let config = OkexConfig {
+            use_testnet: true,
+            ws_base_urls: vec!["wss://example.test/ws?foo=1".to_string()],
+            ..OkexConfig::default()
+        };
+        assert_eq!(
+            config.resolved_ws_urls(),
+            vec!["wss://example.test/ws?foo=1&brokerId=9999".to_string()]
+        );
+    }
+}
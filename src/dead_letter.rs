@@ -0,0 +1,240 @@
+//! Forensic capture of unprocessable WS payloads.
+//!
+//! A frame that parses as JSON but fails typed deserialization — a field
+//! the exchange changed under us, a corrupted push — used to be logged at
+//! debug and dropped, which loses exactly the evidence needed to diagnose
+//! the breakage. The WS handlers now hand such frames to an optional
+//! [`DeadLetterSink`] instead: [`MemoryDeadLetterSink`] keeps a bounded
+//! ring for dashboards and tests, and [`FileDeadLetterSink`] (behind the
+//! `dead-letter-file` feature) appends them for post-mortems. Frames are
+//! redacted the way the wire log redacts them before any sink sees them,
+//! so credentials never reach a dead-letter file.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Receives frames the typed WS handlers could not process. Implementors
+/// must not block: `capture` runs on the frame-dispatch path.
+pub trait DeadLetterSink: Send + Sync {
+    /// Record one failed frame: the channel whose handler gave up, the
+    /// redacted raw payload, and the deserialization error.
+    fn capture(&self, channel: &str, raw: &str, error: &str);
+}
+
+/// One captured frame, as [`MemoryDeadLetterSink`] retains it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub channel: String,
+    /// The frame, credential keys redacted.
+    pub raw: String,
+    pub error: String,
+}
+
+/// Default ring bound for [`MemoryDeadLetterSink`].
+const DEFAULT_CAPACITY: usize = 128;
+
+/// Bounded in-memory sink: a drop-oldest ring, so a malfunctioning
+/// channel cannot grow memory without limit while still keeping the most
+/// recent evidence.
+pub struct MemoryDeadLetterSink {
+    capacity: usize,
+    letters: Mutex<VecDeque<DeadLetter>>,
+}
+
+impl Default for MemoryDeadLetterSink {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl MemoryDeadLetterSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            letters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Captured frames, oldest first.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.letters.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl DeadLetterSink for MemoryDeadLetterSink {
+    fn capture(&self, channel: &str, raw: &str, error: &str) {
+        let mut letters = self.letters.lock().unwrap();
+        if letters.len() >= self.capacity {
+            letters.pop_front();
+        }
+        letters.push_back(DeadLetter {
+            channel: channel.to_string(),
+            raw: raw.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+/// Appends one JSON line per dead letter to a file, for post-mortems that
+/// outlive the process. Write failures are logged and swallowed — losing
+/// a dead letter must never take the frame path down with it.
+#[cfg(feature = "dead-letter-file")]
+pub struct FileDeadLetterSink {
+    file: Mutex<std::fs::File>,
+}
+
+#[cfg(feature = "dead-letter-file")]
+impl FileDeadLetterSink {
+    /// Open (or create) the file in append mode.
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[cfg(feature = "dead-letter-file")]
+impl DeadLetterSink for FileDeadLetterSink {
+    fn capture(&self, channel: &str, raw: &str, error: &str) {
+        use std::io::Write;
+        let line = serde_json::json!({
+            "channel": channel,
+            "raw": raw,
+            "error": error,
+        });
+        let mut file = self.file.lock().unwrap();
+        if let Err(write_error) = writeln!(file, "{line}") {
+            log::warn!("dead-letter file write failed: {write_error}");
+        }
+    }
+}
+
+/// Hand a typed-deserialization failure to the sink, if the frame really
+/// is a JSON data push addressed to `channel` — anything else (a frame
+/// for another handler, a subscription event, non-JSON noise) is not a
+/// dead letter and is left for the normal routing. Returns whether the
+/// frame was captured.
+pub(crate) fn report_typed_failure(
+    sink: Option<&dyn DeadLetterSink>,
+    channel: &str,
+    frame: &str,
+    error: &dyn std::fmt::Display,
+) -> bool {
+    let Some(sink) = sink else {
+        return false;
+    };
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(frame) else {
+        return false;
+    };
+    let addressed_to = value
+        .get("arg")
+        .and_then(|arg| arg.get("channel"))
+        .and_then(|channel| channel.as_str());
+    if addressed_to != Some(channel) || value.get("event").is_some() {
+        return false;
+    }
+    sink.capture(
+        channel,
+        &crate::wire_log::redact_frame(frame),
+        &error.to_string(),
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_ring_drops_the_oldest_letters() {
+        let sink = MemoryDeadLetterSink::with_capacity(2);
+        sink.capture("orders", "frame1", "err");
+        sink.capture("orders", "frame2", "err");
+        sink.capture("orders", "frame3", "err");
+
+        let raws: Vec<String> = sink
+            .dead_letters()
+            .into_iter()
+            .map(|letter| letter.raw)
+            .collect();
+        assert_eq!(raws, ["frame2", "frame3"]);
+    }
+
+    #[test]
+    fn only_data_pushes_for_the_channel_are_captured() {
+        let sink = MemoryDeadLetterSink::new();
+        let error = "invalid type";
+
+        // Another channel's frame and a subscription event pass through.
+        assert!(!report_typed_failure(
+            Some(&sink),
+            "orders",
+            r#"{"arg":{"channel":"account"},"data":[{}]}"#,
+            &error,
+        ));
+        assert!(!report_typed_failure(
+            Some(&sink),
+            "orders",
+            r#"{"event":"subscribe","arg":{"channel":"orders"}}"#,
+            &error,
+        ));
+        assert!(!report_typed_failure(Some(&sink), "orders", "not json", &error));
+        assert!(sink.dead_letters().is_empty());
+
+        assert!(report_typed_failure(
+            Some(&sink),
+            "orders",
+            r#"{"arg":{"channel":"orders"},"data":[{"ordId":7}]}"#,
+            &error,
+        ));
+        let letters = sink.dead_letters();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].channel, "orders");
+        assert_eq!(letters[0].error, "invalid type");
+    }
+
+    #[cfg(feature = "dead-letter-file")]
+    #[test]
+    fn the_file_sink_appends_one_json_line_per_letter() {
+        let path = std::env::temp_dir().join(format!(
+            "dead-letters-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let sink = FileDeadLetterSink::open(&path).unwrap();
+        sink.capture("orders", r#"{"bad":1}"#, "invalid type");
+        sink.capture("trades", r#"{"bad":2}"#, "missing field");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["channel"], "orders");
+        assert_eq!(lines[1]["error"], "missing field");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn captured_frames_are_redacted_like_wire_logs() {
+        let sink = MemoryDeadLetterSink::new();
+        report_typed_failure(
+            Some(&sink),
+            "orders",
+            r#"{"arg":{"channel":"orders"},"apiKey":"key-123","data":[{"ordId":7}]}"#,
+            &"invalid type",
+        );
+        let letters = sink.dead_letters();
+        assert!(letters[0].raw.contains("[redacted]"), "{}", letters[0].raw);
+        assert!(!letters[0].raw.contains("key-123"), "{}", letters[0].raw);
+    }
+}
@@ -0,0 +1,2031 @@
+//! High-level driver tying the REST client and the private WS together.
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::OkexOrderOpResult;
+use crate::config::AckTimeoutAction;
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::Instrument;
+use crate::orders::{BatchItemError, BatchOutcome, OkexOrderParams, OrderRequest, Side};
+use crate::reporting::KinesisTransaction;
+use crate::rest::trade::cancel_code_means_not_found;
+use crate::rest::OkexClient;
+use crate::ws::OkexWsClient;
+
+pub struct OkexDriver {
+    rest: OkexClient,
+    ws: OkexWsClient,
+    /// Client-side placement pacing; built from the config, `None` when
+    /// disabled. Amends and cancels bypass it by design.
+    order_throttle: Option<crate::order_throttle::OrderThrottle>,
+    /// Synthetic orders placed in dry-run mode as `(ordId, clOrdId,
+    /// instId)` tuples, so cancel-by-id on them resolves locally and
+    /// cancel-all can group them per instrument.
+    dry_run_orders: std::sync::Mutex<Vec<(String, Option<String>, String)>>,
+    dry_run_next_id: std::sync::atomic::AtomicU64,
+    /// Dry-run fill simulator, fed by the consumer's market-data loop; see
+    /// [`enable_fill_simulation`](Self::enable_fill_simulation).
+    fill_sim: Option<std::sync::Arc<crate::fill_sim::FillSimulator>>,
+    /// Local insufficient-balance check on placement, `None` when
+    /// disabled; see [`crate::balance_precheck`].
+    balance_precheck: Option<crate::balance_precheck::BalancePrecheck>,
+    /// Shared fill dedup across the REST reconciliation fetch and the WS
+    /// fills stream; see [`register_trade`](Self::register_trade).
+    trade_dedup: crate::trades::TradeDeduper,
+    /// What this driver instance can do, computed from the config at
+    /// construction and refined with the account config by preflight; see
+    /// [`crate::capabilities`].
+    capabilities: std::sync::Mutex<crate::capabilities::DriverCapabilities>,
+}
+
+impl OkexDriver {
+    pub fn new(rest: OkexClient, mut ws: OkexWsClient) -> Self {
+        if let Some(window) = rest.config().ws_coalesce_window {
+            ws.enable_coalescing(window);
+        }
+        if let Some(hook) = rest.metrics_hook() {
+            ws.set_metrics_hook(hook);
+        }
+        // One wire-log toggle on the REST client covers both protocols.
+        ws.set_wire_logger(rest.wire_logger());
+        let balance_precheck = rest
+            .config()
+            .balance_precheck
+            .map(crate::balance_precheck::BalancePrecheck::new);
+        let order_throttle = rest.config().order_throttle.map(|config| {
+            let mut throttle = crate::order_throttle::OrderThrottle::new(config);
+            if let Some(hook) = rest.metrics_hook() {
+                throttle.set_metrics_hook(hook);
+            }
+            throttle
+        });
+        let capabilities = crate::capabilities::DriverCapabilities::from_config(rest.config());
+        Self {
+            rest,
+            ws,
+            order_throttle,
+            dry_run_orders: std::sync::Mutex::new(Vec::new()),
+            dry_run_next_id: std::sync::atomic::AtomicU64::new(1),
+            fill_sim: None,
+            balance_precheck,
+            trade_dedup: crate::trades::TradeDeduper::new(),
+            capabilities: std::sync::Mutex::new(capabilities),
+        }
+    }
+
+    /// Whether this driver instance supports `feature`; see
+    /// [`crate::capabilities`] for how the answer is computed.
+    pub fn supports_feature(&self, feature: crate::capabilities::DriverFeature) -> bool {
+        self.capabilities.lock().unwrap().supports(feature)
+    }
+
+    /// The full capability matrix, for startup logging.
+    pub fn capabilities(&self) -> crate::capabilities::DriverCapabilities {
+        *self.capabilities.lock().unwrap()
+    }
+
+    /// Refine the capability matrix with the exchange-side account config.
+    /// Preflight calls this with its fetch; consumers that skip preflight
+    /// can feed their own.
+    pub fn apply_account_config(&self, account: &crate::api_structs::OkexAccountConfig) {
+        self.capabilities.lock().unwrap().apply_account_config(account);
+    }
+
+    /// Turn on dry-run fill simulation: placed dry-run limit orders rest
+    /// in a [`crate::fill_sim::FillSimulator`] and fill against the book
+    /// snapshots the consumer feeds via [`fill_simulator`](Self::fill_simulator),
+    /// emitting synthetic fills on `events`. Only meaningful — and only
+    /// allowed — in dry-run mode.
+    pub fn enable_fill_simulation(
+        &mut self,
+        config: crate::fill_sim::FillSimConfig,
+        events: crate::events::DriverEventSender,
+    ) -> DriverResult<()> {
+        if !self.rest.config().dry_run {
+            return Err(DriverError::Config(
+                "fill simulation requires dry_run mode".to_string(),
+            ));
+        }
+        self.fill_sim = Some(std::sync::Arc::new(crate::fill_sim::FillSimulator::new(
+            config, events,
+        )));
+        Ok(())
+    }
+
+    /// The fill simulator, for the consumer's market-data loop to feed
+    /// book snapshots into; `None` unless simulation was enabled.
+    pub fn fill_simulator(&self) -> Option<&std::sync::Arc<crate::fill_sim::FillSimulator>> {
+        self.fill_sim.as_ref()
+    }
+
+    /// The synthetic orders currently resting in the fill simulator;
+    /// empty when simulation is off.
+    pub fn simulated_open_orders(&self) -> Vec<crate::fill_sim::SimulatedOrder> {
+        self.fill_sim
+            .as_ref()
+            .map(|sim| sim.simulated_open_orders())
+            .unwrap_or_default()
+    }
+
+    /// Feed the balance pre-check's cache from a fresh collateral fetch;
+    /// a no-op when the check is disabled. Until the first call the check
+    /// passes everything — it never rejects without data.
+    pub fn update_cached_balances(
+        &self,
+        balances: &[crate::collateral::RawCollateral],
+        fetched_at: chrono::DateTime<chrono::Utc>,
+    ) {
+        if let Some(precheck) = &self.balance_precheck {
+            precheck.update(balances, fetched_at);
+        }
+    }
+
+    /// Like [`Self::update_cached_balances`] for a fetch limited to the
+    /// filtered currency set (see
+    /// [`crate::config::OkexConfig::filter_account_currencies`]): assets
+    /// the fetch did not cover count as unknown rather than zero, so the
+    /// pre-check never rejects on a currency the filter hid.
+    pub fn update_cached_balances_partial(
+        &self,
+        balances: &[crate::collateral::RawCollateral],
+        fetched_at: chrono::DateTime<chrono::Utc>,
+    ) {
+        if let Some(precheck) = &self.balance_precheck {
+            precheck.update_partial(balances, fetched_at);
+        }
+    }
+
+    /// Let orders through the balance pre-check unconditionally until
+    /// re-enabled; a no-op when the check is disabled.
+    pub fn set_balance_precheck_bypass(&self, bypass: bool) {
+        if let Some(precheck) = &self.balance_precheck {
+            precheck.set_bypass(bypass);
+        }
+    }
+
+    /// Record a fill about to be emitted; `false` means the trade already
+    /// went downstream via the other path (REST reconciliation vs the WS
+    /// fills stream) and must be dropped. Both emission paths call this, so
+    /// consumers see each fill exactly once; duplicates are counted through
+    /// [`crate::rest::MetricsHook::on_duplicate_trade`] rather than
+    /// vanishing silently.
+    pub fn register_trade(&self, trade: &crate::trades::RawTrade) -> bool {
+        let seen_at = trade.exchange_timestamp.unwrap_or_else(chrono::Utc::now);
+        if self.trade_dedup.insert(&trade.trade_id, seen_at) {
+            return true;
+        }
+        if let Some(hook) = self.rest.metrics_hook() {
+            hook.on_duplicate_trade(&trade.inst_id, &trade.trade_id);
+        }
+        false
+    }
+
+    /// Whether a trade id was already delivered on either path; for
+    /// callers doing their own reconciliation.
+    pub fn seen_trade(&self, trade_id: &str) -> bool {
+        self.trade_dedup.contains(trade_id)
+    }
+
+    pub fn rest(&self) -> &OkexClient {
+        &self.rest
+    }
+
+    pub fn ws(&self) -> &OkexWsClient {
+        &self.ws
+    }
+
+    /// Place an order over WS within the ack latency budget.
+    ///
+    /// When the ack does not arrive in time the configured
+    /// [`AckTimeoutAction`] decides the recovery path; see its variants for
+    /// the trade-offs.
+    pub async fn open_order(
+        &self,
+        request: &OrderRequest,
+        instrument: &Instrument,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.check_expiry_guard(instrument)?;
+        if let Some(throttle) = &self.order_throttle {
+            throttle.acquire(&request.inst_id).await?;
+        }
+        let config = self.rest.config();
+        let params = OkexOrderParams::build(
+            request,
+            instrument,
+            self.effective_td_mode(instrument)?,
+            self.position_mode(),
+        )?;
+        if let Some(precheck) = &self.balance_precheck {
+            precheck.check(&params, instrument, chrono::Utc::now())?;
+        }
+        if config.dry_run {
+            return Ok(self.dry_run_place(params, instrument));
+        }
+        match self.ws.ws_open_order(&params).await {
+            Err(DriverError::Timeout(reason)) => self.handle_ack_timeout(params, reason).await,
+            other => other,
+        }
+    }
+
+    /// Reject new orders on a contract inside the configured pre-expiry
+    /// window, so quoting stops before settlement instead of racing it.
+    fn check_expiry_guard(&self, instrument: &Instrument) -> DriverResult<()> {
+        let Some(guard) = self.rest.config().expiry_order_guard else {
+            return Ok(());
+        };
+        let Some(remaining) = instrument.time_to_expiry(chrono::Utc::now()) else {
+            return Ok(());
+        };
+        if remaining <= chrono::Duration::from_std(guard).unwrap_or_default() {
+            return Err(DriverError::NearSettlement {
+                inst_id: instrument.inst_id.clone(),
+                remaining_secs: remaining.num_seconds(),
+            });
+        }
+        Ok(())
+    }
+
+    /// `tdMode` for an order on `instrument`: the account-level defaults
+    /// once [`OkexClient::resolve_order_defaults`] has run, the configured
+    /// mode before that.
+    fn effective_td_mode(&self, instrument: &Instrument) -> DriverResult<crate::orders::TradeMode> {
+        match self.rest.order_defaults() {
+            Some(defaults) => defaults.td_mode_for(instrument),
+            None => Ok(self.rest.config().trade_mode),
+        }
+    }
+
+    /// Account position mode as configured; unset means net mode.
+    fn position_mode(&self) -> crate::orders::OkexPositionMode {
+        self.rest
+            .config()
+            .position_mode
+            .as_deref()
+            .map_or_else(Default::default, crate::orders::OkexPositionMode::from_pos_mode)
+    }
+
+    /// Place a batch of orders over the WS `batch-orders` op.
+    ///
+    /// Orders that fail local validation — unknown instrument, below the
+    /// instrument minimum, a bad size denomination — are filtered out and
+    /// reported in the outcome under the `local` code instead of failing
+    /// the whole batch; the rest go to the exchange (or the dry-run book).
+    pub async fn open_orders(
+        &self,
+        requests: &[OrderRequest],
+        converter: &crate::instruments::InstrumentConverter,
+    ) -> DriverResult<BatchOutcome> {
+        if !converter.usable_for_orders() {
+            return Err(DriverError::Config(
+                "instrument metadata is a stale cache; refusing order placement \
+                 (set allow_stale_instrument_orders to override)"
+                    .to_string(),
+            ));
+        }
+        let config = self.rest.config();
+        let mut outcome = BatchOutcome::default();
+        let mut batch = Vec::new();
+        for request in requests {
+            let reference = request
+                .client_order_id
+                .clone()
+                .unwrap_or_else(|| request.inst_id.clone());
+            let Some(instrument) = converter.get(&request.inst_id) else {
+                outcome.failed.push(BatchItemError {
+                    order_id: reference,
+                    client_order_id: None,
+                    code: "local".to_string(),
+                    message: format!("unknown instrument {}", request.inst_id),
+                });
+                continue;
+            };
+            if converter.is_delisted(&request.inst_id) {
+                outcome.failed.push(BatchItemError {
+                    order_id: reference,
+                    client_order_id: None,
+                    code: "local".to_string(),
+                    message: format!(
+                        "instrument {} was delisted; only cancel/close operations remain",
+                        request.inst_id
+                    ),
+                });
+                continue;
+            }
+            if let Err(error) = self.check_expiry_guard(instrument) {
+                outcome.failed.push(BatchItemError {
+                    order_id: reference,
+                    client_order_id: None,
+                    code: "local".to_string(),
+                    message: error.to_string(),
+                });
+                continue;
+            }
+            let build = self
+                .effective_td_mode(instrument)
+                .and_then(|td_mode| {
+                    OkexOrderParams::build(request, instrument, td_mode, self.position_mode())
+                });
+            match build {
+                Ok(params) => batch.push(params),
+                Err(error) => outcome.failed.push(BatchItemError {
+                    order_id: reference,
+                    client_order_id: None,
+                    code: "local".to_string(),
+                    message: error.to_string(),
+                }),
+            }
+        }
+        if batch.is_empty() {
+            return Ok(outcome);
+        }
+        if config.dry_run {
+            for params in batch {
+                let instrument = converter
+                    .get(&params.inst_id)
+                    .expect("batch entries passed instrument lookup above");
+                outcome
+                    .succeeded
+                    .push(self.dry_run_place(params, instrument).order_id);
+            }
+            return Ok(outcome);
+        }
+        if let Some(throttle) = &self.order_throttle {
+            for params in &batch {
+                throttle.acquire(&params.inst_id).await?;
+            }
+        }
+        outcome.merge(self.ws.ws_place_orders(batch).await?);
+        Ok(outcome)
+    }
+
+    /// Cancel an order by whichever id the caller has, preferring the WS
+    /// path and falling back to REST when the WS op times out. Maps "no
+    /// such order" codes to [`DriverError::OrderNotFound`] on both paths.
+    pub async fn cancel_order_by_id(
+        &self,
+        inst_id: &str,
+        order_ref: &crate::orders::OrderRef,
+    ) -> DriverResult<OkexOrderOpResult> {
+        if self.rest.config().dry_run {
+            return self.dry_run_cancel(order_ref);
+        }
+        let result = match self.ws.ws_cancel_order(inst_id, order_ref).await {
+            Err(DriverError::Timeout(_)) => {
+                self.rest.rest_cancel_order_by_ref(inst_id, order_ref).await?
+            }
+            other => other?,
+        };
+        crate::rest::trade::map_cancel_outcome(result)
+    }
+
+    /// Cancel every open order on instruments the converter knows,
+    /// preferring WS per order with a REST fallback for ops that fail or
+    /// time out.
+    ///
+    /// The returned ids are the union of WS- and REST-cancelled orders,
+    /// deduplicated and sorted, so the result is deterministic regardless
+    /// of which path resolved each order. Orders already gone ("not found"
+    /// on either path) are omitted. An empty book short-circuits before
+    /// any cancel traffic, WS or REST, so repeated calls are cheap.
+    pub async fn cancel_all(
+        &self,
+        converter: &crate::instruments::InstrumentConverter,
+    ) -> DriverResult<Vec<String>> {
+        if self.rest.config().dry_run {
+            // Synthetic orders are the whole book in dry-run; drain them.
+            let mut orders = self.dry_run_orders.lock().unwrap();
+            let mut cancelled: Vec<String> = orders.drain(..).map(|(id, _, _)| id).collect();
+            cancelled.sort();
+            if let Some(sim) = &self.fill_sim {
+                sim.cancel_all();
+            }
+            return Ok(cancelled);
+        }
+        let open = self.rest.fetch_open_orders(converter, false).await?;
+        if open.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut cancelled = Vec::new();
+        let mut rest_fallback = Vec::new();
+        for order in &open {
+            let order_ref = crate::orders::OrderRef::ExchangeId(order.order_id.clone());
+            match self.ws.ws_cancel_order(&order.inst_id, &order_ref).await {
+                Ok(result) if result.s_code == "0" => cancelled.push(order.order_id.clone()),
+                Ok(result) if cancel_code_means_not_found(&result.s_code) => {
+                    // Filled or cancelled mid-flight; nothing left to do.
+                }
+                _ => rest_fallback.push(order),
+            }
+        }
+        if !rest_fallback.is_empty() {
+            let pairs: Vec<(String, String)> = rest_fallback
+                .iter()
+                .map(|order| (order.inst_id.clone(), order.order_id.clone()))
+                .collect();
+            match self.rest.rest_cancel_orders(&pairs).await {
+                Ok(outcome) => {
+                    cancelled.extend(outcome.succeeded);
+                    for failure in outcome.failed {
+                        if cancel_code_means_not_found(&failure.code) {
+                            // Filled or cancelled mid-flight; nothing left
+                            // to do.
+                            continue;
+                        }
+                        log::warn!(
+                            "cancel_all could not cancel {} ({}): {}",
+                            failure.order_id,
+                            failure.code,
+                            failure.message
+                        );
+                    }
+                }
+                // Nothing cancelled over REST at all. With no WS successes
+                // either, the book is untouched and reporting an empty
+                // "done" would be a lie; with partial WS success the caller
+                // still learns what did get cancelled.
+                Err(error) if cancelled.is_empty() => return Err(error),
+                Err(error) => log::warn!("cancel_all REST fallback failed: {error}"),
+            }
+        }
+
+        cancelled.sort();
+        cancelled.dedup();
+        Ok(cancelled)
+    }
+
+    /// Cancel every open order across all configured pairs in one pass.
+    ///
+    /// Where [`Self::cancel_all`] cancels order by order over WS, this
+    /// fetches the open-order snapshot once, groups ids by instrument, and
+    /// issues one WS `batch-cancel-orders` op per instrument concurrently —
+    /// the emergency flatten path should not take seconds times the pair
+    /// count. Instruments whose WS op fails or times out fall back to REST
+    /// batch cancels, one instrument at a time. Orders on unconfigured
+    /// instruments are included when `surface_external_orders` is set, same
+    /// as every open-orders fetch. Orders already gone ("not found" on
+    /// either path) are omitted; ids neither path could cancel come back
+    /// under `failed`.
+    pub async fn cancel_all_pairs(
+        &self,
+        converter: &crate::instruments::InstrumentConverter,
+    ) -> DriverResult<CancelAllPairsOutcome> {
+        let mut outcome = CancelAllPairsOutcome::default();
+        if self.rest.config().dry_run {
+            // Synthetic orders are the whole book in dry-run; drain them.
+            let mut orders = self.dry_run_orders.lock().unwrap();
+            for (order_id, _, inst_id) in orders.drain(..) {
+                outcome.cancelled.entry(inst_id).or_default().push(order_id);
+            }
+            for ids in outcome.cancelled.values_mut() {
+                ids.sort();
+            }
+            if let Some(sim) = &self.fill_sim {
+                sim.cancel_all();
+            }
+            return Ok(outcome);
+        }
+        let open = self.rest.fetch_open_orders(converter, false).await?;
+        let mut by_inst: std::collections::BTreeMap<String, Vec<String>> =
+            std::collections::BTreeMap::new();
+        for order in open {
+            by_inst.entry(order.inst_id).or_default().push(order.order_id);
+        }
+        if by_inst.is_empty() {
+            return Ok(outcome);
+        }
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for (inst_id, order_ids) in &by_inst {
+            let ws = self.ws.clone();
+            let inst_id = inst_id.clone();
+            let cancels: Vec<(String, String)> = order_ids
+                .iter()
+                .map(|order_id| (inst_id.clone(), order_id.clone()))
+                .collect();
+            tasks.spawn(async move { (inst_id, ws.ws_cancel_orders(cancels).await) });
+        }
+
+        let mut rest_fallback: std::collections::BTreeMap<String, Vec<String>> =
+            std::collections::BTreeMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            let (inst_id, result) = match joined {
+                Ok(task) => task,
+                Err(join_error) => {
+                    log::error!("ws batch cancel task panicked: {join_error}");
+                    continue;
+                }
+            };
+            match result {
+                Ok(batch) => {
+                    if !batch.succeeded.is_empty() {
+                        outcome
+                            .cancelled
+                            .entry(inst_id.clone())
+                            .or_default()
+                            .extend(batch.succeeded);
+                    }
+                    for failure in batch.failed {
+                        if cancel_code_means_not_found(&failure.code) {
+                            // Filled or cancelled mid-flight; nothing left
+                            // to do.
+                            continue;
+                        }
+                        rest_fallback
+                            .entry(inst_id.clone())
+                            .or_default()
+                            .push(failure.order_id);
+                    }
+                }
+                Err(error) => {
+                    log::warn!("ws batch cancel on {inst_id} failed ({error}); retrying over REST");
+                    let order_ids = by_inst.get(&inst_id).cloned().unwrap_or_default();
+                    rest_fallback.insert(inst_id, order_ids);
+                }
+            }
+        }
+
+        for (inst_id, order_ids) in rest_fallback {
+            let pairs: Vec<(String, String)> = order_ids
+                .iter()
+                .map(|order_id| (inst_id.clone(), order_id.clone()))
+                .collect();
+            match self.rest.rest_cancel_orders(&pairs).await {
+                Ok(batch) => {
+                    if !batch.succeeded.is_empty() {
+                        outcome
+                            .cancelled
+                            .entry(inst_id.clone())
+                            .or_default()
+                            .extend(batch.succeeded);
+                    }
+                    for failure in batch.failed {
+                        if cancel_code_means_not_found(&failure.code) {
+                            continue;
+                        }
+                        log::warn!(
+                            "cancel_all_pairs could not cancel {} on {inst_id} ({}): {}",
+                            failure.order_id,
+                            failure.code,
+                            failure.message
+                        );
+                        outcome.failed.push(failure);
+                    }
+                }
+                Err(error) => {
+                    log::warn!("cancel_all_pairs REST fallback on {inst_id} failed: {error}");
+                    outcome
+                        .failed
+                        .extend(order_ids.into_iter().map(|order_id| BatchItemError {
+                            order_id,
+                            client_order_id: None,
+                            code: "local".to_string(),
+                            message: error.to_string(),
+                        }));
+                }
+            }
+        }
+
+        for ids in outcome.cancelled.values_mut() {
+            ids.sort();
+            ids.dedup();
+        }
+        Ok(outcome)
+    }
+
+    /// Record a fully validated order without sending it: log the exact
+    /// payload the exchange would have received and ack it from a synthetic
+    /// id namespace that can never collide with real OKX order ids.
+    fn dry_run_place(&self, params: OkexOrderParams, instrument: &Instrument) -> OkexOrderOpResult {
+        let n = self
+            .dry_run_next_id
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let order_id = format!("dry-run-{n}");
+        log::info!(
+            "dry-run order {order_id}: {}",
+            serde_json::to_string(&params).unwrap_or_default()
+        );
+        self.dry_run_orders
+            .lock()
+            .unwrap()
+            .push((order_id.clone(), params.cl_ord_id.clone(), params.inst_id.clone()));
+        if let Some(sim) = &self.fill_sim {
+            sim.place(&params, &order_id, instrument);
+        }
+        OkexOrderOpResult {
+            order_id,
+            client_order_id: params.cl_ord_id,
+            s_code: "0".to_string(),
+            s_msg: "dry run".to_string(),
+        }
+    }
+
+    /// Resolve a cancel against the synthetic book; same outcome mapping as
+    /// the real paths, including [`DriverError::OrderNotFound`].
+    fn dry_run_cancel(&self, order_ref: &crate::orders::OrderRef) -> DriverResult<OkexOrderOpResult> {
+        let mut orders = self.dry_run_orders.lock().unwrap();
+        let position = orders.iter().position(|(order_id, client_order_id, _)| {
+            match order_ref {
+                crate::orders::OrderRef::ExchangeId(id) => order_id == id,
+                crate::orders::OrderRef::ClientId(id) => client_order_id.as_deref() == Some(id),
+            }
+        });
+        match position {
+            Some(index) => {
+                let (order_id, client_order_id, _) = orders.remove(index);
+                log::info!("dry-run cancel of {order_id}");
+                if let Some(sim) = &self.fill_sim {
+                    sim.cancel(&order_id);
+                }
+                Ok(OkexOrderOpResult {
+                    order_id,
+                    client_order_id,
+                    s_code: "0".to_string(),
+                    s_msg: "dry run".to_string(),
+                })
+            }
+            None => Err(DriverError::OrderNotFound(order_ref.id().to_string())),
+        }
+    }
+
+    /// Rotate to a new credential set without a restart.
+    ///
+    /// The new key is first validated with a signed read call, then the WS
+    /// session re-logs-in with it, and only then does it become the REST
+    /// signing context — so a bad key is rejected up front and a failed WS
+    /// login leaves the old key fully active. REST requests already signed
+    /// with the old key complete untouched; the exchange honours both keys
+    /// until the old one is deleted server-side.
+    pub async fn rotate_credentials(
+        &self,
+        new: crate::config::OkexCredentials,
+    ) -> DriverResult<()> {
+        self.rest.validate_credentials(&new).await?;
+        self.ws.ws_login(&new).await?;
+        self.rest.swap_credentials(new);
+        Ok(())
+    }
+
+    /// Fetch the positions closed on `instrument` since the given time
+    /// (milliseconds), mapped into reporting records with contract sizes
+    /// converted to internal amounts.
+    pub async fn fetch_closed_positions(
+        &self,
+        instrument: &Instrument,
+        since: Option<u64>,
+    ) -> DriverResult<Vec<KinesisTransaction>> {
+        let records = self
+            .rest
+            .rest_fetch_positions_history(&instrument.inst_id, since, None)
+            .await?;
+        Ok(records
+            .iter()
+            .map(|record| KinesisTransaction::from_position_history(record, instrument))
+            .collect())
+    }
+
+    /// Current positions folded into per-instrument gross legs with the
+    /// net derived from them; correct in both account modes (see
+    /// [`crate::positions`] for why naive summing is not).
+    pub async fn fetch_positions(
+        &self,
+    ) -> DriverResult<std::collections::BTreeMap<String, crate::positions::PositionLegs>> {
+        let positions = self.rest.rest_fetch_positions().await?;
+        Ok(crate::positions::aggregate_positions(&positions))
+    }
+
+    /// Reduce the long leg by `amount` with a market order; `amount` is
+    /// denominated like a regular order size (contracts), so legs from
+    /// [`fetch_positions`](Self::fetch_positions) pass straight through.
+    /// Only meaningful in long/short mode — in net mode there is no long
+    /// leg to name, so this errors instead of guessing at a side.
+    pub async fn close_long(
+        &self,
+        instrument: &Instrument,
+        amount: Decimal,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.close_leg(instrument, amount, Side::Sell, "close_long")
+            .await
+    }
+
+    /// Reduce the short leg by `amount` (contracts); see
+    /// [`close_long`](Self::close_long).
+    pub async fn close_short(
+        &self,
+        instrument: &Instrument,
+        amount: Decimal,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.close_leg(instrument, amount, Side::Buy, "close_short")
+            .await
+    }
+
+    /// Shared close-leg path: the [`PositionIntent::Close`] intent makes
+    /// the builder derive the right `posSide` and set `reduceOnly`, so an
+    /// oversized close flattens the leg instead of flipping it.
+    async fn close_leg(
+        &self,
+        instrument: &Instrument,
+        amount: Decimal,
+        side: Side,
+        helper: &str,
+    ) -> DriverResult<OkexOrderOpResult> {
+        if self.position_mode() != crate::orders::OkexPositionMode::LongShort {
+            return Err(DriverError::Config(format!(
+                "{helper} needs a long_short_mode account; in net mode close by \
+                 trading the opposite side"
+            )));
+        }
+        let request = OrderRequest {
+            inst_id: instrument.inst_id.clone(),
+            side,
+            order_type: crate::orders::OrderType::Market,
+            price: None,
+            amount,
+            size_denomination: crate::orders::SizeDenomination::Base,
+            position_intent: Some(crate::orders::PositionIntent::Close),
+            client_order_id: None,
+            metadata: None,
+        };
+        self.open_order(&request, instrument).await
+    }
+
+    async fn handle_ack_timeout(
+        &self,
+        params: OkexOrderParams,
+        timeout_reason: String,
+    ) -> DriverResult<OkexOrderOpResult> {
+        match self.rest.config().ack_timeout_action {
+            AckTimeoutAction::Fail => Err(DriverError::Timeout(timeout_reason)),
+            AckTimeoutAction::FallbackRest => self.rest.rest_place_order(&params).await,
+            AckTimeoutAction::CancelByClOrdId => {
+                let Some(client_order_id) = params.cl_ord_id.clone() else {
+                    // Nothing to cancel by; all we can say is that the state
+                    // is unknown.
+                    return Err(DriverError::Timeout(format!(
+                        "{timeout_reason}; no clOrdId set, cannot issue recovery cancel"
+                    )));
+                };
+                let cancel = self
+                    .rest
+                    .rest_cancel_order_by_client_id(&params.inst_id, &client_order_id)
+                    .await?;
+                let cancel_found_order = if cancel.s_code == "0" {
+                    true
+                } else if cancel_code_means_not_found(&cancel.s_code) {
+                    false
+                } else {
+                    return Err(DriverError::Api {
+                        code: cancel.s_code,
+                        message: cancel.s_msg,
+                    });
+                };
+                Err(DriverError::AmbiguousOrderOutcome {
+                    client_order_id,
+                    cancel_found_order,
+                })
+            }
+        }
+    }
+
+    /// One-call, I/O-free state snapshot for ops and debugging endpoints.
+    ///
+    /// Everything comes from caches the driver already holds — no network
+    /// round trips. Components the driver does not own (the supervisor,
+    /// the subscription tracker, the instrument converter) are `None` until
+    /// the caller enriches the snapshot via the `with_*` builders on
+    /// [`DriverSnapshot`].
+    pub fn snapshot(&self) -> DriverSnapshot {
+        DriverSnapshot {
+            taken_at: chrono::Utc::now().to_rfc3339(),
+            credentials_invalid: self.rest.credentials_invalid(),
+            rate_limits: self
+                .rest
+                .rate_limit_state()
+                .into_iter()
+                .map(|(category, state)| (category, RateLimitSnapshot::from(state)))
+                .collect(),
+            circuit_breakers: self.rest.circuit_breaker_states(),
+            endpoint_latencies: self.rest.endpoint_latencies(),
+            rest_errors: self.rest.last_error_details(),
+            ws_errors: self.ws.last_error_details(),
+            pending_ws_ops: self.ws.pending_ops(),
+            ws_pending_evictions: self.ws.pending_evictions(),
+            dry_run_orders: self.dry_run_orders.lock().unwrap().len(),
+            connection_status: None,
+            status_history: None,
+            subscriptions: None,
+            execution_stats: None,
+            instrument_count: None,
+            instruments_usable_for_orders: None,
+            cached_balances_age_ms: None,
+            ws_processing_lag_p99_ms: None,
+        }
+    }
+}
+
+/// Outcome of [`OkexDriver::cancel_all_pairs`]: cancelled order ids
+/// grouped by instrument, sorted and deduplicated within each list, plus
+/// the orders neither the WS nor the REST path could cancel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CancelAllPairsOutcome {
+    pub cancelled: std::collections::BTreeMap<String, Vec<String>>,
+    pub failed: Vec<BatchItemError>,
+}
+
+/// Serializable mirror of [`crate::rest::RateLimitState`]; the `Instant`
+/// observation time becomes an age.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RateLimitSnapshot {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_ms: Option<u64>,
+    /// How long ago the exchange reported this, milliseconds.
+    pub age_ms: u64,
+}
+
+impl From<crate::rest::RateLimitState> for RateLimitSnapshot {
+    fn from(state: crate::rest::RateLimitState) -> Self {
+        Self {
+            limit: state.limit,
+            remaining: state.remaining,
+            reset_ms: state.reset_ms,
+            age_ms: state.observed_at.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// One subscription as the snapshot reports it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SubscriptionSnapshot {
+    pub channel: String,
+    pub inst_id: Option<String>,
+    /// Debug rendering of the [`crate::ws::subscriptions::SubscriptionState`].
+    pub state: String,
+}
+
+/// Point-in-time driver state for debugging endpoints; see
+/// [`OkexDriver::snapshot`]. Dumps cleanly as JSON.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DriverSnapshot {
+    /// Snapshot time, RFC 3339 (chrono types are not serializable here).
+    pub taken_at: String,
+    pub credentials_invalid: bool,
+    /// Latest exchange-reported rate-limit state per endpoint category.
+    pub rate_limits: std::collections::HashMap<String, RateLimitSnapshot>,
+    /// Circuit-breaker state per endpoint category that has tripped at
+    /// least once; `Open` categories fail fast. See
+    /// [`crate::circuit_breaker`].
+    pub circuit_breakers:
+        std::collections::HashMap<String, crate::circuit_breaker::CircuitState>,
+    /// Last endpoint-probe measurements, in configured URL order; empty
+    /// when [`crate::rest::OkexClient::probe_endpoints`] has not run.
+    pub endpoint_latencies: Vec<crate::rest::EndpointLatency>,
+    /// Recent REST failures, oldest first.
+    pub rest_errors: Vec<crate::errors::OkexErrorDetails>,
+    /// Recent WS op failures, oldest first.
+    pub ws_errors: Vec<crate::errors::OkexErrorDetails>,
+    pub pending_ws_ops: usize,
+    pub ws_pending_evictions: u64,
+    /// Synthetic dry-run orders currently outstanding.
+    pub dry_run_orders: usize,
+    pub connection_status: Option<String>,
+    /// Rendered status transitions, oldest first.
+    pub status_history: Option<Vec<String>>,
+    pub subscriptions: Option<Vec<SubscriptionSnapshot>>,
+    /// Per-pair execution stats (see [`crate::execution_stats`]); filled
+    /// by [`Self::with_execution_stats`] when a tracker is attached.
+    pub execution_stats: Option<
+        std::collections::BTreeMap<String, crate::execution_stats::ExecutionStats>,
+    >,
+    pub instrument_count: Option<usize>,
+    pub instruments_usable_for_orders: Option<bool>,
+    /// Age of the consumer's last balances fetch, when it records one.
+    pub cached_balances_age_ms: Option<i64>,
+    /// p99 of recent WS socket-read-to-handler-completion lags across
+    /// channels (see [`crate::ws::lag`]); filled by [`Self::with_ws_lag`]
+    /// when a tracker is attached and at least one frame was measured.
+    pub ws_processing_lag_p99_ms: Option<u64>,
+}
+
+impl DriverSnapshot {
+    /// Fill connection status and history from the supervisor.
+    pub fn with_connection(mut self, supervisor: &crate::ws::supervisor::WsSupervisor) -> Self {
+        self.connection_status = Some(format!("{:?}", supervisor.status()));
+        self.status_history = Some(
+            supervisor
+                .status_history()
+                .iter()
+                .map(|transition| {
+                    format!(
+                        "{} {:?} -> {:?} ({:?})",
+                        transition.at.to_rfc3339(),
+                        transition.from,
+                        transition.to,
+                        transition.reason
+                    )
+                })
+                .collect(),
+        );
+        self
+    }
+
+    /// Fill per-channel subscription states from the tracker.
+    pub fn with_subscriptions(
+        mut self,
+        tracker: &crate::ws::subscriptions::SubscriptionTracker,
+    ) -> Self {
+        self.subscriptions = Some(
+            tracker
+                .subscriptions()
+                .into_iter()
+                .map(|(key, state)| SubscriptionSnapshot {
+                    channel: key.channel,
+                    inst_id: key.inst_id,
+                    state: format!("{state:?}"),
+                })
+                .collect(),
+        );
+        self
+    }
+
+    /// Fill instrument metadata counters from the converter.
+    pub fn with_instruments(
+        mut self,
+        converter: &crate::instruments::InstrumentConverter,
+    ) -> Self {
+        self.instrument_count = Some(converter.instruments().count());
+        self.instruments_usable_for_orders = Some(converter.usable_for_orders());
+        self
+    }
+
+    /// Fill per-pair execution stats from the tracker, over `window`.
+    pub fn with_execution_stats(
+        mut self,
+        tracker: &crate::execution_stats::ExecutionStatsTracker,
+        window: std::time::Duration,
+    ) -> Self {
+        self.execution_stats = Some(tracker.stats_by_pair(window));
+        self
+    }
+
+    /// Fill the WS processing-lag p99 from the tracker.
+    pub fn with_ws_lag(mut self, tracker: &crate::ws::lag::LagTracker) -> Self {
+        self.ws_processing_lag_p99_ms = tracker
+            .processing_p99()
+            .map(|p99| p99.as_millis() as u64);
+        self
+    }
+
+    /// Record when balances were last fetched, as an age at snapshot time.
+    pub fn with_balances_fetched_at(
+        mut self,
+        fetched_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        self.cached_balances_age_ms = Some((chrono::Utc::now() - fetched_at).num_milliseconds());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rust_decimal::Decimal;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::orders::{OrderType, Side};
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    fn instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn order_request() -> OrderRequest {
+        OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some("43250.1".parse().unwrap()),
+            amount: Decimal::ONE,
+            size_denomination: crate::orders::SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: Some("clord1".to_string()),
+            metadata: None,
+        }
+    }
+
+    /// A driver whose WS peer swallows frames and never answers, so every
+    /// order op times out. The returned receiver must stay alive for the
+    /// outbound channel to accept sends.
+    fn silent_ws_driver(
+        action: AckTimeoutAction,
+        transport: &Arc<MockTransport>,
+    ) -> (OkexDriver, mpsc::UnboundedReceiver<String>) {
+        let config = OkexConfig {
+            ack_timeout_action: action,
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let mut ws = OkexWsClient::new(out_tx, in_rx);
+        ws.set_request_timeout(Duration::from_millis(20));
+        (OkexDriver::new(rest, ws), out_rx)
+    }
+
+    #[tokio::test]
+    async fn fail_mode_surfaces_the_timeout() {
+        let transport = Arc::new(MockTransport::new());
+        let (driver, _out_rx) = silent_ws_driver(AckTimeoutAction::Fail, &transport);
+
+        let err = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Timeout(_)), "got: {err}");
+        assert!(transport.requests().is_empty(), "no REST recovery expected");
+    }
+
+    #[tokio::test]
+    async fn cancel_mode_reports_ambiguous_outcome_when_cancel_finds_order() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"ord1","clOrdId":"clord1","sCode":"0","sMsg":""}]}"#,
+        );
+        let (driver, _out_rx) = silent_ws_driver(AckTimeoutAction::CancelByClOrdId, &transport);
+
+        let err = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        match err {
+            DriverError::AmbiguousOrderOutcome {
+                client_order_id,
+                cancel_found_order,
+            } => {
+                assert_eq!(client_order_id, "clord1");
+                assert!(cancel_found_order);
+            }
+            other => panic!("expected ambiguous outcome, got: {other}"),
+        }
+
+        let cancel_body = transport.requests()[0].body.clone().unwrap();
+        assert!(cancel_body.contains(r#""clOrdId":"clord1""#), "{cancel_body}");
+        assert!(!cancel_body.contains("ordId\":\"ord"), "{cancel_body}");
+    }
+
+    #[tokio::test]
+    async fn cancel_mode_reports_when_order_was_never_seen() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"1","msg":"","data":[{"ordId":"","clOrdId":"clord1","sCode":"51400","sMsg":"Cancellation failed as the order does not exist"}]}"#,
+        );
+        let (driver, _out_rx) = silent_ws_driver(AckTimeoutAction::CancelByClOrdId, &transport);
+
+        let err = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                DriverError::AmbiguousOrderOutcome {
+                    cancel_found_order: false,
+                    ..
+                }
+            ),
+            "got: {err}"
+        );
+    }
+
+    /// WS peer that answers `cancel-order` ops with the given `sCode`,
+    /// echoing the id args back, and records each op's args.
+    fn cancel_peer(
+        mut from_client: mpsc::UnboundedReceiver<String>,
+        to_client: mpsc::UnboundedSender<String>,
+        s_code: &'static str,
+        seen_args: mpsc::UnboundedSender<serde_json::Value>,
+    ) {
+        tokio::spawn(async move {
+            while let Some(frame) = from_client.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "cancel-order");
+                seen_args.send(request["args"].clone()).unwrap();
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": "cancel-order",
+                    "code": if s_code == "0" { "0" } else { "1" },
+                    "msg": "",
+                    "data": [{"ordId": "", "sCode": s_code, "sMsg": if s_code == "0" { "" } else { "Order does not exist" }}],
+                });
+                to_client.send(ack.to_string()).unwrap();
+            }
+        });
+    }
+
+    fn driver_with_cancel_peer(
+        s_code: &'static str,
+    ) -> (OkexDriver, mpsc::UnboundedReceiver<serde_json::Value>) {
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (args_tx, args_rx) = mpsc::unbounded_channel();
+        cancel_peer(out_rx, in_tx, s_code, args_tx);
+        (OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx)), args_rx)
+    }
+
+    #[tokio::test]
+    async fn cancel_by_client_id_serializes_clordid() {
+        let (driver, mut args_rx) = driver_with_cancel_peer("0");
+
+        driver
+            .cancel_order_by_id(
+                "BTC-USDT",
+                &crate::orders::OrderRef::ClientId("clord7".to_string()),
+            )
+            .await
+            .unwrap();
+
+        let args = args_rx.recv().await.unwrap();
+        assert_eq!(args[0]["clOrdId"], "clord7");
+        assert_eq!(args[0]["instId"], "BTC-USDT");
+        assert!(args[0].get("ordId").is_none(), "must not send ordId: {args}");
+    }
+
+    #[tokio::test]
+    async fn cancel_maps_unknown_id_to_order_not_found() {
+        let (driver, _args_rx) = driver_with_cancel_peer("51400");
+
+        let err = driver
+            .cancel_order_by_id(
+                "BTC-USDT",
+                &crate::orders::OrderRef::ClientId("nosuch".to_string()),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::OrderNotFound(_)), "got: {err}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn order_throttle_rejects_placements_but_not_cancels() {
+        let config = OkexConfig {
+            order_throttle: Some(crate::order_throttle::OrderThrottleConfig {
+                max_per_second: 1,
+                max_per_minute: 1000,
+                mode: crate::order_throttle::ThrottleMode::FailFast,
+            }),
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer acking both placements and cancels.
+        tokio::spawn(async move {
+            let mut from_client = out_rx;
+            let to_client = in_tx;
+            while let Some(frame) = from_client.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                let ack = serde_json::json!({
+                    "id": request["id"], "op": request["op"],
+                    "code": "0", "msg": "",
+                    "data": [{"ordId": "ord1", "clOrdId": "clord1", "sCode": "0", "sMsg": ""}],
+                });
+                to_client.send(ack.to_string()).unwrap();
+            }
+        });
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        let err = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Throttled(_)), "got: {err}");
+
+        // Cancels are exempt: getting out of the market never waits.
+        driver
+            .cancel_order_by_id(
+                "BTC-USDT",
+                &crate::orders::OrderRef::ExchangeId("ord1".to_string()),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn expiry_guard_rejects_orders_near_settlement() {
+        let transport = Arc::new(MockTransport::new());
+        let config = OkexConfig {
+            expiry_order_guard: Some(Duration::from_secs(600)),
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        // Expires one minute from now, well inside the 10-minute guard.
+        let expiring = Instrument {
+            inst_id: "BTC-USDT-240329".to_string(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: Some((chrono::Utc::now().timestamp_millis() + 60_000) as u64),
+            ..instrument()
+        };
+        let request = OrderRequest {
+            inst_id: expiring.inst_id.clone(),
+            ..order_request()
+        };
+
+        let err = driver.open_order(&request, &expiring).await.unwrap_err();
+        assert!(matches!(err, DriverError::NearSettlement { .. }), "got: {err}");
+        assert!(out_rx.try_recv().is_err(), "nothing may reach the exchange");
+
+        // A contract expiring in a week is untouched by the guard (the WS
+        // send proves the order got past it; nobody acks, so it times out).
+        let far = Instrument {
+            expiry_time: Some(
+                (chrono::Utc::now().timestamp_millis() + 7 * 24 * 3_600_000) as u64,
+            ),
+            ..expiring
+        };
+        let mut far_request = order_request();
+        far_request.inst_id = far.inst_id.clone();
+        let pending = driver.open_order(&far_request, &far);
+        tokio::select! {
+            _ = pending => {}
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+        }
+        assert!(out_rx.try_recv().is_ok(), "order frame was sent");
+    }
+
+    #[tokio::test]
+    async fn batch_placement_filters_local_failures_and_sends_the_rest() {
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer acking every batch-orders entry.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "batch-orders");
+                let data: Vec<serde_json::Value> = request["args"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        serde_json::json!({
+                            "ordId": format!("ord{i}"),
+                            "clOrdId": arg["clOrdId"],
+                            "sCode": "0", "sMsg": "",
+                        })
+                    })
+                    .collect();
+                let ack = serde_json::json!({
+                    "id": request["id"], "op": "batch-orders",
+                    "code": "0", "msg": "", "data": data,
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        converter.insert(instrument());
+
+        let good = order_request();
+        let below_min = OrderRequest {
+            amount: "0.00001".parse().unwrap(),
+            client_order_id: Some("tiny".to_string()),
+            ..order_request()
+        };
+        let unknown = OrderRequest {
+            inst_id: "ETH-USDT".to_string(),
+            client_order_id: Some("nowhere".to_string()),
+            ..order_request()
+        };
+
+        let outcome = driver
+            .open_orders(&[good, below_min, unknown], &converter)
+            .await
+            .unwrap();
+        assert_eq!(outcome.succeeded, vec!["ord0"]);
+        assert_eq!(outcome.failed.len(), 2);
+        assert!(outcome.failed.iter().all(|f| f.code == "local"));
+        // 0.00001 is below one 0.0001 lot, so flooring leaves nothing.
+        assert!(outcome.failed[0].message.contains("serializes to zero"));
+        assert_eq!(outcome.failed[1].order_id, "nowhere");
+    }
+
+    #[tokio::test]
+    async fn a_delisted_instrument_still_cancels_but_refuses_new_orders() {
+        // BTC-USDT vanishes from a refresh while one order is still open.
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        converter.insert(instrument());
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        converter.apply_refresh(
+            crate::instruments::InstrumentConverter::new(),
+            |_| true,
+            &events_tx,
+        );
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","instType":"SPOT","ordId":"ord1","clOrdId":"clord1","px":"43250.1","sz":"1","side":"buy","state":"live","cTime":"1700000000000"}]}"#,
+        );
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (args_tx, mut args_rx) = mpsc::unbounded_channel();
+        cancel_peer(out_rx, in_tx, "0", args_tx);
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        // Cleanup keeps working: the tombstone still resolves the instId,
+        // so the open order is found and cancelled.
+        let cancelled = driver.cancel_all(&converter).await.unwrap();
+        assert_eq!(cancelled, vec!["ord1".to_string()]);
+        let args = args_rx.recv().await.unwrap();
+        assert_eq!(args[0]["instId"], "BTC-USDT");
+
+        // New orders on the tombstone are refused before any wire traffic.
+        let outcome = driver
+            .open_orders(&[order_request()], &converter)
+            .await
+            .unwrap();
+        assert!(outcome.succeeded.is_empty());
+        assert!(
+            outcome.failed[0].message.contains("delisted"),
+            "{}",
+            outcome.failed[0].message
+        );
+        assert_eq!(
+            transport.requests().len(),
+            1,
+            "only the open-orders fetch may hit REST"
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_acks_locally_and_never_touches_trade_endpoints() {
+        let transport = Arc::new(MockTransport::new());
+        let config = OkexConfig {
+            dry_run: true,
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        let ack = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        assert!(ack.order_id.starts_with("dry-run-"), "{}", ack.order_id);
+        assert_eq!(ack.s_code, "0");
+
+        // Cancel by client id resolves against the synthetic book...
+        driver
+            .cancel_order_by_id(
+                "BTC-USDT",
+                &crate::orders::OrderRef::ClientId("clord1".to_string()),
+            )
+            .await
+            .unwrap();
+        // ...and a second cancel maps to the usual not-found error.
+        let err = driver
+            .cancel_order_by_id(
+                "BTC-USDT",
+                &crate::orders::OrderRef::ExchangeId(ack.order_id.clone()),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::OrderNotFound(_)), "got: {err}");
+
+        let second = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        let cancelled = driver
+            .cancel_all(&crate::instruments::InstrumentConverter::new())
+            .await
+            .unwrap();
+        assert_eq!(cancelled, vec![second.order_id]);
+
+        assert!(transport.requests().is_empty(), "no REST calls in dry-run");
+        assert!(out_rx.try_recv().is_err(), "no WS frames in dry-run");
+    }
+
+    #[tokio::test]
+    async fn balance_precheck_rejects_short_orders_before_any_wire_traffic() {
+        let transport = Arc::new(MockTransport::new());
+        let config = OkexConfig {
+            balance_precheck: Some(crate::balance_precheck::BalancePrecheckConfig::default()),
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        // Fresh cache with nowhere near enough USDT for a 43250 notional.
+        driver.update_cached_balances(
+            &[crate::collateral::RawCollateral {
+                asset: "USDT".to_string(),
+                total: "100".parse().unwrap(),
+                free: "100".parse().unwrap(),
+                liability: Decimal::ZERO,
+            }],
+            chrono::Utc::now(),
+        );
+        let err = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DriverError::InsufficientFunds { ref asset, .. } if asset == "USDT"),
+            "got: {err}"
+        );
+        assert!(out_rx.try_recv().is_err(), "nothing may reach the exchange");
+        assert!(transport.requests().is_empty());
+
+        // The bypass lets the same order through (to the silent WS peer).
+        driver.set_balance_precheck_bypass(true);
+        let (request, inst) = (order_request(), instrument());
+        let pending = driver.open_order(&request, &inst);
+        tokio::select! {
+            _ = pending => {}
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+        }
+        assert!(out_rx.try_recv().is_ok(), "order frame was sent");
+    }
+
+    #[tokio::test]
+    async fn enabled_fill_simulation_fills_dry_run_orders_from_fed_books() {
+        let config = OkexConfig {
+            dry_run: true,
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, _out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let mut driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        driver
+            .enable_fill_simulation(crate::fill_sim::FillSimConfig::default(), events_tx)
+            .unwrap();
+
+        let ack = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        assert_eq!(driver.simulated_open_orders().len(), 1);
+
+        // The consumer's market-data loop feeds a book whose ask touches
+        // the resting bid.
+        let book = crate::order_book::OrderBook {
+            inst_id: "BTC-USDT".to_string(),
+            bids: Vec::new(),
+            asks: vec![crate::order_book::BookLevel {
+                price: "43250.1".parse().unwrap(),
+                size: "5".parse().unwrap(),
+                order_count: None,
+            }],
+            timestamp: "1700000000000".to_string(),
+            exchange_timestamp: chrono::DateTime::from_timestamp_millis(1_700_000_000_000),
+        };
+        driver.fill_simulator().unwrap().on_book(&book);
+
+        match events_rx.try_recv().unwrap() {
+            crate::events::DriverEvent::SimulatedFill(trade) => {
+                assert_eq!(trade.order_id, ack.order_id);
+                assert_eq!(trade.client_order_id.as_deref(), Some("clord1"));
+                assert_eq!(trade.amount, Decimal::ONE);
+            }
+            other => panic!("expected a simulated fill, got {other:?}"),
+        }
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            crate::events::DriverEvent::SimulatedOrderUpdate { state, .. } if state == "filled"
+        ));
+        assert!(driver.simulated_open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fill_simulation_is_rejected_outside_dry_run() {
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, _out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let mut driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+
+        let err = driver
+            .enable_fill_simulation(crate::fill_sim::FillSimConfig::default(), events_tx)
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "got: {err}");
+    }
+
+    #[tokio::test]
+    async fn cancel_all_returns_the_sorted_union_of_ws_and_rest_cancels() {
+        let transport = Arc::new(MockTransport::new());
+        // Open-orders snapshot: three resting orders.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT","ordId":"ord-c","sz":"1","side":"buy","state":"live","cTime":"1700000000000"},
+                {"instId":"BTC-USDT","ordId":"ord-a","sz":"1","side":"buy","state":"live","cTime":"1700000000001"},
+                {"instId":"BTC-USDT","ordId":"ord-b","sz":"1","side":"sell","state":"live","cTime":"1700000000002"}
+            ]}"#,
+        );
+        // REST fallback cancel for the order the WS path failed on.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"ord-b","sCode":"0","sMsg":""}]}"#,
+        );
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // WS peer that cancels everything except ord-b, which hard-fails.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "cancel-order");
+                let ord_id = request["args"][0]["ordId"].as_str().unwrap().to_string();
+                let busy = ord_id == "ord-b";
+                let ack = serde_json::json!({
+                    "id": request["id"], "op": "cancel-order",
+                    "code": if busy { "1" } else { "0" }, "msg": "",
+                    "data": [{
+                        "ordId": ord_id,
+                        "sCode": if busy { "50013" } else { "0" },
+                        "sMsg": if busy { "System is busy" } else { "" },
+                    }],
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        converter.insert(instrument());
+
+        let cancelled = driver.cancel_all(&converter).await.unwrap();
+        // WS-cancelled ids are kept alongside the REST remainder, sorted.
+        assert_eq!(cancelled, vec!["ord-a", "ord-b", "ord-c"]);
+
+        let rest_cancel = &transport.requests()[1];
+        assert!(rest_cancel.url.ends_with("/api/v5/trade/cancel-batch-orders"));
+        assert!(rest_cancel.body.as_deref().unwrap().contains("ord-b"));
+    }
+
+    #[tokio::test]
+    async fn cancel_all_pairs_batches_per_instrument_with_a_rest_fallback() {
+        let transport = Arc::new(MockTransport::new());
+        // Open-orders snapshot: four orders across three instruments.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT","ordId":"ord-b1","sz":"1","side":"buy","state":"live","cTime":"1700000000000"},
+                {"instId":"BTC-USDT","ordId":"ord-b2","sz":"1","side":"sell","state":"live","cTime":"1700000000001"},
+                {"instId":"ETH-USDT","ordId":"ord-e1","sz":"1","side":"buy","state":"live","cTime":"1700000000002"},
+                {"instId":"SOL-USDT","ordId":"ord-s1","sz":"1","side":"buy","state":"live","cTime":"1700000000003"}
+            ]}"#,
+        );
+        // REST fallback batch cancel for the instrument whose WS op failed.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"ord-e1","sCode":"0","sMsg":""}]}"#,
+        );
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // WS peer answering per-instrument batch cancels: ETH's op is
+        // rejected outright, BTC's second order is already gone.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "batch-cancel-orders");
+                let args = request["args"].as_array().unwrap();
+                if args[0]["instId"] == "ETH-USDT" {
+                    let error = serde_json::json!({
+                        "event": "error", "id": request["id"],
+                        "code": "63999", "msg": "Internal error",
+                    });
+                    in_tx.send(error.to_string()).unwrap();
+                    continue;
+                }
+                let data: Vec<serde_json::Value> = args
+                    .iter()
+                    .map(|arg| {
+                        let ord_id = arg["ordId"].as_str().unwrap();
+                        let gone = ord_id == "ord-b2";
+                        serde_json::json!({
+                            "ordId": ord_id,
+                            "sCode": if gone { "51400" } else { "0" },
+                            "sMsg": if gone { "Cancellation failed as the order does not exist" } else { "" },
+                        })
+                    })
+                    .collect();
+                let ack = serde_json::json!({
+                    "id": request["id"], "op": "batch-cancel-orders",
+                    "code": "2", "msg": "", "data": data,
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        for inst_id in ["BTC-USDT", "ETH-USDT", "SOL-USDT"] {
+            converter.insert(Instrument {
+                inst_id: inst_id.to_string(),
+                ..instrument()
+            });
+        }
+
+        let outcome = driver.cancel_all_pairs(&converter).await.unwrap();
+        let cancelled: Vec<(&str, &[String])> = outcome
+            .cancelled
+            .iter()
+            .map(|(inst_id, ids)| (inst_id.as_str(), ids.as_slice()))
+            .collect();
+        assert_eq!(
+            cancelled,
+            vec![
+                ("BTC-USDT", &["ord-b1".to_string()][..]),
+                ("ETH-USDT", &["ord-e1".to_string()][..]),
+                ("SOL-USDT", &["ord-s1".to_string()][..]),
+            ],
+            "ord-b2 was already gone and is omitted"
+        );
+        assert!(outcome.failed.is_empty(), "{:?}", outcome.failed);
+
+        // Only ETH's orders went to the REST fallback.
+        let rest_cancel = &transport.requests()[1];
+        assert!(rest_cancel.url.ends_with("/api/v5/trade/cancel-batch-orders"));
+        let body = rest_cancel.body.as_deref().unwrap();
+        assert!(body.contains("ord-e1"), "{body}");
+        assert!(!body.contains("ord-b"), "{body}");
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn cancel_all_on_an_empty_book_sends_no_cancel_traffic() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        let cancelled = driver
+            .cancel_all(&crate::instruments::InstrumentConverter::new())
+            .await
+            .unwrap();
+        assert!(cancelled.is_empty());
+        assert_eq!(transport.requests().len(), 1, "only the snapshot fetch");
+        assert!(out_rx.try_recv().is_err(), "no WS frames expected");
+    }
+
+    /// WS peer that acks `order` ops and `login` ops, forwarding the apiKey
+    /// of each login so the test can see which key authenticated.
+    fn rotation_peer(
+        mut from_client: mpsc::UnboundedReceiver<String>,
+        to_client: mpsc::UnboundedSender<String>,
+        logins: mpsc::UnboundedSender<String>,
+    ) {
+        tokio::spawn(async move {
+            let mut next_ord = 1;
+            while let Some(frame) = from_client.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                let ack = match request["op"].as_str().unwrap() {
+                    "login" => {
+                        let api_key = request["args"][0]["apiKey"].as_str().unwrap();
+                        assert!(!request["args"][0]["sign"].as_str().unwrap().is_empty());
+                        logins.send(api_key.to_string()).unwrap();
+                        serde_json::json!({
+                            "id": request["id"], "op": "login",
+                            "code": "0", "msg": "", "data": [],
+                        })
+                    }
+                    "order" => {
+                        let ord_id = format!("ord{next_ord}");
+                        next_ord += 1;
+                        serde_json::json!({
+                            "id": request["id"], "op": "order",
+                            "code": "0", "msg": "",
+                            "data": [{"ordId": ord_id, "clOrdId": "clord1", "sCode": "0", "sMsg": ""}],
+                        })
+                    }
+                    other => panic!("unexpected op {other}"),
+                };
+                to_client.send(ack.to_string()).unwrap();
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn rotation_between_two_orders_lets_both_succeed() {
+        let transport = Arc::new(MockTransport::new());
+        // Validation probe for the new key.
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let config = OkexConfig {
+            api_key: "old-key".to_string(),
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (logins_tx, mut logins_rx) = mpsc::unbounded_channel();
+        rotation_peer(out_rx, in_tx, logins_tx);
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        let first = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        assert_eq!(first.order_id, "ord1");
+
+        driver
+            .rotate_credentials(crate::config::OkexCredentials {
+                api_key: "new-key".to_string(),
+                api_secret: "new-secret".to_string(),
+                passphrase: "new-pass".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let second = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        assert_eq!(second.order_id, "ord2");
+
+        // The probe was signed with the candidate key, the re-login carried
+        // it, and it is now the active REST signing context.
+        let probe = transport.requests()[0].clone();
+        assert!(probe.url.ends_with("/api/v5/account/balance"));
+        assert!(probe
+            .headers
+            .iter()
+            .any(|(n, v)| n == "OK-ACCESS-KEY" && v == "new-key"));
+        assert_eq!(logins_rx.recv().await.unwrap(), "new-key");
+        assert_eq!(driver.rest().current_credentials().api_key, "new-key");
+    }
+
+    #[tokio::test]
+    async fn rotation_with_a_bad_key_leaves_the_old_one_active() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"50111","msg":"Invalid OK-ACCESS-KEY","data":[]}"#);
+        let config = OkexConfig {
+            api_key: "old-key".to_string(),
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let driver = OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx));
+
+        let err = driver
+            .rotate_credentials(crate::config::OkexCredentials {
+                api_key: "bad-key".to_string(),
+                api_secret: "bad-secret".to_string(),
+                passphrase: "bad-pass".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Api { .. }), "got: {err}");
+        assert_eq!(driver.rest().current_credentials().api_key, "old-key");
+    }
+
+    /// Hook counting duplicate-trade drops by trade id.
+    #[derive(Default)]
+    struct DuplicateCountingHook {
+        duplicates: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl crate::rest::MetricsHook for DuplicateCountingHook {
+        fn on_request(&self, _metrics: &crate::rest::RequestMetrics) {}
+
+        fn on_duplicate_trade(&self, _inst_id: &str, trade_id: &str) {
+            self.duplicates.lock().unwrap().push(trade_id.to_string());
+        }
+    }
+
+    fn dedup_driver() -> (OkexDriver, Arc<DuplicateCountingHook>) {
+        let hook = Arc::new(DuplicateCountingHook::default());
+        let mut rest = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        rest.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        (OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx)), hook)
+    }
+
+    fn raw_trade(trade_id: &str) -> crate::trades::RawTrade {
+        let fill: crate::api_structs::TransactionResult = serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT","tradeId":"{trade_id}","ordId":"ord1","fillPx":"43250.1","fillSz":"0.25","side":"buy","fee":"-0.1","feeCcy":"USDT","ts":"1700000000000"}}"#
+        ))
+        .unwrap();
+        crate::trades::RawTrade::from_transaction(&fill, &instrument(), chrono::Utc::now())
+    }
+
+    #[tokio::test]
+    async fn a_fill_seen_on_ws_is_dropped_and_counted_when_rest_refetches_it() {
+        let (driver, hook) = dedup_driver();
+
+        // WS fills stream delivers first; the REST reconciliation refetches
+        // the same trade later.
+        assert!(driver.register_trade(&raw_trade("t1")));
+        assert!(driver.seen_trade("t1"));
+        assert!(!driver.register_trade(&raw_trade("t1")));
+
+        assert_eq!(*hook.duplicates.lock().unwrap(), vec!["t1"]);
+        // Unrelated ids are unaffected.
+        assert!(!driver.seen_trade("t2"));
+        assert!(driver.register_trade(&raw_trade("t2")));
+    }
+
+    #[tokio::test]
+    async fn a_fill_seen_on_rest_is_dropped_and_counted_when_ws_replays_it() {
+        let (driver, hook) = dedup_driver();
+
+        // REST reconciliation wins the race; the WS frame arrives after.
+        assert!(driver.register_trade(&raw_trade("t9")));
+        assert!(!driver.register_trade(&raw_trade("t9")));
+        assert!(!driver.register_trade(&raw_trade("t9")));
+
+        assert_eq!(*hook.duplicates.lock().unwrap(), vec!["t9", "t9"]);
+    }
+
+    #[tokio::test]
+    async fn fallback_rest_mode_resubmits_over_rest() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"ord9","clOrdId":"clord1","sCode":"0","sMsg":""}]}"#,
+        );
+        let (driver, _out_rx) = silent_ws_driver(AckTimeoutAction::FallbackRest, &transport);
+
+        let result = driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap();
+        assert_eq!(result.order_id, "ord9");
+        assert!(transport.requests()[0]
+            .url
+            .ends_with("/api/v5/trade/order"));
+    }
+
+    #[tokio::test]
+    async fn snapshot_reports_driver_state_and_serializes() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"51000","msg":"parameter error","data":[]}"#);
+        let (driver, _out_rx) = silent_ws_driver(AckTimeoutAction::Fail, &transport);
+
+        // A bit of traffic: one WS op that times out, one rejected REST
+        // call; both land in the respective error logs.
+        driver
+            .open_order(&order_request(), &instrument())
+            .await
+            .unwrap_err();
+        driver.rest().rest_fetch_balances().await.unwrap_err();
+
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        converter.insert(instrument());
+        let snapshot = driver
+            .snapshot()
+            .with_instruments(&converter)
+            .with_balances_fetched_at(chrono::Utc::now());
+
+        assert!(!snapshot.ws_errors.is_empty(), "ws timeout was recorded");
+        assert!(!snapshot.rest_errors.is_empty(), "rest rejection was recorded");
+        assert_eq!(snapshot.pending_ws_ops, 0);
+        assert_eq!(snapshot.instrument_count, Some(1));
+        assert_eq!(snapshot.instruments_usable_for_orders, Some(true));
+        assert!(snapshot.cached_balances_age_ms.is_some());
+        assert!(!snapshot.credentials_invalid);
+        assert!(snapshot.connection_status.is_none(), "no supervisor attached");
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["instrument_count"], 1);
+        assert_eq!(json["rest_errors"][0]["code"], "51000");
+    }
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn close_leg_helpers_error_in_net_mode_without_wire_traffic() {
+        let transport = Arc::new(MockTransport::new());
+        let (driver, mut out_rx) = silent_ws_driver(AckTimeoutAction::Fail, &transport);
+
+        let err = driver
+            .close_long(&swap_instrument(), Decimal::ONE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "got: {err}");
+        assert!(err.to_string().contains("long_short_mode"), "{err}");
+        assert!(out_rx.try_recv().is_err(), "no order op may be sent");
+    }
+
+    #[tokio::test]
+    async fn close_short_builds_the_reduce_only_close_leg_order() {
+        let config = OkexConfig {
+            position_mode: Some("long_short_mode".to_string()),
+            trade_mode: crate::orders::TradeMode::Cross,
+            ack_timeout_action: AckTimeoutAction::Fail,
+            ..OkexConfig::default()
+        };
+        let transport = Arc::new(MockTransport::new());
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let mut ws = OkexWsClient::new(out_tx, in_rx);
+        ws.set_request_timeout(Duration::from_millis(20));
+        let driver = OkexDriver::new(rest, ws);
+
+        // The silent peer never acks, so the op itself times out; the
+        // outbound frame still shows what would have been placed.
+        driver
+            .close_short(&swap_instrument(), "50".parse().unwrap())
+            .await
+            .unwrap_err();
+
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        let order = &frame["args"][0];
+        // Closing a short buys back posSide=short, reduce-only.
+        assert_eq!(order["side"], "buy");
+        assert_eq!(order["posSide"], "short");
+        assert_eq!(order["reduceOnly"], true);
+        assert_eq!(order["ordType"], "market");
+        assert_eq!(order["sz"], "50");
+    }
+}
@@ -0,0 +1,211 @@
+//! Error types shared across the driver.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use thiserror::Error;
+
+/// Result alias used by every fallible driver API.
+pub type DriverResult<T> = Result<T, DriverError>;
+
+/// Top-level driver error.
+#[derive(Debug, Error)]
+pub enum DriverError {
+    /// Catch-all for errors that do not warrant their own variant.
+    #[error("{0}")]
+    Generic(String),
+
+    /// Transport-level failure (connect, TLS, timeout, non-2xx status).
+    #[error("http error: {0}")]
+    Http(String),
+
+    /// OKX returned a non-zero business code.
+    #[error("okx api error {code}: {message}")]
+    Api { code: String, message: String },
+
+    /// Payload could not be (de)serialized.
+    #[error("serde error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    /// The exchange rejected the credentials themselves (HTTP 401/403 or
+    /// an OKX auth code). Not transient: retrying cannot help until the
+    /// credentials are rotated, and the client fails fast once flagged.
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// The driver configuration is invalid for the requested operation.
+    #[error("invalid configuration: {0}")]
+    Config(String),
+
+    /// Neither the exchange order id nor the client order id matched a
+    /// known order.
+    #[error("order not found: {0}")]
+    OrderNotFound(String),
+
+    /// An operation did not complete within its latency budget.
+    #[error("timeout: {0}")]
+    Timeout(String),
+
+    /// The request reached the exchange after its `expTime` deadline and
+    /// was rejected without being acted on; see
+    /// [`crate::config::OkexConfig::order_request_validity`]. Retriable:
+    /// nothing rested, so re-submitting at a fresh price is safe.
+    #[error("request expired: {0}")]
+    RequestExpired(String),
+
+    /// The order is below the instrument's minimum size (or minimum
+    /// notional, for spot); caught locally before any round trip. Both
+    /// values are in base units.
+    /// The order's size would serialize as `"0"` — a zero (or negative)
+    /// amount passed through from upstream, or an amount below one lot
+    /// that flooring ate entirely. OKX rejects `"sz":"0"` with a generic
+    /// parameter error; caught locally before serialization.
+    #[error("order size {requested} on {inst_id} serializes to zero")]
+    ZeroSize {
+        inst_id: String,
+        requested: rust_decimal::Decimal,
+    },
+
+    #[error("order size {requested} is below the instrument minimum {min}")]
+    BelowMinimumSize {
+        min: rust_decimal::Decimal,
+        requested: rust_decimal::Decimal,
+    },
+
+    /// The cached balance clearly cannot cover the order; caught locally
+    /// before any round trip. Only raised while the balance pre-check is
+    /// enabled and its cache is fresh; see [`crate::balance_precheck`].
+    #[error("insufficient {asset}: order requires {required}, {available} available")]
+    InsufficientFunds {
+        asset: String,
+        /// Requirement after the configured safety margin.
+        required: rust_decimal::Decimal,
+        available: rust_decimal::Decimal,
+    },
+
+    /// The contract settles within the configured pre-expiry guard window
+    /// and new orders on it are rejected locally.
+    #[error("order rejected: {inst_id} settles in {remaining_secs}s")]
+    NearSettlement {
+        inst_id: String,
+        remaining_secs: i64,
+    },
+
+    /// Records referenced instruments nobody configured while the
+    /// unknown-instrument policy is `Error`; nothing was dropped — the
+    /// whole call failed instead. See
+    /// [`crate::config::UnknownInstrumentPolicy`].
+    #[error("unknown instruments in {context}: {}", instruments.join(", "))]
+    UnknownInstruments {
+        /// The call that hit them, e.g. `fetch_open_orders`.
+        context: String,
+        /// Offending instrument ids, sorted.
+        instruments: Vec<String>,
+    },
+
+    /// The WS correlation map refused or evicted the op: either too many
+    /// ops were already in flight, or the entry outlived its TTL without
+    /// an ack. Either way the connection is not keeping up.
+    #[error("ws backpressure: {0}")]
+    Backpressure(String),
+
+    /// The client-side order throttle rejected a placement (fail-fast
+    /// mode); the order never reached the exchange.
+    #[error("order throttled: {0}")]
+    Throttled(String),
+
+    /// The endpoint category's circuit is open after repeated system-busy
+    /// responses from the exchange, so the call failed fast locally; see
+    /// [`crate::circuit_breaker`]. Retriable once the cooldown elapses.
+    #[error("exchange unavailable: {0}")]
+    ExchangeUnavailable(String),
+
+    /// The response body exceeded the configured size limit and reading
+    /// was aborted partway; see
+    /// [`crate::config::OkexConfig::max_response_bytes`]. Deterministic,
+    /// so the logical call fails instead of re-requesting the same flood
+    /// from the same host — but it still counts as an endpoint failure,
+    /// so the failover rotation backs off.
+    #[error("response from {endpoint} exceeded the {limit_bytes}-byte response limit")]
+    ResponseTooLarge { endpoint: String, limit_bytes: usize },
+
+    /// A 2xx response arrived with a non-JSON content type — typically a
+    /// proxy or captive portal serving an HTML page — so parsing was
+    /// skipped outright. Counts as an endpoint failure like
+    /// [`Self::ResponseTooLarge`].
+    #[error("unexpected content type {content_type:?} from {endpoint}")]
+    UnexpectedContentType {
+        endpoint: String,
+        content_type: String,
+    },
+
+    /// An order op timed out and the recovery cancel leaves the final order
+    /// state uncertain: the order may have rested and been cancelled, or
+    /// never have reached the book.
+    #[error(
+        "ambiguous outcome for client order {client_order_id}: ack timed out; \
+         cancel {}",
+        if *.cancel_found_order { "found and removed the order" } else { "did not find the order" }
+    )]
+    AmbiguousOrderOutcome {
+        client_order_id: String,
+        cancel_found_order: bool,
+    },
+}
+
+/// Structured context behind one driver error, kept alongside the string
+/// form so automated handling does not have to parse messages. Retrievable
+/// after the fact via `last_error_details()` on the REST and WS clients;
+/// serializable so debug snapshots can dump it as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct OkexErrorDetails {
+    /// REST path or WS op name that failed.
+    pub endpoint: String,
+    /// WS correlation id; REST requests have none.
+    pub request_id: Option<String>,
+    /// OKX business code (top-level `code` or per-item `sCode`), when the
+    /// exchange got far enough to report one.
+    pub code: Option<String>,
+    /// Exchange message (`msg`/`sMsg`) or a transport description.
+    pub message: String,
+}
+
+impl OkexErrorDetails {
+    /// Compact `key=value` prefix embedded in error strings, so log
+    /// queries can filter on endpoint and code without a custom parser.
+    pub fn context(&self) -> String {
+        let mut parts = vec![format!("endpoint={}", self.endpoint)];
+        if let Some(id) = &self.request_id {
+            parts.push(format!("reqId={id}"));
+        }
+        if let Some(code) = &self.code {
+            parts.push(format!("code={code}"));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Most recent error details the ring keeps.
+const ERROR_LOG_CAPACITY: usize = 64;
+
+/// Fixed-size ring of recent [`OkexErrorDetails`], oldest first. One lives
+/// on each client as the structured side channel next to the error values
+/// themselves.
+#[derive(Default)]
+pub(crate) struct ErrorLog {
+    ring: Mutex<VecDeque<OkexErrorDetails>>,
+}
+
+impl ErrorLog {
+    pub(crate) fn record(&self, details: OkexErrorDetails) {
+        let mut ring = self.ring.lock().unwrap();
+        if ring.len() == ERROR_LOG_CAPACITY {
+            ring.pop_front();
+        }
+        ring.push_back(details);
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<OkexErrorDetails> {
+        self.ring.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,137 @@
+//! Events emitted by the driver to its consumer.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::balance_events::BalanceChangeReason;
+
+/// A single balance change on one asset, annotated with its best-effort
+/// cause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceUpdate {
+    pub asset: String,
+    /// Signed change; negative for outflows.
+    pub delta: Decimal,
+    pub reason: BalanceChangeReason,
+    pub exchange_time: DateTime<Utc>,
+}
+
+/// Driver event stream payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriverEvent {
+    BalanceUpdate(BalanceUpdate),
+    /// A WS channel subscription was rejected or never acknowledged; the
+    /// driver is blind to that channel until it recovers.
+    SubscriptionFailed {
+        channel: String,
+        inst_id: Option<String>,
+        reason: String,
+    },
+    /// A configured dated contract entered the pre-settlement guard
+    /// window; quoting on it should wind down.
+    PreSettlement {
+        inst_id: String,
+        /// `expTime`, milliseconds.
+        expiry_time: u64,
+    },
+    /// An instrument we hold orders or positions on disappeared from the
+    /// exchange listing. Its metadata is retained as a tombstone so
+    /// cleanup (cancel, fetch, close) keeps working; new orders on it are
+    /// refused. See
+    /// [`crate::instruments::InstrumentConverter::apply_refresh`].
+    InstrumentDelisted { inst_id: String },
+    /// A funding payment detected at settlement time, estimated from the
+    /// positions cache and the published rate; reconciled against the
+    /// authoritative bill when that arrives. `amount` is signed from our
+    /// side: negative means funding was paid.
+    FundingPayment {
+        inst_id: String,
+        amount: Decimal,
+        rate: Decimal,
+        exchange_time: DateTime<Utc>,
+    },
+    /// The funding bill disagreed with the earlier estimate; downstream
+    /// accounting should replace `estimated` with `actual`.
+    FundingPaymentCorrection {
+        inst_id: String,
+        estimated: Decimal,
+        actual: Decimal,
+        exchange_time: DateTime<Utc>,
+    },
+    /// The private WS connection status changed; the reason rides along so
+    /// outages can be reconstructed from the event stream alone.
+    ConnectionStatusChanged {
+        from: crate::ws::supervisor::ConnectionStatus,
+        to: crate::ws::supervisor::ConnectionStatus,
+        reason: crate::ws::supervisor::StatusChangeReason,
+        at: DateTime<Utc>,
+    },
+    /// The WS event loop died repeatedly in quick succession and the
+    /// supervisor stopped restarting it; manual intervention is required.
+    ConnectionPermanentlyFailed { reason: String },
+    /// The exchange rejected the API credentials; private calls fail fast
+    /// until a rotation succeeds. Worth paging on.
+    CredentialsInvalid { reason: String },
+    /// An endpoint category's circuit breaker changed state (see
+    /// [`crate::circuit_breaker`]); `Open` means REST calls in that
+    /// category fail fast until a probe comes back healthy.
+    CircuitBreakerStateChanged {
+        category: String,
+        from: crate::circuit_breaker::CircuitState,
+        to: crate::circuit_breaker::CircuitState,
+    },
+    /// One public trade on a tracked instrument (see
+    /// [`crate::market_trades`]); size already in base units.
+    MarketTrade {
+        inst_id: String,
+        trade: crate::market_trades::MarketTrade,
+    },
+    /// An exchange-hosted algo order changed state (see
+    /// [`crate::algo_orders`]); `order_id` carries the regular order the
+    /// activation placed, once there is one.
+    AlgoOrderUpdate {
+        algo_id: String,
+        inst_id: String,
+        /// OKX algo state string: `live`, `effective`, `canceled`,
+        /// `order_failed`.
+        state: String,
+        order_id: Option<String>,
+    },
+    /// A WS channel's p99 processing lag crossed the configured threshold
+    /// ([`crate::config::OkexConfig::ws_lag_threshold`]): the event loop
+    /// is falling behind the socket and updates on that channel are being
+    /// acted on late. Emitted once per excursion; see [`crate::ws::lag`].
+    WsLagDegraded {
+        channel: String,
+        p99: std::time::Duration,
+        threshold: std::time::Duration,
+    },
+    /// The stale-order reaper cancelled an order resting past the
+    /// configured TTL (see [`crate::stale_orders`]); `age` is how long it
+    /// had been resting by the exchange's own creation time.
+    StaleOrderCancelled {
+        inst_id: String,
+        order_id: String,
+        client_order_id: Option<String>,
+        age: std::time::Duration,
+    },
+    /// A fill produced by the dry-run simulator (see [`crate::fill_sim`]);
+    /// never emitted outside dry-run mode.
+    SimulatedFill(Box<crate::trades::RawTrade>),
+    /// A dry-run simulated order changed state, mirroring the transitions
+    /// a real order-update stream would carry.
+    SimulatedOrderUpdate {
+        order_id: String,
+        client_order_id: Option<String>,
+        inst_id: String,
+        /// OKX order state string: `partially_filled` or `filled`.
+        state: String,
+        /// Remaining unfilled size, base units.
+        remaining: Decimal,
+    },
+}
+
+/// Sender half of the driver event stream.
+pub type DriverEventSender = tokio::sync::mpsc::UnboundedSender<DriverEvent>;
+/// Receiver half handed to the driver consumer.
+pub type DriverEventReceiver = tokio::sync::mpsc::UnboundedReceiver<DriverEvent>;
@@ -0,0 +1,485 @@
+//! Per-pair execution quality statistics.
+//!
+//! Strategy tuning wants to know, per instrument, how orders actually
+//! fare: how many get placed, filled, cancelled or rejected, how long the
+//! first fill takes, and how much of the flow ends up making versus
+//! taking. Downstream services compute this today from partial data;
+//! [`ExecutionStatsTracker`] computes it at the driver, fed by the same
+//! order event flow the driver already sees — `orders`-channel updates,
+//! normalized fills, and per-item placement results. All latencies use
+//! exchange timestamps (first `live` push to first fill push), never the
+//! local clock.
+//!
+//! Memory is bounded: each pair keeps a drop-oldest ring of samples, so a
+//! busy pair ages out its own history instead of growing without limit.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::{OkexOrderOpResult, OkexOrderUpdate};
+use crate::trades::RawTrade;
+
+/// Typed classification of placement-reject `sCode`s, so dashboards can
+/// bucket rejections without string-matching exchange messages.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum RejectReason {
+    /// `51008`.
+    InsufficientBalance,
+    /// `51016`.
+    DuplicateClientOrderId,
+    /// `51006`: price outside the allowed band.
+    PriceOutOfRange,
+    /// `50011`.
+    RateLimited,
+    /// `51400`/`51401`/`51503`; mostly seen on cancels that raced a fill.
+    OrderNotFound,
+    /// Anything else, carrying the verbatim code.
+    Other(String),
+}
+
+impl RejectReason {
+    pub fn from_s_code(s_code: &str) -> Self {
+        match s_code {
+            "51008" => RejectReason::InsufficientBalance,
+            "51016" => RejectReason::DuplicateClientOrderId,
+            "51006" => RejectReason::PriceOutOfRange,
+            "50011" => RejectReason::RateLimited,
+            "51400" | "51401" | "51503" => RejectReason::OrderNotFound,
+            other => RejectReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Computed stats for one pair over one window; see
+/// [`ExecutionStatsTracker::execution_stats`]. Dumps cleanly as JSON for
+/// the debug snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ExecutionStats {
+    pub placed: u64,
+    pub filled: u64,
+    pub cancelled: u64,
+    pub rejected: u64,
+    /// Exchange-time milliseconds from the first `live` push to the first
+    /// fill; `None` until a first fill falls inside the window.
+    pub median_time_to_first_fill_ms: Option<u64>,
+    pub p95_time_to_first_fill_ms: Option<u64>,
+    pub maker_fills: u64,
+    pub taker_fills: u64,
+    /// `maker / (maker + taker)`; `None` when no attributed fills landed
+    /// in the window.
+    pub maker_fill_ratio: Option<Decimal>,
+    /// Reject histogram, most frequent first (ties by reason order of
+    /// first appearance).
+    pub reject_reasons: Vec<(RejectReason, u64)>,
+}
+
+/// One dated observation in a pair's ring.
+struct Sample {
+    /// Exchange time, milliseconds (local time only for REST rejects,
+    /// which carry no exchange timestamp).
+    ts: u64,
+    kind: SampleKind,
+}
+
+enum SampleKind {
+    Placed,
+    Filled,
+    Cancelled,
+    Rejected(RejectReason),
+    FirstFill { latency_ms: u64 },
+    Fill { maker: bool },
+}
+
+/// Default per-pair ring bound.
+const RING_CAPACITY: usize = 1024;
+/// Orders awaiting their first fill; beyond this the oldest is forgotten.
+const MAX_PENDING: usize = 4096;
+
+struct PendingOrder {
+    /// `uTime` of the first `live` push.
+    placed_ms: u64,
+    first_fill_seen: bool,
+}
+
+struct TrackerState {
+    rings: HashMap<String, VecDeque<Sample>>,
+    pending: HashMap<String, PendingOrder>,
+    /// Insertion order of `pending`, for bounded eviction.
+    pending_order: VecDeque<String>,
+}
+
+/// Collects execution stats from the order event flow; see the module
+/// docs. Optional: nothing in the driver requires one to be attached.
+pub struct ExecutionStatsTracker {
+    ring_capacity: usize,
+    state: Mutex<TrackerState>,
+}
+
+impl Default for ExecutionStatsTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExecutionStatsTracker {
+    pub fn new() -> Self {
+        Self::with_ring_capacity(RING_CAPACITY)
+    }
+
+    pub fn with_ring_capacity(ring_capacity: usize) -> Self {
+        Self {
+            ring_capacity,
+            state: Mutex::new(TrackerState {
+                rings: HashMap::new(),
+                pending: HashMap::new(),
+                pending_order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Feed one typed `orders`-channel update. The first push for an
+    /// unseen order counts as its placement; the first push with any
+    /// accumulated fill dates the time-to-first-fill; terminal states
+    /// close the books on the order.
+    pub fn on_order_update(&self, update: &OkexOrderUpdate) {
+        let Ok(ts) = update.updated_at.parse::<u64>() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if !state.pending.contains_key(&update.order_id) {
+            push_sample(
+                &mut state,
+                self.ring_capacity,
+                &update.inst_id,
+                Sample {
+                    ts,
+                    kind: SampleKind::Placed,
+                },
+            );
+            state.pending.insert(
+                update.order_id.clone(),
+                PendingOrder {
+                    placed_ms: ts,
+                    first_fill_seen: false,
+                },
+            );
+            state.pending_order.push_back(update.order_id.clone());
+            while state.pending_order.len() > MAX_PENDING {
+                if let Some(evicted) = state.pending_order.pop_front() {
+                    state.pending.remove(&evicted);
+                }
+            }
+        }
+        let filled_something = update
+            .accumulated_fill_size
+            .is_some_and(|size| size > Decimal::ZERO);
+        if filled_something {
+            let first_fill = state.pending.get_mut(&update.order_id).and_then(|pending| {
+                (!pending.first_fill_seen).then(|| {
+                    pending.first_fill_seen = true;
+                    ts.saturating_sub(pending.placed_ms)
+                })
+            });
+            if let Some(latency_ms) = first_fill {
+                push_sample(
+                    &mut state,
+                    self.ring_capacity,
+                    &update.inst_id,
+                    Sample {
+                        ts,
+                        kind: SampleKind::FirstFill { latency_ms },
+                    },
+                );
+            }
+        }
+        let terminal = match update.state.as_str() {
+            "filled" => Some(SampleKind::Filled),
+            "canceled" => Some(SampleKind::Cancelled),
+            _ => None,
+        };
+        if let Some(kind) = terminal {
+            push_sample(
+                &mut state,
+                self.ring_capacity,
+                &update.inst_id,
+                Sample { ts, kind },
+            );
+            if state.pending.remove(&update.order_id).is_some() {
+                state.pending_order.retain(|id| id != &update.order_id);
+            }
+        }
+    }
+
+    /// Feed one normalized fill for the maker/taker ratio; fills whose
+    /// source record does not attribute liquidity are skipped.
+    pub fn on_fill(&self, fill: &RawTrade) {
+        let maker = match fill.liquidity.as_deref() {
+            Some("M") => true,
+            Some("T") => false,
+            _ => return,
+        };
+        let Ok(ts) = fill.timestamp.parse::<u64>() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        push_sample(
+            &mut state,
+            self.ring_capacity,
+            &fill.inst_id.clone(),
+            Sample {
+                ts,
+                kind: SampleKind::Fill { maker },
+            },
+        );
+    }
+
+    /// Feed one per-item placement result. Successes are ignored — the
+    /// `live` push counts the placement — so only rejects land here.
+    /// `ts_ms` is the caller's clock: a reject carries no exchange time.
+    pub fn record_placement_result(&self, pair: &str, result: &OkexOrderOpResult, ts_ms: u64) {
+        if result.s_code == "0" {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        push_sample(
+            &mut state,
+            self.ring_capacity,
+            pair,
+            Sample {
+                ts: ts_ms,
+                kind: SampleKind::Rejected(RejectReason::from_s_code(&result.s_code)),
+            },
+        );
+    }
+
+    /// Stats for one pair over the trailing `window`; an unseen pair
+    /// yields all-zero stats.
+    pub fn execution_stats(&self, pair: &str, window: Duration) -> ExecutionStats {
+        self.execution_stats_at(pair, window, chrono::Utc::now().timestamp_millis() as u64)
+    }
+
+    /// Stats per pair over the trailing `window`, for the debug snapshot.
+    pub fn stats_by_pair(&self, window: Duration) -> BTreeMap<String, ExecutionStats> {
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let pairs: Vec<String> = self.state.lock().unwrap().rings.keys().cloned().collect();
+        pairs
+            .into_iter()
+            .map(|pair| {
+                let stats = self.execution_stats_at(&pair, window, now_ms);
+                (pair, stats)
+            })
+            .collect()
+    }
+
+    fn execution_stats_at(&self, pair: &str, window: Duration, now_ms: u64) -> ExecutionStats {
+        let cutoff = now_ms.saturating_sub(window.as_millis() as u64);
+        let state = self.state.lock().unwrap();
+        let Some(ring) = state.rings.get(pair) else {
+            return ExecutionStats::default();
+        };
+        let mut stats = ExecutionStats::default();
+        let mut latencies = Vec::new();
+        let mut rejects: Vec<(RejectReason, u64)> = Vec::new();
+        for sample in ring.iter().filter(|sample| sample.ts >= cutoff) {
+            match &sample.kind {
+                SampleKind::Placed => stats.placed += 1,
+                SampleKind::Filled => stats.filled += 1,
+                SampleKind::Cancelled => stats.cancelled += 1,
+                SampleKind::Rejected(reason) => {
+                    stats.rejected += 1;
+                    match rejects.iter_mut().find(|(seen, _)| seen == reason) {
+                        Some((_, count)) => *count += 1,
+                        None => rejects.push((reason.clone(), 1)),
+                    }
+                }
+                SampleKind::FirstFill { latency_ms } => latencies.push(*latency_ms),
+                SampleKind::Fill { maker } => {
+                    if *maker {
+                        stats.maker_fills += 1;
+                    } else {
+                        stats.taker_fills += 1;
+                    }
+                }
+            }
+        }
+        latencies.sort_unstable();
+        stats.median_time_to_first_fill_ms = percentile(&latencies, 50);
+        stats.p95_time_to_first_fill_ms = percentile(&latencies, 95);
+        let attributed = stats.maker_fills + stats.taker_fills;
+        if attributed > 0 {
+            stats.maker_fill_ratio =
+                Some(Decimal::from(stats.maker_fills) / Decimal::from(attributed));
+        }
+        rejects.sort_by(|(_, a), (_, b)| b.cmp(a));
+        stats.reject_reasons = rejects;
+        stats
+    }
+}
+
+fn push_sample(state: &mut TrackerState, capacity: usize, pair: &str, sample: Sample) {
+    let ring = state.rings.entry(pair.to_string()).or_default();
+    if ring.len() >= capacity {
+        ring.pop_front();
+    }
+    ring.push_back(sample);
+}
+
+/// Nearest-rank percentile of an ascending-sorted slice.
+pub(crate) fn percentile(sorted: &[u64], pct: u64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (sorted.len() as u64 * pct).div_ceil(100).max(1) as usize;
+    Some(sorted[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(order_id: &str, state: &str, acc_fill: Option<&str>, ts: u64) -> OkexOrderUpdate {
+        OkexOrderUpdate {
+            inst_id: "BTC-USDT".to_string(),
+            order_id: order_id.to_string(),
+            client_order_id: None,
+            order_type: "limit".to_string(),
+            state: state.to_string(),
+            price: Some("43000".parse().unwrap()),
+            average_price: None,
+            size: "1".parse().unwrap(),
+            accumulated_fill_size: acc_fill.map(|size| size.parse().unwrap()),
+            target_currency: String::new(),
+            side: "buy".to_string(),
+            updated_at: ts.to_string(),
+        }
+    }
+
+    fn fill(liquidity: &str, ts: u64) -> RawTrade {
+        RawTrade {
+            inst_id: "BTC-USDT".to_string(),
+            trade_id: format!("t{ts}"),
+            order_id: "ord1".to_string(),
+            client_order_id: None,
+            level_id: None,
+            price: Some("43000".parse().unwrap()),
+            amount: "0.5".parse().unwrap(),
+            side: "buy".to_string(),
+            liquidity: Some(liquidity.to_string()),
+            fee: None,
+            fee_currency: None,
+            realized_pnl: None,
+            timestamp: ts.to_string(),
+            exchange_timestamp: None,
+            internal_created_at: chrono::DateTime::from_timestamp_millis(ts as i64).unwrap(),
+        }
+    }
+
+    fn reject(s_code: &str) -> OkexOrderOpResult {
+        OkexOrderOpResult {
+            order_id: String::new(),
+            client_order_id: None,
+            s_code: s_code.to_string(),
+            s_msg: "rejected".to_string(),
+        }
+    }
+
+    #[test]
+    fn a_scripted_lifecycle_produces_the_expected_stats() {
+        let tracker = ExecutionStatsTracker::new();
+
+        // ord1: live at t=1s, first partial fill 250ms later, filled at
+        // t=1.4s with one maker and one taker execution.
+        tracker.on_order_update(&update("ord1", "live", None, 1_000));
+        tracker.on_order_update(&update("ord1", "partially_filled", Some("0.5"), 1_250));
+        tracker.on_order_update(&update("ord1", "filled", Some("1"), 1_400));
+        tracker.on_fill(&fill("M", 1_250));
+        tracker.on_fill(&fill("T", 1_400));
+        // ord2 never fills and gets pulled.
+        tracker.on_order_update(&update("ord2", "live", None, 2_000));
+        tracker.on_order_update(&update("ord2", "canceled", None, 2_500));
+        // One placement bounced for balance before reaching the book.
+        tracker.record_placement_result("BTC-USDT", &reject("51008"), 2_600);
+
+        let stats =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(10), 3_000);
+        assert_eq!(stats.placed, 2);
+        assert_eq!(stats.filled, 1);
+        assert_eq!(stats.cancelled, 1);
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.median_time_to_first_fill_ms, Some(250));
+        assert_eq!(stats.p95_time_to_first_fill_ms, Some(250));
+        assert_eq!(stats.maker_fills, 1);
+        assert_eq!(stats.taker_fills, 1);
+        assert_eq!(stats.maker_fill_ratio, Some("0.5".parse().unwrap()));
+        assert_eq!(
+            stats.reject_reasons,
+            vec![(RejectReason::InsufficientBalance, 1)]
+        );
+    }
+
+    #[test]
+    fn the_window_excludes_older_samples() {
+        let tracker = ExecutionStatsTracker::new();
+        tracker.on_order_update(&update("ord1", "live", None, 1_000));
+        tracker.on_order_update(&update("ord1", "filled", Some("1"), 1_500));
+        tracker.on_order_update(&update("ord2", "live", None, 60_000));
+
+        let stats =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(10), 61_000);
+        assert_eq!(stats.placed, 1);
+        assert_eq!(stats.filled, 0);
+        assert_eq!(stats.median_time_to_first_fill_ms, None);
+        // The full history is still there for a wider window.
+        let wide =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(120), 61_000);
+        assert_eq!(wide.placed, 2);
+        assert_eq!(wide.filled, 1);
+    }
+
+    #[test]
+    fn the_ring_drops_the_oldest_samples_per_pair() {
+        let tracker = ExecutionStatsTracker::with_ring_capacity(3);
+        for (i, ts) in (0..4).map(|i| (i, 1_000 + i)) {
+            tracker.on_order_update(&update(&format!("ord{i}"), "live", None, ts));
+        }
+
+        // Four placements through a three-deep ring: the first aged out.
+        let stats =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(10), 2_000);
+        assert_eq!(stats.placed, 3);
+    }
+
+    #[test]
+    fn an_immediately_filled_order_reports_a_zero_latency() {
+        let tracker = ExecutionStatsTracker::new();
+        // A market order's first push can already be terminal.
+        tracker.on_order_update(&update("ord1", "filled", Some("1"), 5_000));
+
+        let stats =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(10), 6_000);
+        assert_eq!(stats.placed, 1);
+        assert_eq!(stats.filled, 1);
+        assert_eq!(stats.median_time_to_first_fill_ms, Some(0));
+    }
+
+    #[test]
+    fn unclassified_reject_codes_keep_their_verbatim_code() {
+        let tracker = ExecutionStatsTracker::new();
+        tracker.record_placement_result("BTC-USDT", &reject("51099"), 1_000);
+        tracker.record_placement_result("BTC-USDT", &reject("51099"), 1_100);
+        tracker.record_placement_result("BTC-USDT", &reject("51006"), 1_200);
+
+        let stats =
+            tracker.execution_stats_at("BTC-USDT", Duration::from_secs(10), 2_000);
+        assert_eq!(
+            stats.reject_reasons,
+            vec![
+                (RejectReason::Other("51099".to_string()), 2),
+                (RejectReason::PriceOutOfRange, 1),
+            ]
+        );
+    }
+}
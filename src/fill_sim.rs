@@ -0,0 +1,407 @@
+//! Dry-run fill simulation against observed market data.
+//!
+//! Dry-run mode acks orders without sending them, so a strategy sees its
+//! placements succeed but never gets a fill — it cannot be evaluated end
+//! to end. The simulator closes that gap: synthetic resting orders are
+//! matched against the top of observed book snapshots, and a touched or
+//! crossed price produces a synthetic [`RawTrade`] plus an order-state
+//! transition on the normal [`crate::events`] stream. Time comes from the
+//! book timestamps, never the wall clock, so replaying a recorded
+//! market-data sequence reproduces the exact same fills.
+//!
+//! The model is deliberately simple: a resting buy fills at its own price
+//! once the best ask trades at or through it (mirrored for sells), with no
+//! queue-position modeling. Partial-fill mode caps each order's fill at
+//! the displayed top-of-book size — independently per order, the displayed
+//! liquidity is not consumed across orders. Orders without a price (market
+//! orders) are acked by dry-run as before but do not rest here.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rust_decimal::Decimal;
+
+use crate::events::{DriverEvent, DriverEventSender};
+use crate::instruments::Instrument;
+use crate::order_book::OrderBook;
+use crate::orders::{OkexOrderParams, Side};
+use crate::trades::RawTrade;
+
+/// Tuning knobs of the simulator.
+#[derive(Debug, Clone, Copy)]
+pub struct FillSimConfig {
+    /// Simulated placement latency: an order only matches against book
+    /// snapshots at least this much later (by book timestamp) than the
+    /// snapshot clock at placement time.
+    pub latency: std::time::Duration,
+    /// Cap each fill at the displayed top-of-book size, leaving the rest
+    /// of the order resting; `false` fills the whole order on first touch.
+    pub partial_fills: bool,
+}
+
+impl Default for FillSimConfig {
+    fn default() -> Self {
+        Self {
+            latency: std::time::Duration::ZERO,
+            partial_fills: false,
+        }
+    }
+}
+
+/// One synthetic resting order, as [`FillSimulator::simulated_open_orders`]
+/// reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedOrder {
+    /// The dry-run order id the placement was acked with.
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub inst_id: String,
+    pub side: Side,
+    /// Limit price; fills execute exactly here.
+    pub price: Decimal,
+    /// Remaining unfilled size, base units.
+    pub remaining: Decimal,
+    /// Simulated clock (latest book timestamp of the instrument,
+    /// milliseconds) when the order was placed; `0` before any data.
+    pub placed_at: u64,
+}
+
+struct SimState {
+    /// Latest observed book timestamp per instrument — the simulated clock
+    /// placements are stamped with.
+    clock: HashMap<String, u64>,
+    open: Vec<SimulatedOrder>,
+    next_trade: u64,
+}
+
+/// Matches dry-run orders against observed books; see the module docs for
+/// the model. Shared between the driver's dry-run book and whatever task
+/// consumes the public market-data stream.
+pub struct FillSimulator {
+    config: FillSimConfig,
+    events: DriverEventSender,
+    state: Mutex<SimState>,
+}
+
+impl FillSimulator {
+    pub fn new(config: FillSimConfig, events: DriverEventSender) -> Self {
+        Self {
+            config,
+            events,
+            state: Mutex::new(SimState {
+                clock: HashMap::new(),
+                open: Vec::new(),
+                next_trade: 1,
+            }),
+        }
+    }
+
+    /// Rest a placed dry-run order. Orders without a price do not rest —
+    /// the simulator only models limit orders.
+    pub fn place(&self, params: &OkexOrderParams, order_id: &str, instrument: &Instrument) {
+        let Some(price) = params.px.as_deref().and_then(|px| px.parse().ok()) else {
+            return;
+        };
+        let Ok(size) = params.sz.parse::<Decimal>() else {
+            return;
+        };
+        let contract_value = instrument.contract_value.unwrap_or(Decimal::ONE);
+        let mut state = self.state.lock().unwrap();
+        let placed_at = state.clock.get(&params.inst_id).copied().unwrap_or(0);
+        state.open.push(SimulatedOrder {
+            order_id: order_id.to_string(),
+            client_order_id: params.cl_ord_id.clone(),
+            inst_id: params.inst_id.clone(),
+            side: params.side,
+            price,
+            remaining: size * contract_value,
+            placed_at,
+        });
+    }
+
+    /// Drop a resting order; `false` when it was not resting (already
+    /// filled, or never priced).
+    pub fn cancel(&self, order_id: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let before = state.open.len();
+        state.open.retain(|order| order.order_id != order_id);
+        state.open.len() != before
+    }
+
+    /// Drop every resting order (the dry-run `cancel_all`).
+    pub fn cancel_all(&self) {
+        self.state.lock().unwrap().open.clear();
+    }
+
+    /// The currently resting synthetic orders.
+    pub fn simulated_open_orders(&self) -> Vec<SimulatedOrder> {
+        self.state.lock().unwrap().open.clone()
+    }
+
+    /// Feed one observed book snapshot: advances the instrument's
+    /// simulated clock and fills every eligible resting order the top of
+    /// this book touches or crosses.
+    pub fn on_book(&self, book: &OrderBook) {
+        let ts = book.timestamp.parse::<u64>().unwrap_or(0);
+        let latency_ms = self.config.latency.as_millis() as u64;
+        let mut fills = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let clock = state.clock.entry(book.inst_id.clone()).or_insert(0);
+            *clock = (*clock).max(ts);
+
+            for order in &mut state.open {
+                if order.inst_id != book.inst_id
+                    || ts < order.placed_at.saturating_add(latency_ms)
+                {
+                    continue;
+                }
+                let touched = match order.side {
+                    Side::Buy => book.asks.first().filter(|ask| ask.price <= order.price),
+                    Side::Sell => book.bids.first().filter(|bid| bid.price >= order.price),
+                };
+                let Some(level) = touched else { continue };
+                let filled = if self.config.partial_fills {
+                    order.remaining.min(level.size)
+                } else {
+                    order.remaining
+                };
+                if filled <= Decimal::ZERO {
+                    continue;
+                }
+                order.remaining -= filled;
+                fills.push((order.clone(), filled));
+            }
+            state.open.retain(|order| !order.remaining.is_zero());
+        }
+
+        for (order, filled) in fills {
+            self.emit_fill(&order, filled, book);
+        }
+    }
+
+    fn emit_fill(&self, order: &SimulatedOrder, filled: Decimal, book: &OrderBook) {
+        let trade_id = {
+            let mut state = self.state.lock().unwrap();
+            let n = state.next_trade;
+            state.next_trade += 1;
+            format!("sim-{n}")
+        };
+        let trade = RawTrade {
+            inst_id: order.inst_id.clone(),
+            trade_id,
+            order_id: order.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            level_id: crate::trades::decode_level_id(order.client_order_id.as_deref()),
+            price: Some(order.price),
+            amount: filled,
+            side: match order.side {
+                Side::Buy => "buy".to_string(),
+                Side::Sell => "sell".to_string(),
+            },
+            // A resting order filled on a crossing is maker by definition.
+            liquidity: Some("M".to_string()),
+            fee: None,
+            fee_currency: None,
+            realized_pnl: None,
+            timestamp: book.timestamp.clone(),
+            exchange_timestamp: book.exchange_timestamp,
+            // Synthetic fills are "received" the moment they are made.
+            internal_created_at: chrono::Utc::now(),
+        };
+        let _ = self.events.send(DriverEvent::SimulatedFill(Box::new(trade)));
+        let _ = self.events.send(DriverEvent::SimulatedOrderUpdate {
+            order_id: order.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            inst_id: order.inst_id.clone(),
+            state: if order.remaining.is_zero() {
+                "filled".to_string()
+            } else {
+                "partially_filled".to_string()
+            },
+            remaining: order.remaining,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_book::BookLevel;
+
+    fn instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn limit_buy(price: &str, size: &str) -> OkexOrderParams {
+        OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: crate::orders::TradeMode::Cash,
+            side: Side::Buy,
+            ord_type: crate::orders::OrderType::Limit,
+            px: Some(price.to_string()),
+            sz: size.to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: Some("clord1".to_string()),
+        }
+    }
+
+    fn book(ts: u64, best_bid: (&str, &str), best_ask: (&str, &str)) -> OrderBook {
+        let level = |(price, size): (&str, &str)| BookLevel {
+            price: price.parse().unwrap(),
+            size: size.parse().unwrap(),
+            order_count: None,
+        };
+        OrderBook {
+            inst_id: "BTC-USDT".to_string(),
+            bids: vec![level(best_bid)],
+            asks: vec![level(best_ask)],
+            timestamp: ts.to_string(),
+            exchange_timestamp: chrono::DateTime::from_timestamp_millis(ts as i64),
+        }
+    }
+
+    fn simulator(config: FillSimConfig) -> (FillSimulator, crate::events::DriverEventReceiver) {
+        let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+        (FillSimulator::new(config, events_tx), events_rx)
+    }
+
+    /// Drain and return all events the simulator emitted so far.
+    fn drain(events_rx: &mut crate::events::DriverEventReceiver) -> Vec<DriverEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = events_rx.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn a_scripted_book_replay_produces_the_known_fill() {
+        let (sim, mut events_rx) = simulator(FillSimConfig::default());
+        sim.place(&limit_buy("100", "1"), "dry-run-1", &instrument());
+
+        // Best ask above the bid: nothing happens.
+        sim.on_book(&book(1_000, ("99", "2"), ("101", "2")));
+        assert!(drain(&mut events_rx).is_empty());
+        assert_eq!(sim.simulated_open_orders().len(), 1);
+
+        // The ask touches the order price: full fill at the order price.
+        sim.on_book(&book(2_000, ("99", "2"), ("100", "2")));
+        let events = drain(&mut events_rx);
+        assert_eq!(events.len(), 2, "{events:?}");
+        match &events[0] {
+            DriverEvent::SimulatedFill(trade) => {
+                assert_eq!(trade.trade_id, "sim-1");
+                assert_eq!(trade.order_id, "dry-run-1");
+                assert_eq!(trade.price, Some("100".parse().unwrap()));
+                assert_eq!(trade.amount, Decimal::ONE);
+                assert_eq!(trade.side, "buy");
+                assert_eq!(trade.liquidity.as_deref(), Some("M"));
+                assert_eq!(trade.timestamp, "2000");
+            }
+            other => panic!("expected a fill, got {other:?}"),
+        }
+        assert!(matches!(
+            &events[1],
+            DriverEvent::SimulatedOrderUpdate { state, remaining, .. }
+                if state == "filled" && remaining.is_zero()
+        ));
+        assert!(sim.simulated_open_orders().is_empty());
+
+        // Replaying the same book again finds nothing resting.
+        sim.on_book(&book(3_000, ("99", "2"), ("100", "2")));
+        assert!(drain(&mut events_rx).is_empty());
+    }
+
+    #[test]
+    fn partial_fills_are_capped_at_the_displayed_size() {
+        let (sim, mut events_rx) = simulator(FillSimConfig {
+            partial_fills: true,
+            ..FillSimConfig::default()
+        });
+        sim.place(&limit_buy("100", "1"), "dry-run-1", &instrument());
+
+        // Only 0.4 displayed at the touch: the order rests partially filled.
+        sim.on_book(&book(1_000, ("99", "2"), ("100", "0.4")));
+        let events = drain(&mut events_rx);
+        assert!(matches!(
+            &events[0],
+            DriverEvent::SimulatedFill(trade) if trade.amount == "0.4".parse().unwrap()
+        ));
+        assert!(matches!(
+            &events[1],
+            DriverEvent::SimulatedOrderUpdate { state, remaining, .. }
+                if state == "partially_filled" && *remaining == "0.6".parse().unwrap()
+        ));
+        assert_eq!(
+            sim.simulated_open_orders()[0].remaining,
+            "0.6".parse().unwrap()
+        );
+
+        // Enough size on the next crossing: the remainder fills.
+        sim.on_book(&book(2_000, ("99", "2"), ("99.9", "5")));
+        let events = drain(&mut events_rx);
+        assert!(matches!(
+            &events[0],
+            DriverEvent::SimulatedFill(trade) if trade.amount == "0.6".parse().unwrap()
+        ));
+        assert!(sim.simulated_open_orders().is_empty());
+    }
+
+    #[test]
+    fn latency_holds_an_order_out_of_the_market() {
+        let (sim, mut events_rx) = simulator(FillSimConfig {
+            latency: std::time::Duration::from_millis(5_000),
+            ..FillSimConfig::default()
+        });
+        // The clock is at 1000 when the order is placed.
+        sim.on_book(&book(1_000, ("99", "2"), ("101", "2")));
+        sim.place(&limit_buy("100", "1"), "dry-run-1", &instrument());
+
+        // Crossing before placed_at + latency: the order is not live yet.
+        sim.on_book(&book(2_000, ("99", "2"), ("100", "2")));
+        assert!(drain(&mut events_rx).is_empty());
+
+        sim.on_book(&book(6_000, ("99", "2"), ("100", "2")));
+        assert!(matches!(
+            drain(&mut events_rx).first(),
+            Some(DriverEvent::SimulatedFill(_))
+        ));
+    }
+
+    #[test]
+    fn a_cancelled_order_never_fills_and_sells_mirror_buys() {
+        let (sim, mut events_rx) = simulator(FillSimConfig::default());
+        sim.place(&limit_buy("100", "1"), "dry-run-1", &instrument());
+        assert!(sim.cancel("dry-run-1"));
+        assert!(!sim.cancel("dry-run-1"), "already gone");
+        sim.on_book(&book(1_000, ("99", "2"), ("100", "2")));
+        assert!(drain(&mut events_rx).is_empty());
+
+        let sell = OkexOrderParams {
+            side: Side::Sell,
+            px: Some("102".to_string()),
+            ..limit_buy("102", "1")
+        };
+        sim.place(&sell, "dry-run-2", &instrument());
+        // The bid reaches through the offer: fill at the order price.
+        sim.on_book(&book(2_000, ("103", "2"), ("104", "2")));
+        let events = drain(&mut events_rx);
+        assert!(matches!(
+            &events[0],
+            DriverEvent::SimulatedFill(trade)
+                if trade.side == "sell" && trade.price == Some("102".parse().unwrap())
+        ));
+    }
+}
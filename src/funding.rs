@@ -0,0 +1,247 @@
+//! Real-time funding payment detection.
+//!
+//! The authoritative funding record is the bill (`type` 8), but bills can
+//! trail the actual settlement by hours. The `balance_and_position` channel
+//! reflects the payment as a balance change at the funding timestamp, so
+//! [`FundingEstimator`] combines the published funding schedule
+//! (`fundingTime` from `/public/funding-rate`) with the positions cache to
+//! emit an estimated [`crate::events::DriverEvent::FundingPayment`] within
+//! seconds of settlement. When the bill eventually arrives it is reconciled
+//! against the estimate and a correction event is emitted if they differ.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::events::{DriverEvent, DriverEventSender};
+
+/// How far a balance change may land from the scheduled `fundingTime` and
+/// still be taken as that funding settlement.
+const FUNDING_DETECTION_WINDOW: chrono::Duration = chrono::Duration::seconds(60);
+
+/// Funding state of one perpetual position.
+struct InstrumentFunding {
+    /// Currency the funding payment settles in.
+    settlement_asset: String,
+    /// Position notional in the settlement currency, signed: positive for
+    /// long, negative for short.
+    signed_notional: Decimal,
+    /// Rate published for the upcoming settlement; consumed on detection.
+    rate: Option<Decimal>,
+    /// Scheduled settlement time of that rate.
+    funding_time: Option<DateTime<Utc>>,
+    /// Estimate already emitted, awaiting its authoritative bill.
+    pending_estimate: Option<Decimal>,
+}
+
+/// Estimates funding payments at settlement time and reconciles them
+/// against the bills that confirm them later.
+///
+/// Like [`crate::balance_events::BalanceReasonMatcher`], every input is fed
+/// explicitly by the event loop so tests stay deterministic.
+pub struct FundingEstimator {
+    state: HashMap<String, InstrumentFunding>,
+    out: DriverEventSender,
+}
+
+impl FundingEstimator {
+    pub fn new(out: DriverEventSender) -> Self {
+        Self {
+            state: HashMap::new(),
+            out,
+        }
+    }
+
+    /// Update the cached position of one perpetual. `signed_notional` is
+    /// the position notional in `settlement_asset`, negative for shorts; a
+    /// zero notional stops estimation without forgetting the schedule.
+    pub fn on_position(
+        &mut self,
+        inst_id: &str,
+        settlement_asset: &str,
+        signed_notional: Decimal,
+    ) {
+        let entry = self
+            .state
+            .entry(inst_id.to_string())
+            .or_insert_with(|| InstrumentFunding {
+                settlement_asset: settlement_asset.to_string(),
+                signed_notional,
+                rate: None,
+                funding_time: None,
+                pending_estimate: None,
+            });
+        entry.settlement_asset = settlement_asset.to_string();
+        entry.signed_notional = signed_notional;
+    }
+
+    /// Record the published rate and settlement time for the upcoming
+    /// funding cycle of one perpetual.
+    pub fn on_funding_rate(
+        &mut self,
+        inst_id: &str,
+        rate: Decimal,
+        funding_time: DateTime<Utc>,
+    ) {
+        if let Some(entry) = self.state.get_mut(inst_id) {
+            entry.rate = Some(rate);
+            entry.funding_time = Some(funding_time);
+        }
+    }
+
+    /// Feed one balance change from the account channel. A change on a
+    /// settlement asset landing within [`FUNDING_DETECTION_WINDOW`] of a
+    /// scheduled `fundingTime` is taken as that settlement: the estimated
+    /// payment (position notional x rate, longs pay a positive rate) is
+    /// emitted and parked for reconciliation, and the schedule slot is
+    /// consumed so one settlement cannot fire twice.
+    pub fn on_balance_delta(&mut self, asset: &str, exchange_time: DateTime<Utc>) {
+        for (inst_id, entry) in &mut self.state {
+            if entry.settlement_asset != asset || entry.signed_notional.is_zero() {
+                continue;
+            }
+            let (Some(rate), Some(funding_time)) = (entry.rate, entry.funding_time) else {
+                continue;
+            };
+            if (exchange_time - funding_time).abs() > FUNDING_DETECTION_WINDOW {
+                continue;
+            }
+            // Longs pay when the rate is positive, shorts receive; the
+            // emitted amount is signed from our side (negative = paid).
+            let amount = -entry.signed_notional * rate;
+            entry.rate = None;
+            entry.funding_time = None;
+            entry.pending_estimate = Some(amount);
+            let _ = self.out.send(DriverEvent::FundingPayment {
+                inst_id: inst_id.clone(),
+                amount,
+                rate,
+                exchange_time: funding_time,
+            });
+        }
+    }
+
+    /// Reconcile the authoritative funding bill against the emitted
+    /// estimate. A differing amount produces a correction event so
+    /// downstream accounting can amend the earlier figure; a matching one
+    /// just clears the pending estimate.
+    pub fn on_funding_bill(
+        &mut self,
+        inst_id: &str,
+        actual: Decimal,
+        exchange_time: DateTime<Utc>,
+    ) {
+        let Some(entry) = self.state.get_mut(inst_id) else {
+            return;
+        };
+        let Some(estimated) = entry.pending_estimate.take() else {
+            return;
+        };
+        if estimated != actual {
+            let _ = self.out.send(DriverEvent::FundingPaymentCorrection {
+                inst_id: inst_id.to_string(),
+                estimated,
+                actual,
+                exchange_time,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DriverEventReceiver;
+
+    fn setup() -> (FundingEstimator, DriverEventReceiver) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (FundingEstimator::new(tx), rx)
+    }
+
+    fn funding_time() -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+    }
+
+    #[test]
+    fn a_long_position_pays_a_positive_rate() {
+        let (mut estimator, mut rx) = setup();
+        estimator.on_position("BTC-USDT-SWAP", "USDT", "10000".parse().unwrap());
+        estimator.on_funding_rate("BTC-USDT-SWAP", "0.0001".parse().unwrap(), funding_time());
+
+        estimator.on_balance_delta("USDT", funding_time() + chrono::Duration::seconds(3));
+
+        match rx.try_recv().unwrap() {
+            DriverEvent::FundingPayment {
+                inst_id,
+                amount,
+                rate,
+                exchange_time,
+            } => {
+                assert_eq!(inst_id, "BTC-USDT-SWAP");
+                assert_eq!(amount, "-1.0000".parse().unwrap());
+                assert_eq!(rate, "0.0001".parse().unwrap());
+                assert_eq!(exchange_time, funding_time());
+            }
+            other => panic!("expected a funding payment, got {other:?}"),
+        }
+        // The schedule slot is consumed: the next delta does not re-fire.
+        estimator.on_balance_delta("USDT", funding_time() + chrono::Duration::seconds(5));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_short_position_receives_a_positive_rate() {
+        let (mut estimator, mut rx) = setup();
+        estimator.on_position("BTC-USDT-SWAP", "USDT", "-10000".parse().unwrap());
+        estimator.on_funding_rate("BTC-USDT-SWAP", "0.0001".parse().unwrap(), funding_time());
+
+        estimator.on_balance_delta("USDT", funding_time());
+
+        match rx.try_recv().unwrap() {
+            DriverEvent::FundingPayment { amount, .. } => {
+                assert_eq!(amount, "1.0000".parse().unwrap());
+            }
+            other => panic!("expected a funding payment, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn deltas_outside_the_window_or_wrong_asset_do_not_fire() {
+        let (mut estimator, mut rx) = setup();
+        estimator.on_position("BTC-USDT-SWAP", "USDT", "10000".parse().unwrap());
+        estimator.on_funding_rate("BTC-USDT-SWAP", "0.0001".parse().unwrap(), funding_time());
+
+        estimator.on_balance_delta("BTC", funding_time());
+        estimator.on_balance_delta("USDT", funding_time() + chrono::Duration::seconds(61));
+
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_differing_bill_emits_a_correction_and_a_matching_one_does_not() {
+        let (mut estimator, mut rx) = setup();
+        estimator.on_position("BTC-USDT-SWAP", "USDT", "10000".parse().unwrap());
+        estimator.on_funding_rate("BTC-USDT-SWAP", "0.0001".parse().unwrap(), funding_time());
+        estimator.on_balance_delta("USDT", funding_time());
+        let DriverEvent::FundingPayment { amount, .. } = rx.try_recv().unwrap() else {
+            panic!("expected a funding payment");
+        };
+
+        let bill_time = funding_time() + chrono::Duration::minutes(90);
+        estimator.on_funding_bill("BTC-USDT-SWAP", "-1.05".parse().unwrap(), bill_time);
+        match rx.try_recv().unwrap() {
+            DriverEvent::FundingPaymentCorrection {
+                estimated, actual, ..
+            } => {
+                assert_eq!(estimated, amount);
+                assert_eq!(actual, "-1.05".parse().unwrap());
+            }
+            other => panic!("expected a correction, got {other:?}"),
+        }
+
+        // A second bill has nothing pending to correct.
+        estimator.on_funding_bill("BTC-USDT-SWAP", "-1.05".parse().unwrap(), bill_time);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,836 @@
+//! Instrument metadata used for order normalization and conversions.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DriverError, DriverResult};
+
+/// Precision and sizing rules of a single OKX instrument.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instrument {
+    /// OKX instrument id, e.g. `BTC-USDT` or `BTC-USDT-SWAP`.
+    pub inst_id: String,
+    /// Price increment (`tickSz`).
+    pub tick_size: Decimal,
+    /// Size increment (`lotSz`).
+    pub lot_size: Decimal,
+    /// Minimum order size (`minSz`).
+    pub min_size: Decimal,
+    /// Contract value (`ctVal`); `None` for spot.
+    pub contract_value: Option<Decimal>,
+    /// Trade this pair as spot margin (OKX `MARGIN` instType). Margin
+    /// instruments share the spot id scheme (`BTC-USDT`), so the intent has
+    /// to be declared here rather than derived from the id.
+    pub margin: bool,
+    /// Settlement time (`expTime`, milliseconds); only dated contracts
+    /// have one.
+    pub expiry_time: Option<u64>,
+}
+
+impl Instrument {
+    /// Map one `/api/v5/public/instruments` entry. The margin flag is a
+    /// local trading decision, not exchange data, so it starts `false` and
+    /// is set by the caller for pairs configured to trade as margin.
+    pub fn from_info(info: &crate::api_structs::OkexInstrumentInfo) -> Self {
+        Self {
+            inst_id: info.inst_id.clone(),
+            tick_size: info.tick_size,
+            lot_size: info.lot_size,
+            min_size: info.min_size,
+            contract_value: info.contract_value,
+            margin: false,
+            expiry_time: info.expiry_time,
+        }
+    }
+
+    /// Best-effort instrument synthesized from an OKX `instId` alone
+    /// (`BASE-QUOTE`, `BASE-QUOTE-SWAP`, dated variants), for attributing
+    /// activity on instruments nobody configured — manual UI trades land on
+    /// ids the converter does not know. Sizing metadata is zeroed: a
+    /// synthetic instrument exists so external activity can be surfaced in
+    /// events and reports, never for placing orders. `None` when the id
+    /// does not look like an OKX instrument id at all.
+    pub fn synthetic_from_inst_id(inst_id: &str) -> Option<Self> {
+        let mut parts = inst_id.split('-');
+        let (base, quote) = (parts.next()?, parts.next()?);
+        let currency_like = |part: &str| {
+            !part.is_empty() && part.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        };
+        if !currency_like(base) || !currency_like(quote) {
+            return None;
+        }
+        Some(Self {
+            inst_id: inst_id.to_string(),
+            tick_size: Decimal::ZERO,
+            lot_size: Decimal::ZERO,
+            min_size: Decimal::ZERO,
+            contract_value: None,
+            margin: false,
+            expiry_time: None,
+        })
+    }
+
+    /// OKX `instType` derived from the instrument id naming scheme:
+    /// `BTC-USDT-SWAP` is a swap, `BTC-USDT-240329` a future,
+    /// `BTC-USD-240329-50000-C` an option, anything else spot — unless the
+    /// pair is flagged for margin trading, which is `MARGIN` on the same id.
+    pub fn inst_type(&self) -> &'static str {
+        let last = self.inst_id.rsplit('-').next().unwrap_or_default();
+        if last == "SWAP" {
+            "SWAP"
+        } else if last == "C" || last == "P" {
+            "OPTION"
+        } else if !last.is_empty() && last.bytes().all(|b| b.is_ascii_digit()) {
+            "FUTURES"
+        } else if self.margin {
+            "MARGIN"
+        } else {
+            "SPOT"
+        }
+    }
+
+    /// Quote currency of a spot/margin pair id (`USDT` for `BTC-USDT`);
+    /// used as the margin currency on margin orders.
+    pub fn quote_currency(&self) -> Option<&str> {
+        let mut parts = self.inst_id.split('-');
+        parts.next()?;
+        parts.next()
+    }
+
+    /// Time until settlement as of `now`; negative once expiry has passed,
+    /// `None` for instruments without an expiry.
+    pub fn time_to_expiry(&self, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::Duration> {
+        let expiry = chrono::DateTime::from_timestamp_millis(self.expiry_time? as i64)?;
+        Some(expiry - now)
+    }
+}
+
+/// Lookup of the instruments this driver instance trades, keyed by OKX
+/// instrument id. Data the exchange reports for instruments outside the
+/// converter is not ours and gets dropped during mapping.
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentConverter {
+    by_inst_id: HashMap<String, Instrument>,
+    /// Instruments delisted by the exchange while local orders or
+    /// positions still reference them; kept resolvable for cleanup, never
+    /// for new orders. See [`Self::apply_refresh`].
+    tombstones: HashMap<String, Instrument>,
+    /// Set when the data came from an on-disk cache past its maximum age;
+    /// such a converter may back read paths but not order placement.
+    stale_for_orders: bool,
+}
+
+impl InstrumentConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, instrument: Instrument) {
+        self.by_inst_id.insert(instrument.inst_id.clone(), instrument);
+    }
+
+    /// Look up an instrument, tombstoned ones included — cancel, fetch and
+    /// close paths must keep resolving a delisted instrument until its
+    /// local state drains. Order placement checks [`Self::is_delisted`]
+    /// before trusting the result.
+    pub fn get(&self, inst_id: &str) -> Option<&Instrument> {
+        self.by_inst_id
+            .get(inst_id)
+            .or_else(|| self.tombstones.get(inst_id))
+    }
+
+    pub fn contains(&self, inst_id: &str) -> bool {
+        self.by_inst_id.contains_key(inst_id) || self.tombstones.contains_key(inst_id)
+    }
+
+    /// Whether the instrument survives only as a tombstone: the exchange
+    /// delisted it, and it is retained solely for cleaning up local state.
+    pub fn is_delisted(&self, inst_id: &str) -> bool {
+        self.tombstones.contains_key(inst_id)
+    }
+
+    pub fn instruments(&self) -> impl Iterator<Item = &Instrument> {
+        self.by_inst_id.values()
+    }
+
+    /// Time until settlement of a dated contract; `None` for unknown pairs
+    /// and instruments without an expiry.
+    pub fn time_to_expiry(&self, inst_id: &str) -> Option<chrono::Duration> {
+        self.get(inst_id)?.time_to_expiry(chrono::Utc::now())
+    }
+
+    /// Every currency the configured instruments can touch, sorted and
+    /// deduplicated: base and quote per pair, which also covers the
+    /// settlement asset (coin-margined contracts settle in their base,
+    /// linear ones in their quote). Drives the account-channel currency
+    /// filter; see
+    /// [`crate::config::OkexConfig::filter_account_currencies`].
+    pub fn currencies(&self) -> std::collections::BTreeSet<String> {
+        self.instruments()
+            .flat_map(|instrument| {
+                [
+                    instrument.inst_id.split('-').next().map(str::to_string),
+                    instrument.quote_currency().map(str::to_string),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// [`Self::currencies`] comma-joined for the account-channel `ccy`
+    /// arg; `None` with no instruments configured, since an empty filter
+    /// would subscribe to nothing.
+    pub fn account_channel_ccy(&self) -> Option<String> {
+        let currencies = self.currencies();
+        if currencies.is_empty() {
+            return None;
+        }
+        Some(currencies.into_iter().collect::<Vec<_>>().join(","))
+    }
+
+    /// Swap in a freshly fetched instrument set, diffing against the
+    /// current one.
+    ///
+    /// A removed instrument that still has local orders or positions (per
+    /// `has_local_state`) becomes a tombstone instead of vanishing:
+    /// [`Self::get`] and [`Self::contains`] keep resolving it so cancels,
+    /// fetches and closes can finish their cleanup, while
+    /// [`Self::is_delisted`] refuses it for new orders. Each live-to-
+    /// tombstone transition emits one
+    /// [`crate::events::DriverEvent::InstrumentDelisted`]. A tombstone is
+    /// dropped as soon as its local state drains (re-checked on every
+    /// refresh) or the exchange relists the instrument.
+    pub fn apply_refresh(
+        &mut self,
+        fresh: InstrumentConverter,
+        has_local_state: impl Fn(&str) -> bool,
+        events: &crate::events::DriverEventSender,
+    ) {
+        for (inst_id, instrument) in std::mem::take(&mut self.by_inst_id) {
+            if fresh.by_inst_id.contains_key(&inst_id) || !has_local_state(&inst_id) {
+                continue;
+            }
+            let _ = events.send(crate::events::DriverEvent::InstrumentDelisted {
+                inst_id: inst_id.clone(),
+            });
+            self.tombstones.insert(inst_id, instrument);
+        }
+        // Relisted instruments and drained tombstones drop out.
+        self.tombstones
+            .retain(|inst_id, _| !fresh.by_inst_id.contains_key(inst_id) && has_local_state(inst_id));
+        self.by_inst_id = fresh.by_inst_id;
+        self.stale_for_orders = fresh.stale_for_orders;
+    }
+
+    /// Flag the converter as too stale for order placement.
+    pub fn mark_stale_for_orders(&mut self) {
+        self.stale_for_orders = true;
+    }
+
+    /// Whether order placement may trust this converter's metadata.
+    pub fn usable_for_orders(&self) -> bool {
+        !self.stale_for_orders
+    }
+
+    /// Verify every configured pair resolves to a fetched instrument,
+    /// reporting all mismatches at once. Meant for startup, right after
+    /// [`crate::rest::OkexClient::load_instruments`]: a spot pair
+    /// configured against a swap-only fetch otherwise survives until the
+    /// first order and dies there as an unhelpful symbol rejection. Each
+    /// unresolvable pair is reported with the closest instrument id the
+    /// fetch actually returned — `ETH-USDT` against swaps points at
+    /// `ETH-USDT-SWAP`, a typo'd id at its likely spelling.
+    pub fn verify_configured_pairs(&self, configured: &[&str]) -> DriverResult<()> {
+        let problems: Vec<String> = configured
+            .iter()
+            .filter(|inst_id| !self.contains(inst_id))
+            .map(|inst_id| match self.closest_inst_id(inst_id) {
+                Some(suggestion) => format!("{inst_id} (closest offered: {suggestion})"),
+                None => format!("{inst_id} (nothing similar offered)"),
+            })
+            .collect();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(DriverError::Config(format!(
+            "{} configured pair(s) do not resolve to any fetched instrument: {}",
+            problems.len(),
+            problems.join(", ")
+        )))
+    }
+
+    /// Closest fetched id to an unresolvable one. An id extending the
+    /// configured one by a segment (`ETH-USDT` vs `ETH-USDT-SWAP`) — the
+    /// wrong-instrument-type case — wins over edit distance, which
+    /// catches typos; nothing within editing reach returns `None`.
+    fn closest_inst_id(&self, inst_id: &str) -> Option<&str> {
+        let extension = self
+            .by_inst_id
+            .keys()
+            .filter(|id| {
+                id.starts_with(&format!("{inst_id}-")) || inst_id.starts_with(&format!("{id}-"))
+            })
+            .min();
+        if let Some(suggestion) = extension {
+            return Some(suggestion);
+        }
+        self.by_inst_id
+            .keys()
+            .map(|id| (edit_distance(inst_id, id), id))
+            .filter(|(distance, _)| *distance <= 3)
+            .min()
+            .map(|(_, id)| id.as_str())
+    }
+}
+
+/// Plain Levenshtein distance, for suggesting the instrument id a typo'd
+/// pair probably meant.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Dynamically updatable set of traded pairs with snapshot reads.
+///
+/// Pair add/remove happens at runtime while hot paths iterate the set, so
+/// storage cannot hand out references into a vector a writer may mutate or
+/// reallocate underneath them. Writers build a fresh vector and swap the
+/// published [`Arc`] in one step; readers clone the `Arc` — a pointer copy
+/// under a briefly held lock — and the slice they hold stays valid for as
+/// long as they keep the snapshot. A snapshot reflects the set as of the
+/// call; later updates never show through it.
+#[derive(Debug, Default)]
+pub struct PairSet {
+    snapshot: std::sync::RwLock<Arc<PairSnapshot>>,
+}
+
+/// One immutable view of a [`PairSet`]. The generation increases with
+/// every update, so pollers can detect change without comparing contents.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PairSnapshot {
+    /// Update counter; `0` is the initial empty set.
+    pub generation: u64,
+    pairs: Vec<Instrument>,
+}
+
+impl PairSnapshot {
+    /// The pairs as of when this snapshot was taken. The slice lives as
+    /// long as the snapshot `Arc`, not as long as the originating set.
+    pub fn pairs(&self) -> &[Instrument] {
+        &self.pairs
+    }
+
+    pub fn contains(&self, inst_id: &str) -> bool {
+        self.pairs.iter().any(|pair| pair.inst_id == inst_id)
+    }
+}
+
+impl PairSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cheap, torn-read-free view of the current set.
+    pub fn snapshot(&self) -> Arc<PairSnapshot> {
+        Arc::clone(&self.snapshot.read().unwrap())
+    }
+
+    /// Add a pair, replacing any existing entry with the same id.
+    pub fn add_pair(&self, pair: Instrument) {
+        self.publish(|pairs| {
+            pairs.retain(|existing| existing.inst_id != pair.inst_id);
+            pairs.push(pair);
+        });
+    }
+
+    /// Remove a pair by id; `false` when it was not in the set.
+    pub fn remove_pair(&self, inst_id: &str) -> bool {
+        let mut removed = false;
+        self.publish(|pairs| {
+            let before = pairs.len();
+            pairs.retain(|pair| pair.inst_id != inst_id);
+            removed = pairs.len() != before;
+        });
+        removed
+    }
+
+    /// Copy-on-write update: mutate a clone of the current vector, then
+    /// swap it in as a new snapshot. Readers holding the old `Arc` keep
+    /// the old contents; nobody observes the intermediate state.
+    fn publish(&self, mutate: impl FnOnce(&mut Vec<Instrument>)) {
+        let mut slot = self.snapshot.write().unwrap();
+        let mut pairs = slot.pairs.clone();
+        mutate(&mut pairs);
+        *slot = Arc::new(PairSnapshot {
+            generation: slot.generation + 1,
+            pairs,
+        });
+    }
+}
+
+/// On-disk cache schema version; bumped whenever [`Instrument`] changes
+/// shape, so old files are rejected instead of misread.
+const INSTRUMENT_CACHE_SCHEMA: u32 = 1;
+
+/// Serialized instrument snapshot for fast cold starts: startup can reuse
+/// it when the live `/public/instruments` fetch fails (or skip the fetch
+/// entirely with `fast_start`), since instrument metadata changes rarely.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentCache {
+    pub schema_version: u32,
+    /// When the instruments were fetched, milliseconds.
+    pub fetched_at_ms: i64,
+    pub instruments: Vec<Instrument>,
+}
+
+impl InstrumentCache {
+    /// Snapshot a converter, sorted by instrument id so the file is
+    /// deterministic.
+    pub fn from_converter(
+        converter: &InstrumentConverter,
+        fetched_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let mut instruments: Vec<Instrument> = converter.instruments().cloned().collect();
+        instruments.sort_by(|a, b| a.inst_id.cmp(&b.inst_id));
+        Self {
+            schema_version: INSTRUMENT_CACHE_SCHEMA,
+            fetched_at_ms: fetched_at.timestamp_millis(),
+            instruments,
+        }
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> DriverResult<()> {
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).map_err(|err| {
+            DriverError::Generic(format!(
+                "could not write instrument cache {}: {err}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Load a cache file, rejecting unknown schema versions outright.
+    pub fn load(path: &std::path::Path) -> DriverResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|err| {
+            DriverError::Generic(format!(
+                "could not read instrument cache {}: {err}",
+                path.display()
+            ))
+        })?;
+        let cache: Self = serde_json::from_str(&json)?;
+        if cache.schema_version != INSTRUMENT_CACHE_SCHEMA {
+            return Err(DriverError::Generic(format!(
+                "instrument cache {} has schema version {}, expected {INSTRUMENT_CACHE_SCHEMA}",
+                path.display(),
+                cache.schema_version
+            )));
+        }
+        Ok(cache)
+    }
+
+    /// Whether the snapshot is older than `max_age` as of `now`.
+    pub fn is_stale(
+        &self,
+        max_age: std::time::Duration,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        let age = now.timestamp_millis().saturating_sub(self.fetched_at_ms);
+        age > max_age.as_millis() as i64
+    }
+
+    /// Build a converter from the snapshot. A stale snapshot is flagged as
+    /// unusable for order placement unless `allow_stale_orders` overrides
+    /// it; read paths work either way.
+    pub fn into_converter(
+        self,
+        max_age: std::time::Duration,
+        allow_stale_orders: bool,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> InstrumentConverter {
+        let stale = self.is_stale(max_age, now);
+        let mut converter = InstrumentConverter::new();
+        for instrument in self.instruments {
+            converter.insert(instrument);
+        }
+        if stale && !allow_stale_orders {
+            converter.mark_stale_for_orders();
+        }
+        converter
+    }
+}
+
+/// Emit [`crate::events::DriverEvent::PreSettlement`] for every configured
+/// contract within `guard` of its expiry. The instrument refresh cycle
+/// calls this after each refresh; `flagged` carries the already-announced
+/// ids across cycles so each contract is announced once.
+pub fn emit_pre_settlement_events(
+    converter: &InstrumentConverter,
+    guard: std::time::Duration,
+    now: chrono::DateTime<chrono::Utc>,
+    flagged: &mut std::collections::HashSet<String>,
+    events: &crate::events::DriverEventSender,
+) {
+    let guard = chrono::Duration::from_std(guard).unwrap_or_default();
+    for instrument in converter.instruments() {
+        let Some(remaining) = instrument.time_to_expiry(now) else {
+            continue;
+        };
+        if remaining <= guard && flagged.insert(instrument.inst_id.clone()) {
+            let _ = events.send(crate::events::DriverEvent::PreSettlement {
+                inst_id: instrument.inst_id.clone(),
+                expiry_time: instrument.expiry_time.unwrap_or_default(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dated_future(expiry_time: Option<u64>) -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-240329".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time,
+            margin: false,
+        }
+    }
+
+    #[test]
+    fn time_to_expiry_is_signed_and_absent_without_an_expiry() {
+        let expiry = 1_700_000_000_000u64;
+        let instrument = dated_future(Some(expiry));
+
+        let before = chrono::DateTime::from_timestamp_millis(expiry as i64 - 60_000).unwrap();
+        assert_eq!(
+            instrument.time_to_expiry(before),
+            Some(chrono::Duration::seconds(60))
+        );
+        let after = chrono::DateTime::from_timestamp_millis(expiry as i64 + 1_000).unwrap();
+        assert_eq!(
+            instrument.time_to_expiry(after),
+            Some(chrono::Duration::seconds(-1))
+        );
+        assert_eq!(dated_future(None).time_to_expiry(before), None);
+    }
+
+    #[test]
+    fn synthetic_instruments_parse_spot_and_swap_ids_only() {
+        let spot = Instrument::synthetic_from_inst_id("ETH-BTC").unwrap();
+        assert_eq!(spot.inst_id, "ETH-BTC");
+        assert_eq!(spot.inst_type(), "SPOT");
+
+        let swap = Instrument::synthetic_from_inst_id("ETH-USDT-SWAP").unwrap();
+        assert_eq!(swap.inst_type(), "SWAP");
+        assert_eq!(swap.min_size, Decimal::ZERO, "sizing metadata is unknown");
+
+        assert!(Instrument::synthetic_from_inst_id("garbage").is_none());
+        assert!(Instrument::synthetic_from_inst_id("eth-usdt").is_none());
+        assert!(Instrument::synthetic_from_inst_id("ETH-").is_none());
+    }
+
+    #[test]
+    fn derived_currencies_cover_base_quote_and_settlement_assets() {
+        let mut converter = InstrumentConverter::new();
+        assert_eq!(converter.account_channel_ccy(), None, "no empty filter");
+
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+        converter.insert(Instrument::synthetic_from_inst_id("ETH-USDT-SWAP").unwrap());
+        // Coin-margined: settles in BTC, which the base slot covers.
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USD-SWAP").unwrap());
+
+        let currencies: Vec<String> = converter.currencies().into_iter().collect();
+        assert_eq!(currencies, vec!["BTC", "ETH", "USD", "USDT"]);
+        assert_eq!(
+            converter.account_channel_ccy().as_deref(),
+            Some("BTC,ETH,USD,USDT")
+        );
+    }
+
+    #[test]
+    fn pre_settlement_events_fire_once_inside_the_window() {
+        let expiry = 1_700_000_000_000u64;
+        let mut converter = InstrumentConverter::new();
+        converter.insert(dated_future(Some(expiry)));
+        let guard = std::time::Duration::from_secs(300);
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut flagged = std::collections::HashSet::new();
+
+        // Outside the window: nothing.
+        let early = chrono::DateTime::from_timestamp_millis(expiry as i64 - 600_000).unwrap();
+        emit_pre_settlement_events(&converter, guard, early, &mut flagged, &events_tx);
+        assert!(events_rx.try_recv().is_err());
+
+        // Inside the window: one event, and only once across cycles.
+        let close = chrono::DateTime::from_timestamp_millis(expiry as i64 - 200_000).unwrap();
+        emit_pre_settlement_events(&converter, guard, close, &mut flagged, &events_tx);
+        emit_pre_settlement_events(&converter, guard, close, &mut flagged, &events_tx);
+        assert!(matches!(
+            events_rx.try_recv(),
+            Ok(crate::events::DriverEvent::PreSettlement { expiry_time, .. })
+                if expiry_time == expiry
+        ));
+        assert!(events_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_pair_snapshot_reflects_the_set_at_call_time() {
+        let set = PairSet::new();
+        set.add_pair(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+        let before = set.snapshot();
+        assert_eq!(before.generation, 1);
+        assert!(before.contains("BTC-USDT"));
+
+        set.add_pair(Instrument::synthetic_from_inst_id("ETH-USDT").unwrap());
+        assert!(set.remove_pair("BTC-USDT"));
+        assert!(!set.remove_pair("BTC-USDT"), "already gone");
+
+        // The held snapshot is unaffected by later updates.
+        assert_eq!(before.pairs().len(), 1);
+        assert!(before.contains("BTC-USDT"));
+        let after = set.snapshot();
+        assert_eq!(after.generation, 4);
+        assert!(after.contains("ETH-USDT"));
+        assert!(!after.contains("BTC-USDT"));
+    }
+
+    #[test]
+    fn concurrent_pair_updates_never_tear_reader_snapshots() {
+        let set = Arc::new(PairSet::new());
+        let writers: Vec<_> = (0..4)
+            .map(|n| {
+                let set = Arc::clone(&set);
+                std::thread::spawn(move || {
+                    let inst_id = format!("W{n}-USDT");
+                    for _ in 0..500 {
+                        set.add_pair(
+                            Instrument::synthetic_from_inst_id(&inst_id).unwrap(),
+                        );
+                        set.remove_pair(&inst_id);
+                    }
+                })
+            })
+            .collect();
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let set = Arc::clone(&set);
+                std::thread::spawn(move || {
+                    let mut last_generation = 0;
+                    for _ in 0..2_000 {
+                        let snapshot = set.snapshot();
+                        assert!(
+                            snapshot.generation >= last_generation,
+                            "generations went backwards"
+                        );
+                        last_generation = snapshot.generation;
+                        // Every pair in a snapshot is fully formed and
+                        // unique: no half-written entries, no entry seen
+                        // both before and after a removal.
+                        let pairs = snapshot.pairs();
+                        for (index, pair) in pairs.iter().enumerate() {
+                            assert!(pair.inst_id.ends_with("-USDT"), "{}", pair.inst_id);
+                            assert!(pairs[index + 1..]
+                                .iter()
+                                .all(|other| other.inst_id != pair.inst_id));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+        assert_eq!(set.snapshot().pairs().len(), 0, "every add was removed");
+    }
+
+    #[test]
+    fn refresh_tombstones_delisted_instruments_until_state_drains() {
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+        converter.insert(Instrument::synthetic_from_inst_id("ETH-USDT").unwrap());
+        let fresh = || {
+            let mut fresh = InstrumentConverter::new();
+            fresh.insert(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+            fresh
+        };
+
+        // ETH-USDT disappears while an order still references it: cleanup
+        // lookups keep working, one event fires.
+        converter.apply_refresh(fresh(), |inst_id| inst_id == "ETH-USDT", &events_tx);
+        assert!(converter.get("ETH-USDT").is_some());
+        assert!(converter.contains("ETH-USDT"));
+        assert!(converter.is_delisted("ETH-USDT"));
+        assert!(!converter.is_delisted("BTC-USDT"));
+        assert!(matches!(
+            events_rx.try_recv(),
+            Ok(crate::events::DriverEvent::InstrumentDelisted { inst_id })
+                if inst_id == "ETH-USDT"
+        ));
+
+        // Still gone on the next refresh: the tombstone persists silently.
+        converter.apply_refresh(fresh(), |inst_id| inst_id == "ETH-USDT", &events_tx);
+        assert!(events_rx.try_recv().is_err(), "delisting is announced once");
+        assert!(converter.is_delisted("ETH-USDT"));
+
+        // Local state drained: the tombstone goes with it.
+        converter.apply_refresh(fresh(), |_| false, &events_tx);
+        assert!(!converter.contains("ETH-USDT"));
+    }
+
+    #[test]
+    fn removals_without_local_state_and_relistings_drop_the_tombstone() {
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument::synthetic_from_inst_id("XRP-USDT").unwrap());
+
+        // Nothing local references it: removed outright, no event.
+        converter.apply_refresh(InstrumentConverter::new(), |_| false, &events_tx);
+        assert!(!converter.contains("XRP-USDT"));
+        assert!(events_rx.try_recv().is_err());
+
+        // Tombstone an instrument, then relist it: the live entry wins.
+        converter.insert(Instrument::synthetic_from_inst_id("DOGE-USDT").unwrap());
+        converter.apply_refresh(InstrumentConverter::new(), |_| true, &events_tx);
+        assert!(converter.is_delisted("DOGE-USDT"));
+        let mut relisted = InstrumentConverter::new();
+        relisted.insert(Instrument::synthetic_from_inst_id("DOGE-USDT").unwrap());
+        converter.apply_refresh(relisted, |_| true, &events_tx);
+        assert!(!converter.is_delisted("DOGE-USDT"));
+        assert!(converter.contains("DOGE-USDT"));
+    }
+
+    #[test]
+    fn a_type_mismatched_pair_fails_startup_with_the_offered_id() {
+        // A swap-only fetch, as a swap-typed deployment would produce.
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument::synthetic_from_inst_id("ETH-USDT-SWAP").unwrap());
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USDT-SWAP").unwrap());
+
+        let error = converter
+            .verify_configured_pairs(&["ETH-USDT", "BTC-USDT-SWAP"])
+            .unwrap_err();
+        assert!(matches!(error, DriverError::Config(_)), "got: {error}");
+        let message = error.to_string();
+        assert!(message.contains("1 configured pair(s)"), "{message}");
+        assert!(
+            message.contains("ETH-USDT (closest offered: ETH-USDT-SWAP)"),
+            "{message}"
+        );
+        assert!(
+            !message.contains("BTC-USDT-SWAP ("),
+            "the resolvable pair is not reported: {message}"
+        );
+    }
+
+    #[test]
+    fn a_typod_pair_suggests_its_likely_spelling() {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument::synthetic_from_inst_id("ETH-USDT").unwrap());
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+
+        let message = converter
+            .verify_configured_pairs(&["ETH-USTD", "DOGE-USDT"])
+            .unwrap_err()
+            .to_string();
+        // Every mismatch in one report: the transposition gets its likely
+        // spelling, the unlisted pair is called out as such.
+        assert!(message.contains("2 configured pair(s)"), "{message}");
+        assert!(
+            message.contains("ETH-USTD (closest offered: ETH-USDT)"),
+            "{message}"
+        );
+        assert!(
+            message.contains("DOGE-USDT (nothing similar offered)"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn a_valid_pair_config_verifies_clean() {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument::synthetic_from_inst_id("ETH-USDT-SWAP").unwrap());
+        converter.insert(Instrument::synthetic_from_inst_id("BTC-USDT").unwrap());
+
+        assert!(converter
+            .verify_configured_pairs(&["ETH-USDT-SWAP", "BTC-USDT"])
+            .is_ok());
+        assert!(converter.verify_configured_pairs(&[]).is_ok());
+    }
+
+    fn temp_cache_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("okx-instrument-cache-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn instrument_cache_round_trips_through_disk() {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(dated_future(Some(1_700_000_000_000)));
+        let fetched_at = chrono::DateTime::from_timestamp_millis(1_699_000_000_000).unwrap();
+        let cache = InstrumentCache::from_converter(&converter, fetched_at);
+
+        let path = temp_cache_path("round-trip");
+        cache.save(&path).unwrap();
+        let loaded = InstrumentCache::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, cache);
+        let restored =
+            loaded.into_converter(std::time::Duration::from_secs(60), false, fetched_at);
+        assert_eq!(
+            restored.get("BTC-USDT-240329"),
+            converter.get("BTC-USDT-240329")
+        );
+        assert!(restored.usable_for_orders());
+    }
+
+    #[test]
+    fn unknown_schema_versions_are_rejected() {
+        let path = temp_cache_path("schema");
+        std::fs::write(
+            &path,
+            r#"{"schema_version":99,"fetched_at_ms":0,"instruments":[]}"#,
+        )
+        .unwrap();
+        let error = InstrumentCache::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains("schema version 99"));
+    }
+
+    #[test]
+    fn a_stale_cache_blocks_orders_unless_explicitly_allowed() {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(dated_future(None));
+        let fetched_at = chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let cache = InstrumentCache::from_converter(&converter, fetched_at);
+        let max_age = std::time::Duration::from_secs(3600);
+        let later = fetched_at + chrono::Duration::seconds(3601);
+
+        let stale = cache.clone().into_converter(max_age, false, later);
+        assert!(!stale.usable_for_orders());
+        // Read paths keep working on a stale cache.
+        assert!(stale.contains("BTC-USDT-240329"));
+
+        let allowed = cache.clone().into_converter(max_age, true, later);
+        assert!(allowed.usable_for_orders());
+
+        let fresh = cache.into_converter(max_age, false, fetched_at);
+        assert!(fresh.usable_for_orders());
+    }
+}
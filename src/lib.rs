@@ -0,0 +1,47 @@
+//! OKX (v5 API) exchange driver.
+//!
+//! The crate is split into a thin transport layer ([`transport`]), the typed
+//! wire structs ([`api_structs`]), and the signed REST client ([`rest`]).
+//! Higher-level driver logic builds on top of [`rest::OkexClient`].
+
+pub mod algo_orders;
+pub mod api_structs;
+pub mod balance_events;
+pub mod balance_precheck;
+pub mod balances;
+pub mod bills;
+pub mod cancel_all_after;
+pub mod capabilities;
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod circuit_breaker;
+pub mod client_id;
+pub mod collateral;
+pub mod config;
+pub mod dead_letter;
+pub mod driver;
+pub mod errors;
+pub mod events;
+pub mod execution_stats;
+pub mod export;
+pub mod fill_sim;
+pub mod funding;
+pub mod instruments;
+pub mod liquidity;
+pub mod market_trades;
+pub mod order_book;
+pub mod order_lifecycle;
+pub mod order_throttle;
+pub mod orders;
+pub mod positions;
+pub mod precision;
+pub mod preflight;
+pub mod rate_limiter;
+pub mod reporting;
+pub mod rest;
+pub mod resume;
+pub mod stale_orders;
+pub mod trades;
+pub mod transport;
+pub mod wire_log;
+pub mod ws;
@@ -0,0 +1,277 @@
+//! Live maker/taker and rebate accounting from the fills stream.
+//!
+//! Rebate revenue is reconciled monthly today, which means a strategy
+//! whose post-only flow starts getting tagged taker — a flag dropped in a
+//! refactor, an amend that crossed the book — burns fees for weeks before
+//! anyone notices. [`LiquidityTracker`] counts it live: every normalized
+//! fill lands in a per-pair, per-UTC-day bucket of maker vs taker filled
+//! notional and accumulated fee per currency, readable via
+//! [`LiquidityTracker::liquidity_breakdown`] and pushed to the metrics
+//! hook as gauges after each fill. Fees keep the [`RawTrade`] cost
+//! convention — positive charged, negative for rebates — and stay in
+//! their own currency; converting to a reporting currency is the
+//! observer's job.
+//!
+//! The tracker lives on the driver's consumer, not on a connection, so
+//! the buckets survive WS reconnects; retention is bounded to the recent
+//! days a monthly reconciliation could still argue about.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::Decimal;
+
+use crate::trades::RawTrade;
+
+/// Days of buckets kept per pair: two monthly billing cycles, pruned as
+/// newer fills arrive.
+const RETENTION_DAYS: i64 = 62;
+
+/// One pair's liquidity totals for one UTC day; see
+/// [`LiquidityTracker::liquidity_breakdown`]. Dumps cleanly as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct LiquidityBreakdown {
+    pub maker_fills: u64,
+    pub taker_fills: u64,
+    /// Filled notional (price times base amount, quote units) on the
+    /// maker side; fills without a price contribute to the counts only.
+    pub maker_notional: Decimal,
+    pub taker_notional: Decimal,
+    /// Accumulated fee per currency, as costs: positive when charged,
+    /// negative when the day netted rebates.
+    pub fees: BTreeMap<String, Decimal>,
+}
+
+/// Collects per-pair, per-UTC-day liquidity buckets from the fills flow;
+/// see the module docs. Optional: nothing in the driver requires one to
+/// be attached.
+#[derive(Default)]
+pub struct LiquidityTracker {
+    buckets: Mutex<BTreeMap<(String, chrono::NaiveDate), LiquidityBreakdown>>,
+    metrics: Mutex<Option<Arc<dyn crate::rest::MetricsHook>>>,
+}
+
+impl LiquidityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Push every updated bucket to a metrics hook as gauges.
+    pub fn set_metrics_hook(&self, hook: Arc<dyn crate::rest::MetricsHook>) {
+        *self.metrics.lock().unwrap() = Some(hook);
+    }
+
+    /// Feed one normalized fill. Fills whose source record does not
+    /// attribute liquidity are skipped — counting them on either side
+    /// would hide exactly the mis-tagging this tracker exists to catch —
+    /// as are fills without a parseable exchange timestamp to date the
+    /// bucket by.
+    pub fn on_fill(&self, fill: &RawTrade) {
+        let maker = match fill.liquidity.as_deref() {
+            Some("M") => true,
+            Some("T") => false,
+            _ => return,
+        };
+        let Some(filled_at) = fill.exchange_timestamp else {
+            log::debug!(
+                "skipping fill {} with unparseable ts {:?} in liquidity tracking",
+                fill.trade_id,
+                fill.timestamp
+            );
+            return;
+        };
+        let date = filled_at.date_naive();
+        let key = (fill.inst_id.clone(), date);
+        let updated = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = buckets.entry(key).or_default();
+            if maker {
+                bucket.maker_fills += 1;
+            } else {
+                bucket.taker_fills += 1;
+            }
+            if let Some(price) = fill.price {
+                let notional = price * fill.amount;
+                if maker {
+                    bucket.maker_notional += notional;
+                } else {
+                    bucket.taker_notional += notional;
+                }
+            }
+            if let (Some(fee), Some(currency)) = (fill.fee, &fill.fee_currency) {
+                *bucket.fees.entry(currency.clone()).or_insert(Decimal::ZERO) += fee;
+            }
+            let updated = bucket.clone();
+            let cutoff = date - chrono::Duration::days(RETENTION_DAYS);
+            buckets.retain(|(_, day), _| *day >= cutoff);
+            updated
+        };
+        if let Some(hook) = self.metrics.lock().unwrap().clone() {
+            hook.on_liquidity_breakdown(&fill.inst_id, date, &updated);
+        }
+    }
+
+    /// The bucket for one pair and UTC day; an unseen combination yields
+    /// all-zero totals.
+    pub fn liquidity_breakdown(
+        &self,
+        pair: &str,
+        date: chrono::NaiveDate,
+    ) -> LiquidityBreakdown {
+        self.buckets
+            .lock()
+            .unwrap()
+            .get(&(pair.to_string(), date))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every tracked bucket, oldest day first, for dashboards and exports.
+    pub fn breakdown_by_day(
+        &self,
+    ) -> BTreeMap<(String, chrono::NaiveDate), LiquidityBreakdown> {
+        self.buckets.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    fn fill(
+        inst_id: &str,
+        liquidity: Option<&str>,
+        price: &str,
+        amount: &str,
+        fee: &str,
+        fee_currency: &str,
+        ts_ms: i64,
+    ) -> RawTrade {
+        RawTrade {
+            inst_id: inst_id.to_string(),
+            trade_id: format!("t{ts_ms}"),
+            order_id: "ord1".to_string(),
+            client_order_id: None,
+            level_id: None,
+            price: Some(price.parse().unwrap()),
+            amount: amount.parse().unwrap(),
+            side: "buy".to_string(),
+            liquidity: liquidity.map(str::to_string),
+            fee: Some(fee.parse().unwrap()),
+            fee_currency: Some(fee_currency.to_string()),
+            realized_pnl: None,
+            timestamp: ts_ms.to_string(),
+            exchange_timestamp: chrono::DateTime::from_timestamp_millis(ts_ms),
+            internal_created_at: chrono::DateTime::from_timestamp_millis(ts_ms).unwrap(),
+        }
+    }
+
+    /// 2023-11-14 UTC.
+    const DAY_ONE_MS: i64 = 1_700_000_000_000;
+    /// The following UTC day.
+    const DAY_TWO_MS: i64 = DAY_ONE_MS + 86_400_000;
+
+    fn date_of(ts_ms: i64) -> chrono::NaiveDate {
+        chrono::DateTime::from_timestamp_millis(ts_ms)
+            .unwrap()
+            .date_naive()
+    }
+
+    #[test]
+    fn maker_and_taker_fills_bucket_by_pair_and_utc_day() {
+        let tracker = LiquidityTracker::new();
+        // Two maker fills rebating USDT, one taker charged, on day one...
+        tracker.on_fill(&fill("BTC-USDT", Some("M"), "100", "2", "-0.02", "USDT", DAY_ONE_MS));
+        tracker.on_fill(&fill("BTC-USDT", Some("M"), "110", "1", "-0.011", "USDT", DAY_ONE_MS + 1));
+        tracker.on_fill(&fill("BTC-USDT", Some("T"), "120", "1", "0.06", "USDT", DAY_ONE_MS + 2));
+        // ...one fill on the next day and one on another pair, charged in
+        // the base currency.
+        tracker.on_fill(&fill("BTC-USDT", Some("T"), "130", "1", "0.065", "USDT", DAY_TWO_MS));
+        tracker.on_fill(&fill("ETH-USDT", Some("M"), "50", "4", "-0.004", "ETH", DAY_ONE_MS));
+
+        let day_one = tracker.liquidity_breakdown("BTC-USDT", date_of(DAY_ONE_MS));
+        assert_eq!(day_one.maker_fills, 2);
+        assert_eq!(day_one.taker_fills, 1);
+        assert_eq!(day_one.maker_notional, "310".parse().unwrap());
+        assert_eq!(day_one.taker_notional, "120".parse().unwrap());
+        // The day netted a rebate: maker credits outweigh the taker fee.
+        assert_eq!(day_one.fees["USDT"], "0.029".parse::<Decimal>().unwrap());
+
+        let day_two = tracker.liquidity_breakdown("BTC-USDT", date_of(DAY_TWO_MS));
+        assert_eq!(day_two.maker_fills, 0);
+        assert_eq!(day_two.taker_notional, "130".parse().unwrap());
+
+        let eth = tracker.liquidity_breakdown("ETH-USDT", date_of(DAY_ONE_MS));
+        assert_eq!(eth.fees["ETH"], "-0.004".parse::<Decimal>().unwrap());
+        assert!(!eth.fees.contains_key("USDT"));
+
+        // An unseen combination reads as zeros, not an error.
+        let unseen = tracker.liquidity_breakdown("XRP-USDT", date_of(DAY_ONE_MS));
+        assert_eq!(unseen, LiquidityBreakdown::default());
+    }
+
+    #[test]
+    fn unattributed_fills_are_skipped_not_guessed() {
+        let tracker = LiquidityTracker::new();
+        tracker.on_fill(&fill("BTC-USDT", None, "100", "1", "0.05", "USDT", DAY_ONE_MS));
+        let mut unparseable = fill("BTC-USDT", Some("M"), "100", "1", "0.05", "USDT", DAY_ONE_MS);
+        unparseable.exchange_timestamp = None;
+        tracker.on_fill(&unparseable);
+
+        assert!(tracker.breakdown_by_day().is_empty());
+    }
+
+    #[derive(Default)]
+    struct GaugeHook {
+        seen: StdMutex<Vec<(String, chrono::NaiveDate, LiquidityBreakdown)>>,
+    }
+
+    impl crate::rest::MetricsHook for GaugeHook {
+        fn on_request(&self, _metrics: &crate::rest::RequestMetrics) {}
+
+        fn on_liquidity_breakdown(
+            &self,
+            pair: &str,
+            date: chrono::NaiveDate,
+            breakdown: &LiquidityBreakdown,
+        ) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((pair.to_string(), date, breakdown.clone()));
+        }
+    }
+
+    #[test]
+    fn each_fill_pushes_the_updated_bucket_as_a_gauge() {
+        let tracker = LiquidityTracker::new();
+        let hook = Arc::new(GaugeHook::default());
+        tracker.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+
+        tracker.on_fill(&fill("BTC-USDT", Some("M"), "100", "1", "-0.01", "USDT", DAY_ONE_MS));
+        tracker.on_fill(&fill("BTC-USDT", Some("T"), "100", "1", "0.05", "USDT", DAY_ONE_MS + 1));
+
+        let seen = hook.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].0, "BTC-USDT");
+        assert_eq!(seen[0].2.maker_fills, 1);
+        // The second gauge carries the running bucket, not a delta.
+        assert_eq!(seen[1].2.maker_fills, 1);
+        assert_eq!(seen[1].2.taker_fills, 1);
+        assert_eq!(seen[1].2.fees["USDT"], "0.04".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn buckets_older_than_the_retention_window_age_out() {
+        let tracker = LiquidityTracker::new();
+        tracker.on_fill(&fill("BTC-USDT", Some("M"), "100", "1", "-0.01", "USDT", DAY_ONE_MS));
+        let later = DAY_ONE_MS + (RETENTION_DAYS + 1) * 86_400_000;
+        tracker.on_fill(&fill("BTC-USDT", Some("M"), "100", "1", "-0.01", "USDT", later));
+
+        let buckets = tracker.breakdown_by_day();
+        assert_eq!(buckets.len(), 1, "the old bucket was pruned");
+        assert!(buckets.contains_key(&("BTC-USDT".to_string(), date_of(later))));
+    }
+}
@@ -0,0 +1,341 @@
+//! Public `trades` channel ingestion for market volume context.
+//!
+//! The participation-rate limiter needs to know how much the market traded
+//! recently. [`MarketTradeFeed`] parses public-WS `trades` pushes for the
+//! tracked instruments into lightweight [`MarketTrade`]s — contract sizes
+//! multiplied out to base units — and keeps a per-instrument ring of
+//! `(timestamp, size)` so [`traded_volume`](MarketTradeFeed::traded_volume)
+//! can answer over any window up to the configured retention. Each parsed
+//! trade is also forwarded on the driver event stream when a sender is
+//! attached.
+//!
+//! OKX batches multiple trades per frame during bursts, so the parser
+//! works on one frame at a time with borrowed fields: per trade it
+//! allocates nothing — prices, sizes and timestamps are parsed straight
+//! out of the frame buffer.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::events::{DriverEvent, DriverEventSender};
+use crate::instruments::Instrument;
+use crate::orders::Side;
+
+/// One public trade, normalized: size in base units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketTrade {
+    pub price: Decimal,
+    /// Traded size in base units; contract sizes are multiplied by `ctVal`.
+    pub size: Decimal,
+    /// Taker side, as OKX reports it.
+    pub side: Side,
+    /// Trade time, milliseconds.
+    pub ts: u64,
+}
+
+/// `trades` push frame, borrowed from the frame buffer.
+#[derive(Deserialize)]
+struct TradesFrame<'a> {
+    #[serde(borrow)]
+    arg: TradesArg<'a>,
+    #[serde(borrow, default)]
+    data: Vec<WireTrade<'a>>,
+    /// Set on subscription acks/rejections, which carry the same `arg`
+    /// shape; pushes never have it.
+    #[serde(default)]
+    event: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct TradesArg<'a> {
+    channel: &'a str,
+    #[serde(rename = "instId")]
+    inst_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct WireTrade<'a> {
+    px: &'a str,
+    sz: &'a str,
+    side: &'a str,
+    ts: &'a str,
+}
+
+struct TrackedInstrument {
+    /// `ctVal`, or one for spot; sizes are multiplied by it on ingest.
+    contract_value: Decimal,
+    /// `(ts, size)` of recent trades, oldest first, pruned to the
+    /// configured retention against the newest trade's exchange time.
+    ring: VecDeque<(u64, Decimal)>,
+}
+
+/// Parses and aggregates public trades for the tracked instruments; see
+/// the module docs.
+pub struct MarketTradeFeed {
+    /// Longest window [`traded_volume`](Self::traded_volume) can answer;
+    /// older ring entries are dropped on ingest.
+    retention: std::time::Duration,
+    events: Option<DriverEventSender>,
+    dead_letters: Option<std::sync::Arc<dyn crate::dead_letter::DeadLetterSink>>,
+    state: Mutex<HashMap<String, TrackedInstrument>>,
+}
+
+impl MarketTradeFeed {
+    pub fn new(retention: std::time::Duration) -> Self {
+        Self {
+            retention,
+            events: None,
+            dead_letters: None,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forward each parsed trade as [`DriverEvent::MarketTrade`].
+    pub fn set_event_sender(&mut self, events: DriverEventSender) {
+        self.events = Some(events);
+    }
+
+    /// Capture `trades` pushes that fail typed deserialization; see
+    /// [`crate::dead_letter`].
+    pub fn set_dead_letter_sink(
+        &mut self,
+        sink: std::sync::Arc<dyn crate::dead_letter::DeadLetterSink>,
+    ) {
+        self.dead_letters = Some(sink);
+    }
+
+    /// Start aggregating trades on this instrument; frames for untracked
+    /// instruments are ignored.
+    pub fn track(&self, instrument: &Instrument) {
+        self.state.lock().unwrap().insert(
+            instrument.inst_id.clone(),
+            TrackedInstrument {
+                contract_value: instrument.contract_value.unwrap_or(Decimal::ONE),
+                ring: VecDeque::new(),
+            },
+        );
+    }
+
+    /// Feed one inbound public frame; `true` when it was a `trades` push
+    /// for a tracked instrument. Anything else — other channels,
+    /// subscription acks, untracked instruments — is left untouched for
+    /// the next handler.
+    pub fn handle_frame(&self, frame: &str) -> bool {
+        let parsed = match serde_json::from_str::<TradesFrame<'_>>(frame) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                crate::dead_letter::report_typed_failure(
+                    self.dead_letters.as_deref(),
+                    "trades",
+                    frame,
+                    &error,
+                );
+                return false;
+            }
+        };
+        if parsed.arg.channel != "trades" || parsed.event.is_some() {
+            return false;
+        }
+        let mut state = self.state.lock().unwrap();
+        let Some(tracked) = state.get_mut(parsed.arg.inst_id) else {
+            return false;
+        };
+        let retention_ms = self.retention.as_millis() as u64;
+        for wire in &parsed.data {
+            let (Ok(price), Ok(size), Ok(ts)) = (
+                wire.px.parse::<Decimal>(),
+                wire.sz.parse::<Decimal>(),
+                wire.ts.parse::<u64>(),
+            ) else {
+                log::debug!("skipping unparseable public trade on {}", parsed.arg.inst_id);
+                if let Some(sink) = &self.dead_letters {
+                    sink.capture(
+                        "trades",
+                        &crate::wire_log::redact_frame(frame),
+                        "unparseable px/sz/ts in trade row",
+                    );
+                }
+                continue;
+            };
+            let side = match wire.side {
+                "buy" => Side::Buy,
+                "sell" => Side::Sell,
+                _ => continue,
+            };
+            let trade = MarketTrade {
+                price,
+                size: size * tracked.contract_value,
+                side,
+                ts,
+            };
+            tracked.ring.push_back((trade.ts, trade.size));
+            if let Some(newest) = tracked.ring.back().map(|(ts, _)| *ts) {
+                while let Some((oldest, _)) = tracked.ring.front() {
+                    if newest.saturating_sub(*oldest) > retention_ms {
+                        tracked.ring.pop_front();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            if let Some(events) = &self.events {
+                let _ = events.send(DriverEvent::MarketTrade {
+                    inst_id: parsed.arg.inst_id.to_string(),
+                    trade,
+                });
+            }
+        }
+        true
+    }
+
+    /// Total base-unit volume traded on `inst_id` within `window` of now.
+    /// Windows longer than the configured retention are effectively capped
+    /// at it — older trades were already dropped.
+    pub fn traded_volume(&self, inst_id: &str, window: std::time::Duration) -> Decimal {
+        self.traded_volume_at(
+            inst_id,
+            window,
+            chrono::Utc::now().timestamp_millis() as u64,
+        )
+    }
+
+    fn traded_volume_at(
+        &self,
+        inst_id: &str,
+        window: std::time::Duration,
+        now_ms: u64,
+    ) -> Decimal {
+        let cutoff = now_ms.saturating_sub(window.as_millis() as u64);
+        let state = self.state.lock().unwrap();
+        let Some(tracked) = state.get(inst_id) else {
+            return Decimal::ZERO;
+        };
+        tracked
+            .ring
+            .iter()
+            .filter(|(ts, _)| *ts >= cutoff)
+            .map(|(_, size)| size)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn batched_frame() -> String {
+        r#"{"arg":{"channel":"trades","instId":"BTC-USDT-SWAP"},"data":[
+            {"instId":"BTC-USDT-SWAP","tradeId":"901","px":"43250.1","sz":"5","side":"buy","ts":"1700000000000"},
+            {"instId":"BTC-USDT-SWAP","tradeId":"902","px":"43250.0","sz":"2","side":"sell","ts":"1700000000050"}
+        ]}"#
+        .to_string()
+    }
+
+    #[test]
+    fn a_batched_trades_frame_parses_into_normalized_events() {
+        let mut feed = MarketTradeFeed::new(std::time::Duration::from_secs(60));
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        feed.set_event_sender(events_tx);
+        feed.track(&swap_instrument());
+
+        assert!(feed.handle_frame(&batched_frame()));
+
+        let first = match events_rx.try_recv().unwrap() {
+            DriverEvent::MarketTrade { inst_id, trade } => {
+                assert_eq!(inst_id, "BTC-USDT-SWAP");
+                trade
+            }
+            other => panic!("expected a market trade, got {other:?}"),
+        };
+        // 5 contracts of 0.01 BTC.
+        assert_eq!(first.size, "0.05".parse().unwrap());
+        assert_eq!(first.price, "43250.1".parse().unwrap());
+        assert_eq!(first.side, Side::Buy);
+        assert_eq!(first.ts, 1_700_000_000_000);
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            DriverEvent::MarketTrade { trade, .. } if trade.side == Side::Sell
+        ));
+        assert!(events_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn non_trades_frames_and_untracked_instruments_are_left_alone() {
+        let feed = MarketTradeFeed::new(std::time::Duration::from_secs(60));
+        feed.track(&swap_instrument());
+
+        let ack = r#"{"event":"subscribe","arg":{"channel":"trades","instId":"BTC-USDT-SWAP"}}"#;
+        assert!(!feed.handle_frame(ack), "acks are for the subscription tracker");
+        let books = r#"{"arg":{"channel":"books","instId":"BTC-USDT-SWAP"},"data":[]}"#;
+        assert!(!feed.handle_frame(books));
+        let foreign = r#"{"arg":{"channel":"trades","instId":"ETH-USDT"},"data":[
+            {"px":"2000","sz":"1","side":"buy","ts":"1700000000000"}
+        ]}"#;
+        assert!(!feed.handle_frame(foreign));
+        assert_eq!(
+            feed.traded_volume("ETH-USDT", std::time::Duration::from_secs(60)),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn traded_volume_answers_the_requested_window() {
+        let feed = MarketTradeFeed::new(std::time::Duration::from_secs(60));
+        feed.track(&swap_instrument());
+        for (ts, sz) in [(1_000u64, "5"), (5_000, "3"), (11_000, "2")] {
+            let frame = format!(
+                r#"{{"arg":{{"channel":"trades","instId":"BTC-USDT-SWAP"}},"data":[
+                    {{"px":"43000","sz":"{sz}","side":"buy","ts":"{ts}"}}
+                ]}}"#
+            );
+            assert!(feed.handle_frame(&frame));
+        }
+
+        let window = std::time::Duration::from_secs(10);
+        // At t=12s only the trades from t=5s and t=11s are inside 10s.
+        assert_eq!(
+            feed.traded_volume_at("BTC-USDT-SWAP", window, 12_000),
+            "0.05".parse().unwrap()
+        );
+        // At t=30s everything has aged out of the 10s window.
+        assert_eq!(
+            feed.traded_volume_at("BTC-USDT-SWAP", window, 30_000),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn the_ring_drops_trades_older_than_the_retention() {
+        let feed = MarketTradeFeed::new(std::time::Duration::from_secs(10));
+        feed.track(&swap_instrument());
+        for ts in [1_000u64, 2_000, 20_000] {
+            let frame = format!(
+                r#"{{"arg":{{"channel":"trades","instId":"BTC-USDT-SWAP"}},"data":[
+                    {{"px":"43000","sz":"1","side":"sell","ts":"{ts}"}}
+                ]}}"#
+            );
+            feed.handle_frame(&frame);
+        }
+        // The first two trades fell out of retention when t=20s arrived,
+        // so even a generous window cannot resurrect them.
+        assert_eq!(
+            feed.traded_volume_at("BTC-USDT-SWAP", std::time::Duration::from_secs(60), 20_000),
+            "0.01".parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Normalized order book depth snapshot.
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::OkexOrderBookSnapshot;
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::Instrument;
+
+/// One price level, sizes in base units with contract sizes multiplied out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookLevel {
+    pub price: Decimal,
+    /// Resting size in base units; contract levels are multiplied by
+    /// `ctVal`.
+    pub size: Decimal,
+    /// Number of orders at the level, when reported (books-lite omits it).
+    pub order_count: Option<u32>,
+}
+
+/// A depth snapshot normalized against its instrument, best levels first on
+/// both sides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBook {
+    pub inst_id: String,
+    /// Bids, highest price first.
+    pub bids: Vec<BookLevel>,
+    /// Asks, lowest price first.
+    pub asks: Vec<BookLevel>,
+    /// Snapshot time, milliseconds, as reported.
+    pub timestamp: String,
+    /// Parsed snapshot time; `None` when the exchange timestamp does not
+    /// parse — never a fallback clock read.
+    pub exchange_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl OrderBook {
+    /// Normalize one `/api/v5/market/books` snapshot against its
+    /// instrument. The deprecated liquidated-orders slot is ignored.
+    pub fn from_snapshot(
+        snapshot: &OkexOrderBookSnapshot,
+        instrument: &Instrument,
+    ) -> DriverResult<Self> {
+        let contract_value = instrument.contract_value.unwrap_or(Decimal::ONE);
+        let mut bids = parse_levels(&snapshot.bids, contract_value)?;
+        let mut asks = parse_levels(&snapshot.asks, contract_value)?;
+        // OKX sends best-first already; sorting makes it a guarantee.
+        bids.sort_by_key(|level| std::cmp::Reverse(level.price));
+        asks.sort_by_key(|level| level.price);
+        Ok(Self {
+            inst_id: instrument.inst_id.clone(),
+            bids,
+            asks,
+            timestamp: snapshot.ts.clone(),
+            exchange_timestamp: crate::orders::parse_exchange_millis(&snapshot.ts),
+        })
+    }
+}
+
+/// Outcome of walking the book for a hypothetical marketable order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillEstimate {
+    /// Size-weighted average fill price over the consumed levels.
+    pub avg_price: Decimal,
+    /// Deepest level touched.
+    pub worst_price: Decimal,
+    pub levels_consumed: usize,
+    /// The book ran out before the full amount; the estimate only covers
+    /// the liquidity that was there.
+    pub exhausted: bool,
+}
+
+impl OrderBook {
+    /// Estimate the average price of buying (walks asks) or selling (walks
+    /// bids) `amount` base units against this snapshot. Contract sizes were
+    /// already converted to base units during normalization, so the walk is
+    /// uniform across spot and derivatives.
+    ///
+    /// A snapshot older than `max_age` as of `now` — or one whose timestamp
+    /// did not parse — returns an error instead of a misleading estimate.
+    pub fn estimate_fill(
+        &self,
+        side: crate::orders::Side,
+        amount: Decimal,
+        max_age: std::time::Duration,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> DriverResult<FillEstimate> {
+        let Some(taken_at) = self.exchange_timestamp else {
+            return Err(DriverError::Generic(format!(
+                "order book for {} has no parseable timestamp",
+                self.inst_id
+            )));
+        };
+        let age = now - taken_at;
+        if age > chrono::Duration::from_std(max_age).unwrap_or_default() {
+            return Err(DriverError::Generic(format!(
+                "order book for {} is {}s stale",
+                self.inst_id,
+                age.num_seconds()
+            )));
+        }
+        let levels = match side {
+            crate::orders::Side::Buy => &self.asks,
+            crate::orders::Side::Sell => &self.bids,
+        };
+
+        let mut remaining = amount;
+        let mut notional = Decimal::ZERO;
+        let mut worst_price = Decimal::ZERO;
+        let mut levels_consumed = 0;
+        for level in levels {
+            let take = remaining.min(level.size);
+            notional += take * level.price;
+            worst_price = level.price;
+            levels_consumed += 1;
+            remaining -= take;
+            if remaining.is_zero() {
+                break;
+            }
+        }
+        let filled = amount - remaining;
+        if filled.is_zero() {
+            return Err(DriverError::Generic(format!(
+                "no liquidity on the {} side of {}",
+                match side {
+                    crate::orders::Side::Buy => "ask",
+                    crate::orders::Side::Sell => "bid",
+                },
+                self.inst_id
+            )));
+        }
+        Ok(FillEstimate {
+            avg_price: notional / filled,
+            worst_price,
+            levels_consumed,
+            exhausted: !remaining.is_zero(),
+        })
+    }
+}
+
+/// Parse one side's positional `[price, size, liquidated, orders]` arrays.
+fn parse_levels(levels: &[Vec<String>], contract_value: Decimal) -> DriverResult<Vec<BookLevel>> {
+    levels
+        .iter()
+        .map(|level| {
+            let [price, size, ..] = level.as_slice() else {
+                return Err(DriverError::Generic(format!(
+                    "malformed book level: {level:?}"
+                )));
+            };
+            let price: Decimal = price.parse().map_err(|_| {
+                DriverError::Generic(format!("unparseable book price: {price}"))
+            })?;
+            let size: Decimal = size.parse().map_err(|_| {
+                DriverError::Generic(format!("unparseable book size: {size}"))
+            })?;
+            Ok(BookLevel {
+                price,
+                size: size * contract_value,
+                order_count: level.get(3).and_then(|count| count.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> OkexOrderBookSnapshot {
+        serde_json::from_str(
+            r#"{
+                "bids": [["43250.0", "0.5", "0", "3"], ["43250.2", "1.25", "0", "1"]],
+                "asks": [["43250.5", "0.75", "0", "2"], ["43250.3", "2", "0", "4"]],
+                "ts": "1700000000000"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn spot_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    #[test]
+    fn positional_levels_parse_typed_and_sorted_best_first() {
+        let book = OrderBook::from_snapshot(&snapshot(), &spot_instrument()).unwrap();
+
+        assert_eq!(book.bids[0].price, "43250.2".parse().unwrap());
+        assert_eq!(book.bids[1].price, "43250.0".parse().unwrap());
+        assert_eq!(book.asks[0].price, "43250.3".parse().unwrap());
+        assert_eq!(book.asks[1].price, "43250.5".parse().unwrap());
+        assert_eq!(book.bids[0].size, "1.25".parse().unwrap());
+        assert_eq!(book.bids[0].order_count, Some(1));
+        assert_eq!(
+            book.exchange_timestamp,
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_000)
+        );
+    }
+
+    #[test]
+    fn swap_level_sizes_convert_contracts_to_base_amounts() {
+        let swap = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            contract_value: Some("0.01".parse().unwrap()),
+            ..spot_instrument()
+        };
+        let book = OrderBook::from_snapshot(&snapshot(), &swap).unwrap();
+        // 1.25 contracts of 0.01 BTC each.
+        assert_eq!(book.bids[0].size, "0.0125".parse().unwrap());
+    }
+
+    /// Asks 43250.3 (2 base) then 43250.5 (0.75 base), freshly stamped.
+    fn book() -> OrderBook {
+        OrderBook::from_snapshot(&snapshot(), &spot_instrument()).unwrap()
+    }
+
+    fn fresh_now() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_001_000).unwrap()
+    }
+
+    const MAX_AGE: std::time::Duration = std::time::Duration::from_secs(5);
+
+    #[test]
+    fn an_exact_single_level_fill_has_no_slippage() {
+        let estimate = book()
+            .estimate_fill(crate::orders::Side::Buy, "2".parse().unwrap(), MAX_AGE, fresh_now())
+            .unwrap();
+        assert_eq!(estimate.avg_price, "43250.3".parse().unwrap());
+        assert_eq!(estimate.worst_price, "43250.3".parse().unwrap());
+        assert_eq!(estimate.levels_consumed, 1);
+        assert!(!estimate.exhausted);
+    }
+
+    #[test]
+    fn a_multi_level_fill_averages_across_levels() {
+        // 2 @ 43250.3 + 0.5 @ 43250.5 over 2.5 base units.
+        let estimate = book()
+            .estimate_fill(crate::orders::Side::Buy, "2.5".parse().unwrap(), MAX_AGE, fresh_now())
+            .unwrap();
+        assert_eq!(estimate.avg_price, "43250.34".parse().unwrap());
+        assert_eq!(estimate.worst_price, "43250.5".parse().unwrap());
+        assert_eq!(estimate.levels_consumed, 2);
+        assert!(!estimate.exhausted);
+    }
+
+    #[test]
+    fn an_oversized_fill_is_flagged_exhausted() {
+        let estimate = book()
+            .estimate_fill(crate::orders::Side::Sell, "10".parse().unwrap(), MAX_AGE, fresh_now())
+            .unwrap();
+        // Both bid levels (1.25 + 0.5) get consumed, nothing more to take.
+        assert_eq!(estimate.levels_consumed, 2);
+        assert!(estimate.exhausted);
+        assert_eq!(estimate.worst_price, "43250.0".parse().unwrap());
+    }
+
+    #[test]
+    fn a_stale_book_refuses_to_estimate() {
+        let much_later = fresh_now() + chrono::Duration::seconds(60);
+        let err = book()
+            .estimate_fill(crate::orders::Side::Buy, Decimal::ONE, MAX_AGE, much_later)
+            .unwrap_err();
+        assert!(err.to_string().contains("stale"), "got: {err}");
+    }
+
+    #[test]
+    fn books_lite_levels_without_trailing_slots_still_parse() {
+        let lite: OkexOrderBookSnapshot = serde_json::from_str(
+            r#"{"bids": [["43250.0", "0.5"]], "asks": [], "ts": "1700000000000"}"#,
+        )
+        .unwrap();
+        let book = OrderBook::from_snapshot(&lite, &spot_instrument()).unwrap();
+        assert_eq!(book.bids[0].order_count, None);
+    }
+}
@@ -0,0 +1,279 @@
+//! Per-order lifecycle state machine with invalid-transition detection.
+//!
+//! Order updates are not guaranteed to arrive in lifecycle order: after a
+//! reconnect the reconciliation fetch and the resubscribed channel can
+//! interleave, so a `filled` update may be followed by a stale `live` one.
+//! Forwarding such updates verbatim has resurrected dead orders in
+//! downstream consumers. [`OrderLifecycleTracker`] sits between the
+//! update source and the consumer, validates each transition against the
+//! OKX lifecycle (`live` → `partially_filled` → `filled`/`canceled`), and
+//! either annotates the update or suppresses it outright.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api_structs::OkexOrderUpdate;
+
+/// Most orders the tracker follows at once; beyond this the least
+/// recently touched entry is evicted.
+const MAX_TRACKED: usize = 4096;
+
+/// How long a terminal order's entry survives after its last update. Long
+/// enough to absorb the stale pushes that trail a fill or cancel, short
+/// enough that finished orders do not pin tracker slots.
+const TERMINAL_TTL: Duration = Duration::from_secs(60);
+
+/// Lifecycle rank of an OKX order state. Comparing ranks is what makes a
+/// regression detectable: a valid lifecycle only ever moves up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum StateRank {
+    Live,
+    PartiallyFilled,
+    Terminal,
+}
+
+impl StateRank {
+    /// `None` for states outside the regular order lifecycle; those pass
+    /// through unvalidated rather than guessing a rank.
+    fn from_state(state: &str) -> Option<Self> {
+        match state {
+            "live" => Some(Self::Live),
+            "partially_filled" => Some(Self::PartiallyFilled),
+            "filled" | "canceled" => Some(Self::Terminal),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with one observed update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateDisposition {
+    /// Forward downstream, annotated.
+    Forward {
+        /// The update ends the order's lifecycle (`filled`/`canceled`).
+        is_terminal: bool,
+        /// The update is older (by `uTime`) or ranks lower than what was
+        /// already seen for this order, but is harmless to forward —
+        /// consumers wanting strict ordering can drop it on this flag.
+        out_of_order: bool,
+    },
+    /// Suppress: the update would regress an order already seen in a
+    /// terminal state, resurrecting it for anyone tracking open orders.
+    Suppress,
+}
+
+struct TrackedOrder {
+    rank: StateRank,
+    /// Highest `uTime` seen, milliseconds.
+    updated_ms: u64,
+    /// When the entry last changed; drives LRU eviction and, for terminal
+    /// orders, the TTL.
+    touched: Instant,
+}
+
+/// Validates order-update sequences per `ordId`. Memory is bounded twice
+/// over: terminal entries expire after a TTL, and the map itself is a
+/// bounded LRU.
+#[derive(Default)]
+pub struct OrderLifecycleTracker {
+    orders: Mutex<HashMap<String, TrackedOrder>>,
+}
+
+impl OrderLifecycleTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observe one update and decide its fate. `now` drives eviction, not
+    /// ordering — staleness is judged purely by the exchange's `uTime`.
+    pub fn observe(&self, update: &OkexOrderUpdate, now: Instant) -> UpdateDisposition {
+        let Some(rank) = StateRank::from_state(&update.state) else {
+            // Algo activations and other exotic states are not part of
+            // the lifecycle this machine validates.
+            log::debug!(
+                "order {} in unvalidated state {:?}; forwarding as-is",
+                update.order_id,
+                update.state
+            );
+            return UpdateDisposition::Forward {
+                is_terminal: false,
+                out_of_order: false,
+            };
+        };
+        let updated_ms = update.updated_at.parse::<u64>().unwrap_or_default();
+        let is_terminal = rank == StateRank::Terminal;
+
+        let mut orders = self.orders.lock().unwrap();
+        orders.retain(|_, entry| {
+            entry.rank != StateRank::Terminal || now.duration_since(entry.touched) < TERMINAL_TTL
+        });
+
+        let Some(entry) = orders.get_mut(&update.order_id) else {
+            if orders.len() >= MAX_TRACKED {
+                // Scanned only on overflow; entries are usually freed by
+                // the terminal TTL first.
+                if let Some(oldest) = orders
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.touched)
+                    .map(|(order_id, _)| order_id.clone())
+                {
+                    orders.remove(&oldest);
+                }
+            }
+            orders.insert(
+                update.order_id.clone(),
+                TrackedOrder {
+                    rank,
+                    updated_ms,
+                    touched: now,
+                },
+            );
+            return UpdateDisposition::Forward {
+                is_terminal,
+                out_of_order: false,
+            };
+        };
+
+        // A non-terminal update for an order already seen terminal can
+        // only be stale; forwarding it would resurrect the order.
+        if entry.rank == StateRank::Terminal && rank < entry.rank {
+            return UpdateDisposition::Suppress;
+        }
+
+        let out_of_order = rank < entry.rank || updated_ms < entry.updated_ms;
+        // The entry only ever moves forward, so one stale update cannot
+        // blind the machine to the regressions after it.
+        entry.rank = entry.rank.max(rank);
+        entry.updated_ms = entry.updated_ms.max(updated_ms);
+        entry.touched = now;
+        UpdateDisposition::Forward {
+            is_terminal,
+            out_of_order,
+        }
+    }
+
+    /// Orders currently tracked; exposed for snapshot-style introspection.
+    pub fn tracked_orders(&self) -> usize {
+        self.orders.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(order_id: &str, state: &str, u_time_ms: u64) -> OkexOrderUpdate {
+        serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT","ordId":"{order_id}","clOrdId":"","ordType":"limit","state":"{state}","px":"43250.1","sz":"1","accFillSz":"0","side":"buy","uTime":"{u_time_ms}"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn an_ordered_lifecycle_forwards_with_terminal_annotation() {
+        let tracker = OrderLifecycleTracker::new();
+        let now = Instant::now();
+
+        for (state, terminal) in [
+            ("live", false),
+            ("partially_filled", false),
+            ("filled", true),
+        ] {
+            assert_eq!(
+                tracker.observe(&update("ord1", state, 1_000), now),
+                UpdateDisposition::Forward {
+                    is_terminal: terminal,
+                    out_of_order: false
+                },
+                "state {state}"
+            );
+        }
+    }
+
+    #[test]
+    fn stale_updates_after_a_terminal_state_are_suppressed() {
+        let tracker = OrderLifecycleTracker::new();
+        let now = Instant::now();
+        tracker.observe(&update("ord1", "filled", 2_000), now);
+
+        // The trailing stale push that resurrects orders downstream.
+        assert_eq!(
+            tracker.observe(&update("ord1", "live", 1_000), now),
+            UpdateDisposition::Suppress
+        );
+        assert_eq!(
+            tracker.observe(&update("ord1", "partially_filled", 1_500), now),
+            UpdateDisposition::Suppress
+        );
+        // A repeated terminal push is harmless and merely flagged.
+        assert_eq!(
+            tracker.observe(&update("ord1", "canceled", 1_000), now),
+            UpdateDisposition::Forward {
+                is_terminal: true,
+                out_of_order: true
+            }
+        );
+    }
+
+    #[test]
+    fn regressions_before_any_terminal_state_are_flagged_not_dropped() {
+        let tracker = OrderLifecycleTracker::new();
+        let now = Instant::now();
+        tracker.observe(&update("ord1", "partially_filled", 2_000), now);
+
+        // A stale `live` cannot resurrect anything yet, but consumers
+        // should know it is out of sequence.
+        assert_eq!(
+            tracker.observe(&update("ord1", "live", 1_000), now),
+            UpdateDisposition::Forward {
+                is_terminal: false,
+                out_of_order: true
+            }
+        );
+        // The machine did not regress: a terminal update still lands
+        // normally afterwards.
+        assert_eq!(
+            tracker.observe(&update("ord1", "filled", 3_000), now),
+            UpdateDisposition::Forward {
+                is_terminal: true,
+                out_of_order: false
+            }
+        );
+    }
+
+    #[test]
+    fn terminal_entries_expire_and_the_map_stays_bounded() {
+        let tracker = OrderLifecycleTracker::new();
+        let now = Instant::now();
+        tracker.observe(&update("ord1", "filled", 1_000), now);
+        assert_eq!(tracker.tracked_orders(), 1);
+
+        // Past the TTL the entry is gone, so the same stale `live` that
+        // was suppressed above would now be treated as a fresh order —
+        // the accepted cost of not tracking forever.
+        let later = now + TERMINAL_TTL;
+        tracker.observe(&update("ord2", "live", 2_000), later);
+        assert_eq!(tracker.tracked_orders(), 1, "ord1 expired");
+
+        // Overflow evicts the least recently touched entry.
+        for i in 0..MAX_TRACKED {
+            tracker.observe(&update(&format!("bulk{i}"), "live", 3_000), later);
+        }
+        assert_eq!(tracker.tracked_orders(), MAX_TRACKED);
+    }
+
+    #[test]
+    fn exotic_states_pass_through_unvalidated() {
+        let tracker = OrderLifecycleTracker::new();
+        let now = Instant::now();
+        tracker.observe(&update("ord1", "filled", 2_000), now);
+        assert_eq!(
+            tracker.observe(&update("ord1", "mmp_canceled", 1_000), now),
+            UpdateDisposition::Forward {
+                is_terminal: false,
+                out_of_order: false
+            }
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Client-side order placement throttle.
+//!
+//! Distinct from the HTTP rate limiter: OKX additionally caps *order*
+//! rates per sub-account, and tripping that cap briefly suspends all
+//! trading rather than just delaying one request. [`OrderThrottle`] paces
+//! placements per instrument over rolling one-second and one-minute
+//! windows before they ever reach the wire, either blocking until a permit
+//! frees up or failing fast per [`ThrottleMode`]. Amends and cancels are
+//! deliberately not routed through it — getting out of the market must
+//! never wait behind getting in.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::errors::{DriverError, DriverResult};
+use crate::rest::MetricsHook;
+
+/// What to do when a placement would exceed the throttle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThrottleMode {
+    /// Wait until the rolling windows free a permit.
+    #[default]
+    Block,
+    /// Reject immediately with [`DriverError::Throttled`]; for strategies
+    /// that would rather skip an order than place it late.
+    FailFast,
+}
+
+/// Limits for one instance of the throttle; applied per instrument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderThrottleConfig {
+    pub max_per_second: u32,
+    pub max_per_minute: u32,
+    pub mode: ThrottleMode,
+}
+
+/// Throttle utilization after a granted permit, handed to
+/// [`MetricsHook::on_order_throttle`].
+#[derive(Debug, Clone)]
+pub struct ThrottleUtilization {
+    pub inst_id: String,
+    pub second_used: u32,
+    pub second_limit: u32,
+    pub minute_used: u32,
+    pub minute_limit: u32,
+}
+
+/// Rolling-window order placement throttle, one window set per instrument.
+pub struct OrderThrottle {
+    config: OrderThrottleConfig,
+    hook: Option<Arc<dyn MetricsHook>>,
+    /// Placement times within the last minute, oldest first.
+    windows: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl OrderThrottle {
+    pub fn new(config: OrderThrottleConfig) -> Self {
+        Self {
+            config,
+            hook: None,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Report utilization after each granted permit on this hook.
+    pub fn set_metrics_hook(&mut self, hook: Arc<dyn MetricsHook>) {
+        self.hook = Some(hook);
+    }
+
+    /// Take one placement permit for `inst_id`. Blocks until the windows
+    /// allow it, or fails fast, per the configured [`ThrottleMode`].
+    pub async fn acquire(&self, inst_id: &str) -> DriverResult<()> {
+        loop {
+            let wait_until = {
+                let mut windows = self.windows.lock().unwrap();
+                let now = Instant::now();
+                let window = windows.entry(inst_id.to_string()).or_default();
+                while window
+                    .front()
+                    .is_some_and(|&t| now.duration_since(t) >= Duration::from_secs(60))
+                {
+                    window.pop_front();
+                }
+                let second_used = window
+                    .iter()
+                    .rev()
+                    .take_while(|&&t| now.duration_since(t) < Duration::from_secs(1))
+                    .count() as u32;
+                let minute_used = window.len() as u32;
+
+                if second_used < self.config.max_per_second
+                    && minute_used < self.config.max_per_minute
+                {
+                    window.push_back(now);
+                    if let Some(hook) = &self.hook {
+                        hook.on_order_throttle(&ThrottleUtilization {
+                            inst_id: inst_id.to_string(),
+                            second_used: second_used + 1,
+                            second_limit: self.config.max_per_second,
+                            minute_used: minute_used + 1,
+                            minute_limit: self.config.max_per_minute,
+                        });
+                    }
+                    return Ok(());
+                }
+
+                if self.config.mode == ThrottleMode::FailFast {
+                    return Err(DriverError::Throttled(format!(
+                        "{inst_id}: {second_used}/{} orders in the last second, \
+                         {minute_used}/{} in the last minute",
+                        self.config.max_per_second, self.config.max_per_minute
+                    )));
+                }
+
+                // Earliest instant a permit frees up in the binding window.
+                if minute_used >= self.config.max_per_minute {
+                    *window.front().expect("minute window is full") + Duration::from_secs(60)
+                } else {
+                    let oldest_in_second = window
+                        .iter()
+                        .rev()
+                        .take_while(|&&t| now.duration_since(t) < Duration::from_secs(1))
+                        .last()
+                        .expect("second window is full");
+                    *oldest_in_second + Duration::from_secs(1)
+                }
+            };
+            tokio::time::sleep_until(wait_until).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    fn throttle(max_per_second: u32, max_per_minute: u32, mode: ThrottleMode) -> OrderThrottle {
+        OrderThrottle::new(OrderThrottleConfig {
+            max_per_second,
+            max_per_minute,
+            mode,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn blocking_mode_paces_a_burst_across_windows() {
+        let throttle = throttle(20, 1000, ThrottleMode::Block);
+        let started = Instant::now();
+
+        for _ in 0..100 {
+            throttle.acquire("BTC-USDT").await.unwrap();
+        }
+
+        // 100 placements at 20/s: the last one lands in the fifth
+        // one-second window.
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(4), "paced too fast: {elapsed:?}");
+        assert!(elapsed < Duration::from_secs(5), "paced too slow: {elapsed:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fail_fast_mode_rejects_the_burst_overflow() {
+        let throttle = throttle(20, 1000, ThrottleMode::FailFast);
+
+        let mut granted = 0;
+        let mut rejected = 0;
+        for _ in 0..100 {
+            match throttle.acquire("BTC-USDT").await {
+                Ok(()) => granted += 1,
+                Err(DriverError::Throttled(_)) => rejected += 1,
+                Err(other) => panic!("unexpected error: {other}"),
+            }
+        }
+        assert_eq!(granted, 20);
+        assert_eq!(rejected, 80);
+
+        // A fresh second frees the per-second window again.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        throttle.acquire("BTC-USDT").await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn minute_window_binds_after_the_second_window() {
+        let throttle = throttle(100, 30, ThrottleMode::FailFast);
+
+        for _ in 0..30 {
+            throttle.acquire("BTC-USDT").await.unwrap();
+        }
+        let err = throttle.acquire("BTC-USDT").await.unwrap_err();
+        assert!(matches!(err, DriverError::Throttled(_)), "got: {err}");
+
+        // Not even a fresh second helps until the minute rolls.
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(throttle.acquire("BTC-USDT").await.is_err());
+        tokio::time::sleep(Duration::from_secs(59)).await;
+        throttle.acquire("BTC-USDT").await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_are_tracked_per_instrument() {
+        let throttle = throttle(1, 1000, ThrottleMode::FailFast);
+
+        throttle.acquire("BTC-USDT").await.unwrap();
+        throttle.acquire("ETH-USDT").await.unwrap();
+        assert!(throttle.acquire("BTC-USDT").await.is_err());
+    }
+
+    #[derive(Default)]
+    struct UtilizationHook {
+        seen: StdMutex<Vec<ThrottleUtilization>>,
+    }
+
+    impl MetricsHook for UtilizationHook {
+        fn on_request(&self, _metrics: &crate::rest::RequestMetrics) {}
+
+        fn on_order_throttle(&self, utilization: &ThrottleUtilization) {
+            self.seen.lock().unwrap().push(utilization.clone());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn utilization_is_reported_through_the_metrics_hook() {
+        let mut throttle = throttle(20, 1000, ThrottleMode::Block);
+        let hook = Arc::new(UtilizationHook::default());
+        throttle.set_metrics_hook(hook.clone() as Arc<dyn MetricsHook>);
+
+        for _ in 0..3 {
+            throttle.acquire("BTC-USDT").await.unwrap();
+        }
+
+        let seen = hook.seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[2].second_used, 3);
+        assert_eq!(seen[2].second_limit, 20);
+        assert_eq!(seen[2].minute_used, 3);
+        assert_eq!(seen[2].inst_id, "BTC-USDT");
+    }
+}
@@ -0,0 +1,1486 @@
+//! Order domain types and the shared order param builder.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::instruments::Instrument;
+use crate::precision::{serialize_price, serialize_size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Limit,
+    Market,
+    /// Market order with price protection, contracts only: fills at the best
+    /// prices inside the exchange's optimal price band and cancels whatever
+    /// does not fill immediately.
+    OptimalLimitIoc,
+}
+
+/// OKX `tdMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeMode {
+    Cash,
+    Cross,
+    Isolated,
+}
+
+/// OKX `posSide` on contract orders in long/short mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PosSide {
+    Long,
+    Short,
+}
+
+/// Account position mode as the order builder needs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OkexPositionMode {
+    #[default]
+    Net,
+    LongShort,
+}
+
+impl OkexPositionMode {
+    /// Map the account-config `posMode` string; anything unrecognized is
+    /// treated as net mode (preflight catches genuine mismatches).
+    pub fn from_pos_mode(pos_mode: &str) -> Self {
+        if pos_mode == "long_short_mode" {
+            Self::LongShort
+        } else {
+            Self::Net
+        }
+    }
+}
+
+/// Whether an order opens (or adds to) a position or closes (reduces) one.
+///
+/// In long/short mode `posSide` cannot be derived from `side` alone: closing
+/// a long is side=sell with posSide=long, and a naive sell→short mapping
+/// would open a short instead of closing the long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionIntent {
+    Open,
+    Close,
+}
+
+/// Which currency `OrderRequest::amount` is expressed in.
+///
+/// Quote sizing ("spend exactly 1,000 USDT") maps to `tgtCcy=quote_ccy` on
+/// spot market orders and is emulated for limit orders by dividing by the
+/// limit price with lot flooring. Contract instruments are always sized in
+/// contracts, so only `Base` is valid there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeDenomination {
+    #[default]
+    Base,
+    Quote,
+}
+
+/// An order as the strategy expresses it, before exchange normalization.
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub inst_id: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    /// Required for limit orders.
+    pub price: Option<Decimal>,
+    pub amount: Decimal,
+    /// What `amount` is denominated in; base units unless stated otherwise.
+    pub size_denomination: SizeDenomination,
+    /// Open/close intent; required for contract orders in long/short mode,
+    /// ignored everywhere else.
+    pub position_intent: Option<PositionIntent>,
+    pub client_order_id: Option<String>,
+    /// Attribution metadata encoded into generated client order ids (see
+    /// [`crate::client_id`]); irrelevant when `client_order_id` is set by
+    /// hand.
+    pub metadata: Option<crate::client_id::OrderMetadata>,
+}
+
+/// Wire shape for `/api/v5/trade/order` and the WS `order` op.
+///
+/// `px`/`sz` are pre-normalized strings; construct via
+/// [`OkexOrderParams::build`] so precision clamping is never skipped.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OkexOrderParams {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tdMode")]
+    pub td_mode: TradeMode,
+    pub side: Side,
+    #[serde(rename = "ordType")]
+    pub ord_type: OrderType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub px: Option<String>,
+    pub sz: String,
+    /// Margin currency; OKX requires it on cross/isolated MARGIN orders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    /// Size denomination for spot market orders. OKX defaults market buys
+    /// to quote units, so spot market orders always carry it explicitly —
+    /// relying on the default would silently misread base-sized buys.
+    #[serde(rename = "tgtCcy", skip_serializing_if = "Option::is_none")]
+    pub tgt_ccy: Option<String>,
+    /// Only contract orders in long/short mode carry it.
+    #[serde(rename = "posSide", skip_serializing_if = "Option::is_none")]
+    pub pos_side: Option<PosSide>,
+    /// Set alongside `posSide` when the intent is [`PositionIntent::Close`],
+    /// so a close that outsizes the position reduces it to flat instead of
+    /// flipping into the opposite leg.
+    #[serde(rename = "reduceOnly", skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(rename = "clOrdId", skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<String>,
+}
+
+impl OkexOrderParams {
+    /// Shared order param builder: clamps price to `tickSz` and size to
+    /// `lotSz` and renders both as plain fixed-point strings.
+    ///
+    /// Margin instruments keep the configured cross/isolated `tdMode` and
+    /// carry the pair's quote currency as the margin currency; everything
+    /// else omits `ccy`.
+    ///
+    /// Quote-denominated sizes pass through as `tgtCcy=quote_ccy` on spot
+    /// market orders and are converted to a lot-floored base size for limit
+    /// orders; contract instruments reject them outright.
+    ///
+    /// `optimal_limit_ioc` orders are validated as contract-only and never
+    /// carry a price — the exchange derives the protected band itself.
+    ///
+    /// In long/short mode contract orders must state their
+    /// [`PositionIntent`]; `posSide` is derived from (side, intent), never
+    /// from side alone. Net mode and non-contract instruments omit it.
+    pub fn build(
+        request: &OrderRequest,
+        instrument: &Instrument,
+        td_mode: TradeMode,
+        position_mode: OkexPositionMode,
+    ) -> crate::errors::DriverResult<Self> {
+        use crate::errors::DriverError;
+
+        // optimal_limit_ioc exists only on contracts; OKX rejects it
+        // elsewhere with a generic parameter error, so catch it locally.
+        if request.order_type == OrderType::OptimalLimitIoc
+            && !matches!(instrument.inst_type(), "SWAP" | "FUTURES")
+        {
+            return Err(DriverError::Config(format!(
+                "optimal_limit_ioc orders are only supported on SWAP/FUTURES, not {} {}",
+                instrument.inst_type(),
+                request.inst_id
+            )));
+        }
+
+        let pos_side = match (position_mode, instrument.contract_value.is_some()) {
+            (OkexPositionMode::LongShort, true) => {
+                let Some(intent) = request.position_intent else {
+                    return Err(DriverError::Config(format!(
+                        "long/short mode needs a position intent for contract order on {}",
+                        request.inst_id
+                    )));
+                };
+                Some(match (request.side, intent) {
+                    (Side::Buy, PositionIntent::Open) | (Side::Sell, PositionIntent::Close) => {
+                        PosSide::Long
+                    }
+                    (Side::Sell, PositionIntent::Open) | (Side::Buy, PositionIntent::Close) => {
+                        PosSide::Short
+                    }
+                })
+            }
+            _ => None,
+        };
+        let ccy = (instrument.margin && td_mode != TradeMode::Cash)
+            .then(|| instrument.quote_currency().map(str::to_string))
+            .flatten();
+        let (sz, tgt_ccy) = match (request.size_denomination, request.order_type) {
+            (SizeDenomination::Base, ord_type) => (
+                serialize_size(request.amount, instrument.lot_size),
+                (ord_type == OrderType::Market && instrument.inst_type() == "SPOT")
+                    .then(|| "base_ccy".to_string()),
+            ),
+            (SizeDenomination::Quote, OrderType::Market) => {
+                if instrument.inst_type() != "SPOT" {
+                    return Err(DriverError::Config(format!(
+                        "quote-denominated market orders are only supported on spot, not {} {}",
+                        instrument.inst_type(),
+                        request.inst_id
+                    )));
+                }
+                // `sz` is a quote amount here; base lot flooring does not
+                // apply.
+                (
+                    request.amount.normalize().to_string(),
+                    Some("quote_ccy".to_string()),
+                )
+            }
+            (SizeDenomination::Quote, OrderType::Limit) => {
+                if instrument.contract_value.is_some() {
+                    return Err(DriverError::Config(format!(
+                        "quote-denominated size is not supported for contract instrument {}",
+                        request.inst_id
+                    )));
+                }
+                let Some(price) = request.price.filter(|price| !price.is_zero()) else {
+                    return Err(DriverError::Config(format!(
+                        "quote-denominated limit order on {} needs a non-zero price",
+                        request.inst_id
+                    )));
+                };
+                (
+                    serialize_size(request.amount / price, instrument.lot_size),
+                    None,
+                )
+            }
+            // The contract-only guard above already ran, and contracts are
+            // always sized in contracts.
+            (SizeDenomination::Quote, OrderType::OptimalLimitIoc) => {
+                return Err(DriverError::Config(format!(
+                    "quote-denominated size is not supported for contract instrument {}",
+                    request.inst_id
+                )));
+            }
+        };
+        // OKX rejects `"sz":"0"` with a generic parameter error, and a
+        // zero lands here whenever upstream passes a zero amount through
+        // or lot flooring eats the whole amount. Quote-sized market
+        // orders skip the minimum-size check below, so this guard runs
+        // for every shape.
+        let rendered_size: Decimal = sz.parse().unwrap_or_default();
+        if rendered_size <= Decimal::ZERO {
+            return Err(DriverError::ZeroSize {
+                inst_id: request.inst_id.clone(),
+                requested: request.amount,
+            });
+        }
+        // Reject below-minimum sizes locally instead of burning a round
+        // trip on the inevitable sCode. The spot minimum-notional rule
+        // (minSz × price) reduces to the same base-size comparison once the
+        // size is normalized; quote-sized market orders are exempt because
+        // no local price exists to convert them.
+        if tgt_ccy.as_deref() != Some("quote_ccy") && rendered_size < instrument.min_size {
+            return Err(DriverError::BelowMinimumSize {
+                min: instrument.min_size,
+                requested: rendered_size,
+            });
+        }
+        // Price handling per order type: a limit order must carry a
+        // genuine price — `"px":"0"` is as rejected as an absent one —
+        // while market and optimal_limit_ioc orders must omit the field
+        // entirely, even when a stray caller price is set.
+        let px = match request.order_type {
+            OrderType::Limit => {
+                let Some(price) = request.price.filter(|price| !price.is_zero()) else {
+                    return Err(DriverError::Config(format!(
+                        "limit order on {} needs a non-zero price",
+                        request.inst_id
+                    )));
+                };
+                Some(serialize_price(price, instrument.tick_size))
+            }
+            OrderType::Market | OrderType::OptimalLimitIoc => None,
+        };
+        Ok(Self {
+            inst_id: request.inst_id.clone(),
+            td_mode,
+            side: request.side,
+            ccy,
+            tgt_ccy,
+            // Close intents in long/short mode never flip the position.
+            reduce_only: (pos_side.is_some()
+                && request.position_intent == Some(PositionIntent::Close))
+            .then_some(true),
+            pos_side,
+            ord_type: request.order_type,
+            px,
+            sz,
+            cl_ord_id: request.client_order_id.clone(),
+        })
+    }
+}
+
+/// Pre-serialized static order fields for one instrument.
+///
+/// Profiling the quote loop showed serde re-encoding the same static
+/// fields — `instId`, `tdMode`, the margin `ccy` — for every order. A
+/// template renders those once;
+/// [`render_into`](OrderTemplate::render_into) then only splices the
+/// per-order fields (side, order type, price, size, `clOrdId`) into the
+/// caller's buffer, byte-identical to `serde_json::to_string` on the same
+/// [`OkexOrderParams`]. Cache templates per instrument and gate reuse on
+/// [`matches`](OrderTemplate::matches): a trade-mode or account-mode
+/// change makes the statics stale, and a mismatch rebuilds instead of
+/// rendering them.
+#[derive(Debug)]
+pub struct OrderTemplate {
+    inst_id: String,
+    td_mode: TradeMode,
+    ccy: Option<String>,
+    /// `{"instId":"…","tdMode":"…","side":"` — everything ahead of the
+    /// first per-order field.
+    prefix: String,
+    /// `,"ccy":"…"` for margin orders, empty otherwise; spliced after
+    /// `sz` to keep serde's field order.
+    ccy_segment: String,
+}
+
+/// Whether `s` serializes as itself inside a JSON string, with no escape
+/// work. Everything the param builder emits qualifies; a caller-supplied
+/// `clOrdId` might not.
+fn is_escape_free(s: &str) -> bool {
+    s.bytes().all(|b| b >= 0x20 && b != b'"' && b != b'\\')
+}
+
+impl OrderTemplate {
+    /// Capture the static fields of `params` as a rendered prefix.
+    pub fn new(params: &OkexOrderParams) -> Self {
+        let td_mode = match params.td_mode {
+            TradeMode::Cash => "cash",
+            TradeMode::Cross => "cross",
+            TradeMode::Isolated => "isolated",
+        };
+        let ccy_segment = params
+            .ccy
+            .as_deref()
+            .map(|ccy| format!(r#","ccy":"{ccy}""#))
+            .unwrap_or_default();
+        Self {
+            inst_id: params.inst_id.clone(),
+            td_mode: params.td_mode,
+            ccy: params.ccy.clone(),
+            prefix: format!(
+                r#"{{"instId":"{}","tdMode":"{td_mode}","side":""#,
+                params.inst_id
+            ),
+            ccy_segment,
+        }
+    }
+
+    /// Buffer capacity that fits a typical rendered order without
+    /// reallocating: the static parts plus headroom for the per-order
+    /// fields.
+    pub fn rendered_capacity(&self) -> usize {
+        self.prefix.len() + self.ccy_segment.len() + 96
+    }
+
+    /// Whether the captured statics still describe `params`; `false`
+    /// after a trade-mode or margin-currency change.
+    pub fn matches(&self, params: &OkexOrderParams) -> bool {
+        self.inst_id == params.inst_id
+            && self.td_mode == params.td_mode
+            && self.ccy == params.ccy
+    }
+
+    /// Append `params` rendered as the `/api/v5/trade/order` body to
+    /// `buf`, byte-identical to `serde_json::to_string`. Dynamic fields
+    /// that would need JSON escaping (only possible via a hand-set
+    /// `clOrdId`) fall back to serde for the whole order.
+    pub fn render_into(
+        &self,
+        params: &OkexOrderParams,
+        buf: &mut String,
+    ) -> crate::errors::DriverResult<()> {
+        debug_assert!(self.matches(params), "stale template for {}", params.inst_id);
+        let escape_free = [
+            params.px.as_deref(),
+            Some(params.sz.as_str()),
+            params.tgt_ccy.as_deref(),
+            params.cl_ord_id.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .all(is_escape_free);
+        if !escape_free {
+            buf.push_str(&serde_json::to_string(params)?);
+            return Ok(());
+        }
+
+        buf.push_str(&self.prefix);
+        buf.push_str(match params.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        });
+        buf.push_str(r#"","ordType":""#);
+        buf.push_str(match params.ord_type {
+            OrderType::Limit => "limit",
+            OrderType::Market => "market",
+            OrderType::OptimalLimitIoc => "optimal_limit_ioc",
+        });
+        buf.push('"');
+        if let Some(px) = &params.px {
+            buf.push_str(r#","px":""#);
+            buf.push_str(px);
+            buf.push('"');
+        }
+        buf.push_str(r#","sz":""#);
+        buf.push_str(&params.sz);
+        buf.push('"');
+        buf.push_str(&self.ccy_segment);
+        if let Some(tgt_ccy) = &params.tgt_ccy {
+            buf.push_str(r#","tgtCcy":""#);
+            buf.push_str(tgt_ccy);
+            buf.push('"');
+        }
+        if let Some(pos_side) = params.pos_side {
+            buf.push_str(r#","posSide":""#);
+            buf.push_str(match pos_side {
+                PosSide::Long => "long",
+                PosSide::Short => "short",
+            });
+            buf.push('"');
+        }
+        if let Some(reduce_only) = params.reduce_only {
+            buf.push_str(r#","reduceOnly":"#);
+            buf.push_str(if reduce_only { "true" } else { "false" });
+        }
+        if let Some(cl_ord_id) = &params.cl_ord_id {
+            buf.push_str(r#","clOrdId":""#);
+            buf.push_str(cl_ord_id);
+            buf.push('"');
+        }
+        buf.push('}');
+        Ok(())
+    }
+}
+
+/// Effective per-instrument-type `tdMode` defaults, resolved from the
+/// account level so orders never guess and run into 51010 rejections.
+///
+/// The matrix: a simple account (acctLv 1) trades spot with `cash` and
+/// nothing else; single-currency margin (2) keeps `cash` spot and trades
+/// margin/contracts with the configured cross/isolated mode (cross when the
+/// configuration says `cash`); multi-currency and portfolio margin (3/4)
+/// additionally trade spot as `cross`. The margin settlement `ccy` stays
+/// per-instrument (the pair's quote currency, derived by the builder).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderDefaults {
+    /// `acctLv` the defaults were resolved from; compared on refresh to
+    /// detect account mode changes behind the driver's back.
+    pub acct_lv: String,
+    /// `tdMode` for SPOT orders.
+    pub spot: TradeMode,
+    /// `tdMode` for MARGIN orders; `None` when the account level cannot
+    /// trade margin at all.
+    pub margin: Option<TradeMode>,
+    /// `tdMode` for SWAP/FUTURES/OPTION orders; `None` as above.
+    pub contracts: Option<TradeMode>,
+}
+
+impl OrderDefaults {
+    /// Resolve the defaults from the account config, validating the
+    /// configured trade mode against the account level first so an invalid
+    /// override fails here instead of as a per-order exchange rejection.
+    pub fn resolve(
+        account: &crate::api_structs::OkexAccountConfig,
+        configured: TradeMode,
+    ) -> crate::errors::DriverResult<Self> {
+        account.validate_trade_mode(configured)?;
+        let leveraged = match configured {
+            TradeMode::Cash => TradeMode::Cross,
+            mode => mode,
+        };
+        Ok(match account.acct_lv.as_str() {
+            "1" => Self {
+                acct_lv: account.acct_lv.clone(),
+                spot: TradeMode::Cash,
+                margin: None,
+                contracts: None,
+            },
+            "2" => Self {
+                acct_lv: account.acct_lv.clone(),
+                spot: TradeMode::Cash,
+                margin: Some(leveraged),
+                contracts: Some(leveraged),
+            },
+            _ => Self {
+                acct_lv: account.acct_lv.clone(),
+                spot: TradeMode::Cross,
+                margin: Some(leveraged),
+                contracts: Some(leveraged),
+            },
+        })
+    }
+
+    /// The `tdMode` an order on `instrument` should carry; an error for
+    /// instrument types the account level cannot trade.
+    pub fn td_mode_for(&self, instrument: &Instrument) -> crate::errors::DriverResult<TradeMode> {
+        let resolved = match instrument.inst_type() {
+            "SPOT" => Some(self.spot),
+            "MARGIN" => self.margin,
+            _ => self.contracts,
+        };
+        resolved.ok_or_else(|| {
+            crate::errors::DriverError::Config(format!(
+                "account level {} cannot trade {} ({})",
+                self.acct_lv,
+                instrument.inst_type(),
+                instrument.inst_id
+            ))
+        })
+    }
+}
+
+/// Reference to an order by whichever id the caller has: right after
+/// placement only the client order id exists; after the ack, the exchange
+/// id is preferred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderRef {
+    ExchangeId(String),
+    ClientId(String),
+}
+
+impl OrderRef {
+    /// The id value, regardless of kind.
+    pub fn id(&self) -> &str {
+        match self {
+            OrderRef::ExchangeId(id) | OrderRef::ClientId(id) => id,
+        }
+    }
+
+    /// Cancel/amend arg payload: `ordId` for exchange ids, `clOrdId` for
+    /// client ids.
+    pub fn to_args(&self, inst_id: &str) -> serde_json::Value {
+        match self {
+            OrderRef::ExchangeId(id) => {
+                serde_json::json!({ "instId": inst_id, "ordId": id })
+            }
+            OrderRef::ClientId(id) => {
+                serde_json::json!({ "instId": inst_id, "clOrdId": id })
+            }
+        }
+    }
+}
+
+/// Parse an OKX millisecond timestamp string; `None` on anything that does
+/// not parse, never a fallback clock read.
+pub(crate) fn parse_exchange_millis(ts: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    ts.parse::<i64>()
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+}
+
+/// An exchange order normalized to internal conventions, with ingestion
+/// time and exchange time kept strictly apart.
+///
+/// `internal_created_at` is always the driver-receive time;
+/// `exchange_created_at`/`exchange_updated_at` come only from `cTime` /
+/// `uTime` and are `None` when the exchange timestamp does not parse, so
+/// latency dashboards never mistake ingestion time for creation time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawOrder {
+    pub inst_id: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub price: Option<Decimal>,
+    pub size: Decimal,
+    pub side: String,
+    /// Exchange `ordType` (`limit`, `market`, `optimal_limit_ioc`, ...);
+    /// empty when the source payload omitted it.
+    pub order_type: String,
+    pub state: String,
+    /// When this driver received the record.
+    pub internal_created_at: chrono::DateTime<chrono::Utc>,
+    /// Exchange `cTime`; `None` when absent or unparseable.
+    pub exchange_created_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exchange `uTime`; only order updates carry it.
+    pub exchange_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl RawOrder {
+    /// Normalize one `orders-pending` entry received at `received_at`.
+    pub fn from_pending(
+        order: &crate::api_structs::OkexPendingOrder,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        Self {
+            inst_id: order.inst_id.clone(),
+            order_id: order.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            price: order.price,
+            size: order.size,
+            side: order.side.clone(),
+            order_type: order.order_type.clone(),
+            state: order.state.clone(),
+            internal_created_at: received_at,
+            exchange_created_at: parse_exchange_millis(&order.created_at),
+            exchange_updated_at: None,
+        }
+    }
+
+    /// Normalize one order update received at `received_at`.
+    ///
+    /// A quote-sized market order echoes `sz` in quote units; `size` is
+    /// reported from the filled base amount instead (zero before any fill)
+    /// so it is base-denominated regardless of the input denomination.
+    pub fn from_update(
+        update: &crate::api_structs::OkexOrderUpdate,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let size = if update.target_currency == "quote_ccy" {
+            update.accumulated_fill_size.unwrap_or(Decimal::ZERO)
+        } else {
+            update.size
+        };
+        Self {
+            inst_id: update.inst_id.clone(),
+            order_id: update.order_id.clone(),
+            client_order_id: update.client_order_id.clone(),
+            price: update.price,
+            size,
+            side: update.side.clone(),
+            order_type: update.order_type.clone(),
+            state: update.state.clone(),
+            internal_created_at: received_at,
+            exchange_created_at: None,
+            exchange_updated_at: parse_exchange_millis(&update.updated_at),
+        }
+    }
+
+    /// Whether this record ends the order's lifecycle as a normal
+    /// completion.
+    ///
+    /// IOC-style types (`optimal_limit_ioc`) report their unfilled
+    /// remainder as `canceled` — possibly after partial fills — so for them
+    /// that state is the order finishing, not an external cancellation.
+    pub fn is_normal_completion(&self) -> bool {
+        match self.state.as_str() {
+            "filled" => true,
+            "canceled" => self.order_type == "optimal_limit_ioc",
+            _ => false,
+        }
+    }
+}
+
+/// One order that failed inside a batch operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchItemError {
+    pub order_id: String,
+    /// Client order id the rejected item carried, when any; lets callers
+    /// tie a rejection back to their own order without the exchange id.
+    pub client_order_id: Option<String>,
+    /// OKX per-item `sCode`.
+    pub code: String,
+    pub message: String,
+}
+
+/// Outcome of a batch order operation (amend, cancel): which orders went
+/// through and which were rejected, in submission order within each list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<BatchItemError>,
+}
+
+impl BatchOutcome {
+    pub fn merge(&mut self, other: BatchOutcome) {
+        self.succeeded.extend(other.succeeded);
+        self.failed.extend(other.failed);
+    }
+
+    pub fn all_succeeded(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: dec("0.1"),
+            lot_size: dec("0.00000001"),
+            min_size: dec("0.00001"),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    #[test]
+    fn build_normalizes_price_and_size() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1700") / dec("1")),
+            amount: Decimal::ONE / dec("3"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: Some("abc123".to_string()),
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert_eq!(params.px.as_deref(), Some("43250.1"));
+        assert_eq!(params.sz, "0.33333333");
+    }
+
+    #[test]
+    fn serialized_payload_has_exact_strings() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            price: Some(dec("0.1") + dec("0.2")),
+            amount: dec("2.000"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"instId":"BTC-USDT","tdMode":"cash","side":"sell","ordType":"limit","px":"0.3","sz":"2"}"#
+        );
+    }
+
+    /// Every structurally distinct order shape the builder can emit: each
+    /// optional field present and absent, all trade modes and order types.
+    fn order_shape_matrix() -> Vec<OkexOrderParams> {
+        let base = OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: TradeMode::Cash,
+            side: Side::Buy,
+            ord_type: OrderType::Limit,
+            px: Some("43250.1".to_string()),
+            sz: "0.01".to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: None,
+        };
+        vec![
+            base.clone(),
+            OkexOrderParams {
+                side: Side::Sell,
+                cl_ord_id: Some("myalgo42abc".to_string()),
+                ..base.clone()
+            },
+            // Spot market orders: explicit tgtCcy, no price.
+            OkexOrderParams {
+                ord_type: OrderType::Market,
+                px: None,
+                tgt_ccy: Some("base_ccy".to_string()),
+                ..base.clone()
+            },
+            OkexOrderParams {
+                ord_type: OrderType::Market,
+                px: None,
+                sz: "1000".to_string(),
+                tgt_ccy: Some("quote_ccy".to_string()),
+                ..base.clone()
+            },
+            // Margin order carrying the settlement currency.
+            OkexOrderParams {
+                td_mode: TradeMode::Cross,
+                ccy: Some("USDT".to_string()),
+                ..base.clone()
+            },
+            // Long/short-mode contract order closing a leg.
+            OkexOrderParams {
+                inst_id: "BTC-USDT-SWAP".to_string(),
+                td_mode: TradeMode::Isolated,
+                sz: "5".to_string(),
+                pos_side: Some(PosSide::Short),
+                reduce_only: Some(true),
+                cl_ord_id: Some("close7".to_string()),
+                ..base.clone()
+            },
+            OkexOrderParams {
+                inst_id: "BTC-USDT-SWAP".to_string(),
+                td_mode: TradeMode::Cross,
+                ord_type: OrderType::OptimalLimitIoc,
+                px: None,
+                sz: "3".to_string(),
+                ..base
+            },
+        ]
+    }
+
+    #[test]
+    fn template_rendering_matches_serde_byte_for_byte() {
+        for params in order_shape_matrix() {
+            let template = OrderTemplate::new(&params);
+            let mut rendered = String::with_capacity(template.rendered_capacity());
+            template.render_into(&params, &mut rendered).unwrap();
+            assert_eq!(
+                rendered,
+                serde_json::to_string(&params).unwrap(),
+                "template diverged for {params:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn template_falls_back_to_serde_for_escapable_client_ids() {
+        let mut params = order_shape_matrix().remove(0);
+        params.cl_ord_id = Some(r#"we"ird\id"#.to_string());
+        let template = OrderTemplate::new(&params);
+        let mut rendered = String::new();
+        template.render_into(&params, &mut rendered).unwrap();
+        assert_eq!(rendered, serde_json::to_string(&params).unwrap());
+    }
+
+    #[test]
+    fn template_statics_go_stale_on_mode_changes() {
+        let params = order_shape_matrix().remove(0);
+        let template = OrderTemplate::new(&params);
+        assert!(template.matches(&params));
+        assert!(!template.matches(&OkexOrderParams {
+            td_mode: TradeMode::Cross,
+            ..params.clone()
+        }));
+        assert!(!template.matches(&OkexOrderParams {
+            ccy: Some("USDT".to_string()),
+            ..params
+        }));
+    }
+
+    #[test]
+    #[ignore = "manual benchmark; run with --ignored --nocapture"]
+    fn bench_template_vs_serde_order_serialization() {
+        let params = order_shape_matrix().remove(1);
+        const ITERS: usize = 200_000;
+
+        let started = std::time::Instant::now();
+        let mut bytes = 0usize;
+        for _ in 0..ITERS {
+            bytes += serde_json::to_string(&params).unwrap().len();
+        }
+        println!("serde: {ITERS} orders ({bytes} bytes) in {:?}", started.elapsed());
+
+        let template = OrderTemplate::new(&params);
+        let mut buf = String::with_capacity(template.rendered_capacity());
+        let started = std::time::Instant::now();
+        let mut bytes = 0usize;
+        for _ in 0..ITERS {
+            buf.clear();
+            template.render_into(&params, &mut buf).unwrap();
+            bytes += buf.len();
+        }
+        println!("template: {ITERS} orders ({bytes} bytes) in {:?}", started.elapsed());
+    }
+
+    fn pending_order(c_time: &str) -> crate::api_structs::OkexPendingOrder {
+        serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT","ordId":"ord1","clOrdId":"clord1","px":"100","sz":"1","side":"buy","state":"live","cTime":"{c_time}"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn raw_order_keeps_internal_and_exchange_times_apart() {
+        let received_at = chrono::Utc::now();
+        let order = RawOrder::from_pending(&pending_order("1700000000000"), received_at);
+        assert_eq!(order.internal_created_at, received_at);
+        assert_eq!(
+            order.exchange_created_at,
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_000)
+        );
+        assert_eq!(order.exchange_updated_at, None);
+    }
+
+    #[test]
+    fn unparseable_exchange_time_stays_none() {
+        let received_at = chrono::Utc::now();
+        let order = RawOrder::from_pending(&pending_order("garbage"), received_at);
+        // No clock fallback: ingestion time and creation time must never be
+        // conflated.
+        assert_eq!(order.exchange_created_at, None);
+        assert_eq!(order.internal_created_at, received_at);
+    }
+
+    #[test]
+    fn order_update_carries_exchange_updated_at() {
+        let update: crate::api_structs::OkexOrderUpdate = serde_json::from_str(
+            r#"{"instId":"BTC-USDT","ordId":"ord1","state":"filled","px":"100","avgPx":"100","sz":"1","accFillSz":"1","side":"buy","uTime":"1700000000500"}"#,
+        )
+        .unwrap();
+        let order = RawOrder::from_update(&update, chrono::Utc::now());
+        assert_eq!(
+            order.exchange_updated_at,
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_500)
+        );
+        assert_eq!(order.exchange_created_at, None);
+    }
+
+    #[test]
+    fn margin_order_carries_trade_mode_and_margin_currency() {
+        let instrument = Instrument {
+            expiry_time: None,
+            margin: true,
+            ..instrument()
+        };
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1")),
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument, TradeMode::Cross, OkexPositionMode::Net).unwrap();
+        assert_eq!(params.td_mode, TradeMode::Cross);
+        assert_eq!(params.ccy.as_deref(), Some("USDT"));
+        let payload: serde_json::Value =
+            serde_json::to_value(&params).unwrap();
+        assert_eq!(payload["tdMode"], "cross");
+        assert_eq!(payload["ccy"], "USDT");
+        assert_eq!(instrument.inst_type(), "MARGIN");
+    }
+
+    #[test]
+    fn spot_order_never_carries_a_margin_currency() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1")),
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert!(params.ccy.is_none());
+        assert!(!serde_json::to_string(&params).unwrap().contains("ccy"));
+    }
+
+    #[test]
+    fn quote_sized_spot_market_order_passes_the_quote_amount_through() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: None,
+            amount: dec("1000.00"),
+            size_denomination: SizeDenomination::Quote,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert_eq!(params.sz, "1000");
+        assert_eq!(params.tgt_ccy.as_deref(), Some("quote_ccy"));
+        assert!(serde_json::to_string(&params)
+            .unwrap()
+            .contains(r#""tgtCcy":"quote_ccy""#));
+    }
+
+    #[test]
+    fn base_sized_spot_market_order_pins_tgt_ccy_explicitly() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: None,
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        // OKX would otherwise read a market-buy `sz` as quote units.
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert_eq!(params.tgt_ccy.as_deref(), Some("base_ccy"));
+        assert_eq!(params.sz, "0.5");
+    }
+
+    #[test]
+    fn quote_sized_limit_order_divides_by_price_and_floors_to_lot() {
+        let instrument = Instrument {
+            lot_size: dec("0.0001"),
+            ..instrument()
+        };
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1")),
+            amount: dec("1000"),
+            size_denomination: SizeDenomination::Quote,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument, TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        // 1000 / 43250.1 = 0.02312133..., floored to the lot, never up.
+        assert_eq!(params.sz, "0.0231");
+        assert!(params.tgt_ccy.is_none(), "emulated sizing must not set tgtCcy");
+    }
+
+    #[test]
+    fn quote_sizing_is_rejected_for_contracts_and_priceless_limits() {
+        let swap = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            contract_value: Some(dec("0.01")),
+            ..instrument()
+        };
+        let request = OrderRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: None,
+            amount: dec("1000"),
+            size_denomination: SizeDenomination::Quote,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+        let err = OkexOrderParams::build(&request, &swap, TradeMode::Cross, OkexPositionMode::Net).unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::Config(_)),
+            "got: {err}"
+        );
+
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: None,
+            amount: dec("1000"),
+            size_denomination: SizeDenomination::Quote,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+        let err = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        assert!(err.to_string().contains("needs a non-zero price"), "{err}");
+    }
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: dec("0.1"),
+            lot_size: dec("1"),
+            min_size: dec("1"),
+            contract_value: Some(dec("0.01")),
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn contract_request(side: Side, intent: Option<PositionIntent>) -> OrderRequest {
+        OrderRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1")),
+            amount: dec("5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: intent,
+            client_order_id: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn pos_side_covers_all_side_intent_combinations_in_long_short_mode() {
+        // Closing a long is side=sell posSide=long; a naive sell→short
+        // mapping would open a short instead.
+        let cases = [
+            (Side::Buy, PositionIntent::Open, "long"),
+            (Side::Sell, PositionIntent::Close, "long"),
+            (Side::Sell, PositionIntent::Open, "short"),
+            (Side::Buy, PositionIntent::Close, "short"),
+        ];
+        for (side, intent, expected) in cases {
+            let params = OkexOrderParams::build(
+                &contract_request(side, Some(intent)),
+                &swap_instrument(),
+                TradeMode::Cross,
+                OkexPositionMode::LongShort,
+            )
+            .unwrap();
+            let payload = serde_json::to_value(&params).unwrap();
+            assert_eq!(payload["posSide"], expected, "{side:?} {intent:?}");
+        }
+    }
+
+    #[test]
+    fn long_short_mode_requires_an_intent_for_contract_orders() {
+        for side in [Side::Buy, Side::Sell] {
+            let err = OkexOrderParams::build(
+                &contract_request(side, None),
+                &swap_instrument(),
+                TradeMode::Cross,
+                OkexPositionMode::LongShort,
+            )
+            .unwrap_err();
+            assert!(
+                matches!(err, crate::errors::DriverError::Config(_)),
+                "got: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn net_mode_and_spot_orders_never_carry_pos_side() {
+        for intent in [None, Some(PositionIntent::Open), Some(PositionIntent::Close)] {
+            for side in [Side::Buy, Side::Sell] {
+                let params = OkexOrderParams::build(
+                    &contract_request(side, intent),
+                    &swap_instrument(),
+                    TradeMode::Cross,
+                    OkexPositionMode::Net,
+                )
+                .unwrap();
+                assert!(params.pos_side.is_none(), "{side:?} {intent:?}");
+            }
+        }
+        // Spot has no positions even in long/short mode.
+        let spot = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            position_intent: None,
+            ..contract_request(Side::Sell, None)
+        };
+        let params = OkexOrderParams::build(
+            &spot,
+            &instrument(),
+            TradeMode::Cash,
+            OkexPositionMode::LongShort,
+        )
+        .unwrap();
+        assert!(params.pos_side.is_none());
+        assert!(!serde_json::to_string(&params).unwrap().contains("posSide"));
+    }
+
+    #[test]
+    fn minimum_size_boundary_is_inclusive() {
+        let instrument = Instrument {
+            lot_size: dec("0.001"),
+            min_size: dec("0.01"),
+            ..instrument()
+        };
+        let mut request = contract_request(Side::Buy, None);
+        request.inst_id = "BTC-USDT".to_string();
+
+        // Exactly the minimum passes...
+        request.amount = dec("0.01");
+        let params = OkexOrderParams::build(&request, &instrument, TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert_eq!(params.sz, "0.01");
+
+        // ...one lot below is rejected locally with both values attached.
+        request.amount = dec("0.009");
+        let err = OkexOrderParams::build(&request, &instrument, TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        match err {
+            crate::errors::DriverError::BelowMinimumSize { min, requested } => {
+                assert_eq!(min, dec("0.01"));
+                assert_eq!(requested, dec("0.009"));
+            }
+            other => panic!("expected BelowMinimumSize, got: {other}"),
+        }
+    }
+
+    #[test]
+    fn minimum_size_applies_after_quote_conversion() {
+        let instrument = Instrument {
+            lot_size: dec("0.0001"),
+            min_size: dec("0.01"),
+            ..instrument()
+        };
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("100000")),
+            amount: dec("500"),
+            size_denomination: SizeDenomination::Quote,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        // 500 / 100000 = 0.005 base, below the 0.01 minimum.
+        let err = OkexOrderParams::build(&request, &instrument, TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::BelowMinimumSize { .. }),
+            "got: {err}"
+        );
+    }
+
+    #[test]
+    fn quote_sized_update_reports_base_size_from_fills() {
+        let update: crate::api_structs::OkexOrderUpdate = serde_json::from_str(
+            r#"{"instId":"BTC-USDT","ordId":"ord1","state":"filled","px":"","avgPx":"43250.1","sz":"1000","accFillSz":"0.0231","tgtCcy":"quote_ccy","side":"buy","uTime":"1700000000500"}"#,
+        )
+        .unwrap();
+        let order = RawOrder::from_update(&update, chrono::Utc::now());
+        assert_eq!(order.size, dec("0.0231"));
+    }
+
+    #[test]
+    fn zero_sizes_are_rejected_before_serialization() {
+        // A zero amount passed straight through from upstream...
+        let mut request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec("43250.1")),
+            amount: Decimal::ZERO,
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+        let err = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::ZeroSize { .. }),
+            "got: {err}"
+        );
+
+        // ...a zero quote amount, which the minimum-size check exempts...
+        request.order_type = OrderType::Market;
+        request.price = None;
+        request.size_denomination = SizeDenomination::Quote;
+        let err = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::ZeroSize { .. }),
+            "got: {err}"
+        );
+
+        // ...and an amount below one lot that flooring ate entirely.
+        let coarse = Instrument {
+            lot_size: dec("0.001"),
+            min_size: dec("0.001"),
+            ..instrument()
+        };
+        request.order_type = OrderType::Limit;
+        request.price = Some(dec("43250.1"));
+        request.size_denomination = SizeDenomination::Base;
+        request.amount = dec("0.0004");
+        let err = OkexOrderParams::build(&request, &coarse, TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+        match err {
+            crate::errors::DriverError::ZeroSize { requested, .. } => {
+                assert_eq!(requested, dec("0.0004"));
+            }
+            other => panic!("expected ZeroSize, got: {other}"),
+        }
+    }
+
+    #[test]
+    fn limit_orders_require_a_nonzero_price() {
+        let mut request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(Decimal::ZERO),
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+        // `"px":"0"` is as rejected by OKX as an absent price.
+        for price in [Some(Decimal::ZERO), None] {
+            request.price = price;
+            let err = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap_err();
+            assert!(err.to_string().contains("non-zero price"), "{err}");
+        }
+    }
+
+    #[test]
+    fn a_stray_price_on_a_market_order_never_reaches_the_payload() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: Some(dec("43250.1")),
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert!(params.px.is_none());
+        assert!(!serde_json::to_string(&params).unwrap().contains("px"));
+    }
+
+    #[test]
+    fn market_order_omits_price() {
+        let request = OrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: None,
+            amount: dec("0.5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        };
+
+        let params = OkexOrderParams::build(&request, &instrument(), TradeMode::Cash, OkexPositionMode::Net).unwrap();
+        assert!(params.px.is_none());
+        assert!(!serde_json::to_string(&params).unwrap().contains("px"));
+    }
+
+    fn optimal_limit_ioc_request(inst_id: &str) -> OrderRequest {
+        OrderRequest {
+            inst_id: inst_id.to_string(),
+            side: Side::Buy,
+            order_type: OrderType::OptimalLimitIoc,
+            price: None,
+            amount: dec("5"),
+            size_denomination: SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn optimal_limit_ioc_serializes_without_a_price() {
+        let mut request = optimal_limit_ioc_request("BTC-USDT-SWAP");
+        // Even a stray caller price must not leak into the payload; the
+        // exchange derives the protected band itself.
+        request.price = Some(dec("43250.1"));
+
+        let params = OkexOrderParams::build(
+            &request,
+            &swap_instrument(),
+            TradeMode::Cross,
+            OkexPositionMode::Net,
+        )
+        .unwrap();
+        assert!(params.px.is_none());
+        let json = serde_json::to_string(&params).unwrap();
+        assert!(json.contains(r#""ordType":"optimal_limit_ioc""#), "{json}");
+        assert!(!json.contains("px"), "{json}");
+    }
+
+    #[test]
+    fn optimal_limit_ioc_is_rejected_off_contracts() {
+        let mut request = optimal_limit_ioc_request("BTC-USDT");
+        request.amount = dec("0.5");
+        let err = OkexOrderParams::build(
+            &request,
+            &instrument(),
+            TradeMode::Cash,
+            OkexPositionMode::Net,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::Config(_)),
+            "got: {err}"
+        );
+        assert!(err.to_string().contains("SWAP/FUTURES"), "{err}");
+    }
+
+    #[test]
+    fn an_ioc_partial_fill_then_cancel_reads_as_normal_completion() {
+        let sequence = [
+            r#"{"instId":"BTC-USDT-SWAP","ordId":"ord1","ordType":"optimal_limit_ioc","state":"partially_filled","px":"","avgPx":"43250.1","sz":"5","accFillSz":"3","side":"buy","uTime":"1700000000500"}"#,
+            r#"{"instId":"BTC-USDT-SWAP","ordId":"ord1","ordType":"optimal_limit_ioc","state":"canceled","px":"","avgPx":"43250.1","sz":"5","accFillSz":"3","side":"buy","uTime":"1700000000600"}"#,
+        ];
+        let orders: Vec<RawOrder> = sequence
+            .iter()
+            .map(|raw| {
+                let update: crate::api_structs::OkexOrderUpdate =
+                    serde_json::from_str(raw).unwrap();
+                RawOrder::from_update(&update, chrono::Utc::now())
+            })
+            .collect();
+
+        assert!(!orders[0].is_normal_completion(), "partial fill is not terminal");
+        // The cancel is the IOC remainder expiring after 3 of 5 contracts
+        // filled — a completed order, not an external cancellation.
+        assert_eq!(orders[1].order_type, "optimal_limit_ioc");
+        assert!(orders[1].is_normal_completion());
+
+        // The same cancel on a plain limit order stays an abnormal end.
+        let update: crate::api_structs::OkexOrderUpdate = serde_json::from_str(
+            r#"{"instId":"BTC-USDT","ordId":"ord2","ordType":"limit","state":"canceled","px":"100","sz":"1","side":"buy","uTime":"1700000000700"}"#,
+        )
+        .unwrap();
+        assert!(!RawOrder::from_update(&update, chrono::Utc::now()).is_normal_completion());
+    }
+
+    fn account_config(acct_lv: &str) -> crate::api_structs::OkexAccountConfig {
+        serde_json::from_str(&format!(
+            r#"{{"posMode":"net_mode","acctLv":"{acct_lv}"}}"#
+        ))
+        .unwrap()
+    }
+
+    fn margin_instrument() -> Instrument {
+        Instrument {
+            margin: true,
+            ..instrument()
+        }
+    }
+
+    #[test]
+    fn default_resolution_matrix_across_account_levels() {
+        // Simple: spot-only, cash.
+        let simple = OrderDefaults::resolve(&account_config("1"), TradeMode::Cash).unwrap();
+        assert_eq!(simple.td_mode_for(&instrument()).unwrap(), TradeMode::Cash);
+        assert!(simple.td_mode_for(&margin_instrument()).is_err());
+        assert!(simple.td_mode_for(&swap_instrument()).is_err());
+
+        // Single-currency margin: cash spot, cross elsewhere by default.
+        let single = OrderDefaults::resolve(&account_config("2"), TradeMode::Cash).unwrap();
+        assert_eq!(single.td_mode_for(&instrument()).unwrap(), TradeMode::Cash);
+        assert_eq!(single.td_mode_for(&margin_instrument()).unwrap(), TradeMode::Cross);
+        assert_eq!(single.td_mode_for(&swap_instrument()).unwrap(), TradeMode::Cross);
+
+        // Multi-currency and portfolio margin trade spot as cross too.
+        for acct_lv in ["3", "4"] {
+            let multi = OrderDefaults::resolve(&account_config(acct_lv), TradeMode::Cash).unwrap();
+            assert_eq!(multi.td_mode_for(&instrument()).unwrap(), TradeMode::Cross);
+            assert_eq!(multi.td_mode_for(&swap_instrument()).unwrap(), TradeMode::Cross);
+        }
+    }
+
+    #[test]
+    fn isolated_override_applies_to_leveraged_types_only() {
+        let defaults = OrderDefaults::resolve(&account_config("2"), TradeMode::Isolated).unwrap();
+        assert_eq!(defaults.td_mode_for(&instrument()).unwrap(), TradeMode::Cash);
+        assert_eq!(
+            defaults.td_mode_for(&margin_instrument()).unwrap(),
+            TradeMode::Isolated
+        );
+        assert_eq!(
+            defaults.td_mode_for(&swap_instrument()).unwrap(),
+            TradeMode::Isolated
+        );
+    }
+
+    #[test]
+    fn margin_override_on_a_simple_account_is_rejected() {
+        let err = OrderDefaults::resolve(&account_config("1"), TradeMode::Cross).unwrap_err();
+        assert!(
+            matches!(err, crate::errors::DriverError::Config(_)),
+            "got: {err}"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Position accounting across both account modes.
+//!
+//! In net mode `/api/v5/account/positions` reports one signed `pos` per
+//! instrument, but in long/short mode a pair holds a long and a short leg
+//! at the same time — `posSide` splits them and both legs report positive
+//! sizes. Summing entries naively nets the legs away (or, worse, adds
+//! them), so aggregation keys on (instrument, `posSide`) and keeps the
+//! gross legs with the net derived from them. Sizes stay in the wire
+//! denomination: contracts, or base units for spot margin.
+
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::OkexPosition;
+
+/// Both legs of one instrument's position, gross; sizes are positive
+/// magnitudes in the wire denomination.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionLegs {
+    pub long: Decimal,
+    pub short: Decimal,
+}
+
+impl PositionLegs {
+    /// Signed net exposure: long minus short. In net mode this is the
+    /// exchange's own `pos`; in long/short mode it is derived here.
+    pub fn net(&self) -> Decimal {
+        self.long - self.short
+    }
+}
+
+/// Fold raw position entries into per-instrument legs.
+///
+/// Long/short-mode entries land on the leg their `posSide` names; net-mode
+/// entries split on the sign of `pos`. Multiple entries on the same leg
+/// (cross and isolated margin can coexist) sum.
+pub fn aggregate_positions(positions: &[OkexPosition]) -> BTreeMap<String, PositionLegs> {
+    let mut legs: BTreeMap<String, PositionLegs> = BTreeMap::new();
+    for position in positions {
+        let entry = legs.entry(position.inst_id.clone()).or_default();
+        match position.position_side.as_str() {
+            "long" => entry.long += position.position,
+            "short" => entry.short += position.position,
+            _ if position.position >= Decimal::ZERO => entry.long += position.position,
+            _ => entry.short += -position.position,
+        }
+    }
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(inst_id: &str, pos_side: &str, pos: &str) -> OkexPosition {
+        OkexPosition {
+            inst_id: inst_id.to_string(),
+            position_side: pos_side.to_string(),
+            position: pos.parse().unwrap(),
+            average_price: None,
+            unrealized_pnl: None,
+            leverage: None,
+            notional_usd: None,
+        }
+    }
+
+    #[test]
+    fn net_mode_entries_split_on_the_sign() {
+        let legs = aggregate_positions(&[
+            position("BTC-USDT-SWAP", "net", "120"),
+            position("ETH-USDT-SWAP", "net", "-40"),
+        ]);
+
+        assert_eq!(legs["BTC-USDT-SWAP"].long, "120".parse::<Decimal>().unwrap());
+        assert_eq!(legs["BTC-USDT-SWAP"].short, Decimal::ZERO);
+        // A net short keeps its magnitude on the short leg.
+        assert_eq!(legs["ETH-USDT-SWAP"].short, "40".parse::<Decimal>().unwrap());
+        assert_eq!(legs["ETH-USDT-SWAP"].net(), "-40".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn long_short_mode_keeps_both_gross_legs() {
+        // Both legs report positive sizes; a naive sum would read this
+        // hedged pair as a 180-contract position.
+        let legs = aggregate_positions(&[
+            position("BTC-USDT-SWAP", "long", "100"),
+            position("BTC-USDT-SWAP", "short", "80"),
+        ]);
+
+        let btc = &legs["BTC-USDT-SWAP"];
+        assert_eq!(btc.long, "100".parse::<Decimal>().unwrap());
+        assert_eq!(btc.short, "80".parse::<Decimal>().unwrap());
+        assert_eq!(btc.net(), "20".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn repeated_legs_sum_instead_of_overwriting() {
+        // Cross and isolated positions on the same leg coexist.
+        let legs = aggregate_positions(&[
+            position("BTC-USDT-SWAP", "long", "60"),
+            position("BTC-USDT-SWAP", "long", "40"),
+        ]);
+
+        assert_eq!(legs["BTC-USDT-SWAP"].long, "100".parse::<Decimal>().unwrap());
+    }
+}
@@ -0,0 +1,262 @@
+//! Fixed-point rendering of prices and sizes.
+//!
+//! OKX rejects numbers with more decimal places than the instrument's
+//! `tickSz`/`lotSz` allow, and rejects exponent notation outright. Every
+//! price or size that leaves the driver goes through these helpers.
+
+use rust_decimal::Decimal;
+
+/// Truncate `value` toward zero to a multiple of `step`, rendered with
+/// exactly the step's scale trimmed of trailing zeros.
+///
+/// Truncation (rather than rounding) guarantees the result never exceeds
+/// what the caller computed, which is the safe direction for both sizes and
+/// limit prices.
+pub fn quantize_to_step(value: Decimal, step: Decimal) -> Decimal {
+    if step.is_zero() {
+        return value.normalize();
+    }
+    let steps = (value / step).trunc();
+    (steps * step).normalize()
+}
+
+/// Render a Decimal as a plain fixed-point string, never exponent notation
+/// and without trailing zeros (`1.50` -> `"1.5"`, `1E-8` -> `"0.00000001"`).
+pub fn to_plain_string(value: Decimal) -> String {
+    value.normalize().to_string()
+}
+
+/// Normalize a price against the instrument tick size and render it.
+pub fn serialize_price(value: Decimal, tick_size: Decimal) -> String {
+    to_plain_string(quantize_to_step(value, tick_size))
+}
+
+/// Normalize a size against the instrument lot size and render it.
+pub fn serialize_size(value: Decimal, lot_size: Decimal) -> String {
+    to_plain_string(quantize_to_step(value, lot_size))
+}
+
+/// Outcome of a checked base/contract conversion.
+///
+/// `Decimal` holds 28 significant digits; converting between base units and
+/// contracts divides or multiplies by `ctVal`, and on inverse contracts with
+/// extreme prices the exact result can need more digits than that. The
+/// arithmetic then rounds silently, which has produced penny-level
+/// reconciliation breaks. The checked variants below surface exactly what
+/// was dropped instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckedConversion {
+    /// The converted value, as close as 28 significant digits allow.
+    pub value: Decimal,
+    /// Whether `remainder` exceeds the caller's epsilon in magnitude.
+    pub precision_loss: bool,
+    /// What the conversion dropped, always in base units, so callers on
+    /// either side of the conversion can sum it with amounts directly.
+    pub remainder: Decimal,
+}
+
+/// Default [`CheckedConversion::precision_loss`] tolerance: 1e-12 base
+/// units, far below any real lot size but above `Decimal`'s own noise
+/// floor at everyday magnitudes.
+pub fn default_precision_epsilon() -> Decimal {
+    Decimal::new(1, 12)
+}
+
+/// Base amount to exchange size in contracts (passthrough without a
+/// `ctVal`), reporting what the truncated quotient dropped: the exact
+/// amount is `value * contract_value + remainder`.
+pub fn checked_to_exchange_size(
+    amount: Decimal,
+    contract_value: Option<Decimal>,
+    epsilon: Decimal,
+) -> CheckedConversion {
+    let Some(contract_value) = contract_value.filter(|cv| !cv.is_zero()) else {
+        return CheckedConversion {
+            value: amount,
+            precision_loss: false,
+            remainder: Decimal::ZERO,
+        };
+    };
+    let contracts = amount / contract_value;
+    // Verify through partial products: multiplying the full quotient back
+    // can round to `amount` again and mask the loss, while its integral
+    // and fractional parts each stay within Decimal's precision.
+    let integral = contracts.trunc();
+    let fractional = contracts - integral;
+    let remainder = amount - integral * contract_value - fractional * contract_value;
+    CheckedConversion {
+        value: contracts,
+        precision_loss: remainder.abs() > epsilon,
+        remainder,
+    }
+}
+
+/// Exchange size in contracts back to a base amount (passthrough without a
+/// `ctVal`), reporting what the 28-digit product dropped so downstream sums
+/// can reconcile: the exact amount is `value + remainder` even when that
+/// sum itself is not representable.
+pub fn checked_to_internal_amount(
+    size: Decimal,
+    contract_value: Option<Decimal>,
+    epsilon: Decimal,
+) -> CheckedConversion {
+    let Some(contract_value) = contract_value.filter(|cv| !cv.is_zero()) else {
+        return CheckedConversion {
+            value: size,
+            precision_loss: false,
+            remainder: Decimal::ZERO,
+        };
+    };
+    let amount = size * contract_value;
+    // Recompute the product in two pieces: the integral and fractional
+    // parts of the size each stay within 28 digits far longer than the
+    // full product does, so their exact partial products expose what the
+    // rounded product dropped.
+    let integral = size.trunc();
+    let fractional = size - integral;
+    let remainder = (integral * contract_value - amount) + fractional * contract_value;
+    CheckedConversion {
+        value: amount,
+        precision_loss: remainder.abs() > epsilon,
+        remainder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn long_division_artifacts_are_clamped_to_tick() {
+        // amount / contract_value style expansion: 1/3 = 0.333...
+        let value = Decimal::ONE / dec("3");
+        assert_eq!(serialize_price(value, dec("0.01")), "0.33");
+        assert_eq!(serialize_price(value, dec("0.0001")), "0.3333");
+    }
+
+    #[test]
+    fn very_small_sizes_render_without_exponent() {
+        let value = Decimal::new(1, 8); // 1E-8
+        assert_eq!(to_plain_string(value), "0.00000001");
+        assert_eq!(serialize_size(value, dec("0.00000001")), "0.00000001");
+    }
+
+    #[test]
+    fn large_contract_counts_stay_integral() {
+        let value = dec("1234567");
+        assert_eq!(serialize_size(value, dec("1")), "1234567");
+        assert_eq!(serialize_size(dec("1234567.9"), dec("1")), "1234567");
+    }
+
+    #[test]
+    fn trailing_zeros_are_trimmed() {
+        assert_eq!(serialize_price(dec("43250.1000"), dec("0.1")), "43250.1");
+        assert_eq!(serialize_size(dec("2.000"), dec("0.001")), "2");
+    }
+
+    #[test]
+    fn truncation_never_rounds_up() {
+        assert_eq!(serialize_price(dec("0.999999"), dec("0.01")), "0.99");
+        assert_eq!(serialize_size(dec("0.0019"), dec("0.001")), "0.001");
+    }
+
+    #[test]
+    fn zero_step_only_normalizes() {
+        assert_eq!(serialize_price(dec("1.230"), Decimal::ZERO), "1.23");
+    }
+
+    #[test]
+    fn exact_conversions_report_no_loss() {
+        let to = checked_to_exchange_size(dec("2.5"), Some(dec("0.01")), default_precision_epsilon());
+        assert_eq!(to.value, dec("250"));
+        assert!(!to.precision_loss);
+        assert_eq!(to.remainder, Decimal::ZERO);
+
+        let spot = checked_to_exchange_size(dec("2.5"), None, default_precision_epsilon());
+        assert_eq!(spot.value, dec("2.5"));
+        assert!(!spot.precision_loss);
+    }
+
+    #[test]
+    fn an_overflowing_quotient_reports_the_dropped_base_units() {
+        // A meme-coin-sized amount against a ctVal of 3 base units: the
+        // exact quotient repeats forever, and at this magnitude the 28th
+        // significant digit already sits above the default epsilon.
+        let to = checked_to_exchange_size(
+            dec("1000000000000000000"),
+            Some(dec("3")),
+            default_precision_epsilon(),
+        );
+        assert!(to.precision_loss);
+        assert!(to.remainder > Decimal::ZERO, "{}", to.remainder);
+
+        // The same conversion under a looser epsilon merely notes the
+        // remainder without raising the flag.
+        let lenient = checked_to_exchange_size(dec("1000000000000000000"), Some(dec("3")), dec("0.001"));
+        assert!(!lenient.precision_loss);
+        assert_eq!(lenient.remainder, to.remainder);
+    }
+
+    #[test]
+    fn the_outbound_remainder_closes_the_round_trip() {
+        let epsilon = default_precision_epsilon();
+        let to = checked_to_exchange_size(dec("1000000000000000000"), Some(dec("3")), epsilon);
+        let back = checked_to_internal_amount(to.value, Some(dec("3")), epsilon);
+        assert_eq!(
+            back.value + back.remainder + to.remainder,
+            dec("1000000000000000000")
+        );
+    }
+
+    #[test]
+    fn an_overflowing_product_reports_the_dropped_base_units() {
+        // 28 significant digits of contracts against an integral ctVal: the
+        // exact product needs 29 and the plain multiplication rounds.
+        let back = checked_to_internal_amount(
+            dec("9876543210987654321.987654321"),
+            Some(dec("9")),
+            default_precision_epsilon(),
+        );
+        assert!(back.precision_loss);
+        assert_ne!(back.remainder, Decimal::ZERO);
+        // The exact product ends in .888888889; the rounded value carries
+        // .88888889, so exactly one billionth went missing.
+        assert_eq!(back.value, dec("88888888898888888897.88888889"));
+        assert_eq!(back.remainder, dec("-0.000000001"));
+    }
+
+    #[test]
+    fn random_round_trips_equal_the_reported_remainder() {
+        // Deterministic xorshift sequence; the crate carries no rng.
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..500 {
+            let amount = Decimal::new((next() % 1_000_000_000_000_000_000) as i64 + 1, (next() % 12) as u32);
+            let contract_value = Decimal::new((next() % 99_999) as i64 + 1, (next() % 8) as u32);
+            let epsilon = default_precision_epsilon();
+
+            let to = checked_to_exchange_size(amount, Some(contract_value), epsilon);
+            let back =
+                checked_to_internal_amount(to.value, Some(contract_value), epsilon);
+
+            // The two reported remainders account for the entire round-trip
+            // error — the bound the unchecked arithmetic could not state.
+            // The reassembling additions may themselves round, hence the
+            // tolerance instead of exact equality.
+            let reconstructed = back.value + back.remainder + to.remainder;
+            assert!(
+                (reconstructed - amount).abs() <= Decimal::new(1, 9),
+                "amount {amount} ctVal {contract_value} reconstructed {reconstructed}"
+            );
+        }
+    }
+}
@@ -0,0 +1,480 @@
+//! Startup preflight probes.
+//!
+//! Before a driver starts trading it can verify that the credentials sign
+//! correctly, the key has the scopes it needs, the configured pairs exist on
+//! the exchange, and the account-side settings (trade mode, position mode,
+//! leverage) match what the configuration assumes. Each probe lands in a
+//! [`PreflightReport`] instead of failing fast, so one run surfaces every
+//! problem at once.
+
+use std::collections::BTreeMap;
+
+use crate::api_structs::OkexPendingOrder;
+use crate::driver::OkexDriver;
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::InstrumentConverter;
+use crate::orders::TradeMode;
+use crate::rest::OkexClient;
+use crate::transport::Method;
+use crate::ws::OkexWsClient;
+
+/// Outcome of a single preflight probe.
+#[derive(Debug, Clone)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    /// Whether a failure of this probe should block startup. Soft probes
+    /// (e.g. leverage) only warn.
+    pub hard: bool,
+    pub detail: String,
+}
+
+/// Structured result of [`OkexDriver::preflight`].
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    fn record(&mut self, name: &'static str, hard: bool, outcome: Result<String, String>) {
+        let (passed, detail) = match outcome {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        self.checks.push(PreflightCheck {
+            name,
+            passed,
+            hard,
+            detail,
+        });
+    }
+
+    /// Whether every probe passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// The failed probes that should block startup.
+    pub fn hard_failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.checks
+            .iter()
+            .filter(|check| !check.passed && check.hard)
+    }
+
+    /// Look up a probe by name; mostly useful in tests and log formatting.
+    pub fn check(&self, name: &str) -> Option<&PreflightCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+}
+
+impl OkexDriver {
+    /// Run every preflight probe and collect the outcomes. Probes never
+    /// abort the run; a dead endpoint simply shows up as failed checks.
+    pub async fn preflight(&self, converter: &InstrumentConverter) -> PreflightReport {
+        let mut report = PreflightReport::default();
+        let config = self.rest().config().clone();
+
+        // Any authenticated read endpoint rejects a bad key, secret, or
+        // passphrase, so one balance fetch validates the signature chain.
+        report.record(
+            "signature",
+            true,
+            match self.rest().rest_fetch_balances().await {
+                Ok(_) => Ok("authenticated read succeeded".to_string()),
+                Err(err) => Err(format!("authenticated read failed: {err}")),
+            },
+        );
+
+        // Read scope on the trade namespace; a key without it can neither
+        // reconcile nor recover open orders.
+        let pending: DriverResult<Vec<OkexPendingOrder>> = self
+            .rest()
+            .call(Method::Get, "/api/v5/trade/orders-pending", Some("limit=1"), None)
+            .await;
+        report.record(
+            "orders-read",
+            true,
+            match pending {
+                Ok(_) => Ok("trade read scope available".to_string()),
+                Err(err) => Err(format!("orders-pending probe failed: {err}")),
+            },
+        );
+
+        match self.rest().rest_fetch_account_config().await {
+            Ok(account_config) => {
+                self.apply_account_config(&account_config);
+                report.record(
+                    "account-config",
+                    true,
+                    Ok(format!(
+                        "acctLv {} ({})",
+                        account_config.acct_lv,
+                        account_config.account_level_name()
+                    )),
+                );
+                report.record(
+                    "trade-mode",
+                    true,
+                    match account_config.validate_trade_mode(config.trade_mode) {
+                        Ok(()) => Ok(format!("{:?} is valid for this account", config.trade_mode)),
+                        Err(err) => Err(err.to_string()),
+                    },
+                );
+                if let Some(expected) = &config.position_mode {
+                    report.record(
+                        "position-mode",
+                        true,
+                        if &account_config.pos_mode == expected {
+                            Ok(format!("position mode is {expected}"))
+                        } else {
+                            Err(format!(
+                                "configured position mode {expected} but account is set to {}",
+                                account_config.pos_mode
+                            ))
+                        },
+                    );
+                }
+            }
+            Err(err) => report.record(
+                "account-config",
+                true,
+                Err(format!("account config fetch failed: {err}")),
+            ),
+        }
+
+        report.record(
+            "instruments",
+            true,
+            self.probe_instruments(converter).await,
+        );
+
+        if config.trade_mode != TradeMode::Cash {
+            report.record("leverage", false, self.probe_leverage(converter, config.trade_mode).await);
+        }
+
+        report
+    }
+
+    /// Verify every configured instrument resolves to a live exchange
+    /// instrument, grouping lookups by `instType` to keep the probe cheap.
+    async fn probe_instruments(&self, converter: &InstrumentConverter) -> Result<String, String> {
+        let mut by_type: BTreeMap<&'static str, Vec<&str>> = BTreeMap::new();
+        for instrument in converter.instruments() {
+            by_type
+                .entry(instrument.inst_type())
+                .or_default()
+                .push(&instrument.inst_id);
+        }
+        if by_type.is_empty() {
+            return Ok("no instruments configured".to_string());
+        }
+
+        let mut problems = Vec::new();
+        let mut resolved = 0usize;
+        for (inst_type, inst_ids) in by_type {
+            let listed = match self.rest().rest_fetch_instruments(inst_type).await {
+                Ok(listed) => listed,
+                Err(err) => {
+                    problems.push(format!("{inst_type} instrument fetch failed: {err}"));
+                    continue;
+                }
+            };
+            for inst_id in inst_ids {
+                match listed.iter().find(|info| info.inst_id == inst_id) {
+                    Some(info) if info.state == "live" => resolved += 1,
+                    Some(info) => {
+                        problems.push(format!("{inst_id} is not live (state {})", info.state))
+                    }
+                    None => problems.push(format!("{inst_id} is not listed as {inst_type}")),
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(format!("{resolved} instrument(s) live"))
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    /// Verify leverage is set on every non-spot instrument for the
+    /// configured margin mode. Soft: OKX falls back to its own defaults.
+    async fn probe_leverage(
+        &self,
+        converter: &InstrumentConverter,
+        trade_mode: TradeMode,
+    ) -> Result<String, String> {
+        let mgn_mode = match trade_mode {
+            TradeMode::Isolated => "isolated",
+            _ => "cross",
+        };
+        let mut inst_ids: Vec<&str> = converter
+            .instruments()
+            .filter(|instrument| instrument.inst_type() != "SPOT")
+            .map(|instrument| instrument.inst_id.as_str())
+            .collect();
+        inst_ids.sort_unstable();
+        if inst_ids.is_empty() {
+            return Ok("no leveraged instruments configured".to_string());
+        }
+
+        let mut problems = Vec::new();
+        let mut levers = Vec::new();
+        for inst_id in inst_ids {
+            match self.rest().rest_fetch_leverage_info(inst_id, mgn_mode).await {
+                Ok(infos) => match infos.iter().find_map(|info| info.leverage) {
+                    Some(lever) => levers.push(format!("{inst_id}={lever}x")),
+                    None => problems.push(format!("{inst_id} has no {mgn_mode} leverage set")),
+                },
+                Err(err) => problems.push(format!("{inst_id} leverage fetch failed: {err}")),
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(levers.join(", "))
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    /// Like [`OkexDriver::new`] but runs [`Self::preflight`] first and
+    /// refuses to hand out the driver when any hard probe fails.
+    pub async fn new_checked(
+        rest: OkexClient,
+        ws: OkexWsClient,
+        converter: &InstrumentConverter,
+    ) -> DriverResult<Self> {
+        let driver = Self::new(rest, ws);
+        let report = driver.preflight(converter).await;
+        let failures: Vec<String> = report
+            .hard_failures()
+            .map(|check| format!("{}: {}", check.name, check.detail))
+            .collect();
+        if failures.is_empty() {
+            Ok(driver)
+        } else {
+            Err(DriverError::Config(format!(
+                "preflight failed: {}",
+                failures.join("; ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::instruments::Instrument;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::{HttpResponse, HttpTransport};
+
+    const BALANCES_OK: &str = r#"{"code":"0","msg":"","data":[{"details":[]}]}"#;
+    const PENDING_OK: &str = r#"{"code":"0","msg":"","data":[]}"#;
+    const CONFIG_NET_SIMPLE: &str =
+        r#"{"code":"0","msg":"","data":[{"posMode":"net_mode","acctLv":"1"}]}"#;
+    const CONFIG_NET_MARGIN: &str =
+        r#"{"code":"0","msg":"","data":[{"posMode":"net_mode","acctLv":"3"}]}"#;
+    const SPOT_BTC_LIVE: &str = r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","instType":"SPOT","state":"live","tickSz":"0.1","lotSz":"0.0001","minSz":"0.0001","ctVal":""}]}"#;
+
+    fn spot_converter() -> InstrumentConverter {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        });
+        converter
+    }
+
+    fn driver(config: OkexConfig, transport: &Arc<MockTransport>) -> OkexDriver {
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        OkexDriver::new(rest, OkexWsClient::new(out_tx, in_rx))
+    }
+
+    #[tokio::test]
+    async fn all_probes_pass_on_healthy_account() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        transport.push_json(CONFIG_NET_SIMPLE);
+        transport.push_json(SPOT_BTC_LIVE);
+        let driver = driver(OkexConfig::default(), &transport);
+
+        let report = driver.preflight(&spot_converter()).await;
+        assert!(report.passed(), "report: {report:?}");
+        assert_eq!(report.hard_failures().count(), 0);
+
+        let requests = transport.requests();
+        let urls: Vec<&str> = requests
+            .iter()
+            .map(|r| r.url.strip_prefix("https://www.okx.com").unwrap())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "/api/v5/account/balance",
+                "/api/v5/trade/orders-pending?limit=1",
+                "/api/v5/account/config",
+                "/api/v5/public/instruments?instType=SPOT",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_signature_is_a_hard_failure() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(HttpResponse {
+            status: 401,
+            headers: vec![],
+            body: r#"{"code":"50113","msg":"Invalid Sign","data":[]}"#.to_string(),
+        });
+        // The 401 latches the credentials, so the remaining private probes
+        // fail fast without consuming responses; only the public
+        // instruments fetch still hits the wire.
+        transport.push_json(SPOT_BTC_LIVE);
+        let driver = driver(OkexConfig::default(), &transport);
+
+        let report = driver.preflight(&spot_converter()).await;
+        let signature = report.check("signature").unwrap();
+        assert!(!signature.passed);
+        assert!(signature.hard);
+        let orders_read = report.check("orders-read").unwrap();
+        assert!(!orders_read.passed);
+        assert!(orders_read.detail.contains("unauthorized"), "{}", orders_read.detail);
+        // Public probes still ran.
+        assert!(report.check("instruments").unwrap().passed);
+    }
+
+    #[tokio::test]
+    async fn position_mode_mismatch_is_reported() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        transport.push_json(CONFIG_NET_SIMPLE);
+        transport.push_json(SPOT_BTC_LIVE);
+        let config = OkexConfig {
+            position_mode: Some("long_short_mode".to_string()),
+            ..OkexConfig::default()
+        };
+        let driver = driver(config, &transport);
+
+        let report = driver.preflight(&spot_converter()).await;
+        let check = report.check("position-mode").unwrap();
+        assert!(!check.passed);
+        assert!(check.detail.contains("net_mode"), "{}", check.detail);
+    }
+
+    #[tokio::test]
+    async fn suspended_instrument_fails_the_instruments_probe() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        transport.push_json(CONFIG_NET_SIMPLE);
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","instType":"SPOT","state":"suspend","tickSz":"0.1","lotSz":"0.0001","minSz":"0.0001","ctVal":""}]}"#,
+        );
+        let driver = driver(OkexConfig::default(), &transport);
+
+        let report = driver.preflight(&spot_converter()).await;
+        let check = report.check("instruments").unwrap();
+        assert!(!check.passed && check.hard);
+        assert!(check.detail.contains("suspend"), "{}", check.detail);
+    }
+
+    #[tokio::test]
+    async fn missing_leverage_is_a_soft_failure() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        transport.push_json(CONFIG_NET_MARGIN);
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT-SWAP","instType":"SWAP","state":"live","tickSz":"0.1","lotSz":"1","minSz":"1","ctVal":"0.01"}]}"#,
+        );
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT-SWAP","mgnMode":"cross","lever":""}]}"#,
+        );
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let driver = driver(config, &transport);
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        });
+
+        let report = driver.preflight(&converter).await;
+        let check = report.check("leverage").unwrap();
+        assert!(!check.passed);
+        assert!(!check.hard);
+        assert_eq!(report.hard_failures().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn preflight_refines_capabilities_from_the_account_level() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        transport.push_json(CONFIG_NET_MARGIN);
+        transport.push_json(SPOT_BTC_LIVE);
+        let driver = driver(OkexConfig::default(), &transport);
+
+        // A cash-mode config assumes no margin until the account says so.
+        use crate::capabilities::DriverFeature;
+        assert!(!driver.supports_feature(DriverFeature::MarginOrders));
+        driver.preflight(&spot_converter()).await;
+        assert!(driver.supports_feature(DriverFeature::MarginOrders));
+    }
+
+    #[tokio::test]
+    async fn new_checked_refuses_construction_on_hard_failure() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_OK);
+        transport.push_json(PENDING_OK);
+        // Simple account cannot run cross-margin orders.
+        transport.push_json(CONFIG_NET_SIMPLE);
+        transport.push_json(SPOT_BTC_LIVE);
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let rest = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+
+        let result =
+            OkexDriver::new_checked(rest, OkexWsClient::new(out_tx, in_rx), &spot_converter())
+                .await;
+        match result {
+            Err(DriverError::Config(message)) => {
+                assert!(message.contains("trade-mode"), "{message}")
+            }
+            Err(other) => panic!("expected config error, got: {other}"),
+            Ok(_) => panic!("expected construction to be refused"),
+        }
+    }
+}
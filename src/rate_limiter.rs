@@ -0,0 +1,134 @@
+//! Adaptive per-category rate limiting.
+//!
+//! Each endpoint category (`trade`, `account`, ...) gets a fixed-window
+//! token budget. The REST layer feeds observed `ratelimit-*` response
+//! headers back in via [`AdaptiveRateLimiter::adapt`], shrinking the local
+//! budget when the exchange reports fewer remaining requests than we think
+//! we have — so we slow down *before* code 50011 arrives.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default requests-per-window when the exchange has not told us better.
+const DEFAULT_CAPACITY: u32 = 60;
+/// Fixed window length.
+const WINDOW: Duration = Duration::from_secs(2);
+
+struct CategoryBudget {
+    capacity: u32,
+    available: u32,
+    window_ends: Instant,
+}
+
+impl CategoryBudget {
+    fn new(capacity: u32, now: Instant) -> Self {
+        Self {
+            capacity,
+            available: capacity,
+            window_ends: now + WINDOW,
+        }
+    }
+
+    fn roll_window(&mut self, now: Instant) {
+        if now >= self.window_ends {
+            self.available = self.capacity;
+            self.window_ends = now + WINDOW;
+        }
+    }
+}
+
+/// Shared limiter; one instance per [`crate::rest::OkexClient`].
+#[derive(Default)]
+pub struct AdaptiveRateLimiter {
+    budgets: Mutex<HashMap<String, CategoryBudget>>,
+}
+
+impl AdaptiveRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one permit for `category`, sleeping until the window rolls when
+    /// the budget is exhausted.
+    pub async fn acquire(&self, category: &str) {
+        loop {
+            let wait_until = {
+                let mut budgets = self.budgets.lock().unwrap();
+                let now = Instant::now();
+                let budget = budgets
+                    .entry(category.to_string())
+                    .or_insert_with(|| CategoryBudget::new(DEFAULT_CAPACITY, now));
+                budget.roll_window(now);
+                if budget.available > 0 {
+                    budget.available -= 1;
+                    return;
+                }
+                budget.window_ends
+            };
+            tokio::time::sleep(wait_until.saturating_duration_since(Instant::now())).await;
+        }
+    }
+
+    /// Reconcile the local budget with exchange-reported headers. The
+    /// capacity follows the reported limit and the available budget can only
+    /// shrink toward the reported remaining, never grow.
+    pub fn adapt(&self, category: &str, limit: u32, remaining: u32) {
+        let mut budgets = self.budgets.lock().unwrap();
+        let now = Instant::now();
+        let budget = budgets
+            .entry(category.to_string())
+            .or_insert_with(|| CategoryBudget::new(limit, now));
+        budget.capacity = limit;
+        budget.available = budget.available.min(remaining);
+    }
+
+    /// Currently available permits for `category`, for tests and dashboards.
+    pub fn available(&self, category: &str) -> Option<u32> {
+        self.budgets
+            .lock()
+            .unwrap()
+            .get(category)
+            .map(|b| b.available)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire_decrements_available() {
+        let limiter = AdaptiveRateLimiter::new();
+        limiter.acquire("trade").await;
+        limiter.acquire("trade").await;
+        assert_eq!(limiter.available("trade"), Some(DEFAULT_CAPACITY - 2));
+    }
+
+    #[tokio::test]
+    async fn adapt_shrinks_but_never_grows_budget() {
+        let limiter = AdaptiveRateLimiter::new();
+        limiter.acquire("trade").await;
+
+        limiter.adapt("trade", 60, 3);
+        assert_eq!(limiter.available("trade"), Some(3));
+
+        // A higher reported remaining must not inflate the local budget.
+        limiter.adapt("trade", 60, 50);
+        assert_eq!(limiter.available("trade"), Some(3));
+    }
+
+    #[tokio::test]
+    async fn exhausted_budget_waits_for_window_roll() {
+        let limiter = AdaptiveRateLimiter::new();
+        limiter.acquire("account").await;
+        limiter.adapt("account", 60, 0);
+
+        let started = Instant::now();
+        limiter.acquire("account").await;
+        assert!(
+            started.elapsed() >= Duration::from_millis(100),
+            "acquire should have blocked until the window rolled"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Records exported to the reporting pipeline.
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::OkexPositionHistory;
+use crate::instruments::Instrument;
+
+/// What a [`KinesisTransaction`] represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KinesisTransactionType {
+    /// A closed derivative position with exchange-attributed realized PnL.
+    ClosedPosition,
+    /// An account bill: any balance-changing event (trade legs, fees,
+    /// funding, transfers).
+    Bill,
+    /// A driver-initiated balance movement, e.g. a dust-conversion leg.
+    Transfer,
+    /// An instant conversion executed through OKX Convert — economically
+    /// a spot trade, though it never rests on the book.
+    Trade,
+}
+
+/// One record for the reporting pipeline, normalized to internal
+/// conventions: amounts in base units, fees as positive costs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KinesisTransaction {
+    pub transaction_type: KinesisTransactionType,
+    pub inst_id: String,
+    /// Closed amount in base units.
+    pub amount: Decimal,
+    /// Base units dropped when expanding contract sizes past `Decimal`'s
+    /// 28 digits; almost always zero. The exact amount is
+    /// `amount + amount_remainder`, so reconciliation sums should include
+    /// it even when the single record cannot represent the total.
+    pub amount_remainder: Decimal,
+    pub open_price: Option<Decimal>,
+    pub close_price: Option<Decimal>,
+    /// Realized PnL as the exchange attributes it, including fees and
+    /// funding.
+    pub realized_pnl: Option<Decimal>,
+    /// Trading fees as a positive cost.
+    pub fee: Option<Decimal>,
+    /// Funding as a positive cost (negative when received).
+    pub funding_fee: Option<Decimal>,
+    /// Strategy level decoded from the originating client order id; empty
+    /// when the record carries no id or the id holds no metadata.
+    pub level_id: String,
+    /// Close time, milliseconds.
+    pub timestamp: String,
+}
+
+impl KinesisTransaction {
+    /// Map one closed-position record against its instrument: contract
+    /// sizes are multiplied out and the fee/funding signs flipped to costs.
+    pub fn from_position_history(
+        record: &OkexPositionHistory,
+        instrument: &Instrument,
+    ) -> Self {
+        let amount = crate::precision::checked_to_internal_amount(
+            record.close_total_position,
+            instrument.contract_value,
+            crate::precision::default_precision_epsilon(),
+        );
+        Self {
+            transaction_type: KinesisTransactionType::ClosedPosition,
+            inst_id: record.inst_id.clone(),
+            amount: amount.value,
+            amount_remainder: amount.remainder,
+            open_price: record.open_average_price,
+            close_price: record.close_average_price,
+            realized_pnl: record.realized_pnl,
+            fee: record.fee.map(|fee| -fee),
+            funding_fee: record.funding_fee.map(|fee| -fee),
+            // Position history aggregates many orders; no single id exists.
+            level_id: String::new(),
+            timestamp: record.updated_at.clone(),
+        }
+    }
+
+    /// Map one account bill. Bills are single-currency balance changes, so
+    /// the currency stands in for the instrument and only trade bills carry
+    /// a price.
+    pub fn from_bill(bill: &crate::api_structs::OkexBillResponse) -> Self {
+        Self {
+            transaction_type: KinesisTransactionType::Bill,
+            inst_id: bill.ccy.clone(),
+            amount: bill.balance_change,
+            // Bills arrive already in currency units; nothing is expanded.
+            amount_remainder: Decimal::ZERO,
+            open_price: None,
+            close_price: bill.price,
+            realized_pnl: None,
+            fee: None,
+            funding_fee: None,
+            level_id: bill
+                .client_order_id
+                .as_deref()
+                .and_then(crate::client_id::ClientOrderId::from_exchange)
+                .and_then(|id| crate::client_id::parse_client_id(&id))
+                .map(|parts| parts.level_id)
+                .unwrap_or_default(),
+            timestamp: bill.timestamp.clone(),
+        }
+    }
+
+    /// Map one leg of a dust conversion. The amount is the dust removed
+    /// from the account (negative, matching the bill convention for
+    /// outgoing balance changes); the OKB credited arrives as a separate
+    /// funding bill, so only the fee is carried here. The endpoint reports
+    /// no timestamp, so the caller supplies the sweep time.
+    pub fn from_dust_conversion(
+        detail: &crate::api_structs::OkexDustConversionDetail,
+        timestamp: String,
+    ) -> Self {
+        Self {
+            transaction_type: KinesisTransactionType::Transfer,
+            inst_id: detail.ccy.clone(),
+            amount: -detail.amount,
+            amount_remainder: Decimal::ZERO,
+            open_price: None,
+            close_price: None,
+            realized_pnl: None,
+            fee: Some(detail.fee),
+            funding_fee: None,
+            // Conversions are account-level; no order id exists.
+            level_id: String::new(),
+            timestamp,
+        }
+    }
+
+    /// Map one OKX Convert execution. Base units move like a spot trade:
+    /// the amount is the filled base size, negative when the conversion
+    /// sold the base currency, and the fill price stands in for the close
+    /// price. Convert charges no explicit fee — the spread is the cost.
+    pub fn from_conversion(result: &crate::api_structs::OkexConvertResult) -> Self {
+        let amount = if result.side == "sell" {
+            -result.fill_base_size
+        } else {
+            result.fill_base_size
+        };
+        Self {
+            transaction_type: KinesisTransactionType::Trade,
+            inst_id: result.inst_id.clone(),
+            amount,
+            amount_remainder: Decimal::ZERO,
+            open_price: None,
+            close_price: Some(result.fill_price),
+            realized_pnl: None,
+            fee: None,
+            funding_fee: None,
+            level_id: String::new(),
+            timestamp: result.timestamp.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(inst_id: &str, closed: &str) -> OkexPositionHistory {
+        serde_json::from_str(&format!(
+            r#"{{"instId":"{inst_id}","openAvgPx":"43000","closeAvgPx":"43500","realizedPnl":"12.5","fee":"-1.2","fundingFee":"-0.3","closeTotalPos":"{closed}","cTime":"1700000000000","uTime":"1700000500000"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn linear_contract_sizes_convert_to_base_units() {
+        // Linear swap: ctVal is 0.01 of the base coin.
+        let instrument = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let tx = KinesisTransaction::from_position_history(
+            &record("BTC-USDT-SWAP", "250"),
+            &instrument,
+        );
+        assert_eq!(tx.amount, "2.5".parse::<Decimal>().unwrap());
+        assert_eq!(tx.transaction_type, KinesisTransactionType::ClosedPosition);
+        assert_eq!(tx.fee, Some("1.2".parse().unwrap()));
+        assert_eq!(tx.funding_fee, Some("0.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn inverse_contract_sizes_convert_with_their_usd_face_value() {
+        // Inverse swap: ctVal is the USD face value per contract.
+        let instrument = Instrument {
+            inst_id: "BTC-USD-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("100".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let tx = KinesisTransaction::from_position_history(
+            &record("BTC-USD-SWAP", "5"),
+            &instrument,
+        );
+        assert_eq!(tx.amount, "500".parse::<Decimal>().unwrap());
+        assert_eq!(tx.realized_pnl, Some("12.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn oversized_expansions_carry_the_dropped_remainder() {
+        // 28 significant digits of contracts: the exact base amount needs
+        // 29, so the expansion rounds and the remainder records the loss.
+        let instrument = Instrument {
+            inst_id: "XXX-USD-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("9".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let tx = KinesisTransaction::from_position_history(
+            &record("XXX-USD-SWAP", "9876543210987654321.987654321"),
+            &instrument,
+        );
+        assert_eq!(tx.amount_remainder, "-0.000000001".parse::<Decimal>().unwrap());
+
+        // The common case keeps the remainder at an exact zero.
+        let tx = KinesisTransaction::from_position_history(
+            &record("XXX-USD-SWAP", "250"),
+            &instrument,
+        );
+        assert_eq!(tx.amount_remainder, Decimal::ZERO);
+    }
+
+    #[test]
+    fn bill_level_id_decodes_from_the_client_order_id() {
+        let bill: crate::api_structs::OkexBillResponse = serde_json::from_str(
+            // `fmt` + len-prefixed "grid"/"L17" + 8-char sequence.
+            r#"{"billId":"b1","ccy":"USDT","clOrdId":"fmt04grid03L17000000ab","balChg":"-300","px":"43250.1","type":"2","ts":"1700000000000"}"#,
+        )
+        .unwrap();
+        let tx = KinesisTransaction::from_bill(&bill);
+        assert_eq!(tx.level_id, "L17");
+
+        // Foreign ids leave it empty rather than failing the record.
+        let bill: crate::api_structs::OkexBillResponse = serde_json::from_str(
+            r#"{"billId":"b2","ccy":"USDT","clOrdId":"mm-42","balChg":"-300","type":"2","ts":"1700000000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(KinesisTransaction::from_bill(&bill).level_id, "");
+    }
+}
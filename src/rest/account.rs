@@ -0,0 +1,800 @@
+//! Account-scoped REST endpoints.
+
+use futures_lite::StreamExt;
+use rust_decimal::Decimal;
+
+use crate::api_structs::{
+    BorrowRepaySide, OkexAccountConfig, OkexAssetBalance, OkexBalance, OkexBillResponse,
+    OkexBorrowRepayHistory, OkexBorrowRepayRequest, OkexBorrowRepayResult, OkexLeverageInfo,
+    OkexPosition, OkexPositionHistory,
+};
+use crate::balances::{merge_balances, RawBalance};
+use crate::bills::BillsCursor;
+use crate::collateral::{CollateralDetail, RawCollateral};
+use crate::errors::{DriverError, DriverResult};
+use crate::reporting::KinesisTransaction;
+use crate::resume::{FetchCursor, InterruptedFetch};
+use crate::transport::Method;
+
+use super::OkexClient;
+
+impl OkexClient {
+    /// Fetch `/api/v5/account/config`.
+    pub async fn rest_fetch_account_config(&self) -> DriverResult<OkexAccountConfig> {
+        let mut data: Vec<OkexAccountConfig> = self
+            .call(Method::Get, "/api/v5/account/config", None, None)
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty account config response".to_string()))
+    }
+
+    /// Fetch `/api/v5/account/positions`; one entry per (instrument,
+    /// `posSide`), so a long/short-mode pair shows up twice. Aggregate via
+    /// [`crate::positions::aggregate_positions`].
+    pub async fn rest_fetch_positions(&self) -> DriverResult<Vec<OkexPosition>> {
+        self.call_elements(Method::Get, "/api/v5/account/positions", None, None)
+            .await
+    }
+
+    /// Fetch the account config and verify the configured trade mode is
+    /// valid under the account level. Intended to run at startup so a
+    /// misconfigured `tdMode` fails loudly instead of on the first order.
+    pub async fn validate_configured_trade_mode(&self) -> DriverResult<()> {
+        let account_config = self.rest_fetch_account_config().await?;
+        account_config.validate_trade_mode(self.config().trade_mode)
+    }
+
+    /// Fetch the account config and resolve the per-instrument-type
+    /// `tdMode` defaults the order builder should use; see
+    /// [`crate::orders::OrderDefaults`] for the matrix. Run at startup and
+    /// from the periodic config refresh: an account level changed on the
+    /// exchange mid-session re-resolves the defaults and is logged loudly,
+    /// since every in-flight assumption about margin capability just moved.
+    pub async fn resolve_order_defaults(&self) -> DriverResult<crate::orders::OrderDefaults> {
+        let account_config = self.rest_fetch_account_config().await?;
+        let resolved =
+            crate::orders::OrderDefaults::resolve(&account_config, self.config().trade_mode)?;
+        let mut slot = self.order_defaults.lock().unwrap();
+        if let Some(previous) = slot.as_ref() {
+            if previous.acct_lv != resolved.acct_lv {
+                log::error!(
+                    "account level changed mid-session ({} -> {}); order defaults re-resolved, \
+                     review margin settings",
+                    previous.acct_lv,
+                    resolved.acct_lv
+                );
+            }
+        }
+        *slot = Some(resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Fetch `/api/v5/account/balance` details for all currencies.
+    pub async fn rest_fetch_balances(&self) -> DriverResult<OkexBalance> {
+        let mut data: Vec<OkexBalance> = self
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty balance response".to_string()))
+    }
+
+    /// Fetch `/api/v5/asset/balances` — the funding account, which classic
+    /// accounts keep separate from the trading book.
+    pub async fn rest_fetch_funding_balances(&self) -> DriverResult<Vec<OkexAssetBalance>> {
+        self.call_elements(Method::Get, "/api/v5/asset/balances", None, None)
+            .await
+    }
+
+    /// Fetch every balance the account holds, tagged by source book.
+    ///
+    /// Unified accounts are complete after the single
+    /// `/api/v5/account/balance` call. A classic simple account still keeps
+    /// its funding book apart, so the trading-account call alone silently
+    /// under-reports; for those the funding balances are fetched too and
+    /// merged via [`crate::balances::merge_balances`]. The account style is
+    /// read from `/api/v5/account/config` per call rather than cached — an
+    /// account upgraded mid-session must not keep paying for the extra
+    /// fetch.
+    pub async fn fetch_all_balances(&self) -> DriverResult<Vec<RawBalance>> {
+        let account_config = self.rest_fetch_account_config().await?;
+        let trading = self.rest_fetch_balances().await?;
+        let funding = if account_config.is_unified() {
+            None
+        } else {
+            Some(self.rest_fetch_funding_balances().await?)
+        };
+        Ok(merge_balances(&trading, funding.as_deref()))
+    }
+
+    /// Per-asset collateral for risk sizing.
+    ///
+    /// On cash accounts collateral is simply the cash balance. On margin
+    /// accounts `total` is the discounted equity (`disEq`) — the value OKX
+    /// itself counts after tiered collateral weights — so 1 LTC worth $32
+    /// with a $30 discounted value never overstates headroom. `free` stays
+    /// the available balance in both modes.
+    pub async fn fetch_collateral_balances(&self) -> DriverResult<Vec<RawCollateral>> {
+        let balances = self.rest_fetch_balances().await?;
+        let use_discounted = self.config().trade_mode != crate::orders::TradeMode::Cash;
+        Ok(balances
+            .details
+            .iter()
+            .map(|detail| {
+                let liability = detail.liability.unwrap_or_default();
+                let interest = detail.interest.unwrap_or_default();
+                RawCollateral {
+                    asset: detail.ccy.clone(),
+                    total: if use_discounted {
+                        // `disEq` already nets borrowings into the equity;
+                        // when it is absent the loan is only visible in
+                        // `liab`, so subtract it (plus accrued interest) to
+                        // keep borrowed assets negative.
+                        detail
+                            .discounted_equity
+                            .unwrap_or(detail.cash_balance - liability - interest)
+                    } else {
+                        detail.cash_balance
+                    },
+                    // Net the loan and its interest out of the available
+                    // balance: a -500 USDT liability must read as negative
+                    // headroom, not as a flat zero.
+                    free: detail.available_balance - liability - interest,
+                    liability,
+                }
+            })
+            .collect())
+    }
+
+    /// The raw per-asset collateral values behind
+    /// [`Self::fetch_collateral_balances`], for dashboards that want both
+    /// the weighted and unweighted numbers.
+    pub async fn fetch_collateral_details(&self) -> DriverResult<Vec<CollateralDetail>> {
+        let balances = self.rest_fetch_balances().await?;
+        Ok(balances
+            .details
+            .iter()
+            .map(CollateralDetail::from_balance_detail)
+            .collect())
+    }
+
+    /// Manual borrow or repay in multi-currency/portfolio margin mode via
+    /// `/api/v5/account/borrow-repay`.
+    ///
+    /// Fails with a configuration error when the account level does not
+    /// support margin, so the exchange-side rejection never has to be
+    /// deciphered.
+    pub async fn rest_borrow_repay(
+        &self,
+        ccy: &str,
+        side: BorrowRepaySide,
+        amount: Decimal,
+    ) -> DriverResult<OkexBorrowRepayResult> {
+        let account_config = self.rest_fetch_account_config().await?;
+        if !account_config.is_margin_capable() {
+            return Err(DriverError::Config(format!(
+                "borrow/repay requires a margin-capable account mode, but acctLv is {}",
+                account_config.acct_lv
+            )));
+        }
+
+        let request = OkexBorrowRepayRequest {
+            ccy: ccy.to_string(),
+            side,
+            amt: amount,
+        };
+        let body = serde_json::to_string(&request)?;
+        let mut data: Vec<OkexBorrowRepayResult> = self
+            .call(Method::Post, "/api/v5/account/borrow-repay", None, Some(body))
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty borrow-repay response".to_string()))
+    }
+
+    /// Fetch `/api/v5/account/borrow-repay-history`, optionally filtered by
+    /// currency, for reconciliation.
+    pub async fn rest_fetch_borrow_repay_history(
+        &self,
+        ccy: Option<&str>,
+    ) -> DriverResult<Vec<OkexBorrowRepayHistory>> {
+        let query = ccy.map(|c| format!("ccy={c}"));
+        self.call(
+            Method::Get,
+            "/api/v5/account/borrow-repay-history",
+            query.as_deref(),
+            None,
+        )
+        .await
+    }
+
+    /// Fetch `/api/v5/account/bills`, paging on the bill-id cursor,
+    /// optionally filtered by currency.
+    pub async fn rest_fetch_account_bills(
+        &self,
+        ccy: Option<&str>,
+    ) -> DriverResult<Vec<OkexBillResponse>> {
+        self.rest_fetch_account_bills_resumable(ccy, None)
+            .await
+            .map_err(DriverError::from)
+    }
+
+    /// [`rest_fetch_account_bills`](Self::rest_fetch_account_bills) with
+    /// mid-fetch failure recovery: a failed page fails the call with an
+    /// [`InterruptedFetch`] carrying the bills received so far and the
+    /// cursor of the last complete page; pass that cursor back as `resume`
+    /// to fetch only what is still missing.
+    pub async fn rest_fetch_account_bills_resumable(
+        &self,
+        ccy: Option<&str>,
+        resume: Option<FetchCursor>,
+    ) -> Result<Vec<OkexBillResponse>, Box<InterruptedFetch<OkexBillResponse>>> {
+        const PAGE_LIMIT: usize = 100;
+
+        let mut bills: Vec<OkexBillResponse> = Vec::new();
+        let mut after: Option<String> = resume.and_then(|cursor| cursor.after);
+        loop {
+            let mut query = format!("limit={PAGE_LIMIT}");
+            if let Some(ccy) = ccy {
+                query.push_str(&format!("&ccy={ccy}"));
+            }
+            if let Some(cursor) = &after {
+                query.push_str(&format!("&after={cursor}"));
+            }
+            let page: Vec<OkexBillResponse> = match self
+                .call_elements(Method::Get, "/api/v5/account/bills", Some(&query), None)
+                .await
+            {
+                Ok(page) => page,
+                Err(error) => {
+                    return Err(Box::new(InterruptedFetch {
+                        partial: bills,
+                        // `after` still names the last complete page.
+                        cursor: FetchCursor { segment: 0, after },
+                        error,
+                    }));
+                }
+            };
+            let page_len = page.len();
+            after = page.last().map(|bill| bill.bill_id.clone());
+            bills.extend(page);
+            if page_len < PAGE_LIMIT {
+                break;
+            }
+        }
+        Ok(bills)
+    }
+
+    /// Poll for bills newer than the cursor, oldest first, exactly once.
+    ///
+    /// Pages newest-first until a page overlaps already-delivered territory
+    /// (or history runs out), keeps the overlap, and dedupes by `billId` so
+    /// clock skew between us and the exchange never drops or duplicates a
+    /// bill. A cursor older than the recent window reads from the archive
+    /// endpoint instead, so restarts across the 7-day boundary do not lose
+    /// bills. Delivered bills advance the cursor; persist it through a
+    /// [`crate::bills::BillsCursorStore`] to survive restarts.
+    pub async fn poll_new_bills(
+        &self,
+        cursor: &mut BillsCursor,
+    ) -> DriverResult<Vec<KinesisTransaction>> {
+        const PAGE_LIMIT: usize = 100;
+        /// `/account/bills` covers 7 days; a day of margin against slow
+        /// polls and clock skew.
+        const RECENT_WINDOW_MS: u64 = 6 * 24 * 60 * 60 * 1000;
+
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let endpoint = match cursor.last_timestamp {
+            Some(ts) if now.saturating_sub(ts) > RECENT_WINDOW_MS => {
+                "/api/v5/account/bills-archive"
+            }
+            _ => "/api/v5/account/bills",
+        };
+
+        let mut collected: Vec<OkexBillResponse> = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let query = match &after {
+                Some(id) => format!("limit={PAGE_LIMIT}&after={id}"),
+                None => format!("limit={PAGE_LIMIT}"),
+            };
+            let page: Vec<OkexBillResponse> = self
+                .call_elements(Method::Get, endpoint, Some(&query), None)
+                .await?;
+            let page_len = page.len();
+            after = page.last().map(|bill| bill.bill_id.clone());
+            // Once a page reaches already-delivered bills (or times before
+            // the cursor) the gap is bridged; the overlap is deduped below.
+            let bridged = page.iter().any(|bill| {
+                cursor.is_seen(&bill.bill_id)
+                    || cursor.last_timestamp.is_some_and(|last| {
+                        bill.timestamp.parse::<u64>().is_ok_and(|ts| ts < last)
+                    })
+            });
+            collected.extend(page);
+            if bridged || page_len < PAGE_LIMIT {
+                break;
+            }
+        }
+
+        collected.reverse();
+        let mut fresh = Vec::new();
+        for bill in collected {
+            if cursor.is_seen(&bill.bill_id) {
+                continue;
+            }
+            fresh.push(KinesisTransaction::from_bill(&bill));
+            cursor.mark_delivered(&bill.bill_id, bill.timestamp.parse().unwrap_or_default());
+        }
+        Ok(fresh)
+    }
+
+    /// Fetch `/api/v5/account/positions-history` for one instrument within
+    /// `[begin, end]` (milliseconds, inclusive), newest first.
+    ///
+    /// The endpoint pages backwards in time: `after` asks for records older
+    /// than the given timestamp, so the cursor walks from `end` towards
+    /// `begin` and records before `begin` are cut client-side.
+    pub async fn rest_fetch_positions_history(
+        &self,
+        inst_id: &str,
+        begin: Option<u64>,
+        end: Option<u64>,
+    ) -> DriverResult<Vec<OkexPositionHistory>> {
+        const PAGE_LIMIT: usize = 100;
+
+        let stream = self.stream_paginated(
+            crate::rest::PageWalk {
+                path: "/api/v5/account/positions-history",
+                base_query: format!("instId={inst_id}"),
+                limit: PAGE_LIMIT,
+                after: end.map(|ts| ts.saturating_add(1).to_string()),
+            },
+            |record: &OkexPositionHistory| Some(record.updated_at.clone()),
+        );
+        futures_lite::pin!(stream);
+        let mut records: Vec<OkexPositionHistory> = Vec::new();
+        while let Some(record) = stream.next().await {
+            let record = record?;
+            let reached_begin = begin.is_some_and(|begin| {
+                record
+                    .updated_at
+                    .parse::<u64>()
+                    .is_ok_and(|ts| ts < begin)
+            });
+            records.push(record);
+            // Once the walk reaches records older than `begin` the rest of
+            // the history is older still; dropping the stream here skips
+            // the remaining pages.
+            if reached_begin {
+                break;
+            }
+        }
+
+        if let Some(begin) = begin {
+            records.retain(|record| {
+                record
+                    .updated_at
+                    .parse::<u64>()
+                    .map(|ts| ts >= begin)
+                    .unwrap_or(true)
+            });
+        }
+        Ok(records)
+    }
+
+    /// Fetch `/api/v5/account/leverage-info` for one instrument and margin
+    /// mode (`cross` or `isolated`).
+    pub async fn rest_fetch_leverage_info(
+        &self,
+        inst_id: &str,
+        mgn_mode: &str,
+    ) -> DriverResult<Vec<OkexLeverageInfo>> {
+        let query = format!("instId={inst_id}&mgnMode={mgn_mode}");
+        self.call(
+            Method::Get,
+            "/api/v5/account/leverage-info",
+            Some(&query),
+            None,
+        )
+        .await
+    }
+
+    /// Repay up to `amount` of the current liability in `asset`.
+    ///
+    /// The amount is capped at the liability read from balances; returns the
+    /// amount actually submitted, or `None` when there is no liability.
+    pub async fn repay_liability(
+        &self,
+        asset: &str,
+        amount: Decimal,
+    ) -> DriverResult<Option<Decimal>> {
+        let balances = self.rest_fetch_balances().await?;
+        let liability = balances
+            .details
+            .iter()
+            .find(|d| d.ccy == asset)
+            .and_then(|d| d.liability)
+            .unwrap_or_default();
+        if liability <= Decimal::ZERO {
+            return Ok(None);
+        }
+
+        let to_repay = amount.min(liability);
+        self.rest_borrow_repay(asset, BorrowRepaySide::Repay, to_repay)
+            .await?;
+        Ok(Some(to_repay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+
+    const ACCOUNT_CONFIG_MARGIN: &str =
+        r#"{"code":"0","msg":"","data":[{"posMode":"net_mode","acctLv":"3"}]}"#;
+    const ACCOUNT_CONFIG_SIMPLE: &str =
+        r#"{"code":"0","msg":"","data":[{"posMode":"net_mode","acctLv":"1"}]}"#;
+
+    fn client(transport: Arc<MockTransport>) -> OkexClient {
+        OkexClient::with_transport(OkexConfig::default(), transport)
+    }
+
+    #[tokio::test]
+    async fn resolved_order_defaults_are_stored_and_refreshed() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ACCOUNT_CONFIG_SIMPLE);
+        transport.push_json(ACCOUNT_CONFIG_MARGIN);
+        let client = client(Arc::clone(&transport));
+        assert!(client.order_defaults().is_none());
+
+        let resolved = client.resolve_order_defaults().await.unwrap();
+        assert_eq!(resolved.acct_lv, "1");
+        assert_eq!(resolved.spot, crate::orders::TradeMode::Cash);
+        assert_eq!(client.order_defaults(), Some(resolved));
+
+        // The account switched to multi-currency margin mid-session; the
+        // next refresh picks up the new matrix.
+        let refreshed = client.resolve_order_defaults().await.unwrap();
+        assert_eq!(refreshed.acct_lv, "3");
+        assert_eq!(refreshed.spot, crate::orders::TradeMode::Cross);
+        assert_eq!(client.order_defaults(), Some(refreshed));
+    }
+
+    #[test]
+    fn borrow_repay_request_serializes_decimal_as_string() {
+        let request = OkexBorrowRepayRequest {
+            ccy: "USDT".to_string(),
+            side: BorrowRepaySide::Repay,
+            amt: Decimal::new(125, 1),
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"ccy":"USDT","side":"repay","amt":"12.5"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn borrow_repay_rejects_simple_account_mode() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ACCOUNT_CONFIG_SIMPLE);
+        let client = client(transport);
+
+        let err = client
+            .rest_borrow_repay("USDT", BorrowRepaySide::Borrow, Decimal::ONE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn borrow_repay_parses_result() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ACCOUNT_CONFIG_MARGIN);
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"ccy":"USDT","side":"repay","amt":"12.5"}]}"#);
+        let client = client(Arc::clone(&transport));
+
+        let result = client
+            .rest_borrow_repay("USDT", BorrowRepaySide::Repay, Decimal::new(125, 1))
+            .await
+            .unwrap();
+        assert_eq!(result.amt, Decimal::new(125, 1));
+
+        let requests = transport.requests();
+        assert_eq!(requests[1].url, "https://www.okx.com/api/v5/account/borrow-repay");
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some(r#"{"ccy":"USDT","side":"repay","amt":"12.5"}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn repay_liability_caps_at_current_liability() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"details":[{"ccy":"USDT","cashBal":"-12.5","availBal":"0","liab":"12.5"}]}]}"#,
+        );
+        transport.push_json(ACCOUNT_CONFIG_MARGIN);
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"ccy":"USDT","side":"repay","amt":"12.5"}]}"#);
+        let client = client(Arc::clone(&transport));
+
+        let repaid = client
+            .repay_liability("USDT", Decimal::new(1000, 0))
+            .await
+            .unwrap();
+        assert_eq!(repaid, Some(Decimal::new(125, 1)));
+    }
+
+    #[tokio::test]
+    async fn repay_liability_is_a_noop_without_liability() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"details":[{"ccy":"USDT","cashBal":"100","availBal":"100","liab":""}]}]}"#,
+        );
+        let client = client(Arc::clone(&transport));
+
+        let repaid = client
+            .repay_liability("USDT", Decimal::ONE)
+            .await
+            .unwrap();
+        assert_eq!(repaid, None);
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    /// 1 LTC worth $32 whose discounted collateral value is $30.
+    const BALANCES_WITH_DISEQ: &str = r#"{"code":"0","msg":"","data":[{"details":[
+        {"ccy":"LTC","cashBal":"1","availBal":"0.4","eqUsd":"32","disEq":"30"},
+        {"ccy":"USDT","cashBal":"100","availBal":"100","eqUsd":"100","disEq":"100"}
+    ]}]}"#;
+
+    #[tokio::test]
+    async fn margin_collateral_uses_discounted_equity() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_WITH_DISEQ);
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let client = OkexClient::with_transport(config, transport);
+
+        let collateral = client.fetch_collateral_balances().await.unwrap();
+        assert_eq!(collateral[0].asset, "LTC");
+        assert_eq!(collateral[0].total, Decimal::new(30, 0));
+        assert_eq!(collateral[0].free, Decimal::new(4, 1));
+    }
+
+    #[tokio::test]
+    async fn borrowed_margin_asset_nets_out_negative() {
+        let transport = Arc::new(MockTransport::new());
+        // A borrowed currency whose loan shows up only in `liab`.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"details":[{"ccy":"USDT","cashBal":"2","availBal":"2","liab":"12.5"}]}]}"#,
+        );
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let client = OkexClient::with_transport(config, transport);
+
+        let collateral = client.fetch_collateral_balances().await.unwrap();
+        assert_eq!(collateral[0].total, Decimal::new(-105, 1));
+        assert_eq!(collateral[0].liability, Decimal::new(125, 1));
+    }
+
+    #[tokio::test]
+    async fn liability_and_interest_push_free_balance_negative() {
+        let transport = Arc::new(MockTransport::new());
+        // Borrowed USDT: the exchange reports zero available, but the loan
+        // plus accrued interest is real negative headroom.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"details":[{"ccy":"USDT","cashBal":"0","availBal":"0","liab":"500","crossLiab":"500","interest":"1.5"}]}]}"#,
+        );
+        let config = OkexConfig {
+            trade_mode: crate::orders::TradeMode::Cross,
+            ..OkexConfig::default()
+        };
+        let client = OkexClient::with_transport(config, transport);
+
+        let collateral = client.fetch_collateral_balances().await.unwrap();
+        assert_eq!(collateral[0].free, Decimal::new(-5015, 1));
+        assert_eq!(collateral[0].total, Decimal::new(-5015, 1));
+    }
+
+    #[tokio::test]
+    async fn cash_collateral_keeps_the_cash_balance() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_WITH_DISEQ);
+        let client = client(transport);
+
+        let collateral = client.fetch_collateral_balances().await.unwrap();
+        assert_eq!(collateral[0].total, Decimal::ONE);
+    }
+
+    #[tokio::test]
+    async fn collateral_details_preserve_raw_values() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(BALANCES_WITH_DISEQ);
+        let client = client(transport);
+
+        let details = client.fetch_collateral_details().await.unwrap();
+        assert_eq!(details[0].equity_usd, Some(Decimal::new(32, 0)));
+        assert_eq!(details[0].discounted_equity, Some(Decimal::new(30, 0)));
+        assert_eq!(details[0].cash_balance, Decimal::ONE);
+    }
+
+    #[tokio::test]
+    async fn unified_accounts_fetch_balances_in_one_call() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ACCOUNT_CONFIG_MARGIN);
+        transport.push_json(include_str!("../test_data/trading_balance.json"));
+        let client = client(Arc::clone(&transport));
+
+        let balances = client.fetch_all_balances().await.unwrap();
+
+        assert_eq!(balances.len(), 2);
+        assert!(balances
+            .iter()
+            .all(|b| b.source == crate::balances::BalanceSource::Trading));
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests
+            .iter()
+            .any(|r| r.url.contains("/api/v5/asset/balances")));
+    }
+
+    #[tokio::test]
+    async fn classic_accounts_merge_the_funding_book_in() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(ACCOUNT_CONFIG_SIMPLE);
+        transport.push_json(include_str!("../test_data/trading_balance.json"));
+        transport.push_json(include_str!("../test_data/funding_balances.json"));
+        let client = client(Arc::clone(&transport));
+
+        let balances = client.fetch_all_balances().await.unwrap();
+
+        // USDT sits in both books and keeps one tagged entry per source.
+        let usdt: Vec<_> = balances.iter().filter(|b| b.asset == "USDT").collect();
+        assert_eq!(usdt.len(), 2);
+        assert_eq!(usdt[0].source, crate::balances::BalanceSource::Trading);
+        assert_eq!(usdt[1].source, crate::balances::BalanceSource::Funding);
+        assert_eq!(usdt[1].total, Decimal::new(250, 0));
+        // The funding-only asset is no longer invisible.
+        assert!(balances
+            .iter()
+            .any(|b| b.asset == "ETH" && b.source == crate::balances::BalanceSource::Funding));
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].url.ends_with("/api/v5/asset/balances"));
+    }
+
+    fn position_history_json(i: u64, u_time: u64) -> String {
+        format!(
+            r#"{{"instId":"BTC-USDT-SWAP","openAvgPx":"43000","closeAvgPx":"43500","realizedPnl":"1.{i}","fee":"-0.1","fundingFee":"","closeTotalPos":"10","cTime":"1700000000000","uTime":"{u_time}"}}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn positions_history_pages_backwards_and_cuts_at_begin() {
+        let transport = Arc::new(MockTransport::new());
+        // A full page walking back in time, then a short one that crosses
+        // the begin boundary.
+        let first_page: Vec<String> = (0..100)
+            .map(|i| position_history_json(i, 1_700_000_200_000 - i * 1000))
+            .collect();
+        transport.push_json(&format!(
+            r#"{{"code":"0","msg":"","data":[{}]}}"#,
+            first_page.join(",")
+        ));
+        transport.push_json(&format!(
+            r#"{{"code":"0","msg":"","data":[{},{}]}}"#,
+            position_history_json(100, 1_700_000_100_000),
+            position_history_json(101, 1_699_999_000_000)
+        ));
+        let client = client(Arc::clone(&transport));
+
+        let records = client
+            .rest_fetch_positions_history(
+                "BTC-USDT-SWAP",
+                Some(1_700_000_000_000),
+                Some(1_700_000_200_000),
+            )
+            .await
+            .unwrap();
+
+        // The record before `begin` is cut client-side.
+        assert_eq!(records.len(), 101);
+        assert!(records
+            .iter()
+            .all(|r| r.updated_at.parse::<u64>().unwrap() >= 1_700_000_000_000));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests[0].url.contains("after=1700000200001"),
+            "{}",
+            requests[0].url
+        );
+        assert!(
+            requests[1].url.contains("after=1700000101000"),
+            "{}",
+            requests[1].url
+        );
+    }
+
+    fn bill_json(id: u64, ts: u64) -> String {
+        format!(r#"{{"billId":"b{id}","ccy":"USDT","balChg":"{id}","type":"2","ts":"{ts}"}}"#)
+    }
+
+    fn bills_page(ids_newest_first: &[u64]) -> String {
+        let rows: Vec<String> = ids_newest_first
+            .iter()
+            .map(|id| bill_json(*id, 1_700_000_000_000 + id * 1000))
+            .collect();
+        format!(r#"{{"code":"0","msg":"","data":[{}]}}"#, rows.join(","))
+    }
+
+    #[tokio::test]
+    async fn bill_polls_with_overlapping_pages_deliver_exactly_once() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&bills_page(&[3, 2, 1]));
+        // Each later poll re-reads part of the previous page.
+        transport.push_json(&bills_page(&[5, 4, 3, 2]));
+        transport.push_json(&bills_page(&[6, 5, 4]));
+        let client = client(Arc::clone(&transport));
+        let mut cursor = BillsCursor::new();
+
+        let mut delivered: Vec<KinesisTransaction> = Vec::new();
+        for _ in 0..3 {
+            delivered.extend(client.poll_new_bills(&mut cursor).await.unwrap());
+        }
+
+        // Oldest first, each bill exactly once despite the overlaps.
+        let amounts: Vec<String> = delivered.iter().map(|tx| tx.amount.to_string()).collect();
+        assert_eq!(amounts, ["1", "2", "3", "4", "5", "6"]);
+        assert!(delivered
+            .iter()
+            .all(|tx| tx.transaction_type == crate::reporting::KinesisTransactionType::Bill));
+        assert_eq!(cursor.last_bill_id.as_deref(), Some("b6"));
+        assert_eq!(cursor.last_timestamp, Some(1_700_000_006_000));
+    }
+
+    #[tokio::test]
+    async fn stale_bill_cursor_reads_from_the_archive_endpoint() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = client(Arc::clone(&transport));
+        // A cursor from well past the 7-day window of /account/bills.
+        let mut cursor = BillsCursor {
+            last_bill_id: Some("b0".to_string()),
+            last_timestamp: Some(1_000),
+            ..BillsCursor::new()
+        };
+
+        client.poll_new_bills(&mut cursor).await.unwrap();
+        assert!(
+            transport.requests()[0]
+                .url
+                .contains("/api/v5/account/bills-archive"),
+            "{}",
+            transport.requests()[0].url
+        );
+    }
+
+    #[tokio::test]
+    async fn history_fetch_passes_currency_filter() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ccy":"USDT","type":"auto_repay","tradedLoan":"1","usedLoan":"0","ts":"1700000000000"}]}"#,
+        );
+        let client = client(Arc::clone(&transport));
+
+        let history = client
+            .rest_fetch_borrow_repay_history(Some("USDT"))
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(transport.requests()[0].url.ends_with("borrow-repay-history?ccy=USDT"));
+    }
+}
@@ -0,0 +1,640 @@
+//! Asset-scoped REST endpoints: currency and chain metadata.
+
+use std::time::Instant;
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::{
+    OkexConvertQuote, OkexConvertResult, OkexCurrencyChain, OkexDustConversionResult,
+};
+use crate::errors::{DriverError, DriverResult};
+use crate::reporting::KinesisTransaction;
+use crate::transport::Method;
+
+use super::OkexClient;
+
+/// Chain metadata changes rarely; refetch at most daily.
+const CURRENCY_TTL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// One funding-account balance eligible for a dust sweep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DustCandidate {
+    pub ccy: String,
+    /// Available funding balance, in `ccy` units.
+    pub balance: Decimal,
+    /// `balance` priced through the asset's USDT spot ticker.
+    pub notional_usdt: Decimal,
+}
+
+/// What a dust sweep would convert, priced before anything is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DustConversionPreview {
+    pub candidates: Vec<DustCandidate>,
+    /// Summed USDT notional of all candidates.
+    pub expected_usdt: Decimal,
+    /// `expected_usdt` through the OKB-USDT ticker — what the conversion
+    /// credits, before fees. Absent when OKB has no USDT ticker.
+    pub expected_okb: Option<Decimal>,
+}
+
+/// Fallback validity window when a quote omits `ttlMs`; OKX documents
+/// Convert quotes as good for about ten seconds.
+const DEFAULT_QUOTE_TTL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// One OKX Convert quote bound to the clock it was received on, so expiry
+/// is checked locally before the execution call burns a round trip on a
+/// quote the exchange will refuse anyway.
+#[derive(Debug, Clone)]
+pub struct ConversionQuote {
+    pub quote: OkexConvertQuote,
+    /// When the quote arrived; the TTL counts from here, not from the
+    /// exchange's `quoteTime`, so clock skew cannot shorten the window.
+    received_at: Instant,
+}
+
+impl ConversionQuote {
+    /// Time left before the exchange stops honouring the quote.
+    pub fn expires_in(&self, now: Instant) -> std::time::Duration {
+        let ttl = self
+            .quote
+            .ttl_ms
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(DEFAULT_QUOTE_TTL);
+        ttl.saturating_sub(now.duration_since(self.received_at))
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires_in(now).is_zero()
+    }
+}
+
+/// Cached `/api/v5/asset/currencies` rows.
+pub(crate) struct CurrencyCache {
+    fetched_at: Instant,
+    rows: Vec<OkexCurrencyChain>,
+}
+
+impl OkexClient {
+    /// Fetch `/api/v5/asset/currencies`, optionally for one currency.
+    /// Uncached; most callers want [`Self::currency_chains`].
+    pub async fn rest_fetch_currencies(
+        &self,
+        ccy: Option<&str>,
+    ) -> DriverResult<Vec<OkexCurrencyChain>> {
+        let query = ccy.map(|c| format!("ccy={c}"));
+        self.call_elements(
+            Method::Get,
+            "/api/v5/asset/currencies",
+            query.as_deref(),
+            None,
+        )
+        .await
+    }
+
+    /// All (currency, chain) rows, refetched at most daily.
+    async fn cached_currencies(&self) -> DriverResult<Vec<OkexCurrencyChain>> {
+        {
+            let cache = self.currency_cache.lock().unwrap();
+            if let Some(cache) = cache.as_ref() {
+                if cache.fetched_at.elapsed() < CURRENCY_TTL {
+                    return Ok(cache.rows.clone());
+                }
+            }
+        }
+        let rows = self.rest_fetch_currencies(None).await?;
+        *self.currency_cache.lock().unwrap() = Some(CurrencyCache {
+            fetched_at: Instant::now(),
+            rows: rows.clone(),
+        });
+        Ok(rows)
+    }
+
+    /// Chain rows for one asset (one per supported chain), from the daily
+    /// cache.
+    pub async fn currency_chains(&self, asset: &str) -> DriverResult<Vec<OkexCurrencyChain>> {
+        Ok(self
+            .cached_currencies()
+            .await?
+            .into_iter()
+            .filter(|row| row.ccy == asset)
+            .collect())
+    }
+
+    /// Validate a withdrawal against the chain metadata before it ever
+    /// reaches the exchange: the chain must exist for the asset, have
+    /// withdrawals enabled, and the amount must meet the chain minimum.
+    pub async fn validate_withdrawal(
+        &self,
+        asset: &str,
+        chain: &str,
+        amount: Decimal,
+    ) -> DriverResult<()> {
+        let chains = self.currency_chains(asset).await?;
+        if chains.is_empty() {
+            return Err(DriverError::Config(format!(
+                "unknown withdrawal asset {asset}"
+            )));
+        }
+        let Some(row) = chains.iter().find(|row| row.chain.as_deref() == Some(chain)) else {
+            let available: Vec<&str> = chains
+                .iter()
+                .filter_map(|row| row.chain.as_deref())
+                .collect();
+            return Err(DriverError::Config(format!(
+                "{asset} is not available on chain {chain}; supported: {}",
+                available.join(", ")
+            )));
+        };
+        if !row.can_withdraw {
+            return Err(DriverError::Config(format!(
+                "withdrawals for {asset} on {chain} are currently disabled"
+            )));
+        }
+        if let Some(min_wd) = row.min_withdrawal {
+            if amount < min_wd {
+                return Err(DriverError::Config(format!(
+                    "withdrawal of {amount} {asset} is below the {chain} minimum of {min_wd}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert the listed funding-account dust balances to OKB via
+    /// `/api/v5/asset/convert-dust-assets`. Raw endpoint — no eligibility
+    /// checks; most callers want [`Self::sweep_dust`].
+    pub async fn rest_convert_dust(
+        &self,
+        assets: Vec<String>,
+    ) -> DriverResult<OkexDustConversionResult> {
+        if assets.is_empty() {
+            return Err(DriverError::Config(
+                "dust conversion needs at least one asset".to_string(),
+            ));
+        }
+        let body = serde_json::json!({ "ccy": assets }).to_string();
+        let mut data: Vec<OkexDustConversionResult> = self
+            .call(
+                Method::Post,
+                "/api/v5/asset/convert-dust-assets",
+                None,
+                Some(body),
+            )
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty dust conversion response".to_string()))
+    }
+
+    /// Price the funding book through the spot tickers and report what a
+    /// sweep with this threshold would convert, without sending anything.
+    ///
+    /// A balance is eligible when its available amount prices below
+    /// `max_notional` USDT. OKB is never a candidate (it is the conversion
+    /// target), and assets without a USDT spot ticker are skipped rather
+    /// than converted blind — an unpriceable balance might not be dust.
+    pub async fn preview_dust_conversion(
+        &self,
+        max_notional: Decimal,
+    ) -> DriverResult<DustConversionPreview> {
+        let balances = self.rest_fetch_funding_balances().await?;
+        let tickers = self.rest_fetch_tickers("SPOT").await?;
+        let price = |ccy: &str| -> Option<Decimal> {
+            if ccy == "USDT" {
+                return Some(Decimal::ONE);
+            }
+            let inst_id = format!("{ccy}-USDT");
+            tickers
+                .iter()
+                .find(|ticker| ticker.inst_id == inst_id)
+                .and_then(|ticker| ticker.last)
+        };
+
+        let mut candidates = Vec::new();
+        let mut expected_usdt = Decimal::ZERO;
+        for balance in &balances {
+            if balance.ccy == "OKB" || balance.available_balance <= Decimal::ZERO {
+                continue;
+            }
+            let Some(price) = price(&balance.ccy) else {
+                log::debug!(
+                    "dust sweep: no USDT ticker for {}, leaving it alone",
+                    balance.ccy
+                );
+                continue;
+            };
+            let notional_usdt = balance.available_balance * price;
+            if notional_usdt >= max_notional {
+                continue;
+            }
+            expected_usdt += notional_usdt;
+            candidates.push(DustCandidate {
+                ccy: balance.ccy.clone(),
+                balance: balance.available_balance,
+                notional_usdt,
+            });
+        }
+        let expected_okb = price("OKB")
+            .filter(|okb_price| !okb_price.is_zero())
+            .map(|okb_price| expected_usdt / okb_price);
+        Ok(DustConversionPreview {
+            candidates,
+            expected_usdt,
+            expected_okb,
+        })
+    }
+
+    /// Convert every funding balance worth less than `max_notional` USDT to
+    /// OKB, and report each converted leg as a transfer-type
+    /// [`KinesisTransaction`]. A book with no eligible dust returns an
+    /// empty list without touching the conversion endpoint.
+    pub async fn sweep_dust(
+        &self,
+        max_notional: Decimal,
+    ) -> DriverResult<Vec<KinesisTransaction>> {
+        let preview = self.preview_dust_conversion(max_notional).await?;
+        if preview.candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+        let assets = preview
+            .candidates
+            .iter()
+            .map(|candidate| candidate.ccy.clone())
+            .collect();
+        let result = self.rest_convert_dust(assets).await?;
+        // The endpoint reports no timestamp; stamp the legs with the sweep
+        // time so reconciliation can window them.
+        let swept_at = chrono::Utc::now().timestamp_millis().to_string();
+        Ok(result
+            .details
+            .iter()
+            .map(|detail| KinesisTransaction::from_dust_conversion(detail, swept_at.clone()))
+            .collect())
+    }
+
+    /// Request a Convert quote for disposing of `amount` of `from` into
+    /// `to` via `/api/v5/asset/convert/estimate-quote`. Raw endpoint; most
+    /// callers want [`Self::request_conversion_quote`], which binds the
+    /// quote to the local clock for expiry checks.
+    pub async fn rest_convert_estimate(
+        &self,
+        from: &str,
+        to: &str,
+        amount: Decimal,
+    ) -> DriverResult<OkexConvertQuote> {
+        let body = serde_json::json!({
+            "baseCcy": from,
+            "quoteCcy": to,
+            "side": "sell",
+            "rfqSz": amount,
+            "rfqSzCcy": from,
+        })
+        .to_string();
+        let mut data: Vec<OkexConvertQuote> = self
+            .call(
+                Method::Post,
+                "/api/v5/asset/convert/estimate-quote",
+                None,
+                Some(body),
+            )
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty convert quote response".to_string()))
+    }
+
+    /// Execute a Convert quote via `/api/v5/asset/convert/trade`. Raw
+    /// endpoint — no expiry check; see [`Self::execute_conversion`].
+    pub async fn rest_convert_trade(
+        &self,
+        quote_id: &str,
+        base_ccy: &str,
+        quote_ccy: &str,
+        side: &str,
+        size: Decimal,
+        size_ccy: &str,
+    ) -> DriverResult<OkexConvertResult> {
+        let body = serde_json::json!({
+            "quoteId": quote_id,
+            "baseCcy": base_ccy,
+            "quoteCcy": quote_ccy,
+            "side": side,
+            "sz": size,
+            "szCcy": size_ccy,
+        })
+        .to_string();
+        let mut data: Vec<OkexConvertResult> = self
+            .call(Method::Post, "/api/v5/asset/convert/trade", None, Some(body))
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty convert trade response".to_string()))
+    }
+
+    /// Step one of the quote-then-execute Convert flow: price a
+    /// conversion of `amount` `from` into `to`. The returned quote is
+    /// short-lived (~10s); pass it to [`Self::execute_conversion`]
+    /// promptly.
+    pub async fn request_conversion_quote(
+        &self,
+        from: &str,
+        to: &str,
+        amount: Decimal,
+        now: Instant,
+    ) -> DriverResult<ConversionQuote> {
+        let quote = self.rest_convert_estimate(from, to, amount).await?;
+        Ok(ConversionQuote {
+            quote,
+            received_at: now,
+        })
+    }
+
+    /// Step two: execute a previously requested quote, reporting the
+    /// conversion as a trade-type [`KinesisTransaction`]. An expired quote
+    /// fails locally — the exchange would refuse it anyway, so no round
+    /// trip is spent confirming that.
+    pub async fn execute_conversion(
+        &self,
+        quote: &ConversionQuote,
+        now: Instant,
+    ) -> DriverResult<KinesisTransaction> {
+        if quote.is_expired(now) {
+            return Err(DriverError::RequestExpired(format!(
+                "convert quote {} for {}-{} expired; request a fresh quote",
+                quote.quote.quote_id, quote.quote.base_ccy, quote.quote.quote_ccy
+            )));
+        }
+        let result = self
+            .rest_convert_trade(
+                &quote.quote.quote_id,
+                &quote.quote.base_ccy,
+                &quote.quote.quote_ccy,
+                &quote.quote.side,
+                quote.quote.rfq_size,
+                &quote.quote.rfq_size_ccy,
+            )
+            .await?;
+        if result.state != "fullyFilled" {
+            log::warn!(
+                "convert trade {} finished in state {:?}; reporting the filled part",
+                result.trade_id,
+                result.state
+            );
+        }
+        Ok(KinesisTransaction::from_conversion(&result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    /// USDT on two chains plus a chain with empty-string numerics and
+    /// withdrawals disabled.
+    const CURRENCIES_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"ccy":"USDT","chain":"USDT-ERC20","canDep":true,"canWd":true,"minWd":"2","wdQuota":"10000000","minFee":"0.8","maxFee":"1.6","minDepArrivalConfirm":"12","minWdUnlockConfirm":"64"},
+        {"ccy":"USDT","chain":"USDT-TRC20","canDep":true,"canWd":false,"minWd":"","wdQuota":"","minFee":"","maxFee":"","minDepArrivalConfirm":"","minWdUnlockConfirm":""},
+        {"ccy":"BTC","chain":"BTC-Bitcoin","canDep":true,"canWd":true,"minWd":"0.001","wdQuota":"10000000","minFee":"0.0002","maxFee":"0.0004","minDepArrivalConfirm":"2","minWdUnlockConfirm":"4"}
+    ]}"#;
+
+    fn client(transport: &Arc<MockTransport>) -> OkexClient {
+        OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        )
+    }
+
+    #[tokio::test]
+    async fn currencies_parse_including_empty_string_numerics() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(CURRENCIES_PAGE);
+        let client = client(&transport);
+
+        let rows = client.rest_fetch_currencies(Some("USDT")).await.unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].min_withdrawal, Some("2".parse().unwrap()));
+        assert_eq!(rows[0].deposit_confirmations, Some(12));
+        // Empty strings come through as absent, not as parse failures.
+        assert_eq!(rows[1].min_withdrawal, None);
+        assert_eq!(rows[1].min_fee, None);
+        assert!(!rows[1].can_withdraw);
+
+        assert!(transport.requests()[0].url.ends_with("/api/v5/asset/currencies?ccy=USDT"));
+    }
+
+    #[tokio::test]
+    async fn chain_lookup_is_served_from_the_daily_cache() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(CURRENCIES_PAGE);
+        let client = client(&transport);
+
+        let usdt = client.currency_chains("USDT").await.unwrap();
+        assert_eq!(usdt.len(), 2);
+        let btc = client.currency_chains("BTC").await.unwrap();
+        assert_eq!(btc.len(), 1);
+
+        assert_eq!(transport.requests().len(), 1, "second lookup hits the cache");
+    }
+
+    #[tokio::test]
+    async fn withdrawal_validation_checks_chain_minimum_and_availability() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(CURRENCIES_PAGE);
+        let client = client(&transport);
+
+        client
+            .validate_withdrawal("USDT", "USDT-ERC20", "5".parse().unwrap())
+            .await
+            .unwrap();
+
+        let below_min = client
+            .validate_withdrawal("USDT", "USDT-ERC20", "1".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(below_min.to_string().contains("below the USDT-ERC20 minimum"), "{below_min}");
+
+        let disabled = client
+            .validate_withdrawal("USDT", "USDT-TRC20", "5".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(disabled.to_string().contains("disabled"), "{disabled}");
+
+        let wrong_chain = client
+            .validate_withdrawal("BTC", "BTC-Lightning", "1".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(wrong_chain.to_string().contains("supported: BTC-Bitcoin"), "{wrong_chain}");
+
+        let unknown = client
+            .validate_withdrawal("DOGE", "DOGE-Dogecoin", "1".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(unknown, DriverError::Config(_)), "got: {unknown}");
+    }
+
+    /// Funding book: real BTC, two dust balances, existing OKB, and an
+    /// asset with no USDT ticker.
+    const FUNDING_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"ccy":"BTC","bal":"0.5","availBal":"0.5","frozenBal":"0"},
+        {"ccy":"SHIB","bal":"100000","availBal":"100000","frozenBal":"0"},
+        {"ccy":"USDT","bal":"0.5","availBal":"0.5","frozenBal":"0"},
+        {"ccy":"OKB","bal":"2","availBal":"2","frozenBal":"0"},
+        {"ccy":"XYZ","bal":"7","availBal":"7","frozenBal":"0"}
+    ]}"#;
+
+    const TICKERS_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"instId":"BTC-USDT","last":"43250","ts":"1700000000000"},
+        {"instId":"SHIB-USDT","last":"0.00001","ts":"1700000000000"},
+        {"instId":"OKB-USDT","last":"50","ts":"1700000000000"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn dust_preview_keeps_only_balances_below_the_threshold() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(FUNDING_PAGE);
+        transport.push_json(TICKERS_PAGE);
+        let client = client(&transport);
+
+        let preview = client
+            .preview_dust_conversion("10".parse().unwrap())
+            .await
+            .unwrap();
+
+        // BTC prices at 21625 USDT and stays; OKB is the target; XYZ has
+        // no ticker. Only the two real dust balances remain.
+        let ccys: Vec<&str> = preview.candidates.iter().map(|c| c.ccy.as_str()).collect();
+        assert_eq!(ccys, ["SHIB", "USDT"]);
+        assert_eq!(preview.candidates[0].notional_usdt, "1".parse::<Decimal>().unwrap());
+        assert_eq!(preview.expected_usdt, "1.5".parse::<Decimal>().unwrap());
+        // 1.5 USDT at 50 USDT/OKB.
+        assert_eq!(preview.expected_okb, Some("0.03".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn sweep_converts_the_eligible_dust_and_reports_transfers() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(FUNDING_PAGE);
+        transport.push_json(TICKERS_PAGE);
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"totalCnvAmt":"0.0298","details":[
+                {"ccy":"SHIB","amt":"100000","cnvAmt":"0.0199","fee":"0.0001"},
+                {"ccy":"USDT","amt":"0.5","cnvAmt":"0.0099","fee":"0.0001"}
+            ]}]}"#,
+        );
+        let client = client(&transport);
+
+        let transactions = client.sweep_dust("10".parse().unwrap()).await.unwrap();
+
+        let convert = &transport.requests()[2];
+        assert!(convert.url.ends_with("/api/v5/asset/convert-dust-assets"));
+        assert_eq!(
+            convert.body.as_deref(),
+            Some(r#"{"ccy":["SHIB","USDT"]}"#),
+            "only the eligible assets may be converted"
+        );
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(
+            transactions[0].transaction_type,
+            crate::reporting::KinesisTransactionType::Transfer
+        );
+        assert_eq!(transactions[0].inst_id, "SHIB");
+        // Dust leaves the account: negative amount, fee as a positive cost.
+        assert_eq!(transactions[0].amount, "-100000".parse::<Decimal>().unwrap());
+        assert_eq!(transactions[0].fee, Some("0.0001".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn a_clean_book_never_hits_the_conversion_endpoint() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(FUNDING_PAGE);
+        transport.push_json(TICKERS_PAGE);
+        let client = client(&transport);
+
+        // Threshold below every balance: nothing qualifies.
+        let transactions = client.sweep_dust("0.1".parse().unwrap()).await.unwrap();
+        assert!(transactions.is_empty());
+        assert_eq!(transport.requests().len(), 2, "no conversion request was sent");
+
+        let empty = client.rest_convert_dust(Vec::new()).await.unwrap_err();
+        assert!(matches!(empty, DriverError::Config(_)), "got: {empty}");
+    }
+
+    /// Quote for selling 1500 USDC into USDT, good for ten seconds.
+    const QUOTE_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"quoteId":"quoterUSDC1700","baseCcy":"USDC","quoteCcy":"USDT","side":"sell","cnvtPx":"0.9988","baseSz":"1500","quoteSz":"1498.2","rfqSz":"1500","rfqSzCcy":"USDC","quoteTime":"1700000000000","ttlMs":"10000"}
+    ]}"#;
+
+    const TRADE_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"tradeId":"trade1700","instId":"USDC-USDT","baseCcy":"USDC","quoteCcy":"USDT","side":"sell","fillPx":"0.9988","fillBaseSz":"1500","fillQuoteSz":"1498.2","state":"fullyFilled","ts":"1700000005000"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn a_conversion_quotes_then_executes_as_a_trade() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(QUOTE_PAGE);
+        transport.push_json(TRADE_PAGE);
+        let client = client(&transport);
+        let now = Instant::now();
+
+        let quote = client
+            .request_conversion_quote("USDC", "USDT", "1500".parse().unwrap(), now)
+            .await
+            .unwrap();
+        assert_eq!(quote.quote.convert_price, "0.9988".parse::<Decimal>().unwrap());
+        assert_eq!(quote.expires_in(now), std::time::Duration::from_millis(10_000));
+
+        // Well inside the window: the quote executes.
+        let transaction = client
+            .execute_conversion(&quote, now + std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let estimate = &transport.requests()[0];
+        assert!(estimate.url.ends_with("/api/v5/asset/convert/estimate-quote"));
+        assert_eq!(
+            estimate.body.as_deref(),
+            Some(r#"{"baseCcy":"USDC","quoteCcy":"USDT","rfqSz":"1500","rfqSzCcy":"USDC","side":"sell"}"#)
+        );
+        let trade = &transport.requests()[1];
+        assert!(trade.url.ends_with("/api/v5/asset/convert/trade"));
+        assert_eq!(
+            trade.body.as_deref(),
+            Some(r#"{"baseCcy":"USDC","quoteCcy":"USDT","quoteId":"quoterUSDC1700","side":"sell","sz":"1500","szCcy":"USDC"}"#)
+        );
+
+        assert_eq!(
+            transaction.transaction_type,
+            crate::reporting::KinesisTransactionType::Trade
+        );
+        assert_eq!(transaction.inst_id, "USDC-USDT");
+        // Sold base: the amount is negative, the fill price is the close.
+        assert_eq!(transaction.amount, "-1500".parse::<Decimal>().unwrap());
+        assert_eq!(transaction.close_price, Some("0.9988".parse().unwrap()));
+        assert_eq!(transaction.timestamp, "1700000005000");
+    }
+
+    #[tokio::test]
+    async fn an_expired_quote_fails_locally_without_an_execution_call() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(QUOTE_PAGE);
+        let client = client(&transport);
+        let now = Instant::now();
+
+        let quote = client
+            .request_conversion_quote("USDC", "USDT", "1500".parse().unwrap(), now)
+            .await
+            .unwrap();
+
+        // One second past the 10s TTL.
+        let error = client
+            .execute_conversion(&quote, now + std::time::Duration::from_secs(11))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DriverError::RequestExpired(_)), "got: {error}");
+        assert!(error.to_string().contains("request a fresh quote"), "{error}");
+        assert_eq!(transport.requests().len(), 1, "no trade call was made");
+    }
+}
@@ -0,0 +1,113 @@
+//! Market data REST endpoints.
+
+use crate::api_structs::{OkexOrderBookSnapshot, OkexTicker};
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::Instrument;
+use crate::order_book::OrderBook;
+use crate::transport::Method;
+
+use super::OkexClient;
+
+/// Deepest snapshot `/api/v5/market/books` serves.
+const MAX_BOOK_DEPTH: usize = 400;
+
+impl OkexClient {
+    /// Fetch one `/api/v5/market/books` depth snapshot — a one-shot
+    /// alternative to a persistent books subscription. `depth` is capped at
+    /// 400 levels per side by the exchange.
+    pub async fn rest_fetch_order_book(
+        &self,
+        inst_id: &str,
+        depth: usize,
+    ) -> DriverResult<OkexOrderBookSnapshot> {
+        if depth == 0 || depth > MAX_BOOK_DEPTH {
+            return Err(DriverError::Config(format!(
+                "book depth must be 1..={MAX_BOOK_DEPTH}, got {depth}"
+            )));
+        }
+        let query = format!("instId={inst_id}&sz={depth}");
+        let mut data: Vec<OkexOrderBookSnapshot> = self
+            .call(Method::Get, "/api/v5/market/books", Some(&query), None)
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic(format!("no order book for {inst_id}")))
+    }
+
+    /// Like [`Self::rest_fetch_order_book`] but normalized against the
+    /// instrument: Decimal levels, contract sizes converted to base
+    /// amounts, both sides sorted best-first.
+    pub async fn fetch_order_book(
+        &self,
+        instrument: &Instrument,
+        depth: usize,
+    ) -> DriverResult<OrderBook> {
+        let snapshot = self.rest_fetch_order_book(&instrument.inst_id, depth).await?;
+        OrderBook::from_snapshot(&snapshot, instrument)
+    }
+
+    /// Fetch `/api/v5/market/tickers` for one instrument type (`SPOT`,
+    /// `SWAP`, ...). One-shot pricing for notional estimates; anything
+    /// latency-sensitive should use the tickers WS channel instead.
+    pub async fn rest_fetch_tickers(&self, inst_type: &str) -> DriverResult<Vec<OkexTicker>> {
+        let query = format!("instType={inst_type}");
+        self.call_elements(Method::Get, "/api/v5/market/tickers", Some(&query), None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    #[tokio::test]
+    async fn book_fetch_sends_depth_and_normalizes_the_snapshot() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{
+                "bids": [["43250.0", "100", "0", "3"]],
+                "asks": [["43250.5", "50", "0", "1"]],
+                "ts": "1700000000000"
+            }]}"#,
+        );
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let swap = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+
+        let book = client.fetch_order_book(&swap, 20).await.unwrap();
+        assert_eq!(book.bids[0].size, "1".parse().unwrap());
+        assert_eq!(book.asks[0].size, "0.5".parse().unwrap());
+        assert!(transport.requests()[0]
+            .url
+            .ends_with("/api/v5/market/books?instId=BTC-USDT-SWAP&sz=20"));
+    }
+
+    #[tokio::test]
+    async fn depth_outside_the_exchange_cap_is_rejected_locally() {
+        let transport = Arc::new(MockTransport::new());
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+
+        let err = client.rest_fetch_order_book("BTC-USDT", 401).await.unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "got: {err}");
+        let err = client.rest_fetch_order_book("BTC-USDT", 0).await.unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "got: {err}");
+        assert!(transport.requests().is_empty(), "no request may be sent");
+    }
+}
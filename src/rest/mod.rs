@@ -0,0 +1,1931 @@
+//! Signed REST client for the OKX v5 API.
+
+mod account;
+mod asset;
+mod market;
+mod paginate;
+mod public;
+pub(crate) mod trade;
+
+pub use paginate::PageWalk;
+pub use public::SystemStatusPoller;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+
+use crate::api_structs::OkexRestResponse;
+use crate::config::{OkexConfig, OkexCredentials};
+use crate::errors::{DriverError, DriverResult, ErrorLog, OkexErrorDetails};
+use crate::rate_limiter::AdaptiveRateLimiter;
+use crate::transport::{HttpRequest, HttpTransport, IsahcTransport, Method};
+
+/// Consecutive connect/5xx failures on one endpoint before failing over.
+const ENDPOINT_FAILOVER_THRESHOLD: u32 = 2;
+
+/// OKX business codes meaning the credentials themselves are bad (invalid
+/// key, invalid sign, invalid passphrase) rather than the request.
+fn is_auth_code(code: &str) -> bool {
+    matches!(code, "50111" | "50113" | "50114")
+}
+
+/// Business code OKX returns when a request arrives after the `expTime`
+/// deadline it carried; the request was dropped, not acted on.
+pub(crate) const REQUEST_EXPIRED_CODE: &str = "50022";
+
+/// Order-mutating endpoints that carry an `expTime` deadline when
+/// [`OkexConfig::order_request_validity`] is set. Cancels are deliberately
+/// absent: a late cancel is still worth executing.
+const DEADLINE_PATHS: [&str; 3] = [
+    "/api/v5/trade/order",
+    "/api/v5/trade/amend-order",
+    "/api/v5/trade/amend-batch-orders",
+];
+
+/// Whether a path needs no authentication; public endpoints keep working
+/// while the credentials are latched invalid.
+fn is_public_path(path: &str) -> bool {
+    matches!(endpoint_category(path), "public" | "market")
+}
+
+/// Where one call's `x-simulated-trading` header comes from. Every typed
+/// call follows the config; the per-call overrides exist so support can
+/// replay a failing request against the demo environment without spinning
+/// up a second client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimulatedTradingMode {
+    /// Follow [`OkexConfig::use_testnet`].
+    #[default]
+    FromConfig,
+    /// Force the header on, routing the call to the demo environment.
+    ForceDemo,
+    /// Suppress the header even on a testnet-configured client.
+    ForceLive,
+}
+
+impl SimulatedTradingMode {
+    /// Whether the header is sent, given the configured default.
+    fn simulated(self, use_testnet: bool) -> bool {
+        match self {
+            Self::FromConfig => use_testnet,
+            Self::ForceDemo => true,
+            Self::ForceLive => false,
+        }
+    }
+}
+
+/// Per-request observability data handed to the [`MetricsHook`].
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    /// Base URL that served (or failed to serve) the request.
+    pub endpoint: String,
+    pub path: String,
+    /// HTTP status, `None` when the request never got a response.
+    pub status: Option<u16>,
+    pub latency: Duration,
+}
+
+/// Hook invoked after every REST attempt, including failed ones.
+pub trait MetricsHook: Send + Sync {
+    fn on_request(&self, metrics: &RequestMetrics);
+
+    /// Elements of a fetched page that failed to deserialize and were
+    /// skipped in lenient parsing mode.
+    fn on_skipped_elements(&self, _path: &str, _count: usize) {}
+
+    /// Order-throttle utilization after each granted placement permit.
+    fn on_order_throttle(&self, _utilization: &crate::order_throttle::ThrottleUtilization) {}
+
+    /// A fill arrived on one path (REST reconciliation or the WS fills
+    /// stream) after already being delivered on the other and was dropped.
+    fn on_duplicate_trade(&self, _inst_id: &str, _trade_id: &str) {}
+
+    /// Records (fills, orders) on an instrument nobody configured were
+    /// skipped during mapping; see
+    /// [`crate::config::UnknownInstrumentPolicy`].
+    fn on_unknown_instrument_records(&self, _inst_id: &str, _count: usize) {}
+
+    /// In-flight WS op count after each insert or removal — a gauge of the
+    /// correlation map.
+    fn on_ws_pending_ops(&self, _size: usize) {}
+
+    /// Unanswered WS ops evicted from the correlation map by its TTL
+    /// backstop.
+    fn on_ws_pending_evicted(&self, _count: u64) {}
+
+    /// One inbound WS frame's delay from socket read to handler
+    /// completion, per channel; see [`crate::ws::lag`]. The observer's
+    /// histogram material.
+    fn on_ws_processing_lag(&self, _channel: &str, _lag: Duration) {}
+
+    /// Receive time minus the exchange's own event timestamp on an
+    /// inbound WS frame — a network+exchange delivery lag estimate.
+    fn on_ws_exchange_lag(&self, _channel: &str, _lag: Duration) {}
+
+    /// A pair's running maker/taker and fee bucket for one UTC day,
+    /// pushed after each attributed fill (see [`crate::liquidity`]); the
+    /// observer's gauge material.
+    fn on_liquidity_breakdown(
+        &self,
+        _pair: &str,
+        _date: chrono::NaiveDate,
+        _breakdown: &crate::liquidity::LiquidityBreakdown,
+    ) {
+    }
+}
+
+/// Latest exchange-reported rate-limit state for one endpoint category.
+#[derive(Debug, Clone)]
+pub struct RateLimitState {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Milliseconds until the exchange window resets, when reported.
+    pub reset_ms: Option<u64>,
+    pub observed_at: Instant,
+}
+
+/// Category key for rate limiting: the first path segment after `/api/v5/`
+/// (`trade`, `account`, `market`, ...).
+fn endpoint_category(path: &str) -> &str {
+    let rest = path.strip_prefix("/api/v5/").unwrap_or(path);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+/// Pull `ratelimit-limit` / `-remaining` / `-reset` out of response headers,
+/// tolerating vendor prefixes. Returns `None` when the endpoint omits them.
+fn parse_rate_limit_headers(headers: &[(String, String)]) -> Option<RateLimitState> {
+    let find = |suffix: &str| {
+        headers.iter().find_map(|(name, value)| {
+            name.to_ascii_lowercase()
+                .ends_with(suffix)
+                .then(|| value.trim().parse::<u64>().ok())
+                .flatten()
+        })
+    };
+    let limit = find("ratelimit-limit")?;
+    let remaining = find("ratelimit-remaining")?;
+    Some(RateLimitState {
+        limit: limit as u32,
+        remaining: remaining as u32,
+        reset_ms: find("ratelimit-reset"),
+        observed_at: Instant::now(),
+    })
+}
+
+/// Lower-cased `Content-Type` value, without parameters like `charset`.
+/// `None` when the response does not declare one.
+fn response_content_type(headers: &[(String, String)]) -> Option<String> {
+    headers.iter().find_map(|(name, value)| {
+        name.eq_ignore_ascii_case("content-type").then(|| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or(value)
+                .trim()
+                .to_ascii_lowercase()
+        })
+    })
+}
+
+/// Rotation state across the configured base URLs.
+struct EndpointState {
+    /// Position in `order`; `order[active]` indexes `config.http_base_urls`.
+    active: usize,
+    /// Rotation order over `config.http_base_urls`: configured order until
+    /// a latency probe reorders it fastest-first.
+    order: Vec<usize>,
+    consecutive_failures: u32,
+    /// When we left the primary; cleared once we return to it.
+    failed_over_at: Option<Instant>,
+}
+
+/// One probed REST endpoint measurement; see
+/// [`OkexClient::probe_endpoints`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct EndpointLatency {
+    pub base_url: String,
+    /// Round trip of one `/api/v5/public/time` call; `None` when the
+    /// probe could not reach the endpoint.
+    pub latency_ms: Option<u64>,
+}
+
+/// Signed OKX REST client. Cheap to clone via the shared transport.
+pub struct OkexClient {
+    config: OkexConfig,
+    /// Active signing credentials; swappable at runtime for key rotation.
+    /// Requests read the set once per attempt, so in-flight requests signed
+    /// with an old key complete untouched by a swap.
+    credentials: std::sync::RwLock<OkexCredentials>,
+    transport: Arc<dyn HttpTransport>,
+    endpoint_state: Mutex<EndpointState>,
+    /// Latest probe measurements, in configured URL order; empty until
+    /// [`OkexClient::probe_endpoints`] runs.
+    endpoint_latencies: Mutex<Vec<EndpointLatency>>,
+    metrics_hook: Option<Arc<dyn MetricsHook>>,
+    rate_limiter: AdaptiveRateLimiter,
+    rate_limits: Mutex<HashMap<String, RateLimitState>>,
+    error_log: ErrorLog,
+    currency_cache: Mutex<Option<asset::CurrencyCache>>,
+    /// Per-instrument margin tier ladders with a daily TTL; see
+    /// [`OkexClient::position_tiers`].
+    position_tier_cache: Mutex<HashMap<String, public::PositionTierCache>>,
+    /// Per-instrument-type order defaults resolved from the account level;
+    /// `None` until [`OkexClient::resolve_order_defaults`] has run.
+    order_defaults: Mutex<Option<crate::orders::OrderDefaults>>,
+    /// Per-instrument pre-serialized order templates for the hot
+    /// placement path; a template whose statics no longer match (e.g.
+    /// after an account-mode change flips `tdMode`) is rebuilt on use.
+    order_templates: Mutex<HashMap<String, crate::orders::OrderTemplate>>,
+    /// Latched once the exchange rejects the credentials; private calls
+    /// fail fast until a rotation swaps in a working set.
+    credentials_invalid: std::sync::atomic::AtomicBool,
+    /// Per-endpoint-category breakers for exchange incidents; see
+    /// [`crate::circuit_breaker`].
+    circuit_breaker: crate::circuit_breaker::CircuitBreaker,
+    /// Driver event stream, for out-of-band conditions like invalid
+    /// credentials; `None` when no consumer is attached.
+    events: Option<crate::events::DriverEventSender>,
+    /// Public-data WS component; attached by the connection owner, and the
+    /// connection itself is only dialed by the first public subscription.
+    public_ws: std::sync::OnceLock<Arc<crate::ws::public::PublicWsClient>>,
+    /// Runtime wire-logging toggle, shared with the WS loops.
+    wire_log: Arc<crate::wire_log::WireLogger>,
+    /// Wire-traffic recorder appending REST responses to a capture;
+    /// `None` when not recording. See [`crate::capture`].
+    #[cfg(feature = "capture")]
+    recorder: Option<Arc<crate::capture::CaptureRecorder>>,
+}
+
+impl OkexClient {
+    pub fn new(config: OkexConfig) -> DriverResult<Self> {
+        config
+            .validate()
+            .map_err(|report| DriverError::Config(report.to_string()))?;
+        let transport: Arc<dyn HttpTransport> = Arc::new(
+            IsahcTransport::new(config.enable_compression)?
+                .with_max_response_bytes(config.max_response_bytes),
+        );
+        Ok(Self::with_transport(config, transport))
+    }
+
+    /// Construct with a custom transport; used by tests and the real
+    /// constructor alike.
+    pub(crate) fn with_transport(config: OkexConfig, transport: Arc<dyn HttpTransport>) -> Self {
+        let url_count = config.http_base_urls.len();
+        Self {
+            credentials: std::sync::RwLock::new(config.credentials()),
+            config,
+            transport,
+            endpoint_state: Mutex::new(EndpointState {
+                active: 0,
+                order: (0..url_count).collect(),
+                consecutive_failures: 0,
+                failed_over_at: None,
+            }),
+            endpoint_latencies: Mutex::new(Vec::new()),
+            metrics_hook: None,
+            rate_limiter: AdaptiveRateLimiter::new(),
+            rate_limits: Mutex::new(HashMap::new()),
+            error_log: ErrorLog::default(),
+            currency_cache: Mutex::new(None),
+            position_tier_cache: Mutex::new(HashMap::new()),
+            order_defaults: Mutex::new(None),
+            order_templates: Mutex::new(HashMap::new()),
+            credentials_invalid: std::sync::atomic::AtomicBool::new(false),
+            circuit_breaker: crate::circuit_breaker::CircuitBreaker::default(),
+            events: None,
+            public_ws: std::sync::OnceLock::new(),
+            wire_log: Arc::new(crate::wire_log::WireLogger::new()),
+            #[cfg(feature = "capture")]
+            recorder: None,
+        }
+    }
+
+    /// Append every REST response this client receives to a capture; see
+    /// [`crate::capture`] for the format and the replay side.
+    #[cfg(feature = "capture")]
+    pub fn set_recorder(&mut self, recorder: Arc<crate::capture::CaptureRecorder>) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Set the wire-log level; `Off` by default. `Full` logs request and
+    /// frame bodies with credentials and signatures always redacted — see
+    /// [`crate::wire_log`].
+    pub fn set_wire_logging(&self, level: crate::wire_log::WireLogLevel) {
+        self.wire_log.set_level(level);
+    }
+
+    /// [`Self::set_wire_logging`] with an automatic revert to `Off` after
+    /// `window`, so an incident-debugging session cannot be left on.
+    pub fn set_wire_logging_for(
+        &self,
+        level: crate::wire_log::WireLogLevel,
+        window: Duration,
+    ) {
+        self.wire_log.set_level_for(level, window);
+    }
+
+    /// The wire logger, for sharing with the WS loops so one toggle
+    /// covers both protocols.
+    pub fn wire_logger(&self) -> Arc<crate::wire_log::WireLogger> {
+        Arc::clone(&self.wire_log)
+    }
+
+    /// Attach the public-data WS component. Nothing is dialed here: the
+    /// component holds its connection factory until the first
+    /// [`Self::subscribe_public`] starts it.
+    pub fn attach_public_ws(&self, public_ws: Arc<crate::ws::public::PublicWsClient>) {
+        if self.public_ws.set(public_ws).is_err() {
+            log::warn!("public ws component already attached; keeping the first");
+        }
+    }
+
+    /// The attached public-data WS component, when there is one.
+    pub fn public_ws(&self) -> Option<&Arc<crate::ws::public::PublicWsClient>> {
+        self.public_ws.get()
+    }
+
+    /// Subscribe to a public channel (tickers, books, funding), starting
+    /// the public connection on first use. Entirely independent of the
+    /// private connection: a public-data outage never touches trading.
+    pub fn subscribe_public(&self, channel: &str, inst_id: Option<&str>) -> DriverResult<()> {
+        let Some(public_ws) = self.public_ws.get() else {
+            return Err(DriverError::Config(
+                "no public ws component attached".to_string(),
+            ));
+        };
+        public_ws.subscribe(channel, inst_id);
+        Ok(())
+    }
+
+    /// Structured details of the most recent errors, oldest first; the
+    /// machine-readable side channel behind the error strings.
+    pub fn last_error_details(&self) -> Vec<OkexErrorDetails> {
+        self.error_log.snapshot()
+    }
+
+    /// Build (and record) an API error with structured endpoint context.
+    pub(crate) fn api_error(&self, endpoint: &str, code: String, message: String) -> DriverError {
+        let details = OkexErrorDetails {
+            endpoint: endpoint.to_string(),
+            request_id: None,
+            code: Some(code.clone()),
+            message: message.clone(),
+        };
+        let error = if is_auth_code(&code) {
+            let reason = format!("{}: {message}", details.context());
+            self.flag_credentials_invalid(&reason);
+            DriverError::Unauthorized(reason)
+        } else if code == REQUEST_EXPIRED_CODE {
+            DriverError::RequestExpired(format!("{}: {message}", details.context()))
+        } else {
+            DriverError::Api {
+                code,
+                message: format!("{}: {message}", details.context()),
+            }
+        };
+        self.error_log.record(details);
+        error
+    }
+
+    /// Build (and record) an HTTP-level error with structured context.
+    fn http_error(&self, endpoint: &str, status: u16, body: &str) -> DriverError {
+        let details = OkexErrorDetails {
+            endpoint: endpoint.to_string(),
+            request_id: None,
+            code: None,
+            message: format!("status {status}: {body}"),
+        };
+        let error = if status == 401 || status == 403 {
+            let reason = format!("{}: status {status}: {body}", details.context());
+            self.flag_credentials_invalid(&reason);
+            DriverError::Unauthorized(reason)
+        } else {
+            DriverError::Http(format!("{}: status {status}: {body}", details.context()))
+        };
+        self.error_log.record(details);
+        error
+    }
+
+    /// Latest exchange-reported rate-limit state per endpoint category, for
+    /// dashboards.
+    pub fn rate_limit_state(&self) -> HashMap<String, RateLimitState> {
+        self.rate_limits.lock().unwrap().clone()
+    }
+
+    /// The adaptive limiter fed from response headers.
+    pub fn rate_limiter(&self) -> &AdaptiveRateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Install an observer for per-request metrics.
+    pub fn set_metrics_hook(&mut self, hook: Arc<dyn MetricsHook>) {
+        self.metrics_hook = Some(hook);
+    }
+
+    /// Attach the driver event stream for out-of-band conditions.
+    pub fn set_event_sender(&mut self, events: crate::events::DriverEventSender) {
+        self.events = Some(events);
+    }
+
+    /// Whether the credentials have been flagged invalid by the exchange.
+    pub fn credentials_invalid(&self) -> bool {
+        self.credentials_invalid
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Latch the credentials as invalid, once: records the details, fails
+    /// subsequent private calls fast, and pages through the event stream.
+    fn flag_credentials_invalid(&self, reason: &str) {
+        if self
+            .credentials_invalid
+            .swap(true, std::sync::atomic::Ordering::Relaxed)
+        {
+            return;
+        }
+        log::error!("credentials rejected by the exchange: {reason}");
+        if let Some(events) = &self.events {
+            let _ = events.send(crate::events::DriverEvent::CredentialsInvalid {
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    /// Circuit-breaker state per endpoint category, for the health
+    /// snapshot.
+    pub fn circuit_breaker_states(
+        &self,
+    ) -> HashMap<String, crate::circuit_breaker::CircuitState> {
+        self.circuit_breaker.states()
+    }
+
+    /// Log a breaker state change and forward it on the event stream.
+    fn emit_breaker_transition(
+        &self,
+        transition: Option<crate::circuit_breaker::Transition>,
+    ) {
+        let Some(transition) = transition else {
+            return;
+        };
+        log::warn!(
+            "circuit breaker for {} endpoints: {:?} -> {:?}",
+            transition.category,
+            transition.from,
+            transition.to
+        );
+        if let Some(events) = &self.events {
+            let _ = events.send(crate::events::DriverEvent::CircuitBreakerStateChanged {
+                category: transition.category,
+                from: transition.from,
+                to: transition.to,
+            });
+        }
+    }
+
+    /// The installed metrics hook, shared with driver-level components
+    /// (e.g. the order throttle) so one observer sees everything.
+    pub(crate) fn metrics_hook(&self) -> Option<Arc<dyn MetricsHook>> {
+        self.metrics_hook.clone()
+    }
+
+    pub fn config(&self) -> &OkexConfig {
+        &self.config
+    }
+
+    /// Apply the configured [`crate::config::UnknownInstrumentPolicy`] to
+    /// the unknown-instrument records a mapping call skipped: log (debug
+    /// under `Drop`, warn under `Warn`), count through the metrics hook,
+    /// and under `Error` fail the call listing the offending instruments.
+    /// `unknown` maps instrument id to skipped-record count; the sorted
+    /// map keeps logs and error lists deterministic.
+    pub(crate) fn handle_unknown_instruments(
+        &self,
+        context: &str,
+        unknown: &std::collections::BTreeMap<String, usize>,
+    ) -> DriverResult<()> {
+        use crate::config::UnknownInstrumentPolicy;
+
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        let policy = self.config.unknown_instrument_policy;
+        for (inst_id, count) in unknown {
+            match policy {
+                UnknownInstrumentPolicy::Drop => log::debug!(
+                    "{context}: skipped {count} records on unconfigured instrument {inst_id}"
+                ),
+                UnknownInstrumentPolicy::Warn | UnknownInstrumentPolicy::Error => log::warn!(
+                    "{context}: skipped {count} records on unconfigured instrument {inst_id}"
+                ),
+            }
+            if let Some(hook) = &self.metrics_hook {
+                hook.on_unknown_instrument_records(inst_id, *count);
+            }
+        }
+        if policy == UnknownInstrumentPolicy::Error {
+            return Err(DriverError::UnknownInstruments {
+                context: context.to_string(),
+                instruments: unknown.keys().cloned().collect(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The resolved per-instrument-type order defaults, when
+    /// [`Self::resolve_order_defaults`] has run.
+    pub fn order_defaults(&self) -> Option<crate::orders::OrderDefaults> {
+        self.order_defaults.lock().unwrap().clone()
+    }
+
+    /// `expTime` header value for order placements and amendments: now
+    /// plus the configured validity window, epoch milliseconds. `None` on
+    /// other endpoints or when the window is disabled.
+    fn order_exp_time(&self, path: &str) -> Option<String> {
+        self.order_exp_time_at(path, chrono::Utc::now().timestamp_millis())
+    }
+
+    fn order_exp_time_at(&self, path: &str, now_ms: i64) -> Option<String> {
+        if !DEADLINE_PATHS.contains(&path) {
+            return None;
+        }
+        let validity = self.config.order_request_validity?;
+        Some((now_ms + validity.as_millis() as i64).to_string())
+    }
+
+    /// Base URL the next request will target, honouring failover state and
+    /// the cooldown back to the primary.
+    fn current_base_url(&self) -> String {
+        let mut state = self.endpoint_state.lock().unwrap();
+        if state.active != 0 {
+            if let Some(since) = state.failed_over_at {
+                if since.elapsed() >= self.config.endpoint_cooldown {
+                    state.active = 0;
+                    state.consecutive_failures = 0;
+                    state.failed_over_at = None;
+                }
+            }
+        }
+        self.config.http_base_urls[state.order[state.active]].clone()
+    }
+
+    /// Record a connect/5xx failure; rotates to the next endpoint once the
+    /// threshold is reached. Returns `true` when a rotation happened.
+    fn record_endpoint_failure(&self) -> bool {
+        let mut state = self.endpoint_state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= ENDPOINT_FAILOVER_THRESHOLD {
+            state.active = (state.active + 1) % state.order.len();
+            state.consecutive_failures = 0;
+            state.failed_over_at = if state.active == 0 {
+                None
+            } else {
+                Some(Instant::now())
+            };
+            return true;
+        }
+        false
+    }
+
+    fn record_endpoint_success(&self) {
+        self.endpoint_state.lock().unwrap().consecutive_failures = 0;
+    }
+
+    /// Time one cheap `/api/v5/public/time` against every configured REST
+    /// base URL, reorder the failover rotation fastest-first, and keep the
+    /// measurements for the health snapshot. Meant for startup and
+    /// periodic re-runs from a multi-region deployment where the static
+    /// primary is not the nearest host. In-flight requests keep the URL
+    /// they already resolved; only subsequent attempts see the new order.
+    /// Unreachable candidates sort last, keeping their configured relative
+    /// order, so failover still knows about them.
+    pub async fn probe_endpoints(&self) -> Vec<EndpointLatency> {
+        let mut measurements = Vec::with_capacity(self.config.http_base_urls.len());
+        for base_url in &self.config.http_base_urls {
+            let started = Instant::now();
+            let result = self
+                .transport
+                .execute(HttpRequest {
+                    method: Method::Get,
+                    url: format!("{base_url}/api/v5/public/time"),
+                    headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+                    body: None,
+                })
+                .await;
+            let latency_ms = match result {
+                Ok(response) if (200..300).contains(&response.status) => {
+                    Some(started.elapsed().as_millis() as u64)
+                }
+                _ => None,
+            };
+            measurements.push(EndpointLatency {
+                base_url: base_url.clone(),
+                latency_ms,
+            });
+        }
+        let mut order: Vec<usize> = (0..measurements.len()).collect();
+        // Stable sort: unreachable candidates keep their configured order
+        // behind every reachable one.
+        order.sort_by_key(|&i| (measurements[i].latency_ms.is_none(), measurements[i].latency_ms));
+        if let Some(&fastest) = order.first() {
+            log::info!(
+                "endpoint probe selected {} as primary ({:?}ms)",
+                self.config.http_base_urls[fastest],
+                measurements[fastest].latency_ms
+            );
+        }
+        {
+            let mut state = self.endpoint_state.lock().unwrap();
+            state.order = order;
+            state.active = 0;
+            state.consecutive_failures = 0;
+            state.failed_over_at = None;
+        }
+        *self.endpoint_latencies.lock().unwrap() = measurements.clone();
+        measurements
+    }
+
+    /// Measurements of the last [`Self::probe_endpoints`] run, in
+    /// configured URL order; empty when no probe has run.
+    pub fn endpoint_latencies(&self) -> Vec<EndpointLatency> {
+        self.endpoint_latencies.lock().unwrap().clone()
+    }
+
+    /// Private WS URLs reordered by the last probe's verdict. OKX pairs
+    /// its REST and WS hosts per region (default with default, aws with
+    /// aws), so the REST measurement at an index stands in for the WS
+    /// candidate at the same index; WS URLs beyond the probed range keep
+    /// their configured order at the end.
+    pub fn preferred_ws_urls(&self) -> Vec<String> {
+        let ws_urls = self.config.resolved_ws_urls();
+        let order = self.endpoint_state.lock().unwrap().order.clone();
+        let mut preferred: Vec<String> = order
+            .iter()
+            .filter_map(|&i| ws_urls.get(i).cloned())
+            .collect();
+        preferred.extend(
+            ws_urls
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !order.contains(i))
+                .map(|(_, url)| url.clone()),
+        );
+        preferred
+    }
+
+    fn emit_metrics(&self, endpoint: &str, path: &str, status: Option<u16>, started: Instant) {
+        if let Some(hook) = &self.metrics_hook {
+            hook.on_request(&RequestMetrics {
+                endpoint: endpoint.to_string(),
+                path: path.to_string(),
+                status,
+                latency: started.elapsed(),
+            });
+        }
+    }
+
+    /// The active signing credentials.
+    pub(crate) fn current_credentials(&self) -> OkexCredentials {
+        self.credentials.read().unwrap().clone()
+    }
+
+    /// Atomically swap the active signing credentials. Attempts already
+    /// signed keep their old headers and complete normally.
+    pub(crate) fn swap_credentials(&self, new: OkexCredentials) {
+        *self.credentials.write().unwrap() = new;
+        // The new set signed a probe successfully; lift the fast-fail.
+        self.credentials_invalid
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Probe a credential set with a signed read call without touching the
+    /// active set; used before rotation so a bad key is rejected while the
+    /// old one still signs live traffic.
+    pub(crate) async fn validate_credentials(&self, creds: &OkexCredentials) -> DriverResult<()> {
+        let path = "/api/v5/account/balance";
+        self.rate_limiter.acquire(endpoint_category(path)).await;
+        let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let signature = creds.sign(&format!("{timestamp}GET{path}"));
+        let request = HttpRequest {
+            method: Method::Get,
+            url: format!("{}{path}", self.current_base_url()),
+            headers: vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("OK-ACCESS-KEY".to_string(), creds.api_key.clone()),
+                ("OK-ACCESS-SIGN".to_string(), signature),
+                ("OK-ACCESS-TIMESTAMP".to_string(), timestamp),
+                ("OK-ACCESS-PASSPHRASE".to_string(), creds.passphrase.clone()),
+            ],
+            body: None,
+        };
+        // A rejected candidate must not latch `credentials_invalid` — the
+        // active set is untouched and still signing — so the errors are
+        // built without the classifying helpers.
+        let response = self.transport.execute(request).await?;
+        if !(200..300).contains(&response.status) {
+            return Err(DriverError::Http(format!(
+                "endpoint={path}: status {}: {}",
+                response.status, response.body
+            )));
+        }
+        let envelope: OkexRestResponse<serde_json::Value> = serde_json::from_str(&response.body)?;
+        if envelope.code != "0" {
+            return Err(DriverError::Api {
+                code: envelope.code,
+                message: envelope.msg,
+            });
+        }
+        Ok(())
+    }
+
+    /// Shared request path: sign, send, unwrap the OKX envelope and fail on
+    /// any non-zero business code.
+    ///
+    /// `path` is the endpoint path (`/api/v5/...`); `query` the raw query
+    /// string without the leading `?`.
+    pub(crate) async fn call<U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<String>,
+    ) -> DriverResult<Vec<U>> {
+        let envelope = self.call_envelope(method, path, query, body).await?;
+        if envelope.code != "0" {
+            return Err(self.api_error(path, envelope.code, envelope.msg));
+        }
+        Ok(envelope.data)
+    }
+
+    /// Like [`Self::call`] but deserializes the page element-by-element, so
+    /// one malformed entry cannot fail a whole fetch. In the default lenient
+    /// mode bad elements are skipped, logged, and counted through the
+    /// metrics hook; with [`OkexConfig::strict_parsing`] the call fails
+    /// listing the offending elements. Reporting-style paginated fetchers
+    /// go through this; order-critical single calls stay on [`Self::call`].
+    pub(crate) async fn call_elements<U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<String>,
+    ) -> DriverResult<Vec<U>> {
+        let raw: Vec<serde_json::Value> = self.call(method, path, query, body).await?;
+        let mut parsed = Vec::with_capacity(raw.len());
+        let mut bad = Vec::new();
+        for (index, element) in raw.into_iter().enumerate() {
+            match serde_json::from_value::<U>(element.clone()) {
+                Ok(item) => parsed.push(item),
+                Err(err) => bad.push(format!("element {index} ({err}): {element}")),
+            }
+        }
+        if bad.is_empty() {
+            return Ok(parsed);
+        }
+        if self.config.strict_parsing {
+            return Err(DriverError::Generic(format!(
+                "{path}: {} unparseable page element(s): {}",
+                bad.len(),
+                bad.join("; ")
+            )));
+        }
+        log::warn!(
+            "{path}: skipping {} unparseable page element(s): {}",
+            bad.len(),
+            bad.join("; ")
+        );
+        if let Some(hook) = &self.metrics_hook {
+            hook.on_skipped_elements(path, bad.len());
+        }
+        Ok(parsed)
+    }
+
+    /// Escape hatch for endpoints the driver has no typed method for yet:
+    /// a signed request against an arbitrary `/api/v5/...` path, going
+    /// through the same signing, rate limiting, failover, and envelope
+    /// handling as every typed call.
+    ///
+    /// GET serializes `params` as the query string, POST as the JSON body.
+    /// The top-level business code is *not* checked; callers get the full
+    /// envelope since they know the endpoint's semantics better than we do.
+    #[cfg(feature = "raw-api")]
+    pub async fn signed_request<T: serde::Serialize, U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Option<&T>,
+    ) -> DriverResult<OkexRestResponse<U>> {
+        self.signed_request_in_mode(method, path, params, SimulatedTradingMode::FromConfig)
+            .await
+    }
+
+    /// [`Self::signed_request`] with a per-call simulated-trading override;
+    /// see [`SimulatedTradingMode`]. Trade endpoints on a live-configured
+    /// client reject a demo override outright.
+    #[cfg(feature = "raw-api")]
+    pub async fn signed_request_in_mode<T: serde::Serialize, U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Option<&T>,
+        mode: SimulatedTradingMode,
+    ) -> DriverResult<OkexRestResponse<U>> {
+        if path.contains("://") || !path.starts_with("/api/v5/") {
+            return Err(DriverError::Config(format!(
+                "signed_request path must be a relative /api/v5/ path, got {path:?}"
+            )));
+        }
+        self.check_simulated_mode(path, mode)?;
+
+        let (query, body) = match (method, params) {
+            (Method::Get, Some(params)) => {
+                let query = serde_urlencoded::to_string(params)
+                    .map_err(|e| DriverError::Config(format!("unencodable query params: {e}")))?;
+                (Some(query), None)
+            }
+            (Method::Post, Some(params)) => (None, Some(serde_json::to_string(params)?)),
+            (_, None) => (None, None),
+        };
+        self.call_envelope_in_mode(method, path, query.as_deref(), body, mode).await
+    }
+
+    /// Diagnostics escape hatch: re-issue one request verbatim under an
+    /// explicit simulated-trading decision and hand back the raw envelope.
+    /// Meant for replaying a failing call against the demo environment;
+    /// the same environment-mixing safety check as the raw API applies.
+    pub async fn replay_request(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<String>,
+        mode: SimulatedTradingMode,
+    ) -> DriverResult<OkexRestResponse<serde_json::Value>> {
+        if path.contains("://") || !path.starts_with("/api/v5/") {
+            return Err(DriverError::Config(format!(
+                "replay_request path must be a relative /api/v5/ path, got {path:?}"
+            )));
+        }
+        self.check_simulated_mode(path, mode)?;
+        self.call_envelope_in_mode(method, path, query, body, mode).await
+    }
+
+    /// Like [`Self::call`] but returns the raw envelope, leaving business
+    /// code handling to the caller. Batch endpoints need this because a
+    /// non-zero top-level code still carries per-item results.
+    pub(crate) async fn call_envelope<U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<String>,
+    ) -> DriverResult<OkexRestResponse<U>> {
+        self.call_envelope_in_mode(method, path, query, body, SimulatedTradingMode::FromConfig)
+            .await
+    }
+
+    /// Reject per-call environment overrides that could mix demo and live
+    /// order flow: a live-configured client may replay read-style calls
+    /// against demo, but never anything under `/api/v5/trade/`.
+    fn check_simulated_mode(&self, path: &str, mode: SimulatedTradingMode) -> DriverResult<()> {
+        if mode == SimulatedTradingMode::ForceDemo
+            && !self.config.use_testnet
+            && endpoint_category(path) == "trade"
+        {
+            return Err(DriverError::Config(format!(
+                "refusing to force simulated trading on {path}: the client is configured \
+                 live and trade endpoints must not mix environments"
+            )));
+        }
+        Ok(())
+    }
+
+    /// [`Self::call_envelope`] with an explicit simulated-trading decision;
+    /// the header rides every retry attempt alongside its fresh signature,
+    /// so the whole logical call stays in one environment.
+    pub(crate) async fn call_envelope_in_mode<U: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<String>,
+        mode: SimulatedTradingMode,
+    ) -> DriverResult<OkexRestResponse<U>> {
+        if self.credentials_invalid() && !is_public_path(path) {
+            return Err(DriverError::Unauthorized(
+                "credentials were rejected by the exchange; rotate credentials to resume"
+                    .to_string(),
+            ));
+        }
+        let request_path = match query {
+            Some(q) if !q.is_empty() => format!("{path}?{q}"),
+            _ => path.to_string(),
+        };
+        let body = body.unwrap_or_default();
+
+        let category = endpoint_category(path);
+        // Checked before the rate limiter: an open circuit must not spend
+        // budget on calls that fail fast locally.
+        let (admission, transition) = self.circuit_breaker.admit(category, Instant::now());
+        self.emit_breaker_transition(transition);
+        if let crate::circuit_breaker::Admission::Rejected { retry_in } = admission {
+            return Err(DriverError::ExchangeUnavailable(format!(
+                "{category} endpoints are returning system-busy responses; \
+                 circuit open, next probe in {}ms",
+                retry_in.as_millis()
+            )));
+        }
+        self.rate_limiter.acquire(category).await;
+
+        // Computed once per logical call, so endpoint-failover retries of
+        // the same order cannot push the deadline out. The header is not
+        // part of the signed payload, so per-attempt signing is unaffected.
+        let exp_time = self.order_exp_time(path);
+
+        // Each endpoint gets up to the failover threshold of attempts before
+        // the rotation moves on, so one logical call can ride out a dead
+        // primary without surfacing an error.
+        let max_attempts =
+            (self.config.http_base_urls.len() as u32 * ENDPOINT_FAILOVER_THRESHOLD).max(1);
+        let mut last_error = None;
+
+        for _ in 0..max_attempts {
+            let base_url = self.current_base_url();
+            // Sign per attempt so retries carry a fresh timestamp, reading
+            // the credential set once so a concurrent rotation cannot mix
+            // one key's id with another's signature.
+            let creds = self.current_credentials();
+            let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+            let signature =
+                creds.sign(&format!("{timestamp}{}{request_path}{body}", method.as_str()));
+
+            let mut headers = vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("OK-ACCESS-KEY".to_string(), creds.api_key),
+                ("OK-ACCESS-SIGN".to_string(), signature),
+                ("OK-ACCESS-TIMESTAMP".to_string(), timestamp),
+                ("OK-ACCESS-PASSPHRASE".to_string(), creds.passphrase),
+            ];
+            if mode.simulated(self.config.use_testnet) {
+                headers.push(("x-simulated-trading".to_string(), "1".to_string()));
+            }
+            if let Some(deadline) = &exp_time {
+                headers.push(("expTime".to_string(), deadline.clone()));
+            }
+
+            let request = HttpRequest {
+                method,
+                url: format!("{base_url}{request_path}"),
+                headers,
+                body: if body.is_empty() {
+                    None
+                } else {
+                    Some(body.clone())
+                },
+            };
+
+            self.wire_log.log_rest_request(
+                method.as_str(),
+                &request.url,
+                &request.headers,
+                request.body.as_deref(),
+            );
+
+            let started = Instant::now();
+            let response = match self.transport.execute(request).await {
+                Ok(response) => response,
+                Err(error) => {
+                    self.emit_metrics(&base_url, path, None, started);
+                    self.record_endpoint_failure();
+                    // An oversized payload is deterministic, not flaky:
+                    // retrying would buffer the same flood again. The
+                    // failure above still advances the failover rotation.
+                    if matches!(error, DriverError::ResponseTooLarge { .. }) {
+                        return Err(error);
+                    }
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            self.emit_metrics(&base_url, path, Some(response.status), started);
+            self.wire_log
+                .log_rest_response(path, response.status, &response.body);
+            #[cfg(feature = "capture")]
+            if let Some(recorder) = &self.recorder {
+                recorder.record_rest_response(path, response.status, &response.body);
+            }
+
+            if let Some(state) = parse_rate_limit_headers(&response.headers) {
+                self.rate_limiter.adapt(category, state.limit, state.remaining);
+                self.rate_limits
+                    .lock()
+                    .unwrap()
+                    .insert(category.to_string(), state);
+            }
+
+            if response.status >= 500 {
+                self.record_endpoint_failure();
+                last_error = Some(self.http_error(path, response.status, &response.body));
+                continue;
+            }
+            // A 2xx with a non-JSON content type is a proxy or captive
+            // portal talking, not the exchange; feeding its body through
+            // serde would only yield a confusing parse error. Fails the
+            // endpoint so the rotation backs off the host.
+            if let Some(content_type) = response_content_type(&response.headers) {
+                if !content_type.contains("json") {
+                    self.record_endpoint_failure();
+                    return Err(DriverError::UnexpectedContentType {
+                        endpoint: path.to_string(),
+                        content_type,
+                    });
+                }
+            }
+            self.record_endpoint_success();
+
+            if !(200..300).contains(&response.status) {
+                return Err(self.http_error(path, response.status, &response.body));
+            }
+
+            let envelope: OkexRestResponse<U> = serde_json::from_str(&response.body)?;
+            let transition = if crate::circuit_breaker::SYSTEM_BUSY_CODES
+                .contains(&envelope.code.as_str())
+            {
+                self.circuit_breaker.record_busy(category, Instant::now())
+            } else {
+                self.circuit_breaker.record_ok(category)
+            };
+            self.emit_breaker_transition(transition);
+            return Ok(envelope);
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| DriverError::Generic("no endpoints configured".to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+    use crate::transport::mock::{
+        refused_base_url, spawn_delayed_stub_server, spawn_stub_server, MockTransport,
+    };
+
+    const TIME_RESPONSE: &str = r#"{"code":"0","msg":"","data":[{"ts":"1700000000000"}]}"#;
+
+    #[derive(Default)]
+    struct RecordingHook {
+        seen: StdMutex<Vec<RequestMetrics>>,
+        skipped: StdMutex<Vec<(String, usize)>>,
+    }
+
+    impl MetricsHook for RecordingHook {
+        fn on_request(&self, metrics: &RequestMetrics) {
+            self.seen.lock().unwrap().push(metrics.clone());
+        }
+
+        fn on_skipped_elements(&self, path: &str, count: usize) {
+            self.skipped.lock().unwrap().push((path.to_string(), count));
+        }
+    }
+
+    fn config_with_urls(urls: Vec<String>) -> OkexConfig {
+        OkexConfig {
+            http_base_urls: urls,
+            ..OkexConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn call_fails_over_to_second_endpoint_when_primary_refuses() {
+        let (good_url, server) = spawn_stub_server(vec![TIME_RESPONSE.to_string()]);
+        let config = config_with_urls(vec![refused_base_url(), good_url.clone()]);
+        let client =
+            OkexClient::with_transport(config, Arc::new(IsahcTransport::new(true).unwrap()));
+
+        let data: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+        assert_eq!(data[0]["ts"], "1700000000000");
+
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_over_client_stays_on_fallback_until_cooldown() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_json(TIME_RESPONSE);
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec![
+            "http://primary".to_string(),
+            "http://fallback".to_string(),
+        ]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        for _ in 0..2 {
+            let _: Vec<serde_json::Value> = client
+                .call(Method::Get, "/api/v5/public/time", None, None)
+                .await
+                .unwrap();
+        }
+
+        let urls: Vec<String> = transport.requests().iter().map(|r| r.url.clone()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "http://primary/api/v5/public/time",
+                "http://primary/api/v5/public/time",
+                "http://fallback/api/v5/public/time",
+                "http://fallback/api/v5/public/time",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn client_returns_to_primary_after_cooldown() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_json(TIME_RESPONSE);
+        transport.push_json(TIME_RESPONSE);
+        let mut config = config_with_urls(vec![
+            "http://primary".to_string(),
+            "http://fallback".to_string(),
+        ]);
+        config.endpoint_cooldown = Duration::ZERO;
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+
+        let last = transport.requests().last().unwrap().url.clone();
+        assert_eq!(last, "http://primary/api/v5/public/time");
+    }
+
+    #[tokio::test]
+    async fn the_probe_measures_and_prefers_the_faster_endpoint() {
+        let (slow_url, slow) = spawn_delayed_stub_server(
+            vec![TIME_RESPONSE.to_string()],
+            Duration::from_millis(200),
+        );
+        let (fast_url, fast) =
+            spawn_stub_server(vec![TIME_RESPONSE.to_string(), TIME_RESPONSE.to_string()]);
+        let config = config_with_urls(vec![slow_url.clone(), fast_url.clone()]);
+        let client =
+            OkexClient::with_transport(config, Arc::new(IsahcTransport::new(true).unwrap()));
+
+        let measurements = client.probe_endpoints().await;
+        assert_eq!(measurements[0].base_url, slow_url);
+        assert!(
+            measurements[0].latency_ms.unwrap() > measurements[1].latency_ms.unwrap(),
+            "slow endpoint should measure slower: {measurements:?}"
+        );
+
+        // The next call lands on the faster endpoint, which has one
+        // scripted response left for it.
+        let data: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+        assert_eq!(data[0]["ts"], "1700000000000");
+        slow.join().unwrap();
+        fast.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreachable_candidates_sort_behind_reachable_ones() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_json(TIME_RESPONSE);
+        transport.push_json(TIME_RESPONSE);
+        let config = OkexConfig {
+            http_base_urls: vec!["http://primary".to_string(), "http://aws".to_string()],
+            ws_base_urls: vec![
+                "wss://primary-ws".to_string(),
+                "wss://aws-ws".to_string(),
+            ],
+            ..OkexConfig::default()
+        };
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let measurements = client.probe_endpoints().await;
+        assert_eq!(measurements[0].latency_ms, None);
+        assert!(measurements[1].latency_ms.is_some());
+        assert_eq!(client.endpoint_latencies(), measurements);
+
+        // Rotation now starts at the reachable endpoint, and the WS
+        // candidates follow the same regional order.
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+        let last = transport.requests().last().unwrap().url.clone();
+        assert_eq!(last, "http://aws/api/v5/public/time");
+        assert_eq!(
+            client.preferred_ws_urls(),
+            vec!["wss://aws-ws".to_string(), "wss://primary-ws".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn html_responses_short_circuit_parsing_with_a_typed_error() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(crate::transport::HttpResponse {
+            status: 200,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/html; charset=utf-8".to_string(),
+            )],
+            body: "<html>502 Bad Gateway</html>".to_string(),
+        });
+        let config = config_with_urls(vec![
+            "http://primary".to_string(),
+            "http://aws".to_string(),
+        ]);
+        let client = OkexClient::with_transport(config, Arc::clone(&transport) as Arc<dyn HttpTransport>);
+
+        let err = client
+            .call::<serde_json::Value>(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap_err();
+        match err {
+            DriverError::UnexpectedContentType {
+                endpoint,
+                content_type,
+            } => {
+                assert_eq!(endpoint, "/api/v5/public/time");
+                assert_eq!(content_type, "text/html");
+            }
+            other => panic!("expected a content-type error, got: {other}"),
+        }
+        assert_eq!(transport.requests().len(), 1, "the endpoint is not retried");
+    }
+
+    #[tokio::test]
+    async fn an_oversized_response_fails_the_call_without_endpoint_retries() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_error(DriverError::ResponseTooLarge {
+            endpoint: "http://primary/api/v5/market/tickers".to_string(),
+            limit_bytes: 4 * 1024 * 1024,
+        });
+        let config = config_with_urls(vec![
+            "http://primary".to_string(),
+            "http://aws".to_string(),
+        ]);
+        let client = OkexClient::with_transport(config, Arc::clone(&transport) as Arc<dyn HttpTransport>);
+
+        let err = client
+            .call::<serde_json::Value>(
+                Method::Get,
+                "/api/v5/market/tickers",
+                Some("instType=SPOT"),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::ResponseTooLarge { .. }), "got: {err}");
+        assert_eq!(
+            transport.requests().len(),
+            1,
+            "no immediate re-request of the same flood"
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_system_busy_responses_open_the_circuit() {
+        const BUSY_RESPONSE: &str =
+            r#"{"code":"50013","msg":"System is busy. Please try again later.","data":[]}"#;
+        let transport = Arc::new(MockTransport::new());
+        for _ in 0..5 {
+            transport.push_json(BUSY_RESPONSE);
+        }
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let mut client =
+            OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        client.set_event_sender(events_tx);
+
+        // Busy envelopes are well-formed API errors until the threshold.
+        for _ in 0..5 {
+            let error = client
+                .call::<serde_json::Value>(Method::Get, "/api/v5/public/time", None, None)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, DriverError::Api { .. }), "{error}");
+        }
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            crate::events::DriverEvent::CircuitBreakerStateChanged {
+                to: crate::circuit_breaker::CircuitState::Open,
+                ..
+            }
+        ));
+
+        // The sixth call fails fast: the queued healthy response is never
+        // fetched, and other categories keep flowing.
+        let error = client
+            .call::<serde_json::Value>(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DriverError::ExchangeUnavailable(_)), "{error}");
+        assert_eq!(transport.requests().len(), 5);
+        let data: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+        assert_eq!(data[0]["ts"], "1700000000000");
+    }
+
+    #[tokio::test]
+    async fn metrics_hook_records_serving_endpoint_and_status() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(crate::transport::HttpResponse {
+            status: 502,
+            headers: vec![],
+            body: "bad gateway".to_string(),
+        });
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let mut client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+        let hook = Arc::new(RecordingHook::default());
+        client.set_metrics_hook(hook.clone() as Arc<dyn MetricsHook>);
+
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+
+        let seen = hook.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].status, Some(502));
+        assert_eq!(seen[1].status, Some(200));
+        assert!(seen.iter().all(|m| m.endpoint == "http://primary"));
+        assert!(seen.iter().all(|m| m.path == "/api/v5/public/time"));
+    }
+
+    #[tokio::test]
+    async fn a_401_latches_credentials_invalid_and_fails_fast() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(crate::transport::HttpResponse {
+            status: 401,
+            headers: vec![],
+            body: r#"{"code":"50113","msg":"Invalid Sign","data":[]}"#.to_string(),
+        });
+        transport.push_json(TIME_RESPONSE);
+        let mut client = OkexClient::with_transport(
+            config_with_urls(vec!["http://primary".to_string()]),
+            transport.clone() as Arc<dyn HttpTransport>,
+        );
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        client.set_event_sender(events_tx);
+
+        let err = client
+            .call::<serde_json::Value>(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Unauthorized(_)), "got: {err}");
+        assert_eq!(transport.requests().len(), 1, "a 401 must not be retried");
+        assert!(client.credentials_invalid());
+        assert!(matches!(
+            events_rx.try_recv(),
+            Ok(crate::events::DriverEvent::CredentialsInvalid { .. })
+        ));
+
+        // Subsequent private calls fail fast without touching the wire.
+        let err = client
+            .call::<serde_json::Value>(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Unauthorized(_)), "got: {err}");
+        assert_eq!(transport.requests().len(), 1);
+
+        // A successful rotation lifts the latch and traffic resumes.
+        client.swap_credentials(OkexCredentials {
+            api_key: "new-key".to_string(),
+            api_secret: "new-secret".to_string(),
+            passphrase: "new-pass".to_string(),
+        });
+        assert!(!client.credentials_invalid());
+        let data: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/public/time", None, None)
+            .await
+            .unwrap();
+        assert_eq!(data[0]["ts"], "1700000000000");
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_captured_and_fed_to_limiter() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(crate::transport::HttpResponse {
+            status: 200,
+            headers: vec![
+                ("ratelimit-limit".to_string(), "60".to_string()),
+                ("ratelimit-remaining".to_string(), "2".to_string()),
+                ("ratelimit-reset".to_string(), "1500".to_string()),
+            ],
+            body: TIME_RESPONSE.to_string(),
+        });
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+
+        let state = client.rate_limit_state();
+        let account = state.get("account").expect("account category recorded");
+        assert_eq!(account.limit, 60);
+        assert_eq!(account.remaining, 2);
+        assert_eq!(account.reset_ms, Some(1500));
+        // The limiter budget shrank to the reported remaining.
+        assert_eq!(client.rate_limiter().available("account"), Some(2));
+    }
+
+    #[tokio::test]
+    async fn missing_rate_limit_headers_are_harmless() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport as Arc<dyn HttpTransport>);
+
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/market/ticker", None, None)
+            .await
+            .unwrap();
+        assert!(client.rate_limit_state().is_empty());
+    }
+
+    /// A bills page where the second element has an unparseable `balChg`.
+    const CORRUPT_BILLS_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"billId":"b1","ccy":"USDT","balChg":"1","type":"2","ts":"1700000000000"},
+        {"billId":"b2","ccy":"USDT","balChg":"not-a-number","type":"2","ts":"1700000000001"},
+        {"billId":"b3","ccy":"USDT","balChg":"3","type":"2","ts":"1700000000002"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn lenient_mode_skips_corrupt_elements_and_counts_them() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(CORRUPT_BILLS_PAGE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let mut client = OkexClient::with_transport(config, transport as Arc<dyn HttpTransport>);
+        let hook = Arc::new(RecordingHook::default());
+        client.set_metrics_hook(hook.clone() as Arc<dyn MetricsHook>);
+
+        let bills = client.rest_fetch_account_bills(None).await.unwrap();
+
+        assert_eq!(bills.len(), 2);
+        assert_eq!(bills[0].bill_id, "b1");
+        assert_eq!(bills[1].bill_id, "b3");
+        assert_eq!(
+            hook.skipped.lock().unwrap().as_slice(),
+            &[("/api/v5/account/bills".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn strict_mode_fails_listing_corrupt_elements() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(CORRUPT_BILLS_PAGE);
+        let config = OkexConfig {
+            strict_parsing: true,
+            ..config_with_urls(vec!["http://primary".to_string()])
+        };
+        let client = OkexClient::with_transport(config, transport as Arc<dyn HttpTransport>);
+
+        let err = client.rest_fetch_account_bills(None).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("1 unparseable"), "{message}");
+        assert!(message.contains("b2"), "offending element listed: {message}");
+    }
+
+    #[tokio::test]
+    async fn an_interrupted_bills_fetch_resumes_from_the_last_complete_page() {
+        fn bills_page(start: usize, count: usize) -> String {
+            let bills: Vec<String> = (start..start + count)
+                .map(|i| {
+                    format!(
+                        r#"{{"billId":"b{i}","ccy":"USDT","balChg":"1","type":"2","ts":"1700000000000"}}"#
+                    )
+                })
+                .collect();
+            format!(r#"{{"code":"0","msg":"","data":[{}]}}"#, bills.join(","))
+        }
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&bills_page(0, 100));
+        // One base URL at two attempts: both must fail.
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let interrupted = client
+            .rest_fetch_account_bills_resumable(None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(interrupted.partial.len(), 100);
+        assert_eq!(interrupted.cursor.after.as_deref(), Some("b99"));
+
+        transport.push_json(&bills_page(100, 50));
+        let resumed = client
+            .rest_fetch_account_bills_resumable(None, Some(interrupted.cursor))
+            .await
+            .unwrap();
+
+        assert_eq!(resumed.len(), 50);
+        assert_eq!(interrupted.partial.len() + resumed.len(), 150);
+        let resume_url = transport.requests().last().unwrap().url.clone();
+        assert!(resume_url.contains("after=b99"), "{resume_url}");
+    }
+
+    #[tokio::test]
+    async fn signature_failure_records_structured_details() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(crate::transport::HttpResponse {
+            status: 401,
+            headers: vec![],
+            body: r#"{"code":"50113","msg":"Invalid Sign"}"#.to_string(),
+        });
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport as Arc<dyn HttpTransport>);
+
+        let err = client
+            .call::<serde_json::Value>(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("endpoint=/api/v5/account/balance"),
+            "{err}"
+        );
+
+        let details = client.last_error_details();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].endpoint, "/api/v5/account/balance");
+        assert!(details[0].message.contains("status 401"), "{}", details[0].message);
+        assert!(details[0].message.contains("Invalid Sign"), "{}", details[0].message);
+    }
+
+    #[test]
+    fn endpoint_category_extraction() {
+        assert_eq!(endpoint_category("/api/v5/trade/cancel-order"), "trade");
+        assert_eq!(endpoint_category("/api/v5/account/balance"), "account");
+        assert_eq!(endpoint_category("/api/v5/market/ticker"), "market");
+    }
+
+    #[cfg(feature = "raw-api")]
+    #[tokio::test]
+    async fn signed_request_get_serializes_query_params() {
+        #[derive(serde::Serialize)]
+        struct Params {
+            #[serde(rename = "instId")]
+            inst_id: String,
+            limit: u32,
+        }
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let envelope: crate::api_structs::OkexRestResponse<serde_json::Value> = client
+            .signed_request(
+                Method::Get,
+                "/api/v5/some/new-endpoint",
+                Some(&Params {
+                    inst_id: "BTC-USDT".to_string(),
+                    limit: 5,
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(envelope.code, "0");
+
+        let request = &transport.requests()[0];
+        assert_eq!(
+            request.url,
+            "http://primary/api/v5/some/new-endpoint?instId=BTC-USDT&limit=5"
+        );
+        assert!(request.headers.iter().any(|(n, _)| n == "OK-ACCESS-SIGN"));
+    }
+
+    #[cfg(feature = "raw-api")]
+    #[tokio::test]
+    async fn signed_request_post_serializes_json_body() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let params = serde_json::json!({ "ccy": "USDT" });
+        let _: crate::api_structs::OkexRestResponse<serde_json::Value> = client
+            .signed_request(Method::Post, "/api/v5/some/new-endpoint", Some(&params))
+            .await
+            .unwrap();
+
+        let request = &transport.requests()[0];
+        assert_eq!(request.body.as_deref(), Some(r#"{"ccy":"USDT"}"#));
+    }
+
+    #[cfg(feature = "raw-api")]
+    #[tokio::test]
+    async fn signed_request_rejects_absolute_and_foreign_paths() {
+        let transport = Arc::new(MockTransport::new());
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        for path in ["https://evil.example/api/v5/x", "/api/v4/old", "api/v5/x"] {
+            let err = client
+                .signed_request::<(), serde_json::Value>(Method::Get, path, None)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, DriverError::Config(_)), "path {path}: {err}");
+        }
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn replay_can_force_the_demo_header_on_a_live_client() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let envelope = client
+            .replay_request(
+                Method::Get,
+                "/api/v5/public/time",
+                None,
+                None,
+                SimulatedTradingMode::ForceDemo,
+            )
+            .await
+            .unwrap();
+        assert_eq!(envelope.code, "0");
+
+        let request = &transport.requests()[0];
+        assert!(request
+            .headers
+            .iter()
+            .any(|(n, v)| n == "x-simulated-trading" && v == "1"));
+    }
+
+    #[tokio::test]
+    async fn replay_can_suppress_the_demo_header_on_a_testnet_client() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        let config = OkexConfig {
+            use_testnet: true,
+            ..config_with_urls(vec!["http://primary".to_string()])
+        };
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let _ = client
+            .replay_request(
+                Method::Get,
+                "/api/v5/public/time",
+                None,
+                None,
+                SimulatedTradingMode::ForceLive,
+            )
+            .await
+            .unwrap();
+
+        let request = &transport.requests()[0];
+        assert!(!request.headers.iter().any(|(n, _)| n == "x-simulated-trading"));
+    }
+
+    #[tokio::test]
+    async fn a_live_client_refuses_demo_overrides_on_trade_endpoints() {
+        let transport = Arc::new(MockTransport::new());
+        let config = config_with_urls(vec!["http://primary".to_string()]);
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let err = client
+            .replay_request(
+                Method::Post,
+                "/api/v5/trade/order",
+                None,
+                Some(r#"{"instId":"BTC-USDT"}"#.to_string()),
+                SimulatedTradingMode::ForceDemo,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Config(_)), "{err}");
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn exp_time_is_the_injected_clock_plus_the_validity_window() {
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        // Default 500ms window, placements and amendments only.
+        assert_eq!(
+            client.order_exp_time_at("/api/v5/trade/order", 1_700_000_000_000),
+            Some("1700000000500".to_string())
+        );
+        assert_eq!(
+            client.order_exp_time_at("/api/v5/trade/amend-order", 1_700_000_000_000),
+            Some("1700000000500".to_string())
+        );
+        // A late cancel is still worth executing: no deadline.
+        assert_eq!(
+            client.order_exp_time_at("/api/v5/trade/cancel-order", 1_700_000_000_000),
+            None
+        );
+
+        let disabled = OkexClient::with_transport(
+            OkexConfig {
+                order_request_validity: None,
+                ..OkexConfig::default()
+            },
+            Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>,
+        );
+        assert_eq!(
+            disabled.order_exp_time_at("/api/v5/trade/order", 1_700_000_000_000),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn order_placements_carry_the_exp_time_header() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = OkexClient::with_transport(
+            config_with_urls(vec!["http://primary".to_string()]),
+            transport.clone() as Arc<dyn HttpTransport>,
+        );
+
+        let before = chrono::Utc::now().timestamp_millis();
+        let _: Vec<serde_json::Value> = client
+            .call(
+                Method::Post,
+                "/api/v5/trade/order",
+                None,
+                Some(r#"{"instId":"BTC-USDT"}"#.to_string()),
+            )
+            .await
+            .unwrap();
+
+        let request = &transport.requests()[0];
+        let deadline: i64 = request
+            .headers
+            .iter()
+            .find(|(name, _)| name == "expTime")
+            .expect("placement carries expTime")
+            .1
+            .parse()
+            .unwrap();
+        assert!(deadline >= before + 500, "deadline {deadline} vs now {before}");
+    }
+
+    #[tokio::test]
+    async fn an_exp_time_rejection_maps_to_the_retriable_error() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&format!(
+            r#"{{"code":"{REQUEST_EXPIRED_CODE}","msg":"request arrived after expTime","data":[]}}"#
+        ));
+        let client = OkexClient::with_transport(
+            config_with_urls(vec!["http://primary".to_string()]),
+            transport.clone() as Arc<dyn HttpTransport>,
+        );
+
+        let err = client
+            .call::<serde_json::Value>(
+                Method::Post,
+                "/api/v5/trade/order",
+                None,
+                Some(r#"{"instId":"BTC-USDT"}"#.to_string()),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, DriverError::RequestExpired(_)), "{err}");
+        assert!(err.to_string().contains("endpoint=/api/v5/trade/order"), "{err}");
+    }
+
+    #[test]
+    fn signature_covers_plaintext_post_body() {
+        use base64::Engine;
+        use hmac::Mac;
+
+        let config = OkexConfig {
+            api_secret: "topsecret".to_string(),
+            ..OkexConfig::default()
+        };
+        let client =
+            OkexClient::with_transport(config, Arc::new(MockTransport::new()) as Arc<dyn HttpTransport>);
+
+        let body = r#"{"ccy":"USDT","side":"repay","amt":"12.5"}"#;
+        let signature = client.current_credentials().sign(&format!(
+            "2023-11-14T00:00:00.000ZPOST/api/v5/account/borrow-repay{body}"
+        ));
+
+        // Independently computed HMAC over the uncompressed plaintext body;
+        // compression must never change what gets signed.
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(b"topsecret").unwrap();
+        mac.update(
+            format!("2023-11-14T00:00:00.000ZPOST/api/v5/account/borrow-repay{body}").as_bytes(),
+        );
+        let expected = base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+        assert_eq!(signature, expected);
+    }
+
+    #[tokio::test]
+    async fn full_wire_logging_redacts_credential_headers() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        transport.push_json(TIME_RESPONSE);
+        let config = OkexConfig {
+            api_key: "key-123".to_string(),
+            api_secret: "topsecret".to_string(),
+            passphrase: "hunter2".to_string(),
+            ..config_with_urls(vec!["http://primary".to_string()])
+        };
+        let client = OkexClient::with_transport(config, transport as Arc<dyn HttpTransport>);
+        let lines = Arc::new(StdMutex::new(Vec::new()));
+        let sink_lines = Arc::clone(&lines);
+        client.wire_logger().set_sink(Arc::new(move |line: &str| {
+            sink_lines.lock().unwrap().push(line.to_string());
+        }));
+
+        client.set_wire_logging(crate::wire_log::WireLogLevel::Full);
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+
+        {
+            let lines = lines.lock().unwrap();
+            assert_eq!(lines.len(), 2, "one request line, one response line: {lines:?}");
+            assert!(lines[0].contains("OK-ACCESS-SIGN=[redacted]"), "{}", lines[0]);
+            assert!(
+                lines[0].contains("OK-ACCESS-PASSPHRASE=[redacted]"),
+                "{}",
+                lines[0]
+            );
+            assert!(!lines[0].contains("hunter2"), "{}", lines[0]);
+            assert!(lines[1].starts_with("<- 200"), "{}", lines[1]);
+        }
+
+        // Back to Off: the next call leaves no trace.
+        client.set_wire_logging(crate::wire_log::WireLogLevel::Off);
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+        assert_eq!(lines.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn swapped_credentials_sign_subsequent_requests() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(TIME_RESPONSE);
+        transport.push_json(TIME_RESPONSE);
+        let config = OkexConfig {
+            api_key: "old-key".to_string(),
+            ..config_with_urls(vec!["http://primary".to_string()])
+        };
+        let client = OkexClient::with_transport(config, transport.clone() as Arc<dyn HttpTransport>);
+
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+        client.swap_credentials(OkexCredentials {
+            api_key: "new-key".to_string(),
+            api_secret: "new-secret".to_string(),
+            passphrase: "new-pass".to_string(),
+        });
+        let _: Vec<serde_json::Value> = client
+            .call(Method::Get, "/api/v5/account/balance", None, None)
+            .await
+            .unwrap();
+
+        let requests = transport.requests();
+        let key_of = |i: usize| {
+            requests[i]
+                .headers
+                .iter()
+                .find(|(name, _)| name == "OK-ACCESS-KEY")
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(key_of(0), "old-key");
+        assert_eq!(key_of(1), "new-key");
+    }
+}
@@ -0,0 +1,247 @@
+//! Cursor pagination shared by the fetch endpoints.
+//!
+//! Every paged OKX endpoint walks the same shape — `limit=N` pages with an
+//! `after` cursor taken from the last element — and each hand-rolled copy
+//! of that loop grew its own edge cases: one kept going on a cursor that
+//! stopped advancing, another treated an absent per-element cursor as
+//! "request the first page again". The walk lives here once:
+//! [`OkexClient::stream_paginated`] yields elements as their pages arrive
+//! (each page passing through the shared rate limiter like any other
+//! call), stopping on a short page, a cursor that cannot advance, or the
+//! page cap. [`OkexClient::fetch_paginated`] collects the stream for the
+//! callers that want the whole result; consumers that aggregate or cut
+//! early (fee summaries, range fetches) drive the stream directly and
+//! drop it when done.
+
+use std::collections::VecDeque;
+
+use futures_lite::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::errors::{DriverError, DriverResult};
+use crate::transport::Method;
+
+use super::OkexClient;
+
+/// Hard cap on pages per walk. At 100 elements a page this is a million
+/// records — far past any legitimate response — so hitting it means a
+/// cursor loop the advancement checks did not catch; the walk fails
+/// instead of hammering the endpoint forever.
+const MAX_PAGES: usize = 10_000;
+
+/// One cursor-paginated walk, before the `limit` and `after` parameters
+/// are appended.
+#[derive(Debug, Clone)]
+pub struct PageWalk {
+    pub path: &'static str,
+    /// Query without `limit`/`after`, e.g. `instType=SPOT`; empty for
+    /// none.
+    pub base_query: String,
+    /// Page size; the walk stops on the first page shorter than this.
+    pub limit: usize,
+    /// Resume cursor: start after this value instead of the newest record.
+    pub after: Option<String>,
+}
+
+impl OkexClient {
+    /// Walk a cursor-paginated endpoint, yielding elements as their pages
+    /// arrive. `cursor_of` names the element field the endpoint pages on
+    /// (`billId`, `ordId`, ...); a `None` cursor on the last element ends
+    /// the walk, as does a cursor that stopped advancing. An element-level
+    /// `Err` ends the stream after it is yielded.
+    pub fn stream_paginated<'a, T, C>(
+        &'a self,
+        walk: PageWalk,
+        cursor_of: C,
+    ) -> impl Stream<Item = DriverResult<T>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+        C: Fn(&T) -> Option<String> + 'a,
+    {
+        let seed = (
+            self,
+            walk,
+            cursor_of,
+            VecDeque::<T>::new(),
+            0usize,
+            false,
+        );
+        stream::unfold(
+            seed,
+            |(client, mut walk, cursor_of, mut buffer, mut pages, mut done)| async move {
+                loop {
+                    if let Some(element) = buffer.pop_front() {
+                        return Some((
+                            Ok(element),
+                            (client, walk, cursor_of, buffer, pages, done),
+                        ));
+                    }
+                    if done {
+                        return None;
+                    }
+                    if pages >= MAX_PAGES {
+                        return Some((
+                            Err(DriverError::Generic(format!(
+                                "pagination of {} exceeded {MAX_PAGES} pages",
+                                walk.path
+                            ))),
+                            (client, walk, cursor_of, buffer, pages, true),
+                        ));
+                    }
+                    let mut query = if walk.base_query.is_empty() {
+                        format!("limit={}", walk.limit)
+                    } else {
+                        format!("{}&limit={}", walk.base_query, walk.limit)
+                    };
+                    if let Some(cursor) = &walk.after {
+                        query.push_str(&format!("&after={cursor}"));
+                    }
+                    let page: Vec<T> = match client
+                        .call_elements(Method::Get, walk.path, Some(&query), None)
+                        .await
+                    {
+                        Ok(page) => page,
+                        Err(error) => {
+                            return Some((
+                                Err(error),
+                                (client, walk, cursor_of, buffer, pages, true),
+                            ));
+                        }
+                    };
+                    pages += 1;
+                    let next = page.last().and_then(&cursor_of);
+                    // A short page is the end; so is a cursor that cannot
+                    // advance the walk — requesting the same page again
+                    // would spin forever.
+                    if page.len() < walk.limit || next.is_none() || next == walk.after {
+                        done = true;
+                    }
+                    walk.after = next;
+                    buffer.extend(page);
+                }
+            },
+        )
+    }
+
+    /// [`Self::stream_paginated`] collected into a `Vec`, failing on the
+    /// first element-level error.
+    pub(crate) async fn fetch_paginated<T, C>(
+        &self,
+        walk: PageWalk,
+        cursor_of: C,
+    ) -> DriverResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+        C: Fn(&T) -> Option<String>,
+    {
+        let stream = self.stream_paginated(walk, cursor_of);
+        futures_lite::pin!(stream);
+        let mut elements = Vec::new();
+        while let Some(element) = stream.next().await {
+            elements.push(element?);
+        }
+        Ok(elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    #[derive(Debug, Deserialize)]
+    struct Row {
+        id: String,
+    }
+
+    fn client(transport: &Arc<MockTransport>) -> OkexClient {
+        OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        )
+    }
+
+    fn walk(base_query: &str) -> PageWalk {
+        PageWalk {
+            path: "/api/v5/mock/rows",
+            base_query: base_query.to_string(),
+            limit: 2,
+            after: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn elements_stream_across_pages_on_the_cursor() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"a"},{"id":"b"}]}"#);
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"c"}]}"#);
+        let client = client(&transport);
+
+        let rows: Vec<Row> = client
+            .fetch_paginated(walk("instType=SPOT"), |row: &Row| Some(row.id.clone()))
+            .await
+            .unwrap();
+        let ids: Vec<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+
+        let requests = transport.requests();
+        assert!(requests[0].url.ends_with("?instType=SPOT&limit=2"), "{}", requests[0].url);
+        assert!(requests[1].url.ends_with("?instType=SPOT&limit=2&after=b"), "{}", requests[1].url);
+    }
+
+    #[tokio::test]
+    async fn a_cursor_that_stops_advancing_ends_the_walk() {
+        let transport = Arc::new(MockTransport::new());
+        // The same full page twice: a naive loop would request it forever.
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"a"},{"id":"b"}]}"#);
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"a"},{"id":"b"}]}"#);
+        let client = client(&transport);
+
+        let rows: Vec<Row> = client
+            .fetch_paginated(walk(""), |row: &Row| Some(row.id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 4, "the repeated page itself is delivered");
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_full_page_without_a_cursor_ends_the_walk() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"a"},{"id":"b"}]}"#);
+        let client = client(&transport);
+
+        let rows: Vec<Row> = client
+            .fetch_paginated(walk(""), |_: &Row| None)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_failed_page_surfaces_after_the_delivered_elements() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"id":"a"},{"id":"b"}]}"#);
+        for _ in 0..4 {
+            transport.push_error(DriverError::Throttled("rate limit".to_string()));
+        }
+        let client = client(&transport);
+
+        let stream = client.stream_paginated(walk(""), |row: &Row| Some(row.id.clone()));
+        futures_lite::pin!(stream);
+        assert_eq!(stream.next().await.unwrap().unwrap().id, "a");
+        assert_eq!(stream.next().await.unwrap().unwrap().id, "b");
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            Err(DriverError::Throttled(_))
+        ));
+        assert!(stream.next().await.is_none(), "an error ends the stream");
+    }
+}
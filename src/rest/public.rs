@@ -0,0 +1,481 @@
+//! Public (unauthenticated) REST endpoints.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::{
+    OkexEstimatedPrice, OkexFundingRate, OkexInstrumentInfo, OkexPositionTier, OkexSystemStatus,
+};
+use crate::errors::{DriverError, DriverResult};
+use crate::instruments::{Instrument, InstrumentCache, InstrumentConverter};
+use crate::transport::Method;
+use crate::ws::supervisor::MaintenanceWait;
+
+use super::OkexClient;
+
+/// Margin tier ladders change rarely; refetch at most daily.
+const POSITION_TIER_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Cached `/api/v5/public/position-tiers` rows for one instrument.
+pub(crate) struct PositionTierCache {
+    fetched_at: std::time::Instant,
+    tiers: Vec<OkexPositionTier>,
+}
+
+impl OkexClient {
+    /// Fetch `/api/v5/public/instruments` for one `instType`
+    /// (`SPOT`, `SWAP`, `FUTURES`, `OPTION`, `MARGIN`).
+    pub async fn rest_fetch_instruments(
+        &self,
+        inst_type: &str,
+    ) -> DriverResult<Vec<OkexInstrumentInfo>> {
+        let query = format!("instType={inst_type}");
+        self.call(
+            Method::Get,
+            "/api/v5/public/instruments",
+            Some(&query),
+            None,
+        )
+        .await
+    }
+
+    /// Fetch `/api/v5/public/estimated-price` for one dated contract; the
+    /// exchange only publishes it within an hour of delivery/settlement.
+    pub async fn rest_fetch_estimated_price(
+        &self,
+        inst_id: &str,
+    ) -> DriverResult<OkexEstimatedPrice> {
+        let query = format!("instId={inst_id}");
+        let mut data: Vec<OkexEstimatedPrice> = self
+            .call(
+                Method::Get,
+                "/api/v5/public/estimated-price",
+                Some(&query),
+                None,
+            )
+            .await?;
+        data.pop().ok_or_else(|| {
+            DriverError::Generic(format!("no estimated price for {inst_id}"))
+        })
+    }
+
+    /// Fetch `/api/v5/public/funding-rate` for one perpetual; the
+    /// `fundingTime` it reports drives real-time funding payment detection
+    /// (see [`crate::funding::FundingEstimator`]).
+    pub async fn rest_fetch_funding_rate(&self, inst_id: &str) -> DriverResult<OkexFundingRate> {
+        let query = format!("instId={inst_id}");
+        let mut data: Vec<OkexFundingRate> = self
+            .call(
+                Method::Get,
+                "/api/v5/public/funding-rate",
+                Some(&query),
+                None,
+            )
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic(format!("no funding rate for {inst_id}")))
+    }
+
+    /// Fetch `/api/v5/public/position-tiers` for one instrument.
+    /// `td_mode` is `cross` or `isolated`; tiers are sized in contracts
+    /// for derivatives and in quote amount for margin pairs. Uncached;
+    /// most callers want [`Self::position_tiers`].
+    pub async fn rest_fetch_position_tiers(
+        &self,
+        inst_type: &str,
+        inst_id: &str,
+        td_mode: &str,
+    ) -> DriverResult<Vec<OkexPositionTier>> {
+        let query = format!("instType={inst_type}&tdMode={td_mode}&instId={inst_id}");
+        self.call(
+            Method::Get,
+            "/api/v5/public/position-tiers",
+            Some(&query),
+            None,
+        )
+        .await
+    }
+
+    /// The maintenance margin tier ladder for one instrument, refetched at
+    /// most daily. The margin mode follows the configured trade mode;
+    /// `cash` accounts query `cross`, since spot itself has no tiers and
+    /// the ladder only matters for margin-capable pairs.
+    pub async fn position_tiers(
+        &self,
+        instrument: &Instrument,
+    ) -> DriverResult<Vec<OkexPositionTier>> {
+        {
+            let cache = self.position_tier_cache.lock().unwrap();
+            if let Some(entry) = cache.get(&instrument.inst_id) {
+                if entry.fetched_at.elapsed() < POSITION_TIER_TTL {
+                    return Ok(entry.tiers.clone());
+                }
+            }
+        }
+        let td_mode = match self.config().trade_mode {
+            crate::orders::TradeMode::Isolated => "isolated",
+            _ => "cross",
+        };
+        let tiers = self
+            .rest_fetch_position_tiers(instrument.inst_type(), &instrument.inst_id, td_mode)
+            .await?;
+        self.position_tier_cache.lock().unwrap().insert(
+            instrument.inst_id.clone(),
+            PositionTierCache {
+                fetched_at: std::time::Instant::now(),
+                tiers: tiers.clone(),
+            },
+        );
+        Ok(tiers)
+    }
+
+    /// The largest position the tier ladder allows at `leverage`, as a
+    /// base amount for contracts (tier caps are in contracts and are
+    /// multiplied out by the contract value) and as the exchange reports
+    /// it otherwise. `None` when no tier supports that leverage — sizing
+    /// against it should treat the position as unplaceable, not unlimited.
+    pub async fn max_position_at_leverage(
+        &self,
+        instrument: &Instrument,
+        leverage: Decimal,
+    ) -> DriverResult<Option<Decimal>> {
+        let tiers = self.position_tiers(instrument).await?;
+        let cap = tiers
+            .iter()
+            .filter(|tier| tier.max_leverage.is_some_and(|max| max >= leverage))
+            .filter_map(|tier| tier.max_size)
+            .max();
+        Ok(cap.map(|size| match instrument.contract_value {
+            Some(contract_value) => size * contract_value,
+            None => size,
+        }))
+    }
+
+    /// Fetch `/api/v5/system/status`, optionally filtered by `state`
+    /// (`scheduled`, `ongoing`, `pre_open`, `completed`, `canceled`).
+    pub async fn rest_fetch_system_status(
+        &self,
+        state: Option<&str>,
+    ) -> DriverResult<Vec<OkexSystemStatus>> {
+        let query = state.map(|state| format!("state={state}"));
+        self.call(
+            Method::Get,
+            "/api/v5/system/status",
+            query.as_deref(),
+            None,
+        )
+        .await
+    }
+
+    /// Build the instrument converter for startup, cache-aware.
+    ///
+    /// The normal path fetches each `instType` live and, when
+    /// `instrument_cache_path` is configured, rewrites the cache file
+    /// afterwards (a write failure is logged, not fatal). Two situations
+    /// fall back to the file instead: the live fetch failing, and
+    /// `fast_start` being set — in which case the fetch is skipped entirely
+    /// and the next instrument refresh picks up live data. A cache older
+    /// than `instrument_cache_max_age` still serves read paths but is
+    /// flagged so [`crate::driver::OkexDriver::open_orders`] refuses it,
+    /// unless `allow_stale_instrument_orders` is set.
+    pub async fn load_instruments(
+        &self,
+        inst_types: &[&str],
+    ) -> DriverResult<InstrumentConverter> {
+        let config = self.config();
+        let cache_path = config.instrument_cache_path.clone();
+
+        if config.fast_start {
+            if let Some(path) = &cache_path {
+                match InstrumentCache::load(path) {
+                    Ok(cache) => return Ok(self.converter_from_cache(cache)),
+                    Err(error) => log::warn!(
+                        "fast_start set but instrument cache unusable, fetching live: {error}"
+                    ),
+                }
+            }
+        }
+
+        match self.fetch_instruments_live(inst_types).await {
+            Ok(converter) => {
+                if let Some(path) = &cache_path {
+                    let cache = InstrumentCache::from_converter(&converter, chrono::Utc::now());
+                    if let Err(error) = cache.save(path) {
+                        log::warn!("could not persist instrument cache: {error}");
+                    }
+                }
+                Ok(converter)
+            }
+            Err(fetch_error) => {
+                let Some(path) = &cache_path else {
+                    return Err(fetch_error);
+                };
+                let cache = InstrumentCache::load(path).map_err(|cache_error| {
+                    DriverError::Generic(format!(
+                        "instrument fetch failed ({fetch_error}) and the cache fallback \
+                         failed too ({cache_error})"
+                    ))
+                })?;
+                log::warn!(
+                    "instrument fetch failed, starting from cached instruments: {fetch_error}"
+                );
+                Ok(self.converter_from_cache(cache))
+            }
+        }
+    }
+
+    async fn fetch_instruments_live(
+        &self,
+        inst_types: &[&str],
+    ) -> DriverResult<InstrumentConverter> {
+        let mut converter = InstrumentConverter::new();
+        for inst_type in inst_types {
+            for info in self.rest_fetch_instruments(inst_type).await? {
+                converter.insert(Instrument::from_info(&info));
+            }
+        }
+        Ok(converter)
+    }
+
+    fn converter_from_cache(&self, cache: InstrumentCache) -> InstrumentConverter {
+        let config = self.config();
+        cache.into_converter(
+            config.instrument_cache_max_age,
+            config.allow_stale_instrument_orders,
+            chrono::Utc::now(),
+        )
+    }
+}
+
+/// Polls the system status until no maintenance is ongoing. Plugged into
+/// the WS supervisor as its [`MaintenanceWait`], so a maintenance close
+/// holds reconnects until the exchange reports the window over instead of
+/// hammering a dead endpoint.
+pub struct SystemStatusPoller {
+    client: Arc<OkexClient>,
+    interval: Duration,
+}
+
+impl SystemStatusPoller {
+    pub fn new(client: Arc<OkexClient>, interval: Duration) -> Self {
+        Self { client, interval }
+    }
+}
+
+#[async_trait]
+impl MaintenanceWait for SystemStatusPoller {
+    async fn wait_for_end(&self) {
+        loop {
+            match self.client.rest_fetch_system_status(Some("ongoing")).await {
+                Ok(ongoing) if ongoing.is_empty() => return,
+                Ok(ongoing) => {
+                    let titles: Vec<&str> =
+                        ongoing.iter().map(|event| event.title.as_str()).collect();
+                    log::info!("exchange maintenance ongoing: {}", titles.join(", "));
+                }
+                // A status endpoint down mid-maintenance is expected; keep
+                // polling rather than releasing the supervisor early.
+                Err(error) => log::warn!("system status poll failed: {error}"),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    #[tokio::test]
+    async fn estimated_price_parses_the_settlement_fixture() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT-240329","instType":"FUTURES","settlePx":"43250.1","ts":"1700000000000"}]}"#,
+        );
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+
+        let estimate = client
+            .rest_fetch_estimated_price("BTC-USDT-240329")
+            .await
+            .unwrap();
+        assert_eq!(estimate.settlement_price, "43250.1".parse().unwrap());
+        assert!(transport.requests()[0]
+            .url
+            .ends_with("/api/v5/public/estimated-price?instId=BTC-USDT-240329"));
+    }
+
+    /// Three tiers of a linear swap ladder: caps in contracts, leverage
+    /// stepping down as the tiers grow.
+    const SWAP_TIERS_PAGE: &str = r#"{"code":"0","msg":"","data":[
+        {"instId":"BTC-USDT-SWAP","tier":"1","minSz":"0","maxSz":"500","mmr":"0.004","imr":"0.008","maxLever":"125"},
+        {"instId":"BTC-USDT-SWAP","tier":"2","minSz":"501","maxSz":"1000","mmr":"0.005","imr":"0.01","maxLever":"100"},
+        {"instId":"BTC-USDT-SWAP","tier":"3","minSz":"1001","maxSz":"2000","mmr":"0.01","imr":"0.02","maxLever":"50"}
+    ]}"#;
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            margin: false,
+            expiry_time: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn position_tiers_parse_the_swap_fixture_and_cache_daily() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(SWAP_TIERS_PAGE);
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+
+        let tiers = client.position_tiers(&swap_instrument()).await.unwrap();
+        assert_eq!(tiers.len(), 3);
+        assert_eq!(tiers[0].tier, Some(1));
+        assert_eq!(tiers[0].max_size, Some("500".parse().unwrap()));
+        assert_eq!(tiers[2].mmr, Some("0.01".parse().unwrap()));
+        assert_eq!(tiers[2].max_leverage, Some("50".parse().unwrap()));
+        assert!(transport.requests()[0].url.ends_with(
+            "/api/v5/public/position-tiers?instType=SWAP&tdMode=cross&instId=BTC-USDT-SWAP"
+        ));
+
+        // The second read comes from the daily cache.
+        client.position_tiers(&swap_instrument()).await.unwrap();
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn max_position_converts_tier_contract_caps_into_base_amounts() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(SWAP_TIERS_PAGE);
+        let client = OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let instrument = swap_instrument();
+
+        // 100x is available through tier 2: 1000 contracts of 0.01 BTC.
+        let at_100x = client
+            .max_position_at_leverage(&instrument, "100".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(at_100x, Some("10".parse().unwrap()));
+
+        // Lower leverage unlocks the deepest tier; past the ladder top
+        // there is no tier at all.
+        let at_10x = client
+            .max_position_at_leverage(&instrument, "10".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(at_10x, Some("20".parse().unwrap()));
+        let at_200x = client
+            .max_position_at_leverage(&instrument, "200".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(at_200x, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_status_poller_waits_out_an_ongoing_maintenance_window() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"title":"Spot system upgrade","state":"ongoing","begin":"1700000000000","end":"1700007200000"}]}"#,
+        );
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = Arc::new(OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        ));
+        let poller = SystemStatusPoller::new(client, Duration::from_secs(30));
+
+        poller.wait_for_end().await;
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2, "polled until the window cleared");
+        assert!(requests[0]
+            .url
+            .ends_with("/api/v5/system/status?state=ongoing"));
+    }
+
+    fn cached_client(fast_start: bool, path: std::path::PathBuf) -> (OkexClient, Arc<MockTransport>) {
+        let transport = Arc::new(MockTransport::new());
+        let config = OkexConfig {
+            instrument_cache_path: Some(path),
+            fast_start,
+            ..OkexConfig::default()
+        };
+        let client = OkexClient::with_transport(
+            config,
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        (client, transport)
+    }
+
+    fn write_cache(path: &std::path::Path) {
+        let mut converter = crate::instruments::InstrumentConverter::new();
+        converter.insert(crate::instruments::Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            margin: false,
+            expiry_time: None,
+        });
+        crate::instruments::InstrumentCache::from_converter(&converter, chrono::Utc::now())
+            .save(path)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_failed_instrument_fetch_falls_back_to_the_cache() {
+        let path = std::env::temp_dir().join(format!(
+            "okx-load-instruments-fallback-{}.json",
+            std::process::id()
+        ));
+        write_cache(&path);
+        let (client, transport) = cached_client(false, path.clone());
+        transport.push_json(r#"{"code":"51000","msg":"parameter error","data":[]}"#);
+
+        let converter = client.load_instruments(&["SPOT"]).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(converter.contains("BTC-USDT"));
+        assert!(converter.usable_for_orders());
+        assert!(transport.requests()[0]
+            .url
+            .contains("/api/v5/public/instruments"));
+    }
+
+    #[tokio::test]
+    async fn fast_start_serves_the_cache_without_touching_the_exchange() {
+        let path = std::env::temp_dir().join(format!(
+            "okx-load-instruments-fast-start-{}.json",
+            std::process::id()
+        ));
+        write_cache(&path);
+        let (client, transport) = cached_client(true, path.clone());
+
+        let converter = client.load_instruments(&["SPOT"]).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(converter.contains("BTC-USDT"));
+        assert!(transport.requests().is_empty());
+    }
+}
@@ -0,0 +1,2347 @@
+//! Trade-scoped REST endpoints.
+
+use futures_lite::StreamExt;
+use rust_decimal::Decimal;
+
+use crate::api_structs::{
+    OkexAlgoOrderDetails, OkexAlgoOrderResult, OkexAmendOrderRequest, OkexBillResponse,
+    OkexCancelAllAfterResult, OkexOrderOpResult, OkexPendingAlgoOrder, OkexPendingOrder,
+    TransactionResult,
+};
+use crate::errors::{DriverError, DriverResult};
+use crate::export::{ExportFormat, TradeExportCursor};
+use crate::instruments::{Instrument, InstrumentConverter};
+use crate::orders::{BatchItemError, BatchOutcome, RawOrder};
+use crate::precision::{serialize_price, serialize_size};
+use crate::resume::{FetchCursor, InterruptedFetch};
+use crate::trades::{HistoryGap, RawTrade, TradeHistory};
+use crate::transport::Method;
+
+use super::OkexClient;
+
+/// OKX caps amend/cancel batches at 20 entries.
+pub(crate) const BATCH_CHUNK_SIZE: usize = 20;
+/// OKX caps `cancel-algos` batches at 10 entries.
+const ALGO_BATCH_CHUNK_SIZE: usize = 10;
+
+/// Fold per-item results into a [`BatchOutcome`].
+pub(crate) fn collect_batch_outcome(results: Vec<OkexOrderOpResult>) -> BatchOutcome {
+    let mut outcome = BatchOutcome::default();
+    for result in results {
+        if result.s_code == "0" {
+            outcome.succeeded.push(result.order_id);
+        } else {
+            let message = truncate_s_msg(&result.s_msg);
+            outcome.failed.push(BatchItemError {
+                order_id: result.order_id,
+                client_order_id: result.client_order_id,
+                code: result.s_code,
+                message,
+            });
+        }
+    }
+    outcome
+}
+
+/// `sMsg` strings are short in practice, but the field is unbounded input
+/// from the wire; cap what gets copied into errors and the error log.
+pub(crate) const S_MSG_MAX_LEN: usize = 256;
+
+/// Truncate an `sMsg` to [`S_MSG_MAX_LEN`] characters, marking the cut so
+/// a clipped message is not mistaken for the full text.
+pub(crate) fn truncate_s_msg(s_msg: &str) -> String {
+    match s_msg.char_indices().nth(S_MSG_MAX_LEN) {
+        Some((cut, _)) => format!("{} [truncated]", &s_msg[..cut]),
+        None => s_msg.to_string(),
+    }
+}
+
+/// Human-facing description of a rejected per-item result: the verbatim
+/// (truncated) `sMsg`, prefixed with the `clOrdId` when the order carried
+/// one so callers can tie the rejection back to their own order. The
+/// `sCode` travels in the typed error, not here.
+pub(crate) fn rejection_message(result: &OkexOrderOpResult) -> String {
+    let s_msg = truncate_s_msg(&result.s_msg);
+    match result.client_order_id.as_deref() {
+        Some(cl_ord_id) if !cl_ord_id.is_empty() => format!("clOrdId={cl_ord_id}: {s_msg}"),
+        _ => s_msg,
+    }
+}
+
+/// Whether a batch envelope's top-level code still carries meaningful
+/// per-item results: `"0"` is full success, `"1"` every item rejected and
+/// `"2"` partial success — all three come with per-item `sCode`s worth
+/// partitioning. Any other code is a wholesale failure (auth, rate limit)
+/// where retrying the whole batch is the right move.
+pub(crate) fn batch_code_has_item_results(code: &str) -> bool {
+    matches!(code, "0" | "1" | "2")
+}
+
+/// `/api/v5/trade/fills` reaches back 3 days.
+const FILLS_WINDOW_MS: u64 = 3 * 24 * 60 * 60 * 1000;
+/// `/api/v5/trade/fills-history` reaches back 3 months.
+const FILLS_HISTORY_WINDOW_MS: u64 = 90 * 24 * 60 * 60 * 1000;
+/// `/api/v5/account/bills-archive` reaches back a year; older executions
+/// exist only in downloadable statements, outside the API.
+const BILLS_ARCHIVE_WINDOW_MS: u64 = 365 * 24 * 60 * 60 * 1000;
+/// How far past each endpoint boundary the older endpoint is fetched, so
+/// an execution timed right on the edge is never lost to clock skew; the
+/// overlap is deduped by trade id.
+const STITCH_OVERLAP_MS: u64 = 60_000;
+
+/// Clip `[from, to)` to a coverage window; `None` when nothing remains.
+fn clip_range(from: u64, to: u64, cover_from: u64, cover_to: u64) -> Option<(u64, u64)> {
+    let clipped_from = from.max(cover_from);
+    let clipped_to = to.min(cover_to);
+    (clipped_from < clipped_to).then_some((clipped_from, clipped_to))
+}
+
+/// Stable (timestamp, trade id) order shared by the trades fetchers and
+/// the resumable merge path.
+fn sort_trades(trades: &mut [RawTrade]) {
+    trades.sort_by(|a, b| {
+        let key = |t: &RawTrade| (t.timestamp.parse::<u64>().unwrap_or(0), t.trade_id.clone());
+        key(a).cmp(&key(b))
+    });
+}
+
+/// Cancel `sCode`s meaning "no such order" rather than a hard failure.
+pub(crate) fn cancel_code_means_not_found(s_code: &str) -> bool {
+    matches!(s_code, "51400" | "51401" | "51503")
+}
+
+/// Shared error-code mapping for cancel results: success passes through,
+/// "no such order" becomes [`DriverError::OrderNotFound`], anything else is
+/// an API error.
+pub(crate) fn map_cancel_outcome(result: OkexOrderOpResult) -> DriverResult<OkexOrderOpResult> {
+    if result.s_code == "0" {
+        Ok(result)
+    } else if cancel_code_means_not_found(&result.s_code) {
+        Err(DriverError::OrderNotFound(rejection_message(&result)))
+    } else {
+        Err(DriverError::Api {
+            message: rejection_message(&result),
+            code: result.s_code,
+        })
+    }
+}
+
+impl OkexClient {
+    /// Serialize order params through the per-instrument template cache:
+    /// the static fields are pre-rendered and only side, order type,
+    /// price, size and `clOrdId` are spliced per order. Output is
+    /// byte-identical to `serde_json::to_string`; see
+    /// [`crate::orders::OrderTemplate`].
+    fn serialize_order(&self, params: &crate::orders::OkexOrderParams) -> DriverResult<String> {
+        let mut templates = self.order_templates.lock().unwrap();
+        if !templates
+            .get(&params.inst_id)
+            .is_some_and(|template| template.matches(params))
+        {
+            templates.insert(
+                params.inst_id.clone(),
+                crate::orders::OrderTemplate::new(params),
+            );
+        }
+        let template = &templates[&params.inst_id];
+        let mut body = String::with_capacity(template.rendered_capacity());
+        template.render_into(params, &mut body)?;
+        Ok(body)
+    }
+
+    /// Place a single order via `/api/v5/trade/order`.
+    pub async fn rest_place_order(
+        &self,
+        params: &crate::orders::OkexOrderParams,
+    ) -> DriverResult<OkexOrderOpResult> {
+        let body = self.serialize_order(params)?;
+        let mut data: Vec<OkexOrderOpResult> = self
+            .call(Method::Post, "/api/v5/trade/order", None, Some(body))
+            .await?;
+        let result = data
+            .pop()
+            .ok_or_else(|| DriverError::Generic("empty place-order response".to_string()))?;
+        if result.s_code != "0" {
+            return Err(self.api_error("/api/v5/trade/order", result.s_code, result.s_msg));
+        }
+        Ok(result)
+    }
+
+    /// Cancel by exchange order id via `/api/v5/trade/cancel-order`.
+    pub async fn rest_cancel_order(
+        &self,
+        inst_id: &str,
+        order_id: &str,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.rest_cancel_order_by_ref(inst_id, &crate::orders::OrderRef::ExchangeId(order_id.to_string()))
+            .await
+    }
+
+    /// Cancel by client order id; the only option in the window between
+    /// submission and the placement ack.
+    pub async fn rest_cancel_order_by_client_id(
+        &self,
+        inst_id: &str,
+        client_order_id: &str,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.rest_cancel_order_by_ref(
+            inst_id,
+            &crate::orders::OrderRef::ClientId(client_order_id.to_string()),
+        )
+        .await
+    }
+
+    /// Cancel by either id kind; serializes `ordId` or `clOrdId` as
+    /// appropriate. Returns the raw per-item result — callers that want the
+    /// typed error mapping go through the driver's `cancel_order_by_id`.
+    pub async fn rest_cancel_order_by_ref(
+        &self,
+        inst_id: &str,
+        order_ref: &crate::orders::OrderRef,
+    ) -> DriverResult<OkexOrderOpResult> {
+        self.cancel_order_call(order_ref.to_args(inst_id).to_string())
+            .await
+    }
+
+    async fn cancel_order_call(&self, body: String) -> DriverResult<OkexOrderOpResult> {
+        let envelope = self
+            .call_envelope::<OkexOrderOpResult>(
+                Method::Post,
+                "/api/v5/trade/cancel-order",
+                None,
+                Some(body),
+            )
+            .await?;
+        envelope.data.into_iter().next().ok_or(DriverError::Api {
+            code: envelope.code,
+            message: envelope.msg,
+        })
+    }
+
+    /// Fetch all open orders with a post-fetch consistency pass.
+    ///
+    /// Pagination is not atomic on the exchange side: orders cancelled or
+    /// filled mid-fetch can show up twice or straddle page boundaries. The
+    /// result is deduped by `ordId`, restricted to instruments the converter
+    /// knows, and sorted by creation time so upstream diffing is stable.
+    /// With `verify_count` a second fetch double-checks the count and the
+    /// fresher result wins on mismatch.
+    pub async fn fetch_open_orders(
+        &self,
+        converter: &InstrumentConverter,
+        verify_count: bool,
+    ) -> DriverResult<Vec<OkexPendingOrder>> {
+        let orders = self.fetch_open_orders_once(converter).await?;
+        if verify_count {
+            let recheck = self.fetch_open_orders_once(converter).await?;
+            if recheck.len() != orders.len() {
+                log::debug!(
+                    "open-orders count changed mid-fetch ({} -> {}); using the fresher set",
+                    orders.len(),
+                    recheck.len()
+                );
+                return Ok(recheck);
+            }
+        }
+        Ok(orders)
+    }
+
+    async fn fetch_open_orders_once(
+        &self,
+        converter: &InstrumentConverter,
+    ) -> DriverResult<Vec<OkexPendingOrder>> {
+        const PAGE_LIMIT: usize = 100;
+
+        let raw: Vec<OkexPendingOrder> = self
+            .fetch_paginated(
+                crate::rest::PageWalk {
+                    path: "/api/v5/trade/orders-pending",
+                    base_query: String::new(),
+                    limit: PAGE_LIMIT,
+                    after: None,
+                },
+                |order: &OkexPendingOrder| Some(order.order_id.clone()),
+            )
+            .await?;
+
+        let mut seen = std::collections::HashSet::new();
+        let mut unknown: std::collections::BTreeMap<String, usize> =
+            std::collections::BTreeMap::new();
+        let mut orders: Vec<OkexPendingOrder> = raw
+            .into_iter()
+            .filter(|order| {
+                if !seen.insert(order.order_id.clone()) {
+                    return false;
+                }
+                if !converter.contains(&order.inst_id) {
+                    if self.config().surface_external_orders
+                        && Instrument::synthetic_from_inst_id(&order.inst_id).is_some()
+                    {
+                        log::debug!(
+                            "surfacing external order {} on unconfigured instrument {}",
+                            order.order_id,
+                            order.inst_id
+                        );
+                        return true;
+                    }
+                    *unknown.entry(order.inst_id.clone()).or_insert(0) += 1;
+                    return false;
+                }
+                true
+            })
+            .collect();
+        self.handle_unknown_instruments("fetch_open_orders", &unknown)?;
+        orders.sort_by_key(|order| order.created_at.parse::<u64>().unwrap_or(0));
+        Ok(orders)
+    }
+
+    /// Like [`Self::fetch_open_orders`] but normalized into [`RawOrder`]s,
+    /// stamping each record with the driver-receive time.
+    pub async fn fetch_open_raw_orders(
+        &self,
+        converter: &InstrumentConverter,
+        verify_count: bool,
+    ) -> DriverResult<Vec<RawOrder>> {
+        let orders = self.fetch_open_orders(converter, verify_count).await?;
+        let received_at = chrono::Utc::now();
+        Ok(orders
+            .iter()
+            .map(|order| RawOrder::from_pending(order, received_at))
+            .collect())
+    }
+
+    /// Fetch every fill of one order via `/api/v5/trade/fills`.
+    ///
+    /// Pages on the bill-id cursor; only iceberg-style orders with more than
+    /// 100 fills ever need a second page.
+    pub async fn rest_fetch_trades_for_order(
+        &self,
+        inst_id: &str,
+        order_id: &str,
+    ) -> DriverResult<Vec<TransactionResult>> {
+        const PAGE_LIMIT: usize = 100;
+
+        self.fetch_paginated(
+            crate::rest::PageWalk {
+                path: "/api/v5/trade/fills",
+                base_query: format!("instId={inst_id}&ordId={order_id}"),
+                limit: PAGE_LIMIT,
+                after: None,
+            },
+            |fill: &TransactionResult| fill.bill_id.clone(),
+        )
+        .await
+    }
+
+    /// Fetch the fills of one order, normalized into [`RawTrade`]s against
+    /// the instrument (contract sizes multiplied out, fee signs flipped).
+    pub async fn fetch_order_fills(
+        &self,
+        instrument: &Instrument,
+        order_id: &str,
+    ) -> DriverResult<Vec<RawTrade>> {
+        let fills = self
+            .rest_fetch_trades_for_order(&instrument.inst_id, order_id)
+            .await?;
+        let received_at = chrono::Utc::now();
+        Ok(fills
+            .iter()
+            .map(|fill| RawTrade::from_transaction(fill, instrument, received_at))
+            .collect())
+    }
+
+    /// Every fill on the configured instruments since `since`
+    /// (milliseconds; `None` for the exchange default window), normalized
+    /// into [`RawTrade`]s and sorted oldest first.
+    ///
+    /// OKX scopes fills queries by `instType`, so a mixed SPOT+SWAP
+    /// configuration fans out one paged request per instrument type the
+    /// converter holds and merges the results, deduped by trade id. A
+    /// `since` within the 3-day `/api/v5/trade/fills` window uses that
+    /// endpoint; older or open-ended ranges go to
+    /// `/api/v5/trade/fills-history`. The exchange returns the whole
+    /// account's fills for a type, so records on unconfigured instruments
+    /// are handled per the configured
+    /// [`UnknownInstrumentPolicy`](crate::config::UnknownInstrumentPolicy):
+    /// counted through
+    /// [`MetricsHook::on_unknown_instrument_records`](crate::rest::MetricsHook::on_unknown_instrument_records)
+    /// and logged, never dropped without trace.
+    pub async fn fetch_all_trades_since(
+        &self,
+        instruments: &InstrumentConverter,
+        since: Option<u64>,
+    ) -> DriverResult<Vec<RawTrade>> {
+        self.fetch_all_trades_resumable(instruments, since, None)
+            .await
+            .map_err(DriverError::from)
+    }
+
+    /// [`fetch_all_trades_since`](Self::fetch_all_trades_since) with
+    /// mid-fetch failure recovery: a failed page fails the call with an
+    /// [`InterruptedFetch`] carrying the trades received so far and the
+    /// per-type cursor of the last complete page, and a `resume` cursor
+    /// from such a failure skips everything already delivered. Segments
+    /// are the instrument types in their deterministic fan-out order.
+    pub async fn fetch_all_trades_resumable(
+        &self,
+        instruments: &InstrumentConverter,
+        since: Option<u64>,
+        resume: Option<FetchCursor>,
+    ) -> Result<Vec<RawTrade>, Box<InterruptedFetch<RawTrade>>> {
+        const PAGE_LIMIT: usize = 100;
+
+        // BTreeSet: one request per represented type, deterministic order.
+        let inst_types: Vec<&'static str> = instruments
+            .instruments()
+            .map(Instrument::inst_type)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let endpoint = match since {
+            Some(begin) if begin >= now.saturating_sub(FILLS_WINDOW_MS) => "/api/v5/trade/fills",
+            _ => "/api/v5/trade/fills-history",
+        };
+        // A window beyond the fills endpoint's reach is a backfill: its
+        // records keep their exchange fill time as `internal_created_at`
+        // instead of pretending they arrived now.
+        let historical = endpoint == "/api/v5/trade/fills-history";
+        let received_at = chrono::Utc::now();
+        let resume = resume.unwrap_or_default();
+
+        let mut seen = std::collections::HashSet::new();
+        let mut trades: Vec<RawTrade> = Vec::new();
+        let mut unknown: std::collections::BTreeMap<String, usize> =
+            std::collections::BTreeMap::new();
+        for (segment, inst_type) in inst_types.iter().enumerate().skip(resume.segment) {
+            let mut after: Option<String> = (segment == resume.segment)
+                .then(|| resume.after.clone())
+                .flatten();
+            loop {
+                let mut query = format!("instType={inst_type}&limit={PAGE_LIMIT}");
+                if let Some(begin) = since {
+                    query.push_str(&format!("&begin={begin}"));
+                }
+                if let Some(cursor) = &after {
+                    query.push_str(&format!("&after={cursor}"));
+                }
+                let page: Vec<TransactionResult> = match self
+                    .call_elements(Method::Get, endpoint, Some(&query), None)
+                    .await
+                {
+                    Ok(page) => page,
+                    Err(error) => {
+                        sort_trades(&mut trades);
+                        return Err(Box::new(InterruptedFetch {
+                            partial: trades,
+                            // `after` still names the last complete page.
+                            cursor: FetchCursor { segment, after },
+                            error,
+                        }));
+                    }
+                };
+                let page_len = page.len();
+                after = page.last().and_then(|fill| fill.bill_id.clone());
+                for fill in &page {
+                    let Some(instrument) = instruments.get(&fill.inst_id) else {
+                        *unknown.entry(fill.inst_id.clone()).or_insert(0) += 1;
+                        continue;
+                    };
+                    if seen.insert(fill.trade_id.clone()) {
+                        let trade = RawTrade::from_transaction(fill, instrument, received_at);
+                        trades.push(if historical { trade.backdated() } else { trade });
+                    }
+                }
+                if page_len < PAGE_LIMIT || after.is_none() {
+                    break;
+                }
+            }
+        }
+        if let Err(error) = self.handle_unknown_instruments("fetch_all_trades_since", &unknown) {
+            sort_trades(&mut trades);
+            return Err(Box::new(InterruptedFetch {
+                partial: trades,
+                cursor: FetchCursor {
+                    segment: inst_types.len(),
+                    after: None,
+                },
+                error,
+            }));
+        }
+
+        sort_trades(&mut trades);
+        Ok(trades)
+    }
+
+    /// Retry wrapper over
+    /// [`fetch_all_trades_resumable`](Self::fetch_all_trades_resumable):
+    /// up to `retries` interruptions are resumed from their cursor and the
+    /// partial results merged, so a transient failure on a late page costs
+    /// one page of refetching instead of the whole walk. Gives up with the
+    /// underlying error once the budget is spent.
+    pub async fn fetch_all_trades_retrying(
+        &self,
+        instruments: &InstrumentConverter,
+        since: Option<u64>,
+        retries: usize,
+    ) -> DriverResult<Vec<RawTrade>> {
+        let mut merged: Vec<RawTrade> = Vec::new();
+        let mut resume: Option<FetchCursor> = None;
+        let mut remaining = retries;
+        loop {
+            match self
+                .fetch_all_trades_resumable(instruments, since, resume.take())
+                .await
+            {
+                Ok(trades) => {
+                    merged.extend(trades);
+                    sort_trades(&mut merged);
+                    return Ok(merged);
+                }
+                Err(interrupted) => {
+                    merged.extend(interrupted.partial);
+                    if remaining == 0 {
+                        return Err(interrupted.error);
+                    }
+                    remaining -= 1;
+                    resume = Some(interrupted.cursor);
+                }
+            }
+        }
+    }
+
+    /// Per-UTC-day, per-fee-currency fee totals derived from the fills
+    /// history, keyed `(day, currency)`. Fees follow the [`RawTrade`]
+    /// cost convention: positive when charged, negative for maker rebates.
+    ///
+    /// Walks `/api/v5/trade/fills-history` from `since` (milliseconds;
+    /// `None` for the exchange default window) on the bill-id cursor,
+    /// folding each page into the summary as it arrives — a multi-day
+    /// export never holds the raw trades in memory, and every page goes
+    /// through the shared rate limiter like any other call. Fills without
+    /// a fee, fee currency, or parseable timestamp are skipped.
+    pub async fn fee_summary(
+        &self,
+        since: Option<u64>,
+    ) -> DriverResult<std::collections::BTreeMap<(chrono::NaiveDate, String), Decimal>> {
+        const PAGE_LIMIT: usize = 100;
+
+        let stream = self.stream_paginated(
+            crate::rest::PageWalk {
+                path: "/api/v5/trade/fills-history",
+                base_query: since.map(|begin| format!("begin={begin}")).unwrap_or_default(),
+                limit: PAGE_LIMIT,
+                after: None,
+            },
+            |fill: &TransactionResult| fill.bill_id.clone(),
+        );
+        futures_lite::pin!(stream);
+        let mut summary = std::collections::BTreeMap::new();
+        while let Some(fill) = stream.next().await {
+            let fill = fill?;
+            let (Some(fee), Some(currency)) = (fill.fee, fill.fee_currency) else {
+                continue;
+            };
+            let Some(time) = crate::orders::parse_exchange_millis(&fill.timestamp) else {
+                log::debug!(
+                    "skipping fill {} with unparseable ts {:?} in fee summary",
+                    fill.trade_id,
+                    fill.timestamp
+                );
+                continue;
+            };
+            // Same sign flip as the single-trade conversion: OKX
+            // reports charged fees negative.
+            *summary
+                .entry((time.date_naive(), currency))
+                .or_insert(Decimal::ZERO) -= fee;
+        }
+        Ok(summary)
+    }
+
+    /// Stream the fills history into a writer with bounded memory.
+    ///
+    /// Walks `/api/v5/trade/fills-history` from `since` (milliseconds;
+    /// `None` for the exchange default window) on the bill-id cursor like
+    /// [`Self::fee_summary`], but writes every fill to `writer` as its page
+    /// arrives — a month of a million fills never buffers more than one
+    /// page. Each fill is normalized against `instruments`; fills on
+    /// unknown instruments are skipped and logged. Every page goes through
+    /// the shared rate limiter like any other call.
+    ///
+    /// `cursor` is the caller-owned resume point: it advances per fully
+    /// written page, so re-invoking after a failure picks up where the
+    /// export left off (see [`TradeExportCursor`]). Returns the rows
+    /// written by this call.
+    pub async fn export_trades_since<W: std::io::Write>(
+        &self,
+        instruments: &InstrumentConverter,
+        inst_id: Option<&str>,
+        since: Option<u64>,
+        cursor: &mut TradeExportCursor,
+        writer: &mut W,
+        format: ExportFormat,
+    ) -> DriverResult<u64> {
+        const PAGE_LIMIT: usize = 100;
+
+        let received_at = chrono::Utc::now();
+        let mut written_this_call = 0u64;
+        loop {
+            let mut query = format!("limit={PAGE_LIMIT}");
+            if let Some(id) = inst_id {
+                query.push_str(&format!("&instId={id}"));
+            }
+            if let Some(begin) = since {
+                query.push_str(&format!("&begin={begin}"));
+            }
+            if let Some(after) = &cursor.after {
+                query.push_str(&format!("&after={after}"));
+            }
+            let page: Vec<TransactionResult> = self
+                .call_elements(
+                    Method::Get,
+                    "/api/v5/trade/fills-history",
+                    Some(&query),
+                    None,
+                )
+                .await?;
+            let page_len = page.len();
+            let next_after = page.last().and_then(|fill| fill.bill_id.clone());
+
+            if format == ExportFormat::Csv && !cursor.header_written {
+                crate::export::write_csv_header(writer)?;
+                cursor.header_written = true;
+            }
+            let mut written_this_page = 0u64;
+            for fill in &page {
+                let Some(instrument) = instruments.get(&fill.inst_id) else {
+                    log::debug!(
+                        "skipping fill {} on unknown instrument {} in export",
+                        fill.trade_id,
+                        fill.inst_id
+                    );
+                    continue;
+                };
+                // An export is a backfill by nature; keep the fill time.
+                let trade = RawTrade::from_transaction(fill, instrument, received_at).backdated();
+                crate::export::write_trade(writer, &trade, format)?;
+                written_this_page += 1;
+            }
+            // The page is in the sink: only now does the cursor move past
+            // it, so a failed call resumes from the last complete page.
+            cursor.after = next_after.clone();
+            cursor.rows_written += written_this_page;
+            written_this_call += written_this_page;
+
+            if page_len < PAGE_LIMIT || next_after.is_none() {
+                break;
+            }
+        }
+        writer
+            .flush()
+            .map_err(|e| DriverError::Generic(format!("trade export flush failed: {e}")))?;
+        Ok(written_this_call)
+    }
+
+    /// Gap-free execution history for one instrument over `[from, to)`
+    /// (milliseconds), stitched across every endpoint that covers a part
+    /// of it: `/api/v5/trade/fills` for the last 3 days,
+    /// `/api/v5/trade/fills-history` for the last 3 months, and trade
+    /// bills from `/api/v5/account/bills-archive` beyond that. Each
+    /// boundary is fetched with overlap and deduped by trade id, the
+    /// newer endpoint's richer record winning. Sub-ranges older than the
+    /// archive's reach come back as explicit [`HistoryGap`]s rather than
+    /// silently fewer trades. Trades are returned oldest first.
+    pub async fn fetch_trade_history(
+        &self,
+        instrument: &Instrument,
+        from: u64,
+        to: u64,
+    ) -> DriverResult<TradeHistory> {
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        self.fetch_trade_history_at(instrument, from, to, now).await
+    }
+
+    async fn fetch_trade_history_at(
+        &self,
+        instrument: &Instrument,
+        from: u64,
+        to: u64,
+        now: u64,
+    ) -> DriverResult<TradeHistory> {
+        if from >= to {
+            return Err(DriverError::Config(format!(
+                "trade history range is empty: {from}..{to}"
+            )));
+        }
+        let fills_start = now.saturating_sub(FILLS_WINDOW_MS);
+        let history_start = now.saturating_sub(FILLS_HISTORY_WINDOW_MS);
+        let archive_start = now.saturating_sub(BILLS_ARCHIVE_WINDOW_MS);
+
+        let mut gaps = Vec::new();
+        if from < archive_start {
+            gaps.push(HistoryGap {
+                from,
+                to: to.min(archive_start),
+            });
+        }
+
+        // History is a backfill: every record keeps its exchange fill
+        // time as `internal_created_at` via `backdated`.
+        let received_at = chrono::Utc::now();
+        let mut seen = std::collections::HashSet::new();
+        let mut trades: Vec<RawTrade> = Vec::new();
+        let mut collect = |new_trades: Vec<RawTrade>| {
+            for trade in new_trades {
+                if seen.insert(trade.trade_id.clone()) {
+                    trades.push(trade);
+                }
+            }
+        };
+
+        // Newest endpoint first: on boundary overlap the fills record,
+        // which carries PnL, wins the dedupe over its bill-derived twin.
+        if let Some((seg_from, seg_to)) = clip_range(from, to, fills_start, u64::MAX) {
+            let fills = self
+                .fetch_fill_range("/api/v5/trade/fills", &instrument.inst_id, seg_from, seg_to)
+                .await?;
+            collect(
+                fills
+                    .iter()
+                    .map(|fill| RawTrade::from_transaction(fill, instrument, received_at).backdated())
+                    .collect(),
+            );
+        }
+        if let Some((seg_from, seg_to)) = clip_range(
+            from,
+            to,
+            history_start,
+            fills_start.saturating_add(STITCH_OVERLAP_MS),
+        ) {
+            let fills = self
+                .fetch_fill_range(
+                    "/api/v5/trade/fills-history",
+                    &instrument.inst_id,
+                    seg_from,
+                    seg_to,
+                )
+                .await?;
+            collect(
+                fills
+                    .iter()
+                    .map(|fill| RawTrade::from_transaction(fill, instrument, received_at).backdated())
+                    .collect(),
+            );
+        }
+        if let Some((seg_from, seg_to)) = clip_range(
+            from,
+            to,
+            archive_start,
+            history_start.saturating_add(STITCH_OVERLAP_MS),
+        ) {
+            let mut converted = Vec::new();
+            for bill in self.fetch_trade_bill_range(seg_from, seg_to).await? {
+                if bill.inst_id.as_deref() != Some(instrument.inst_id.as_str()) {
+                    continue;
+                }
+                let Some(trade) = RawTrade::from_trade_bill(&bill, instrument, received_at) else {
+                    log::debug!(
+                        "skipping trade bill {} without execution fields",
+                        bill.bill_id
+                    );
+                    continue;
+                };
+                converted.push(trade.backdated());
+            }
+            collect(converted);
+        }
+
+        trades.sort_by(|a, b| {
+            let key = |t: &RawTrade| (t.timestamp.parse::<u64>().unwrap_or(0), t.trade_id.clone());
+            key(a).cmp(&key(b))
+        });
+        Ok(TradeHistory { trades, gaps })
+    }
+
+    /// Fills of one endpoint over `[begin, end)`, paged on the bill-id
+    /// cursor like [`Self::fee_summary`].
+    async fn fetch_fill_range(
+        &self,
+        endpoint: &'static str,
+        inst_id: &str,
+        begin: u64,
+        end: u64,
+    ) -> DriverResult<Vec<TransactionResult>> {
+        const PAGE_LIMIT: usize = 100;
+
+        self.fetch_paginated(
+            crate::rest::PageWalk {
+                path: endpoint,
+                base_query: format!("instId={inst_id}&begin={begin}&end={end}"),
+                limit: PAGE_LIMIT,
+                after: None,
+            },
+            |fill: &TransactionResult| fill.bill_id.clone(),
+        )
+        .await
+    }
+
+    /// Trade bills (`type` 2) from the archive over `[begin, end)`, paged
+    /// on the bill-id cursor. The endpoint has no instrument filter;
+    /// callers cut by `instId` client-side.
+    async fn fetch_trade_bill_range(
+        &self,
+        begin: u64,
+        end: u64,
+    ) -> DriverResult<Vec<OkexBillResponse>> {
+        const PAGE_LIMIT: usize = 100;
+
+        self.fetch_paginated(
+            crate::rest::PageWalk {
+                path: "/api/v5/account/bills-archive",
+                base_query: format!("type=2&begin={begin}&end={end}"),
+                limit: PAGE_LIMIT,
+                after: None,
+            },
+            |bill: &OkexBillResponse| Some(bill.bill_id.clone()),
+        )
+        .await
+    }
+
+    /// Arm — or with `0`, disarm — the account-wide "cancel all after"
+    /// dead-man's switch via `/api/v5/trade/cancel-all-after`. The switch
+    /// applies to every instrument on the account; see
+    /// [`crate::cancel_all_after`] for the refresher that keeps it live.
+    pub async fn rest_set_cancel_all_after(
+        &self,
+        timeout_secs: u64,
+    ) -> DriverResult<OkexCancelAllAfterResult> {
+        let body = serde_json::json!({ "timeOut": timeout_secs.to_string() }).to_string();
+        let mut data: Vec<OkexCancelAllAfterResult> = self
+            .call(
+                Method::Post,
+                "/api/v5/trade/cancel-all-after",
+                None,
+                Some(body),
+            )
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty cancel-all-after response".to_string()))
+    }
+
+    /// Cancel a batch of `(instId, ordId)` pairs via
+    /// `/api/v5/trade/cancel-batch-orders`, chunked at the exchange limit
+    /// of 20.
+    ///
+    /// A chunk-level failure (transport error, or a non-zero envelope with
+    /// no per-item results) marks every order of that chunk as failed in
+    /// the outcome rather than being swallowed. When not a single order was
+    /// cancelled and at least one chunk failed outright, the whole call
+    /// errors — an exchange rejecting everything must not read as "nothing
+    /// left to do" upstream.
+    pub async fn rest_cancel_orders(
+        &self,
+        orders: &[(String, String)],
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        let mut chunk_errors: Vec<DriverError> = Vec::new();
+        for chunk in orders.chunks(BATCH_CHUNK_SIZE) {
+            let entries: Vec<serde_json::Value> = chunk
+                .iter()
+                .map(|(inst_id, order_id)| {
+                    serde_json::json!({ "instId": inst_id, "ordId": order_id })
+                })
+                .collect();
+            let body = serde_json::to_string(&entries)?;
+            let result = self
+                .call_envelope::<OkexOrderOpResult>(
+                    Method::Post,
+                    "/api/v5/trade/cancel-batch-orders",
+                    None,
+                    Some(body),
+                )
+                .await;
+            let error = match result {
+                Ok(envelope)
+                    if batch_code_has_item_results(&envelope.code)
+                        && !envelope.data.is_empty() =>
+                {
+                    outcome.merge(collect_batch_outcome(envelope.data));
+                    continue;
+                }
+                Ok(envelope) => DriverError::Api {
+                    code: envelope.code,
+                    message: envelope.msg,
+                },
+                Err(error) => error,
+            };
+            log::warn!("cancel chunk of {} orders failed: {error}", chunk.len());
+            for (_, order_id) in chunk {
+                outcome.failed.push(BatchItemError {
+                    order_id: order_id.clone(),
+                    client_order_id: None,
+                    code: "chunk".to_string(),
+                    message: error.to_string(),
+                });
+            }
+            chunk_errors.push(error);
+        }
+        if outcome.succeeded.is_empty() && !chunk_errors.is_empty() {
+            if chunk_errors.len() == 1 {
+                return Err(chunk_errors.pop().expect("one error is present"));
+            }
+            let messages: Vec<String> =
+                chunk_errors.iter().map(ToString::to_string).collect();
+            return Err(DriverError::Generic(format!(
+                "all {} cancel chunks failed: {}",
+                chunk_errors.len(),
+                messages.join("; ")
+            )));
+        }
+        Ok(outcome)
+    }
+
+    /// Amend a single order via `/api/v5/trade/amend-order`.
+    pub async fn rest_amend_order(
+        &self,
+        amendment: OkexAmendOrderRequest,
+    ) -> DriverResult<OkexOrderOpResult> {
+        let body = serde_json::to_string(&amendment)?;
+        let mut data: Vec<OkexOrderOpResult> = self
+            .call(Method::Post, "/api/v5/trade/amend-order", None, Some(body))
+            .await?;
+        data.pop()
+            .ok_or_else(|| DriverError::Generic("empty amend-order response".to_string()))
+    }
+
+    /// Amend up to any number of orders via `/api/v5/trade/amend-batch-orders`,
+    /// chunked at the exchange limit of 20, correlating per-order results.
+    /// A non-zero top-level code is fine as long as per-item results are
+    /// present; per-order failures land in [`BatchOutcome::failed`].
+    pub async fn rest_amend_orders(
+        &self,
+        amendments: Vec<OkexAmendOrderRequest>,
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        for chunk in amendments.chunks(BATCH_CHUNK_SIZE) {
+            let body = serde_json::to_string(chunk)?;
+            let envelope = self
+                .call_envelope::<OkexOrderOpResult>(
+                    Method::Post,
+                    "/api/v5/trade/amend-batch-orders",
+                    None,
+                    Some(body),
+                )
+                .await?;
+            if !batch_code_has_item_results(&envelope.code) || envelope.data.is_empty() && envelope.code != "0" {
+                return Err(DriverError::Api {
+                    code: envelope.code,
+                    message: envelope.msg,
+                });
+            }
+            outcome.merge(collect_batch_outcome(envelope.data));
+        }
+        Ok(outcome)
+    }
+
+    /// High-level amend: normalizes each new price/size against the
+    /// instrument before batching.
+    pub async fn amend_orders(
+        &self,
+        instrument: &Instrument,
+        amendments: Vec<(String, Option<Decimal>, Option<Decimal>)>,
+    ) -> DriverResult<BatchOutcome> {
+        let mut requests = Vec::with_capacity(amendments.len());
+        for (order_id, new_price, new_amount) in amendments {
+            // `newSz:"0"` / `newPx:"0"` are exchange rejections, not
+            // no-ops; catch them before anything in the batch goes out.
+            let new_sz = new_amount
+                .map(|amount| {
+                    let rendered = serialize_size(amount, instrument.lot_size);
+                    if rendered.parse::<Decimal>().unwrap_or_default() <= Decimal::ZERO {
+                        return Err(DriverError::ZeroSize {
+                            inst_id: instrument.inst_id.clone(),
+                            requested: amount,
+                        });
+                    }
+                    Ok(rendered)
+                })
+                .transpose()?;
+            if new_price.is_some_and(|price| price.is_zero()) {
+                return Err(DriverError::Config(format!(
+                    "amendment of {order_id} on {} needs a non-zero price",
+                    instrument.inst_id
+                )));
+            }
+            requests.push(OkexAmendOrderRequest {
+                inst_id: instrument.inst_id.clone(),
+                order_id,
+                new_px: new_price.map(|p| serialize_price(p, instrument.tick_size)),
+                new_sz,
+            });
+        }
+        self.rest_amend_orders(requests).await
+    }
+
+    /// Place one algo order (e.g. a trailing stop built through
+    /// [`crate::algo_orders::TrailingStopRequest`]) via
+    /// `/api/v5/trade/order-algo`.
+    pub async fn rest_place_algo_order(
+        &self,
+        params: &crate::algo_orders::OkexAlgoOrderParams,
+    ) -> DriverResult<OkexAlgoOrderResult> {
+        let body = serde_json::to_string(params)?;
+        let mut data: Vec<OkexAlgoOrderResult> = self
+            .call(Method::Post, "/api/v5/trade/order-algo", None, Some(body))
+            .await?;
+        let result = data
+            .pop()
+            .ok_or_else(|| DriverError::Generic("empty place-algo-order response".to_string()))?;
+        if result.s_code != "0" {
+            return Err(self.api_error("/api/v5/trade/order-algo", result.s_code, result.s_msg));
+        }
+        Ok(result)
+    }
+
+    /// Cancel algo orders via `/api/v5/trade/cancel-algos`, chunked at the
+    /// exchange limit. Entries are `(instId, algoId)` pairs; the outcome
+    /// carries algo ids where regular batches carry order ids.
+    pub async fn rest_cancel_algo_orders(
+        &self,
+        orders: &[(String, String)],
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        for chunk in orders.chunks(ALGO_BATCH_CHUNK_SIZE) {
+            let entries: Vec<serde_json::Value> = chunk
+                .iter()
+                .map(|(inst_id, algo_id)| {
+                    serde_json::json!({ "instId": inst_id, "algoId": algo_id })
+                })
+                .collect();
+            let body = serde_json::to_string(&entries)?;
+            let envelope = self
+                .call_envelope::<OkexAlgoOrderResult>(
+                    Method::Post,
+                    "/api/v5/trade/cancel-algos",
+                    None,
+                    Some(body),
+                )
+                .await?;
+            if !batch_code_has_item_results(&envelope.code)
+                || envelope.data.is_empty() && envelope.code != "0"
+            {
+                return Err(DriverError::Api {
+                    code: envelope.code,
+                    message: envelope.msg,
+                });
+            }
+            outcome.merge(collect_batch_outcome(
+                envelope
+                    .data
+                    .into_iter()
+                    .map(|result| OkexOrderOpResult {
+                        order_id: result.algo_id,
+                        client_order_id: result.algo_client_id,
+                        s_code: result.s_code,
+                        s_msg: result.s_msg,
+                    })
+                    .collect(),
+            ));
+        }
+        Ok(outcome)
+    }
+
+    /// Resting trailing stops via `/api/v5/trade/orders-algo-pending`,
+    /// paginated like the regular open-orders fetch.
+    pub async fn rest_fetch_pending_algo_orders(
+        &self,
+    ) -> DriverResult<Vec<OkexPendingAlgoOrder>> {
+        self.rest_fetch_pending_algo_orders_of_type("move_order_stop")
+            .await
+    }
+
+    /// Resting algo orders of one `ordType` (`move_order_stop`, `iceberg`,
+    /// `twap`); the endpoint requires the type in the query, so there is no
+    /// single fetch covering all of them.
+    pub async fn rest_fetch_pending_algo_orders_of_type(
+        &self,
+        ord_type: &str,
+    ) -> DriverResult<Vec<OkexPendingAlgoOrder>> {
+        const PAGE_LIMIT: usize = 100;
+
+        self.fetch_paginated(
+            crate::rest::PageWalk {
+                path: "/api/v5/trade/orders-algo-pending",
+                base_query: format!("ordType={ord_type}"),
+                limit: PAGE_LIMIT,
+                after: None,
+            },
+            |order: &OkexPendingAlgoOrder| Some(order.algo_id.clone()),
+        )
+        .await
+    }
+
+    /// One algo order's details via `GET /api/v5/trade/order-algo`.
+    pub async fn rest_fetch_algo_order(
+        &self,
+        algo_id: &str,
+    ) -> DriverResult<OkexAlgoOrderDetails> {
+        let query = format!("algoId={algo_id}");
+        let mut data: Vec<OkexAlgoOrderDetails> = self
+            .call(Method::Get, "/api/v5/trade/order-algo", Some(&query), None)
+            .await?;
+        data.pop().ok_or_else(|| {
+            DriverError::OrderNotFound(format!("algo order {algo_id} not found"))
+        })
+    }
+
+    /// Poll a sliced algo order into a base-denominated
+    /// [`AlgoProgress`](crate::algo_orders::AlgoProgress) snapshot; the
+    /// `orders-algo` channel signals *that* something changed, this says
+    /// how far along the execution is.
+    pub async fn rest_fetch_algo_progress(
+        &self,
+        algo_id: &str,
+        instrument: &Instrument,
+    ) -> DriverResult<crate::algo_orders::AlgoProgress> {
+        let details = self.rest_fetch_algo_order(algo_id).await?;
+        Ok(crate::algo_orders::AlgoProgress::from_details(
+            &details, instrument,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    fn client(transport: &Arc<MockTransport>) -> OkexClient {
+        OkexClient::with_transport(
+            OkexConfig::default(),
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        )
+    }
+
+    fn instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    fn ok_results(ids: std::ops::Range<usize>) -> String {
+        let entries: Vec<String> = ids
+            .map(|i| format!(r#"{{"ordId":"ord{i}","sCode":"0","sMsg":""}}"#))
+            .collect();
+        format!(r#"{{"code":"0","msg":"","data":[{}]}}"#, entries.join(","))
+    }
+
+    #[tokio::test]
+    async fn batch_amend_spans_chunks_and_reports_rejection() {
+        let transport = Arc::new(MockTransport::new());
+        // First chunk: 20 successes. Second chunk: 4 successes and one
+        // per-item rejection under a partial-success top-level code.
+        transport.push_json(&ok_results(0..20));
+        transport.push_json(
+            r#"{"code":"2","msg":"partial success","data":[
+                {"ordId":"ord20","sCode":"0","sMsg":""},
+                {"ordId":"ord21","sCode":"0","sMsg":""},
+                {"ordId":"ord22","sCode":"51503","sMsg":"Order does not exist"},
+                {"ordId":"ord23","sCode":"0","sMsg":""},
+                {"ordId":"ord24","sCode":"0","sMsg":""}
+            ]}"#,
+        );
+        let client = client(&transport);
+
+        let amendments = (0..25)
+            .map(|i| {
+                (
+                    format!("ord{i}"),
+                    Some("43250.17".parse().unwrap()),
+                    None,
+                )
+            })
+            .collect();
+        let outcome = client.amend_orders(&instrument(), amendments).await.unwrap();
+
+        assert_eq!(outcome.succeeded.len(), 24);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].order_id, "ord22");
+        assert_eq!(outcome.failed[0].code, "51503");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        let first_chunk: Vec<OkexAmendOrderRequest> =
+            serde_json::from_str(requests[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(first_chunk.len(), 20);
+        // Normalization clamps 43250.17 to the 0.1 tick.
+        assert_eq!(first_chunk[0].new_px.as_deref(), Some("43250.1"));
+        let second_chunk: Vec<OkexAmendOrderRequest> =
+            serde_json::from_str(requests[1].body.as_deref().unwrap()).unwrap();
+        assert_eq!(second_chunk.len(), 5);
+    }
+
+    #[test]
+    fn amendments_serialize_only_the_changed_fields() {
+        let price_only = OkexAmendOrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            order_id: "ord1".to_string(),
+            new_px: Some("43250.1".to_string()),
+            new_sz: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&price_only).unwrap(),
+            r#"{"instId":"BTC-USDT","ordId":"ord1","newPx":"43250.1"}"#
+        );
+
+        let size_only = OkexAmendOrderRequest {
+            new_px: None,
+            new_sz: Some("0.5".to_string()),
+            ..price_only
+        };
+        // An omitted field means "keep as is"; a literal `"0"` would be a
+        // rejection, so `None` must vanish rather than serialize as zero.
+        assert_eq!(
+            serde_json::to_string(&size_only).unwrap(),
+            r#"{"instId":"BTC-USDT","ordId":"ord1","newSz":"0.5"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_amendments_are_rejected_before_the_batch_goes_out() {
+        let transport = Arc::new(MockTransport::new());
+        let client = client(&transport);
+
+        let err = client
+            .amend_orders(
+                &instrument(),
+                vec![("ord1".to_string(), None, Some("0".parse().unwrap()))],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::ZeroSize { .. }), "got: {err}");
+
+        let err = client
+            .amend_orders(
+                &instrument(),
+                vec![("ord1".to_string(), Some(Decimal::ZERO), None)],
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("non-zero price"), "{err}");
+        assert!(transport.requests().is_empty(), "nothing may reach the wire");
+    }
+
+    #[tokio::test]
+    async fn batch_amend_surfaces_wholesale_failure() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"50011","msg":"rate limited","data":[]}"#);
+        let client = client(&transport);
+
+        let err = client
+            .rest_amend_orders(vec![OkexAmendOrderRequest {
+                inst_id: "BTC-USDT".to_string(),
+                order_id: "ord1".to_string(),
+                new_px: Some("1".to_string()),
+                new_sz: None,
+            }])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Api { ref code, .. } if code == "50011"));
+    }
+
+    fn cancel_pairs(count: usize) -> Vec<(String, String)> {
+        (0..count)
+            .map(|i| ("BTC-USDT".to_string(), format!("ord{i}")))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn batch_cancel_succeeds_across_chunks() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&ok_results(0..20));
+        transport.push_json(&ok_results(20..25));
+        let client = client(&transport);
+
+        let outcome = client.rest_cancel_orders(&cancel_pairs(25)).await.unwrap();
+
+        assert_eq!(outcome.succeeded.len(), 25);
+        assert!(outcome.failed.is_empty());
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].body.as_deref().unwrap().contains(r#""ordId":"ord0""#));
+        assert!(requests[1].body.as_deref().unwrap().contains(r#""ordId":"ord24""#));
+    }
+
+    #[tokio::test]
+    async fn batch_cancel_keeps_partial_success_and_marks_the_failed_chunk() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&ok_results(0..20));
+        // Second chunk rejected wholesale, no per-item results.
+        transport.push_json(r#"{"code":"50011","msg":"rate limited","data":[]}"#);
+        let client = client(&transport);
+
+        let outcome = client.rest_cancel_orders(&cancel_pairs(25)).await.unwrap();
+
+        assert_eq!(outcome.succeeded.len(), 20);
+        assert_eq!(outcome.failed.len(), 5);
+        assert!(outcome.failed.iter().all(|f| f.code == "chunk"));
+        assert!(outcome.failed[0].message.contains("50011"));
+    }
+
+    #[tokio::test]
+    async fn batch_cancel_partitions_a_code_1_partial_success() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"1","msg":"Operation partially succeeded","data":[
+                {"ordId":"ord0","sCode":"0","sMsg":""},
+                {"ordId":"ord1","sCode":"51503","sMsg":"Order does not exist"},
+                {"ordId":"ord2","sCode":"0","sMsg":""}
+            ]}"#,
+        );
+        let client = client(&transport);
+
+        let outcome = client.rest_cancel_orders(&cancel_pairs(3)).await.unwrap();
+
+        assert_eq!(outcome.succeeded, vec!["ord0", "ord2"]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].order_id, "ord1");
+        assert_eq!(outcome.failed[0].code, "51503");
+    }
+
+    #[tokio::test]
+    async fn batch_cancel_errors_when_every_chunk_fails() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"50011","msg":"rate limited","data":[]}"#);
+        transport.push_json(r#"{"code":"50011","msg":"rate limited","data":[]}"#);
+        let client = client(&transport);
+
+        let err = client.rest_cancel_orders(&cancel_pairs(25)).await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("all 2 cancel chunks failed"),
+            "{err}"
+        );
+    }
+
+    fn pending_order_json(i: usize, inst_id: &str) -> String {
+        format!(
+            r#"{{"instId":"{inst_id}","instType":"SPOT","ordId":"ord{i}","clOrdId":"","px":"100.{i}","sz":"1","side":"buy","state":"live","cTime":"{}"}}"#,
+            1_700_000_000_000u64 + i as u64
+        )
+    }
+
+    fn page_of(entries: Vec<String>) -> String {
+        format!(r#"{{"code":"0","msg":"","data":[{}]}}"#, entries.join(","))
+    }
+
+    #[tokio::test]
+    async fn open_orders_fetch_dedupes_and_drops_unknown_instruments() {
+        let transport = Arc::new(MockTransport::new());
+        // Full first page of 100; the second page repeats the boundary order
+        // and contains one order on an instrument we do not trade.
+        let first_page: Vec<String> =
+            (0..100).map(|i| pending_order_json(i, "BTC-USDT")).collect();
+        let second_page = vec![
+            pending_order_json(99, "BTC-USDT"),
+            pending_order_json(100, "DOGE-USDT"),
+            pending_order_json(101, "BTC-USDT"),
+        ];
+        transport.push_json(&page_of(first_page));
+        transport.push_json(&page_of(second_page));
+        let client = client(&transport);
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(instrument());
+        let orders = client.fetch_open_orders(&converter, false).await.unwrap();
+
+        assert_eq!(orders.len(), 101, "100 unique + ord101, duplicate and unknown dropped");
+        assert!(orders.iter().all(|o| o.inst_id == "BTC-USDT"));
+        // Sorted by creation time and deduped.
+        assert_eq!(orders.first().unwrap().order_id, "ord0");
+        assert_eq!(orders.last().unwrap().order_id, "ord101");
+        assert_eq!(
+            orders.iter().filter(|o| o.order_id == "ord99").count(),
+            1
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].url.contains("after=ord99"), "{}", requests[1].url);
+    }
+
+    #[tokio::test]
+    async fn external_orders_are_surfaced_when_the_toggle_is_on() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&page_of(vec![
+            pending_order_json(0, "BTC-USDT"),
+            // Placed from the UI on a pair the driver does not trade.
+            pending_order_json(1, "DOGE-USDT"),
+        ]));
+        let client = OkexClient::with_transport(
+            OkexConfig {
+                surface_external_orders: true,
+                ..OkexConfig::default()
+            },
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(instrument());
+        let orders = client.fetch_open_orders(&converter, false).await.unwrap();
+
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[1].inst_id, "DOGE-USDT");
+    }
+
+    #[tokio::test]
+    async fn the_warn_policy_keeps_known_orders_and_counts_the_rest() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&page_of(vec![
+            pending_order_json(0, "BTC-USDT"),
+            pending_order_json(1, "DOGE-USDT"),
+        ]));
+        let mut client = OkexClient::with_transport(
+            OkexConfig {
+                unknown_instrument_policy: crate::config::UnknownInstrumentPolicy::Warn,
+                ..OkexConfig::default()
+            },
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+        let hook = Arc::new(UnknownCountingHook::default());
+        client.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(instrument());
+        let orders = client.fetch_open_orders(&converter, false).await.unwrap();
+
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].inst_id, "BTC-USDT");
+        assert_eq!(
+            *hook.unknown.lock().unwrap(),
+            vec![("DOGE-USDT".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_error_policy_fails_the_call_listing_the_offenders() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&page_of(vec![
+            pending_order_json(0, "BTC-USDT"),
+            pending_order_json(1, "DOGE-USDT"),
+        ]));
+        let client = OkexClient::with_transport(
+            OkexConfig {
+                unknown_instrument_policy: crate::config::UnknownInstrumentPolicy::Error,
+                ..OkexConfig::default()
+            },
+            Arc::clone(&transport) as Arc<dyn HttpTransport>,
+        );
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(instrument());
+        let err = client
+            .fetch_open_orders(&converter, false)
+            .await
+            .unwrap_err();
+
+        match err {
+            DriverError::UnknownInstruments { context, instruments } => {
+                assert_eq!(context, "fetch_open_orders");
+                assert_eq!(instruments, vec!["DOGE-USDT".to_string()]);
+            }
+            other => panic!("expected UnknownInstruments, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn open_orders_count_verification_prefers_fresher_set() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&page_of(vec![
+            pending_order_json(0, "BTC-USDT"),
+            pending_order_json(1, "BTC-USDT"),
+        ]));
+        // An order got filled between the two fetches.
+        transport.push_json(&page_of(vec![pending_order_json(1, "BTC-USDT")]));
+        let client = client(&transport);
+
+        let mut converter = InstrumentConverter::new();
+        converter.insert(instrument());
+        let orders = client.fetch_open_orders(&converter, true).await.unwrap();
+
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "ord1");
+    }
+
+    #[tokio::test]
+    async fn rest_cancel_by_client_id_serializes_clordid() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"ord1","clOrdId":"clord7","sCode":"0","sMsg":""}]}"#,
+        );
+        let client = client(&transport);
+
+        client
+            .rest_cancel_order_by_client_id("BTC-USDT", "clord7")
+            .await
+            .unwrap();
+
+        let body = transport.requests()[0].body.clone().unwrap();
+        assert_eq!(body, r#"{"clOrdId":"clord7","instId":"BTC-USDT"}"#);
+    }
+
+    #[test]
+    fn cancel_outcome_error_mapping() {
+        let not_found = OkexOrderOpResult {
+            order_id: String::new(),
+            client_order_id: None,
+            s_code: "51400".to_string(),
+            s_msg: "Cancellation failed as the order does not exist".to_string(),
+        };
+        assert!(matches!(
+            map_cancel_outcome(not_found),
+            Err(DriverError::OrderNotFound(_))
+        ));
+
+        let hard_failure = OkexOrderOpResult {
+            order_id: String::new(),
+            client_order_id: Some("clord7".to_string()),
+            s_code: "50011".to_string(),
+            s_msg: "rate limited".to_string(),
+        };
+        let err = map_cancel_outcome(hard_failure).unwrap_err();
+        let DriverError::Api { code, message } = &err else {
+            panic!("expected an API error, got: {err}");
+        };
+        assert_eq!(code, "50011");
+        assert_eq!(message, "clOrdId=clord7: rate limited");
+    }
+
+    #[test]
+    fn rejection_messages_are_truncated_on_a_char_boundary() {
+        // A multi-byte char straddling the cap must not split mid-char.
+        let s_msg: String = "é".repeat(S_MSG_MAX_LEN + 10);
+        let truncated = truncate_s_msg(&s_msg);
+        assert!(truncated.ends_with(" [truncated]"), "{truncated}");
+        assert_eq!(
+            truncated.trim_end_matches(" [truncated]").chars().count(),
+            S_MSG_MAX_LEN
+        );
+        assert_eq!(truncate_s_msg("short"), "short");
+    }
+
+    #[tokio::test]
+    async fn algo_order_placement_serializes_the_trailing_stop_payload() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"algoId":"algo1","algoClOrdId":"trail-1","sCode":"0","sMsg":""}]}"#,
+        );
+        let client = client(&transport);
+
+        let stop = crate::algo_orders::TrailingStopRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side: crate::orders::Side::Sell,
+            size: "0.5".parse().unwrap(),
+            offset: crate::algo_orders::TrailingOffset::Ratio("0.05".parse().unwrap()),
+            active_price: None,
+            position_intent: None,
+            client_algo_id: Some("trail-1".to_string()),
+        };
+        let instrument = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let params = stop
+            .build(
+                &instrument,
+                crate::orders::TradeMode::Cross,
+                crate::orders::OkexPositionMode::Net,
+            )
+            .unwrap();
+        let result = client.rest_place_algo_order(&params).await.unwrap();
+        assert_eq!(result.algo_id, "algo1");
+
+        let request = &transport.requests()[0];
+        assert!(request.url.ends_with("/api/v5/trade/order-algo"), "{}", request.url);
+        let body: serde_json::Value =
+            serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body["ordType"], "move_order_stop");
+        assert_eq!(body["callbackRatio"], "0.05");
+        assert_eq!(body["sz"], "50");
+    }
+
+    #[tokio::test]
+    async fn cancel_algos_partitions_per_item_results_onto_algo_ids() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"2","msg":"partial","data":[
+                {"algoId":"algo1","sCode":"0","sMsg":""},
+                {"algoId":"algo2","sCode":"51293","sMsg":"The algo order does not exist"}
+            ]}"#,
+        );
+        let client = client(&transport);
+
+        let outcome = client
+            .rest_cancel_algo_orders(&[
+                ("BTC-USDT-SWAP".to_string(), "algo1".to_string()),
+                ("BTC-USDT-SWAP".to_string(), "algo2".to_string()),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.succeeded, vec!["algo1"]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].order_id, "algo2");
+        assert_eq!(outcome.failed[0].code, "51293");
+
+        let body: serde_json::Value =
+            serde_json::from_str(transport.requests()[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(body[0]["algoId"], "algo1");
+        assert_eq!(body[1]["instId"], "BTC-USDT-SWAP");
+    }
+
+    #[tokio::test]
+    async fn pending_algo_orders_parse_both_offset_variants() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"algoId":"algo1","instId":"BTC-USDT-SWAP","ordType":"move_order_stop","state":"live","side":"sell","sz":"50","callbackRatio":"0.05","callbackSpread":"","activePx":"","moveTriggerPx":"41087.6","cTime":"1700000000000"},
+                {"algoId":"algo2","instId":"ETH-USDT-SWAP","ordType":"move_order_stop","state":"live","side":"buy","sz":"10","callbackRatio":"","callbackSpread":"25.5","activePx":"2150","moveTriggerPx":"","cTime":"1700000000500"}
+            ]}"#,
+        );
+        let client = client(&transport);
+
+        let pending = client.rest_fetch_pending_algo_orders().await.unwrap();
+
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].callback_ratio, Some("0.05".parse().unwrap()));
+        assert_eq!(pending[0].callback_spread, None);
+        assert_eq!(pending[0].move_trigger_price, Some("41087.6".parse().unwrap()));
+        assert_eq!(pending[1].callback_spread, Some("25.5".parse().unwrap()));
+        assert_eq!(pending[1].active_price, Some("2150".parse().unwrap()));
+        assert!(transport.requests()[0]
+            .url
+            .contains("orders-algo-pending?ordType=move_order_stop"));
+    }
+
+    #[tokio::test]
+    async fn algo_progress_converts_the_details_fixture_back_to_base_units() {
+        const DETAILS: &str = include_str!("../test_data/algo_order_details.json");
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(DETAILS);
+        let client = client(&transport);
+
+        let swap = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let progress = client
+            .rest_fetch_algo_progress("algo42", &swap)
+            .await
+            .unwrap();
+
+        // 45 of 120 contracts filled at ctVal 0.01.
+        assert_eq!(progress.filled, "0.45".parse::<Decimal>().unwrap());
+        assert_eq!(progress.remaining, "0.75".parse::<Decimal>().unwrap());
+        assert_eq!(progress.child_orders, vec!["ord101", "ord102", "ord103"]);
+        assert!(transport.requests()[0]
+            .url
+            .contains("/api/v5/trade/order-algo?algoId=algo42"));
+
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let err = client
+            .rest_fetch_algo_progress("missing", &swap)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::OrderNotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn order_fills_convert_and_sum_to_the_order_size() {
+        let transport = Arc::new(MockTransport::new());
+        // A 5 BTC swap order (ctVal 0.01) filled in three parts of
+        // 100 + 150 + 250 contracts.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT-SWAP","tradeId":"t1","ordId":"ord1","billId":"b1","fillPx":"43250.1","fillSz":"100","side":"buy","fee":"-0.43","feeCcy":"USDT","ts":"1700000000100"},
+                {"instId":"BTC-USDT-SWAP","tradeId":"t2","ordId":"ord1","billId":"b2","fillPx":"43250.2","fillSz":"150","side":"buy","fee":"-0.65","feeCcy":"USDT","ts":"1700000000200"},
+                {"instId":"BTC-USDT-SWAP","tradeId":"t3","ordId":"ord1","billId":"b3","fillPx":"43250.3","fillSz":"250","side":"buy","fee":"-1.08","feeCcy":"USDT","ts":"1700000000300"}
+            ]}"#,
+        );
+        let client = client(&transport);
+
+        let instrument = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let fills = client.fetch_order_fills(&instrument, "ord1").await.unwrap();
+
+        assert_eq!(fills.len(), 3);
+        let total: Decimal = fills.iter().map(|f| f.amount).sum();
+        assert_eq!(total, "5".parse::<Decimal>().unwrap());
+        assert!(fills.iter().all(|f| f.fee.unwrap() > Decimal::ZERO));
+
+        let url = &transport.requests()[0].url;
+        assert!(url.contains("instId=BTC-USDT-SWAP"), "{url}");
+        assert!(url.contains("ordId=ord1"), "{url}");
+    }
+
+    #[tokio::test]
+    async fn order_fills_page_on_the_bill_id_cursor() {
+        let transport = Arc::new(MockTransport::new());
+        let full_page: Vec<String> = (0..100)
+            .map(|i| {
+                format!(
+                    r#"{{"instId":"BTC-USDT","tradeId":"t{i}","ordId":"ord1","billId":"b{i}","fillPx":"100","fillSz":"0.01","side":"buy","fee":"-0.001","feeCcy":"USDT","ts":"1700000000000"}}"#
+                )
+            })
+            .collect();
+        transport.push_json(&page_of(full_page));
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","tradeId":"t100","ordId":"ord1","billId":"b100","fillPx":"100","fillSz":"0.01","side":"buy","fee":"-0.001","feeCcy":"USDT","ts":"1700000000000"}]}"#,
+        );
+        let client = client(&transport);
+
+        let fills = client
+            .rest_fetch_trades_for_order("BTC-USDT", "ord1")
+            .await
+            .unwrap();
+
+        assert_eq!(fills.len(), 101);
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].url.contains("after=b99"), "{}", requests[1].url);
+    }
+
+    #[tokio::test]
+    async fn templated_order_bodies_stay_byte_identical_across_cache_reuse() {
+        let transport = Arc::new(MockTransport::new());
+        for _ in 0..3 {
+            transport.push_json(
+                r#"{"code":"0","msg":"","data":[{"ordId":"ord1","sCode":"0","sMsg":""}]}"#,
+            );
+        }
+        let client = client(&transport);
+        let params = crate::orders::OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: crate::orders::TradeMode::Cash,
+            side: crate::orders::Side::Buy,
+            ord_type: crate::orders::OrderType::Limit,
+            px: Some("43250.1".to_string()),
+            sz: "0.01".to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: Some("clord1".to_string()),
+        };
+        // Second order reuses the cached template; the third flips the
+        // trade mode behind its back and must get rebuilt statics.
+        let second = crate::orders::OkexOrderParams {
+            side: crate::orders::Side::Sell,
+            px: Some("43251.3".to_string()),
+            cl_ord_id: None,
+            ..params.clone()
+        };
+        let third = crate::orders::OkexOrderParams {
+            td_mode: crate::orders::TradeMode::Cross,
+            ccy: Some("USDT".to_string()),
+            ..params.clone()
+        };
+
+        for order in [&params, &second, &third] {
+            client.rest_place_order(order).await.unwrap();
+        }
+
+        let requests = transport.requests();
+        for (request, order) in requests.iter().zip([&params, &second, &third]) {
+            assert_eq!(
+                request.body.as_deref().unwrap(),
+                serde_json::to_string(order).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_order_carries_structured_context() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[{"ordId":"","clOrdId":"clord1","sCode":"51008","sMsg":"Order failed. Insufficient balance"}]}"#,
+        );
+        let client = client(&transport);
+
+        let err = client
+            .rest_place_order(&crate::orders::OkexOrderParams {
+                inst_id: "BTC-USDT".to_string(),
+                td_mode: crate::orders::TradeMode::Cash,
+                side: crate::orders::Side::Buy,
+                ord_type: crate::orders::OrderType::Limit,
+                px: Some("100".to_string()),
+                sz: "1".to_string(),
+                ccy: None,
+                tgt_ccy: None,
+                pos_side: None,
+                reduce_only: None,
+                cl_ord_id: Some("clord1".to_string()),
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, DriverError::Api { ref code, .. } if code == "51008"));
+        assert!(
+            err.to_string().contains("endpoint=/api/v5/trade/order code=51008"),
+            "{err}"
+        );
+        let details = client.last_error_details();
+        assert_eq!(details[0].code.as_deref(), Some("51008"));
+        assert!(details[0].message.contains("Insufficient balance"));
+    }
+
+    #[tokio::test]
+    async fn fee_summary_aggregates_days_and_currencies_across_pages() {
+        let transport = Arc::new(MockTransport::new());
+        // 300 fills spread over three UTC days, two fee currencies each:
+        // 50 USDT taker fees and 50 BTC maker rebates per day.
+        let day_start = 1_700_000_000_000u64; // 2023-11-14 UTC
+        for day in 0..3 {
+            let page: Vec<String> = (0..100)
+                .map(|i| {
+                    let index = day * 100 + i;
+                    let (fee, ccy) = if i % 2 == 0 {
+                        ("-0.01", "USDT")
+                    } else {
+                        ("0.001", "BTC")
+                    };
+                    format!(
+                        r#"{{"instId":"BTC-USDT","tradeId":"t{index}","ordId":"ord{index}","billId":"b{index}","fillPx":"100","fillSz":"0.01","side":"buy","fee":"{fee}","feeCcy":"{ccy}","ts":"{}"}}"#,
+                        day_start + day * 86_400_000 + i
+                    )
+                })
+                .collect();
+            transport.push_json(&page_of(page));
+        }
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = client(&transport);
+
+        let summary = client.fee_summary(Some(day_start)).await.unwrap();
+
+        assert_eq!(summary.len(), 6, "three days x two currencies");
+        let day1 = chrono::NaiveDate::from_ymd_opt(2023, 11, 14).unwrap();
+        // 50 charged fees of 0.01 become a positive cost.
+        assert_eq!(
+            summary[&(day1, "USDT".to_string())],
+            "0.5".parse::<Decimal>().unwrap()
+        );
+        // 50 maker rebates of 0.001 stay negative.
+        assert_eq!(
+            summary[&(day1, "BTC".to_string())],
+            "-0.05".parse::<Decimal>().unwrap()
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[0].url.contains("begin=1700000000000"), "{}", requests[0].url);
+        assert!(requests[1].url.contains("after=b99"), "{}", requests[1].url);
+        assert!(requests[0].url.contains("fills-history"), "{}", requests[0].url);
+    }
+
+    fn spot_converter() -> InstrumentConverter {
+        let mut converter = InstrumentConverter::new();
+        converter.insert(Instrument {
+            inst_id: "BTC-USDT".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "0.0001".parse().unwrap(),
+            min_size: "0.0001".parse().unwrap(),
+            contract_value: None,
+            expiry_time: None,
+            margin: false,
+        });
+        converter
+    }
+
+    fn export_fill_page(start: usize, count: usize) -> String {
+        let fills: Vec<String> = (start..start + count)
+            .map(|i| {
+                format!(
+                    r#"{{"instId":"BTC-USDT","tradeId":"t{i}","ordId":"ord{i}","billId":"b{i}","fillPx":"100","fillSz":"0.01","side":"buy","fee":"-0.001","feeCcy":"USDT","ts":"1700000000000"}}"#
+                )
+            })
+            .collect();
+        page_of(fills)
+    }
+
+    #[tokio::test]
+    async fn export_streams_three_pages_as_csv_with_a_stable_header() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&export_fill_page(0, 100));
+        transport.push_json(&export_fill_page(100, 100));
+        transport.push_json(&export_fill_page(200, 50));
+        let client = client(&transport);
+
+        let mut out = Vec::new();
+        let mut cursor = TradeExportCursor::default();
+        let written = client
+            .export_trades_since(
+                &spot_converter(),
+                Some("BTC-USDT"),
+                Some(1_700_000_000_000),
+                &mut cursor,
+                &mut out,
+                ExportFormat::Csv,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(written, 250);
+        assert_eq!(cursor.rows_written, 250);
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 251, "header plus one row per fill");
+        assert_eq!(lines[0], crate::export::CSV_HEADER);
+        assert_eq!(lines[1], "BTC-USDT,t0,ord0,,100,0.01,buy,0.001,USDT,,1700000000000");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3, "one request per page, nothing buffered beyond it");
+        assert!(requests[0].url.contains("instId=BTC-USDT"), "{}", requests[0].url);
+        assert!(requests[0].url.contains("begin=1700000000000"), "{}", requests[0].url);
+        assert!(requests[2].url.contains("after=b199"), "{}", requests[2].url);
+    }
+
+    #[tokio::test]
+    async fn export_resumes_from_the_last_complete_page_after_a_failure() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&export_fill_page(0, 100));
+        transport.push_error(DriverError::Http("connect refused".to_string()));
+        let client = client(&transport);
+
+        let mut out = Vec::new();
+        let mut cursor = TradeExportCursor::default();
+        client
+            .export_trades_since(
+                &spot_converter(),
+                None,
+                None,
+                &mut cursor,
+                &mut out,
+                ExportFormat::JsonLines,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(cursor.rows_written, 100, "the complete first page survived");
+        assert_eq!(cursor.after.as_deref(), Some("b99"));
+
+        transport.push_json(&export_fill_page(100, 50));
+        let written = client
+            .export_trades_since(
+                &spot_converter(),
+                None,
+                None,
+                &mut cursor,
+                &mut out,
+                ExportFormat::JsonLines,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(written, 50);
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 150);
+        let first: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(first["tradeId"], "t0");
+        assert_eq!(first["amount"], "0.01");
+        let resume_url = transport.requests().last().unwrap().url.clone();
+        assert!(resume_url.contains("after=b99"), "{resume_url}");
+    }
+
+    /// Hook recording the unknown-instrument skip counter.
+    #[derive(Default)]
+    struct UnknownCountingHook {
+        unknown: std::sync::Mutex<Vec<(String, usize)>>,
+    }
+
+    impl crate::rest::MetricsHook for UnknownCountingHook {
+        fn on_request(&self, _metrics: &crate::rest::RequestMetrics) {}
+
+        fn on_unknown_instrument_records(&self, inst_id: &str, count: usize) {
+            self.unknown
+                .lock()
+                .unwrap()
+                .push((inst_id.to_string(), count));
+        }
+    }
+
+    #[tokio::test]
+    async fn all_trades_fan_out_per_instrument_type_and_merge() {
+        let transport = Arc::new(MockTransport::new());
+        // SPOT page: one configured fill plus one on a pair nobody
+        // configured — OKX returns the whole account's fills per type.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT","tradeId":"t1","ordId":"o1","fillPx":"43000","fillSz":"0.5","side":"buy","fee":"-0.1","feeCcy":"USDT","ts":"1700000000000"},
+                {"instId":"ETH-USDT","tradeId":"t9","ordId":"o9","fillPx":"2000","fillSz":"1","side":"buy","ts":"1700000000100"}
+            ]}"#,
+        );
+        // SWAP page: a contract fill and a duplicate of t1.
+        transport.push_json(
+            r#"{"code":"0","msg":"","data":[
+                {"instId":"BTC-USDT-SWAP","tradeId":"t2","ordId":"o2","fillPx":"43000","fillSz":"5","side":"sell","fee":"-0.2","feeCcy":"USDT","ts":"1699999999000"},
+                {"instId":"BTC-USDT","tradeId":"t1","ordId":"o1","fillPx":"43000","fillSz":"0.5","side":"buy","fee":"-0.1","feeCcy":"USDT","ts":"1700000000000"}
+            ]}"#,
+        );
+        let hook = Arc::new(UnknownCountingHook::default());
+        let mut client = client(&transport);
+        client.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+        let mut converter = spot_converter();
+        converter.insert(Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            contract_value: Some("0.01".parse().unwrap()),
+            ..instrument()
+        });
+
+        let trades = client
+            .fetch_all_trades_since(&converter, None)
+            .await
+            .unwrap();
+
+        // One request per represented type, alphabetical, on the history
+        // endpoint for an open-ended range.
+        let urls: Vec<String> = transport.requests().iter().map(|r| r.url.clone()).collect();
+        assert_eq!(urls.len(), 2, "{urls:?}");
+        assert!(urls[0].contains("/api/v5/trade/fills-history?instType=SPOT"), "{}", urls[0]);
+        assert!(urls[1].contains("/api/v5/trade/fills-history?instType=SWAP"), "{}", urls[1]);
+
+        // Merged oldest-first, duplicate t1 kept once, contract size
+        // multiplied out and the foreign pair skipped.
+        let ids: Vec<&str> = trades.iter().map(|t| t.trade_id.as_str()).collect();
+        assert_eq!(ids, vec!["t2", "t1"]);
+        assert_eq!(trades[0].amount, "0.05".parse().unwrap());
+        assert_eq!(trades[1].amount, "0.5".parse().unwrap());
+        assert_eq!(
+            *hook.unknown.lock().unwrap(),
+            vec![("ETH-USDT".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn an_interrupted_trades_fetch_resumes_with_only_the_missing_pages() {
+        // The uninterrupted 5-page walk, as the reference result.
+        let reference = Arc::new(MockTransport::new());
+        for start in [0, 100, 200, 300] {
+            reference.push_json(&export_fill_page(start, 100));
+        }
+        reference.push_json(&export_fill_page(400, 50));
+        let full = client(&reference)
+            .fetch_all_trades_since(&spot_converter(), None)
+            .await
+            .unwrap();
+        assert_eq!(full.len(), 450);
+
+        // The same walk dying on page 3 of 5.
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&export_fill_page(0, 100));
+        transport.push_json(&export_fill_page(100, 100));
+        // Two base URLs at two attempts each: the page has to fail on all
+        // four before the logical call gives up.
+        for _ in 0..4 {
+            transport.push_error(DriverError::Throttled("rate limit".to_string()));
+        }
+        let client = client(&transport);
+
+        let interrupted = client
+            .fetch_all_trades_resumable(&spot_converter(), None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(interrupted.partial.len(), 200, "pages 1-2 survived");
+        assert_eq!(interrupted.cursor.segment, 0);
+        assert_eq!(interrupted.cursor.after.as_deref(), Some("b199"));
+        assert!(matches!(interrupted.error, DriverError::Throttled(_)));
+
+        transport.push_json(&export_fill_page(200, 100));
+        transport.push_json(&export_fill_page(300, 100));
+        transport.push_json(&export_fill_page(400, 50));
+        let resumed = client
+            .fetch_all_trades_resumable(&spot_converter(), None, Some(interrupted.cursor))
+            .await
+            .unwrap();
+
+        // Pages 3-5 only: three more requests after the four failed
+        // attempts, starting past page 2.
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 9);
+        assert!(requests[6].url.contains("after=b199"), "{}", requests[6].url);
+
+        let mut merged: Vec<String> = interrupted
+            .partial
+            .iter()
+            .chain(&resumed)
+            .map(|t| t.trade_id.clone())
+            .collect();
+        merged.sort();
+        let mut expected: Vec<String> = full.iter().map(|t| t.trade_id.clone()).collect();
+        expected.sort();
+        assert_eq!(merged, expected, "no gap, no overlap");
+    }
+
+    #[tokio::test]
+    async fn the_retry_wrapper_resumes_transient_failures_itself() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&export_fill_page(0, 100));
+        for _ in 0..4 {
+            transport.push_error(DriverError::Http("connect refused".to_string()));
+        }
+        transport.push_json(&export_fill_page(100, 50));
+        let client = client(&transport);
+
+        let trades = client
+            .fetch_all_trades_retrying(&spot_converter(), None, 1)
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 150);
+
+        // A spent budget surfaces the underlying error.
+        for _ in 0..4 {
+            transport.push_error(DriverError::Http("connect refused".to_string()));
+        }
+        let err = client
+            .fetch_all_trades_retrying(&spot_converter(), None, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Http(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn all_trades_use_the_fills_endpoint_for_recent_ranges() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = client(&transport);
+
+        let since = chrono::Utc::now().timestamp_millis() as u64 - 60_000;
+        let trades = client
+            .fetch_all_trades_since(&spot_converter(), Some(since))
+            .await
+            .unwrap();
+
+        assert!(trades.is_empty());
+        let url = transport.requests()[0].url.clone();
+        assert!(url.contains("/api/v5/trade/fills?instType=SPOT"), "{url}");
+        assert!(url.contains(&format!("begin={since}")), "{url}");
+    }
+
+    #[tokio::test]
+    async fn historical_windows_backdate_internal_created_at() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(&page_of(vec![history_fill("t1", 1_700_000_000_000)]));
+        let client = client(&transport);
+
+        // Ten days back is beyond the fills window: a backfill, stamped
+        // with the exchange fill time.
+        let since = chrono::Utc::now().timestamp_millis() as u64 - 10 * DAY_MS;
+        let trades = client
+            .fetch_all_trades_since(&spot_converter(), Some(since))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades[0].internal_created_at,
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+        );
+
+        // A recent window keeps receive-time semantics.
+        transport.push_json(&page_of(vec![history_fill("t1", 1_700_000_000_000)]));
+        let before = chrono::Utc::now();
+        let since = chrono::Utc::now().timestamp_millis() as u64 - 60_000;
+        let trades = client
+            .fetch_all_trades_since(&spot_converter(), Some(since))
+            .await
+            .unwrap();
+        assert!(trades[0].internal_created_at >= before);
+    }
+
+    const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+    const HISTORY_NOW: u64 = 1_700_000_000_000;
+
+    fn history_fill(trade_id: &str, ts: u64) -> String {
+        format!(
+            r#"{{"instId":"BTC-USDT","tradeId":"{trade_id}","ordId":"ord1","billId":"b-{trade_id}","fillPx":"100","fillSz":"0.01","side":"buy","fee":"-0.001","feeCcy":"USDT","ts":"{ts}"}}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn trade_history_stitches_across_the_three_day_edge() {
+        let transport = Arc::new(MockTransport::new());
+        let fills_start = HISTORY_NOW - FILLS_WINDOW_MS;
+        // The boundary fill t2 shows up on both endpoints; the stitch must
+        // keep exactly one copy.
+        transport.push_json(&page_of(vec![
+            history_fill("t3", HISTORY_NOW - 1_000),
+            history_fill("t2", fills_start + 10_000),
+        ]));
+        transport.push_json(&page_of(vec![
+            history_fill("t2", fills_start + 10_000),
+            history_fill("t1", HISTORY_NOW - 4 * DAY_MS + 5_000),
+        ]));
+        let client = client(&transport);
+
+        let history = client
+            .fetch_trade_history_at(
+                &instrument(),
+                HISTORY_NOW - 4 * DAY_MS,
+                HISTORY_NOW,
+                HISTORY_NOW,
+            )
+            .await
+            .unwrap();
+
+        assert!(history.gaps.is_empty());
+        let ids: Vec<&str> = history.trades.iter().map(|t| t.trade_id.as_str()).collect();
+        assert_eq!(ids, vec!["t1", "t2", "t3"], "deduped and oldest first");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].url.contains("/api/v5/trade/fills?"), "{}", requests[0].url);
+        assert!(
+            requests[0].url.contains(&format!("begin={fills_start}")),
+            "{}",
+            requests[0].url
+        );
+        assert!(requests[1].url.contains("fills-history"), "{}", requests[1].url);
+        // The older endpoint reads past the boundary by the overlap.
+        assert!(
+            requests[1]
+                .url
+                .contains(&format!("end={}", fills_start + STITCH_OVERLAP_MS)),
+            "{}",
+            requests[1].url
+        );
+    }
+
+    #[tokio::test]
+    async fn trade_history_reads_trade_bills_beyond_the_three_month_edge() {
+        let transport = Arc::new(MockTransport::new());
+        let from = HISTORY_NOW - 100 * DAY_MS;
+        let to = HISTORY_NOW - 95 * DAY_MS;
+        transport.push_json(&page_of(vec![
+            // Close-long leg on our instrument, taker.
+            format!(
+                r#"{{"billId":"b1","ccy":"USDT","instId":"BTC-USDT-SWAP","tradeId":"bt1","ordId":"ord9","balChg":"-300","sz":"100","px":"43250.1","fee":"-0.43","execType":"T","type":"2","subType":"5","ts":"{}"}}"#,
+                from + 1_000
+            ),
+            // Another instrument; cut client-side.
+            format!(
+                r#"{{"billId":"b2","ccy":"USDT","instId":"ETH-USDT-SWAP","tradeId":"bt2","ordId":"ord10","balChg":"-30","sz":"10","px":"2300.1","fee":"-0.04","execType":"M","type":"2","subType":"1","ts":"{}"}}"#,
+                from + 2_000
+            ),
+        ]));
+        let client = client(&transport);
+
+        let swap = Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        };
+        let history = client
+            .fetch_trade_history_at(&swap, from, to, HISTORY_NOW)
+            .await
+            .unwrap();
+
+        assert!(history.gaps.is_empty());
+        assert_eq!(history.trades.len(), 1);
+        let trade = &history.trades[0];
+        assert_eq!(trade.trade_id, "bt1");
+        assert_eq!(trade.side, "sell", "close long is a sell");
+        assert_eq!(trade.amount, "1".parse::<Decimal>().unwrap());
+        assert_eq!(trade.fee, Some("0.43".parse().unwrap()));
+        assert_eq!(trade.liquidity.as_deref(), Some("T"));
+
+        // The whole range predates both fills endpoints: one archive call.
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0].url.contains("/api/v5/account/bills-archive"),
+            "{}",
+            requests[0].url
+        );
+        assert!(requests[0].url.contains("type=2"), "{}", requests[0].url);
+    }
+
+    #[tokio::test]
+    async fn trade_history_reports_the_unreachable_range_as_a_gap() {
+        let transport = Arc::new(MockTransport::new());
+        // The newer half is archive territory; the older half is beyond
+        // every endpoint.
+        transport.push_json(r#"{"code":"0","msg":"","data":[]}"#);
+        let client = client(&transport);
+
+        let from = HISTORY_NOW - 400 * DAY_MS;
+        let to = HISTORY_NOW - 360 * DAY_MS;
+        let history = client
+            .fetch_trade_history_at(&instrument(), from, to, HISTORY_NOW)
+            .await
+            .unwrap();
+
+        let archive_start = HISTORY_NOW - BILLS_ARCHIVE_WINDOW_MS;
+        assert_eq!(
+            history.gaps,
+            vec![crate::trades::HistoryGap {
+                from,
+                to: archive_start
+            }]
+        );
+        assert!(history.trades.is_empty());
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1, "only the covered half was fetched");
+        assert!(
+            requests[0].url.contains(&format!("begin={archive_start}")),
+            "{}",
+            requests[0].url
+        );
+    }
+
+    #[tokio::test]
+    async fn single_amend_round_trips() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(r#"{"code":"0","msg":"","data":[{"ordId":"ord1","sCode":"0","sMsg":""}]}"#);
+        let client = client(&transport);
+
+        let result = client
+            .rest_amend_order(OkexAmendOrderRequest {
+                inst_id: "BTC-USDT".to_string(),
+                order_id: "ord1".to_string(),
+                new_px: None,
+                new_sz: Some("0.5".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.s_code, "0");
+        assert!(transport.requests()[0].url.ends_with("/api/v5/trade/amend-order"));
+    }
+}
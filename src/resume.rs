@@ -0,0 +1,45 @@
+//! Resumable pagination for long multi-page fetches.
+//!
+//! A 40-page history fetch that dies on page 37 — rate limit exhaustion,
+//! a transient 502 — used to start from scratch on retry, re-spending the
+//! rate budget that likely caused the failure in the first place. The
+//! resumable fetchers instead fail with an [`InterruptedFetch`] carrying
+//! everything already fetched and a [`FetchCursor`] naming the exact page
+//! the next attempt should start from; callers (or a retry wrapper like
+//! [`crate::rest::OkexClient::fetch_all_trades_retrying`]) merge the
+//! partial results and resume. The streaming export keeps its own richer
+//! cursor ([`crate::export::TradeExportCursor`]); this covers the fetchers
+//! that buffer their result.
+
+use crate::errors::DriverError;
+
+/// Where a paginated fetch stands: which segment it was walking — an
+/// instrument type for the fills fan-out, a window for windowed fetches,
+/// always 0 for single-segment fetchers — and the last exchange `after`
+/// cursor inside it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchCursor {
+    pub segment: usize,
+    /// The cursor of the last fully received page; `None` at a segment
+    /// start.
+    pub after: Option<String>,
+}
+
+/// A paginated fetch that died mid-way: everything received before the
+/// failure plus the cursor to resume from. The failed page itself never
+/// arrived, so resuming from `cursor` refetches exactly that page onward
+/// and the merged result has no gap and no overlap.
+#[derive(Debug)]
+pub struct InterruptedFetch<T> {
+    pub partial: Vec<T>,
+    pub cursor: FetchCursor,
+    pub error: DriverError,
+}
+
+/// Callers that do not resume can `?` straight back to the underlying
+/// error, dropping the partial results.
+impl<T> From<Box<InterruptedFetch<T>>> for DriverError {
+    fn from(interrupted: Box<InterruptedFetch<T>>) -> Self {
+        interrupted.error
+    }
+}
@@ -0,0 +1,358 @@
+//! Automatic cancellation of orders resting past a TTL.
+//!
+//! A hung strategy leaves its quotes resting at prices nobody is updating;
+//! the exchange-side dead-man's switch ([`crate::cancel_all_after`]) only
+//! fires when the whole driver stops refreshing it, not when one strategy
+//! wedges while the process stays healthy. [`StaleOrderReaper`] is the
+//! finer net: a background task that periodically scans open orders and
+//! batch-cancels any whose exchange creation time exceeds the configured
+//! TTL, emitting [`crate::events::DriverEvent::StaleOrderCancelled`] per
+//! cancel so the wedged strategy is visible, not just flattened.
+//!
+//! Intentional long-lived orders are excluded per pair or per client-id
+//! prefix ([`StaleOrderReaperConfig`]); order age is judged strictly by
+//! the exchange's `cTime`, so an order whose creation time did not parse
+//! is left alone rather than guessed at.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::events::{DriverEvent, DriverEventSender};
+use crate::instruments::InstrumentConverter;
+use crate::orders::RawOrder;
+use crate::rest::OkexClient;
+
+/// Settings for the reaper; carried in
+/// [`crate::config::OkexConfig::stale_order_reaper`], `None` there — the
+/// default — disables it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleOrderReaperConfig {
+    /// Orders resting longer than this are cancelled.
+    pub ttl: Duration,
+    /// How often open orders are scanned.
+    pub scan_interval: Duration,
+    /// Pairs whose orders are never reaped (resting far-from-mid orders
+    /// placed on purpose).
+    pub exempt_pairs: Vec<String>,
+    /// Client order id prefixes that are never reaped; matched against
+    /// `clOrdId`, so orders without one are always eligible.
+    pub exempt_client_id_prefixes: Vec<String>,
+}
+
+/// Consumer-provided view of the currently open orders, so a consumer
+/// already mirroring the `orders` channel spares the reaper a REST fetch
+/// per scan. Returning `None` — "I don't have a trustworthy view right
+/// now", e.g. mid-reconnect — makes the scan fall back to
+/// [`OkexClient::fetch_open_raw_orders`].
+pub trait OpenOrderSource: Send + Sync {
+    fn open_orders(&self) -> Option<Vec<RawOrder>>;
+}
+
+/// Handle to the background scanner; dropping it (or calling
+/// [`Self::shutdown`]) stops the scans.
+pub struct StaleOrderReaper {
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl StaleOrderReaper {
+    /// Start scanning. The first scan runs immediately — the TTL has
+    /// usually long expired by the time an operator turns this on.
+    pub fn spawn(
+        client: Arc<OkexClient>,
+        converter: Arc<InstrumentConverter>,
+        config: StaleOrderReaperConfig,
+        source: Option<Arc<dyn OpenOrderSource>>,
+        events: Option<DriverEventSender>,
+    ) -> Self {
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            loop {
+                scan_once(&client, &converter, &config, source.as_deref(), events.as_ref()).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(config.scan_interval) => {}
+                    _ = &mut stop_rx => break,
+                }
+            }
+        });
+        Self {
+            stop: Some(stop_tx),
+            task,
+        }
+    }
+
+    /// Spawn the reaper when the config asks for one.
+    pub fn from_config(
+        client: &Arc<OkexClient>,
+        converter: &Arc<InstrumentConverter>,
+        source: Option<Arc<dyn OpenOrderSource>>,
+        events: Option<DriverEventSender>,
+    ) -> Option<Self> {
+        client.config().stale_order_reaper.clone().map(|config| {
+            Self::spawn(
+                Arc::clone(client),
+                Arc::clone(converter),
+                config,
+                source,
+                events,
+            )
+        })
+    }
+
+    /// Stop scanning and wait for an in-flight scan to finish. Open orders
+    /// are left as they are — unlike the dead-man's switch there is no
+    /// exchange-side state to disarm.
+    pub async fn shutdown(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.task).await;
+    }
+}
+
+/// One scan: collect stale, non-exempt orders and batch-cancel them.
+async fn scan_once(
+    client: &OkexClient,
+    converter: &InstrumentConverter,
+    config: &StaleOrderReaperConfig,
+    source: Option<&dyn OpenOrderSource>,
+    events: Option<&DriverEventSender>,
+) {
+    let orders = match source.and_then(OpenOrderSource::open_orders) {
+        Some(cached) => cached,
+        None => match client.fetch_open_raw_orders(converter, false).await {
+            Ok(fetched) => fetched,
+            Err(error) => {
+                // Skipping a scan is safe — the next one sees the same
+                // orders, only older.
+                log::warn!("stale-order scan could not fetch open orders: {error}");
+                return;
+            }
+        },
+    };
+
+    let now = chrono::Utc::now();
+    let stale: Vec<(RawOrder, Duration)> = orders
+        .into_iter()
+        .filter(|order| !is_exempt(order, config))
+        .filter_map(|order| {
+            let created = order.exchange_created_at?;
+            let age = (now - created).to_std().unwrap_or(Duration::ZERO);
+            (age > config.ttl).then_some((order, age))
+        })
+        .collect();
+    if stale.is_empty() {
+        return;
+    }
+
+    let pairs: Vec<(String, String)> = stale
+        .iter()
+        .map(|(order, _)| (order.inst_id.clone(), order.order_id.clone()))
+        .collect();
+    let outcome = match client.rest_cancel_orders(&pairs).await {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            log::warn!("stale-order cancel of {} orders failed: {error}", pairs.len());
+            return;
+        }
+    };
+    for error in &outcome.failed {
+        // Usually a race: the order filled or was cancelled between the
+        // scan and the batch. The next scan confirms either way.
+        log::warn!(
+            "stale-order cancel of {} rejected: {} {}",
+            error.order_id,
+            error.code,
+            error.message
+        );
+    }
+    for (order, age) in stale {
+        if !outcome.succeeded.contains(&order.order_id) {
+            continue;
+        }
+        log::info!(
+            "auto-cancelled stale order {} on {} after {age:?}",
+            order.order_id,
+            order.inst_id
+        );
+        if let Some(events) = events {
+            let _ = events.send(DriverEvent::StaleOrderCancelled {
+                inst_id: order.inst_id,
+                order_id: order.order_id,
+                client_order_id: order.client_order_id,
+                age,
+            });
+        }
+    }
+}
+
+fn is_exempt(order: &RawOrder, config: &StaleOrderReaperConfig) -> bool {
+    if config.exempt_pairs.contains(&order.inst_id) {
+        return true;
+    }
+    order.client_order_id.as_deref().is_some_and(|client_id| {
+        config
+            .exempt_client_id_prefixes
+            .iter()
+            .any(|prefix| client_id.starts_with(prefix))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OkexConfig;
+    use crate::instruments::Instrument;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::HttpTransport;
+
+    fn client(transport: &Arc<MockTransport>, config: OkexConfig) -> Arc<OkexClient> {
+        Arc::new(OkexClient::with_transport(
+            config,
+            Arc::clone(transport) as Arc<dyn HttpTransport>,
+        ))
+    }
+
+    fn converter() -> Arc<InstrumentConverter> {
+        let mut converter = InstrumentConverter::new();
+        for inst_id in ["BTC-USDT", "ETH-USDT"] {
+            converter.insert(Instrument {
+                inst_id: inst_id.to_string(),
+                tick_size: "0.1".parse().unwrap(),
+                lot_size: "0.0001".parse().unwrap(),
+                min_size: "0.0001".parse().unwrap(),
+                contract_value: None,
+                expiry_time: None,
+                margin: false,
+            });
+        }
+        Arc::new(converter)
+    }
+
+    fn pending_order(inst_id: &str, order_id: &str, client_order_id: &str, age: Duration) -> String {
+        let created = chrono::Utc::now() - chrono::Duration::from_std(age).unwrap();
+        format!(
+            r#"{{"instId":"{inst_id}","ordId":"{order_id}","clOrdId":"{client_order_id}","ordType":"limit","px":"100","sz":"1","side":"buy","state":"live","cTime":"{}"}}"#,
+            created.timestamp_millis()
+        )
+    }
+
+    fn reaper_config() -> StaleOrderReaperConfig {
+        StaleOrderReaperConfig {
+            ttl: Duration::from_secs(600),
+            scan_interval: Duration::from_secs(60),
+            exempt_pairs: vec!["ETH-USDT".to_string()],
+            exempt_client_id_prefixes: vec!["hold-".to_string()],
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stale_order_is_cancelled_and_reported_with_its_age() {
+        let transport = Arc::new(MockTransport::new());
+        // One stale order and one fresh one.
+        transport.push_json(&format!(
+            r#"{{"code":"0","msg":"","data":[{},{}]}}"#,
+            pending_order("BTC-USDT", "ord-stale", "q1", Duration::from_secs(3600)),
+            pending_order("BTC-USDT", "ord-fresh", "q2", Duration::from_secs(5)),
+        ));
+        transport
+            .push_json(r#"{"code":"0","msg":"","data":[{"ordId":"ord-stale","sCode":"0","sMsg":""}]}"#);
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let reaper = StaleOrderReaper::spawn(
+            client(&transport, OkexConfig::default()),
+            converter(),
+            reaper_config(),
+            None,
+            Some(events_tx),
+        );
+        tokio::task::yield_now().await;
+        reaper.shutdown().await;
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].url.contains("/api/v5/trade/orders-pending"));
+        assert!(requests[1].url.ends_with("/api/v5/trade/cancel-batch-orders"));
+        let body = requests[1].body.as_deref().unwrap();
+        assert!(body.contains("ord-stale"), "{body}");
+        assert!(!body.contains("ord-fresh"), "{body}");
+
+        match events_rx.try_recv().unwrap() {
+            DriverEvent::StaleOrderCancelled {
+                inst_id,
+                order_id,
+                client_order_id,
+                age,
+            } => {
+                assert_eq!(inst_id, "BTC-USDT");
+                assert_eq!(order_id, "ord-stale");
+                assert_eq!(client_order_id.as_deref(), Some("q1"));
+                assert!(age >= Duration::from_secs(3600), "{age:?}");
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        assert!(events_rx.try_recv().is_err(), "exactly one auto-cancel event");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exempt_orders_outlive_the_ttl_untouched() {
+        let transport = Arc::new(MockTransport::new());
+        // All three exceed the TTL, but one pair and one client-id prefix
+        // are exempt.
+        transport.push_json(&format!(
+            r#"{{"code":"0","msg":"","data":[{},{},{}]}}"#,
+            pending_order("ETH-USDT", "ord-pair", "q1", Duration::from_secs(3600)),
+            pending_order("BTC-USDT", "ord-held", "hold-7", Duration::from_secs(3600)),
+            pending_order("BTC-USDT", "ord-stale", "q2", Duration::from_secs(3600)),
+        ));
+        transport
+            .push_json(r#"{"code":"0","msg":"","data":[{"ordId":"ord-stale","sCode":"0","sMsg":""}]}"#);
+
+        let reaper = StaleOrderReaper::spawn(
+            client(&transport, OkexConfig::default()),
+            converter(),
+            reaper_config(),
+            None,
+            None,
+        );
+        tokio::task::yield_now().await;
+        reaper.shutdown().await;
+
+        let body = transport.requests()[1].body.as_deref().unwrap().to_string();
+        assert!(body.contains("ord-stale"), "{body}");
+        assert!(!body.contains("ord-pair"), "{body}");
+        assert!(!body.contains("ord-held"), "{body}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_cached_order_view_spares_the_rest_fetch() {
+        struct Cache;
+        impl OpenOrderSource for Cache {
+            fn open_orders(&self) -> Option<Vec<RawOrder>> {
+                // A consumer-side mirror with nothing resting.
+                Some(Vec::new())
+            }
+        }
+        let transport = Arc::new(MockTransport::new());
+
+        let reaper = StaleOrderReaper::spawn(
+            client(&transport, OkexConfig::default()),
+            converter(),
+            reaper_config(),
+            Some(Arc::new(Cache)),
+            None,
+        );
+        tokio::task::yield_now().await;
+        reaper.shutdown().await;
+
+        assert!(transport.requests().is_empty(), "no REST traffic expected");
+    }
+
+    #[test]
+    fn the_reaper_is_disabled_by_default() {
+        assert_eq!(OkexConfig::default().stale_order_reaper, None);
+    }
+}
@@ -0,0 +1,427 @@
+//! Normalized fill domain type.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use rust_decimal::Decimal;
+
+use crate::api_structs::TransactionResult;
+use crate::instruments::Instrument;
+
+/// A fill normalized to internal conventions: amounts in base units with
+/// contract sizes multiplied out, and fees expressed as positive costs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawTrade {
+    pub inst_id: String,
+    pub trade_id: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    /// Strategy level decoded from `client_order_id` when the id was
+    /// generated by [`crate::client_id::ClientIdGenerator`]; `None` for
+    /// foreign ids.
+    pub level_id: Option<String>,
+    /// Empty for some fill types (e.g. exercised options).
+    pub price: Option<Decimal>,
+    /// Fill amount in base units; contract fills are multiplied by `ctVal`.
+    pub amount: Decimal,
+    pub side: String,
+    /// `"T"` taker or `"M"` maker as the exchange reports it; `None` when
+    /// the source record does not say.
+    pub liquidity: Option<String>,
+    /// Fee as a cost: positive when charged, negative for rebates. OKX
+    /// reports charged fees as negative, so the sign is flipped here.
+    pub fee: Option<Decimal>,
+    pub fee_currency: Option<String>,
+    /// Realized PnL of this execution as OKX states it (`fillPnl`), so
+    /// downstream does not recompute it and drift from the statements.
+    /// Spot fills carry no PnL concept and are always `None`; OKX reports
+    /// `"0"` there, which is dropped rather than passed through as a value.
+    pub realized_pnl: Option<Decimal>,
+    /// Fill time, milliseconds, as reported.
+    pub timestamp: String,
+    /// Parsed fill time; `None` when the exchange timestamp does not
+    /// parse — never a fallback clock read.
+    pub exchange_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// When this driver ingested the record. Historical backfill paths
+    /// re-stamp it from the exchange fill time (see [`Self::backdated`]),
+    /// so year-old trades do not read as created "now" in downstream
+    /// time-bucketing.
+    pub internal_created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl RawTrade {
+    /// Normalize one `/api/v5/trade/fills` entry against its instrument,
+    /// received at `received_at`.
+    pub fn from_transaction(
+        fill: &TransactionResult,
+        instrument: &Instrument,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let contract_value = instrument.contract_value.unwrap_or(Decimal::ONE);
+        Self {
+            inst_id: fill.inst_id.clone(),
+            trade_id: fill.trade_id.clone(),
+            order_id: fill.order_id.clone(),
+            client_order_id: fill.client_order_id.clone(),
+            level_id: decode_level_id(fill.client_order_id.as_deref()),
+            price: fill.price,
+            amount: fill.size * contract_value,
+            side: fill.side.clone(),
+            liquidity: fill.exec_type.clone().filter(|t| !t.is_empty()),
+            fee: fill.fee.map(|fee| -fee),
+            fee_currency: fill.fee_currency.clone(),
+            realized_pnl: if instrument.inst_type() == "SPOT" {
+                None
+            } else {
+                fill.fill_pnl
+            },
+            timestamp: fill.timestamp.clone(),
+            exchange_timestamp: crate::orders::parse_exchange_millis(&fill.timestamp),
+            internal_created_at: received_at,
+        }
+    }
+
+    /// Normalize one trade bill (`type` 2) against its instrument. Bills
+    /// are the only execution record older than the fills history; the
+    /// execution fields (`instId`, `tradeId`, `sz`, `subType`) are
+    /// populated on trade bills only, so any other bill — or a trade bill
+    /// missing them — yields `None`. Bills do not attribute realized PnL
+    /// per execution, so it stays `None`.
+    pub fn from_trade_bill(
+        bill: &crate::api_structs::OkexBillResponse,
+        instrument: &Instrument,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) -> Option<Self> {
+        if bill.bill_type != TRADE_BILL_TYPE {
+            return None;
+        }
+        let side = trade_bill_side(bill.sub_type.as_deref()?)?;
+        let inst_id = bill.inst_id.clone().filter(|id| !id.is_empty())?;
+        let trade_id = bill.trade_id.clone().filter(|id| !id.is_empty())?;
+        let size = bill.size?;
+        let contract_value = instrument.contract_value.unwrap_or(Decimal::ONE);
+        let client_order_id = bill.client_order_id.clone().filter(|id| !id.is_empty());
+        Some(Self {
+            inst_id,
+            trade_id,
+            order_id: bill.order_id.clone().unwrap_or_default(),
+            level_id: decode_level_id(client_order_id.as_deref()),
+            client_order_id,
+            price: bill.price,
+            amount: size * contract_value,
+            side: side.to_string(),
+            liquidity: bill.exec_type.clone().filter(|t| !t.is_empty()),
+            fee: bill.fee.map(|fee| -fee),
+            // Bills are single-currency balance changes; the fee is
+            // charged in the bill's own currency.
+            fee_currency: bill.fee.is_some().then(|| bill.ccy.clone()),
+            realized_pnl: None,
+            timestamp: bill.timestamp.clone(),
+            exchange_timestamp: crate::orders::parse_exchange_millis(&bill.timestamp),
+            internal_created_at: received_at,
+        })
+    }
+
+    /// Re-stamp `internal_created_at` from the exchange fill time, applied
+    /// by historical fetch paths where the receive time would timestamp a
+    /// backfill as "now". A record whose exchange timestamp does not parse
+    /// keeps the receive time rather than inventing one.
+    pub(crate) fn backdated(mut self) -> Self {
+        if let Some(filled_at) = self.exchange_timestamp {
+            self.internal_created_at = filled_at;
+        }
+        self
+    }
+}
+
+/// Bill `type` marking a trade leg.
+const TRADE_BILL_TYPE: &str = "2";
+
+/// Map a trade bill's `subType` to a fill side: spot legs are buy/sell,
+/// contract legs split by open/close and long/short.
+fn trade_bill_side(sub_type: &str) -> Option<&'static str> {
+    match sub_type {
+        // Buy, open long, close short.
+        "1" | "3" | "6" => Some("buy"),
+        // Sell, open short, close long.
+        "2" | "4" | "5" => Some("sell"),
+        _ => None,
+    }
+}
+
+/// Strategy level from a generated client order id; `None` for foreign
+/// or absent ids.
+pub(crate) fn decode_level_id(client_order_id: Option<&str>) -> Option<String> {
+    client_order_id
+        .and_then(crate::client_id::ClientOrderId::from_exchange)
+        .and_then(|id| crate::client_id::parse_client_id(&id))
+        .map(|parts| parts.level_id)
+}
+
+/// A millisecond sub-range `[from, to)` of a requested trade-history
+/// fetch that no endpoint could cover (see
+/// [`crate::rest::OkexClient::fetch_trade_history`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryGap {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// Stitched trade history: every execution the endpoints could produce
+/// for the requested range, oldest first, plus the sub-ranges none of
+/// them covers. A non-empty `gaps` means fewer trades, not an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeHistory {
+    pub trades: Vec<RawTrade>,
+    pub gaps: Vec<HistoryGap>,
+}
+
+/// How long a delivered trade id stays remembered. OKX replays fills for
+/// at most a session's reconnect horizon, so a day is generous.
+const DEDUP_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+/// Hard cap on remembered ids, so a fill storm cannot grow the set without
+/// bound before the time window catches up.
+const DEDUP_CAPACITY: usize = 100_000;
+
+/// Bounded, time-windowed set of delivered trade ids. The REST
+/// reconciliation fetch and the WS fills stream both run their trades
+/// through one shared instance, so downstream sees each fill exactly once
+/// no matter which path wins the race.
+#[derive(Default)]
+pub struct TradeDeduper {
+    inner: Mutex<DeduperInner>,
+}
+
+#[derive(Default)]
+struct DeduperInner {
+    /// Insertion order with the delivery time, oldest first, for eviction.
+    order: VecDeque<(String, chrono::DateTime<chrono::Utc>)>,
+    ids: HashSet<String>,
+}
+
+impl TradeDeduper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a trade id delivered at `seen_at`; `false` when the id was
+    /// already delivered inside the window.
+    pub fn insert(&self, trade_id: &str, seen_at: chrono::DateTime<chrono::Utc>) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        while let Some((_, oldest)) = inner.order.front() {
+            if seen_at - *oldest <= DEDUP_WINDOW && inner.order.len() < DEDUP_CAPACITY {
+                break;
+            }
+            let (evicted, _) = inner.order.pop_front().unwrap();
+            inner.ids.remove(&evicted);
+        }
+        if !inner.ids.insert(trade_id.to_string()) {
+            return false;
+        }
+        inner.order.push_back((trade_id.to_string(), seen_at));
+        true
+    }
+
+    /// Whether a trade id has already been delivered inside the window.
+    pub fn contains(&self, trade_id: &str) -> bool {
+        self.inner.lock().unwrap().ids.contains(trade_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(size: &str, fee: &str) -> TransactionResult {
+        serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT-SWAP","tradeId":"t1","ordId":"ord1","fillPx":"43250.1","fillSz":"{size}","side":"buy","fee":"{fee}","feeCcy":"USDT","ts":"1700000000000"}}"#
+        ))
+        .unwrap()
+    }
+
+    fn received() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_731_000_000_000).unwrap()
+    }
+
+    fn swap_instrument() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: Some("0.01".parse().unwrap()),
+            expiry_time: None,
+            margin: false,
+        }
+    }
+
+    #[test]
+    fn contract_fill_converts_to_base_amount() {
+        let trade = RawTrade::from_transaction(&fill("100", "-0.5"), &swap_instrument(), received());
+        assert_eq!(trade.amount, "1".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn charged_fee_becomes_a_positive_cost() {
+        let trade = RawTrade::from_transaction(&fill("100", "-0.5"), &swap_instrument(), received());
+        assert_eq!(trade.fee, Some("0.5".parse().unwrap()));
+        // Rebates stay negative costs.
+        let rebate = RawTrade::from_transaction(&fill("100", "0.1"), &swap_instrument(), received());
+        assert_eq!(rebate.fee, Some("-0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn spot_fill_keeps_its_size() {
+        let mut instrument = swap_instrument();
+        instrument.contract_value = None;
+        let trade = RawTrade::from_transaction(&fill("0.25", "-0.1"), &instrument, received());
+        assert_eq!(trade.amount, "0.25".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn closing_swap_fill_carries_the_stated_pnl() {
+        let fill: TransactionResult = serde_json::from_str(
+            r#"{"instId":"BTC-USDT-SWAP","tradeId":"t1","ordId":"ord1","fillPx":"43500.0","fillSz":"100","side":"sell","fee":"-0.43","feeCcy":"USDT","fillPnl":"250.5","ts":"1700000000000"}"#,
+        )
+        .unwrap();
+        let trade = RawTrade::from_transaction(&fill, &swap_instrument(), received());
+        assert_eq!(trade.realized_pnl, Some("250.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn spot_fill_has_no_pnl_even_when_okx_reports_zero() {
+        let fill: TransactionResult = serde_json::from_str(
+            r#"{"instId":"BTC-USDT","tradeId":"t1","ordId":"ord1","fillPx":"43250.1","fillSz":"0.25","side":"buy","fee":"-0.1","feeCcy":"USDT","fillPnl":"0","ts":"1700000000000"}"#,
+        )
+        .unwrap();
+        let mut instrument = swap_instrument();
+        instrument.inst_id = "BTC-USDT".to_string();
+        instrument.contract_value = None;
+        let trade = RawTrade::from_transaction(&fill, &instrument, received());
+        assert_eq!(trade.realized_pnl, None);
+    }
+
+    #[test]
+    fn fill_level_id_decodes_from_generated_client_ids() {
+        let generator = crate::client_id::ClientIdGenerator::new();
+        let id = generator.generate_client_id(&crate::orders::OrderRequest {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            side: crate::orders::Side::Buy,
+            order_type: crate::orders::OrderType::Limit,
+            price: Some("43250.1".parse().unwrap()),
+            amount: "1".parse().unwrap(),
+            size_denomination: crate::orders::SizeDenomination::Base,
+            position_intent: None,
+            client_order_id: None,
+            metadata: Some(crate::client_id::OrderMetadata {
+                strategy: "grid".to_string(),
+                level_id: "L17".to_string(),
+            }),
+        });
+        let fill: TransactionResult = serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT-SWAP","tradeId":"t1","ordId":"ord1","clOrdId":"{id}","fillPx":"43250.1","fillSz":"100","side":"buy","fee":"-0.5","feeCcy":"USDT","ts":"1700000000000"}}"#
+        ))
+        .unwrap();
+
+        let trade = RawTrade::from_transaction(&fill, &swap_instrument(), received());
+        assert_eq!(trade.level_id.as_deref(), Some("L17"));
+
+        // Foreign ids carry no decodable metadata.
+        let foreign = RawTrade::from_transaction(&fill_with_id("mm-42"), &swap_instrument(), received());
+        assert_eq!(foreign.level_id, None);
+    }
+
+    fn fill_with_id(cl_ord_id: &str) -> TransactionResult {
+        serde_json::from_str(&format!(
+            r#"{{"instId":"BTC-USDT-SWAP","tradeId":"t2","ordId":"ord2","clOrdId":"{cl_ord_id}","fillPx":"43250.1","fillSz":"100","side":"buy","fee":"-0.5","feeCcy":"USDT","ts":"1700000000000"}}"#
+        ))
+        .unwrap()
+    }
+
+    fn trade_bill(bill_type: &str, sub_type: &str) -> crate::api_structs::OkexBillResponse {
+        serde_json::from_str(&format!(
+            r#"{{"billId":"b1","ccy":"USDT","instId":"BTC-USDT-SWAP","tradeId":"bt1","ordId":"ord1","balChg":"-300","sz":"100","px":"43250.1","fee":"-0.43","execType":"T","type":"{bill_type}","subType":"{sub_type}","ts":"1700000000000"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn trade_bill_converts_like_a_fill() {
+        let trade = RawTrade::from_trade_bill(&trade_bill("2", "1"), &swap_instrument(), received()).unwrap();
+        assert_eq!(trade.trade_id, "bt1");
+        assert_eq!(trade.amount, "1".parse::<Decimal>().unwrap());
+        assert_eq!(trade.fee, Some("0.43".parse().unwrap()));
+        assert_eq!(trade.fee_currency.as_deref(), Some("USDT"));
+        assert_eq!(trade.liquidity.as_deref(), Some("T"));
+        assert_eq!(trade.realized_pnl, None, "bills attribute no per-fill PnL");
+    }
+
+    #[test]
+    fn trade_bill_sides_follow_the_sub_type() {
+        let cases = [
+            ("1", "buy"),
+            ("2", "sell"),
+            ("3", "buy"),  // open long
+            ("4", "sell"), // open short
+            ("5", "sell"), // close long
+            ("6", "buy"),  // close short
+        ];
+        for (sub_type, side) in cases {
+            let trade =
+                RawTrade::from_trade_bill(&trade_bill("2", sub_type), &swap_instrument(), received()).unwrap();
+            assert_eq!(trade.side, side, "subType {sub_type}");
+        }
+    }
+
+    #[test]
+    fn only_trade_bills_convert() {
+        // A funding bill with otherwise plausible fields stays a bill.
+        assert!(RawTrade::from_trade_bill(&trade_bill("8", "173"), &swap_instrument(), received()).is_none());
+    }
+
+    #[test]
+    fn backdating_swaps_receive_time_for_the_exchange_fill_time() {
+        let trade = RawTrade::from_transaction(&fill("100", "-0.5"), &swap_instrument(), received());
+        assert_eq!(trade.internal_created_at, received());
+        assert_eq!(
+            trade.backdated().internal_created_at,
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+        );
+
+        // An unparseable exchange timestamp keeps the receive time rather
+        // than inventing one.
+        let odd: TransactionResult = serde_json::from_str(
+            r#"{"instId":"BTC-USDT-SWAP","tradeId":"t1","ordId":"ord1","fillPx":"43250.1","fillSz":"100","side":"buy","ts":""}"#,
+        )
+        .unwrap();
+        let trade = RawTrade::from_transaction(&odd, &swap_instrument(), received());
+        assert_eq!(trade.backdated().internal_created_at, received());
+    }
+
+    #[test]
+    fn deduper_forgets_ids_that_fall_out_of_the_window() {
+        let deduper = TradeDeduper::new();
+        let t0 = chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+
+        assert!(deduper.insert("t1", t0));
+        assert!(!deduper.insert("t1", t0 + chrono::Duration::hours(1)));
+        assert!(deduper.contains("t1"));
+
+        // A day and change later the id has aged out and is new again.
+        assert!(deduper.insert("t1", t0 + chrono::Duration::hours(25)));
+    }
+
+    #[test]
+    fn deduper_evicts_oldest_ids_at_capacity() {
+        let deduper = TradeDeduper::new();
+        let t0 = chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        for n in 0..DEDUP_CAPACITY {
+            assert!(deduper.insert(&format!("t{n}"), t0));
+        }
+        assert!(deduper.contains("t0"));
+        // One over capacity pushes out the oldest id, and only that one.
+        assert!(deduper.insert("overflow", t0));
+        assert!(!deduper.contains("t0"));
+        assert!(deduper.contains("t1"));
+    }
+}
@@ -0,0 +1,408 @@
+//! HTTP transport abstraction.
+//!
+//! The REST client talks to OKX through [`HttpTransport`] so that unit tests
+//! can script exchange responses without a network.
+
+use async_trait::async_trait;
+use futures_lite::io::AsyncReadExt;
+use isahc::config::Configurable;
+
+use crate::errors::{DriverError, DriverResult};
+
+/// Default cap on buffered response bodies. The biggest legitimate OKX
+/// pages are low single-digit MB; anything larger is a misbehaving proxy.
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// HTTP method subset used by the OKX v5 API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+/// A fully prepared request, URL and signing headers included.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// Raw response as delivered by the transport.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn execute(&self, request: HttpRequest) -> DriverResult<HttpResponse>;
+}
+
+/// Production transport backed by a shared isahc client.
+pub struct IsahcTransport {
+    client: isahc::HttpClient,
+    max_response_bytes: usize,
+}
+
+impl IsahcTransport {
+    /// `enable_compression` makes the client send `Accept-Encoding: gzip`
+    /// and decompress responses before they reach the caller. Request bodies
+    /// are always sent as plaintext, so signatures stay valid either way.
+    pub fn new(enable_compression: bool) -> DriverResult<Self> {
+        let client = isahc::HttpClient::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .automatic_decompression(enable_compression)
+            .build()
+            .map_err(|e| DriverError::Http(format!("failed to build http client: {e}")))?;
+        Ok(Self {
+            client,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        })
+    }
+
+    /// Cap buffered response bodies at `limit` bytes: reading aborts at
+    /// the cap instead of buffering whatever a misbehaving peer sends.
+    /// See [`crate::config::OkexConfig::max_response_bytes`].
+    pub fn with_max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
+}
+
+#[async_trait]
+impl HttpTransport for IsahcTransport {
+    async fn execute(&self, request: HttpRequest) -> DriverResult<HttpResponse> {
+        let url = request.url.clone();
+        let mut builder = isahc::http::Request::builder()
+            .method(request.method.as_str())
+            .uri(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let req = builder
+            .body(request.body.unwrap_or_default())
+            .map_err(|e| DriverError::Http(format!("invalid request: {e}")))?;
+
+        let response = self
+            .client
+            .send_async(req)
+            .await
+            .map_err(|e| DriverError::Http(format!("request failed: {e}")))?;
+
+        let status = response.status().as_u16();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.as_str().to_string(), v.to_string()))
+            })
+            .collect();
+
+        // A declared length over the cap fails before a single body byte
+        // is read; chunked responses are caught by the capped read below,
+        // which stops at the limit instead of buffering the rest.
+        let declared = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.parse::<usize>().ok());
+        if declared.is_some_and(|length| length > self.max_response_bytes) {
+            return Err(DriverError::ResponseTooLarge {
+                endpoint: url,
+                limit_bytes: self.max_response_bytes,
+            });
+        }
+        let mut raw = Vec::new();
+        response
+            .into_body()
+            .take(self.max_response_bytes as u64 + 1)
+            .read_to_end(&mut raw)
+            .await
+            .map_err(|e| DriverError::Http(format!("failed to read body: {e}")))?;
+        if raw.len() > self.max_response_bytes {
+            return Err(DriverError::ResponseTooLarge {
+                endpoint: url,
+                limit_bytes: self.max_response_bytes,
+            });
+        }
+        let body = String::from_utf8_lossy(&raw).into_owned();
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    //! Scripted transport for unit tests.
+
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Returns scripted responses in order and records every request.
+    #[derive(Default)]
+    pub(crate) struct MockTransport {
+        responses: Mutex<VecDeque<DriverResult<HttpResponse>>>,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn push_json(&self, body: &str) {
+            self.push_response(HttpResponse {
+                status: 200,
+                headers: vec![],
+                body: body.to_string(),
+            });
+        }
+
+        pub(crate) fn push_response(&self, response: HttpResponse) {
+            self.responses.lock().unwrap().push_back(Ok(response));
+        }
+
+        pub(crate) fn push_error(&self, error: DriverError) {
+            self.responses.lock().unwrap().push_back(Err(error));
+        }
+
+        pub(crate) fn requests(&self) -> Vec<HttpRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// Minimal single-threaded HTTP stub for integration-style tests.
+    ///
+    /// Serves the given bodies as `200 OK` JSON responses, one per
+    /// connection, then exits. Returns the base URL to point a client at.
+    pub(crate) fn spawn_stub_server(bodies: Vec<String>) -> (String, std::thread::JoinHandle<()>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            for body in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 8192];
+                let _ = stream.read(&mut buf);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (format!("http://{addr}"), handle)
+    }
+
+    /// [`spawn_stub_server`] with an artificial per-response delay, for
+    /// latency-sensitive tests.
+    pub(crate) fn spawn_delayed_stub_server(
+        bodies: Vec<String>,
+        delay: std::time::Duration,
+    ) -> (String, std::thread::JoinHandle<()>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            for body in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 8192];
+                let _ = stream.read(&mut buf);
+                std::thread::sleep(delay);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (format!("http://{addr}"), handle)
+    }
+
+    /// Base URL of a port that actively refuses connections.
+    pub(crate) fn refused_base_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{addr}")
+    }
+
+    #[async_trait]
+    impl HttpTransport for MockTransport {
+        async fn execute(&self, request: HttpRequest) -> DriverResult<HttpResponse> {
+            self.requests.lock().unwrap().push(request);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| {
+                    Err(DriverError::Generic(
+                        "mock transport: no scripted response left".to_string(),
+                    ))
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    /// Serves one gzip-encoded response and reports the raw request head.
+    fn spawn_gzip_server(body: &str) -> (String, mpsc::Receiver<String>) {
+        let compressed = {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 8192];
+            let n = stream.read(&mut buf).unwrap();
+            tx.send(String::from_utf8_lossy(&buf[..n]).to_string()).unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                compressed.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&compressed).unwrap();
+        });
+        (format!("http://{addr}"), rx)
+    }
+
+    #[tokio::test]
+    async fn isahc_transport_requests_and_decompresses_gzip() {
+        let body = r#"{"code":"0","msg":"","data":[{"ts":"1700000000000"}]}"#;
+        let (url, rx) = spawn_gzip_server(body);
+        let transport = IsahcTransport::new(true).unwrap();
+
+        let response = transport
+            .execute(HttpRequest {
+                method: Method::Get,
+                url: format!("{url}/api/v5/public/time"),
+                headers: vec![],
+                body: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, body);
+        let request_head = rx.recv().unwrap().to_ascii_lowercase();
+        assert!(
+            request_head.contains("accept-encoding:") && request_head.contains("gzip"),
+            "request did not advertise gzip: {request_head}"
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_fail_with_a_typed_error_instead_of_buffering() {
+        // Declared length over the cap: rejected before the body is read.
+        let big = "x".repeat(64 * 1024);
+        let (url, _handle) = super::mock::spawn_stub_server(vec![big.clone()]);
+        let transport = IsahcTransport::new(false).unwrap().with_max_response_bytes(1024);
+        let err = transport
+            .execute(HttpRequest {
+                method: Method::Get,
+                url: format!("{url}/api/v5/public/time"),
+                headers: vec![],
+                body: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DriverError::ResponseTooLarge { limit_bytes: 1024, .. }),
+            "got: {err}"
+        );
+
+        // No declared length (read-until-close): the capped read aborts at
+        // the limit instead of buffering the rest.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 8192];
+            let _ = stream.read(&mut buf);
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n")
+                .unwrap();
+            stream.write_all(big.as_bytes()).unwrap();
+        });
+        let err = transport
+            .execute(HttpRequest {
+                method: Method::Get,
+                url: format!("http://{addr}/api/v5/public/time"),
+                headers: vec![],
+                body: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DriverError::ResponseTooLarge { .. }),
+            "got: {err}"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "manual benchmark; run with --ignored --nocapture"]
+    async fn bench_fetch_and_parse_compressed_vs_plain() {
+        // ~200KB of realistic-looking trade history JSON.
+        let row = r#"{"instId":"BTC-USDT","px":"43250.1","sz":"0.0012","ts":"1700000000000"}"#;
+        let body = format!(
+            r#"{{"code":"0","msg":"","data":[{}]}}"#,
+            vec![row; 2500].join(",")
+        );
+
+        for enable_compression in [false, true] {
+            let started = std::time::Instant::now();
+            let (url, _rx) = spawn_gzip_server(&body);
+            let transport = IsahcTransport::new(enable_compression).unwrap();
+            let response = transport
+                .execute(HttpRequest {
+                    method: Method::Get,
+                    url: format!("{url}/api/v5/trade/fills-history"),
+                    headers: vec![],
+                    body: None,
+                })
+                .await
+                .unwrap();
+            let parsed: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+            assert_eq!(parsed["data"].as_array().unwrap().len(), 2500);
+            println!(
+                "compression={enable_compression}: fetch+parse took {:?}",
+                started.elapsed()
+            );
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Runtime-toggleable wire logging for incident debugging.
+//!
+//! Full frame and body logging is too noisy — and too PII-laden — to run
+//! permanently, so the level lives behind an atomic that the REST call
+//! path and the WS loops consult per request or frame; `Off` costs one
+//! relaxed load. Credential headers and signatures are redacted at every
+//! level: no mode writes them out. An elevated level can carry an
+//! automatic revert window so a debugging session cannot be left on by
+//! accident.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// How much of the wire traffic gets logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireLogLevel {
+    /// Nothing; the per-request cost is one atomic read.
+    #[default]
+    Off = 0,
+    /// REST request lines with headers and WS frame summaries; no bodies.
+    Headers = 1,
+    /// Bodies and full frames too, credentials still redacted.
+    Full = 2,
+}
+
+impl WireLogLevel {
+    fn from_u8(raw: u8) -> Self {
+        match raw {
+            1 => Self::Headers,
+            2 => Self::Full,
+            _ => Self::Off,
+        }
+    }
+}
+
+/// Header names (lowercase) whose values never reach the log.
+const REDACTED_HEADERS: [&str; 3] = ["ok-access-key", "ok-access-sign", "ok-access-passphrase"];
+/// JSON keys whose values never reach the log (WS login frames).
+const REDACTED_KEYS: [&str; 3] = ["apiKey", "passphrase", "sign"];
+const REDACTED: &str = "[redacted]";
+
+/// Destination for formatted wire-log lines.
+pub type WireLogSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// The toggle plus the formatting/redaction it gates. One instance is
+/// shared between the REST client and the WS loops (see
+/// [`crate::rest::OkexClient::wire_logger`]) so a single call elevates
+/// both protocols.
+pub struct WireLogger {
+    level: AtomicU8,
+    /// When an elevated level reverts to `Off` on its own; `None` keeps
+    /// the level until the next explicit set.
+    revert_at: Mutex<Option<Instant>>,
+    /// Where formatted lines go; defaults to `log::info!` under the
+    /// `wire` target. Swappable so tests (or embedders) can capture the
+    /// output.
+    sink: RwLock<WireLogSink>,
+}
+
+impl Default for WireLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WireLogger {
+    pub fn new() -> Self {
+        Self {
+            level: AtomicU8::new(WireLogLevel::Off as u8),
+            revert_at: Mutex::new(None),
+            sink: RwLock::new(Arc::new(|line: &str| log::info!(target: "wire", "{line}"))),
+        }
+    }
+
+    /// Set the level until the next call; clears any revert window.
+    pub fn set_level(&self, level: WireLogLevel) {
+        *self.revert_at.lock().unwrap() = None;
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Set the level and revert to `Off` automatically once `window`
+    /// elapses. The revert is lazy — the next level check past the
+    /// deadline flips it — so no timer task runs.
+    pub fn set_level_for(&self, level: WireLogLevel, window: Duration) {
+        *self.revert_at.lock().unwrap() = Some(Instant::now() + window);
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Current level; one relaxed load when logging is off.
+    pub fn level(&self) -> WireLogLevel {
+        let level = WireLogLevel::from_u8(self.level.load(Ordering::Relaxed));
+        if level == WireLogLevel::Off {
+            return level;
+        }
+        let mut revert_at = self.revert_at.lock().unwrap();
+        if revert_at.is_some_and(|at| Instant::now() >= at) {
+            *revert_at = None;
+            self.level.store(WireLogLevel::Off as u8, Ordering::Relaxed);
+            return WireLogLevel::Off;
+        }
+        level
+    }
+
+    /// Redirect formatted lines away from the `log` facade.
+    pub fn set_sink(&self, sink: WireLogSink) {
+        *self.sink.write().unwrap() = sink;
+    }
+
+    fn emit(&self, line: String) {
+        (self.sink.read().unwrap())(&line);
+    }
+
+    /// Log one outgoing REST request at the current level.
+    pub fn log_rest_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(String, String)],
+        body: Option<&str>,
+    ) {
+        match self.level() {
+            WireLogLevel::Off => {}
+            WireLogLevel::Headers => {
+                self.emit(format!("-> {method} {url} {}", format_headers(headers)));
+            }
+            WireLogLevel::Full => self.emit(format!(
+                "-> {method} {url} {} body={}",
+                format_headers(headers),
+                body.unwrap_or("-")
+            )),
+        }
+    }
+
+    /// Log one REST response at the current level.
+    pub fn log_rest_response(&self, path: &str, status: u16, body: &str) {
+        match self.level() {
+            WireLogLevel::Off => {}
+            WireLogLevel::Headers => self.emit(format!("<- {status} {path}")),
+            WireLogLevel::Full => self.emit(format!("<- {status} {path} body={body}")),
+        }
+    }
+
+    /// Log one WS frame; `direction` is `"->"` or `"<-"`.
+    pub fn log_ws_frame(&self, direction: &str, frame: &str) {
+        match self.level() {
+            WireLogLevel::Off => {}
+            WireLogLevel::Headers => {
+                self.emit(format!("{direction} ws frame ({} bytes)", frame.len()));
+            }
+            WireLogLevel::Full => self.emit(format!("{direction} ws {}", redact_frame(frame))),
+        }
+    }
+}
+
+/// Render headers with credential values redacted.
+fn format_headers(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if REDACTED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                format!("{name}={REDACTED}")
+            } else {
+                format!("{name}={value}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Redact credential-bearing JSON keys anywhere in a frame. A frame that
+/// fails to parse is summarized rather than risking a partially logged
+/// login frame.
+pub(crate) fn redact_frame(frame: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(frame) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<unparseable frame, {} bytes>", frame.len()),
+    }
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, entry) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&key.as_str()) {
+                    *entry = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(entry);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capturing_logger() -> (Arc<WireLogger>, Arc<Mutex<Vec<String>>>) {
+        let logger = Arc::new(WireLogger::new());
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink_lines = Arc::clone(&lines);
+        logger.set_sink(Arc::new(move |line: &str| {
+            sink_lines.lock().unwrap().push(line.to_string());
+        }));
+        (logger, lines)
+    }
+
+    fn signed_headers() -> Vec<(String, String)> {
+        vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("OK-ACCESS-KEY".to_string(), "key-123".to_string()),
+            ("OK-ACCESS-SIGN".to_string(), "c2VjcmV0c2ln".to_string()),
+            ("OK-ACCESS-PASSPHRASE".to_string(), "hunter2".to_string()),
+        ]
+    }
+
+    #[test]
+    fn full_mode_redacts_the_sign_header_and_passphrase() {
+        let (logger, lines) = capturing_logger();
+        logger.set_level(WireLogLevel::Full);
+
+        logger.log_rest_request(
+            "POST",
+            "https://www.okx.com/api/v5/trade/order",
+            &signed_headers(),
+            Some(r#"{"instId":"BTC-USDT"}"#),
+        );
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("OK-ACCESS-SIGN=[redacted]"), "{}", lines[0]);
+        assert!(lines[0].contains("OK-ACCESS-PASSPHRASE=[redacted]"), "{}", lines[0]);
+        assert!(!lines[0].contains("hunter2"), "{}", lines[0]);
+        assert!(!lines[0].contains("c2VjcmV0c2ln"), "{}", lines[0]);
+        // Full mode does log the body.
+        assert!(lines[0].contains(r#"body={"instId":"BTC-USDT"}"#), "{}", lines[0]);
+    }
+
+    #[test]
+    fn headers_mode_logs_no_bodies() {
+        let (logger, lines) = capturing_logger();
+        logger.set_level(WireLogLevel::Headers);
+
+        logger.log_rest_request("POST", "https://x/api/v5/trade/order", &[], Some("secret body"));
+        logger.log_rest_response("/api/v5/trade/order", 200, "response body");
+
+        let lines = lines.lock().unwrap();
+        assert!(lines.iter().all(|line| !line.contains("body")), "{lines:?}");
+    }
+
+    #[test]
+    fn login_frames_never_leak_credentials_in_full_mode() {
+        let (logger, lines) = capturing_logger();
+        logger.set_level(WireLogLevel::Full);
+
+        logger.log_ws_frame(
+            "->",
+            r#"{"id":"1","op":"login","args":[{"apiKey":"key-123","passphrase":"hunter2","timestamp":"1700000000","sign":"c2VjcmV0c2ln"}]}"#,
+        );
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("login"), "{}", lines[0]);
+        assert!(!lines[0].contains("hunter2"), "{}", lines[0]);
+        assert!(!lines[0].contains("c2VjcmV0c2ln"), "{}", lines[0]);
+        assert!(lines[0].matches(REDACTED).count() >= 3, "{}", lines[0]);
+    }
+
+    #[test]
+    fn off_is_the_default_and_logs_nothing() {
+        let (logger, lines) = capturing_logger();
+        logger.log_rest_request("GET", "https://x/api/v5/public/time", &[], None);
+        logger.log_ws_frame("<-", "{}");
+        assert!(lines.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_expired_revert_window_flips_back_to_off() {
+        let (logger, lines) = capturing_logger();
+        logger.set_level_for(WireLogLevel::Full, Duration::from_millis(5));
+        assert_eq!(logger.level(), WireLogLevel::Full);
+
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(logger.level(), WireLogLevel::Off);
+        logger.log_rest_response("/api/v5/public/time", 200, "{}");
+        assert!(lines.lock().unwrap().is_empty());
+
+        // An explicit set clears the window instead of inheriting it.
+        logger.set_level(WireLogLevel::Headers);
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(logger.level(), WireLogLevel::Headers);
+    }
+}
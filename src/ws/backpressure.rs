@@ -0,0 +1,241 @@
+//! Bounded inbound-frame queueing.
+//!
+//! During an exchange burst (a mass-cancel storm, a volatile funding
+//! minute) the raw socket can deliver frames far faster than the consumer
+//! drains them. An unbounded queue balloons memory and, worse, parks
+//! latency-critical order updates behind thousands of stale balance
+//! snapshots. [`FrameQueue`] sits between the socket reader and the
+//! dispatcher and applies per-class policy: op responses, `orders` data
+//! and event frames are never dropped and always drain first, while
+//! coalescable state snapshots (balances, positions) live in a bounded
+//! queue with drop-oldest semantics — the newest snapshot supersedes the
+//! ones behind it anyway. Depth and drop counters are exposed for
+//! dashboards.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use tokio::sync::Notify;
+
+/// Default bound for the coalescable queue.
+const DEFAULT_COALESCABLE_CAPACITY: usize = 256;
+/// Critical frames are never dropped; past this depth the queue logs so a
+/// stalled consumer is visible before memory becomes a problem.
+const CRITICAL_DEPTH_WARNING: usize = 4096;
+
+/// Delivery class of one inbound frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageClass {
+    /// Op acks, order updates, subscription events: never dropped,
+    /// drained before anything else.
+    Critical,
+    /// Full-state snapshots where only the newest matters: balances,
+    /// positions. Oldest entries are dropped when the bound is hit.
+    Coalescable,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelArg {
+    channel: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboundFrame {
+    arg: Option<ChannelArg>,
+}
+
+/// Classify a raw text frame. Anything that is not recognizably a
+/// coalescable channel snapshot is treated as critical — dropping an
+/// unknown frame is never the safe default.
+pub fn classify(frame: &str) -> MessageClass {
+    let Ok(parsed) = serde_json::from_str::<InboundFrame>(frame) else {
+        return MessageClass::Critical;
+    };
+    match parsed.arg {
+        Some(arg) => match arg.channel.as_str() {
+            "account" | "positions" | "balance_and_position" => MessageClass::Coalescable,
+            _ => MessageClass::Critical,
+        },
+        None => MessageClass::Critical,
+    }
+}
+
+/// Queue depths and loss counters, for dashboards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueDepths {
+    pub critical: usize,
+    pub coalescable: usize,
+    /// Coalescable frames dropped since construction.
+    pub dropped: u64,
+}
+
+struct Queues {
+    critical: VecDeque<String>,
+    coalescable: VecDeque<String>,
+    dropped: u64,
+}
+
+/// Bounded two-class frame queue between the socket reader and the
+/// dispatcher. The reader calls [`FrameQueue::push`]; the dispatcher
+/// awaits [`FrameQueue::next`].
+pub struct FrameQueue {
+    coalescable_capacity: usize,
+    queues: Mutex<Queues>,
+    notify: Notify,
+}
+
+impl Default for FrameQueue {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_COALESCABLE_CAPACITY)
+    }
+}
+
+impl FrameQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bound for the coalescable queue; critical frames are never bounded.
+    pub fn with_capacity(coalescable_capacity: usize) -> Self {
+        Self {
+            coalescable_capacity,
+            queues: Mutex::new(Queues {
+                critical: VecDeque::new(),
+                coalescable: VecDeque::new(),
+                dropped: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Enqueue one inbound frame, classifying it and applying the per-class
+    /// policy. Returns `false` when the frame displaced an older
+    /// coalescable one.
+    pub fn push(&self, frame: String) -> bool {
+        let mut queues = self.queues.lock().unwrap();
+        let mut displaced = false;
+        match classify(&frame) {
+            MessageClass::Critical => {
+                queues.critical.push_back(frame);
+                if queues.critical.len() == CRITICAL_DEPTH_WARNING {
+                    log::warn!(
+                        "critical ws queue reached {CRITICAL_DEPTH_WARNING} frames; \
+                         the consumer is falling behind"
+                    );
+                }
+            }
+            MessageClass::Coalescable => {
+                if queues.coalescable.len() >= self.coalescable_capacity {
+                    queues.coalescable.pop_front();
+                    queues.dropped += 1;
+                    displaced = true;
+                }
+                queues.coalescable.push_back(frame);
+            }
+        }
+        drop(queues);
+        self.notify.notify_one();
+        !displaced
+    }
+
+    /// Dequeue the next frame, critical class first; waits when both
+    /// queues are empty.
+    pub async fn next(&self) -> String {
+        loop {
+            let notified = self.notify.notified();
+            if let Some(frame) = self.try_next() {
+                return frame;
+            }
+            notified.await;
+        }
+    }
+
+    /// Non-blocking dequeue, critical class first.
+    pub fn try_next(&self) -> Option<String> {
+        let mut queues = self.queues.lock().unwrap();
+        queues
+            .critical
+            .pop_front()
+            .or_else(|| queues.coalescable.pop_front())
+    }
+
+    /// Current depths and the running drop counter.
+    pub fn depths(&self) -> QueueDepths {
+        let queues = self.queues.lock().unwrap();
+        QueueDepths {
+            critical: queues.critical.len(),
+            coalescable: queues.coalescable.len(),
+            dropped: queues.dropped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance_frame(i: usize) -> String {
+        format!(
+            r#"{{"arg":{{"channel":"account"}},"data":[{{"ccy":"USDT","cashBal":"{i}"}}]}}"#
+        )
+    }
+
+    const ORDER_FRAME: &str =
+        r#"{"arg":{"channel":"orders","instId":"BTC-USDT"},"data":[{"ordId":"ord1","state":"filled"}]}"#;
+
+    #[test]
+    fn classification_by_channel_and_shape() {
+        assert_eq!(classify(&balance_frame(0)), MessageClass::Coalescable);
+        assert_eq!(
+            classify(r#"{"arg":{"channel":"positions"},"data":[]}"#),
+            MessageClass::Coalescable
+        );
+        assert_eq!(classify(ORDER_FRAME), MessageClass::Critical);
+        assert_eq!(
+            classify(r#"{"id":"7","op":"order","code":"0","msg":"","data":[]}"#),
+            MessageClass::Critical
+        );
+        assert_eq!(classify("not json"), MessageClass::Critical);
+    }
+
+    #[tokio::test]
+    async fn order_updates_cut_ahead_of_a_balance_flood() {
+        let queue = FrameQueue::with_capacity(256);
+        for i in 0..10_000 {
+            queue.push(balance_frame(i));
+        }
+        queue.push(ORDER_FRAME.to_string());
+
+        // The order update is delivered first despite arriving last, and
+        // the flood stayed bounded.
+        assert_eq!(queue.next().await, ORDER_FRAME);
+        let depths = queue.depths();
+        assert_eq!(depths.critical, 0);
+        assert_eq!(depths.coalescable, 256);
+        assert_eq!(depths.dropped, 10_000 - 256);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_newest_snapshots() {
+        let queue = FrameQueue::with_capacity(2);
+        assert!(queue.push(balance_frame(1)));
+        assert!(queue.push(balance_frame(2)));
+        assert!(!queue.push(balance_frame(3)), "push must report displacement");
+
+        assert_eq!(queue.try_next(), Some(balance_frame(2)));
+        assert_eq!(queue.try_next(), Some(balance_frame(3)));
+        assert_eq!(queue.try_next(), None);
+    }
+
+    #[tokio::test]
+    async fn next_wakes_on_push() {
+        let queue = std::sync::Arc::new(FrameQueue::new());
+        let waiter = std::sync::Arc::clone(&queue);
+        let handle = tokio::spawn(async move { waiter.next().await });
+        tokio::task::yield_now().await;
+
+        queue.push(ORDER_FRAME.to_string());
+        assert_eq!(handle.await.unwrap(), ORDER_FRAME);
+    }
+}
@@ -0,0 +1,65 @@
+//! Classification of server-initiated WS close frames.
+//!
+//! OKX closes connections with distinct codes — 4001 asks for a fresh
+//! login, 4006 drops idle sockets, and maintenance windows are announced
+//! in the close reason. Treating them all as generic failures turns a
+//! mandatory re-login into a backoff-delayed outage and a maintenance
+//! window into a reconnect storm; [`classify_close`] maps each close to
+//! the restart behavior the supervisor should apply.
+
+/// What the supervisor should do after a server-initiated close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseAction {
+    /// Reconnect and re-login immediately, skipping the restart delay:
+    /// the server demanded a fresh session and waiting gains nothing.
+    /// Rapid-failure escalation still applies, so a genuine re-login
+    /// loop ends in `Failed` rather than a storm.
+    ReloginNow,
+    /// Restart on the normal supervisor backoff.
+    Backoff,
+    /// The exchange is down for maintenance; hold restarts until the
+    /// system-status poller reports the window over instead of hammering
+    /// a dead endpoint.
+    AwaitMaintenanceEnd,
+}
+
+/// Map one close frame to its restart action. The reason text wins over
+/// the code: a maintenance notice can arrive under any code.
+pub fn classify_close(code: u16, reason: &str) -> CloseAction {
+    let reason = reason.to_ascii_lowercase();
+    if reason.contains("maintenance") || reason.contains("upgrade") {
+        return CloseAction::AwaitMaintenanceEnd;
+    }
+    match code {
+        // "Login again": the session was invalidated server-side.
+        4001 => CloseAction::ReloginNow,
+        // API upgrade in progress.
+        4007 => CloseAction::AwaitMaintenanceEnd,
+        // 4006 (idle timeout) and anything unrecognized: plain backoff.
+        _ => CloseAction::Backoff,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_close_codes_map_to_their_actions() {
+        assert_eq!(classify_close(4001, "Login again"), CloseAction::ReloginNow);
+        assert_eq!(classify_close(4006, "No data in 30s"), CloseAction::Backoff);
+        assert_eq!(
+            classify_close(4007, "API upgrade in progress"),
+            CloseAction::AwaitMaintenanceEnd
+        );
+        assert_eq!(classify_close(1006, ""), CloseAction::Backoff);
+    }
+
+    #[test]
+    fn a_maintenance_notice_in_the_reason_wins_over_the_code() {
+        assert_eq!(
+            classify_close(1000, "Scheduled Maintenance, back at 08:00 UTC"),
+            CloseAction::AwaitMaintenanceEnd
+        );
+    }
+}
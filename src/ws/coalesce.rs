@@ -0,0 +1,200 @@
+//! Outbound op coalescing for bursts of small WS ops.
+//!
+//! A requote fires dozens of single-arg `cancel-order`/`order` ops
+//! back-to-back; written as individual frames, the per-frame syscall and
+//! Nagle overhead add measurable latency even with nodelay. Where the OKX
+//! protocol has a multi-arg form of the op (`batch-orders`,
+//! `batch-cancel-orders`, `batch-amend-orders`), [`Coalescer`] holds ops
+//! queued within a short window and writes them as one frame, splitting the
+//! per-item results of the combined ack back to each caller — every op
+//! keeps its own response future exactly as on the direct path. Ops without
+//! a batch form are never held back.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::errors::{DriverError, DriverResult};
+use crate::rest::trade::BATCH_CHUNK_SIZE;
+
+use super::pending::PendingWaiter;
+use super::{PendingMap, WireLogSlot, WsOpResponse};
+
+/// Batch form of an op, for ops the exchange accepts multiple args on.
+pub(super) fn batch_op(op: &str) -> Option<&'static str> {
+    match op {
+        "order" | "batch-orders" => Some("batch-orders"),
+        "cancel-order" | "batch-cancel-orders" => Some("batch-cancel-orders"),
+        "amend-order" | "batch-amend-orders" => Some("batch-amend-orders"),
+        _ => None,
+    }
+}
+
+/// Args and waiters accumulated for one not-yet-written frame.
+struct OpenBatch {
+    /// Distinguishes this batch from a successor under the same op when
+    /// the flush timer fires late.
+    generation: u64,
+    args: Vec<serde_json::Value>,
+    /// Each waiter owns `arg_count` consecutive items of the combined
+    /// response data, in queue order.
+    waiters: Vec<(usize, PendingWaiter)>,
+}
+
+/// Coalesces batchable ops queued within `window` into single frames.
+pub(super) struct Coalescer {
+    window: Duration,
+    /// How long a combined ack is awaited before its waiters are dropped;
+    /// mirrors the per-op request timeout.
+    ack_budget: Duration,
+    outbound: mpsc::UnboundedSender<String>,
+    pending: PendingMap,
+    next_id: Arc<AtomicU64>,
+    state: Mutex<HashMap<&'static str, OpenBatch>>,
+    next_generation: AtomicU64,
+    wire_log: WireLogSlot,
+}
+
+impl Coalescer {
+    pub(super) fn new(
+        window: Duration,
+        ack_budget: Duration,
+        outbound: mpsc::UnboundedSender<String>,
+        pending: PendingMap,
+        next_id: Arc<AtomicU64>,
+        wire_log: WireLogSlot,
+    ) -> Self {
+        Self {
+            window,
+            ack_budget,
+            outbound,
+            pending,
+            next_id,
+            state: Mutex::new(HashMap::new()),
+            next_generation: AtomicU64::new(1),
+            wire_log,
+        }
+    }
+
+    /// Queue the args of one op under its batch form, returning the
+    /// caller's response future. The frame is written when the window
+    /// elapses, or immediately when another arg would overflow the
+    /// exchange's 20-entry batch cap.
+    pub(super) fn enqueue(
+        self: &Arc<Self>,
+        op: &'static str,
+        args: Vec<serde_json::Value>,
+    ) -> oneshot::Receiver<DriverResult<WsOpResponse>> {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(open) = state.get(op) {
+            if open.args.len() + args.len() > BATCH_CHUNK_SIZE {
+                let full = state.remove(op).expect("batch was just observed");
+                self.flush(op, full);
+            }
+        }
+
+        match state.get_mut(op) {
+            Some(open) => {
+                open.waiters.push((args.len(), tx));
+                open.args.extend(args);
+            }
+            None => {
+                let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+                state.insert(
+                    op,
+                    OpenBatch {
+                        generation,
+                        waiters: vec![(args.len(), tx)],
+                        args,
+                    },
+                );
+                let coalescer = Arc::clone(self);
+                tokio::spawn(async move {
+                    tokio::time::sleep(coalescer.window).await;
+                    let batch = {
+                        let mut state = coalescer.state.lock().unwrap();
+                        match state.get(op) {
+                            Some(open) if open.generation == generation => {
+                                state.remove(op)
+                            }
+                            // Flushed early by the cap; a successor batch
+                            // runs on its own timer.
+                            _ => None,
+                        }
+                    };
+                    if let Some(batch) = batch {
+                        coalescer.flush(op, batch);
+                    }
+                });
+            }
+        }
+        rx
+    }
+
+    /// Write one combined frame and spawn the ack splitter that hands each
+    /// waiter its slice of the per-item results.
+    fn flush(self: &Arc<Self>, op: &'static str, batch: OpenBatch) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let frame =
+            serde_json::json!({ "id": id, "op": op, "args": batch.args }).to_string();
+
+        let (tx, rx) = oneshot::channel();
+        if let Err(error) = self.pending.insert(id.clone(), tx) {
+            let message = error.to_string();
+            for (_, waiter) in batch.waiters {
+                let _ = waiter.send(Err(DriverError::Backpressure(message.clone())));
+            }
+            return;
+        }
+        self.wire_log.read().unwrap().log_ws_frame("->", &frame);
+        if self.outbound.send(frame).is_err() {
+            // Dropping the waiters makes each caller's future resolve to a
+            // closed-channel error on its own path.
+            self.pending.remove(&id);
+            return;
+        }
+
+        let coalescer = Arc::clone(self);
+        tokio::spawn(async move {
+            match tokio::time::timeout(coalescer.ack_budget, rx).await {
+                Ok(Ok(Ok(response))) => Self::split(batch.waiters, response),
+                // The combined entry was evicted; forward the typed error.
+                Ok(Ok(Err(error))) => {
+                    let message = error.to_string();
+                    for (_, waiter) in batch.waiters {
+                        let _ =
+                            waiter.send(Err(DriverError::Backpressure(message.clone())));
+                    }
+                }
+                // Unanswered or dispatch gone: clean up the combined entry;
+                // each caller times out on its own budget.
+                _ => {
+                    coalescer.pending.remove(&id);
+                }
+            }
+        });
+    }
+
+    /// Distribute the combined ack positionally: the per-item results come
+    /// back in args order, so each waiter gets as many items as it queued.
+    /// A wholesale rejection with no per-item data hands every waiter the
+    /// empty data and the envelope code, same as the direct path would see.
+    fn split(waiters: Vec<(usize, PendingWaiter)>, response: WsOpResponse) {
+        let mut data = response.data.into_iter();
+        for (count, waiter) in waiters {
+            let slice: Vec<serde_json::Value> = data.by_ref().take(count).collect();
+            let _ = waiter.send(Ok(WsOpResponse {
+                id: response.id.clone(),
+                op: response.op.clone(),
+                code: response.code.clone(),
+                msg: response.msg.clone(),
+                data: slice,
+            }));
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! WS message processing-lag measurement.
+//!
+//! During a burst the event loop can fall behind the socket and act on
+//! order updates that are already stale — and without a measurement that
+//! failure mode is invisible until it costs money. The connection owner
+//! stamps every inbound frame at socket read, carries the stamp through
+//! parsing and dispatch, and reports the delta to handler completion
+//! here, per channel. Each sample reaches the metrics hook (the
+//! observer's histogram), a recent-sample ring feeds the p99 exposed on
+//! the driver snapshot, and a channel whose p99 crosses the configured
+//! threshold emits [`DriverEvent::WsLagDegraded`] once per excursion.
+//!
+//! The exchange's own event timestamps (`uTime`, `ts`) against receive
+//! time give a second, coarser estimate — network plus exchange-side
+//! delay — recorded separately so a slow consumer and a slow feed are
+//! distinguishable. Time is passed in explicitly so tests control the
+//! clock.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::events::{DriverEvent, DriverEventSender};
+
+/// Recent lag samples kept per channel; p99 is computed over this window.
+const SAMPLE_CAPACITY: usize = 512;
+
+/// Lag state of one channel.
+#[derive(Default)]
+struct ChannelLag {
+    /// Recent socket-read-to-handler-completion lags, microseconds,
+    /// arrival order.
+    processing_us: VecDeque<u64>,
+    /// Recent exchange-timestamp-to-receive lags, microseconds.
+    exchange_us: VecDeque<u64>,
+    /// Whether the degradation event for the current excursion has fired;
+    /// re-armed once the p99 falls back under the threshold.
+    degraded: bool,
+}
+
+fn push_sample(ring: &mut VecDeque<u64>, sample: u64) {
+    if ring.len() >= SAMPLE_CAPACITY {
+        ring.pop_front();
+    }
+    ring.push_back(sample);
+}
+
+fn p99_of(ring: &VecDeque<u64>) -> Option<Duration> {
+    let mut sorted: Vec<u64> = ring.iter().copied().collect();
+    sorted.sort_unstable();
+    crate::execution_stats::percentile(&sorted, 99).map(Duration::from_micros)
+}
+
+/// Tracks per-channel WS processing lag against the configured threshold.
+pub struct LagTracker {
+    channels: Mutex<HashMap<String, ChannelLag>>,
+    threshold: Option<Duration>,
+    metrics: Option<Arc<dyn crate::rest::MetricsHook>>,
+    events: Option<DriverEventSender>,
+}
+
+impl Default for LagTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LagTracker {
+    pub fn new() -> Self {
+        Self {
+            channels: Mutex::new(HashMap::new()),
+            threshold: None,
+            metrics: None,
+            events: None,
+        }
+    }
+
+    /// Emit [`DriverEvent::WsLagDegraded`] when a channel's p99 processing
+    /// lag exceeds `threshold`; see
+    /// [`crate::config::OkexConfig::ws_lag_threshold`]. `None` disables
+    /// the event; samples still reach the metrics hook.
+    pub fn set_threshold(&mut self, threshold: Option<Duration>) {
+        self.threshold = threshold;
+    }
+
+    /// Feed every lag sample to a metrics hook.
+    pub fn set_metrics_hook(&mut self, hook: Arc<dyn crate::rest::MetricsHook>) {
+        self.metrics = Some(hook);
+    }
+
+    /// Emit degradation events on this stream.
+    pub fn set_event_sender(&mut self, events: DriverEventSender) {
+        self.events = Some(events);
+    }
+
+    /// Record one processed frame on `channel`: `received_at` is the
+    /// socket-read stamp, `completed_at` when its handler finished.
+    pub fn record_processed(&self, channel: &str, received_at: Instant, completed_at: Instant) {
+        let lag = completed_at.saturating_duration_since(received_at);
+        let crossing = {
+            let mut channels = self.channels.lock().unwrap();
+            let entry = channels.entry(channel.to_string()).or_default();
+            push_sample(&mut entry.processing_us, lag.as_micros() as u64);
+            match (self.threshold, p99_of(&entry.processing_us)) {
+                (Some(threshold), Some(p99)) if p99 > threshold && !entry.degraded => {
+                    entry.degraded = true;
+                    Some((p99, threshold))
+                }
+                (Some(threshold), Some(p99)) if p99 <= threshold => {
+                    entry.degraded = false;
+                    None
+                }
+                _ => None,
+            }
+        };
+        if let Some(hook) = &self.metrics {
+            hook.on_ws_processing_lag(channel, lag);
+        }
+        if let Some((p99, threshold)) = crossing {
+            log::warn!(
+                "ws {channel} channel p99 processing lag {p99:?} exceeds the \
+                 {threshold:?} threshold; updates are being acted on late"
+            );
+            if let Some(events) = &self.events {
+                let _ = events.send(DriverEvent::WsLagDegraded {
+                    channel: channel.to_string(),
+                    p99,
+                    threshold,
+                });
+            }
+        }
+    }
+
+    /// Record the exchange's own event timestamp (an OKX millisecond
+    /// string: `uTime`, `ts`) against `received_at` — a network+exchange
+    /// delivery lag estimate. Unparseable timestamps are skipped; a clock
+    /// ahead of the exchange clamps to zero rather than going negative.
+    pub fn record_exchange_timestamp(
+        &self,
+        channel: &str,
+        exchange_ts: &str,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) {
+        let Some(exchange_time) = crate::orders::parse_exchange_millis(exchange_ts) else {
+            return;
+        };
+        let lag = (received_at - exchange_time)
+            .to_std()
+            .unwrap_or(Duration::ZERO);
+        {
+            let mut channels = self.channels.lock().unwrap();
+            let entry = channels.entry(channel.to_string()).or_default();
+            push_sample(&mut entry.exchange_us, lag.as_micros() as u64);
+        }
+        if let Some(hook) = &self.metrics {
+            hook.on_ws_exchange_lag(channel, lag);
+        }
+    }
+
+    /// p99 of recent processing lags across all channels; `None` before
+    /// any frame has been measured.
+    pub fn processing_p99(&self) -> Option<Duration> {
+        let channels = self.channels.lock().unwrap();
+        let mut all: Vec<u64> = channels
+            .values()
+            .flat_map(|entry| entry.processing_us.iter().copied())
+            .collect();
+        all.sort_unstable();
+        crate::execution_stats::percentile(&all, 99).map(Duration::from_micros)
+    }
+
+    /// p99 of recent processing lags on one channel.
+    pub fn channel_processing_p99(&self, channel: &str) -> Option<Duration> {
+        let channels = self.channels.lock().unwrap();
+        channels
+            .get(channel)
+            .and_then(|entry| p99_of(&entry.processing_us))
+    }
+
+    /// p99 of recent exchange-to-receive lags on one channel.
+    pub fn channel_exchange_p99(&self, channel: &str) -> Option<Duration> {
+        let channels = self.channels.lock().unwrap();
+        channels.get(channel).and_then(|entry| p99_of(&entry.exchange_us))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingHook {
+        processing: StdMutex<Vec<(String, Duration)>>,
+        exchange: StdMutex<Vec<(String, Duration)>>,
+    }
+
+    impl crate::rest::MetricsHook for RecordingHook {
+        fn on_request(&self, _metrics: &crate::rest::RequestMetrics) {}
+
+        fn on_ws_processing_lag(&self, channel: &str, lag: Duration) {
+            self.processing
+                .lock()
+                .unwrap()
+                .push((channel.to_string(), lag));
+        }
+
+        fn on_ws_exchange_lag(&self, channel: &str, lag: Duration) {
+            self.exchange
+                .lock()
+                .unwrap()
+                .push((channel.to_string(), lag));
+        }
+    }
+
+    #[test]
+    fn injected_processing_delays_are_recorded_per_channel() {
+        let mut tracker = LagTracker::new();
+        let hook = Arc::new(RecordingHook::default());
+        tracker.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+        let read_at = Instant::now();
+
+        // The mock dispatch path: each frame stamped at read, its handler
+        // completing after an injected delay.
+        tracker.record_processed("orders", read_at, read_at + Duration::from_millis(25));
+        tracker.record_processed("account", read_at, read_at + Duration::from_millis(2));
+
+        let processing = hook.processing.lock().unwrap();
+        assert_eq!(
+            processing.as_slice(),
+            &[
+                ("orders".to_string(), Duration::from_millis(25)),
+                ("account".to_string(), Duration::from_millis(2)),
+            ]
+        );
+        assert_eq!(
+            tracker.channel_processing_p99("orders"),
+            Some(Duration::from_millis(25))
+        );
+        assert_eq!(tracker.processing_p99(), Some(Duration::from_millis(25)));
+    }
+
+    #[test]
+    fn a_lag_excursion_emits_one_degradation_event_and_rearms() {
+        let mut tracker = LagTracker::new();
+        tracker.set_threshold(Some(Duration::from_millis(10)));
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        tracker.set_event_sender(events_tx);
+        let read_at = Instant::now();
+        let record = |tracker: &LagTracker, delay_ms: u64| {
+            tracker.record_processed("orders", read_at, read_at + Duration::from_millis(delay_ms));
+        };
+
+        for _ in 0..100 {
+            record(&tracker, 2);
+        }
+        assert!(events_rx.try_recv().is_err(), "fast frames stay quiet");
+
+        // An injected slowdown pushes the p99 over the threshold: exactly
+        // one event for the whole excursion.
+        for _ in 0..200 {
+            record(&tracker, 50);
+        }
+        match events_rx.try_recv().unwrap() {
+            DriverEvent::WsLagDegraded { channel, p99, threshold } => {
+                assert_eq!(channel, "orders");
+                assert_eq!(threshold, Duration::from_millis(10));
+                assert!(p99 > threshold, "{p99:?}");
+            }
+            other => panic!("expected a lag degradation, got {other:?}"),
+        }
+        assert!(events_rx.try_recv().is_err(), "one event per excursion");
+
+        // Enough fast frames push the slow ones out of the window, which
+        // re-arms the event; the next excursion fires again.
+        for _ in 0..SAMPLE_CAPACITY {
+            record(&tracker, 2);
+        }
+        for _ in 0..SAMPLE_CAPACITY {
+            record(&tracker, 50);
+        }
+        assert!(matches!(
+            events_rx.try_recv().unwrap(),
+            DriverEvent::WsLagDegraded { .. }
+        ));
+    }
+
+    #[test]
+    fn exchange_timestamps_estimate_delivery_lag() {
+        let mut tracker = LagTracker::new();
+        let hook = Arc::new(RecordingHook::default());
+        tracker.set_metrics_hook(Arc::clone(&hook) as Arc<dyn crate::rest::MetricsHook>);
+        let received_at =
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_250).unwrap();
+
+        tracker.record_exchange_timestamp("orders", "1700000000000", received_at);
+        // Unparseable and clock-skewed timestamps never pollute the estimate.
+        tracker.record_exchange_timestamp("orders", "", received_at);
+        tracker.record_exchange_timestamp("orders", "1700000001000", received_at);
+
+        let exchange = hook.exchange.lock().unwrap();
+        assert_eq!(
+            exchange.as_slice(),
+            &[
+                ("orders".to_string(), Duration::from_millis(250)),
+                ("orders".to_string(), Duration::ZERO),
+            ]
+        );
+        assert_eq!(
+            tracker.channel_exchange_p99("orders"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(tracker.channel_processing_p99("orders"), None);
+    }
+}
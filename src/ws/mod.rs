@@ -0,0 +1,1001 @@
+//! Private WebSocket op client.
+//!
+//! [`OkexWsClient`] speaks the OKX v5 op protocol (`{"id","op","args"}`
+//! requests answered by `{"id","op","code","msg","data"}` frames) over a
+//! pair of text-frame channels, so the socket layer and tests drive it the
+//! same way. Frames without a known correlation id are ignored here; event
+//! channel routing lives with the connection owner.
+
+pub mod backpressure;
+pub mod close;
+mod coalesce;
+pub mod lag;
+mod pending;
+pub mod public;
+pub mod subscriptions;
+pub mod supervisor;
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::api_structs::{OkexAmendOrderRequest, OkexOrderOpResult};
+use crate::errors::{DriverError, DriverResult, ErrorLog, OkexErrorDetails};
+use crate::orders::BatchOutcome;
+use crate::rest::trade::{collect_batch_outcome, BATCH_CHUNK_SIZE};
+
+/// How long to wait for the exchange to acknowledge a WS op.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default cap on inbound frame size. Real OKX frames top out well under
+/// 1MB (full book snapshots); anything larger is a misbehaving peer.
+pub(crate) const DEFAULT_MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+
+/// Ack frame for a WS op request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WsOpResponse {
+    pub id: String,
+    pub op: String,
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+    #[serde(default)]
+    pub data: Vec<serde_json::Value>,
+}
+
+/// OKX `{"event":"error",...}` frame. Op-scoped when the exchange echoes
+/// the request id of the op it rejected; connection-scoped otherwise
+/// (malformed frames, 63999-style rate limiting).
+#[derive(Debug, Clone, Deserialize)]
+pub struct WsErrorFrame {
+    pub event: String,
+    /// Request id of the rejected op, when the error is op-scoped.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+    #[serde(rename = "connId", default)]
+    pub conn_id: Option<String>,
+}
+
+pub(crate) type PendingMap = Arc<pending::PendingRequests>;
+
+/// Shared, swappable wire-logger slot: the dispatch task and the
+/// coalescer hold clones, so a later [`OkexWsClient::set_wire_logger`]
+/// reaches them too.
+pub(crate) type WireLogSlot = Arc<std::sync::RwLock<Arc<crate::wire_log::WireLogger>>>;
+
+/// Shared, swappable recorder slot: the dispatch task holds a clone so a
+/// recorder attached after connect still sees subsequent frames.
+#[cfg(feature = "capture")]
+type RecorderSlot = Arc<std::sync::RwLock<Option<Arc<crate::capture::CaptureRecorder>>>>;
+
+/// Correlates WS op requests with their acks.
+///
+/// Clones are cheap handles onto the same connection and correlation
+/// state, for callers that fan ops out across tasks.
+#[derive(Clone)]
+pub struct OkexWsClient {
+    outbound: mpsc::UnboundedSender<String>,
+    pending: PendingMap,
+    next_id: Arc<AtomicU64>,
+    request_timeout: Duration,
+    /// Inbound frame size cap, shared with the dispatch task; see
+    /// [`Self::set_max_frame_bytes`].
+    max_frame_bytes: Arc<AtomicUsize>,
+    error_log: Arc<ErrorLog>,
+    /// Outbound frame coalescer; `None` writes every op as its own frame.
+    coalescer: Option<Arc<coalesce::Coalescer>>,
+    /// Wire logging for both directions; off by default, usually swapped
+    /// for the REST client's logger so one toggle covers both protocols.
+    wire_log: WireLogSlot,
+    /// Wire-traffic recorder appending inbound frames to a capture;
+    /// empty when not recording. See [`crate::capture`].
+    #[cfg(feature = "capture")]
+    recorder: RecorderSlot,
+}
+
+impl OkexWsClient {
+    /// Build over raw text-frame channels. The owner of the socket forwards
+    /// incoming text frames into `inbound` and drains `outbound` onto the
+    /// wire; a dispatch task resolves acks by id.
+    pub fn new(
+        outbound: mpsc::UnboundedSender<String>,
+        mut inbound: mpsc::UnboundedReceiver<String>,
+    ) -> Self {
+        let pending: PendingMap = Arc::new(pending::PendingRequests::new(
+            pending::PENDING_CAPACITY,
+            // TTL backstop above the request timeout: the normal timeout
+            // path cleans its own entry first; the sweep only catches
+            // waiters nobody is awaiting anymore.
+            DEFAULT_REQUEST_TIMEOUT * 2,
+        ));
+        let dispatch_pending = Arc::clone(&pending);
+        let error_log = Arc::new(ErrorLog::default());
+        let dispatch_errors = Arc::clone(&error_log);
+        let wire_log: WireLogSlot =
+            Arc::new(std::sync::RwLock::new(Arc::new(crate::wire_log::WireLogger::new())));
+        let dispatch_wire = Arc::clone(&wire_log);
+        #[cfg(feature = "capture")]
+        let recorder: RecorderSlot = Arc::new(std::sync::RwLock::new(None));
+        #[cfg(feature = "capture")]
+        let dispatch_recorder = Arc::clone(&recorder);
+        let max_frame_bytes = Arc::new(AtomicUsize::new(DEFAULT_MAX_FRAME_BYTES));
+        let dispatch_limit = Arc::clone(&max_frame_bytes);
+        tokio::spawn(async move {
+            while let Some(frame) = inbound.recv().await {
+                // Checked before anything touches the frame — logging or
+                // parsing a pathological payload is exactly the work the
+                // cap exists to avoid.
+                let limit = dispatch_limit.load(Ordering::Relaxed);
+                if frame.len() > limit {
+                    log::warn!(
+                        "dropping {}-byte inbound ws frame over the {limit}-byte limit",
+                        frame.len()
+                    );
+                    dispatch_errors.record(OkexErrorDetails {
+                        endpoint: "ws-frame".to_string(),
+                        request_id: None,
+                        code: None,
+                        message: format!(
+                            "dropped {}-byte inbound frame over the {limit}-byte limit",
+                            frame.len()
+                        ),
+                    });
+                    continue;
+                }
+                dispatch_wire.read().unwrap().log_ws_frame("<-", &frame);
+                #[cfg(feature = "capture")]
+                if let Some(recorder) = &*dispatch_recorder.read().unwrap() {
+                    recorder.record_ws_frame(&frame);
+                }
+                if let Ok(error) = serde_json::from_str::<WsErrorFrame>(&frame) {
+                    if error.event == "error" {
+                        Self::dispatch_error(&dispatch_pending, &dispatch_errors, error);
+                        continue;
+                    }
+                }
+                let Ok(response) = serde_json::from_str::<WsOpResponse>(&frame) else {
+                    continue;
+                };
+                if let Some(waiter) = dispatch_pending.remove(&response.id) {
+                    let _ = waiter.send(Ok(response));
+                } else {
+                    log::debug!("unmatched ws ack frame: {frame}");
+                }
+            }
+        });
+        Self {
+            outbound,
+            pending,
+            next_id: Arc::new(AtomicU64::new(1)),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_frame_bytes,
+            error_log,
+            coalescer: None,
+            wire_log,
+            #[cfg(feature = "capture")]
+            recorder,
+        }
+    }
+
+    /// Append every inbound frame this connection receives to a capture,
+    /// redacted as the wire log would; see [`crate::capture`].
+    #[cfg(feature = "capture")]
+    pub fn set_recorder(&self, recorder: Arc<crate::capture::CaptureRecorder>) {
+        *self.recorder.write().unwrap() = Some(recorder);
+    }
+
+    /// Share a wire logger — usually the REST client's, so one toggle
+    /// covers both protocols; the read and write paths consult it per
+    /// frame. See [`crate::wire_log`] for levels and redaction.
+    pub fn set_wire_logger(&self, logger: Arc<crate::wire_log::WireLogger>) {
+        *self.wire_log.write().unwrap() = logger;
+    }
+
+    /// Coalesce batchable ops queued within `window` into single frames;
+    /// see [`coalesce`]. Off by default: the window trades a bounded delay
+    /// on every batchable op for fewer frames during bursts, which only
+    /// pays off for strategies that requote in volleys.
+    pub fn enable_coalescing(&mut self, window: Duration) {
+        self.coalescer = Some(Arc::new(coalesce::Coalescer::new(
+            window,
+            self.request_timeout,
+            self.outbound.clone(),
+            Arc::clone(&self.pending),
+            Arc::clone(&self.next_id),
+            Arc::clone(&self.wire_log),
+        )));
+    }
+
+    /// Route an error frame: id-bearing errors resolve the pending op so
+    /// the caller sees the real rejection instead of a timeout;
+    /// connection-scoped errors feed the connection health side channel.
+    fn dispatch_error(pending: &PendingMap, errors: &ErrorLog, error: WsErrorFrame) {
+        let waiter = error.id.as_ref().and_then(|id| pending.remove(id));
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(Ok(WsOpResponse {
+                    id: error.id.unwrap_or_default(),
+                    op: "error".to_string(),
+                    code: error.code,
+                    msg: error.msg,
+                    data: Vec::new(),
+                }));
+            }
+            None => {
+                log::warn!(
+                    "connection-scoped ws error {} (connId {}): {}",
+                    error.code,
+                    error.conn_id.as_deref().unwrap_or("-"),
+                    error.msg
+                );
+                errors.record(OkexErrorDetails {
+                    endpoint: "connection".to_string(),
+                    request_id: error.id,
+                    code: Some(error.code),
+                    message: error.msg,
+                });
+            }
+        }
+    }
+
+    /// Override the per-op ack latency budget. The correlation-map TTL
+    /// backstop follows it.
+    pub fn set_request_timeout(&mut self, timeout: Duration) {
+        self.request_timeout = timeout;
+        self.pending.set_ttl(timeout * 2);
+    }
+
+    /// Override the inbound frame size cap; see
+    /// [`crate::config::OkexConfig::max_ws_frame_bytes`]. Oversized frames
+    /// are dropped before logging or parsing and recorded in the error
+    /// log; any op waiting on such a frame times out normally.
+    pub fn set_max_frame_bytes(&self, limit: usize) {
+        self.max_frame_bytes.store(limit, Ordering::Relaxed);
+    }
+
+    /// Feed correlation-map gauges and eviction counts to a metrics hook.
+    pub fn set_metrics_hook(&self, hook: Arc<dyn crate::rest::MetricsHook>) {
+        self.pending.set_metrics_hook(hook);
+    }
+
+    /// Ops currently awaiting an ack.
+    pub fn pending_ops(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Unanswered ops evicted from the correlation map since construction.
+    pub fn pending_evictions(&self) -> u64 {
+        self.pending.evictions()
+    }
+
+    /// Structured details of the most recent op failures, oldest first.
+    pub fn last_error_details(&self) -> Vec<OkexErrorDetails> {
+        self.error_log.snapshot()
+    }
+
+    /// Build (and record) an op error with structured context.
+    fn op_error(
+        &self,
+        op: &str,
+        request_id: Option<String>,
+        code: Option<String>,
+        message: String,
+    ) -> DriverError {
+        let details = OkexErrorDetails {
+            endpoint: op.to_string(),
+            request_id,
+            code: code.clone(),
+            message: message.clone(),
+        };
+        let error = match code {
+            Some(code) => DriverError::Api {
+                code,
+                message: format!("{}: {message}", details.context()),
+            },
+            None => DriverError::Generic(format!("{}: {message}", details.context())),
+        };
+        self.error_log.record(details);
+        error
+    }
+
+    /// Build (and record) an error for a rejected per-item op result,
+    /// carrying the `sCode`, the `clOrdId` when the order had one, and the
+    /// truncated verbatim `sMsg`, routed through the same code mapping the
+    /// REST paths use.
+    fn rejection_error(
+        &self,
+        op: &str,
+        request_id: Option<String>,
+        result: &OkexOrderOpResult,
+    ) -> DriverError {
+        let message = crate::rest::trade::rejection_message(result);
+        let details = OkexErrorDetails {
+            endpoint: op.to_string(),
+            request_id,
+            code: Some(result.s_code.clone()),
+            message: message.clone(),
+        };
+        let error = if result.s_code == crate::rest::REQUEST_EXPIRED_CODE {
+            DriverError::RequestExpired(format!("{}: {message}", details.context()))
+        } else if crate::rest::trade::cancel_code_means_not_found(&result.s_code) {
+            DriverError::OrderNotFound(format!("{}: {message}", details.context()))
+        } else {
+            DriverError::Api {
+                code: result.s_code.clone(),
+                message: format!("{}: {message}", details.context()),
+            }
+        };
+        self.error_log.record(details);
+        error
+    }
+
+    /// Authenticate the connection via the WS `login` op. Called after
+    /// connect and again on credential rotation; a re-login with a new key
+    /// leaves existing subscriptions intact.
+    pub async fn ws_login(
+        &self,
+        credentials: &crate::config::OkexCredentials,
+    ) -> DriverResult<()> {
+        // Login signs over a fixed path with a unix-seconds timestamp,
+        // unlike REST's ISO-8601 format.
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let sign = credentials.sign(&format!("{timestamp}GET/users/self/verify"));
+        let args = serde_json::json!([{
+            "apiKey": credentials.api_key,
+            "passphrase": credentials.passphrase,
+            "timestamp": timestamp,
+            "sign": sign,
+        }]);
+        let response = self.request("login", args).await?;
+        if response.code != "0" {
+            return Err(self.op_error(
+                "login",
+                Some(response.id),
+                Some(response.code),
+                response.msg,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Place a single order via the WS `order` op.
+    ///
+    /// OKX has no WS counterpart to the REST `expTime` request deadline
+    /// ([`crate::config::OkexConfig::order_request_validity`]); the ack
+    /// timeout and its recovery action are the only staleness bound here.
+    pub async fn ws_open_order(
+        &self,
+        params: &crate::orders::OkexOrderParams,
+    ) -> DriverResult<OkexOrderOpResult> {
+        let response = self
+            .request("order", serde_json::json!([params]))
+            .await?;
+        let WsOpResponse { id, code, msg, data, .. } = response;
+        let result: OkexOrderOpResult = match data.into_iter().next() {
+            Some(value) => serde_json::from_value(value)?,
+            None => return Err(self.op_error("order", Some(id), Some(code), msg)),
+        };
+        if result.s_code != "0" {
+            return Err(self.rejection_error("order", Some(id), &result));
+        }
+        Ok(result)
+    }
+
+    /// Send one op and wait for its ack, failing after the request timeout.
+    pub async fn request(
+        &self,
+        op: &str,
+        args: serde_json::Value,
+    ) -> DriverResult<WsOpResponse> {
+        let (id, rx) = self.submit(op, args)?;
+
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(DriverError::Generic("ws dispatch task gone".to_string())),
+            Err(_) => {
+                if let Some(id) = &id {
+                    self.pending.remove(id);
+                }
+                // Keep the `Timeout` variant — recovery paths match on it —
+                // but still record the structured context.
+                self.error_log.record(OkexErrorDetails {
+                    endpoint: op.to_string(),
+                    request_id: id,
+                    code: None,
+                    message: format!("no ack within {:?}", self.request_timeout),
+                });
+                Err(DriverError::Timeout(format!(
+                    "ws op {op} timed out after {:?}",
+                    self.request_timeout
+                )))
+            }
+        }
+    }
+
+    /// Hand one op to the wire, directly or via the coalescer. Returns the
+    /// request id when the op owns its frame (so a timeout can clean the
+    /// pending entry; coalesced ops share theirs with the batch) and the
+    /// future resolving to the ack.
+    fn submit(
+        &self,
+        op: &str,
+        args: serde_json::Value,
+    ) -> DriverResult<(Option<String>, oneshot::Receiver<DriverResult<WsOpResponse>>)> {
+        let args = match (&self.coalescer, coalesce::batch_op(op), args) {
+            (Some(coalescer), Some(batch_op), serde_json::Value::Array(items)) => {
+                return Ok((None, coalescer.enqueue(batch_op, items)));
+            }
+            (_, _, args) => args,
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        self.send_frame(op, &args, id)
+    }
+
+    fn send_frame(
+        &self,
+        op: &str,
+        args: &serde_json::Value,
+        id: String,
+    ) -> DriverResult<(Option<String>, oneshot::Receiver<DriverResult<WsOpResponse>>)> {
+        let frame = serde_json::json!({ "id": id, "op": op, "args": args }).to_string();
+        self.wire_log.read().unwrap().log_ws_frame("->", &frame);
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(id.clone(), tx)?;
+        if self.outbound.send(frame).is_err() {
+            self.pending.remove(&id);
+            return Err(DriverError::Generic("ws connection is closed".to_string()));
+        }
+        Ok((Some(id), rx))
+    }
+
+    /// Cancel one order via the WS `cancel-order` op, by either id kind.
+    /// Returns the raw per-item result; typed error mapping happens in the
+    /// driver.
+    pub async fn ws_cancel_order(
+        &self,
+        inst_id: &str,
+        order_ref: &crate::orders::OrderRef,
+    ) -> DriverResult<OkexOrderOpResult> {
+        let response = self
+            .request(
+                "cancel-order",
+                serde_json::json!([order_ref.to_args(inst_id)]),
+            )
+            .await?;
+        let WsOpResponse { code, msg, data, .. } = response;
+        match data.into_iter().next() {
+            Some(value) => Ok(serde_json::from_value(value)?),
+            None => Err(DriverError::Api { code, message: msg }),
+        }
+    }
+
+    /// Place orders over WS via `batch-orders`, chunked at the exchange
+    /// limit, mirroring the REST batch outcome semantics.
+    pub async fn ws_place_orders(
+        &self,
+        orders: Vec<crate::orders::OkexOrderParams>,
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        for chunk in orders.chunks(BATCH_CHUNK_SIZE) {
+            let response = self
+                .request("batch-orders", serde_json::to_value(chunk)?)
+                .await?;
+            if !crate::rest::trade::batch_code_has_item_results(&response.code)
+                || response.data.is_empty() && response.code != "0"
+            {
+                return Err(DriverError::Api {
+                    code: response.code,
+                    message: response.msg,
+                });
+            }
+            let results: Vec<OkexOrderOpResult> = response
+                .data
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?;
+            outcome.merge(collect_batch_outcome(results));
+        }
+        Ok(outcome)
+    }
+
+    /// Cancel orders over WS via `batch-cancel-orders`, chunked at the
+    /// exchange limit, mirroring the REST batch outcome semantics. Entries
+    /// are `(instId, ordId)` pairs, as for
+    /// [`rest_cancel_orders`](crate::rest::OkexClient::rest_cancel_orders).
+    pub async fn ws_cancel_orders(
+        &self,
+        orders: Vec<(String, String)>,
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        for chunk in orders.chunks(BATCH_CHUNK_SIZE) {
+            let entries: Vec<serde_json::Value> = chunk
+                .iter()
+                .map(|(inst_id, order_id)| {
+                    serde_json::json!({ "instId": inst_id, "ordId": order_id })
+                })
+                .collect();
+            let response = self
+                .request("batch-cancel-orders", serde_json::Value::Array(entries))
+                .await?;
+            if !crate::rest::trade::batch_code_has_item_results(&response.code)
+                || response.data.is_empty() && response.code != "0"
+            {
+                return Err(DriverError::Api {
+                    code: response.code,
+                    message: response.msg,
+                });
+            }
+            let results: Vec<OkexOrderOpResult> = response
+                .data
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?;
+            outcome.merge(collect_batch_outcome(results));
+        }
+        Ok(outcome)
+    }
+
+    /// Amend orders over WS via `batch-amend-orders`, chunked at the
+    /// exchange limit, mirroring the REST batch outcome semantics.
+    pub async fn ws_amend_orders(
+        &self,
+        amendments: Vec<OkexAmendOrderRequest>,
+    ) -> DriverResult<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+        for chunk in amendments.chunks(BATCH_CHUNK_SIZE) {
+            let response = self
+                .request("batch-amend-orders", serde_json::to_value(chunk)?)
+                .await?;
+            if !crate::rest::trade::batch_code_has_item_results(&response.code)
+                || response.data.is_empty() && response.code != "0"
+            {
+                return Err(DriverError::Api {
+                    code: response.code,
+                    message: response.msg,
+                });
+            }
+            let results: Vec<OkexOrderOpResult> = response
+                .data
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?;
+            outcome.merge(collect_batch_outcome(results));
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fake exchange peer: answers each `batch-amend-orders` op with acks,
+    /// scripting a rejection for the order id in `reject`.
+    fn spawn_fake_peer(
+        mut from_client: mpsc::UnboundedReceiver<String>,
+        to_client: mpsc::UnboundedSender<String>,
+        reject: &'static str,
+    ) {
+        tokio::spawn(async move {
+            while let Some(frame) = from_client.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "batch-amend-orders");
+                let data: Vec<serde_json::Value> = request["args"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|arg| {
+                        let ord_id = arg["ordId"].as_str().unwrap();
+                        if ord_id == reject {
+                            serde_json::json!({"ordId": ord_id, "sCode": "51503", "sMsg": "Order does not exist"})
+                        } else {
+                            serde_json::json!({"ordId": ord_id, "sCode": "0", "sMsg": ""})
+                        }
+                    })
+                    .collect();
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": "batch-amend-orders",
+                    "code": "0",
+                    "msg": "",
+                    "data": data,
+                });
+                to_client.send(ack.to_string()).unwrap();
+            }
+        });
+    }
+
+    fn amendment(i: usize) -> OkexAmendOrderRequest {
+        OkexAmendOrderRequest {
+            inst_id: "BTC-USDT".to_string(),
+            order_id: format!("ord{i}"),
+            new_px: Some("43250.1".to_string()),
+            new_sz: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn ws_batch_amend_chunks_and_correlates_results() {
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        spawn_fake_peer(out_rx, in_tx, "ord22");
+        let client = OkexWsClient::new(out_tx, in_rx);
+
+        let outcome = client
+            .ws_amend_orders((0..25).map(amendment).collect())
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.succeeded.len(), 24);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].order_id, "ord22");
+    }
+
+    /// Fake exchange peer that acks any op, echoing one per-item success
+    /// for every entry of `args`, and counts the frames it saw.
+    fn spawn_counting_peer(
+        mut from_client: mpsc::UnboundedReceiver<String>,
+        to_client: mpsc::UnboundedSender<String>,
+        frames: Arc<AtomicU64>,
+    ) {
+        tokio::spawn(async move {
+            while let Some(frame) = from_client.recv().await {
+                frames.fetch_add(1, Ordering::Relaxed);
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                let data: Vec<serde_json::Value> = request["args"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|arg| {
+                        serde_json::json!({
+                            "ordId": arg["ordId"], "sCode": "0", "sMsg": "",
+                        })
+                    })
+                    .collect();
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": request["op"],
+                    "code": "0",
+                    "msg": "",
+                    "data": data,
+                });
+                to_client.send(ack.to_string()).unwrap();
+            }
+        });
+    }
+
+    /// Fire 40 concurrent single cancels and report how many frames hit
+    /// the wire.
+    async fn frames_for_forty_cancels(client: OkexWsClient, frames: &Arc<AtomicU64>) -> u64 {
+        let client = Arc::new(client);
+        let cancels: Vec<_> = (0..40)
+            .map(|i| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move {
+                    client
+                        .ws_cancel_order(
+                            "BTC-USDT",
+                            &crate::orders::OrderRef::ExchangeId(format!("ord{i}")),
+                        )
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for cancel in cancels {
+            let result = cancel.await.unwrap();
+            assert_eq!(result.s_code, "0");
+        }
+        frames.load(Ordering::Relaxed)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn coalescing_collapses_a_cancel_burst_into_few_frames() {
+        // Baseline: every cancel is its own frame.
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let frames = Arc::new(AtomicU64::new(0));
+        spawn_counting_peer(out_rx, in_tx, Arc::clone(&frames));
+        let client = OkexWsClient::new(out_tx, in_rx);
+        assert_eq!(frames_for_forty_cancels(client, &frames).await, 40);
+
+        // Coalesced: the burst lands within one window and packs into
+        // frames at the 20-entry batch cap.
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let frames = Arc::new(AtomicU64::new(0));
+        spawn_counting_peer(out_rx, in_tx, Arc::clone(&frames));
+        let mut client = OkexWsClient::new(out_tx, in_rx);
+        client.enable_coalescing(Duration::from_millis(1));
+        assert_eq!(frames_for_forty_cancels(client, &frames).await, 2);
+    }
+
+    #[tokio::test]
+    async fn ws_batch_orders_partitions_a_code_1_partial_success() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer acking batch-orders with a partial-success envelope: the
+        // second order is rejected per-item under top-level code "1".
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "batch-orders");
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": "batch-orders",
+                    "code": "1",
+                    "msg": "Operation partially succeeded",
+                    "data": [
+                        {"ordId": "ord0", "sCode": "0", "sMsg": ""},
+                        {"ordId": "", "sCode": "51008", "sMsg": "Insufficient balance"},
+                    ],
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let client = OkexWsClient::new(out_tx, in_rx);
+
+        let order = |cl_ord_id: &str| crate::orders::OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: crate::orders::TradeMode::Cash,
+            side: crate::orders::Side::Buy,
+            ord_type: crate::orders::OrderType::Limit,
+            px: Some("100".to_string()),
+            sz: "1".to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: Some(cl_ord_id.to_string()),
+        };
+        let outcome = client
+            .ws_place_orders(vec![order("clord0"), order("clord1")])
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.succeeded, vec!["ord0"]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].code, "51008");
+    }
+
+    #[tokio::test]
+    async fn an_order_rejection_surfaces_the_s_msg_and_cl_ord_id() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer rejecting every `order` op per-item, echoing the clOrdId and
+        // scripting an oversized sMsg for "clord-long".
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(request["op"], "order");
+                let cl_ord_id = request["args"][0]["clOrdId"].as_str().unwrap().to_string();
+                let s_msg = if cl_ord_id == "clord-long" {
+                    "x".repeat(400)
+                } else {
+                    "Order price is not within the price limit".to_string()
+                };
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": "order",
+                    "code": "1",
+                    "msg": "All operations failed",
+                    "data": [{"ordId": "", "clOrdId": cl_ord_id, "sCode": "51006", "sMsg": s_msg}],
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let client = OkexWsClient::new(out_tx, in_rx);
+
+        let order = |cl_ord_id: &str| crate::orders::OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: crate::orders::TradeMode::Cash,
+            side: crate::orders::Side::Buy,
+            ord_type: crate::orders::OrderType::Limit,
+            px: Some("100".to_string()),
+            sz: "1".to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: Some(cl_ord_id.to_string()),
+        };
+        let err = client.ws_open_order(&order("clord7")).await.unwrap_err();
+        let DriverError::Api { code, message } = &err else {
+            panic!("expected an API error, got: {err}");
+        };
+        assert_eq!(code, "51006");
+        assert!(message.contains("clOrdId=clord7"), "{message}");
+        assert!(
+            message.contains("Order price is not within the price limit"),
+            "{message}"
+        );
+
+        // An unbounded sMsg is clipped before it reaches the error.
+        let err = client.ws_open_order(&order("clord-long")).await.unwrap_err();
+        assert!(err.to_string().ends_with("[truncated]"), "{err}");
+        assert!(err.to_string().len() < 400, "{err}");
+
+        // The structured log carries the same code and clOrdId context.
+        let details = client.last_error_details();
+        assert_eq!(details[0].code.as_deref(), Some("51006"));
+        assert!(details[0].message.contains("clOrdId=clord7"));
+    }
+
+    #[tokio::test]
+    async fn oversized_inbound_frames_are_dropped_before_parsing() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer that prefixes its ack with an oversized garbage frame.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                in_tx.send("x".repeat(512)).unwrap();
+                let ack = serde_json::json!({
+                    "id": request["id"], "op": "order",
+                    "code": "0", "msg": "",
+                    "data": [{"ordId": "ord1", "clOrdId": "", "sCode": "0", "sMsg": ""}],
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let client = OkexWsClient::new(out_tx, in_rx);
+        client.set_max_frame_bytes(256);
+
+        let params = crate::orders::OkexOrderParams {
+            inst_id: "BTC-USDT".to_string(),
+            td_mode: crate::orders::TradeMode::Cash,
+            side: crate::orders::Side::Buy,
+            ord_type: crate::orders::OrderType::Limit,
+            px: Some("100".to_string()),
+            sz: "1".to_string(),
+            ccy: None,
+            tgt_ccy: None,
+            pos_side: None,
+            reduce_only: None,
+            cl_ord_id: None,
+        };
+        // The garbage frame is discarded; the ack behind it still lands.
+        let ack = client.ws_open_order(&params).await.unwrap();
+        assert_eq!(ack.order_id, "ord1");
+
+        let details = client.last_error_details();
+        assert_eq!(details[0].endpoint, "ws-frame");
+        assert!(details[0].message.contains("512-byte"), "{}", details[0].message);
+    }
+
+    #[tokio::test]
+    async fn an_op_scoped_error_frame_resolves_the_pending_op() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer rejecting every op with an id-bearing error frame.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                let error = serde_json::json!({
+                    "event": "error",
+                    "id": request["id"],
+                    "code": "60013",
+                    "msg": "Invalid args",
+                    "connId": "a4d3ae55",
+                });
+                in_tx.send(error.to_string()).unwrap();
+            }
+        });
+        let client = OkexWsClient::new(out_tx, in_rx);
+
+        let response = client
+            .request("batch-orders", serde_json::json!([]))
+            .await
+            .unwrap();
+        assert_eq!(response.code, "60013");
+        assert_eq!(response.msg, "Invalid args");
+        assert_eq!(client.pending_ops(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_connection_scoped_error_frame_leaves_pending_ops_alone() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer answering with a rate-limit error carrying no request id.
+        tokio::spawn(async move {
+            while let Some(_frame) = out_rx.recv().await {
+                let error = serde_json::json!({
+                    "event": "error",
+                    "code": "63999",
+                    "msg": "Too many requests",
+                    "connId": "a4d3ae55",
+                });
+                in_tx.send(error.to_string()).unwrap();
+            }
+        });
+        let mut client = OkexWsClient::new(out_tx, in_rx);
+        client.request_timeout = Duration::from_millis(20);
+
+        let err = client
+            .request("order", serde_json::json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DriverError::Timeout(_)), "got: {err}");
+
+        // The error still landed on the connection health side channel.
+        let details = client.last_error_details();
+        let connection = details
+            .iter()
+            .find(|detail| detail.endpoint == "connection")
+            .unwrap();
+        assert_eq!(connection.code.as_deref(), Some("63999"));
+    }
+
+    #[tokio::test]
+    async fn full_wire_logging_redacts_login_credentials() {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        // Peer acking the login op.
+        tokio::spawn(async move {
+            while let Some(frame) = out_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                let ack = serde_json::json!({
+                    "id": request["id"],
+                    "op": "login",
+                    "code": "0",
+                    "msg": "",
+                });
+                in_tx.send(ack.to_string()).unwrap();
+            }
+        });
+        let client = OkexWsClient::new(out_tx, in_rx);
+        let logger = Arc::new(crate::wire_log::WireLogger::new());
+        let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink_lines = Arc::clone(&lines);
+        logger.set_sink(Arc::new(move |line: &str| {
+            sink_lines.lock().unwrap().push(line.to_string());
+        }));
+        logger.set_level(crate::wire_log::WireLogLevel::Full);
+        client.set_wire_logger(logger);
+
+        client
+            .ws_login(&crate::config::OkexCredentials {
+                api_key: "key-123".to_string(),
+                api_secret: "topsecret".to_string(),
+                passphrase: "hunter2".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let lines = lines.lock().unwrap();
+        assert!(
+            lines.iter().any(|l| l.starts_with("-> ws") && l.contains("login")),
+            "{lines:?}"
+        );
+        assert!(lines.iter().any(|l| l.starts_with("<- ws")), "{lines:?}");
+        assert!(
+            lines.iter().all(|l| !l.contains("hunter2")),
+            "passphrase leaked: {lines:?}"
+        );
+        assert!(
+            lines.iter().any(|l| l.contains("[redacted]")),
+            "{lines:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn unanswered_op_times_out() {
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let (_in_tx, in_rx) = mpsc::unbounded_channel();
+        let mut client = OkexWsClient::new(out_tx, in_rx);
+        client.request_timeout = Duration::from_millis(20);
+
+        let err = client
+            .request("batch-amend-orders", serde_json::json!([]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"), "got: {err}");
+        assert_eq!(client.pending_ops(), 0);
+    }
+}
@@ -0,0 +1,202 @@
+//! Bounded correlation map for in-flight WS ops.
+//!
+//! Every op parks a waiter here until its ack arrives. Callers that time
+//! out clean their own entry, but acks lost to a dying connection — and
+//! waiters whose callers gave up without a timeout — would otherwise
+//! accumulate forever. [`PendingRequests`] puts a hard cap on the map
+//! (rejecting new ops with a typed backpressure error when full) and
+//! sweeps entries past a TTL tied to the request timeout, resolving their
+//! futures with the same typed error instead of dropping them silently.
+//! Size and eviction counts feed the metrics hook for dashboards.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+// Pausable in tests, identical to the std clock otherwise.
+use tokio::time::Instant;
+
+use crate::errors::{DriverError, DriverResult};
+use crate::rest::MetricsHook;
+
+use super::WsOpResponse;
+
+/// Hard cap on in-flight ops. Far above any sane op rate; hitting it means
+/// acks are not coming back and queueing more ops would only burn memory.
+pub(crate) const PENDING_CAPACITY: usize = 4096;
+
+/// Waiter half of one in-flight op; resolves with the ack or a typed error.
+pub(crate) type PendingWaiter = oneshot::Sender<DriverResult<WsOpResponse>>;
+
+struct Entry {
+    waiter: PendingWaiter,
+    expires_at: Instant,
+}
+
+/// Capped, TTL-swept map from request id to its waiter.
+pub(crate) struct PendingRequests {
+    capacity: usize,
+    ttl: Mutex<Duration>,
+    inner: Mutex<HashMap<String, Entry>>,
+    evictions: AtomicU64,
+    hook: Mutex<Option<Arc<dyn MetricsHook>>>,
+}
+
+impl PendingRequests {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl: Mutex::new(ttl),
+            inner: Mutex::new(HashMap::new()),
+            evictions: AtomicU64::new(0),
+            hook: Mutex::new(None),
+        }
+    }
+
+    /// TTL backstop for entries nobody cleans up; kept a bit above the
+    /// request timeout so the normal timeout path always wins the race.
+    pub(crate) fn set_ttl(&self, ttl: Duration) {
+        *self.ttl.lock().unwrap() = ttl;
+    }
+
+    pub(crate) fn set_metrics_hook(&self, hook: Arc<dyn MetricsHook>) {
+        *self.hook.lock().unwrap() = Some(hook);
+    }
+
+    /// Park a waiter under its request id. Expired entries are swept first;
+    /// a map still at capacity afterwards rejects the op outright — the
+    /// caller's future resolves with the error immediately instead of
+    /// joining a queue that is not draining.
+    pub(crate) fn insert(&self, id: String, waiter: PendingWaiter) -> DriverResult<()> {
+        self.sweep_expired(Instant::now());
+        let size = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.len() >= self.capacity {
+                return Err(DriverError::Backpressure(format!(
+                    "ws correlation map is full ({} in-flight ops)",
+                    inner.len()
+                )));
+            }
+            let expires_at = Instant::now() + *self.ttl.lock().unwrap();
+            inner.insert(id, Entry { waiter, expires_at });
+            inner.len()
+        };
+        self.gauge(size);
+        Ok(())
+    }
+
+    /// Take the waiter of one answered (or timed-out) op.
+    pub(crate) fn remove(&self, id: &str) -> Option<PendingWaiter> {
+        let (waiter, size) = {
+            let mut inner = self.inner.lock().unwrap();
+            (inner.remove(id).map(|entry| entry.waiter), inner.len())
+        };
+        if waiter.is_some() {
+            self.gauge(size);
+        }
+        waiter
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    /// Total entries evicted by the TTL backstop since construction.
+    pub(crate) fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    /// Resolve and drop every entry past its deadline.
+    fn sweep_expired(&self, now: Instant) {
+        let (expired, size) = {
+            let mut inner = self.inner.lock().unwrap();
+            let ids: Vec<String> = inner
+                .iter()
+                .filter(|(_, entry)| entry.expires_at <= now)
+                .map(|(id, _)| id.clone())
+                .collect();
+            let expired: Vec<Entry> = ids
+                .iter()
+                .map(|id| inner.remove(id).expect("id was just listed"))
+                .collect();
+            (expired, inner.len())
+        };
+        if expired.is_empty() {
+            return;
+        }
+        let count = expired.len() as u64;
+        for entry in expired {
+            let _ = entry.waiter.send(Err(DriverError::Backpressure(
+                "ws op evicted: no ack within the correlation TTL".to_string(),
+            )));
+        }
+        self.evictions.fetch_add(count, Ordering::Relaxed);
+        log::warn!("evicted {count} unanswered ws ops from the correlation map");
+        let hook = self.hook.lock().unwrap().clone();
+        if let Some(hook) = hook {
+            hook.on_ws_pending_evicted(count);
+        }
+        self.gauge(size);
+    }
+
+    fn gauge(&self, size: usize) {
+        let hook = self.hook.lock().unwrap().clone();
+        if let Some(hook) = hook {
+            hook.on_ws_pending_ops(size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn ten_thousand_unanswered_ops_stay_bounded_and_all_resolve() {
+        let pending = PendingRequests::new(1000, Duration::from_millis(50));
+        let mut accepted = Vec::new();
+        let mut rejected = 0usize;
+        for i in 0..10_000 {
+            let (tx, rx) = oneshot::channel();
+            match pending.insert(format!("req{i}"), tx) {
+                Ok(()) => accepted.push(rx),
+                Err(error) => {
+                    assert!(matches!(error, DriverError::Backpressure(_)));
+                    rejected += 1;
+                }
+            }
+        }
+        assert_eq!(accepted.len(), 1000, "the cap holds");
+        assert_eq!(rejected, 9000);
+        assert_eq!(pending.len(), 1000);
+
+        // Past the TTL the next insert sweeps everything out and every
+        // parked future resolves with the typed eviction error.
+        tokio::time::sleep(Duration::from_millis(51)).await;
+        let (tx, rx) = oneshot::channel();
+        pending.insert("late".to_string(), tx).unwrap();
+        assert_eq!(pending.evictions(), 1000);
+        assert_eq!(pending.len(), 1);
+        for waiter in accepted {
+            let resolved = waiter.await.expect("eviction resolves the future");
+            assert!(matches!(resolved, Err(DriverError::Backpressure(_))));
+        }
+        drop(rx);
+    }
+
+    #[tokio::test]
+    async fn answered_ops_come_and_go_without_evictions() {
+        let pending = PendingRequests::new(10, Duration::from_secs(5));
+        let (tx, mut rx) = oneshot::channel();
+        pending.insert("req1".to_string(), tx).unwrap();
+        assert_eq!(pending.len(), 1);
+
+        let waiter = pending.remove("req1").unwrap();
+        drop(waiter);
+        assert_eq!(pending.len(), 0);
+        assert_eq!(pending.evictions(), 0);
+        assert!(rx.try_recv().is_err());
+    }
+}
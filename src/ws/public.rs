@@ -0,0 +1,280 @@
+//! Public-data WS connection, independent of the private one.
+//!
+//! OKX serves the public channels (tickers, books, funding) from a
+//! separate endpoint, and a public-data outage must not disturb trading:
+//! [`PublicWsClient`] has its own URL, its own supervisor for
+//! reconnect/backoff, and its own subscription registry, so the private
+//! connection's [`ConnectionStatus`] never reflects its failures. The
+//! connection is not dialed until the first subscription asks for it.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+
+use super::subscriptions::{SubscriptionKey, SubscriptionState, SubscriptionTracker};
+use super::supervisor::{ConnectionStatus, LoopExit, SupervisorConfig, WsSupervisor};
+use crate::events::DriverEventSender;
+
+/// Factory producing one incarnation of the public event loop, called
+/// afresh on every (re)connect.
+pub type PublicLoopFactory = Box<dyn FnMut() -> JoinHandle<LoopExit> + Send>;
+
+/// The public-data WS connection: supervisor plus subscription registry,
+/// started lazily by the first subscription.
+pub struct PublicWsClient {
+    /// Resolved public WS URL, kept for logging and health reporting.
+    url: String,
+    supervisor_config: SupervisorConfig,
+    events: DriverEventSender,
+    /// Taken by the lazy start; `None` once the supervisor is running.
+    spawn_loop: Mutex<Option<PublicLoopFactory>>,
+    supervisor: Mutex<Option<WsSupervisor>>,
+    tracker: Mutex<SubscriptionTracker>,
+}
+
+impl PublicWsClient {
+    /// Build the component without dialing anything. `outbound` is the
+    /// channel the event loop drains onto the wire (subscribe frames go
+    /// there); `spawn_loop` starts one incarnation of the loop and is held
+    /// until the first subscription triggers the start.
+    pub fn new(
+        url: String,
+        supervisor_config: SupervisorConfig,
+        events: DriverEventSender,
+        outbound: tokio::sync::mpsc::UnboundedSender<String>,
+        spawn_loop: PublicLoopFactory,
+    ) -> Self {
+        let mut tracker = SubscriptionTracker::new(outbound);
+        tracker.set_event_sender(events.clone());
+        Self {
+            url,
+            supervisor_config,
+            events,
+            spawn_loop: Mutex::new(Some(spawn_loop)),
+            supervisor: Mutex::new(None),
+            tracker: Mutex::new(tracker),
+        }
+    }
+
+    /// Subscribe to one public channel, dialing the connection first if
+    /// this is the first subscription.
+    pub fn subscribe(&self, channel: &str, inst_id: Option<&str>) {
+        self.ensure_started();
+        self.tracker
+            .lock()
+            .unwrap()
+            .subscribe(channel, inst_id, Instant::now());
+    }
+
+    fn ensure_started(&self) {
+        let mut supervisor = self.supervisor.lock().unwrap();
+        if supervisor.is_some() {
+            return;
+        }
+        let Some(spawn_loop) = self.spawn_loop.lock().unwrap().take() else {
+            return;
+        };
+        log::info!("starting public ws connection ({})", self.url);
+        *supervisor = Some(WsSupervisor::spawn(
+            self.supervisor_config,
+            self.events.clone(),
+            spawn_loop,
+            None,
+        ));
+    }
+
+    /// The URL this client dials.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Connection status; `None` while no subscription has started the
+    /// connection yet — deliberately distinct from `Offline`, which means
+    /// a started connection is down.
+    pub fn status(&self) -> Option<ConnectionStatus> {
+        self.supervisor
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(WsSupervisor::status)
+    }
+
+    /// Feed one inbound event frame to the subscription registry; `true`
+    /// when it was a subscription ack or rejection.
+    pub fn on_frame(&self, frame: &str) -> bool {
+        self.tracker.lock().unwrap().on_frame(frame)
+    }
+
+    /// Resend overdue subscriptions; the connection owner calls this on
+    /// its poll cadence.
+    pub fn check_timeouts(&self, now: Instant) {
+        self.tracker.lock().unwrap().check_timeouts(now);
+    }
+
+    pub fn set_ack_timeout(&self, timeout: Duration) {
+        self.tracker.lock().unwrap().set_ack_timeout(timeout);
+    }
+
+    /// Current state of every requested public subscription.
+    pub fn subscriptions(&self) -> Vec<(SubscriptionKey, SubscriptionState)> {
+        self.tracker.lock().unwrap().subscriptions()
+    }
+
+    /// Whether any public subscription has failed.
+    pub fn is_degraded(&self) -> bool {
+        self.tracker.lock().unwrap().is_degraded()
+    }
+
+    /// Stop the connection, if it was ever started.
+    pub async fn shutdown(&self) {
+        let supervisor = self.supervisor.lock().unwrap().take();
+        if let Some(supervisor) = supervisor {
+            supervisor.shutdown().await;
+        }
+    }
+}
+
+/// Side-by-side health of the two WS connections, for dashboards and the
+/// driver snapshot; gathering it never couples one connection's state to
+/// the other's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsHealth {
+    /// Private (trading) connection status.
+    pub private_status: ConnectionStatus,
+    /// Public-data connection status; `None` while it has never been
+    /// started.
+    pub public_status: Option<ConnectionStatus>,
+}
+
+impl WsHealth {
+    pub fn gather(private: &WsSupervisor, public: Option<&PublicWsClient>) -> Self {
+        Self {
+            private_status: private.status(),
+            public_status: public.and_then(PublicWsClient::status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn supervisor_config() -> SupervisorConfig {
+        SupervisorConfig {
+            restart_delay: Duration::from_millis(100),
+            rapid_window: Duration::from_secs(10),
+            max_rapid_failures: 3,
+        }
+    }
+
+    /// Factory counting incarnations; each one idles forever.
+    fn idle_factory(spawns: Arc<AtomicU32>) -> PublicLoopFactory {
+        Box::new(move || {
+            spawns.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(std::future::pending::<LoopExit>())
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_connection_starts_only_on_the_first_subscription() {
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let client = PublicWsClient::new(
+            "wss://example.test/ws/v5/public".to_string(),
+            supervisor_config(),
+            events_tx,
+            out_tx,
+            idle_factory(Arc::clone(&spawns)),
+        );
+
+        assert_eq!(client.status(), None, "never started: no status to report");
+        assert_eq!(spawns.load(Ordering::SeqCst), 0);
+
+        client.subscribe("tickers", Some("BTC-USDT"));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 1, "first subscribe dialed");
+        assert_eq!(client.status(), Some(ConnectionStatus::Online));
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["op"], "subscribe");
+        assert_eq!(frame["args"][0]["channel"], "tickers");
+
+        // Later subscriptions reuse the running connection.
+        client.subscribe("fundingrate", Some("BTC-USDT-SWAP"));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 1);
+        assert_eq!(client.subscriptions().len(), 2);
+        client.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_public_failure_leaves_the_private_connection_alone() {
+        // Private connection: healthy and idle, on its own event stream.
+        let (private_events_tx, _private_events_rx) = mpsc::unbounded_channel();
+        let private = WsSupervisor::spawn(
+            supervisor_config(),
+            private_events_tx,
+            || tokio::spawn(std::future::pending::<LoopExit>()),
+            None,
+        );
+
+        // Public connection: every incarnation dies instantly.
+        let (public_events_tx, _public_events_rx) = mpsc::unbounded_channel();
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let public = PublicWsClient::new(
+            "wss://example.test/ws/v5/public".to_string(),
+            supervisor_config(),
+            public_events_tx,
+            out_tx,
+            Box::new(|| tokio::spawn(async { panic!("public feed down") })),
+        );
+        public.subscribe("tickers", Some("BTC-USDT"));
+
+        // Far past the escalation point for the public loop.
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let health = WsHealth::gather(&private, Some(&public));
+        assert_eq!(health.public_status, Some(ConnectionStatus::Failed));
+        assert_eq!(
+            health.private_status,
+            ConnectionStatus::Online,
+            "trading connection must not notice the public outage"
+        );
+        private.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_private_failure_leaves_the_public_connection_alone() {
+        let (private_events_tx, _private_events_rx) = mpsc::unbounded_channel();
+        let private = WsSupervisor::spawn(
+            supervisor_config(),
+            private_events_tx,
+            || tokio::spawn(async { panic!("login storm") }),
+            None,
+        );
+
+        let (public_events_tx, _public_events_rx) = mpsc::unbounded_channel();
+        let (out_tx, _out_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let public = PublicWsClient::new(
+            "wss://example.test/ws/v5/public".to_string(),
+            supervisor_config(),
+            public_events_tx,
+            out_tx,
+            idle_factory(spawns),
+        );
+        public.subscribe("books", Some("BTC-USDT"));
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let health = WsHealth::gather(&private, Some(&public));
+        assert_eq!(health.private_status, ConnectionStatus::Failed);
+        assert_eq!(health.public_status, Some(ConnectionStatus::Online));
+        public.shutdown().await;
+    }
+}
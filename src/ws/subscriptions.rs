@@ -0,0 +1,444 @@
+//! Subscription acknowledgement tracking.
+//!
+//! OKX acks channel subscriptions with `{"event":"subscribe","arg":{...}}`
+//! frames (and rejections as `{"event":"error",...}`) rather than by op id,
+//! so the connection owner feeds event frames through a
+//! [`SubscriptionTracker`] which matches them by channel + instrument. A
+//! subscription that is neither acked nor rejected within the timeout is
+//! resent a bounded number of times before the tracker declares the driver
+//! degraded — a silently dropped `orders` subscribe must never leave us
+//! blind to order updates.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::events::{DriverEvent, DriverEventSender};
+
+/// Send attempts per subscription before it is declared failed.
+const MAX_SUBSCRIBE_ATTEMPTS: u32 = 3;
+/// Ack budget per attempt.
+const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lifecycle of one channel subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionState {
+    /// Sent, waiting for the ack.
+    Pending { attempts: u32 },
+    Acked,
+    /// Rejected or timed out past the retry budget.
+    Failed { reason: String },
+}
+
+/// Identity of a subscription: channel plus instrument where applicable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubscriptionKey {
+    pub channel: String,
+    pub inst_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventArg {
+    channel: String,
+    #[serde(rename = "instId")]
+    inst_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventFrame {
+    event: String,
+    arg: Option<EventArg>,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    code: String,
+}
+
+struct Entry {
+    state: SubscriptionState,
+    deadline: Instant,
+    /// `instType` sent in the subscribe arg; the `orders` channel requires
+    /// it and it must say MARGIN for spot-margin instruments.
+    inst_type: Option<String>,
+    /// Comma-joined `ccy` filter sent in the subscribe arg; the `account`
+    /// channel uses it to limit pushes to tradeable currencies.
+    ccy: Option<String>,
+}
+
+/// Tracks requested subscriptions against their acks. Time is passed in
+/// explicitly so tests control the clock.
+pub struct SubscriptionTracker {
+    outbound: mpsc::UnboundedSender<String>,
+    events: Option<DriverEventSender>,
+    ack_timeout: Duration,
+    entries: HashMap<SubscriptionKey, Entry>,
+}
+
+impl SubscriptionTracker {
+    pub fn new(outbound: mpsc::UnboundedSender<String>) -> Self {
+        Self {
+            outbound,
+            events: None,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Emit [`DriverEvent::SubscriptionFailed`] on this stream.
+    pub fn set_event_sender(&mut self, events: DriverEventSender) {
+        self.events = Some(events);
+    }
+
+    pub fn set_ack_timeout(&mut self, timeout: Duration) {
+        self.ack_timeout = timeout;
+    }
+
+    /// Request a subscription and start waiting for its ack.
+    pub fn subscribe(&mut self, channel: &str, inst_id: Option<&str>, now: Instant) {
+        self.subscribe_with_inst_type(channel, None, inst_id, now);
+    }
+
+    /// Like [`Self::subscribe`] for channels whose arg carries an
+    /// `instType` — the `orders` channel needs MARGIN here for spot-margin
+    /// pairs, since their ids alone look like spot.
+    pub fn subscribe_with_inst_type(
+        &mut self,
+        channel: &str,
+        inst_type: Option<&str>,
+        inst_id: Option<&str>,
+        now: Instant,
+    ) {
+        let key = SubscriptionKey {
+            channel: channel.to_string(),
+            inst_id: inst_id.map(str::to_string),
+        };
+        let entry = Entry {
+            state: SubscriptionState::Pending { attempts: 1 },
+            deadline: now + self.ack_timeout,
+            inst_type: inst_type.map(str::to_string),
+            ccy: None,
+        };
+        self.send_subscribe(&key, &entry);
+        self.entries.insert(key, entry);
+    }
+
+    /// Like [`Self::subscribe`] with a comma-joined `ccy` currency filter
+    /// in the arg — the `account` channel's way of limiting pushes to the
+    /// currencies we trade (see
+    /// [`crate::instruments::InstrumentConverter::account_channel_ccy`]).
+    /// Subscribing the same channel again replaces the filter, which is
+    /// how a pair added at runtime gets its currencies in; an unchanged
+    /// filter is a no-op, so callers may resync after every pair update
+    /// without churning an acked subscription.
+    pub fn subscribe_with_ccy(
+        &mut self,
+        channel: &str,
+        ccy: Option<&str>,
+        now: Instant,
+    ) {
+        let key = SubscriptionKey {
+            channel: channel.to_string(),
+            inst_id: None,
+        };
+        if let Some(existing) = self.entries.get(&key) {
+            if existing.ccy.as_deref() == ccy
+                && !matches!(existing.state, SubscriptionState::Failed { .. })
+            {
+                return;
+            }
+        }
+        let entry = Entry {
+            state: SubscriptionState::Pending { attempts: 1 },
+            deadline: now + self.ack_timeout,
+            inst_type: None,
+            ccy: ccy.map(str::to_string),
+        };
+        self.send_subscribe(&key, &entry);
+        self.entries.insert(key, entry);
+    }
+
+    /// Feed one inbound event frame. Returns `true` when the frame was a
+    /// subscription ack or rejection this tracker consumed.
+    pub fn on_frame(&mut self, frame: &str) -> bool {
+        let Ok(event) = serde_json::from_str::<EventFrame>(frame) else {
+            return false;
+        };
+        let Some(arg) = event.arg else {
+            return false;
+        };
+        let key = SubscriptionKey {
+            channel: arg.channel,
+            inst_id: arg.inst_id,
+        };
+        let Some(entry) = self.entries.get_mut(&key) else {
+            log::debug!("ack for unrequested subscription {key:?}");
+            return false;
+        };
+        match event.event.as_str() {
+            "subscribe" => {
+                entry.state = SubscriptionState::Acked;
+                true
+            }
+            "error" => {
+                let reason = format!("rejected ({}): {}", event.code, event.msg);
+                entry.state = SubscriptionState::Failed {
+                    reason: reason.clone(),
+                };
+                self.emit_failure(&key, reason);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Resend overdue pending subscriptions, failing those past the retry
+    /// budget. The connection owner calls this on its poll cadence.
+    pub fn check_timeouts(&mut self, now: Instant) {
+        let mut failures = Vec::new();
+        for (key, entry) in &mut self.entries {
+            let SubscriptionState::Pending { attempts } = entry.state else {
+                continue;
+            };
+            if now < entry.deadline {
+                continue;
+            }
+            if attempts < MAX_SUBSCRIBE_ATTEMPTS {
+                entry.state = SubscriptionState::Pending {
+                    attempts: attempts + 1,
+                };
+                entry.deadline = now + self.ack_timeout;
+                let frame = Self::subscribe_frame(key, entry);
+                if self.outbound.send(frame).is_err() {
+                    log::debug!("resubscribe dropped; ws connection is closed");
+                }
+            } else {
+                let reason = format!("no ack after {attempts} attempts");
+                entry.state = SubscriptionState::Failed {
+                    reason: reason.clone(),
+                };
+                failures.push((key.clone(), reason));
+            }
+        }
+        for (key, reason) in failures {
+            self.emit_failure(&key, reason);
+        }
+    }
+
+    /// Current state of every requested subscription.
+    pub fn subscriptions(&self) -> Vec<(SubscriptionKey, SubscriptionState)> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.state.clone()))
+            .collect()
+    }
+
+    /// Whether any subscription has failed; a degraded driver may be
+    /// missing order or balance updates.
+    pub fn is_degraded(&self) -> bool {
+        self.entries
+            .values()
+            .any(|entry| matches!(entry.state, SubscriptionState::Failed { .. }))
+    }
+
+    fn send_subscribe(&self, key: &SubscriptionKey, entry: &Entry) {
+        if self.outbound.send(Self::subscribe_frame(key, entry)).is_err() {
+            log::debug!("subscribe dropped; ws connection is closed");
+        }
+    }
+
+    fn subscribe_frame(key: &SubscriptionKey, entry: &Entry) -> String {
+        let mut arg = serde_json::json!({ "channel": key.channel });
+        if let Some(inst_type) = &entry.inst_type {
+            arg["instType"] = serde_json::json!(inst_type);
+        }
+        if let Some(inst_id) = &key.inst_id {
+            arg["instId"] = serde_json::json!(inst_id);
+        }
+        if let Some(ccy) = &entry.ccy {
+            arg["ccy"] = serde_json::json!(ccy);
+        }
+        serde_json::json!({ "op": "subscribe", "args": [arg] }).to_string()
+    }
+
+    fn emit_failure(&self, key: &SubscriptionKey, reason: String) {
+        log::warn!(
+            "subscription to {} ({:?}) failed: {reason}",
+            key.channel,
+            key.inst_id
+        );
+        if let Some(events) = &self.events {
+            let _ = events.send(DriverEvent::SubscriptionFailed {
+                channel: key.channel.clone(),
+                inst_id: key.inst_id.clone(),
+                reason,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> (
+        SubscriptionTracker,
+        mpsc::UnboundedReceiver<String>,
+        crate::events::DriverEventReceiver,
+    ) {
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let mut tracker = SubscriptionTracker::new(out_tx);
+        tracker.set_event_sender(event_tx);
+        (tracker, out_rx, event_rx)
+    }
+
+    fn orders_key() -> SubscriptionKey {
+        SubscriptionKey {
+            channel: "orders".to_string(),
+            inst_id: Some("BTC-USDT".to_string()),
+        }
+    }
+
+    #[test]
+    fn ack_moves_the_subscription_to_acked() {
+        let (mut tracker, mut out_rx, _event_rx) = tracker();
+        let now = Instant::now();
+        tracker.subscribe("orders", Some("BTC-USDT"), now);
+
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["op"], "subscribe");
+        assert_eq!(frame["args"][0]["channel"], "orders");
+        assert_eq!(frame["args"][0]["instId"], "BTC-USDT");
+
+        let consumed = tracker.on_frame(
+            r#"{"event":"subscribe","arg":{"channel":"orders","instId":"BTC-USDT"},"connId":"c1"}"#,
+        );
+        assert!(consumed);
+        assert_eq!(
+            tracker.subscriptions(),
+            vec![(orders_key(), SubscriptionState::Acked)]
+        );
+        assert!(!tracker.is_degraded());
+    }
+
+    #[test]
+    fn rejection_fails_the_subscription_and_emits_an_event() {
+        let (mut tracker, _out_rx, mut event_rx) = tracker();
+        tracker.subscribe("orders", Some("NOSUCH-USDT"), Instant::now());
+
+        let consumed = tracker.on_frame(
+            r#"{"event":"error","code":"60018","msg":"Invalid instId","arg":{"channel":"orders","instId":"NOSUCH-USDT"}}"#,
+        );
+        assert!(consumed);
+        assert!(tracker.is_degraded());
+        match event_rx.try_recv().unwrap() {
+            DriverEvent::SubscriptionFailed { channel, reason, .. } => {
+                assert_eq!(channel, "orders");
+                assert!(reason.contains("60018"), "{reason}");
+            }
+            other => panic!("expected subscription failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn silent_timeout_retries_then_fails() {
+        let (mut tracker, mut out_rx, mut event_rx) = tracker();
+        tracker.set_ack_timeout(Duration::from_secs(5));
+        let start = Instant::now();
+        tracker.subscribe("orders", Some("BTC-USDT"), start);
+        assert!(out_rx.try_recv().is_ok());
+
+        // Two overdue checks resend; the third exhausts the budget.
+        tracker.check_timeouts(start + Duration::from_secs(6));
+        assert!(out_rx.try_recv().is_ok(), "first retry resent");
+        tracker.check_timeouts(start + Duration::from_secs(12));
+        assert!(out_rx.try_recv().is_ok(), "second retry resent");
+        tracker.check_timeouts(start + Duration::from_secs(18));
+        assert!(out_rx.try_recv().is_err(), "no send past the budget");
+
+        assert!(tracker.is_degraded());
+        assert_eq!(
+            tracker.subscriptions()[0].1,
+            SubscriptionState::Failed {
+                reason: "no ack after 3 attempts".to_string()
+            }
+        );
+        assert!(matches!(
+            event_rx.try_recv().unwrap(),
+            DriverEvent::SubscriptionFailed { .. }
+        ));
+    }
+
+    #[test]
+    fn margin_orders_subscription_carries_inst_type() {
+        let (mut tracker, mut out_rx, _event_rx) = tracker();
+        tracker.subscribe_with_inst_type(
+            "orders",
+            Some("MARGIN"),
+            Some("BTC-USDT"),
+            Instant::now(),
+        );
+
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["args"][0]["instType"], "MARGIN");
+        assert_eq!(frame["args"][0]["instId"], "BTC-USDT");
+
+        // The ack still matches on channel + instId, and a retry keeps the
+        // instType.
+        assert!(tracker.on_frame(
+            r#"{"event":"subscribe","arg":{"channel":"orders","instType":"MARGIN","instId":"BTC-USDT"}}"#
+        ));
+    }
+
+    #[test]
+    fn account_subscription_carries_the_ccy_filter_and_resyncs_on_change() {
+        let (mut tracker, mut out_rx, _event_rx) = tracker();
+        let now = Instant::now();
+        tracker.subscribe_with_ccy("account", Some("BTC,USDT"), now);
+
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["args"][0]["channel"], "account");
+        assert_eq!(frame["args"][0]["ccy"], "BTC,USDT");
+        assert!(tracker.on_frame(r#"{"event":"subscribe","arg":{"channel":"account"}}"#));
+
+        // Resyncing with the same filter leaves the acked subscription
+        // alone; a pair added at runtime widens it with a fresh subscribe.
+        tracker.subscribe_with_ccy("account", Some("BTC,USDT"), now);
+        assert!(out_rx.try_recv().is_err(), "unchanged filter is a no-op");
+        tracker.subscribe_with_ccy("account", Some("BTC,ETH,USDT"), now);
+        let frame: serde_json::Value =
+            serde_json::from_str(&out_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["args"][0]["ccy"], "BTC,ETH,USDT");
+    }
+
+    #[test]
+    fn unrelated_frames_are_not_consumed() {
+        let (mut tracker, _out_rx, _event_rx) = tracker();
+        tracker.subscribe("orders", Some("BTC-USDT"), Instant::now());
+
+        assert!(!tracker.on_frame(r#"{"id":"1","op":"order","code":"0","msg":""}"#));
+        assert!(!tracker.on_frame("not json"));
+        // Ack for a channel nobody requested.
+        assert!(!tracker.on_frame(
+            r#"{"event":"subscribe","arg":{"channel":"tickers","instId":"BTC-USDT"}}"#
+        ));
+    }
+
+    #[test]
+    fn ack_before_the_deadline_stops_retries() {
+        let (mut tracker, mut out_rx, _event_rx) = tracker();
+        tracker.set_ack_timeout(Duration::from_secs(5));
+        let start = Instant::now();
+        tracker.subscribe("account", None, start);
+        assert!(out_rx.try_recv().is_ok());
+
+        tracker.on_frame(r#"{"event":"subscribe","arg":{"channel":"account"}}"#);
+        tracker.check_timeouts(start + Duration::from_secs(60));
+        assert!(out_rx.try_recv().is_err(), "acked subscriptions never resend");
+    }
+}
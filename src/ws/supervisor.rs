@@ -0,0 +1,573 @@
+//! Supervision of the WS event-loop task.
+//!
+//! The event loop owns the socket. If it panics, the op client's outbound
+//! sender keeps accepting frames that go nowhere and callers see whatever
+//! status was last written. The supervisor watches the loop's
+//! [`JoinHandle`], logs the panic payload, flips status to
+//! [`ConnectionStatus::Offline`], and restarts the loop with a fresh
+//! connection; repeated rapid failures escalate to
+//! [`ConnectionStatus::Failed`] and a
+//! [`DriverEvent::ConnectionPermanentlyFailed`] instead of a crash loop.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::close::{classify_close, CloseAction};
+use crate::events::{DriverEvent, DriverEventSender};
+
+/// How one incarnation of the event loop ended, reported by the loop
+/// itself; panics are observed through the [`JoinHandle`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopExit {
+    /// The socket dropped or a read error ended the loop.
+    Finished,
+    /// The server sent a close frame; code and reason as received.
+    ServerClose { code: u16, reason: String },
+}
+
+/// Blocks while the exchange is in maintenance; implemented by the
+/// system-status poller on the REST client so the supervisor does not
+/// hammer a dead endpoint through a maintenance window.
+#[async_trait]
+pub trait MaintenanceWait: Send + Sync {
+    async fn wait_for_end(&self);
+}
+
+/// Health of the private WS connection as the supervisor sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The event loop is running.
+    Online,
+    /// The event loop is down; a restart is pending.
+    Offline,
+    /// Too many rapid failures; the supervisor gave up restarting.
+    Failed,
+}
+
+/// Why a status transition happened; recorded with every transition so an
+/// outage can be reconstructed from the history alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusChangeReason {
+    /// First start of the event loop.
+    Connected,
+    /// The loop was restarted after going down.
+    Reconnected,
+    /// The event loop panicked.
+    LoopPanicked,
+    /// The event loop returned on its own (socket dropped, read error).
+    LoopExited,
+    /// The WS login was rejected.
+    LoginFailed,
+    /// No pong within the heartbeat budget.
+    PingTimeout,
+    /// The server closed the connection with this close code.
+    ServerClose(u16),
+    /// Shutdown was requested.
+    ManualShutdown,
+    /// Rapid failures exhausted the restart budget.
+    RestartsExhausted,
+}
+
+/// One recorded status transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusTransition {
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub from: ConnectionStatus,
+    pub to: ConnectionStatus,
+    pub reason: StatusChangeReason,
+}
+
+/// Most recent transitions the history ring keeps.
+const STATUS_HISTORY_CAPACITY: usize = 64;
+
+/// Transition ring plus when the current status was entered. Every status
+/// write goes through [`StatusTracker::set_status`], so a transition can
+/// never be recorded without its reason.
+#[derive(Default)]
+struct StatusHistory {
+    ring: VecDeque<StatusTransition>,
+    entered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The single write path for connection status: updates the watch channel,
+/// records the transition, and mirrors it onto the event stream.
+struct StatusTracker {
+    status: watch::Sender<ConnectionStatus>,
+    history: Arc<Mutex<StatusHistory>>,
+    events: DriverEventSender,
+}
+
+impl StatusTracker {
+    fn set_status(&self, to: ConnectionStatus, reason: StatusChangeReason) {
+        let from = *self.status.borrow();
+        if from == to {
+            return;
+        }
+        let at = chrono::Utc::now();
+        let transition = StatusTransition {
+            at,
+            from,
+            to,
+            reason: reason.clone(),
+        };
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.ring.len() == STATUS_HISTORY_CAPACITY {
+                history.ring.pop_front();
+            }
+            history.ring.push_back(transition);
+            history.entered_at = Some(at);
+        }
+        let _ = self.status.send(to);
+        let _ = self.events.send(DriverEvent::ConnectionStatusChanged {
+            from,
+            to,
+            reason,
+            at,
+        });
+    }
+}
+
+/// Restart policy for the supervised event loop.
+#[derive(Debug, Clone, Copy)]
+pub struct SupervisorConfig {
+    /// Pause before restarting a crashed loop.
+    pub restart_delay: Duration,
+    /// An incarnation that dies with less uptime than this counts as a
+    /// rapid failure; longer uptimes reset the streak.
+    pub rapid_window: Duration,
+    /// Consecutive rapid failures before escalating to `Failed`.
+    pub max_rapid_failures: u32,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            restart_delay: Duration::from_secs(1),
+            rapid_window: Duration::from_secs(10),
+            max_rapid_failures: 3,
+        }
+    }
+}
+
+/// Owns the restart loop around the WS event-loop task.
+pub struct WsSupervisor {
+    status: watch::Receiver<ConnectionStatus>,
+    history: Arc<Mutex<StatusHistory>>,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl WsSupervisor {
+    /// Spawn the supervisor. `spawn_loop` starts one incarnation of the
+    /// event loop — connecting afresh each time — and returns its handle;
+    /// it is called again after every crash until shutdown or escalation.
+    pub fn spawn<F>(
+        config: SupervisorConfig,
+        events: DriverEventSender,
+        mut spawn_loop: F,
+        maintenance: Option<Arc<dyn MaintenanceWait>>,
+    ) -> Self
+    where
+        F: FnMut() -> JoinHandle<LoopExit> + Send + 'static,
+    {
+        let (status_tx, status_rx) = watch::channel(ConnectionStatus::Offline);
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let history = Arc::new(Mutex::new(StatusHistory::default()));
+        let tracker = StatusTracker {
+            status: status_tx,
+            history: Arc::clone(&history),
+            events: events.clone(),
+        };
+        let task = tokio::spawn(async move {
+            let mut rapid_failures = 0u32;
+            let mut first_start = true;
+            loop {
+                let started = tokio::time::Instant::now();
+                let mut handle = spawn_loop();
+                tracker.set_status(
+                    ConnectionStatus::Online,
+                    if first_start {
+                        StatusChangeReason::Connected
+                    } else {
+                        StatusChangeReason::Reconnected
+                    },
+                );
+                first_start = false;
+                let (down_reason, action) = tokio::select! {
+                    result = &mut handle => match result {
+                        Err(join_error) if join_error.is_panic() => {
+                            log::error!("ws event loop panicked: {join_error}");
+                            (StatusChangeReason::LoopPanicked, CloseAction::Backoff)
+                        }
+                        Ok(LoopExit::ServerClose { code, reason }) => {
+                            let action = classify_close(code, &reason);
+                            log::warn!(
+                                "ws server closed the connection (code {code}, {action:?}): \
+                                 {reason}"
+                            );
+                            (StatusChangeReason::ServerClose(code), action)
+                        }
+                        _ => {
+                            log::warn!("ws event loop exited; restarting");
+                            (StatusChangeReason::LoopExited, CloseAction::Backoff)
+                        }
+                    },
+                    _ = shutdown_rx.changed() => {
+                        handle.abort();
+                        tracker.set_status(
+                            ConnectionStatus::Offline,
+                            StatusChangeReason::ManualShutdown,
+                        );
+                        return;
+                    }
+                };
+                tracker.set_status(ConnectionStatus::Offline, down_reason);
+                if action == CloseAction::AwaitMaintenanceEnd {
+                    // A maintenance window is not a failure streak, and
+                    // restarting into it only adds noise: hold here until
+                    // the status poller says the exchange is back.
+                    rapid_failures = 0;
+                    if let Some(maintenance) = &maintenance {
+                        tokio::select! {
+                            _ = maintenance.wait_for_end() => {}
+                            _ = shutdown_rx.changed() => {
+                                tracker.set_status(
+                                    ConnectionStatus::Offline,
+                                    StatusChangeReason::ManualShutdown,
+                                );
+                                return;
+                            }
+                        }
+                    } else {
+                        tokio::time::sleep(config.restart_delay).await;
+                    }
+                    continue;
+                }
+                if started.elapsed() < config.rapid_window {
+                    rapid_failures += 1;
+                } else {
+                    rapid_failures = 1;
+                }
+                if rapid_failures >= config.max_rapid_failures {
+                    let reason = format!(
+                        "ws event loop failed {rapid_failures} times in quick succession"
+                    );
+                    log::error!("{reason}; giving up");
+                    tracker.set_status(
+                        ConnectionStatus::Failed,
+                        StatusChangeReason::RestartsExhausted,
+                    );
+                    let _ = events.send(DriverEvent::ConnectionPermanentlyFailed { reason });
+                    return;
+                }
+                if action == CloseAction::ReloginNow {
+                    // The server demanded a fresh login; reconnect without
+                    // the delay (escalation above still caps a loop).
+                    continue;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(config.restart_delay) => {}
+                    _ = shutdown_rx.changed() => {
+                        tracker.set_status(
+                            ConnectionStatus::Offline,
+                            StatusChangeReason::ManualShutdown,
+                        );
+                        return;
+                    }
+                }
+            }
+        });
+        Self {
+            status: status_rx,
+            history,
+            shutdown: shutdown_tx,
+            task,
+        }
+    }
+
+    /// Current connection status.
+    pub fn status(&self) -> ConnectionStatus {
+        *self.status.borrow()
+    }
+
+    /// A watch on the status for callers that want transitions, not polls.
+    pub fn status_watch(&self) -> watch::Receiver<ConnectionStatus> {
+        self.status.clone()
+    }
+
+    /// The recorded status transitions, oldest first.
+    pub fn status_history(&self) -> Vec<StatusTransition> {
+        self.history.lock().unwrap().ring.iter().cloned().collect()
+    }
+
+    /// How long the connection has been in its current status; `None`
+    /// before the first transition.
+    pub fn time_in_current_status(&self) -> Option<chrono::Duration> {
+        let entered_at = self.history.lock().unwrap().entered_at?;
+        Some(chrono::Utc::now() - entered_at)
+    }
+
+    /// Stop supervising: aborts the running loop and waits for the
+    /// supervisor task to exit.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn config() -> SupervisorConfig {
+        SupervisorConfig {
+            restart_delay: Duration::from_millis(100),
+            rapid_window: Duration::from_secs(10),
+            max_rapid_failures: 3,
+        }
+    }
+
+    /// Event loop stand-in: panics on any received frame, idles otherwise.
+    fn poisonable_loop(
+        frames: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>>,
+        spawns: Arc<AtomicU32>,
+    ) -> impl FnMut() -> JoinHandle<LoopExit> + Send + 'static {
+        move || {
+            spawns.fetch_add(1, Ordering::SeqCst);
+            let frames = Arc::clone(&frames);
+            tokio::spawn(async move {
+                let mut frames = frames.lock().await;
+                match frames.recv().await {
+                    Some(frame) => panic!("poisoned frame: {frame}"),
+                    None => std::future::pending::<LoopExit>().await,
+                }
+            })
+        }
+    }
+
+    /// Event loop stand-in whose first incarnation exits with a server
+    /// close frame; later incarnations idle.
+    fn close_once_loop(
+        code: u16,
+        reason: &'static str,
+        spawns: Arc<AtomicU32>,
+    ) -> impl FnMut() -> JoinHandle<LoopExit> + Send + 'static {
+        move || {
+            let incarnation = spawns.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                if incarnation == 0 {
+                    LoopExit::ServerClose {
+                        code,
+                        reason: reason.to_string(),
+                    }
+                } else {
+                    std::future::pending::<LoopExit>().await
+                }
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_panicking_loop_is_restarted() {
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            poisonable_loop(
+                Arc::new(tokio::sync::Mutex::new(frames_rx)),
+                Arc::clone(&spawns),
+            ),
+            None,
+        );
+
+        frames_tx.send("{bad payload".to_string()).unwrap();
+        // Let the supervisor observe the panic and restart past the delay.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(spawns.load(Ordering::SeqCst), 2, "loop was restarted");
+        assert_eq!(supervisor.status(), ConnectionStatus::Online);
+
+        // The full outage is readable from the history: up, down with the
+        // panic as the reason, back up.
+        let reasons: Vec<StatusChangeReason> = supervisor
+            .status_history()
+            .into_iter()
+            .map(|transition| transition.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                StatusChangeReason::Connected,
+                StatusChangeReason::LoopPanicked,
+                StatusChangeReason::Reconnected,
+            ]
+        );
+        assert!(supervisor.time_in_current_status().is_some());
+
+        // The same transitions came through the event stream, and one
+        // panic must not escalate to a permanent failure.
+        let mut seen = Vec::new();
+        while let Ok(event) = events_rx.try_recv() {
+            match event {
+                DriverEvent::ConnectionStatusChanged { reason, .. } => seen.push(reason),
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+        assert_eq!(seen, reasons);
+        supervisor.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rapid_repeated_panics_escalate_to_permanent_failure() {
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let spawn_count = Arc::clone(&spawns);
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            move || {
+                spawn_count.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async { panic!("poisoned frame") })
+            },
+            None,
+        );
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        assert_eq!(supervisor.status(), ConnectionStatus::Failed);
+        assert_eq!(spawns.load(Ordering::SeqCst), 3, "stops at the limit");
+        let last = supervisor.status_history().pop().unwrap();
+        assert_eq!(last.to, ConnectionStatus::Failed);
+        assert_eq!(last.reason, StatusChangeReason::RestartsExhausted);
+        let mut permanently_failed = false;
+        while let Ok(event) = events_rx.try_recv() {
+            permanently_failed |=
+                matches!(event, DriverEvent::ConnectionPermanentlyFailed { .. });
+        }
+        assert!(permanently_failed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_aborts_the_loop_and_exits() {
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            || tokio::spawn(std::future::pending::<LoopExit>()),
+            None,
+        );
+
+        // Let the supervisor bring the loop up before shutting down.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut watch = supervisor.status_watch();
+        let history = Arc::clone(&supervisor.history);
+        supervisor.shutdown().await;
+        assert_eq!(*watch.borrow_and_update(), ConnectionStatus::Offline);
+        let last = history.lock().unwrap().ring.back().cloned().unwrap();
+        assert_eq!(last.reason, StatusChangeReason::ManualShutdown);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_login_again_close_reconnects_without_the_restart_delay() {
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            close_once_loop(4001, "Login again", Arc::clone(&spawns)),
+            None,
+        );
+
+        // Well under the 100ms restart delay: the relogin restart must not
+        // have waited for it.
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 2, "restarted immediately");
+        assert_eq!(supervisor.status(), ConnectionStatus::Online);
+        let reasons: Vec<StatusChangeReason> = supervisor
+            .status_history()
+            .into_iter()
+            .map(|transition| transition.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                StatusChangeReason::Connected,
+                StatusChangeReason::ServerClose(4001),
+                StatusChangeReason::Reconnected,
+            ]
+        );
+        supervisor.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_idle_close_restarts_on_the_normal_backoff() {
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            close_once_loop(4006, "No data received in 30s", Arc::clone(&spawns)),
+            None,
+        );
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 1, "still in the backoff");
+        assert_eq!(supervisor.status(), ConnectionStatus::Offline);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 2, "restarted after the delay");
+        let down = &supervisor.status_history()[1];
+        assert_eq!(down.reason, StatusChangeReason::ServerClose(4006));
+        supervisor.shutdown().await;
+    }
+
+    /// Maintenance gate that releases when told to.
+    struct GatedMaintenance(tokio::sync::Notify);
+
+    #[async_trait]
+    impl MaintenanceWait for GatedMaintenance {
+        async fn wait_for_end(&self) {
+            self.0.notified().await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_maintenance_close_holds_restarts_until_the_poller_releases() {
+        let (events_tx, _events_rx) = mpsc::unbounded_channel();
+        let spawns = Arc::new(AtomicU32::new(0));
+        let gate = Arc::new(GatedMaintenance(tokio::sync::Notify::new()));
+        let supervisor = WsSupervisor::spawn(
+            config(),
+            events_tx,
+            close_once_loop(4007, "API upgrade in progress", Arc::clone(&spawns)),
+            Some(Arc::clone(&gate) as Arc<dyn MaintenanceWait>),
+        );
+
+        // Far past both the restart delay and the rapid window: nothing
+        // restarts while the poller says maintenance is ongoing.
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 1, "held during maintenance");
+        assert_eq!(supervisor.status(), ConnectionStatus::Offline);
+
+        gate.0.notify_one();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(spawns.load(Ordering::SeqCst), 2, "restarted after the window");
+        assert_eq!(supervisor.status(), ConnectionStatus::Online);
+        let down = &supervisor.status_history()[1];
+        assert_eq!(down.reason, StatusChangeReason::ServerClose(4007));
+        supervisor.shutdown().await;
+    }
+}